@@ -0,0 +1,88 @@
+//! 1 スレッドと並列版の比較
+//!
+//! 同じ範囲を、スレッド数を変えて文字列にする時間を測る。書き出し先は 2 通り:
+//!
+//! - `discard`: 長さを数えて捨てる。文字列を作る時間だけなので、スレッド数に応じて速くなる
+//! - `file`: 一時ファイルに書く。書き出しは 1 本のままなので、速くなる分は小さい
+//!
+//! 範囲が小さいとスレッドを立てる分だけ遅くなる。コアが 1 つしかない環境では
+//! どの範囲でも 1 スレッドが一番速い (並べ直しとチャネルの分だけ遅くなる)。
+//!
+//! ```text
+//! cargo bench --bench parallel
+//! ```
+
+mod common;
+
+use std::fs::File;
+use std::hint::black_box;
+use std::io::{self, BufWriter, Write};
+use std::time::Duration;
+
+use fizzbuzz::parallel::{self, DEFAULT_CHUNK_SIZE};
+use fizzbuzz::Config;
+
+use common::measure;
+
+/// 1 スレッドなら並べ直しもスレッドも使わずに作る
+fn render(config: &Config, threads: usize, mut emit: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    if threads > 1 {
+        return parallel::render(config, threads, DEFAULT_CHUNK_SIZE, emit);
+    }
+    let mut first = config.from;
+    while first <= config.to {
+        let last = first.saturating_add(DEFAULT_CHUNK_SIZE - 1).min(config.to);
+        emit(&parallel::render_chunk(config, first..=last))?;
+        first = match last.checked_add(1) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    Ok(())
+}
+
+fn main() {
+    let path = std::env::temp_dir().join("fizzbuzz-bench.txt");
+    let thread_counts = [1, 2, 4, 8];
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    println!("available parallelism: {}", cores);
+
+    print!("{:>10} {:>8}", "n", "output");
+    thread_counts.iter().for_each(|threads| print!(" {:>12}", format!("{} threads", threads)));
+    println!();
+
+    for n in [1_000, 100_000, 10_000_000] {
+        let config = Config {
+            to: n,
+            ..Config::default()
+        };
+        for output in ["discard", "file"] {
+            let times: Vec<Duration> = thread_counts
+                .iter()
+                .map(|&threads| match output {
+                    "discard" => measure(|| {
+                        let mut bytes = 0;
+                        render(&config, threads, |chunk| {
+                            bytes += chunk.len();
+                            Ok(())
+                        })
+                        .unwrap();
+                        black_box(bytes);
+                    }),
+                    _ => measure(|| {
+                        let mut file = BufWriter::new(File::create(&path).unwrap());
+                        render(&config, threads, |chunk| file.write_all(chunk.as_bytes())).unwrap();
+                        file.flush().unwrap();
+                    }),
+                })
+                .collect();
+
+            print!("{:>10} {:>8}", n, output);
+            times.iter().for_each(|time| print!(" {:>12.2?}", time));
+            // 1 スレッドに対する倍率
+            let speedup = times[0].as_secs_f64() / times[times.len() - 1].as_secs_f64();
+            println!("   (x{:.1} at {} threads)", speedup, thread_counts[thread_counts.len() - 1]);
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,140 @@
+//! 遅延評価する FizzBuzz
+//!
+//! [`FizzBuzz`] は数を 1 つずつ文字列にするイテレータ。`take` や `zip` などの
+//! アダプタとそのままつなげられ、必要になるまで計算しない。
+//!
+//! 要素は `Cow<'static, str>` で、割り切れるルールが 1 つだけならルールの単語を
+//! そのまま返す。単語が `&'static str` から作ったルールなら確保は起きない
+//! (確保するのは数を文字列にするときと、単語を 2 つ以上つなげるときだけ)。
+
+use std::borrow::Cow;
+use std::ops::RangeInclusive;
+
+use crate::Rule;
+
+/// ルールを当てはめた結果を順に返すイテレータ
+///
+/// ```
+/// use fizzbuzz::{FizzBuzz, Rule};
+///
+/// let rules = vec![Rule::new(3, "Fizz").unwrap(), Rule::new(5, "Buzz").unwrap()];
+/// let words: Vec<_> = FizzBuzz::new(rules).skip(9).take(6).collect();
+/// assert_eq!(words, ["Buzz", "11", "Fizz", "13", "14", "FizzBuzz"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FizzBuzz {
+    rules: Vec<Rule>,
+    next: u64,
+    /// 最後の数 (含む)。None なら u64::MAX まで
+    last: Option<u64>,
+    done: bool,
+}
+
+impl FizzBuzz {
+    /// 1 から終わりなく数える
+    pub fn new(rules: Vec<Rule>) -> Self {
+        FizzBuzz {
+            rules,
+            next: 1,
+            last: None,
+            done: false,
+        }
+    }
+
+    /// `range` の数だけを数える
+    pub fn with_range(rules: Vec<Rule>, range: RangeInclusive<u64>) -> Self {
+        let (first, last) = range.into_inner();
+        FizzBuzz {
+            rules,
+            next: first,
+            last: Some(last),
+            done: first > last,
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+}
+
+impl Iterator for FizzBuzz {
+    type Item = Cow<'static, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let n = self.next;
+        if Some(n) == self.last || n == u64::MAX {
+            self.done = true;
+        } else {
+            self.next += 1;
+        }
+        Some(apply(n, &self.rules))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            return (0, Some(0));
+        }
+        let remaining = self.last.unwrap_or(u64::MAX) - self.next + 1;
+        match usize::try_from(remaining) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+/// `n` にルールを当てはめる (単語が 1 つならそれを借りたまま返す)
+pub fn apply(n: u64, rules: &[Rule]) -> Cow<'static, str> {
+    let mut matched = rules.iter().filter(|rule| n.is_multiple_of(rule.divisor));
+    let Some(first) = matched.next() else {
+        return Cow::Owned(n.to_string());
+    };
+    match matched.next() {
+        None => first.word.clone(),
+        Some(second) => {
+            let mut words = String::with_capacity(first.word.len() + second.word.len());
+            words.push_str(&first.word);
+            words.push_str(&second.word);
+            matched.for_each(|rule| words.push_str(&rule.word));
+            Cow::Owned(words)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default_rules;
+
+    #[test]
+    fn test_lazy_and_chainable() {
+        let fizz_buzz = FizzBuzz::new(default_rules());
+        // 無限に続くので必要な分だけ取り出す
+        let first: Vec<_> = fizz_buzz.clone().take(5).collect();
+        assert_eq!(first, ["1", "2", "Fizz", "4", "Buzz"]);
+        let fizz_buzz_at = fizz_buzz.zip(1..).find(|(word, _)| word == "FizzBuzz").map(|(_, n)| n);
+        assert_eq!(fizz_buzz_at, Some(15));
+
+        let range = FizzBuzz::with_range(default_rules(), 13..=16);
+        assert_eq!(range.size_hint(), (4, Some(4)));
+        assert_eq!(range.collect::<Vec<_>>().join(","), "13,14,FizzBuzz,16");
+        let (first, last) = (5, 4);
+        assert_eq!(FizzBuzz::with_range(default_rules(), first..=last).count(), 0);
+        // 最後まで数えても溢れない
+        assert_eq!(FizzBuzz::with_range(default_rules(), u64::MAX - 1..=u64::MAX).count(), 2);
+    }
+
+    #[test]
+    fn test_rule_words_are_borrowed() {
+        let rules = vec![Rule::new(3, "Fizz").unwrap(), Rule::new(5, "Buzz").unwrap()];
+        for (n, word) in (1..=30).zip(FizzBuzz::new(rules)) {
+            let single_rule = (n % 3 == 0) != (n % 5 == 0);
+            assert_eq!(matches!(word, Cow::Borrowed(_)), single_rule, "{}: {}", n, word);
+        }
+        // 引数から作ったルールの単語は持ち主がいるので複製する
+        let parsed = vec![Rule::parse("2=Even").unwrap()];
+        assert!(matches!(apply(4, &parsed), Cow::Owned(ref word) if word == "Even"));
+    }
+}
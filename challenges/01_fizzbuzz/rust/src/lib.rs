@@ -0,0 +1,476 @@
+//! FizzBuzz - Rust 実装
+//!
+//! 複数のアプローチと、範囲とルールを引数で選べる CLI の中身。
+//! `main.rs` は引数を [`Config::parse`] に渡して結果を表示するだけ。
+//!
+//! ルールは `--config` で JSON ファイルからも読める (パースは 04_json_parser の
+//! クレートを使う)。省略したキーは既定値のまま:
+//!
+//! ```text
+//! {
+//!   "from": 1,
+//!   "to": 30,
+//!   "rules": [{"divisor": 3, "word": "Fizz"}, {"divisor": 5, "word": "Buzz"}],
+//!   "separator": ", "
+//! }
+//! ```
+//!
+//! ```
+//! use fizzbuzz::{apply_rules, Rule};
+//!
+//! let rules = [Rule::new(3, "Fizz").unwrap(), Rule::new(7, "Bazz").unwrap()];
+//! assert_eq!(apply_rules(21, &rules), "FizzBazz");
+//! assert_eq!(apply_rules(4, &rules), "4");
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+use json_parser::{FromJson, JsonValue};
+
+pub mod iter;
+pub mod parallel;
+
+pub use iter::FizzBuzz;
+
+/// 使い方を表示する
+pub fn print_help() {
+    println!(
+        r#"
+fizzbuzz - FizzBuzz with custom ranges and rules
+
+USAGE:
+    fizzbuzz [OPTIONS]
+
+OPTIONS:
+    --from <n>           First number (default: 1)
+    --to <n>             Last number, inclusive (default: 100)
+    --rule <d>=<word>    Print <word> for multiples of <d>; repeatable,
+                         applied in the given order (default: 3=Fizz 5=Buzz)
+    --config <file>      Load from/to/rules/separator from a JSON file;
+                         other options override the values in the file
+    --threads <n>        Build the output on <n> worker threads (default: 1);
+                         the order of the output does not change
+    --output <file>      Write to <file> instead of stdout
+    -h, --help           Show this help message
+
+EXAMPLES:
+    fizzbuzz --to 15
+    fizzbuzz --from 1 --to 100 --rule 3=Fizz --rule 5=Buzz --rule 7=Bazz
+    fizzbuzz --config rules.json --to 50
+    fizzbuzz --to 10000000 --threads 8 --output out.txt
+"#
+    );
+}
+
+/// 割り切れたら `word` を出すルール
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub divisor: u64,
+    /// `&'static str` から作れば借りたまま持つ
+    pub word: Cow<'static, str>,
+}
+
+impl Rule {
+    pub fn new(divisor: u64, word: impl Into<Cow<'static, str>>) -> Result<Self, String> {
+        let word = word.into();
+        if divisor == 0 {
+            return Err("rule divisor must be greater than 0".to_string());
+        }
+        if word.is_empty() {
+            return Err(format!("rule for {} has an empty word", divisor));
+        }
+        Ok(Rule {
+            divisor,
+            word,
+        })
+    }
+
+    /// `3=Fizz` の形から作る
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (divisor, word) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("invalid rule {:?} (expected <divisor>=<word>, e.g. 3=Fizz)", spec))?;
+        let divisor = divisor
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid rule divisor {:?} in {:?}", divisor, spec))?;
+        Rule::new(divisor, word.to_string())
+    }
+}
+
+/// `{"divisor": 3, "word": "Fizz"}`
+impl FromJson for Rule {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let JsonValue::Object(map) = value else {
+            return Err("expected a rule object like {\"divisor\": 3, \"word\": \"Fizz\"}".to_string());
+        };
+        let divisor = u64::from_json(required(map, "divisor")?).map_err(|e| format!("divisor: {}", e))?;
+        let word = String::from_json(required(map, "word")?).map_err(|e| format!("word: {}", e))?;
+        Rule::new(divisor, word)
+    }
+}
+
+fn required<'a>(map: &'a HashMap<String, JsonValue>, key: &str) -> Result<&'a JsonValue, String> {
+    map.get(key).ok_or_else(|| format!("missing {:?}", key))
+}
+
+/// 3=Fizz, 5=Buzz
+pub fn default_rules() -> Vec<Rule> {
+    vec![Rule::new(3, "Fizz").unwrap(), Rule::new(5, "Buzz").unwrap()]
+}
+
+/// 設定
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub from: u64,
+    /// 最後の数 (含む)
+    pub to: u64,
+    /// 並べた順に適用するルール
+    pub rules: Vec<Rule>,
+    /// 出力と出力の間に入れる文字列 (最後には改行)
+    pub separator: String,
+    /// 1 より大きければ [`parallel`] で作る
+    pub threads: usize,
+    /// 書き出すファイル (None なら標準出力)
+    pub output: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            from: 1,
+            to: 100,
+            rules: default_rules(),
+            separator: "\n".to_string(),
+            threads: 1,
+            output: None,
+        }
+    }
+}
+
+/// 設定ファイルの中身。キーはすべて省略できる
+impl FromJson for Config {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let JsonValue::Object(map) = value else {
+            return Err("expected an object at the top level".to_string());
+        };
+        if let Some(key) = map
+            .keys()
+            .find(|key| !["from", "to", "rules", "separator"].contains(&key.as_str()))
+        {
+            return Err(format!("unknown key {:?}", key));
+        }
+        let field = |key: &str| map.get(key).filter(|value| **value != JsonValue::Null);
+
+        let mut config = Config::default();
+        if let Some(from) = field("from") {
+            config.from = u64::from_json(from).map_err(|e| format!("from: {}", e))?;
+        }
+        if let Some(to) = field("to") {
+            config.to = u64::from_json(to).map_err(|e| format!("to: {}", e))?;
+        }
+        if let Some(rules) = field("rules") {
+            config.rules = Vec::from_json(rules).map_err(|e| format!("rules{}", e))?;
+        }
+        if let Some(separator) = field("separator") {
+            config.separator = String::from_json(separator).map_err(|e| format!("separator: {}", e))?;
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// 引数 (プログラム名を除く) をパースする
+    ///
+    /// `--config` があれば先にファイルを読み、ほかの引数で上書きする。
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut from = None;
+        let mut to = None;
+        let mut rules = Vec::new();
+        let mut config_path = None;
+        let mut threads = None;
+        let mut output = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--from" => from = Some(parse_number("--from", iter.next())?),
+                "--to" => to = Some(parse_number("--to", iter.next())?),
+                "--rule" => {
+                    let spec = iter.next().ok_or("--rule requires <divisor>=<word>")?;
+                    rules.push(Rule::parse(spec)?);
+                }
+                "--config" => config_path = Some(iter.next().ok_or("--config requires a path")?),
+                "--threads" => match parse_number("--threads", iter.next())? {
+                    0 => return Err("--threads must be at least 1".to_string()),
+                    n => threads = Some(n as usize),
+                },
+                "--output" => output = Some(iter.next().ok_or("--output requires a path")?.clone()),
+                other => return Err(format!("unknown argument: {}", other)),
+            }
+        }
+
+        let mut config = match config_path {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        config.from = from.unwrap_or(config.from);
+        config.to = to.unwrap_or(config.to);
+        config.threads = threads.unwrap_or(config.threads);
+        config.output = output;
+        if !rules.is_empty() {
+            config.rules = rules;
+        }
+
+        if config.from > config.to {
+            return Err(format!(
+                "--from ({}) must not be greater than --to ({})",
+                config.from, config.to
+            ));
+        }
+        Ok(config)
+    }
+
+    /// JSON の設定ファイルを読む
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+        Config::from_json_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self, String> {
+        let value = json_parser::parse(text).map_err(|e| e.to_string())?;
+        Config::from_json(&value)
+    }
+}
+
+fn parse_number(flag: &str, value: Option<&String>) -> Result<u64, String> {
+    let value = value.ok_or_else(|| format!("{} requires a number", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a non-negative integer, got {:?}", flag, value))
+}
+
+/// 割り切れるルールの単語をつなげたもの (1 つも無ければ数そのもの)
+pub fn apply_rules(n: u64, rules: &[Rule]) -> String {
+    iter::apply(n, rules).into_owned()
+}
+
+/// 設定どおりに `config.output` (無ければ標準出力) へ書き出す
+///
+/// 1 行ごとに書き込むと遅いので、`BufWriter` でまとめてから書く。
+pub fn run(config: &Config) -> io::Result<()> {
+    match &config.output {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| io::Error::new(e.kind(), format!("cannot create {}: {}", path, e)))?;
+            write_to(config, &mut BufWriter::new(file))
+        }
+        None => write_to(config, &mut BufWriter::new(io::stdout().lock())),
+    }
+}
+
+/// 設定どおりに `out` へ書き出す
+pub fn write_to(config: &Config, out: &mut impl Write) -> io::Result<()> {
+    if config.threads > 1 {
+        parallel::render(config, config.threads, parallel::DEFAULT_CHUNK_SIZE, |chunk| {
+            out.write_all(chunk.as_bytes())
+        })?;
+    } else {
+        let fizz_buzz = FizzBuzz::with_range(config.rules.clone(), config.from..=config.to);
+        for (n, word) in (config.from..=config.to).zip(fizz_buzz) {
+            let end = if n == config.to { "\n" } else { config.separator.as_str() };
+            out.write_all(word.as_bytes())?;
+            out.write_all(end.as_bytes())?;
+        }
+    }
+    out.flush()
+}
+
+/// 基本的な実装
+pub fn fizzbuzz_basic(n: u32, out: &mut impl Write) -> io::Result<()> {
+    for i in 1..=n {
+        if i % 15 == 0 {
+            writeln!(out, "FizzBuzz")?;
+        } else if i % 3 == 0 {
+            writeln!(out, "Fizz")?;
+        } else if i % 5 == 0 {
+            writeln!(out, "Buzz")?;
+        } else {
+            writeln!(out, "{}", i)?;
+        }
+    }
+    Ok(())
+}
+
+/// match を使った実装
+pub fn fizzbuzz_match(n: u32, out: &mut impl Write) -> io::Result<()> {
+    for i in 1..=n {
+        match (i % 3, i % 5) {
+            (0, 0) => writeln!(out, "FizzBuzz")?,
+            (0, _) => writeln!(out, "Fizz")?,
+            (_, 0) => writeln!(out, "Buzz")?,
+            _ => writeln!(out, "{}", i)?,
+        }
+    }
+    Ok(())
+}
+
+/// イテレータを使った実装
+pub fn fizzbuzz_iterator(n: u32, out: &mut impl Write) -> io::Result<()> {
+    (1..=n)
+        .map(|i| match (i % 3, i % 5) {
+            (0, 0) => "FizzBuzz".to_string(),
+            (0, _) => "Fizz".to_string(),
+            (_, 0) => "Buzz".to_string(),
+            _ => i.to_string(),
+        })
+        .try_for_each(|s| writeln!(out, "{}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn written(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_variants_write_the_same_lines() {
+        let expected: String = (1..=100).map(|n| apply_rules(n, &default_rules()) + "\n").collect();
+        assert_eq!(written(|out| fizzbuzz_basic(100, out)), expected);
+        assert_eq!(written(|out| fizzbuzz_match(100, out)), expected);
+        assert_eq!(written(|out| fizzbuzz_iterator(100, out)), expected);
+
+        let config = Config {
+            to: 100,
+            ..Config::default()
+        };
+        assert_eq!(written(|out| write_to(&config, out)), expected);
+        let parallel = Config { threads: 3, ..config };
+        assert_eq!(written(|out| write_to(&parallel, out)), expected);
+    }
+
+    #[test]
+    fn test_write_to_separator_and_output_file() {
+        let config = Config {
+            from: 9,
+            to: 12,
+            separator: " ".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(written(|out| write_to(&config, out)), "Fizz Buzz 11 Fizz\n");
+
+        let path = std::env::temp_dir().join(format!("fizzbuzz-output-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let config = Config::parse(&args(&["--to", "5", "--output", &path])).unwrap();
+        run(&config).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1\n2\nFizz\n4\nBuzz\n");
+        fs::remove_file(&path).unwrap();
+
+        let missing = Config {
+            output: Some("/nonexistent/out.txt".to_string()),
+            ..Config::default()
+        };
+        assert!(run(&missing).unwrap_err().to_string().starts_with("cannot create /nonexistent/out.txt"));
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let rules = default_rules();
+        assert_eq!(apply_rules(1, &rules), "1");
+        assert_eq!(apply_rules(9, &rules), "Fizz");
+        assert_eq!(apply_rules(10, &rules), "Buzz");
+        assert_eq!(apply_rules(30, &rules), "FizzBuzz");
+        // 0 はどの数でも割り切れる
+        assert_eq!(apply_rules(0, &rules), "FizzBuzz");
+
+        // 単語は並べた順につながる
+        let reversed = [Rule::new(5, "Buzz").unwrap(), Rule::new(3, "Fizz").unwrap()];
+        assert_eq!(apply_rules(15, &reversed), "BuzzFizz");
+    }
+
+    #[test]
+    fn test_parse_defaults_and_options() {
+        assert_eq!(Config::parse(&[]), Ok(Config::default()));
+
+        let config = Config::parse(&args(&["--from", "10", "--to", "21", "--rule", "3=Fizz", "--rule", "7=Bazz"])).unwrap();
+        assert_eq!((config.from, config.to, config.threads), (10, 21, 1));
+        assert_eq!(Config::parse(&args(&["--threads", "4"])).unwrap().threads, 4);
+        assert_eq!(config.rules, vec![Rule::new(3, "Fizz").unwrap(), Rule::new(7, "Bazz").unwrap()]);
+        // 単語に = を含めてもよい
+        assert_eq!(Rule::parse("2=a=b").unwrap().word, "a=b");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |list: &[&str]| Config::parse(&args(list)).unwrap_err();
+        assert_eq!(error(&["--to"]), "--to requires a number");
+        assert_eq!(error(&["--to", "-5"]), "--to expects a non-negative integer, got \"-5\"");
+        assert_eq!(error(&["--from", "10", "--to", "5"]), "--from (10) must not be greater than --to (5)");
+        assert_eq!(error(&["--rule", "3"]), "invalid rule \"3\" (expected <divisor>=<word>, e.g. 3=Fizz)");
+        assert_eq!(error(&["--rule", "x=Fizz"]), "invalid rule divisor \"x\" in \"x=Fizz\"");
+        assert_eq!(error(&["--rule", "0=Zero"]), "rule divisor must be greater than 0");
+        assert_eq!(error(&["--rule", "3="]), "rule for 3 has an empty word");
+        assert_eq!(error(&["--verbose"]), "unknown argument: --verbose");
+        assert_eq!(error(&["--config"]), "--config requires a path");
+        assert_eq!(error(&["--threads", "0"]), "--threads must be at least 1");
+        assert_eq!(error(&["--output"]), "--output requires a path");
+        assert!(error(&["--config", "/nonexistent/rules.json"]).starts_with("cannot read /nonexistent/rules.json"));
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        let config = Config::from_json_str(
+            r#"{"to": 21, "rules": [{"divisor": 3, "word": "Fizz"}, {"divisor": 7, "word": "Bazz"}], "separator": ", "}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            Config {
+                from: 1,
+                to: 21,
+                rules: vec![Rule::new(3, "Fizz").unwrap(), Rule::new(7, "Bazz").unwrap()],
+                separator: ", ".to_string(),
+                threads: 1,
+                output: None,
+            }
+        );
+        assert_eq!(Config::from_json_str("{}"), Ok(Config::default()));
+
+        let error = |json: &str| Config::from_json_str(json).unwrap_err();
+        assert_eq!(error("[]"), "expected an object at the top level");
+        assert_eq!(error(r#"{"step": 2}"#), "unknown key \"step\"");
+        assert_eq!(error(r#"{"to": 1.5}"#), "to: 1.5 is not a valid u64");
+        assert_eq!(error(r#"{"rules": [{"divisor": 3}]}"#), "rules[0]: missing \"word\"");
+        assert_eq!(error(r#"{"rules": [{"divisor": 0, "word": "Zero"}]}"#), "rules[0]: rule divisor must be greater than 0");
+        assert!(error("{").starts_with("Parse error"));
+    }
+
+    #[test]
+    fn test_arguments_override_config_file() {
+        let path = std::env::temp_dir().join(format!("fizzbuzz-config-{}.json", std::process::id()));
+        fs::write(&path, r#"{"from": 5, "to": 10, "rules": [{"divisor": 2, "word": "Even"}]}"#).unwrap();
+        let path = path.to_str().unwrap();
+
+        let config = Config::parse(&args(&["--config", path])).unwrap();
+        assert_eq!((config.from, config.to), (5, 10));
+        assert_eq!(config.rules, vec![Rule::new(2, "Even").unwrap()]);
+
+        let config = Config::parse(&args(&["--config", path, "--to", "20", "--rule", "3=Fizz"])).unwrap();
+        assert_eq!((config.from, config.to), (5, 20));
+        assert_eq!(config.rules, vec![Rule::new(3, "Fizz").unwrap()]);
+        // 上書きした後の範囲で確かめる
+        assert!(Config::parse(&args(&["--config", path, "--to", "1"])).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
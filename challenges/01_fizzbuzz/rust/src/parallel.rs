@@ -0,0 +1,162 @@
+//! 並列版 FizzBuzz
+//!
+//! 範囲を同じ大きさのチャンクに分け、ワーカースレッドがチャンクを 1 つずつ取って
+//! 文字列にする。できたチャンクは番号を付けてチャネルで送り返し、受け取る側が
+//! 番号順に並べ直してから渡すので、出力は 1 スレッドのときと同じになる。
+//!
+//! 速くなるのは文字列を作る部分だけで、書き出しは順番を守るために 1 本のまま。
+//! 書き出し先が遅ければそこで頭打ちになる (`cargo bench --bench parallel` で比べられる)。
+//! 書き出しに失敗したら受け取るのをやめ、ワーカーも送れなくなった時点で止まる。
+
+use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::{iter, Config};
+
+/// チャンク 1 つに入れる数の個数
+pub const DEFAULT_CHUNK_SIZE: u64 = 10_000;
+
+/// `range` の分の出力 (区切り文字込み。`config.to` の後ろだけは改行)
+pub fn render_chunk(config: &Config, range: RangeInclusive<u64>) -> String {
+    let mut out = String::new();
+    for n in range {
+        out.push_str(&iter::apply(n, &config.rules));
+        out.push_str(if n == config.to { "\n" } else { &config.separator });
+    }
+    out
+}
+
+/// `threads` 本のスレッドでチャンクを作り、できた順ではなく範囲の順に `emit` に渡す
+///
+/// `emit` がエラーを返したらそこで打ち切ってそのエラーを返す。
+pub fn render(
+    config: &Config,
+    threads: usize,
+    chunk_size: u64,
+    mut emit: impl FnMut(&str) -> io::Result<()>,
+) -> io::Result<()> {
+    let chunk_size = chunk_size.max(1);
+    // 次に取るチャンクの番号
+    let next = AtomicU64::new(0);
+    // 書き出しが遅いときにワーカーが先に進みすぎないよう、送れる数を絞る
+    let (sender, receiver) = mpsc::sync_channel::<(u64, String)>(threads * 2);
+
+    thread::scope(|scope| -> io::Result<()> {
+        for _ in 0..threads.max(1) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let first = match index
+                    .checked_mul(chunk_size)
+                    .and_then(|offset| config.from.checked_add(offset))
+                {
+                    Some(first) if first <= config.to => first,
+                    _ => break,
+                };
+                let last = first.saturating_add(chunk_size - 1).min(config.to);
+                // 受け取る側がいなくなったら終わる
+                if sender.send((index, render_chunk(config, first..=last))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // 先に届いたチャンクは、前のチャンクが揃うまで取っておく
+        let mut pending = HashMap::new();
+        let mut expected = 0;
+        for (index, chunk) in receiver {
+            pending.insert(index, chunk);
+            while let Some(chunk) = pending.remove(&expected) {
+                // ここで返ると受信側が落ち、ワーカーの send が失敗して止まる
+                emit(&chunk)?;
+                expected += 1;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rule;
+
+    fn sequential(config: &Config) -> String {
+        render_chunk(config, config.from..=config.to)
+    }
+
+    fn parallel(config: &Config, threads: usize, chunk_size: u64) -> String {
+        let mut out = String::new();
+        render(config, threads, chunk_size, |chunk| {
+            out.push_str(chunk);
+            Ok(())
+        })
+        .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_same_output_as_sequential() {
+        let mut config = Config {
+            from: 7,
+            to: 1_000,
+            ..Config::default()
+        };
+        config.rules.push(Rule::new(7, "Bazz").unwrap());
+        let expected = sequential(&config);
+        assert!(expected.starts_with("Bazz\n8\nFizz\nBuzz\n"));
+        assert!(expected.ends_with("Buzz\n"));
+
+        for threads in [1, 2, 3, 8] {
+            // 割り切れない大きさや、範囲より大きいチャンクも試す
+            for chunk_size in [1, 10, 33, 5_000] {
+                assert_eq!(parallel(&config, threads, chunk_size), expected, "{} threads, chunk {}", threads, chunk_size);
+            }
+        }
+
+        config.separator = ", ".to_string();
+        assert_eq!(parallel(&config, 4, 10), sequential(&config));
+    }
+
+    #[test]
+    fn test_edges_of_the_range() {
+        let single = Config {
+            from: 15,
+            to: 15,
+            ..Config::default()
+        };
+        assert_eq!(parallel(&single, 4, 3), "FizzBuzz\n");
+
+        // 最後のチャンクが u64::MAX で終わっても溢れない
+        let top = Config {
+            from: u64::MAX - 4,
+            to: u64::MAX,
+            ..Config::default()
+        };
+        assert_eq!(parallel(&top, 3, 2), sequential(&top));
+    }
+
+    #[test]
+    fn test_stops_on_write_error() {
+        let config = Config {
+            to: 10_000_000,
+            ..Config::default()
+        };
+        let mut chunks = 0;
+        let result = render(&config, 4, 100, |_| {
+            chunks += 1;
+            match chunks {
+                3 => Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed")),
+                _ => Ok(()),
+            }
+        });
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(chunks, 3);
+    }
+}
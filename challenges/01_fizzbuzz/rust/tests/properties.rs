@@ -0,0 +1,165 @@
+//! ランダムな範囲とルールで成り立つはずの性質を確かめる
+//!
+//! 決まった例の代わりに、xorshift で作った範囲・ルールで何百回も試す。
+//! 失敗したときは seed と入力をメッセージに出す。
+
+use std::borrow::Cow;
+
+use fizzbuzz::{apply_rules, default_rules, fizzbuzz_basic, fizzbuzz_iterator, fizzbuzz_match, write_to, Config, FizzBuzz, Rule};
+
+/// 再現できる乱数 (xorshift)
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+
+    /// 長さ 1..=max_len の範囲 (たまに u64::MAX の近く)
+    fn range(&mut self, max_len: u64) -> (u64, u64) {
+        let from = match self.below(10) {
+            0 => u64::MAX - self.below(max_len),
+            _ => self.below(1_000_000),
+        };
+        let to = from.saturating_add(self.below(max_len));
+        (from, to)
+    }
+
+    /// 約数が 1..=12 で単語がそれぞれ違うルールを 1..=4 個
+    fn rules(&mut self) -> Vec<Rule> {
+        (0..self.below(4) + 1)
+            .map(|i| Rule::new(self.below(12) + 1, format!("W{}x", i)).unwrap())
+            .collect()
+    }
+}
+
+fn lines(config: &Config) -> Vec<String> {
+    let mut out = Vec::new();
+    write_to(config, &mut out).unwrap();
+    String::from_utf8(out).unwrap().lines().map(String::from).collect()
+}
+
+#[test]
+fn default_rules_invariants() {
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    for _ in 0..300 {
+        let (from, to) = random.range(500);
+        let config = Config {
+            from,
+            to,
+            ..Config::default()
+        };
+        let lines = lines(&config);
+        assert_eq!(lines.len() as u64, to - from + 1, "{}..={}", from, to);
+
+        for (n, line) in (from..=to).zip(&lines) {
+            let expected = match (n % 3 == 0, n % 5 == 0) {
+                (true, true) => "FizzBuzz",
+                (true, false) => "Fizz",
+                (false, true) => "Buzz",
+                // 割り切れない数はそのまま
+                (false, false) => {
+                    assert_eq!(line.parse::<u64>(), Ok(n));
+                    continue;
+                }
+            };
+            assert_eq!(line, expected, "n = {}", n);
+        }
+        // 15 ごとに必ず FizzBuzz
+        if let Some(first_multiple) = from.div_ceil(15).checked_mul(15) {
+            for n in (first_multiple..=to).step_by(15) {
+                assert_eq!(lines[(n - from) as usize], "FizzBuzz", "n = {}", n);
+            }
+        }
+    }
+}
+
+#[test]
+fn every_variant_agrees() {
+    let mut random = Random(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..50 {
+        let n = random.below(300) as u32 + 1;
+        let expected: String = FizzBuzz::new(default_rules())
+            .take(n as usize)
+            .map(|word| word.into_owned() + "\n")
+            .collect();
+
+        let mut variants: Vec<(&str, Vec<u8>)> = Vec::new();
+        for (name, write) in [
+            ("basic", fizzbuzz_basic as fn(u32, &mut Vec<u8>) -> std::io::Result<()>),
+            ("match", fizzbuzz_match),
+            ("iterator", fizzbuzz_iterator),
+        ] {
+            let mut out = Vec::new();
+            write(n, &mut out).unwrap();
+            variants.push((name, out));
+        }
+        for threads in [1, 4] {
+            let config = Config {
+                to: n as u64,
+                threads,
+                ..Config::default()
+            };
+            let mut out = Vec::new();
+            write_to(&config, &mut out).unwrap();
+            variants.push(("write_to", out));
+        }
+
+        for (name, out) in variants {
+            assert_eq!(String::from_utf8(out).unwrap(), expected, "{} with n = {}", name, n);
+        }
+    }
+}
+
+#[test]
+fn custom_rules_are_applied_in_order() {
+    let mut random = Random(0x6a09_e667_f3bc_c909);
+    for _ in 0..300 {
+        let rules = random.rules();
+        let (from, to) = random.range(200);
+        let threads = random.below(3) as usize + 1;
+        let config = Config {
+            from,
+            to,
+            rules: rules.clone(),
+            threads,
+            ..Config::default()
+        };
+
+        for (n, line) in (from..=to).zip(lines(&config)) {
+            let matching: Vec<&Rule> = rules.iter().filter(|rule| n % rule.divisor == 0).collect();
+            if matching.is_empty() {
+                assert_eq!(line, n.to_string());
+                continue;
+            }
+            // 単語は互いに違い `x` で終わるので、分ければルールの順に並んでいるかが分かる
+            let words: Vec<&str> = line.split_inclusive('x').collect();
+            let expected: Vec<&str> = matching.iter().map(|rule| rule.word.as_ref()).collect();
+            assert_eq!(words, expected, "n = {}, rules = {:?}", n, rules);
+
+            // 順番を逆にすると単語も逆に並ぶ
+            let reversed: Vec<Rule> = rules.iter().rev().cloned().collect();
+            let mut reversed_words: Vec<String> =
+                apply_rules(n, &reversed).split_inclusive('x').map(String::from).collect();
+            reversed_words.reverse();
+            assert_eq!(reversed_words, expected);
+        }
+    }
+}
+
+#[test]
+fn single_static_words_are_never_allocated() {
+    let mut random = Random(0xbb67_ae85_84ca_a73b);
+    for _ in 0..100 {
+        let divisors = [random.below(20) + 1, random.below(20) + 1];
+        let rules = vec![Rule::new(divisors[0], "Foo").unwrap(), Rule::new(divisors[1], "Bar").unwrap()];
+        let start = random.below(1_000);
+        for (n, word) in (1..).zip(FizzBuzz::new(rules)).skip(start as usize).take(100) {
+            let matching = divisors.iter().filter(|&&d| n % d == 0).count();
+            assert_eq!(matches!(word, Cow::Borrowed(_)), matching == 1, "n = {}, divisors = {:?}", n, divisors);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! ノードを 1 つずつ確保するリストと、配列に並べるリストの比較
+//!
+//! `LinkedList` (ノードごとに `Box`) と `ArenaList` (1 本の `Vec` に並べて添字でつなぐ) で、
+//! 作る時間と先頭から辿る時間を測る。参考に `Vec` も測る。
+//!
+//! 「散らばったノード」は、ほかの確保を挟みながら作ったリスト。`Box` のノードは
+//! メモリ上で離れ離れになり、辿るときにキャッシュを外しやすくなる。
+//!
+//! ```text
+//! cargo bench --bench arena
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use linked_list::arena::ArenaList;
+use linked_list::LinkedList;
+
+use common::measure;
+
+/// 間に大きさのばらばらな確保を挟んで作ったリスト
+fn scattered_list(n: u64) -> (LinkedList<u64>, Vec<Vec<u8>>) {
+    let mut list = LinkedList::new();
+    let mut garbage = Vec::new();
+    for i in 0..n {
+        list.push_back(i);
+        garbage.push(vec![0u8; (i % 7 * 24) as usize]);
+    }
+    (list, garbage)
+}
+
+fn main() {
+    println!("{:>8} {:>10} {:>12} {:>12} {:>12} {:>12}", "n", "", "LinkedList", "scattered", "ArenaList", "Vec");
+    for n in [10_000u64, 100_000, 1_000_000] {
+        let build_list = measure(|| {
+            let mut list = LinkedList::new();
+            for i in 0..n {
+                list.push_back(black_box(i));
+            }
+            black_box(&list);
+        });
+        let build_arena = measure(|| {
+            let mut list = ArenaList::new();
+            for i in 0..n {
+                list.push_back(black_box(i));
+            }
+            black_box(&list);
+        });
+        let build_vec = measure(|| {
+            let mut vec = Vec::new();
+            for i in 0..n {
+                vec.push(black_box(i));
+            }
+            black_box(&vec);
+        });
+        println!(
+            "{:>8} {:>10} {:>12.2?} {:>12} {:>12.2?} {:>12.2?}",
+            n, "push_back", build_list, "-", build_arena, build_vec
+        );
+
+        let list: LinkedList<u64> = {
+            let mut list = LinkedList::new();
+            (0..n).for_each(|i| list.push_back(i));
+            list
+        };
+        let (scattered, garbage) = scattered_list(n);
+        let arena: ArenaList<u64> = {
+            let mut list = ArenaList::new();
+            (0..n).for_each(|i| list.push_back(i));
+            list
+        };
+        let vec: Vec<u64> = (0..n).collect();
+        let walk_list = measure(|| {
+            black_box(list.iter().sum::<u64>());
+        });
+        let walk_scattered = measure(|| {
+            black_box(scattered.iter().sum::<u64>());
+        });
+        let walk_arena = measure(|| {
+            black_box(arena.iter().sum::<u64>());
+        });
+        let walk_vec = measure(|| {
+            black_box(vec.iter().sum::<u64>());
+        });
+        println!(
+            "{:>8} {:>10} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?}",
+            "", "iter", walk_list, walk_scattered, walk_arena, walk_vec
+        );
+        drop(garbage);
+    }
+}
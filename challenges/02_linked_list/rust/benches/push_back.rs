@@ -0,0 +1,72 @@
+//! `push_back` だけでリストを作る時間の比較
+//!
+//! 末尾を覚えていない版 (毎回先頭から辿る、全体で O(n²)) と、末尾のポインタを持つ
+//! `LinkedList` (全体で O(n)) を比べる。参考に標準ライブラリの `LinkedList` も測る。
+//!
+//! ```text
+//! cargo bench --bench push_back
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use linked_list::LinkedList;
+
+use common::measure;
+
+/// 末尾を辿って追加する、以前の設計の単方向リスト
+struct WalkingList<T> {
+    head: Option<Box<Node<T>>>,
+}
+
+struct Node<T> {
+    _value: T,
+    next: Option<Box<Node<T>>>,
+}
+
+impl<T> WalkingList<T> {
+    fn push_back(&mut self, value: T) {
+        let mut current = &mut self.head;
+        while let Some(node) = current {
+            current = &mut node.next;
+        }
+        *current = Some(Box::new(Node { _value: value, next: None }));
+    }
+}
+
+impl<T> Drop for WalkingList<T> {
+    fn drop(&mut self) {
+        let mut current = self.head.take();
+        while let Some(mut node) = current {
+            current = node.next.take();
+        }
+    }
+}
+
+fn main() {
+    println!("{:>8} {:>14} {:>14} {:>14}", "n", "walking", "tail pointer", "std");
+    for n in [1_000, 5_000, 20_000] {
+        let walking = measure(|| {
+            let mut list = WalkingList { head: None };
+            for i in 0..n {
+                list.push_back(black_box(i));
+            }
+        });
+        let tail = measure(|| {
+            let mut list = LinkedList::new();
+            for i in 0..n {
+                list.push_back(black_box(i));
+            }
+            black_box(&list);
+        });
+        let std = measure(|| {
+            let mut list = std::collections::LinkedList::new();
+            for i in 0..n {
+                list.push_back(black_box(i));
+            }
+            black_box(&list);
+        });
+        println!("{:>8} {:>14.2?} {:>14.2?} {:>14.2?}", n, walking, tail, std);
+    }
+}
@@ -0,0 +1,211 @@
+//! 配列の中に作る単方向連結リスト
+//!
+//! ノードはすべて 1 本の `Vec` に並べ、リンクはポインタではなく添字 (`u32`) で持つ。
+//! ノードごとにヒープを確保しないので速く、ノードがメモリ上で近くに並ぶので
+//! 辿るときもキャッシュに乗りやすい。外したノードの場所は空きリストでつなぎ、
+//! 次の追加で使い回す。添字が範囲を外れればパニックするだけなので `unsafe` は要らない。
+//!
+//! ```
+//! use linked_list::arena::ArenaList;
+//!
+//! let mut list = ArenaList::new();
+//! list.push_back(2);
+//! list.push_front(1);
+//! assert_eq!(list.pop_front(), Some(1));
+//! list.push_back(3);
+//! assert_eq!(list.iter().collect::<Vec<_>>(), [&2, &3]);
+//! ```
+
+/// リンクが無いことを表す添字
+const NIL: u32 = u32::MAX;
+
+struct Node<T> {
+    /// 空き場所なら None
+    value: Option<T>,
+    /// 次のノード (空き場所なら次の空き場所)
+    next: u32,
+}
+
+/// 添字でつなぐ単方向連結リスト
+pub struct ArenaList<T> {
+    nodes: Vec<Node<T>>,
+    head: u32,
+    tail: u32,
+    /// 空き場所の先頭
+    free: u32,
+    len: usize,
+}
+
+impl<T> ArenaList<T> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// `capacity` 個のノードを先に確保しておく
+    pub fn with_capacity(capacity: usize) -> Self {
+        ArenaList {
+            nodes: Vec::with_capacity(capacity),
+            head: NIL,
+            tail: NIL,
+            free: NIL,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// ノードを置く場所を決める (空き場所があれば使い回す)
+    fn alloc(&mut self, value: T, next: u32) -> u32 {
+        if self.free != NIL {
+            let index = self.free;
+            let node = &mut self.nodes[index as usize];
+            self.free = node.next;
+            *node = Node { value: Some(value), next };
+            return index;
+        }
+        let index = u32::try_from(self.nodes.len())
+            .ok()
+            .filter(|&index| index != NIL)
+            .expect("ArenaList cannot hold more than u32::MAX - 1 nodes");
+        self.nodes.push(Node { value: Some(value), next });
+        index
+    }
+
+    /// 先頭に要素を追加
+    pub fn push_front(&mut self, value: T) {
+        let index = self.alloc(value, self.head);
+        if self.tail == NIL {
+            self.tail = index;
+        }
+        self.head = index;
+        self.len += 1;
+    }
+
+    /// 末尾に要素を追加
+    pub fn push_back(&mut self, value: T) {
+        let index = self.alloc(value, NIL);
+        match self.tail {
+            NIL => self.head = index,
+            tail => self.nodes[tail as usize].next = index,
+        }
+        self.tail = index;
+        self.len += 1;
+    }
+
+    /// 先頭の要素を削除して返す (場所は空きリストに戻す)
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.head == NIL {
+            return None;
+        }
+        let index = self.head;
+        let node = &mut self.nodes[index as usize];
+        self.head = node.next;
+        node.next = self.free;
+        let value = node.value.take();
+        self.free = index;
+        if self.head == NIL {
+            self.tail = NIL;
+        }
+        self.len -= 1;
+        value
+    }
+
+    /// 先頭の要素
+    pub fn front(&self) -> Option<&T> {
+        self.value(self.head)
+    }
+
+    /// 末尾の要素
+    pub fn back(&self) -> Option<&T> {
+        self.value(self.tail)
+    }
+
+    fn value(&self, index: u32) -> Option<&T> {
+        match index {
+            NIL => None,
+            index => self.nodes[index as usize].value.as_ref(),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            nodes: &self.nodes,
+            current: self.head,
+        }
+    }
+}
+
+impl<T> Default for ArenaList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// イテレータ
+pub struct Iter<'a, T> {
+    nodes: &'a [Node<T>],
+    current: u32,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.current == NIL {
+            return None;
+        }
+        let node = &self.nodes[self.current as usize];
+        self.current = node.next;
+        node.value.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_pop() {
+        let mut list = ArenaList::new();
+        assert_eq!(list.pop_front(), None);
+        assert_eq!((list.front(), list.back()), (None, None));
+
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.len(), 3);
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&3)));
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3]);
+
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), Some(3));
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+        assert_eq!(list.back(), None);
+
+        list.push_back(4);
+        assert_eq!((list.front(), list.back()), (Some(&4), Some(&4)));
+    }
+
+    #[test]
+    fn test_slots_are_reused() {
+        let mut list = ArenaList::with_capacity(4);
+        for i in 0..4 {
+            list.push_back(i.to_string());
+        }
+        // 外した分だけ足しても配列は伸びない
+        for i in 4..100 {
+            assert!(list.pop_front().is_some());
+            list.push_back(i.to_string());
+        }
+        assert_eq!(list.nodes.len(), 4);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec!["96", "97", "98", "99"]);
+    }
+}
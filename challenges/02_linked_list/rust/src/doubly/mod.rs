@@ -0,0 +1,120 @@
+//! 双方向連結リスト
+//!
+//! 同じ操作を 2 通りに実装して比べる。
+//!
+//! | | [`rc::DoublyLinkedList`] | [`ptr::DoublyLinkedList`] |
+//! |---|---|---|
+//! | リンク | 次は `Rc<RefCell<_>>`、前は `Weak` | `NonNull` の生ポインタ |
+//! | `unsafe` | 不要 | ポインタを辿るたびに必要 |
+//! | 誤りの検出 | 二重借用は実行時にパニック | 検出されない (未定義動作) |
+//! | 要素の参照 | `Ref<T>` (借用中はそのノードを書き換えられない) | `&T` |
+//! | コスト | 参照カウントと借用フラグの更新 | ポインタの付け替えだけ |
+//!
+//! 前へのリンクを強い参照にすると隣り合うノードが互いを指して循環し、
+//! リストを捨ててもメモリが解放されない。`rc` 版は前を `Weak` にして循環を避け、
+//! `ptr` 版は所有権をすべてリスト側で管理して `Drop` で 1 つずつ解放する。
+//!
+//! どちらも先頭・末尾への追加と削除は O(1)。
+
+/// 両方の実装で同じテストを動かす
+///
+/// リストの型には `new` / `len` / `is_empty` / `push_front` / `push_back` /
+/// `pop_front` / `pop_back` / `peek_front` / `peek_back` と、両方向に回せる
+/// `into_iter` が要る (`peek_*` は `*` で値を読めるものを返す)。
+#[cfg(test)]
+macro_rules! shared_tests {
+    ($list:ident) => {
+        #[test]
+        fn test_push_and_pop_both_ends() {
+            let mut list = $list::new();
+            assert!(list.is_empty());
+            assert_eq!(list.pop_front(), None);
+            assert_eq!(list.pop_back(), None);
+
+            list.push_back(2);
+            list.push_front(1);
+            list.push_back(3);
+            assert_eq!(list.len(), 3);
+
+            assert_eq!(list.pop_back(), Some(3));
+            assert_eq!(list.pop_front(), Some(1));
+            assert_eq!(list.pop_back(), Some(2));
+            assert_eq!(list.pop_front(), None);
+            assert!(list.is_empty());
+
+            // 空になったあとも両端を付け直せる
+            list.push_front(4);
+            assert_eq!(list.pop_back(), Some(4));
+            list.push_back(5);
+            assert_eq!(list.pop_front(), Some(5));
+        }
+
+        #[test]
+        fn test_peek() {
+            let mut list = $list::new();
+            assert!(list.peek_front().is_none());
+            assert!(list.peek_back().is_none());
+
+            list.push_back(1);
+            list.push_back(2);
+            assert_eq!(list.peek_front().map(|v| *v), Some(1));
+            assert_eq!(list.peek_back().map(|v| *v), Some(2));
+        }
+
+        #[test]
+        fn test_into_iter_both_directions() {
+            let mut list = $list::new();
+            for i in 1..=4 {
+                list.push_back(i);
+            }
+            let mut iter = list.into_iter();
+            assert_eq!(iter.next(), Some(1));
+            assert_eq!(iter.next_back(), Some(4));
+            assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3]);
+
+            let mut list = $list::new();
+            for i in 1..=3 {
+                list.push_front(i);
+            }
+            assert_eq!(list.into_iter().rev().collect::<Vec<_>>(), vec![1, 2, 3]);
+        }
+
+        #[test]
+        fn test_drops_every_value_once() {
+            use std::cell::Cell;
+            use std::rc::Rc;
+
+            struct Counted(Rc<Cell<usize>>);
+            impl Drop for Counted {
+                fn drop(&mut self) {
+                    self.0.set(self.0.get() + 1);
+                }
+            }
+
+            let drops = Rc::new(Cell::new(0));
+            let mut list = $list::new();
+            for _ in 0..5 {
+                list.push_back(Counted(Rc::clone(&drops)));
+            }
+            drop(list.pop_front());
+            drop(list.pop_back());
+            assert_eq!(drops.get(), 2);
+            // 残りはリストと一緒に解放される (循環していれば解放されない)
+            drop(list);
+            assert_eq!(drops.get(), 5);
+        }
+
+        #[test]
+        fn test_drop_long_list() {
+            // 再帰的に解放するとスタックが溢れる長さ
+            let mut list = $list::new();
+            for i in 0..200_000 {
+                list.push_back(i);
+            }
+            assert_eq!(list.len(), 200_000);
+        }
+    };
+}
+
+pub mod ptr;
+pub mod rc;
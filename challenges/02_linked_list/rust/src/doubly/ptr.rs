@@ -0,0 +1,261 @@
+//! 生ポインタで作る双方向連結リスト
+//!
+//! 標準ライブラリの `LinkedList` と同じ作り。前後のリンクはどちらも `NonNull` で、
+//! ノードの所有権はリストがまとめて持つ (確保は `Box::leak`、解放は `Box::from_raw`)。
+//! 借用の確認は実行時にも行われないので、リンクの付け替えを誤らないことは
+//! `unsafe` ブロックを書く側が保証する。
+
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+type Link<T> = Option<NonNull<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    prev: Link<T>,
+    next: Link<T>,
+}
+
+/// 双方向連結リスト (生ポインタ版)
+pub struct DoublyLinkedList<T> {
+    head: Link<T>,
+    tail: Link<T>,
+    len: usize,
+    /// ノードを所有していることを drop check と変性に伝える
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T> DoublyLinkedList<T> {
+    pub fn new() -> Self {
+        DoublyLinkedList {
+            head: None,
+            tail: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn alloc(value: T, prev: Link<T>, next: Link<T>) -> NonNull<Node<T>> {
+        NonNull::from(Box::leak(Box::new(Node { value, prev, next })))
+    }
+
+    /// 先頭に要素を追加
+    pub fn push_front(&mut self, value: T) {
+        let node = Self::alloc(value, None, self.head);
+        match self.head {
+            // SAFETY: head はこのリストが所有する生きたノード
+            Some(old) => unsafe { (*old.as_ptr()).prev = Some(node) },
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    /// 末尾に要素を追加
+    pub fn push_back(&mut self, value: T) {
+        let node = Self::alloc(value, self.tail, None);
+        match self.tail {
+            // SAFETY: tail はこのリストが所有する生きたノード
+            Some(old) => unsafe { (*old.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// 先頭の要素を削除して返す
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.map(|old| {
+            // SAFETY: old はリストから外すノードで、ほかに指しているのは次のノードの prev だけ
+            let node = unsafe { Box::from_raw(old.as_ptr()) };
+            match node.next {
+                Some(next) => unsafe { (*next.as_ptr()).prev = None },
+                None => self.tail = None,
+            }
+            self.head = node.next;
+            self.len -= 1;
+            node.value
+        })
+    }
+
+    /// 末尾の要素を削除して返す
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.map(|old| {
+            // SAFETY: pop_front と同じ (向きが逆)
+            let node = unsafe { Box::from_raw(old.as_ptr()) };
+            match node.prev {
+                Some(prev) => unsafe { (*prev.as_ptr()).next = None },
+                None => self.head = None,
+            }
+            self.tail = node.prev;
+            self.len -= 1;
+            node.value
+        })
+    }
+
+    /// 先頭の要素
+    pub fn peek_front(&self) -> Option<&T> {
+        // SAFETY: &self の間ノードは解放も変更もされない
+        self.head.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// 末尾の要素
+    pub fn peek_back(&self) -> Option<&T> {
+        // SAFETY: peek_front と同じ
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// 先頭の要素を書き換える
+    pub fn peek_front_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: &mut self の間、このノードへの参照はほかに無い
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// 末尾の要素を書き換える
+    pub fn peek_back_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: peek_front_mut と同じ
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// 両方向に回せるイテレータ
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            front: self.head,
+            back: self.tail,
+            remaining: self.len,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for DoublyLinkedList<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+// 単方向の `LinkedList` と同じ理由 (ノードはリストが単独で所有する)
+// SAFETY: リストを送るのは全要素を所有権ごと送るのと同じ
+unsafe impl<T: Send> Send for DoublyLinkedList<T> {}
+// SAFETY: &DoublyLinkedList からは &T しか取り出せない
+unsafe impl<T: Sync> Sync for DoublyLinkedList<T> {}
+
+/// 両端から進むイテレータ (`remaining` で前後が行き違わないようにする)
+pub struct Iter<'a, T> {
+    front: Link<T>,
+    back: Link<T>,
+    remaining: usize,
+    _marker: PhantomData<&'a T>,
+}
+
+// SAFETY: &T を返すだけなので、&T と同じ条件
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+// SAFETY: 同上
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.front.map(|node| {
+            // SAFETY: リストを借用している間、ノードは生きている
+            let node = unsafe { &*node.as_ptr() };
+            self.front = node.next;
+            self.remaining -= 1;
+            &node.value
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T> DoubleEndedIterator for Iter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.back.map(|node| {
+            // SAFETY: next と同じ
+            let node = unsafe { &*node.as_ptr() };
+            self.back = node.prev;
+            self.remaining -= 1;
+            &node.value
+        })
+    }
+}
+
+/// 要素を両端から取り出すイテレータ
+pub struct IntoIter<T>(DoublyLinkedList<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> IntoIterator for DoublyLinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    shared_tests!(DoublyLinkedList);
+
+    #[test]
+    fn test_peek_mut() {
+        let mut list = DoublyLinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        *list.peek_front_mut().unwrap() *= 10;
+        *list.peek_back_mut().unwrap() += 5;
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![10, 7]);
+    }
+
+    #[test]
+    fn test_iter_meets_in_the_middle() {
+        let mut list = DoublyLinkedList::new();
+        for i in 1..=5 {
+            list.push_back(i);
+        }
+        let mut iter = list.iter();
+        assert_eq!(iter.size_hint(), (5, Some(5)));
+        assert_eq!((iter.next(), iter.next_back()), (Some(&1), Some(&5)));
+        assert_eq!((iter.next(), iter.next_back()), (Some(&2), Some(&4)));
+        assert_eq!((iter.next(), iter.next_back()), (Some(&3), None));
+        assert_eq!(list.iter().rev().copied().collect::<Vec<_>>(), vec![5, 4, 3, 2, 1]);
+    }
+}
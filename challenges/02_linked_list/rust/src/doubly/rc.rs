@@ -0,0 +1,210 @@
+//! `Rc<RefCell<_>>` で作る双方向連結リスト (`unsafe` なし)
+//!
+//! ノードは次のノードを `Rc` で所有し、前のノードは `Weak` で指すだけにする。
+//! 中身を書き換えるには `RefCell` で実行時に借用を確かめるので、要素の参照は
+//! `&T` ではなく [`Ref`] で返す。
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::{Rc, Weak};
+
+type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+
+struct Node<T> {
+    value: T,
+    next: Link<T>,
+    /// 強い参照にすると隣同士で循環してしまう
+    prev: Option<Weak<RefCell<Node<T>>>>,
+}
+
+impl<T> Node<T> {
+    fn new(value: T) -> Rc<RefCell<Node<T>>> {
+        Rc::new(RefCell::new(Node {
+            value,
+            next: None,
+            prev: None,
+        }))
+    }
+}
+
+/// 外したノードから値を取り出す (リストからの参照はもう無い)
+fn into_value<T>(node: Rc<RefCell<Node<T>>>) -> T {
+    match Rc::try_unwrap(node) {
+        Ok(node) => node.into_inner().value,
+        Err(_) => unreachable!("a detached node has no other owners"),
+    }
+}
+
+/// 双方向連結リスト (`Rc` 版)
+pub struct DoublyLinkedList<T> {
+    head: Link<T>,
+    /// 末尾のノード (先頭から次を辿っても行き着く。`Rc` なので 2 か所から持てる)
+    tail: Link<T>,
+    len: usize,
+}
+
+impl<T> DoublyLinkedList<T> {
+    pub fn new() -> Self {
+        DoublyLinkedList {
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 先頭に要素を追加
+    pub fn push_front(&mut self, value: T) {
+        let node = Node::new(value);
+        match self.head.take() {
+            Some(old) => {
+                old.borrow_mut().prev = Some(Rc::downgrade(&node));
+                node.borrow_mut().next = Some(old);
+            }
+            None => self.tail = Some(Rc::clone(&node)),
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    /// 末尾に要素を追加
+    pub fn push_back(&mut self, value: T) {
+        let node = Node::new(value);
+        match self.tail.take() {
+            Some(old) => {
+                node.borrow_mut().prev = Some(Rc::downgrade(&old));
+                old.borrow_mut().next = Some(Rc::clone(&node));
+            }
+            None => self.head = Some(Rc::clone(&node)),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// 先頭の要素を削除して返す
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.take().map(|old| {
+            match old.borrow_mut().next.take() {
+                Some(next) => {
+                    next.borrow_mut().prev = None;
+                    self.head = Some(next);
+                }
+                None => self.tail = None,
+            }
+            self.len -= 1;
+            into_value(old)
+        })
+    }
+
+    /// 末尾の要素を削除して返す
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.take().map(|old| {
+            match old.borrow_mut().prev.take().and_then(|prev| prev.upgrade()) {
+                Some(prev) => {
+                    prev.borrow_mut().next = None;
+                    self.tail = Some(prev);
+                }
+                None => self.head = None,
+            }
+            self.len -= 1;
+            into_value(old)
+        })
+    }
+
+    /// 先頭の要素 (返した `Ref` を持っている間、そのノードは書き換えられない)
+    pub fn peek_front(&self) -> Option<Ref<'_, T>> {
+        self.head.as_ref().map(|node| Ref::map(node.borrow(), |node| &node.value))
+    }
+
+    /// 末尾の要素
+    pub fn peek_back(&self) -> Option<Ref<'_, T>> {
+        self.tail.as_ref().map(|node| Ref::map(node.borrow(), |node| &node.value))
+    }
+
+    /// 先頭の要素を書き換える
+    pub fn peek_front_mut(&mut self) -> Option<RefMut<'_, T>> {
+        self.head.as_ref().map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.value))
+    }
+
+    /// 末尾の要素を書き換える
+    pub fn peek_back_mut(&mut self) -> Option<RefMut<'_, T>> {
+        self.tail.as_ref().map(|node| RefMut::map(node.borrow_mut(), |node| &mut node.value))
+    }
+}
+
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for DoublyLinkedList<T> {
+    fn drop(&mut self) {
+        // Rc の連鎖に任せると再帰的に drop されるので、1 つずつ外す
+        while self.pop_front().is_some() {}
+    }
+}
+
+/// 要素を両端から取り出すイテレータ
+pub struct IntoIter<T>(DoublyLinkedList<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.0.pop_back()
+    }
+}
+
+impl<T> IntoIterator for DoublyLinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    shared_tests!(DoublyLinkedList);
+
+    #[test]
+    fn test_peek_mut() {
+        let mut list = DoublyLinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        *list.peek_front_mut().unwrap() *= 10;
+        *list.peek_back_mut().unwrap() += 5;
+        assert_eq!(list.into_iter().collect::<Vec<_>>(), vec![10, 7]);
+    }
+
+    #[test]
+    fn test_links_do_not_form_cycles() {
+        let mut list = DoublyLinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        list.push_back(3);
+
+        // 真ん中のノードを強く指すのは前のノードだけ (後ろからは Weak)
+        let middle = list.head.as_ref().unwrap().borrow().next.clone().unwrap();
+        assert_eq!(Rc::strong_count(&middle), 2);
+        assert_eq!(Rc::weak_count(&middle), 1);
+        // 末尾はリストの tail と前のノードの 2 か所から
+        assert_eq!(Rc::strong_count(list.tail.as_ref().unwrap()), 2);
+    }
+}
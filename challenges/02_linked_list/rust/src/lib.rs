@@ -0,0 +1,1148 @@
+//! Linked List - Rust 実装
+//!
+//! Rust での連結リストは所有権の良い練習になる
+//!
+//! ノードは `Box` で確保し、リンクは生ポインタ (`NonNull`) で持つ。末尾のノードも
+//! 指しておくので `push_back` は O(1)。`Box` とそれを指す生ポインタを混ぜると
+//! `Box` を動かしたときに生ポインタが無効になる (Stacked Borrows) ため、
+//! 確保したノードはすべて `Box::into_raw` した生ポインタとして扱い、
+//! 解放するときだけ `Box::from_raw` で `Box` に戻す。
+//!
+//! ```
+//! use linked_list::LinkedList;
+//!
+//! let mut list = LinkedList::new();
+//! list.push_back(2);
+//! list.push_front(1);
+//! list.push_back(3);
+//! assert_eq!(list.iter().collect::<Vec<_>>(), [&1, &2, &3]);
+//! ```
+
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+use std::ops::Index;
+use std::ptr::NonNull;
+
+use json_parser::convert::from_json_array;
+use json_parser::{FromJson, JsonValue, ToJson};
+
+pub mod arena;
+pub mod doubly;
+pub mod persistent;
+
+#[cfg(test)]
+mod miri_tests;
+#[cfg(test)]
+mod model_tests;
+
+/// 次のノードへのリンク (所有権はリストが持つ)
+type Link<T> = Option<NonNull<Node<T>>>;
+
+/// 連結リストのノード
+struct Node<T> {
+    value: T,
+    next: Link<T>,
+}
+
+impl<T> Node<T> {
+    /// ヒープに確保し、生ポインタにする (解放は `Box::from_raw`)
+    fn alloc(value: T, next: Link<T>) -> NonNull<Node<T>> {
+        NonNull::from(Box::leak(Box::new(Node { value, next })))
+    }
+}
+
+/// 単方向連結リスト
+pub struct LinkedList<T> {
+    head: Link<T>,
+    /// 末尾のノード (空なら None)
+    tail: Link<T>,
+    len: usize,
+    /// ノードを所有していることを drop check と変性に伝える
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+impl<T> LinkedList<T> {
+    /// 新しい空のリストを作成
+    pub fn new() -> Self {
+        LinkedList {
+            head: None,
+            tail: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// リストの長さを返す
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// リストが空かどうか
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 先頭に要素を追加
+    pub fn push_front(&mut self, value: T) {
+        let node = Node::alloc(value, self.head);  // 現在の head を新ノードの next に
+        if self.tail.is_none() {
+            self.tail = Some(node);
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    /// 末尾に要素を追加 (末尾を覚えているので O(1))
+    pub fn push_back(&mut self, value: T) {
+        let node = Node::alloc(value, None);
+        match self.tail {
+            // SAFETY: tail はこのリストが所有する生きたノードを指す
+            Some(tail) => unsafe { (*tail.as_ptr()).next = Some(node) },
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// 先頭の要素を削除して返す
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.map(|head| {
+            // SAFETY: head は Node::alloc で確保したノードで、リストから外すのでここで解放してよい
+            let node = unsafe { Box::from_raw(head.as_ptr()) };
+            self.head = node.next;
+            if self.head.is_none() {
+                self.tail = None;
+            }
+            self.len -= 1;
+            node.value
+        })
+    }
+
+    /// 末尾の要素を削除して返す
+    ///
+    /// 単方向リストなので新しい末尾 (1 つ手前) を知るには先頭から辿る必要があり、O(n) かかる。
+    pub fn pop_back(&mut self) -> Option<T> {
+        let tail = self.tail?;
+        if self.head == Some(tail) {
+            return self.pop_front();
+        }
+
+        // 末尾の 1 つ手前を探す
+        let mut prev = self.head?;
+        // SAFETY: head から tail までのノードはすべて生きている
+        unsafe {
+            while (*prev.as_ptr()).next != Some(tail) {
+                prev = (*prev.as_ptr()).next?;
+            }
+            (*prev.as_ptr()).next = None;
+        }
+        self.tail = Some(prev);
+        self.len -= 1;
+        // SAFETY: tail はもうどのノードからも指されていない
+        Some(unsafe { Box::from_raw(tail.as_ptr()) }.value)
+    }
+
+    /// `keep` が false を返した要素を取り除く (残る要素の順序は変わらない)
+    pub fn retain(&mut self, mut keep: impl FnMut(&T) -> bool) {
+        // prev は最後に残したノード。取り除くときは prev (無ければ head) を次につなぎ直す
+        let mut prev: Link<T> = None;
+        let mut current = self.head;
+        while let Some(node) = current {
+            // SAFETY: node はこのリストの生きたノードで、外すまではどこからも解放されない
+            unsafe {
+                current = (*node.as_ptr()).next;
+                if keep(&(*node.as_ptr()).value) {
+                    prev = Some(node);
+                    continue;
+                }
+                match prev {
+                    Some(prev) => (*prev.as_ptr()).next = current,
+                    None => self.head = current,
+                }
+                if current.is_none() {
+                    self.tail = prev;
+                }
+                self.len -= 1;
+                // リンクを直してから解放するので、値の drop がパニックしてもリストは壊れない
+                drop(Box::from_raw(node.as_ptr()));
+            }
+        }
+    }
+
+    /// 連続する重複を 1 つにまとめる (ソート済みなら重複がすべて消える)
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    /// `key` が同じ値になる連続した要素を 1 つにまとめる
+    pub fn dedup_by_key<K: PartialEq>(&mut self, mut key: impl FnMut(&mut T) -> K) {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    /// `same(next, kept)` が true を返した要素を取り除く
+    ///
+    /// `kept` は残した直前の要素。引数の順は `Vec::dedup_by` と同じ。
+    pub fn dedup_by(&mut self, mut same: impl FnMut(&mut T, &mut T) -> bool) {
+        // kept とその次のノードを見比べ、同じなら次を外して kept の次につなぎ直す
+        let Some(mut kept) = self.head else {
+            return;
+        };
+        // SAFETY: kept と next はこのリストの別々の生きたノードで、外すまではどこからも解放されない
+        unsafe {
+            while let Some(next) = (*kept.as_ptr()).next {
+                if !same(&mut (*next.as_ptr()).value, &mut (*kept.as_ptr()).value) {
+                    kept = next;
+                    continue;
+                }
+                (*kept.as_ptr()).next = (*next.as_ptr()).next;
+                if self.tail == Some(next) {
+                    self.tail = Some(kept);
+                }
+                self.len -= 1;
+                drop(Box::from_raw(next.as_ptr()));
+            }
+        }
+    }
+
+    /// 先頭の要素 (O(1))
+    pub fn front(&self) -> Option<&T> {
+        // SAFETY: &self の間ノードは解放も変更もされない
+        self.head.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// 末尾の要素 (O(1))
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: front と同じ
+        self.tail.map(|node| unsafe { &(*node.as_ptr()).value })
+    }
+
+    /// 先頭の要素を書き換える (O(1))
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: &mut self の間、このノードへの参照はほかに無い
+        self.head.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// 末尾の要素を書き換える (O(1))
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        // SAFETY: front_mut と同じ
+        self.tail.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// `index` 番目の要素 (範囲外なら None)
+    ///
+    /// 先頭から `index` 個辿るので O(n)。順に全部読むなら `iter` を使う。
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.iter().nth(index)
+    }
+
+    /// `index` 番目の要素を書き換える (O(n))
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.iter_mut().nth(index)
+    }
+
+    /// イテレータを返す
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            // SAFETY: Iter はリストを借用している間しか使えない
+            current: self.head.map(|node| unsafe { &*node.as_ptr() }),
+        }
+    }
+
+    /// 要素を書き換えられるイテレータを返す
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            current: self.head,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 全要素を先頭から取り出すイテレータ
+    ///
+    /// 途中で捨てても残りは取り除かれ、リストは空になる。
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain { list: self }
+    }
+
+    /// `pred` が true を返した要素だけを取り除きながら返すイテレータ
+    ///
+    /// 残す要素はつながったまま。途中で捨てると、まだ見ていない要素はすべて残る。
+    pub fn drain_filter<F>(&mut self, pred: F) -> DrainFilter<'_, T, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        DrainFilter {
+            cursor: self.cursor_front_mut(),
+            pred,
+        }
+    }
+
+    /// 要素を `sep` でつないだ文字列 (`Display` は `" -> "` でつなぐ)
+    pub fn join(&self, sep: &str) -> String
+    where
+        T: Display,
+    {
+        let mut out = String::new();
+        self.write_joined(&mut out, sep).expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_joined(&self, out: &mut impl fmt::Write, sep: &str) -> fmt::Result
+    where
+        T: Display,
+    {
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                out.write_str(sep)?;
+            }
+            write!(out, "{}", item)?;
+        }
+        Ok(())
+    }
+
+    /// 先頭を指すカーソルを返す (空なら末尾の先を指す)
+    ///
+    /// 辿りながらその場で要素を足したり外したりできる。たとえば安定な挿入ソート:
+    ///
+    /// ```
+    /// use linked_list::LinkedList;
+    ///
+    /// let mut unsorted = LinkedList::new();
+    /// for pair in [(3, 'a'), (1, 'b'), (3, 'c'), (2, 'd')] {
+    ///     unsorted.push_back(pair);
+    /// }
+    ///
+    /// let mut sorted: LinkedList<(i32, char)> = LinkedList::new();
+    /// while let Some(pair) = unsorted.pop_front() {
+    ///     let mut cursor = sorted.cursor_front_mut();
+    ///     // 等しいキーは後ろへ入れるので、元の順序が保たれる
+    ///     while cursor.current().is_some_and(|current| current.0 <= pair.0) {
+    ///         cursor.move_next();
+    ///     }
+    ///     cursor.insert_before(pair);
+    /// }
+    /// let order: Vec<char> = sorted.iter().map(|pair| pair.1).collect();
+    /// assert_eq!(order, ['b', 'd', 'a', 'c']);
+    /// ```
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.head,
+            prev: None,
+            index: 0,
+            list: self,
+        }
+    }
+}
+
+impl<T> Default for LinkedList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `list[i]` (O(n)。範囲外ならパニック)
+impl<T> Index<usize> for LinkedList<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(value) => value,
+            None => panic!("index out of bounds: the len is {} but the index is {}", self.len, index),
+        }
+    }
+}
+
+impl<T> Drop for LinkedList<T> {
+    fn drop(&mut self) {
+        // 再帰的に drop するとスタックを使い切るので、先頭から 1 つずつ外す
+        while self.pop_front().is_some() {}
+    }
+}
+
+// `NonNull` を持つ型は自動では Send / Sync にならないので、ここで明示する。
+// リストはノードを `Box` と同じく単独で所有し、ノードを指す生ポインタはリストの外に
+// 出ない。ノードに触れるのは `&self` か `&mut self` を通したときだけなので、
+// `Vec<T>` と同じ条件で別スレッドへ送ったり共有したりできる。
+// SAFETY: リストを送るのは全要素を所有権ごと送るのと同じ
+unsafe impl<T: Send> Send for LinkedList<T> {}
+// SAFETY: &LinkedList からは &T しか取り出せない (内部可変性は無い)
+unsafe impl<T: Sync> Sync for LinkedList<T> {}
+
+/// イテレータ
+pub struct Iter<'a, T> {
+    current: Option<&'a Node<T>>,
+}
+
+// SAFETY: Iter は &T を返すだけなので、&T と同じく T: Sync なら送れて共有できる
+unsafe impl<T: Sync> Send for Iter<'_, T> {}
+// SAFETY: 同上
+unsafe impl<T: Sync> Sync for Iter<'_, T> {}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.current.map(|node| {
+            // SAFETY: 次のノードも同じリストが所有していて、借用中は生きている
+            self.current = node.next.map(|next| unsafe { &*next.as_ptr() });
+            &node.value
+        })
+    }
+}
+
+/// 要素を書き換えられるイテレータ
+pub struct IterMut<'a, T> {
+    current: Link<T>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+// SAFETY: IterMut は &mut T を順に返すだけなので、&mut T と同じ条件
+unsafe impl<T: Send> Send for IterMut<'_, T> {}
+// SAFETY: &IterMut からは要素に触れない
+unsafe impl<T: Sync> Sync for IterMut<'_, T> {}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.current.map(|node| {
+            // SAFETY: リストを &mut で借用している間、各ノードを 1 度ずつしか返さないので参照は重ならない
+            let node = unsafe { &mut *node.as_ptr() };
+            self.current = node.next;
+            &mut node.value
+        })
+    }
+}
+
+/// リストの途中を指して読み書きするカーソル
+///
+/// 最後の要素の次には要素の無い「末尾の先」があり、そこで `move_next` すると先頭へ戻る。
+/// 単方向リストなので後ろへは戻れない。
+pub struct CursorMut<'a, T> {
+    list: &'a mut LinkedList<T>,
+    /// 今の位置の 1 つ手前 (先頭なら None、末尾の先なら tail)
+    prev: Link<T>,
+    current: Link<T>,
+    /// 今の位置 (末尾の先なら len)
+    index: usize,
+}
+
+// SAFETY: カーソルは &mut LinkedList と同じ権限しか持たない
+unsafe impl<T: Send> Send for CursorMut<'_, T> {}
+// SAFETY: &CursorMut からは index しか読めない
+unsafe impl<T: Sync> Sync for CursorMut<'_, T> {}
+
+impl<T> CursorMut<'_, T> {
+    /// 今の位置 (末尾の先なら None)
+    pub fn index(&self) -> Option<usize> {
+        self.current.map(|_| self.index)
+    }
+
+    /// 次へ進む (末尾の先からは先頭へ)
+    pub fn move_next(&mut self) {
+        match self.current {
+            Some(node) => {
+                self.prev = Some(node);
+                // SAFETY: current はリストの生きたノード
+                self.current = unsafe { (*node.as_ptr()).next };
+                self.index += 1;
+            }
+            None => {
+                self.prev = None;
+                self.current = self.list.head;
+                self.index = 0;
+            }
+        }
+    }
+
+    /// 今の位置の要素 (末尾の先なら None)
+    pub fn current(&mut self) -> Option<&mut T> {
+        // SAFETY: カーソルがリストを &mut で借用していて、返す参照はカーソルの借用より長生きしない
+        self.current.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// 次の要素を見る (末尾の先からは先頭の要素)
+    pub fn peek_next(&mut self) -> Option<&mut T> {
+        let next = match self.current {
+            // SAFETY: current と同じ
+            Some(node) => unsafe { (*node.as_ptr()).next },
+            None => self.list.head,
+        };
+        // SAFETY: current と同じ
+        next.map(|node| unsafe { &mut (*node.as_ptr()).value })
+    }
+
+    /// 今の位置の前に挿入する (末尾の先なら末尾に追加)。カーソルは動かない
+    pub fn insert_before(&mut self, value: T) {
+        let node = Node::alloc(value, self.current);
+        match self.prev {
+            // SAFETY: prev はリストの生きたノード
+            Some(prev) => unsafe { (*prev.as_ptr()).next = Some(node) },
+            None => self.list.head = Some(node),
+        }
+        if self.current.is_none() {
+            self.list.tail = Some(node);
+        }
+        self.prev = Some(node);
+        self.index += 1;
+        self.list.len += 1;
+    }
+
+    /// 今の位置の後ろに挿入する (末尾の先なら先頭に追加)。カーソルは動かない
+    pub fn insert_after(&mut self, value: T) {
+        match self.current {
+            Some(current) => {
+                // SAFETY: current はリストの生きたノード
+                unsafe {
+                    let node = Node::alloc(value, (*current.as_ptr()).next);
+                    (*current.as_ptr()).next = Some(node);
+                    if self.list.tail == Some(current) {
+                        self.list.tail = Some(node);
+                    }
+                }
+                self.list.len += 1;
+            }
+            None => {
+                self.list.push_front(value);
+                // 末尾の先の 1 つ手前は常に tail
+                self.prev = self.list.tail;
+                self.index += 1;
+            }
+        }
+    }
+
+    /// 今の位置の要素を外して返し、次へ進む (末尾の先なら何もしない)
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current?;
+        // SAFETY: node はリストの生きたノードで、つなぎ直したあとはどこからも指されない
+        unsafe {
+            let next = (*node.as_ptr()).next;
+            match self.prev {
+                Some(prev) => (*prev.as_ptr()).next = next,
+                None => self.list.head = next,
+            }
+            if next.is_none() {
+                self.list.tail = self.prev;
+            }
+            self.current = next;
+            self.list.len -= 1;
+            Some(Box::from_raw(node.as_ptr()).value)
+        }
+    }
+}
+
+/// [`LinkedList::drain`] のイテレータ
+pub struct Drain<'a, T> {
+    list: &'a mut LinkedList<T>,
+}
+
+impl<T> Iterator for Drain<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.list.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.list.len, Some(self.list.len))
+    }
+}
+
+impl<T> Drop for Drain<'_, T> {
+    fn drop(&mut self) {
+        while self.list.pop_front().is_some() {}
+    }
+}
+
+/// [`LinkedList::drain_filter`] のイテレータ
+pub struct DrainFilter<'a, T, F> {
+    cursor: CursorMut<'a, T>,
+    pred: F,
+}
+
+impl<T, F> Iterator for DrainFilter<'_, T, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // 外すときはカーソルが次へ進むので、残すときだけ move_next する
+        while let Some(value) = self.cursor.current() {
+            if (self.pred)(value) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+        None
+    }
+}
+
+/// 要素を先頭から取り出すイテレータ (取り出したノードはその場で解放する)
+pub struct IntoIter<T>(LinkedList<T>);
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.0.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
+}
+
+impl<T> IntoIterator for LinkedList<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter(self)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a LinkedList<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut LinkedList<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+impl<T: Debug> Debug for LinkedList<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        let mut first = true;
+        for item in self {
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{:?}", item)?;
+            first = false;
+        }
+        write!(f, "]")
+    }
+}
+
+/// `1 -> 2 -> 3` の形で表示する (空なら何も出さない)
+impl<T: Display> Display for LinkedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_joined(f, " -> ")
+    }
+}
+
+/// JSON の配列にする
+impl<T: ToJson> ToJson for LinkedList<T> {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+/// JSON の配列から順に読み込む
+impl<T: FromJson> FromJson for LinkedList<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let mut list = LinkedList::new();
+        for item in from_json_array(value)? {
+            list.push_back(item?);
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_front() {
+        let mut list = LinkedList::new();
+        list.push_front(1);
+        list.push_front(2);
+        list.push_front(3);
+
+        let items: Vec<_> = list.iter().collect();
+        assert_eq!(items, vec![&3, &2, &1]);
+    }
+
+    #[test]
+    fn test_push_back() {
+        let mut list = LinkedList::new();
+        list.push_back(1);
+        list.push_back(2);
+        list.push_back(3);
+
+        let items: Vec<_> = list.iter().collect();
+        assert_eq!(items, vec![&1, &2, &3]);
+    }
+
+    #[test]
+    fn test_pop_front() {
+        let mut list = LinkedList::new();
+        list.push_front(1);
+        list.push_front(2);
+
+        assert_eq!(list.pop_front(), Some(2));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_front(), None);
+    }
+
+    #[test]
+    fn test_pop_back() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.pop_back(), None);
+
+        list.push_back(1);
+        list.push_back(2);
+        list.push_back(3);
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.len(), 2);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2]);
+
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), Some(1));
+        assert_eq!(list.pop_back(), None);
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_pop_back_walks_the_whole_list() {
+        // 毎回先頭から辿るので O(n)。長いリストでも再帰せずに動くことを確かめる
+        let mut list = LinkedList::new();
+        for i in 0..1000 {
+            list.push_front(i);
+        }
+        for i in 0..1000 {
+            assert_eq!(list.pop_back(), Some(i));
+        }
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn test_front_back() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.front(), None);
+        assert_eq!(list.back(), None);
+
+        list.push_back(1);
+        assert_eq!((list.front(), list.back()), (Some(&1), Some(&1)));
+        list.push_back(2);
+        list.push_front(0);
+        assert_eq!((list.front(), list.back()), (Some(&0), Some(&2)));
+    }
+
+    #[test]
+    fn test_front_back_mut() {
+        let mut list: LinkedList<String> = LinkedList::new();
+        assert_eq!(list.front_mut(), None);
+        assert_eq!(list.back_mut(), None);
+
+        // 1 要素なら同じ値
+        list.push_back(String::from("a"));
+        list.front_mut().unwrap().push('1');
+        list.back_mut().unwrap().push('2');
+        assert_eq!(list.front().map(String::as_str), Some("a12"));
+
+        list.push_back(String::from("b"));
+        list.push_front(String::from("z"));
+        *list.front_mut().unwrap() = String::from("first");
+        list.back_mut().unwrap().make_ascii_uppercase();
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec!["first", "a12", "B"]);
+    }
+
+    #[test]
+    fn test_tail_stays_in_sync() {
+        // 空になったあとも末尾を正しく付け直す
+        let mut list = LinkedList::new();
+        list.push_back(1);
+        assert_eq!(list.pop_front(), Some(1));
+        list.push_back(2);
+        list.push_front(1);
+        list.push_back(3);
+        assert_eq!(list.pop_back(), Some(3));
+        list.push_back(4);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &4]);
+        assert_eq!(list.back(), Some(&4));
+
+        assert_eq!(list.pop_back(), Some(4));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), Some(1));
+        list.push_back(5);
+        assert_eq!((list.front(), list.back(), list.len()), (Some(&5), Some(&5), 1));
+    }
+
+    #[test]
+    fn test_drop_long_list_and_owned_values() {
+        // 100 万要素でもスタックを溢れさせずに解放できる
+        let mut list = LinkedList::new();
+        for i in 0..1_000_000 {
+            list.push_back(i);
+        }
+        drop(list);
+
+        let mut list = LinkedList::new();
+        list.push_back(String::from("a"));
+        list.push_back(String::from("b"));
+        assert_eq!(list.pop_back().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let mut list = LinkedList::new();
+        for i in 1..=3 {
+            list.push_back(i);
+        }
+
+        let mut sum = 0;
+        for value in &list {
+            sum += value;
+        }
+        assert_eq!(sum, 6);
+
+        for value in &mut list {
+            *value *= 10;
+        }
+        assert_eq!(list.iter_mut().map(|v| *v).collect::<Vec<_>>(), vec![10, 20, 30]);
+
+        let doubled: Vec<_> = (&list).into_iter().map(|v| v * 2).filter(|v| *v > 20).collect();
+        assert_eq!(doubled, vec![40, 60]);
+
+        let mut owned = list.into_iter();
+        assert_eq!(owned.size_hint(), (3, Some(3)));
+        assert_eq!(owned.next(), Some(10));
+        assert_eq!(owned.collect::<Vec<_>>(), vec![20, 30]);
+    }
+
+    #[test]
+    fn test_into_iter_drops_the_rest() {
+        // 途中でやめても残りのノードは IntoIter の drop で解放される
+        let mut list = LinkedList::new();
+        list.push_back(String::from("a"));
+        list.push_back(String::from("b"));
+        list.push_back(String::from("c"));
+        let mut iter = list.into_iter();
+        assert_eq!(iter.next().as_deref(), Some("a"));
+        drop(iter);
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut list = LinkedList::new();
+        for i in 1..=10 {
+            list.push_back(i);
+        }
+        // 先頭・途中・末尾のどれも外れる
+        list.retain(|v| v % 3 == 2);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&2, &5, &8]);
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.back(), Some(&8));
+
+        // 末尾を付け直したあとも push_back できる
+        list.push_back(11);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&2, &5, &8, &11]);
+
+        list.retain(|_| true);
+        assert_eq!(list.len(), 4);
+
+        list.retain(|_| false);
+        assert!(list.is_empty());
+        assert_eq!((list.front(), list.back()), (None, None));
+        list.push_back(1);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1]);
+    }
+
+    #[test]
+    fn test_retain_sees_each_value_once_in_order() {
+        let mut list = LinkedList::new();
+        for word in ["a", "bb", "c", "dd"] {
+            list.push_back(word.to_string());
+        }
+        let mut seen = Vec::new();
+        list.retain(|s| {
+            seen.push(s.clone());
+            s.len() == 2
+        });
+        assert_eq!(seen, ["a", "bb", "c", "dd"]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec!["bb", "dd"]);
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut list = LinkedList::new();
+        list.dedup();
+        for i in [1, 1, 2, 3, 3, 3, 1, 4, 4] {
+            list.push_back(i);
+        }
+        list.dedup();
+        // 連続していない 1 は残る
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3, &1, &4]);
+        assert_eq!((list.len(), list.back()), (5, Some(&4)));
+        list.push_back(5);
+        assert_eq!(list.back(), Some(&5));
+
+        let mut list = LinkedList::new();
+        for _ in 0..3 {
+            list.push_back(7);
+        }
+        list.dedup();
+        assert_eq!((list.len(), list.front(), list.back()), (1, Some(&7), Some(&7)));
+    }
+
+    #[test]
+    fn test_dedup_by_key_and_by() {
+        let mut list = LinkedList::new();
+        for word in ["apple", "avocado", "banana", "blueberry", "cherry", "apricot"] {
+            list.push_back(word.to_string());
+        }
+        list.dedup_by_key(|word| word.chars().next());
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec!["apple", "banana", "cherry", "apricot"]);
+
+        // 比べる相手はいつも残した直前の要素
+        let mut list = LinkedList::new();
+        for i in [1, 2, 3, 10, 11, 20] {
+            list.push_back(i);
+        }
+        list.dedup_by(|next, kept| *next - *kept < 5);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &10, &20]);
+        assert_eq!(list.back(), Some(&20));
+    }
+
+    #[test]
+    fn test_drain() {
+        let mut list = LinkedList::new();
+        for i in 1..=4 {
+            list.push_back(i);
+        }
+        let mut drain = list.drain();
+        assert_eq!(drain.size_hint(), (4, Some(4)));
+        assert_eq!(drain.next(), Some(1));
+        // 残りは捨てても取り除かれる
+        drop(drain);
+        assert!(list.is_empty());
+        assert_eq!(list.back(), None);
+
+        list.push_back(5);
+        assert_eq!(list.drain().collect::<Vec<_>>(), vec![5]);
+        assert_eq!(list.drain().next(), None);
+    }
+
+    #[test]
+    fn test_drain_filter() {
+        let mut list = LinkedList::new();
+        for i in 1..=8 {
+            list.push_back(i);
+        }
+        let evens: Vec<_> = list.drain_filter(|v| *v % 2 == 0).collect();
+        assert_eq!(evens, vec![2, 4, 6, 8]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &3, &5, &7]);
+        assert_eq!((list.len(), list.back()), (4, Some(&7)));
+
+        // 述語で書き換えてから残すこともできる
+        let big: Vec<_> = list
+            .drain_filter(|v| {
+                *v *= 10;
+                *v > 40
+            })
+            .collect();
+        assert_eq!(big, vec![50, 70]);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&10, &30]);
+
+        // 途中で止めるとまだ見ていない要素は残る
+        let mut list = LinkedList::new();
+        for i in 1..=5 {
+            list.push_back(i);
+        }
+        assert_eq!(list.drain_filter(|_| true).next(), Some(1));
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&2, &3, &4, &5]);
+        list.push_back(6);
+        assert_eq!(list.len(), 5);
+    }
+
+    #[test]
+    fn test_get_and_index() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.get(0), None);
+        for word in ["a", "b", "c"] {
+            list.push_back(word.to_string());
+        }
+        assert_eq!(list.get(0).map(String::as_str), Some("a"));
+        assert_eq!(list.get(2).map(String::as_str), Some("c"));
+        assert_eq!(list.get(3), None);
+        assert_eq!(list[1], "b");
+
+        list.get_mut(1).unwrap().push('!');
+        assert_eq!(list.get_mut(3), None);
+        assert_eq!(list[1], "b!");
+
+        let result = std::panic::catch_unwind(|| list[3].len());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cursor_moves_and_wraps() {
+        let mut list = LinkedList::new();
+        for i in 1..=3 {
+            list.push_back(i);
+        }
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!((cursor.index(), cursor.current().copied()), (Some(0), Some(1)));
+        assert_eq!(cursor.peek_next().copied(), Some(2));
+        cursor.move_next();
+        cursor.move_next();
+        *cursor.current().unwrap() = 30;
+        cursor.move_next();
+        // 末尾の先
+        assert_eq!((cursor.index(), cursor.current()), (None, None));
+        assert_eq!(cursor.peek_next().copied(), Some(1));
+        cursor.move_next();
+        assert_eq!(cursor.index(), Some(0));
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &30]);
+
+        let mut empty: LinkedList<i32> = LinkedList::new();
+        let mut cursor = empty.cursor_front_mut();
+        assert_eq!(cursor.index(), None);
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.peek_next(), None);
+        assert_eq!(cursor.remove_current(), None);
+    }
+
+    #[test]
+    fn test_cursor_insert() {
+        let mut list = LinkedList::new();
+        list.push_back(2);
+        let mut cursor = list.cursor_front_mut();
+        cursor.insert_before(1);
+        cursor.insert_after(4);
+        assert_eq!((cursor.index(), cursor.current().copied()), (Some(1), Some(2)));
+        cursor.move_next();
+        cursor.insert_before(3);
+        cursor.move_next();
+        // 末尾の先: 前なら末尾に、後ろなら先頭に入る
+        cursor.insert_before(5);
+        cursor.insert_after(0);
+        assert_eq!(cursor.index(), None);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&0, &1, &2, &3, &4, &5]);
+        assert_eq!((list.len(), list.back()), (6, Some(&5)));
+
+        // 空のリストでもどちらからでも入れられ、末尾も付け直される
+        let mut list = LinkedList::new();
+        list.cursor_front_mut().insert_after(2);
+        assert_eq!(list.back(), Some(&2));
+        list.cursor_front_mut().insert_before(1);
+        list.push_back(3);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3]);
+
+        let mut list = LinkedList::new();
+        let mut cursor = list.cursor_front_mut();
+        cursor.insert_before(1);
+        cursor.insert_after(0);
+        list.push_back(2);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&0, &1, &2]);
+    }
+
+    #[test]
+    fn test_cursor_remove_current() {
+        let mut list = LinkedList::new();
+        for i in 1..=5 {
+            list.push_back(i);
+        }
+        let mut cursor = list.cursor_front_mut();
+        assert_eq!(cursor.remove_current(), Some(1));
+        assert_eq!(cursor.current().copied(), Some(2));
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some(4));
+        assert_eq!(cursor.remove_current(), Some(5));
+        assert_eq!(cursor.index(), None);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&2, &3]);
+        assert_eq!((list.len(), list.back()), (2, Some(&3)));
+
+        list.push_back(6);
+        let mut cursor = list.cursor_front_mut();
+        while cursor.remove_current().is_some() {}
+        assert!(list.is_empty());
+        assert_eq!(list.back(), None);
+        list.push_back(7);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&7]);
+    }
+
+    #[test]
+    fn test_cursor_insertion_sort_is_stable() {
+        let mut unsorted = LinkedList::new();
+        for (i, key) in [5, 1, 4, 1, 5, 9, 2, 6, 5, 3].into_iter().enumerate() {
+            unsorted.push_back((key, i));
+        }
+        let mut expected: Vec<_> = unsorted.iter().copied().collect();
+        expected.sort_by_key(|pair| pair.0);
+
+        let mut sorted: LinkedList<(i32, usize)> = LinkedList::new();
+        while let Some(pair) = unsorted.pop_front() {
+            let mut cursor = sorted.cursor_front_mut();
+            while cursor.current().is_some_and(|current| current.0 <= pair.0) {
+                cursor.move_next();
+            }
+            cursor.insert_before(pair);
+        }
+        assert_eq!(sorted.into_iter().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_display_and_join() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.to_string(), "");
+        assert_eq!(list.join(", "), "");
+
+        list.push_back(1);
+        assert_eq!(list.to_string(), "1");
+        list.push_back(2);
+        list.push_back(3);
+        assert_eq!(list.to_string(), "1 -> 2 -> 3");
+        assert_eq!(format!("{:?}", list), "[1, 2, 3]");
+        assert_eq!(list.join(", "), "1, 2, 3");
+        assert_eq!(list.join(""), "123");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut list = LinkedList::new();
+        list.push_back(Some(String::from("a")));
+        list.push_back(None);
+        list.push_back(Some(String::from("c")));
+        let json = list.to_json().to_string();
+        assert_eq!(json, r#"["a",null,"c"]"#);
+
+        let loaded = LinkedList::<Option<String>>::from_json(&json_parser::parse(&json).unwrap()).unwrap();
+        assert_eq!(loaded.iter().collect::<Vec<_>>(), list.iter().collect::<Vec<_>>());
+        assert_eq!(loaded.len(), 3);
+
+        let empty = LinkedList::<i32>::from_json(&JsonValue::Array(vec![])).unwrap();
+        assert!(empty.is_empty());
+        let error = LinkedList::<i32>::from_json(&json_parser::parse("[1, true]").unwrap());
+        assert_eq!(error.err().unwrap(), "[1]: expected number, found bool");
+        assert!(LinkedList::<i32>::from_json(&JsonValue::Null).is_err());
+    }
+
+    #[test]
+    fn test_len() {
+        let mut list = LinkedList::new();
+        assert_eq!(list.len(), 0);
+        assert!(list.is_empty());
+
+        list.push_front(1);
+        list.push_front(2);
+        assert_eq!(list.len(), 2);
+        assert!(!list.is_empty());
+    }
+}
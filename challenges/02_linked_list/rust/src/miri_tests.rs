@@ -0,0 +1,185 @@
+//! `unsafe` を検査するためのテスト
+//!
+//! ふつうの `cargo test` でも動くが、狙いは `cargo +nightly miri test miri_tests` で
+//! 動かしたときに Miri が見つける誤り:
+//!
+//! - エイリアシング: 生ポインタから作った `&mut` が重なっていないか (Stacked Borrows)
+//! - 二重解放 / 解放後の使用: ノードを `Box::from_raw` するのが 1 回だけか
+//! - リーク: テストの終わりに解放されていないノードが無いか
+//!
+//! Miri は遅いので要素数は少なくしてある。
+
+use std::cell::Cell;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::thread;
+
+use crate::doubly;
+use crate::LinkedList;
+
+/// drop された回数を数える値
+struct Counted<'a> {
+    drops: &'a Cell<usize>,
+    panic_on_drop: bool,
+}
+
+impl<'a> Counted<'a> {
+    fn new(drops: &'a Cell<usize>) -> Self {
+        Counted {
+            drops,
+            panic_on_drop: false,
+        }
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+        if self.panic_on_drop {
+            panic!("drop panicked");
+        }
+    }
+}
+
+#[test]
+fn test_send_and_sync() {
+    fn assert_send<T: Send>() {}
+    fn assert_sync<T: Sync>() {}
+    assert_send::<LinkedList<String>>();
+    assert_sync::<LinkedList<String>>();
+    assert_send::<crate::Iter<'static, String>>();
+    assert_send::<crate::IterMut<'static, String>>();
+    assert_send::<crate::IntoIter<String>>();
+    assert_send::<crate::CursorMut<'static, String>>();
+    assert_send::<doubly::ptr::DoublyLinkedList<String>>();
+    assert_sync::<doubly::ptr::DoublyLinkedList<String>>();
+
+    // 別スレッドで作ったリストを受け取り、複数のスレッドから同時に読む
+    let list = thread::spawn(|| {
+        let mut list = LinkedList::new();
+        for i in 0..4 {
+            list.push_back(i);
+        }
+        list
+    })
+    .join()
+    .unwrap();
+    thread::scope(|scope| {
+        let sums: Vec<_> = (0..2)
+            .map(|_| scope.spawn(|| list.iter().sum::<i32>()))
+            .collect();
+        for sum in sums {
+            assert_eq!(sum.join().unwrap(), 6);
+        }
+    });
+}
+
+#[test]
+fn test_aliasing_through_tail_and_iterators() {
+    let mut list = LinkedList::new();
+    list.push_back(1);
+    // head と tail が同じノードを指している状態で読み書きする
+    assert_eq!((list.front(), list.back()), (Some(&1), Some(&1)));
+    for value in list.iter_mut() {
+        *value += 1;
+    }
+    list.push_back(3);
+    list.push_front(0);
+
+    // iter_mut が返した参照を全部同時に持ち、そのあと tail 経由で書く
+    let refs: Vec<&mut i32> = list.iter_mut().collect();
+    for value in refs {
+        *value *= 10;
+    }
+    list.push_back(4);
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![0, 20, 30, 4]);
+    assert_eq!(list.pop_back(), Some(4));
+    assert_eq!(list.back(), Some(&30));
+
+    // カーソルの参照を使ったあとでリンクを付け替える
+    let mut cursor = list.cursor_front_mut();
+    cursor.move_next();
+    *cursor.current().unwrap() += 1;
+    cursor.insert_after(25);
+    cursor.move_next();
+    assert_eq!(cursor.remove_current(), Some(25));
+    assert_eq!(list.iter().copied().collect::<Vec<_>>(), vec![0, 21, 30]);
+}
+
+#[test]
+fn test_each_value_dropped_exactly_once() {
+    let drops = Cell::new(0);
+    let mut list = LinkedList::new();
+    for _ in 0..6 {
+        list.push_back(Counted::new(&drops));
+    }
+    drop(list.pop_front());
+    drop(list.pop_back());
+    assert_eq!(drops.get(), 2);
+
+    let mut keep = [true, false, true, false].into_iter();
+    list.retain(|_| keep.next().unwrap());
+    assert_eq!(drops.get(), 4);
+
+    let mut cursor = list.cursor_front_mut();
+    drop(cursor.remove_current());
+    assert_eq!(drops.get(), 5);
+
+    let mut iter = list.into_iter();
+    drop(iter.next());
+    assert_eq!(drops.get(), 6);
+    drop(iter);
+    assert_eq!(drops.get(), 6);
+}
+
+#[test]
+fn test_panicking_drop_in_retain_keeps_list_valid() {
+    let drops = Cell::new(0);
+    let mut list = LinkedList::new();
+    for i in 0..4 {
+        let mut value = Counted::new(&drops);
+        value.panic_on_drop = i == 1;
+        list.push_back(value);
+    }
+    // 2 番目の値の drop がパニックしても、それまでのつなぎ直しは済んでいる
+    let result = panic::catch_unwind(AssertUnwindSafe(|| list.retain(|_| false)));
+    assert!(result.is_err());
+    assert_eq!(drops.get(), 2);
+    assert_eq!(list.len(), 2);
+    drop(list);
+    assert_eq!(drops.get(), 4);
+}
+
+#[test]
+fn test_no_leaks() {
+    // どれも途中で捨てる。解放漏れがあれば Miri がテストの終わりに報告する
+    let mut list = LinkedList::new();
+    for i in 0..5 {
+        list.push_back(i.to_string());
+    }
+    let mut iter = list.into_iter();
+    iter.next();
+
+    let mut list = LinkedList::new();
+    list.push_back(String::from("a"));
+    let mut cursor = list.cursor_front_mut();
+    cursor.insert_before(String::from("b"));
+    cursor.insert_after(String::from("c"));
+
+    let mut list = doubly::ptr::DoublyLinkedList::new();
+    for i in 0..4 {
+        list.push_front(i.to_string());
+    }
+    list.pop_back();
+    let mut iter = list.into_iter();
+    iter.next_back();
+
+    // Rc 版は Weak で循環を切っているので、捨てれば全部解放される
+    let value = Rc::new(());
+    let mut list = doubly::rc::DoublyLinkedList::new();
+    for _ in 0..3 {
+        list.push_back(Rc::clone(&value));
+    }
+    drop(list);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
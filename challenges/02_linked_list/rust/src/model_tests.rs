@@ -0,0 +1,179 @@
+//! `VecDeque` をお手本にしたランダムテスト
+//!
+//! 乱数で選んだ操作の列を `LinkedList` と `VecDeque` の両方に適用し、戻り値と
+//! 中身がいつも一致することを確かめる。外部のクレートを使わないので、乱数は
+//! 小さな xorshift で作る。失敗したときはシードと操作の列を表示するので、
+//! 同じシードで再現できる。
+
+use std::collections::VecDeque;
+
+use crate::LinkedList;
+
+/// 再現できる擬似乱数 (xorshift64)
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // 0 だと 0 しか出ない
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// 0..n のどれか
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    PushFront(u8),
+    PushBack(u8),
+    PopFront,
+    PopBack,
+    Get(usize),
+    SetAt(usize, u8),
+    Retain(u8),
+    Dedup,
+    DrainFilter(u8),
+    /// カーソルを n 回進めてから挿入する
+    CursorInsert(usize, u8),
+    /// カーソルを n 回進めてから外す
+    CursorRemove(usize),
+}
+
+impl Op {
+    fn random(rng: &mut Rng) -> Op {
+        // 値の幅を狭くして dedup や retain が効くようにする
+        let value = rng.below(4) as u8;
+        let index = rng.below(8);
+        match rng.below(11) {
+            0 | 1 => Op::PushFront(value),
+            2 | 3 => Op::PushBack(value),
+            4 => Op::PopFront,
+            5 => Op::PopBack,
+            6 => Op::Get(index),
+            7 => Op::SetAt(index, value),
+            8 => match rng.below(3) {
+                0 => Op::Retain(value),
+                1 => Op::Dedup,
+                _ => Op::DrainFilter(value),
+            },
+            9 => Op::CursorInsert(index, value),
+            _ => Op::CursorRemove(index),
+        }
+    }
+}
+
+/// 1 つの操作を両方に適用し、観測できる結果を比べる
+fn apply(list: &mut LinkedList<u8>, model: &mut VecDeque<u8>, op: &Op) -> Result<(), String> {
+    fn check<T: PartialEq + std::fmt::Debug>(actual: T, expected: T) -> Result<(), String> {
+        match actual == expected {
+            true => Ok(()),
+            false => Err(format!("got {:?}, expected {:?}", actual, expected)),
+        }
+    }
+
+    match *op {
+        Op::PushFront(value) => {
+            list.push_front(value);
+            model.push_front(value);
+        }
+        Op::PushBack(value) => {
+            list.push_back(value);
+            model.push_back(value);
+        }
+        Op::PopFront => check(list.pop_front(), model.pop_front())?,
+        Op::PopBack => check(list.pop_back(), model.pop_back())?,
+        Op::Get(index) => check(list.get(index), model.get(index))?,
+        Op::SetAt(index, value) => {
+            if let Some(slot) = list.get_mut(index) {
+                *slot = value;
+            }
+            if let Some(slot) = model.get_mut(index) {
+                *slot = value;
+            }
+        }
+        Op::Retain(value) => {
+            list.retain(|v| *v != value);
+            model.retain(|v| *v != value);
+        }
+        Op::Dedup => {
+            list.dedup();
+            let mut deduped: Vec<u8> = model.drain(..).collect();
+            deduped.dedup();
+            model.extend(deduped);
+        }
+        Op::DrainFilter(value) => {
+            let drained: Vec<u8> = list.drain_filter(|v| *v == value).collect();
+            let expected: Vec<u8> = model.iter().copied().filter(|v| *v == value).collect();
+            model.retain(|v| *v != value);
+            check(drained, expected)?;
+        }
+        Op::CursorInsert(steps, value) => {
+            // 末尾の先で止まったら先頭へ戻る
+            let position = steps % (model.len() + 1);
+            let mut cursor = list.cursor_front_mut();
+            for _ in 0..steps {
+                cursor.move_next();
+            }
+            check(cursor.index(), (position < model.len()).then_some(position))?;
+            cursor.insert_before(value);
+            model.insert(position, value);
+        }
+        Op::CursorRemove(steps) => {
+            let position = steps % (model.len() + 1);
+            let mut cursor = list.cursor_front_mut();
+            for _ in 0..steps {
+                cursor.move_next();
+            }
+            check(cursor.remove_current(), model.remove(position))?;
+        }
+    }
+
+    check(list.len(), model.len())?;
+    check(list.front(), model.front())?;
+    check(list.back(), model.back())?;
+    check(list.iter().collect::<Vec<_>>(), model.iter().collect::<Vec<_>>())
+}
+
+/// シードごとに操作の列を作って流す
+fn run(seed: u64, steps: usize) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut list = LinkedList::new();
+    let mut model = VecDeque::new();
+    let mut history = Vec::new();
+    for _ in 0..steps {
+        let op = Op::random(&mut rng);
+        history.push(op.clone());
+        apply(&mut list, &mut model, &op)
+            .map_err(|error| format!("seed {}: {} after {:?}", seed, error, history))?;
+    }
+    // 最後に空にしても一致する
+    let drained: Vec<u8> = list.drain().collect();
+    if model != drained || !list.is_empty() || list.back().is_some() {
+        return Err(format!("seed {}: drain mismatch after {:?}", seed, history));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_random_operations_match_vecdeque() {
+    for seed in 0..300 {
+        if let Err(error) = run(seed, 200) {
+            panic!("{}", error);
+        }
+    }
+}
+
+#[test]
+fn test_long_random_run() {
+    // 長い列で、リストが大きくなったり空に戻ったりを繰り返す
+    run(0xDEAD_BEEF, 20_000).unwrap();
+}
@@ -0,0 +1,171 @@
+//! 永続 (イミュータブル) リスト
+//!
+//! 関数型言語の cons リストと同じく、一度作ったノードは書き換えない。
+//! `cons` は元のリストを丸ごと後ろに共有した新しいリストを返し、`tail` は
+//! 2 番目のノードを共有するだけなので、どちらも O(1) で要素を複製しない。
+//! ノードを `Rc` で持つので、1 つのノードを複数のリストから指せる
+//! (`concepts/functional` の `enum List { Nil, Cons(T, Box<List<T>>) }` は
+//! `Box` なので共有できない)。
+//!
+//! ```
+//! use linked_list::persistent::PersistentList;
+//!
+//! let base = PersistentList::new().cons(2).cons(1);
+//! let a = base.cons(0);
+//! let b = base.cons(10);
+//! assert_eq!(a.iter().collect::<Vec<_>>(), [&0, &1, &2]);
+//! assert_eq!(b.iter().collect::<Vec<_>>(), [&10, &1, &2]);
+//! ```
+
+use std::rc::Rc;
+
+type Link<T> = Option<Rc<Node<T>>>;
+
+struct Node<T> {
+    value: T,
+    next: Link<T>,
+}
+
+/// 後ろを共有できる単方向リスト
+pub struct PersistentList<T> {
+    head: Link<T>,
+}
+
+impl<T> PersistentList<T> {
+    pub fn new() -> Self {
+        PersistentList { head: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    /// 先頭に `value` を付けたリストを返す (`self` はそのまま使える)
+    pub fn cons(&self, value: T) -> Self {
+        PersistentList {
+            head: Some(Rc::new(Node {
+                value,
+                next: self.head.clone(),
+            })),
+        }
+    }
+
+    /// 先頭の要素
+    pub fn head(&self) -> Option<&T> {
+        self.head.as_ref().map(|node| &node.value)
+    }
+
+    /// 先頭を除いたリスト (空なら空)
+    pub fn tail(&self) -> Self {
+        PersistentList {
+            head: self.head.as_ref().and_then(|node| node.next.clone()),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            current: self.head.as_deref(),
+        }
+    }
+}
+
+impl<T> Default for PersistentList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 先頭のノードを共有するだけなので O(1) (`T: Clone` も要らない)
+impl<T> Clone for PersistentList<T> {
+    fn clone(&self) -> Self {
+        PersistentList {
+            head: self.head.clone(),
+        }
+    }
+}
+
+impl<T> Drop for PersistentList<T> {
+    fn drop(&mut self) {
+        // 自分だけが持っているノードを先頭から外していき、共有されているノードで止める
+        let mut link = self.head.take();
+        while let Some(node) = link {
+            match Rc::try_unwrap(node) {
+                Ok(mut node) => link = node.next.take(),
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// イテレータ
+pub struct Iter<'a, T> {
+    current: Option<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.current.map(|node| {
+            self.current = node.next.as_deref();
+            &node.value
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cons_head_tail() {
+        let empty = PersistentList::new();
+        assert!(empty.is_empty());
+        assert_eq!(empty.head(), None);
+        assert!(empty.tail().is_empty());
+
+        let list = empty.cons(3).cons(2).cons(1);
+        assert_eq!(list.head(), Some(&1));
+        assert_eq!(list.tail().head(), Some(&2));
+        assert_eq!(list.tail().tail().tail().head(), None);
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![&1, &2, &3]);
+        // 元のリストは変わらない
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_structure_is_shared() {
+        let base = PersistentList::new().cons(String::from("b")).cons(String::from("a"));
+        let node = |list: &PersistentList<String>| Rc::clone(list.head.as_ref().unwrap());
+        let shared = node(&base);
+        assert_eq!(Rc::strong_count(&shared), 2);
+
+        // 2 つのリストが同じ後ろを指す (要素は複製されない)
+        let x = base.cons(String::from("x"));
+        let y = base.cons(String::from("y"));
+        assert_eq!(Rc::strong_count(&shared), 4);
+        assert!(Rc::ptr_eq(&node(&x.tail()), &shared));
+        assert!(Rc::ptr_eq(&node(&y.tail()), &shared));
+
+        let copy = x.clone();
+        assert_eq!(Rc::strong_count(&node(&x)), 3);
+        drop(copy);
+
+        // 捨てても共有している部分は残る
+        drop(x);
+        drop(base);
+        assert_eq!(Rc::strong_count(&shared), 2);
+        assert_eq!(y.iter().collect::<Vec<_>>(), vec!["y", "a", "b"]);
+    }
+
+    #[test]
+    fn test_drop_long_list() {
+        let mut list = PersistentList::new();
+        for i in 0..1_000_000 {
+            list = list.cons(i);
+        }
+        let tail = list.tail();
+        drop(list);
+        assert_eq!(tail.head(), Some(&999_998));
+    }
+}
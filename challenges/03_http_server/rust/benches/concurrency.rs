@@ -0,0 +1,112 @@
+//! スレッド版とイベントループ版の比較
+//!
+//! サーバーを `--port 0` で起動し、多数の keep-alive 接続から同時に
+//! `GET /hello/bench` を送り続けて、スループットとレイテンシを測る。
+//!
+//! ```text
+//! cargo bench --features event-loop --bench concurrency            # 500 接続 x 40 リクエスト
+//! cargo bench --features event-loop --bench concurrency -- 1000 10 # 接続数とリクエスト数を指定
+//! ```
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CONNECTIONS: usize = 500;
+const DEFAULT_REQUESTS: usize = 40;
+
+/// サーバーを起動し、待ち受けているアドレスを返す
+fn start(extra: &[&str]) -> (Child, SocketAddr) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_http_server"))
+        .args(["-q", "--port", "0", "--max-requests", "1000000"])
+        .args(extra)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to start the server");
+
+    let stdout = child.stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+    let addr = lines
+        .by_ref()
+        .map_while(Result::ok)
+        .find_map(|line| line.strip_prefix("Listening on http://").map(|a| a.parse().unwrap()))
+        .expect("the server did not report its address");
+    // 残りの出力は読み捨てる (パイプが詰まらないように)
+    thread::spawn(move || lines.for_each(drop));
+    (child, addr)
+}
+
+/// 1 接続で `requests` 回送り、それぞれのレイテンシを返す
+fn client(addr: SocketAddr, requests: usize) -> Vec<Duration> {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut latencies = Vec::with_capacity(requests);
+
+    for _ in 0..requests {
+        let started = Instant::now();
+        stream.write_all(b"GET /hello/bench HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        latencies.push(started.elapsed());
+    }
+    latencies
+}
+
+/// 全接続から同時に送り、結果を 1 行表示する
+fn run(name: &str, extra: &[&str], connections: usize, requests: usize) {
+    let (mut child, addr) = start(extra);
+
+    let started = Instant::now();
+    let clients: Vec<_> = (0..connections)
+        .map(|_| thread::spawn(move || client(addr, requests)))
+        .collect();
+    let mut latencies: Vec<Duration> = clients.into_iter().flat_map(|c| c.join().unwrap()).collect();
+    let elapsed = started.elapsed();
+
+    child.kill().ok();
+    child.wait().ok();
+
+    latencies.sort();
+    let percentile = |p: usize| latencies[(latencies.len() * p / 100).min(latencies.len() - 1)];
+    println!(
+        "{:<22} {:>10.0} req/s {:>9.2?} p50 {:>9.2?} p99 {:>9.2?} max",
+        name,
+        latencies.len() as f64 / elapsed.as_secs_f64(),
+        percentile(50),
+        percentile(99),
+        latencies[latencies.len() - 1]
+    );
+}
+
+fn main() {
+    // cargo bench が付ける --bench などのフラグは無視する
+    let numbers: Vec<usize> = std::env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    let connections = numbers.first().copied().unwrap_or(DEFAULT_CONNECTIONS);
+    let requests = numbers.get(1).copied().unwrap_or(DEFAULT_REQUESTS);
+
+    println!("{} connections x {} requests\n", connections, requests);
+    run("thread per connection", &[], connections, requests);
+    run("8 workers", &["--workers", "8"], connections, requests);
+    if cfg!(feature = "event-loop") {
+        run("event loop", &["--event-loop"], connections, requests);
+    } else {
+        println!("(build with --features event-loop to compare the event loop)");
+    }
+}
@@ -0,0 +1,259 @@
+//! `routes!` — ルートの一覧を書く小さな DSL
+//!
+//! ```text
+//! routes! {
+//!     GET  "/"                  => index,
+//!     GET  "/hello/:name"       => hello,      // fn hello(request: &Request, name: &str)
+//!     GET  "/tasks/:id<usize>"  => show_task,  // fn show_task(request: &Request, id: usize)
+//!     GET  "/files/*path"       => file,       // 残り全部 (`/` を含む)
+//!     POST "/echo"              => echo,
+//! }
+//! ```
+//!
+//! トークン列を `syn` でパースし、パスのパターンもコンパイル時に分解・検査する。
+//! メソッド名の誤り、パラメーター名の重複、`*name` が最後にない、同じルートの二重登録は
+//! コンパイルエラーになる。展開結果は `crate::router::Router` の式で、`static` に置ける。
+
+use std::collections::HashSet;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitStr, Token, Type};
+
+const METHODS: [&str; 7] = ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"];
+
+#[proc_macro]
+pub fn routes(input: TokenStream) -> TokenStream {
+    let routes = parse_macro_input!(input as Routes);
+    expand(routes).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// `METHOD "pattern" => handler`
+struct RouteDef {
+    method: Ident,
+    pattern: LitStr,
+    handler: syn::Path,
+}
+
+struct Routes(Vec<RouteDef>);
+
+impl Parse for Routes {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut routes = Vec::new();
+        while !input.is_empty() {
+            let method: Ident = input.parse()?;
+            let pattern: LitStr = input.parse()?;
+            input.parse::<Token![=>]>()?;
+            let handler: syn::Path = input.parse()?;
+            routes.push(RouteDef { method, pattern, handler });
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(Routes(routes))
+    }
+}
+
+/// パターンの 1 セグメント
+enum Segment {
+    Literal(String),
+    /// `:name` または `:name<Type>`
+    Param(Option<Type>),
+    /// `*name`
+    Rest,
+}
+
+/// パターンを `/` で分けて検査する (`"/"` はセグメントなし)
+fn parse_pattern(pattern: &LitStr) -> syn::Result<Vec<Segment>> {
+    let text = pattern.value();
+    let error = |message: String| syn::Error::new(pattern.span(), message);
+    let Some(path) = text.strip_prefix('/') else {
+        return Err(error(format!("route pattern must start with '/': {:?}", text)));
+    };
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let parts: Vec<&str> = path.split('/').collect();
+    let mut names = HashSet::new();
+    let mut segments = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let (name, segment) = if let Some(param) = part.strip_prefix(':') {
+            let (name, ty) = match param.split_once('<') {
+                Some((name, ty)) => {
+                    let ty = ty
+                        .strip_suffix('>')
+                        .ok_or_else(|| error(format!("unclosed type in {:?}", part)))?;
+                    let ty = syn::parse_str::<Type>(ty).map_err(|_| error(format!("invalid type in {:?}", part)))?;
+                    (name, Some(ty))
+                }
+                None => (param, None),
+            };
+            (name, Segment::Param(ty))
+        } else if let Some(name) = part.strip_prefix('*') {
+            if i + 1 != parts.len() {
+                return Err(error(format!("{:?} must be the last segment", part)));
+            }
+            (name, Segment::Rest)
+        } else if part.is_empty() {
+            return Err(error(format!("empty segment in {:?}", text)));
+        } else {
+            segments.push(Segment::Literal(part.to_string()));
+            continue;
+        };
+        if syn::parse_str::<Ident>(name).is_err() {
+            return Err(error(format!("invalid parameter name {:?}", name)));
+        }
+        if !names.insert(name) {
+            return Err(error(format!("duplicate parameter name {:?}", name)));
+        }
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+/// 同じ形のルートを見分けるキー (パラメーター名は区別しない)
+fn shape(segments: &[Segment]) -> String {
+    let parts: Vec<String> = segments
+        .iter()
+        .map(|segment| match segment {
+            Segment::Literal(text) => text.clone(),
+            Segment::Param(None) => ":".to_string(),
+            Segment::Param(Some(ty)) => format!(":<{}>", quote!(#ty)),
+            Segment::Rest => "*".to_string(),
+        })
+        .collect();
+    format!("/{}", parts.join("/"))
+}
+
+fn expand(routes: Routes) -> syn::Result<TokenStream2> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for RouteDef { method, pattern, handler } in routes.0 {
+        let method_name = method.to_string();
+        if !METHODS.contains(&method_name.as_str()) {
+            return Err(syn::Error::new(
+                method.span(),
+                format!("unknown HTTP method `{}` (expected one of {})", method_name, METHODS.join(", ")),
+            ));
+        }
+        let segments = parse_pattern(&pattern)?;
+        if !seen.insert((method_name.clone(), shape(&segments))) {
+            return Err(syn::Error::new(
+                pattern.span(),
+                format!("duplicate route: {} {}", method_name, pattern.value()),
+            ));
+        }
+
+        let mut arguments = Vec::new();
+        let mut segment_tokens = Vec::new();
+        for segment in &segments {
+            let index = arguments.len();
+            match segment {
+                Segment::Literal(text) => segment_tokens.push(quote!(crate::router::Segment::Literal(#text))),
+                Segment::Param(ty) => {
+                    segment_tokens.push(quote!(crate::router::Segment::Param));
+                    arguments.push(match ty {
+                        // 変換できなければ、このルートには合わなかったことにする
+                        Some(ty) => quote!(__values[#index].parse::<#ty>().ok()?),
+                        None => quote!(__values[#index]),
+                    });
+                }
+                Segment::Rest => {
+                    segment_tokens.push(quote!(crate::router::Segment::Rest));
+                    arguments.push(quote!(__values[#index]));
+                }
+            }
+        }
+
+        entries.push(quote! {
+            crate::router::Route {
+                method: #method_name,
+                segments: &[#(#segment_tokens),*],
+                handler: |__request, __values| {
+                    ::std::option::Option::Some(crate::router::IntoResponse::into_response(
+                        #handler(__request #(, #arguments)*),
+                    ))
+                },
+            }
+        });
+    }
+
+    Ok(quote! {{
+        const ROUTES: &[crate::router::Route] = &[#(#entries),*];
+        crate::router::Router::new(ROUTES)
+    }})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(text: &str) -> syn::Result<Vec<Segment>> {
+        parse_pattern(&LitStr::new(text, proc_macro2::Span::call_site()))
+    }
+
+    fn expand_str(tokens: TokenStream2) -> Result<String, String> {
+        let routes: Routes = syn::parse2(tokens).map_err(|e| e.to_string())?;
+        expand(routes).map(|tokens| tokens.to_string()).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert!(pattern("/").unwrap().is_empty());
+        assert_eq!(shape(&pattern("/users/:id/posts").unwrap()), "/users/:/posts");
+        assert_eq!(shape(&pattern("/tasks/:id<usize>").unwrap()), "/tasks/:<usize>");
+        assert_eq!(shape(&pattern("/files/*path").unwrap()), "/files/*");
+
+        let error = |text: &str| match pattern(text) {
+            Ok(_) => panic!("{:?} should be rejected", text),
+            Err(e) => e.to_string(),
+        };
+        assert_eq!(error("users"), "route pattern must start with '/': \"users\"");
+        assert_eq!(error("/a//b"), "empty segment in \"/a//b\"");
+        assert_eq!(error("/a/"), "empty segment in \"/a/\"");
+        assert_eq!(error("/*rest/more"), "\"*rest\" must be the last segment");
+        assert_eq!(error("/:a/:a"), "duplicate parameter name \"a\"");
+        assert_eq!(error("/:1st"), "invalid parameter name \"1st\"");
+        assert_eq!(error("/:id<usize"), "unclosed type in \":id<usize\"");
+        assert_eq!(error("/:id<1 + 2>"), "invalid type in \":id<1 + 2>\"");
+    }
+
+    #[test]
+    fn test_expand() {
+        let expanded = expand_str(quote! {
+            GET "/" => index,
+            GET "/tasks/:id<usize>/notes/*path" => handlers::note,
+        })
+        .unwrap();
+        assert!(expanded.contains("method : \"GET\""));
+        assert!(expanded.contains(
+            "segments : & [crate :: router :: Segment :: Literal (\"tasks\") , crate :: router :: Segment :: Param , \
+             crate :: router :: Segment :: Literal (\"notes\") , crate :: router :: Segment :: Rest]"
+        ));
+        assert!(expanded.contains("handlers :: note (__request , __values [0usize] . parse :: < usize > () . ok () ? , __values [1usize])"));
+        assert!(expanded.contains("index (__request)"));
+        // 末尾のカンマは省略できる
+        assert!(expand_str(quote!(POST "/echo" => echo)).is_ok());
+        assert!(expand_str(quote!()).is_ok());
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        assert_eq!(
+            expand_str(quote!(FETCH "/" => index)).unwrap_err(),
+            "unknown HTTP method `FETCH` (expected one of GET, HEAD, POST, PUT, PATCH, DELETE, OPTIONS)"
+        );
+        // パラメーター名が違っても同じ形なら重複
+        assert_eq!(
+            expand_str(quote!(GET "/users/:id" => a, POST "/users/:id" => b, GET "/users/:name" => c)).unwrap_err(),
+            "duplicate route: GET /users/:name"
+        );
+        assert!(expand_str(quote!(GET "/a" => a, GET "/a/:id<u32>" => b, GET "/a/:slug" => c)).is_ok());
+        assert!(expand_str(quote!(GET "/" index)).unwrap_err().contains("expected `=>`"));
+        assert!(expand_str(quote!(GET "/" => a GET "/b" => b)).unwrap_err().contains("expected `,`"));
+        assert!(expand_str(quote!(GET 42 => a)).is_err());
+    }
+}
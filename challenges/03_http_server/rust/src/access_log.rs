@@ -0,0 +1,298 @@
+//! アクセスログ (Common Log Format / JSON)
+//!
+//! 1 リクエストにつき 1 行を書く。CLF の項目の後ろに、処理にかかった時間を
+//! マイクロ秒で、リクエスト ID ([`crate::request_id`]) を足している
+//! (Apache の `%h %l %u %t "%r" %>s %b %D %{X-Request-Id}o` と同じ並び)。
+//!
+//! ```text
+//! 127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /hello/world HTTP/1.1" 200 13 85 3f2a9c0d1e4b5a67
+//! ```
+//!
+//! ログ収集ツールに読ませるときは、同じ項目を 1 行 1 オブジェクトの JSON で書ける。
+//!
+//! ```text
+//! {"bytes":13,"duration_us":85,"id":"3f2a9c0d1e4b5a67","method":"GET","path":"/hello/world",...}
+//! ```
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use json_parser::JsonValue;
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::Response;
+
+pub const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// 1970-01-01 からの日数を (年, 月, 日) にする (Howard Hinnant の civil_from_days)
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// UNIX 時刻を CLF の日時 (`10/Oct/2000:13:55:36 +0000`) にする (UTC)
+pub fn format_time(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// UNIX 時刻を ISO 8601 (`2000-10-10T13:55:36Z`) にする (UTC)
+pub fn format_iso_time(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// ログの書式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Common,
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "common" => Ok(LogFormat::Common),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Unknown log format: {} (expected common or json)", other)),
+        }
+    }
+}
+
+/// 1 行分のログ
+pub fn format_line(request: &Request, response: &Response, secs: u64, micros: u128) -> String {
+    let host = request.peer.map_or("-".to_string(), |peer| peer.ip().to_string());
+    let bytes = match response.body.len() {
+        0 => "-".to_string(),
+        n => n.to_string(),
+    };
+    let target = match &request.query {
+        Some(query) => format!("{}?{}", request.path, query),
+        None => request.path.clone(),
+    };
+    format!(
+        "{} - - [{}] \"{} {} {}\" {} {} {} {}",
+        host,
+        format_time(secs),
+        request.method,
+        target,
+        request.version,
+        response.status_code,
+        bytes,
+        micros,
+        request.id.as_deref().unwrap_or("-")
+    )
+}
+
+/// 1 行分のログ (JSON)。無い項目は null
+pub fn format_json(request: &Request, response: &Response, secs: u64, micros: u128) -> String {
+    let text = |s: &str| JsonValue::String(s.to_string());
+    let optional = |s: Option<&str>| s.map_or(JsonValue::Null, text);
+    let number = |n: f64| JsonValue::Number(n);
+    let peer = request.peer.map(|peer| peer.ip().to_string());
+
+    JsonValue::Object(HashMap::from([
+        ("time".to_string(), text(&format_iso_time(secs))),
+        ("id".to_string(), optional(request.id.as_deref())),
+        ("remote".to_string(), optional(peer.as_deref())),
+        ("method".to_string(), text(&request.method)),
+        ("path".to_string(), text(&request.path)),
+        ("query".to_string(), optional(request.query.as_deref())),
+        ("version".to_string(), text(&request.version.to_string())),
+        ("status".to_string(), number(response.status_code as f64)),
+        ("bytes".to_string(), number(response.body.len() as f64)),
+        ("duration_us".to_string(), number(micros as f64)),
+    ]))
+    .to_string()
+}
+
+/// アクセスログを書くミドルウェア
+pub struct AccessLog {
+    out: Mutex<Box<dyn Write + Send>>,
+    format: LogFormat,
+}
+
+impl AccessLog {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        AccessLog {
+            out: Mutex::new(Box::new(out)),
+            format: LogFormat::Common,
+        }
+    }
+
+    /// 書式を変える
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// 標準出力に書く
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+    /// ファイルに追記する
+    pub fn to_file(path: &Path) -> io::Result<Self> {
+        let file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(file))
+    }
+}
+
+impl Middleware for AccessLog {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let started = Instant::now();
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        let response = next(request);
+
+        let micros = started.elapsed().as_micros();
+        let line = match self.format {
+            LogFormat::Common => format_line(request, &response, secs, micros),
+            LogFormat::Json => format_json(request, &response, secs, micros),
+        };
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // ログが書けなくてもレスポンスは返す
+        if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+            eprintln!("Failed to write access log");
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+    use std::sync::Arc;
+
+    /// 書かれた内容を後から読めるバッファ
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(0), "01/Jan/1970:00:00:00 +0000");
+        assert_eq!(format_time(971_185_336), "10/Oct/2000:13:42:16 +0000");
+        assert_eq!(format_time(1_709_210_096), "29/Feb/2024:12:34:56 +0000");
+        assert_eq!(format_iso_time(1_709_210_096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn test_format_line() {
+        let mut request = Request::parse("GET /hello/world HTTP/1.1\r\n\r\n").unwrap();
+        request.peer = Some("127.0.0.1:5000".parse().unwrap());
+        request.id = Some("abc123".to_string());
+        let response = crate::build_response(200, "OK", "Hello, world!");
+        assert_eq!(
+            format_line(&request, &response, 0, 85),
+            "127.0.0.1 - - [01/Jan/1970:00:00:00 +0000] \"GET /hello/world HTTP/1.1\" 200 13 85 abc123"
+        );
+
+        // 接続元や ID が無い、ボディが空のときは `-`
+        let request = Request::parse("GET /?page=2 HTTP/1.1\r\n\r\n").unwrap();
+        let response = crate::build_response(204, "No Content", "");
+        assert!(format_line(&request, &response, 0, 1).contains("\"GET /?page=2 HTTP/1.1\""));
+        assert!(format_line(&request, &response, 0, 1).starts_with("- - - ["));
+        assert!(format_line(&request, &response, 0, 1).ends_with("\" 204 - 1 -"));
+    }
+
+    #[test]
+    fn test_format_json() {
+        let mut request = Request::parse("GET /hello?x=1 HTTP/1.0\r\n\r\n").unwrap();
+        request.id = Some("abc123".to_string());
+        let response = crate::build_response(200, "OK", "Hello");
+
+        let line = format_json(&request, &response, 971_185_336, 85);
+        let JsonValue::Object(map) = json_parser::parse(&line).unwrap() else {
+            panic!("not an object: {}", line);
+        };
+        let field = |key: &str| map[key].to_string();
+        assert_eq!(field("time"), r#""2000-10-10T13:42:16Z""#);
+        assert_eq!(field("id"), r#""abc123""#);
+        assert_eq!(field("remote"), "null");
+        assert_eq!(field("path"), r#""/hello""#);
+        assert_eq!(field("query"), r#""x=1""#);
+        assert_eq!(field("version"), r#""HTTP/1.0""#);
+        assert_eq!((field("status"), field("bytes"), field("duration_us")), ("200".into(), "5".into(), "85".into()));
+        assert!(!line.contains('\n'));
+    }
+
+    #[test]
+    fn test_logs_each_request() {
+        let buffer = Shared::default();
+        let pipeline = Pipeline::new(|req: &Request| crate::build_response(404, "Not Found", &req.path))
+            .with(AccessLog::new(buffer.clone()));
+
+        for path in ["/a", "/bc"] {
+            let mut request = Request::parse(&format!("GET {} HTTP/1.1\r\n\r\n", path)).unwrap();
+            pipeline.handle(&mut request);
+        }
+
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("\"GET /a HTTP/1.1\" 404 2 "));
+        assert!(lines[1].contains("\"GET /bc HTTP/1.1\" 404 3 "));
+    }
+
+    #[test]
+    fn test_logs_request_ids_as_json() {
+        let buffer = Shared::default();
+        let pipeline = Pipeline::new(|req: &Request| crate::build_response(200, "OK", &req.path))
+            .with(crate::request_id::RequestIds)
+            .with(AccessLog::new(buffer.clone()).with_format(LogFormat::Json));
+
+        let response = pipeline.handle(&mut Request::parse("GET /a HTTP/1.1\r\n\r\n").unwrap());
+        let log = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let id = &response.headers["X-Request-Id"];
+        assert!(log.contains(&format!(r#""id":"{}""#, id)));
+        assert_eq!(log.lines().count(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+//! ETag と条件付きリクエスト (RFC 9110 13)
+//!
+//! GET の 200 レスポンスにボディのハッシュから作った弱い ETag を付け、
+//! `If-None-Match` が一致すれば (無ければ `If-Modified-Since` が `Last-Modified`
+//! 以降なら) ボディを送らずに 304 を返す。
+
+use crate::access_log::{civil_from_days, MONTHS};
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::Response;
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+/// ボディの弱い ETag (`W/"<長さ>-<FNV-1a ハッシュ>"`)
+pub fn etag(body: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in body {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("W/\"{:x}-{:016x}\"", body.len(), hash)
+}
+
+/// UNIX 時刻を HTTP の日時 (`Sun, 06 Nov 1994 08:49:37 GMT`) にする
+pub fn http_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let rem = secs % 86_400;
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// HTTP の日時を UNIX 時刻にする (IMF-fixdate のみ。古い書式は None)
+pub fn parse_http_date(date: &str) -> Option<u64> {
+    let parts: Vec<&str> = date.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| m == month)? as i64 + 1;
+    let year: i64 = year.parse().ok()?;
+    let hms: Vec<u64> = time.split(':').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let [h, m, s] = hms.as_slice() else {
+        return None;
+    };
+    if !(1..=31).contains(&day) || *h > 23 || *m > 59 || *s > 60 {
+        return None;
+    }
+
+    // civil_from_days の逆 (days_from_civil)
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    u64::try_from(days).ok().map(|days| days * 86_400 + h * 3600 + m * 60 + s)
+}
+
+/// `If-None-Match` のどれかが `etag` と一致するか (弱い比較)
+fn matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match
+        .split(',')
+        .any(|tag| tag.trim() == "*" || opaque(tag) == opaque(etag))
+}
+
+/// レスポンスを送らずに済むか
+fn not_modified(request: &Request, response: &Response) -> bool {
+    if let Some(if_none_match) = request.header("if-none-match") {
+        return response.headers.get("ETag").is_some_and(|tag| matches(if_none_match, tag));
+    }
+    let since = request.header("if-modified-since").and_then(parse_http_date);
+    let modified = response.headers.get("Last-Modified").and_then(|d| parse_http_date(d));
+    matches!((since, modified), (Some(since), Some(modified)) if modified <= since)
+}
+
+/// ETag を付け、条件付きリクエストに 304 を返すミドルウェア
+pub struct Conditional;
+
+impl Middleware for Conditional {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let mut response = next(request);
+        if request.method != "GET" || response.status_code != 200 {
+            return response;
+        }
+        if !response.headers.contains_key("ETag") {
+            let tag = etag(&response.body);
+            response = response.with_header("ETag", &tag);
+        }
+        if !not_modified(request, &response) {
+            return response;
+        }
+
+        // ETag や Cache-Control などはそのまま、ボディに関するものだけを外す
+        let mut response = response.with_status(304, "Not Modified");
+        response.body.clear();
+        response.headers.remove("Content-Length");
+        response.headers.remove("Content-Type");
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+
+    fn pipeline() -> Pipeline {
+        Pipeline::new(|request: &Request| match request.path.as_str() {
+            "/dated" => crate::build_response(200, "OK", "dated")
+                .with_header("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+            "/missing" => crate::build_response(404, "Not Found", "missing"),
+            _ => crate::build_response(200, "OK", "hello"),
+        })
+        .with(Conditional)
+    }
+
+    fn get(pipeline: &Pipeline, path: &str, header: &str) -> Response {
+        let raw = format!("GET {} HTTP/1.1\r\n{}\r\n", path, header);
+        pipeline.handle(&mut Request::parse(&raw).unwrap())
+    }
+
+    #[test]
+    fn test_etag() {
+        assert_eq!(etag(b"hello"), etag(b"hello"));
+        assert_ne!(etag(b"hello"), etag(b"hellp"));
+        assert!(etag(b"").starts_with("W/\"0-"));
+    }
+
+    #[test]
+    fn test_http_date() {
+        assert_eq!(http_date(784_111_777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(http_date(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse_http_date(&http_date(1_709_251_200)), Some(1_709_251_200));
+        // RFC 850 などの古い書式や壊れた値は無視する
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:00:00 GMT"), None);
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let pipeline = pipeline();
+        let response = get(&pipeline, "/", "");
+        assert_eq!(response.status_code, 200);
+        let tag = response.headers["ETag"].clone();
+
+        let response = get(&pipeline, "/", &format!("If-None-Match: \"x\", {}\r\n", tag));
+        assert_eq!(response.status_code, 304);
+        assert!(response.body.is_empty());
+        assert_eq!(response.headers["ETag"], tag);
+        assert!(!response.headers.contains_key("Content-Length"));
+
+        // W/ の有無は問わない。* は何にでも一致する
+        let strong = tag.trim_start_matches("W/");
+        assert_eq!(get(&pipeline, "/", &format!("If-None-Match: {}\r\n", strong)).status_code, 304);
+        assert_eq!(get(&pipeline, "/", "If-None-Match: *\r\n").status_code, 304);
+        assert_eq!(get(&pipeline, "/", "If-None-Match: \"other\"\r\n").status_code, 200);
+        // 200 以外には付けない
+        let response = get(&pipeline, "/missing", "If-None-Match: *\r\n");
+        assert_eq!(response.status_code, 404);
+        assert!(!response.headers.contains_key("ETag"));
+    }
+
+    #[test]
+    fn test_if_modified_since() {
+        let pipeline = pipeline();
+        let header = |date: &str| format!("If-Modified-Since: {}\r\n", date);
+
+        assert_eq!(get(&pipeline, "/dated", &header("Sun, 06 Nov 1994 08:49:37 GMT")).status_code, 304);
+        assert_eq!(get(&pipeline, "/dated", &header("Mon, 07 Nov 1994 00:00:00 GMT")).status_code, 304);
+        assert_eq!(get(&pipeline, "/dated", &header("Sat, 05 Nov 1994 00:00:00 GMT")).status_code, 200);
+        // Last-Modified が無ければ比べられない
+        assert_eq!(get(&pipeline, "/", &header("Mon, 07 Nov 1994 00:00:00 GMT")).status_code, 200);
+        // If-None-Match があればそちらを優先する
+        let both = format!("If-None-Match: \"other\"\r\n{}", header("Mon, 07 Nov 1994 00:00:00 GMT"));
+        assert_eq!(get(&pipeline, "/dated", &both).status_code, 200);
+    }
+}
@@ -0,0 +1,98 @@
+//! 設定ファイルと環境変数
+//!
+//! 設定ファイルは `todo` コマンドと同じ `key = value` 形式 (`#` 以降はコメント)。
+//! キーはコマンドラインのオプション名から `--` を除いたもので、値を取らない
+//! オプション (`quiet`、`autoindex`) には on / off を書く。どちらもコマンドラインの引数に
+//! 置き換えてから同じパーサーに通すので、書ける値はコマンドラインと変わらない。
+//!
+//! ```text
+//! # server.conf
+//! addr = 0.0.0.0
+//! port = 8080
+//! workers = 8
+//! root = "/srv/www"
+//! quiet = on
+//! ```
+
+/// 値を取らないオプション
+const FLAGS: [&str; 2] = ["quiet", "autoindex"];
+
+/// 環境変数と対応するオプション
+const ENV_VARS: [(&str, &str); 5] = [
+    ("HTTP_SERVER_ADDR", "--addr"),
+    ("HTTP_SERVER_PORT", "--port"),
+    ("HTTP_SERVER_WORKERS", "--workers"),
+    ("HTTP_SERVER_ROOT", "--root"),
+    ("HTTP_SERVER_CONFIG", "--config"),
+];
+
+/// 値を囲む引用符を外す
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+/// 設定ファイルの内容をコマンドラインの引数にする
+pub fn to_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", i + 1))?;
+        let (key, value) = (key.trim(), unquote(value.trim()));
+
+        if FLAGS.contains(&key) {
+            match value {
+                "on" | "true" | "yes" | "1" => args.push(format!("--{}", key)),
+                "off" | "false" | "no" | "0" => {}
+                other => return Err(format!("line {}: expected on/off, got '{}'", i + 1, other)),
+            }
+        } else {
+            args.push(format!("--{}", key));
+            args.push(value.to_string());
+        }
+    }
+    Ok(args)
+}
+
+/// 設定された環境変数をコマンドラインの引数にする
+pub fn env_args(var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    ENV_VARS
+        .iter()
+        .filter_map(|(name, flag)| var(name).map(|value| [flag.to_string(), value]))
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_args() {
+        let text = r#"
+# comment
+port = 9000   # trailing comment
+root = "/srv/www"
+quiet = on
+"#;
+        assert_eq!(to_args(text).unwrap(), ["--port", "9000", "--root", "/srv/www", "--quiet"]);
+        assert!(to_args("quiet = off").unwrap().is_empty());
+
+        assert!(to_args("port 9000").is_err());
+        assert!(to_args("quiet = maybe").is_err());
+    }
+
+    #[test]
+    fn test_env_args() {
+        let var = |name: &str| (name == "HTTP_SERVER_PORT").then(|| "0".to_string());
+        assert_eq!(env_args(var), ["--port", "0"]);
+        assert!(env_args(|_| None).is_empty());
+    }
+}
@@ -0,0 +1,395 @@
+//! 1 本の接続の処理 (keep-alive)
+//!
+//! HTTP/1.1 は `Connection: close` が来るまで、HTTP/1.0 は `Connection: keep-alive`
+//! が来たときだけ接続を使い回す。次のリクエストを待つ時間 (アイドルタイムアウト)
+//! と、1 本の接続で受け付けるリクエスト数には上限を設ける。
+//!
+//! ステータスラインはリクエストのバージョンによらず `HTTP/1.1` にする (RFC 9110 2.5:
+//! サーバーは自分の対応する最も高いバージョンを返す)。1.0 のクライアントには
+//! 1.0 で使える機能 (Content-Length で長さを示すボディ) だけで応える。
+//!
+//! 1 バイトずつゆっくり送ってくるクライアントに接続を占有されないよう、
+//! リクエストを読み始めてから読み終えるまでにも期限を設け、超えたら 408 を返す。
+//!
+//! ハンドラーがパニックしても接続を黙って切らず、500 を返してから閉じる。
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use crate::request::{Limits, Request, RequestError, Version};
+use crate::Response;
+
+/// 既定のアイドルタイムアウト
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 1 本の接続で受け付けるリクエスト数の既定値
+pub const DEFAULT_MAX_REQUESTS: usize = 100;
+
+/// 1 回の読み込み・書き込みを待つ時間の既定値
+pub const DEFAULT_IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 1 リクエストを読み終えるまでの期限の既定値
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 接続の設定
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// 次のリクエストを待つ時間
+    pub idle_timeout: Duration,
+    /// 1 本の接続で受け付けるリクエスト数 (1 なら keep-alive しない)
+    pub max_requests: usize,
+    /// 1 リクエストで読む量の上限
+    pub limits: Limits,
+    /// リクエストの途中で次のデータを待つ時間
+    pub read_timeout: Duration,
+    /// レスポンスの書き込みを待つ時間
+    pub write_timeout: Duration,
+    /// 1 リクエストを読み始めてから読み終えるまでの期限
+    pub request_timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            max_requests: DEFAULT_MAX_REQUESTS,
+            limits: Limits::default(),
+            read_timeout: DEFAULT_IO_TIMEOUT,
+            write_timeout: DEFAULT_IO_TIMEOUT,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+}
+
+/// 読み込みごとに、期限までの残り時間をソケットのタイムアウトにするリーダー
+struct Timed<'a> {
+    stream: &'a TcpStream,
+    /// 1 回の読み込みを待つ時間
+    per_read: Duration,
+    deadline: Instant,
+}
+
+impl Timed<'_> {
+    /// 1 回あたり `per_read`、合計 `total` まで待つようにする
+    fn arm(&mut self, per_read: Duration, total: Duration) {
+        self.per_read = per_read;
+        self.deadline = Instant::now() + total;
+    }
+}
+
+impl Read for Timed<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(self.per_read.min(remaining)))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// クライアントが接続の継続を望んでいるか
+pub fn wants_keep_alive(request: &Request) -> bool {
+    let connection = request.header("connection").unwrap_or("").to_lowercase();
+    let has = |token: &str| connection.split(',').any(|t| t.trim() == token);
+
+    if request.version == Version::Http11 {
+        !has("close")
+    } else {
+        has("keep-alive")
+    }
+}
+
+/// 読み込みの結果に対して返すレスポンスと、接続を続けるか
+///
+/// `count` はこの接続で何番目のリクエストか (1 から)。None なら何も返さずに閉じる。
+/// スレッド版とイベントループ版 ([`crate::event_loop`]) で共有する。
+pub fn respond(
+    result: Result<Request, RequestError>,
+    count: usize,
+    peer: Option<SocketAddr>,
+    config: &Config,
+    handler: &impl Fn(&mut Request) -> Response,
+) -> Option<(Response, bool)> {
+    let (response, keep_alive) = match result {
+        Ok(mut request) => {
+            request.peer = peer;
+            let keep_alive = wants_keep_alive(&request) && count < config.max_requests;
+            match panic::catch_unwind(AssertUnwindSafe(|| handler(&mut request))) {
+                Ok(response) => (response, keep_alive),
+                Err(_) => {
+                    eprintln!("Handler panicked: {} {}", request.method, request.path);
+                    let body = "The server failed to handle the request";
+                    (crate::build_response(500, "Internal Server Error", body), false)
+                }
+            }
+        }
+        // 途中で切断された場合などは何も返さずに閉じる
+        Err(e) => {
+            let (code, text) = e.status()?;
+            eprintln!("Invalid request: {}", e);
+            (crate::build_response(code, text, &e.to_string()), false)
+        }
+    };
+
+    let response = if keep_alive {
+        let remaining = config.max_requests - count;
+        response
+            .with_header("Connection", "keep-alive")
+            .with_header(
+                "Keep-Alive",
+                &format!("timeout={}, max={}", config.idle_timeout.as_secs(), remaining),
+            )
+    } else {
+        response.with_header("Connection", "close")
+    };
+    Some((response, keep_alive))
+}
+
+/// 接続が閉じるまでリクエストを読み、`handler` の結果を返す
+pub fn serve(stream: TcpStream, config: &Config, handler: impl Fn(&mut Request) -> Response) {
+    if let Err(e) = stream.set_write_timeout(Some(config.write_timeout)) {
+        eprintln!("Failed to set timeout: {}", e);
+        return;
+    }
+    let peer = stream.peer_addr().ok();
+    let mut reader = BufReader::new(Timed {
+        stream: &stream,
+        per_read: config.idle_timeout,
+        deadline: Instant::now(),
+    });
+    let mut writer = &stream;
+
+    for count in 1..=config.max_requests.max(1) {
+        // 次のリクエストの最初のバイトを待つ (パイプラインで届いていれば待たない)
+        reader.get_mut().arm(config.idle_timeout, config.idle_timeout);
+        match reader.fill_buf() {
+            Ok(buf) if !buf.is_empty() => {}
+            // 切断、またはアイドルタイムアウトなら何も返さずに閉じる
+            _ => return,
+        }
+
+        reader.get_mut().arm(config.read_timeout, config.request_timeout);
+        let result = Request::read_from(&mut reader, &config.limits);
+        let Some((response, keep_alive)) = respond(result, count, peer, config, &handler) else {
+            return;
+        };
+
+        if let Err(e) = writer.write_all(&response.to_bytes()) {
+            eprintln!("Failed to write response: {}", e);
+            return;
+        }
+        if !keep_alive {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// 1 本の接続だけを受け付けるサーバーを起動し、クライアント側のストリームを返す
+    fn start(config: Config) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, &config, |req: &mut Request| {
+                if req.path == "/panic" {
+                    panic!("bug in a handler");
+                }
+                crate::build_response(200, "OK", &req.path)
+            });
+        });
+        TcpStream::connect(addr).unwrap()
+    }
+
+    /// レスポンスを 1 つ読み、(ヘッダー部, ボディ) を返す
+    fn read_response(reader: &mut impl BufRead) -> (String, String) {
+        let mut head = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+            head.push_str(&line);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    /// 接続がサーバー側から閉じられたか
+    fn closed(reader: &mut impl Read) -> bool {
+        let mut buf = [0; 1];
+        matches!(reader.read(&mut buf), Ok(0))
+    }
+
+    #[test]
+    fn test_wants_keep_alive() {
+        let parse = |raw: &str| Request::parse(raw).unwrap();
+        assert!(wants_keep_alive(&parse("GET / HTTP/1.1\r\n\r\n")));
+        assert!(!wants_keep_alive(&parse("GET / HTTP/1.1\r\nConnection: Close\r\n\r\n")));
+        assert!(!wants_keep_alive(&parse("GET / HTTP/1.0\r\n\r\n")));
+        assert!(wants_keep_alive(&parse("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")));
+    }
+
+    #[test]
+    fn test_serves_several_requests_on_one_connection() {
+        let mut stream = start(Config::default());
+        // 2 つまとめて送っても (パイプライン) 順に返る
+        stream
+            .write_all(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let (head, body) = read_response(&mut reader);
+        assert!(head.contains("Connection: keep-alive"));
+        assert!(head.contains("Keep-Alive: timeout=5, max=99"));
+        assert_eq!(body, "/a");
+        assert_eq!(read_response(&mut reader).1, "/b");
+
+        stream.write_all(b"GET /c HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        let (head, body) = read_response(&mut reader);
+        assert!(head.contains("Connection: close"));
+        assert_eq!(body, "/c");
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_chunked_body_is_not_read_as_the_next_request() {
+        // chunked のボディを読み飛ばさずに続けると、チャンクが次のリクエストに見える
+        let mut stream = start(Config::default());
+        stream
+            .write_all(b"POST /a HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+                         1a\r\nGET /smuggled HTTP/1.1\r\n\r\n\r\n0\r\n\r\nGET /b HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 501 Not Implemented"));
+        assert!(head.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_closes_after_max_requests() {
+        let mut stream = start(Config {
+            max_requests: 2,
+            ..Config::default()
+        });
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        stream.write_all(b"GET /1 HTTP/1.1\r\n\r\n").unwrap();
+        assert!(read_response(&mut reader).0.contains("max=1"));
+        stream.write_all(b"GET /2 HTTP/1.1\r\n\r\n").unwrap();
+        assert!(read_response(&mut reader).0.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_http10_closes_by_default() {
+        let mut stream = start(Config::default());
+        stream.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert!(read_response(&mut reader).0.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut stream = start(Config::default());
+        stream.write_all(b"GET / HTTP/2.0\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 505 HTTP Version Not Supported"));
+        assert!(head.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_rejects_oversized_headers_and_closes() {
+        let mut stream = start(Config {
+            limits: Limits {
+                header_count: 1,
+                ..Limits::default()
+            },
+            ..Config::default()
+        });
+        stream.write_all(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+        assert!(head.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_times_out_stalled_headers() {
+        let mut stream = start(Config {
+            request_timeout: Duration::from_millis(100),
+            ..Config::default()
+        });
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: loc").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 408 Request Timeout"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_deadline_covers_the_whole_request() {
+        // 1 回ごとの待ち時間には収まっていても、全体の期限を過ぎれば 408
+        let stream = start(Config {
+            read_timeout: Duration::from_secs(1),
+            request_timeout: Duration::from_millis(150),
+            ..Config::default()
+        });
+        let mut writer = stream.try_clone().unwrap();
+        thread::spawn(move || {
+            for byte in b"GET / HTTP/1.1\r\nX-Slow: ".iter().cycle() {
+                if writer.write_all(&[*byte]).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let started = Instant::now();
+        let mut reader = BufReader::new(stream);
+        assert!(read_response(&mut reader).0.starts_with("HTTP/1.1 408"));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_panicking_handler_returns_500() {
+        let mut stream = start(Config::default());
+        stream.write_all(b"GET /panic HTTP/1.1\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (head, _) = read_response(&mut reader);
+        assert!(head.starts_with("HTTP/1.1 500 Internal Server Error"));
+        assert!(head.contains("Connection: close"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_closes_idle_connection() {
+        let stream = start(Config {
+            idle_timeout: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let mut reader = BufReader::new(stream);
+        assert!(closed(&mut reader));
+    }
+}
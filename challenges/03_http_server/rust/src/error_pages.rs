@@ -0,0 +1,175 @@
+//! エラーページ
+//!
+//! ステータスコードごとにハンドラーを登録し、ルートが返したエラーを差し替える。
+//! 既定では 404 / 405 / 500 を、`Accept` に応じて HTML か JSON のページにする。
+//! 差し替えるのは `build_response` で作った text/plain のエラーだけで、
+//! `/tasks` の JSON エラーのように中身を作り込んだレスポンスはそのまま返す。
+//!
+//! 内側でパニックが起きたときも 500 にして、このページで返す。
+
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+
+use json_parser::JsonValue;
+
+use crate::middleware::{Middleware, Next};
+use crate::negotiate;
+use crate::request::Request;
+use crate::Response;
+
+/// エラーのレスポンスを作り直すハンドラー (元のレスポンスを受け取る)
+pub type Handler = Box<dyn Fn(&Request, Response) -> Response + Send + Sync>;
+
+/// HTML より JSON を望んでいるか (どちらも受け付けないクライアントには HTML を返す)
+fn wants_json(request: &Request) -> bool {
+    negotiate::choose(request.header("accept"), &["text/html", "application/json"]) == Some("application/json")
+}
+
+/// 既定のエラーページ (元のボディをメッセージとして使う)
+pub fn default_page(request: &Request, response: Response) -> Response {
+    let message = String::from_utf8_lossy(&response.body).into_owned();
+    let (status, reason) = (response.status_code, response.status_text.clone());
+    let text = |s: &str| JsonValue::String(s.to_string());
+
+    let page = if wants_json(request) {
+        Response::json(&JsonValue::Object(HashMap::from([
+            ("error".to_string(), text(&message)),
+            ("status".to_string(), JsonValue::Number(status as f64)),
+        ])))
+    } else {
+        let context = JsonValue::Object(HashMap::from([
+            ("status".to_string(), JsonValue::Number(status as f64)),
+            ("reason".to_string(), text(&reason)),
+            ("message".to_string(), text(&message)),
+        ]));
+        Response::render(include_str!("../templates/error.html"), &context)
+    };
+
+    // Allow などのヘッダーは残す
+    let mut response = response;
+    for (key, value) in page.headers {
+        response.headers.insert(key, value);
+    }
+    response.body = page.body;
+    response
+}
+
+/// ステータスコードごとのエラーページに差し替えるミドルウェア
+pub struct ErrorPages {
+    handlers: HashMap<u16, Handler>,
+}
+
+impl ErrorPages {
+    /// `status_code` のハンドラーを登録する (既にあれば置き換える)
+    pub fn on(
+        mut self,
+        status_code: u16,
+        handler: impl Fn(&Request, Response) -> Response + Send + Sync + 'static,
+    ) -> Self {
+        self.handlers.insert(status_code, Box::new(handler));
+        self
+    }
+}
+
+impl Default for ErrorPages {
+    /// 404 / 405 / 500 に既定のページを使う
+    fn default() -> Self {
+        let pages = ErrorPages {
+            handlers: HashMap::new(),
+        };
+        pages.on(404, default_page).on(405, default_page).on(500, default_page)
+    }
+}
+
+impl Middleware for ErrorPages {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let response = panic::catch_unwind(AssertUnwindSafe(|| next(request))).unwrap_or_else(|_| {
+            let id = request.id.as_deref().unwrap_or("-");
+            eprintln!("Handler panicked: {} {} (request {})", request.method, request.path, id);
+            crate::build_response(500, "Internal Server Error", "The server failed to handle the request")
+        });
+
+        let plain = response
+            .headers
+            .get("Content-Type")
+            .is_some_and(|t| t.starts_with("text/plain"));
+        match self.handlers.get(&response.status_code) {
+            Some(handler) if plain => handler(request, response),
+            _ => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+
+    fn pipeline(pages: ErrorPages) -> Pipeline {
+        Pipeline::new(|request: &Request| match request.path.as_str() {
+            "/panic" => panic!("bug in a route"),
+            "/post-only" => crate::build_response(405, "Method Not Allowed", "use POST").with_header("Allow", "POST"),
+            "/api" => Response::json(&JsonValue::Null).with_status(404, "Not Found"),
+            "/ok" => crate::build_response(200, "OK", "fine"),
+            _ => crate::build_response(404, "Not Found", "no <such> page"),
+        })
+        .with(pages)
+    }
+
+    fn get(pipeline: &Pipeline, path: &str, accept: &str) -> Response {
+        let raw = format!("GET {} HTTP/1.1\r\nAccept: {}\r\n\r\n", path, accept);
+        pipeline.handle(&mut Request::parse(&raw).unwrap())
+    }
+
+    fn body(response: &Response) -> String {
+        String::from_utf8(response.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_html_or_json_by_accept() {
+        let pipeline = pipeline(ErrorPages::default());
+
+        let response = get(&pipeline, "/missing", "text/html,*/*");
+        assert_eq!(response.status_code, 404);
+        assert_eq!(response.headers["Content-Type"], "text/html; charset=utf-8");
+        assert!(body(&response).contains("<h1>404 Not Found</h1>"));
+        assert!(body(&response).contains("no &lt;such&gt; page"));
+        assert_eq!(response.headers["Content-Length"], response.body.len().to_string());
+
+        let response = get(&pipeline, "/missing", "application/json");
+        assert_eq!(response.status_code, 404);
+        let json = json_parser::parse(&body(&response)).unwrap();
+        assert_eq!(json.to_string(), r#"{"error":"no <such> page","status":404}"#);
+
+        // ヘッダーは残る
+        let response = get(&pipeline, "/post-only", "*/*");
+        assert_eq!(response.status_code, 405);
+        assert_eq!(response.headers["Allow"], "POST");
+        assert!(body(&response).contains("use POST"));
+    }
+
+    #[test]
+    fn test_leaves_other_responses_alone() {
+        let pipeline = pipeline(ErrorPages::default());
+        assert_eq!(body(&get(&pipeline, "/ok", "*/*")), "fine");
+        assert_eq!(body(&get(&pipeline, "/api", "*/*")), "null");
+    }
+
+    #[test]
+    fn test_custom_handler() {
+        let pages = ErrorPages::default().on(404, |request: &Request, _| {
+            crate::build_response(404, "Not Found", &format!("nothing at {}", request.path))
+                .with_header("Content-Type", "text/html; charset=utf-8")
+        });
+        let response = get(&pipeline(pages), "/gone", "*/*");
+        assert_eq!(body(&response), "nothing at /gone");
+    }
+
+    #[test]
+    fn test_panic_becomes_500() {
+        let pipeline = pipeline(ErrorPages::default());
+        let response = get(&pipeline, "/panic", "application/json");
+        assert_eq!(response.status_code, 500);
+        assert!(body(&response).contains(r#""status":500"#));
+    }
+}
@@ -0,0 +1,542 @@
+//! イベントループ版のサーバー (`--features event-loop` / `--event-loop`)
+//!
+//! 接続ごとにスレッドを作る代わりに、1 本のスレッドで mio (Linux では epoll、
+//! macOS では kqueue) を使ってすべての接続を見張る。ソケットはノンブロッキングで、
+//! 届いたバイト列を接続ごとのバッファにためておき、リクエストが 1 つ分そろったら
+//! スレッド版と同じ [`connection::respond`] でレスポンスを作る。
+//!
+//! アイドル中の接続がスレッドを占有しないので、同時接続数が多いときに軽い。
+//! その代わりハンドラーはループの中で呼ばれるため、遅いハンドラーは全体を待たせる。
+//!
+//! タイムアウトはアイドルタイムアウト、1 リクエストの期限、書き込みの待ち時間を
+//! 見る (1 回ごとの読み込みの待ち時間 `read_timeout` は使わない)。
+//!
+//! ヘッダーの終わりは新しく届いたバイトだけから探し、ボディはそろうまで待つので、
+//! 少しずつ届くリクエストでもバッファを読み直さない。レスポンスを読まずに
+//! リクエストを送り続けるクライアントには、書き込み待ちがたまったところで
+//! 読むのを止め、TCP の受信側で待たせる。
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::net::{self, SocketAddr};
+use std::time::Instant;
+
+use mio::net::{TcpListener, TcpStream};
+use mio::{Events, Interest, Poll, Token};
+
+use crate::connection::{self, Config};
+use crate::request::{Limits, Request, RequestError};
+use crate::Response;
+
+const LISTENER: Token = Token(0);
+
+/// 書き込み待ちがこれを超えたら、新しいリクエストを読まない
+const OUTPUT_HIGH_WATER: usize = 64 * 1024;
+
+/// ヘッダーの終わり (空行) を探した結果
+#[derive(Debug, PartialEq)]
+enum Scan {
+    /// まだ空行が届いていない
+    Incomplete,
+    /// 空行の直後の位置
+    Complete(usize),
+    /// 空行より前に上限を超えた
+    TooLarge,
+}
+
+/// ヘッダーの終わりを前回の続きから探すための位置
+#[derive(Debug, Default)]
+struct HeadScan {
+    /// ここまでは探し終えている
+    pos: usize,
+    /// 読みかけの行の先頭
+    line_start: usize,
+    /// 読み終えた行の数 (リクエストラインを含む)
+    lines: usize,
+}
+
+impl HeadScan {
+    /// `input` の新しく届いた部分から空行を探す
+    ///
+    /// 行の長さと数の上限は [`Request::read_head`] と同じように見る。
+    fn advance(&mut self, input: &[u8], limits: &Limits) -> Scan {
+        let limit = |lines| if lines == 0 { limits.request_line } else { limits.header_size };
+        while let Some(i) = input[self.pos..].iter().position(|&b| b == b'\n') {
+            let end = self.pos + i + 1;
+            let mut line = &input[self.line_start..end];
+            while let [rest @ .., b'\r' | b'\n'] = line {
+                line = rest;
+            }
+            if line.is_empty() {
+                return Scan::Complete(end);
+            }
+            if line.len() > limit(self.lines) {
+                return Scan::TooLarge;
+            }
+            self.lines += 1;
+            if self.lines > limits.header_count + 1 {
+                return Scan::TooLarge;
+            }
+            self.pos = end;
+            self.line_start = end;
+        }
+        self.pos = input.len();
+        // CRLF の分を除いても上限を超えている
+        if self.pos - self.line_start >= limit(self.lines) + 2 {
+            return Scan::TooLarge;
+        }
+        Scan::Incomplete
+    }
+}
+
+/// 1 本の接続の状態
+struct Conn {
+    stream: TcpStream,
+    peer: Option<SocketAddr>,
+    /// 読んだがまだ処理していないバイト列
+    input: Vec<u8>,
+    /// `input` の中でヘッダーの終わりを探した位置
+    scan: HeadScan,
+    /// ヘッダーまで読んでボディを待っているリクエストと、ボディの長さ
+    pending: Option<(Request, usize)>,
+    /// まだ書き終えていないレスポンス
+    output: Vec<u8>,
+    /// 処理したリクエストの数
+    served: usize,
+    /// 書き終えたら閉じる
+    closing: bool,
+    /// 最後に読み書きできた時刻
+    last_active: Instant,
+    /// 読みかけのリクエストの最初のバイトが届いた時刻
+    request_started: Option<Instant>,
+}
+
+impl Conn {
+    fn new(stream: TcpStream, now: Instant) -> Self {
+        Conn {
+            peer: stream.peer_addr().ok(),
+            stream,
+            input: Vec::new(),
+            scan: HeadScan::default(),
+            pending: None,
+            output: Vec::new(),
+            served: 0,
+            closing: false,
+            last_active: now,
+            request_started: None,
+        }
+    }
+
+    /// 読めるだけ読む (相手が閉じていれば false)
+    fn fill(&mut self, now: Instant) -> io::Result<bool> {
+        let mut buf = [0; 8192];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    if self.request_started.is_none() {
+                        self.request_started = Some(now);
+                    }
+                    self.input.extend_from_slice(&buf[..n]);
+                    self.last_active = now;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// 書けるだけ書く
+    fn flush(&mut self, now: Instant) -> io::Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.output.drain(..n);
+                    self.last_active = now;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// バッファの先頭からリクエストを 1 つ読む (まだ 1 つ分そろっていなければ None)
+    ///
+    /// ヘッダーは空行が届いてからまとめてパースし、ボディは長さの分だけ届くのを待つ。
+    /// エラーを返したら接続は閉じるので、バッファはそのままにしておく。
+    fn parse(&mut self, config: &Config) -> Option<Result<Request, RequestError>> {
+        if self.pending.is_none() {
+            let end = match self.scan.advance(&self.input, &config.limits) {
+                Scan::Incomplete => return None,
+                Scan::Complete(end) => end,
+                // どの上限を超えたかは read_head に任せる
+                Scan::TooLarge => self.input.len(),
+            };
+            match Request::read_head(&mut &self.input[..end], &config.limits) {
+                Ok(head) => self.pending = Some(head),
+                Err(e) => return Some(Err(e)),
+            }
+            self.input.drain(..end);
+            self.scan = HeadScan::default();
+        }
+
+        let length = self.pending.as_ref().map_or(0, |(_, length)| *length);
+        if self.input.len() < length {
+            return None;
+        }
+        let (mut request, _) = self.pending.take()?;
+        request.body = self.input.drain(..length).collect();
+        self.request_started = (!self.input.is_empty()).then(Instant::now);
+        Some(Ok(request))
+    }
+
+    /// そろっているリクエストを処理して、レスポンスを書き込み待ちにする
+    ///
+    /// 書き込み待ちが [`OUTPUT_HIGH_WATER`] を超えたら、残りは書き終えてから処理する。
+    fn process(&mut self, config: &Config, handler: &impl Fn(&mut Request) -> Response) {
+        while !self.closing && self.output.len() < OUTPUT_HIGH_WATER {
+            let Some(result) = self.parse(config) else {
+                return;
+            };
+            self.served += 1;
+            match connection::respond(result, self.served, self.peer, config, handler) {
+                Some((response, keep_alive)) => {
+                    self.output.extend_from_slice(&response.to_bytes());
+                    self.closing = !keep_alive;
+                }
+                None => self.closing = true,
+            }
+        }
+    }
+
+    /// 読む・処理する・書くを、書き込み待ちで止めた分がなくなるまで繰り返す
+    ///
+    /// エッジトリガーなので、読むのを止めていた分はここで続けて読む。
+    fn pump(&mut self, config: &Config, handler: &impl Fn(&mut Request) -> Response, now: Instant) -> io::Result<()> {
+        loop {
+            let throttled = self.output.len() >= OUTPUT_HIGH_WATER;
+            let open = throttled || self.closing || self.fill(now).unwrap_or(false);
+            self.process(config, handler);
+            let throttled = throttled || self.output.len() >= OUTPUT_HIGH_WATER;
+            // 相手が書き込みを閉じても、返すべきレスポンスは書いてから閉じる
+            if !open {
+                self.closing = true;
+            }
+            self.flush(now)?;
+            if !throttled || self.closing || self.output.len() >= OUTPUT_HIGH_WATER {
+                return Ok(());
+            }
+        }
+    }
+
+    /// 次に期限が来る時刻 (その時刻を過ぎたら `expire` で閉じるか 408 を返す)
+    fn deadline(&self, config: &Config) -> Instant {
+        if !self.output.is_empty() {
+            self.last_active + config.write_timeout
+        } else if let Some(started) = self.request_started {
+            started + config.request_timeout
+        } else {
+            self.last_active + config.idle_timeout
+        }
+    }
+
+    /// 期限切れの処理 (読みかけのリクエストがあれば 408 を返してから閉じる)
+    fn expire(&mut self) {
+        if self.output.is_empty() && self.request_started.is_some() {
+            let response = Response::from(RequestError::Timeout).with_header("Connection", "close");
+            self.output = response.to_bytes();
+            self.input.clear();
+            self.pending = None;
+            self.request_started = None;
+            self.last_active = Instant::now();
+        } else {
+            self.output.clear();
+        }
+        self.closing = true;
+    }
+
+    /// もう閉じてよいか
+    fn done(&self) -> bool {
+        self.closing && self.output.is_empty()
+    }
+}
+
+/// `listener` で受け付けた接続をイベントループで処理する (戻らない)
+pub fn run(
+    listener: net::TcpListener,
+    config: &Config,
+    handler: impl Fn(&mut Request) -> Response,
+) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let mut listener = TcpListener::from_std(listener);
+    let mut poll = Poll::new()?;
+    poll.registry().register(&mut listener, LISTENER, Interest::READABLE)?;
+
+    let mut events = Events::with_capacity(1024);
+    let mut conns: HashMap<Token, Conn> = HashMap::new();
+    let mut next_token = 1;
+
+    loop {
+        let now = Instant::now();
+        let timeout = conns
+            .values()
+            .map(|conn| conn.deadline(config).saturating_duration_since(now))
+            .min();
+        if let Err(e) = poll.poll(&mut events, timeout) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        let now = Instant::now();
+        for event in events.iter() {
+            if event.token() == LISTENER {
+                accept(&listener, &poll, &mut conns, &mut next_token, now);
+                continue;
+            }
+            let token = event.token();
+            let Some(conn) = conns.get_mut(&token) else {
+                continue;
+            };
+            if conn.pump(config, &handler, now).is_err() || conn.done() {
+                close(&poll, &mut conns, token);
+            }
+        }
+
+        // 期限を過ぎた接続
+        let expired: Vec<Token> = conns
+            .iter()
+            .filter(|(_, conn)| conn.deadline(config) <= now)
+            .map(|(token, _)| *token)
+            .collect();
+        for token in expired {
+            let conn = conns.get_mut(&token).expect("expired connection");
+            conn.expire();
+            if conn.flush(now).is_err() || conn.done() {
+                close(&poll, &mut conns, token);
+            }
+        }
+    }
+}
+
+/// 待っている接続をすべて受け付ける
+fn accept(listener: &TcpListener, poll: &Poll, conns: &mut HashMap<Token, Conn>, next_token: &mut usize, now: Instant) {
+    loop {
+        match listener.accept() {
+            Ok((mut stream, _)) => {
+                let token = Token(*next_token);
+                *next_token += 1;
+                let interest = Interest::READABLE | Interest::WRITABLE;
+                if let Err(e) = poll.registry().register(&mut stream, token, interest) {
+                    eprintln!("Failed to register connection: {}", e);
+                    continue;
+                }
+                conns.insert(token, Conn::new(stream, now));
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return,
+            Err(e) => {
+                eprintln!("Connection error: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+fn close(poll: &Poll, conns: &mut HashMap<Token, Conn>, token: Token) {
+    if let Some(mut conn) = conns.remove(&token) {
+        let _ = poll.registry().deregister(&mut conn.stream);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::thread;
+    use std::time::Duration;
+
+    /// 分けて送るときの間隔
+    const TICK: Duration = Duration::from_millis(10);
+
+    /// イベントループのサーバーを起動し、接続先を返す
+    fn start(config: Config) -> SocketAddr {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            run(listener, &config, |req: &mut Request| crate::build_response(200, "OK", &req.path))
+        });
+        addr
+    }
+
+    fn read_response(reader: &mut impl BufRead) -> (String, String) {
+        let mut head = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = value.trim().parse().unwrap();
+            }
+            head.push_str(&line);
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        (head, String::from_utf8(body).unwrap())
+    }
+
+    fn closed(reader: &mut impl Read) -> bool {
+        let mut buf = [0; 1];
+        matches!(reader.read(&mut buf), Ok(0))
+    }
+
+    #[test]
+    fn test_serves_many_connections() {
+        let addr = start(Config::default());
+        let clients: Vec<_> = (0..20)
+            .map(|i| {
+                thread::spawn(move || {
+                    let mut stream = net::TcpStream::connect(addr).unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    for j in 0..3 {
+                        stream.write_all(format!("GET /{}/{} HTTP/1.1\r\n\r\n", i, j).as_bytes()).unwrap();
+                        assert_eq!(read_response(&mut reader).1, format!("/{}/{}", i, j));
+                    }
+                })
+            })
+            .collect();
+        for client in clients {
+            client.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_pipelined_and_split_requests() {
+        let addr = start(Config::default());
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        stream.write_all(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\nGET /c HT").unwrap();
+        assert_eq!(read_response(&mut reader).1, "/a");
+        assert_eq!(read_response(&mut reader).1, "/b");
+        thread::sleep(TICK);
+        stream
+            .write_all(b"TP/1.1\r\nContent-Length: 3\r\nConnection: close\r\n\r\nx")
+            .unwrap();
+        thread::sleep(TICK);
+        stream.write_all(b"yz").unwrap();
+        let (head, body) = read_response(&mut reader);
+        assert!(head.contains("Connection: close"));
+        assert_eq!(body, "/c");
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_errors_and_timeouts() {
+        let addr = start(Config {
+            request_timeout: Duration::from_millis(100),
+            ..Config::default()
+        });
+
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GARBAGE\r\n\r\n").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert!(read_response(&mut reader).0.starts_with("HTTP/1.1 400"));
+        assert!(closed(&mut reader));
+
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\nHost: loc").unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert!(read_response(&mut reader).0.starts_with("HTTP/1.1 408"));
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_closes_idle_connection() {
+        let addr = start(Config {
+            idle_timeout: Duration::from_millis(50),
+            ..Config::default()
+        });
+        let mut reader = BufReader::new(net::TcpStream::connect(addr).unwrap());
+        assert!(closed(&mut reader));
+    }
+
+    #[test]
+    fn test_head_scan_resumes_where_it_stopped() {
+        let limits = Limits::default();
+        let mut scan = HeadScan::default();
+        let mut input = b"GET / HTTP/1.1\r\nHost: lo".to_vec();
+        assert_eq!(scan.advance(&input, &limits), Scan::Incomplete);
+        assert_eq!((scan.pos, scan.line_start, scan.lines), (input.len(), 16, 1));
+
+        input.extend_from_slice(b"cal\r\n\r\nbody");
+        assert_eq!(scan.advance(&input, &limits), Scan::Complete(input.len() - 4));
+
+        let limits = Limits {
+            request_line: 8,
+            header_count: 1,
+            ..Limits::default()
+        };
+        let scan = |raw: &[u8]| HeadScan::default().advance(raw, &limits);
+        assert_eq!(scan(b"GET /123"), Scan::Incomplete);
+        assert_eq!(scan(b"GET /12345"), Scan::TooLarge);
+        assert_eq!(scan(b"GET /1\r\nA: 1\r\n"), Scan::Incomplete);
+        assert_eq!(scan(b"GET /1\r\nA: 1\r\nB: 2\r\n"), Scan::TooLarge);
+    }
+
+    #[test]
+    fn test_stops_processing_while_output_is_backed_up() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut conn = Conn::new(TcpStream::from_std(stream), Instant::now());
+
+        let path = format!("/{}", "a".repeat(4000));
+        for _ in 0..100 {
+            conn.input.extend_from_slice(format!("GET {} HTTP/1.1\r\n\r\n", path).as_bytes());
+        }
+        let handler = |req: &mut Request| crate::build_response(200, "OK", &req.path);
+        conn.process(&Config::default(), &handler);
+        assert!(conn.output.len() >= OUTPUT_HIGH_WATER);
+        assert!(conn.output.len() < OUTPUT_HIGH_WATER + 5000);
+        assert!(conn.served < 100);
+    }
+
+    #[test]
+    fn test_serves_pipelined_requests_after_backing_off() {
+        let addr = start(Config::default());
+        let stream = net::TcpStream::connect(addr).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        // 1 本の接続で受け付ける数 (100) より少なく、書き込み待ちの上限よりは多く
+        let paths: Vec<String> = (0..90).map(|i| format!("/{}/{}", i, "a".repeat(4000))).collect();
+        let requests: String = paths.iter().map(|path| format!("GET {} HTTP/1.1\r\n\r\n", path)).collect();
+        // レスポンスを読まずに送り切る
+        let sender = thread::spawn(move || writer.write_all(requests.as_bytes()).unwrap());
+
+        let mut reader = BufReader::new(stream);
+        for path in &paths {
+            assert_eq!(&read_response(&mut reader).1, path);
+        }
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_rejects_chunked_body() {
+        let addr = start(Config::default());
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\nGET /b HTTP/1.1\r\n\r\n")
+            .unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        assert!(read_response(&mut reader).0.starts_with("HTTP/1.1 501"));
+        assert!(closed(&mut reader));
+    }
+}
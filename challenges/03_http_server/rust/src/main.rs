@@ -2,166 +2,603 @@
 //!
 //! 標準ライブラリのみでシンプルな HTTP サーバーを実装
 
+mod access_log;
+mod conditional;
+mod config_file;
+mod connection;
+mod error_pages;
+#[cfg(feature = "event-loop")]
+mod event_loop;
+mod middleware;
+mod multipart;
+mod negotiate;
+mod range;
+mod request;
+mod request_id;
+mod router;
+mod routes;
+#[cfg(test)]
+mod server_tests;
+mod session;
+mod static_files;
+mod tasks;
+mod templates;
+mod url;
+mod urls;
+
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Write};
+use std::env;
+use std::fmt;
+use std::fs;
 use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use cli_tool::store::TextFileStore;
+use concurrency::pool::ThreadPool;
+use json_parser::JsonValue;
+
+use access_log::{AccessLog, LogFormat};
+use conditional::Conditional;
+use error_pages::ErrorPages;
+use middleware::Pipeline;
+use request::{Request, RequestError};
+use request_id::RequestIds;
+use router::{routes, Router};
+use routes::RouteConfig;
+use session::Sessions;
+use static_files::StaticFiles;
+use tasks::TaskApi;
+use urls::{url_for, TrailingSlash};
+
+const USAGE: &str = "Usage: http_server [--addr ADDR] [--port PORT] [--workers N] [--event-loop] [--config FILE] \
+[--keep-alive-timeout SECS] [--max-requests N] \
+[--read-timeout SECS] [--write-timeout SECS] [--request-timeout SECS] \
+[--max-request-line BYTES] [--max-headers N] [--max-header-size BYTES] [--max-body-size BYTES] \
+[--access-log FILE] [--log-format common|json] [--quiet] [--tasks FILE] [--root DIR] [--autoindex] [--cache-control VALUE] [--routes FILE] \
+[--trailing-slash strict|add|remove]";
+
+/// コマンドラインの設定
+#[derive(Debug)]
+struct Options {
+    /// 待ち受けるアドレス
+    addr: String,
+    /// 待ち受けるポート (0 なら空いているポートを OS が選ぶ)
+    port: u16,
+    /// 接続を処理するスレッドの数 (None なら接続ごとにスレッドを作る)
+    workers: Option<usize>,
+    /// イベントループ版で動かす (`event-loop` フィーチャーが必要)
+    event_loop: bool,
+    connection: connection::Config,
+    /// アクセスログを書かない
+    quiet: bool,
+    /// アクセスログの出力先 (None なら標準出力)
+    access_log: Option<PathBuf>,
+    /// アクセスログの書式
+    log_format: LogFormat,
+    /// `/tasks` で読み書きするタスクファイル (None なら `todo` コマンドと同じ場所)
+    tasks: Option<PathBuf>,
+    /// `/static` で配信するディレクトリ
+    root: PathBuf,
+    /// index.html の無いディレクトリで一覧を返す
+    autoindex: bool,
+    /// 静的ファイルに付ける `Cache-Control`
+    cache_control: String,
+    /// 静的ファイル・リダイレクト・プロキシのルートを書いたファイル (変わると読み直す)
+    routes: Option<PathBuf>,
+    /// 末尾の `/` だけが違うパスをリダイレクトするか
+    trailing_slash: TrailingSlash,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            addr: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: None,
+            event_loop: false,
+            connection: connection::Config::default(),
+            quiet: false,
+            access_log: None,
+            log_format: LogFormat::Common,
+            tasks: None,
+            root: PathBuf::from("public"),
+            autoindex: false,
+            cache_control: static_files::DEFAULT_CACHE_CONTROL.to_string(),
+            routes: None,
+            trailing_slash: TrailingSlash::Strict,
+        }
+    }
+}
 
 fn main() {
-    println!("=== HTTP Server Demo ===\n");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    };
 
-    let addr = "127.0.0.1:8080";
-    println!("Starting server at http://{}", addr);
+    let tasks_file = options
+        .tasks
+        .clone()
+        .unwrap_or_else(|| cli_tool::paths::Env::from_process().resolve_file(None));
+    let pipeline = match app(&options, tasks_file.clone()) {
+        Ok(pipeline) => Arc::new(pipeline),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let config = Arc::new(options.connection);
+
+    let listener = match TcpListener::bind((options.addr.as_str(), options.port)) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: cannot listen on {}:{}: {}", options.addr, options.port, e);
+            std::process::exit(1);
+        }
+    };
+    // --port 0 のときは実際に割り当てられたポートを知らせる
+    let addr = listener.local_addr().expect("Failed to get the bound address");
+
+    println!("=== HTTP Server Demo ===\n");
+    println!("Listening on http://{}", addr);
+    let base = format!("http://localhost:{}", addr.port());
+    let hints = [
+        "curl {base}/",
+        "curl {base}/hello/world",
+        "curl {base}/json",
+        "curl -H 'Content-Type: application/json' -d '{\"a\": [1, 2]}' {base}/json",
+        "curl -d 'hello' {base}/echo",
+        "curl -c jar -d 'alice' {base}/login",
+        "curl -b jar {base}/whoami",
+        "curl -H 'Content-Type: application/json' -d '{\"description\": \"Buy milk\"}' {base}/tasks",
+        "todo sync --remote {base}",
+        "curl -r 0-4 {base}/static/hello.txt",
+        "curl -F note=hi -F file=@Cargo.toml {base}/upload",
+        "curl -i -H 'If-None-Match: <ETag>' {base}/static/hello.txt",
+    ];
     println!("Try:");
-    println!("  curl http://localhost:8080/");
-    println!("  curl http://localhost:8080/hello/world");
-    println!("  curl http://localhost:8080/json");
+    for hint in hints {
+        println!("  {}", hint.replace("{base}", &base));
+    }
+    println!("\nTasks are stored in {}", tasks_file.display());
     println!("\nPress Ctrl+C to stop\n");
 
-    let listener = TcpListener::bind(addr).expect("Failed to bind");
+    #[cfg(feature = "event-loop")]
+    if options.event_loop {
+        if let Err(e) = event_loop::run(listener, &config, |request| pipeline.handle(request)) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    for stream in listener.incoming() {
-        match stream {
-            Ok(stream) => {
-                handle_connection(stream);
+    let serve = move |stream: TcpStream| connection::serve(stream, &config, |request| pipeline.handle(request));
+    match options.workers {
+        // 接続をワークスティーリングのスレッドプールに渡す (同時に処理する接続は workers 個まで)。
+        // keep-alive で居座るワーカーの deque に残った接続は、空いたワーカーが盗んで処理する。
+        // workers 個が処理中なら accept せず、後の接続はカーネルのバックログで待たせる
+        Some(workers) => {
+            let pool = ThreadPool::new(workers);
+            let serve = Arc::new(serve);
+            let in_flight = InFlight::new(workers);
+            loop {
+                let slot = in_flight.acquire();
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let serve = Arc::clone(&serve);
+                        pool.execute(move || {
+                            serve(stream);
+                            drop(slot);
+                        });
+                    }
+                    Err(e) => {
+                        // fd が尽きた (EMFILE) ときなどに accept を空回りさせない
+                        eprintln!("Connection error: {}", e);
+                        thread::sleep(Duration::from_millis(100));
+                    }
+                }
             }
-            Err(e) => {
-                eprintln!("Connection error: {}", e);
+        }
+        None => {
+            let serve = Arc::new(serve);
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        // keep-alive 中の接続が他の接続を待たせないよう、接続ごとにスレッドを分ける
+                        let serve = Arc::clone(&serve);
+                        thread::spawn(move || serve(stream));
+                    }
+                    Err(e) => {
+                        eprintln!("Connection error: {}", e);
+                    }
+                }
             }
         }
     }
 }
 
-fn handle_connection(mut stream: TcpStream) {
-    let buf_reader = BufReader::new(&stream);
-    let request_line = buf_reader.lines().next();
-
-    let request_line = match request_line {
-        Some(Ok(line)) => line,
-        _ => return,
-    };
+/// 処理中の接続の数 (上限に達したら空くまで待たせる)
+struct InFlight {
+    count: Mutex<usize>,
+    freed: Condvar,
+    limit: usize,
+}
 
-    println!("Request: {}", request_line);
+/// 処理中の接続 1 本分 (落とすと空く)
+struct Slot(Arc<InFlight>);
 
-    let response = route_request(&request_line);
+impl InFlight {
+    fn new(limit: usize) -> Arc<Self> {
+        Arc::new(InFlight {
+            count: Mutex::new(0),
+            freed: Condvar::new(),
+            limit,
+        })
+    }
 
-    if let Err(e) = stream.write_all(response.as_bytes()) {
-        eprintln!("Failed to write response: {}", e);
+    /// 空きができるまで待ってから 1 本分を取る
+    fn acquire(self: &Arc<Self>) -> Slot {
+        let mut count = self.count.lock().unwrap();
+        while *count >= self.limit {
+            count = self.freed.wait(count).unwrap();
+        }
+        *count += 1;
+        Slot(Arc::clone(self))
     }
 }
 
-fn route_request(request_line: &str) -> String {
-    let parts: Vec<&str> = request_line.split_whitespace().collect();
-
-    if parts.len() < 2 {
-        return build_response(400, "Bad Request", "Invalid request");
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.freed.notify_one();
     }
+}
 
-    let method = parts[0];
-    let path = parts[1];
-
-    if method != "GET" {
-        return build_response(405, "Method Not Allowed", "Only GET is supported");
+/// 設定どおりにミドルウェアとルートをつなぐ
+fn app(options: &Options, tasks_file: PathBuf) -> Result<Pipeline, String> {
+    let mut pipeline = Pipeline::new(route_request).with(RequestIds);
+    if !options.quiet {
+        let log = match &options.access_log {
+            Some(path) => AccessLog::to_file(path).map_err(|e| format!("cannot open {}: {}", path.display(), e))?,
+            None => AccessLog::stdout(),
+        };
+        pipeline = pipeline.with(log.with_format(options.log_format));
     }
+    let mut pipeline = pipeline
+        .with(ErrorPages::default())
+        .with(Sessions::new(session::DEFAULT_TTL))
+        .with(Conditional)
+        .with(options.trailing_slash);
+    if let Some(path) = &options.routes {
+        pipeline = pipeline.with(RouteConfig::load(path)?.watch(cli_tool::watch::DEFAULT_INTERVAL));
+    }
+    Ok(pipeline
+        .with(TaskApi::new(TextFileStore::new(tasks_file)))
+        .with(
+            StaticFiles::new("/static", options.root.clone())
+                .with_cache_control(&options.cache_control)
+                .with_autoindex(options.autoindex),
+        ))
+}
 
-    match_route(path)
+/// コマンドライン引数をパースする (環境変数と設定ファイルの値を既定値にする)
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    parse_args_with(args, |name| env::var(name).ok())
 }
 
-fn match_route(path: &str) -> String {
-    // ルーティング
-    if path == "/" {
-        return build_response(200, "OK", "Welcome to Rust HTTP Server!");
+/// `var` で環境変数を引いてパースする
+///
+/// 優先順位は コマンドライン > 環境変数 > 設定ファイル (`--config` / `HTTP_SERVER_CONFIG`)。
+fn parse_args_with(args: &[String], var: impl Fn(&str) -> Option<String>) -> Result<Options, String> {
+    let mut options = Options::default();
+
+    let config_path = args
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|i| args.get(i + 1).cloned())
+        .or_else(|| var("HTTP_SERVER_CONFIG"));
+    if let Some(path) = config_path {
+        let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read config {}: {}", path, e))?;
+        let file_args = config_file::to_args(&text).map_err(|e| format!("{}: {}", path, e))?;
+        apply_args(&mut options, &file_args).map_err(|e| format!("{}: {}", path, e))?;
     }
+    apply_args(&mut options, &config_file::env_args(var)).map_err(|e| format!("environment: {}", e))?;
+    apply_args(&mut options, args)?;
 
-    if path == "/json" {
-        return build_json_response(200, r#"{"message": "Hello, JSON!", "status": "ok"}"#);
+    let config = &options.connection;
+    if config.idle_timeout.is_zero() {
+        return Err("Keep-alive timeout must be at least 1 second".to_string());
     }
+    // ソケットのタイムアウトに 0 は指定できない
+    if [config.read_timeout, config.write_timeout, config.request_timeout].iter().any(Duration::is_zero) {
+        return Err("Timeouts must be at least 1 second".to_string());
+    }
+    Ok(options)
+}
 
-    if path.starts_with("/hello/") {
-        let name = &path[7..]; // "/hello/" の後の部分
-        if name.is_empty() {
-            return build_response(400, "Bad Request", "Name is required");
+/// 引数の値を `options` に書き込む
+fn apply_args(options: &mut Options, args: &[String]) -> Result<(), String> {
+    let config = &mut options.connection;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--addr" => {
+                let addr = iter.next().ok_or("--addr requires an address")?;
+                options.addr = addr.clone();
+            }
+            "--port" => {
+                let port = iter.next().ok_or("--port requires a number")?;
+                options.port = port.parse().map_err(|_| format!("Invalid port: {}", port))?;
+            }
+            "--workers" => {
+                let n = iter.next().ok_or("--workers requires a number")?;
+                let n: usize = n.parse().map_err(|_| "Invalid number of workers")?;
+                if n == 0 {
+                    return Err("Workers must be at least 1".to_string());
+                }
+                options.workers = Some(n);
+            }
+            "--event-loop" => {
+                if !cfg!(feature = "event-loop") {
+                    return Err("--event-loop requires building with --features event-loop".to_string());
+                }
+                options.event_loop = true;
+            }
+            "--config" => {
+                // 読み込みは parse_args_with で済ませている
+                iter.next().ok_or("--config requires a path")?;
+            }
+            "--keep-alive-timeout" => {
+                let secs = iter.next().ok_or("--keep-alive-timeout requires seconds")?;
+                let secs = secs.parse().map_err(|_| "Invalid keep-alive timeout")?;
+                config.idle_timeout = Duration::from_secs(secs);
+            }
+            "--max-requests" => {
+                let n = iter.next().ok_or("--max-requests requires a number")?;
+                config.max_requests = n.parse().map_err(|_| "Invalid number of requests")?;
+            }
+            "--read-timeout" => {
+                let secs = iter.next().ok_or("--read-timeout requires seconds")?;
+                config.read_timeout = Duration::from_secs(secs.parse().map_err(|_| "Invalid read timeout")?);
+            }
+            "--write-timeout" => {
+                let secs = iter.next().ok_or("--write-timeout requires seconds")?;
+                config.write_timeout = Duration::from_secs(secs.parse().map_err(|_| "Invalid write timeout")?);
+            }
+            "--request-timeout" => {
+                let secs = iter.next().ok_or("--request-timeout requires seconds")?;
+                config.request_timeout = Duration::from_secs(secs.parse().map_err(|_| "Invalid request timeout")?);
+            }
+            "--max-request-line" => {
+                let n = iter.next().ok_or("--max-request-line requires a size")?;
+                config.limits.request_line = n.parse().map_err(|_| "Invalid request line size")?;
+            }
+            "--max-headers" => {
+                let n = iter.next().ok_or("--max-headers requires a number")?;
+                config.limits.header_count = n.parse().map_err(|_| "Invalid number of headers")?;
+            }
+            "--max-header-size" => {
+                let n = iter.next().ok_or("--max-header-size requires a size")?;
+                config.limits.header_size = n.parse().map_err(|_| "Invalid header size")?;
+            }
+            "--max-body-size" => {
+                let n = iter.next().ok_or("--max-body-size requires a size")?;
+                config.limits.body_size = n.parse().map_err(|_| "Invalid body size")?;
+            }
+            "--access-log" => {
+                let path = iter.next().ok_or("--access-log requires a path")?;
+                options.access_log = Some(PathBuf::from(path));
+            }
+            "--log-format" => {
+                let format = iter.next().ok_or("--log-format requires common or json")?;
+                options.log_format = LogFormat::parse(format)?;
+            }
+            "-q" | "--quiet" => {
+                options.quiet = true;
+            }
+            "--root" => {
+                let path = iter.next().ok_or("--root requires a directory")?;
+                options.root = PathBuf::from(path);
+            }
+            "--autoindex" => {
+                options.autoindex = true;
+            }
+            "--cache-control" => {
+                let value = iter.next().ok_or("--cache-control requires a value")?;
+                options.cache_control = value.clone();
+            }
+            "--trailing-slash" => {
+                let policy = iter.next().ok_or("--trailing-slash requires strict, add or remove")?;
+                options.trailing_slash = TrailingSlash::parse(policy)?;
+            }
+            "--routes" => {
+                let path = iter.next().ok_or("--routes requires a path")?;
+                options.routes = Some(PathBuf::from(path));
+            }
+            "--tasks" => {
+                let path = iter.next().ok_or("--tasks requires a path")?;
+                options.tasks = Some(PathBuf::from(path));
+            }
+            _ => return Err(format!("Unknown option: {}", arg)),
         }
-        let body = format!("Hello, {}!", name);
-        return build_response(200, "OK", &body);
     }
+    Ok(())
+}
 
-    if path == "/headers" {
-        return build_response(200, "OK", "Use /headers endpoint to see request headers");
-    }
+/// アプリのルート (パターンは `routes!` がコンパイル時に確かめて表にする)
+static ROUTER: Router = routes! {
+    GET "/" => index,
+    GET "/hello/:name" => hello,
+    GET "/json" => hello_json,
+    POST "/json" => echo_json,
+    GET "/info" => info,
+    POST "/echo" => echo,
+    POST "/login" => login,
+    POST "/logout" => logout,
+    GET "/whoami" => whoami,
+    GET "/upload" => upload_form,
+    POST "/upload" => upload,
+    GET "/headers" => headers,
+};
+
+fn route_request(request: &Request) -> Response {
+    ROUTER
+        .dispatch(request)
+        .unwrap_or_else(|| build_response(404, "Not Found", &format!("Path '{}' not found", request.path)))
+}
 
-    // 404
-    build_response(404, "Not Found", &format!("Path '{}' not found", path))
+fn hello(_request: &Request, name: &str) -> Response {
+    build_response(200, "OK", &format!("Hello, {}!", name))
 }
 
-fn build_response(status_code: u16, status_text: &str, body: &str) -> String {
-    format!(
-        "HTTP/1.1 {} {}\r\n\
-         Content-Type: text/plain; charset=utf-8\r\n\
-         Content-Length: {}\r\n\
-         Connection: close\r\n\
-         \r\n\
-         {}",
-        status_code,
-        status_text,
-        body.len(),
-        body
-    )
+fn hello_json(_request: &Request) -> Response {
+    Response::json(&JsonValue::Object(HashMap::from([
+        ("message".to_string(), JsonValue::String("Hello, JSON!".to_string())),
+        ("status".to_string(), JsonValue::String("ok".to_string())),
+    ])))
 }
 
-fn build_json_response(status_code: u16, body: &str) -> String {
-    format!(
-        "HTTP/1.1 {} OK\r\n\
-         Content-Type: application/json; charset=utf-8\r\n\
-         Content-Length: {}\r\n\
-         Connection: close\r\n\
-         \r\n\
-         {}",
-        status_code,
-        body.len(),
-        body
-    )
+fn echo(request: &Request) -> Response {
+    match request.body_str() {
+        Ok(body) => build_response(200, "OK", body),
+        Err(_) => build_response(400, "Bad Request", "Body must be UTF-8"),
+    }
 }
 
-/// HTTP リクエストをパースする
-#[derive(Debug)]
-pub struct Request {
-    pub method: String,
-    pub path: String,
-    pub headers: HashMap<String, String>,
+fn upload_form(_request: &Request) -> Response {
+    Response::render(include_str!("../templates/upload.html"), &JsonValue::Object(HashMap::new()))
 }
 
-impl Request {
-    pub fn parse(raw: &str) -> Option<Self> {
-        let mut lines = raw.lines();
+fn headers(_request: &Request) -> Response {
+    build_response(200, "OK", "Use /headers endpoint to see request headers")
+}
 
-        // リクエストライン
-        let request_line = lines.next()?;
-        let parts: Vec<&str> = request_line.split_whitespace().collect();
+/// JSON のボディをパースし、整形し直して返す
+fn echo_json(request: &Request) -> Result<Response, RequestError> {
+    let value = request.json()?;
+    Ok(Response::json(&value))
+}
 
-        if parts.len() < 2 {
-            return None;
-        }
+/// アップロードされたフォームの中身を JSON で返す (ファイルは保存せず、大きさとハッシュだけ)
+fn upload(request: &Request) -> Result<Response, RequestError> {
+    let text = |s: &str| JsonValue::String(s.to_string());
+    let fields = request
+        .form()?
+        .into_iter()
+        .map(|(name, value)| (name, JsonValue::String(value)))
+        .collect();
+    let mut files = Vec::new();
+    for file in request.files()? {
+        files.push(JsonValue::Object(HashMap::from([
+            ("field".to_string(), text(&file.field)),
+            ("filename".to_string(), text(&file.filename)),
+            ("content_type".to_string(), text(&file.content_type)),
+            ("size".to_string(), JsonValue::Number(file.data.len() as f64)),
+            ("hash".to_string(), text(&conditional::etag(file.data))),
+        ])));
+    }
+    Ok(Response::json(&JsonValue::Object(HashMap::from([
+        ("fields".to_string(), JsonValue::Object(fields)),
+        ("files".to_string(), JsonValue::Array(files)),
+    ]))))
+}
 
-        let method = parts[0].to_string();
-        let path = parts[1].to_string();
+/// サーバーの名前とバージョン (`Accept` に合わせて text / HTML / JSON で返す)
+fn info(request: &Request) -> Response {
+    let (name, version) = (env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    Response::negotiate(
+        request,
+        &[
+            ("text/plain", &|| build_response(200, "OK", &format!("{} {}", name, version))),
+            ("text/html", &|| {
+                let html = format!("<!DOCTYPE html>\n<h1>{}</h1>\n<p>version {}</p>\n", name, version);
+                build_response(200, "OK", &html).with_header("Content-Type", "text/html; charset=utf-8")
+            }),
+            ("application/json", &|| {
+                Response::json(&JsonValue::Object(HashMap::from([
+                    ("name".to_string(), JsonValue::String(name.to_string())),
+                    ("version".to_string(), JsonValue::String(version.to_string())),
+                ])))
+            }),
+        ],
+    )
+}
 
-        // ヘッダー
-        let mut headers = HashMap::new();
-        for line in lines {
-            if line.is_empty() {
-                break;
-            }
-            if let Some((key, value)) = line.split_once(": ") {
-                headers.insert(key.to_lowercase(), value.to_string());
-            }
+/// ボディの名前でログインする (セッションのデモ)
+fn login(request: &Request) -> Response {
+    let Some(session) = &request.session else {
+        return build_response(500, "Internal Server Error", "Sessions are not enabled");
+    };
+    match request.body_str().map(str::trim) {
+        Ok("") => build_response(400, "Bad Request", "Name is required"),
+        Ok(name) => {
+            session.insert("user", name);
+            build_response(200, "OK", &format!("Logged in as {}", name))
         }
+        Err(_) => build_response(400, "Bad Request", "Body must be UTF-8"),
+    }
+}
 
-        Some(Request {
-            method,
-            path,
-            headers,
-        })
+fn logout(request: &Request) -> Response {
+    if let Some(session) = &request.session {
+        session.destroy();
     }
+    build_response(200, "OK", "Logged out")
+}
+
+fn whoami(request: &Request) -> Response {
+    match request.session.as_ref().and_then(|s| s.get("user")) {
+        Some(user) => build_response(200, "OK", &format!("Logged in as {}", user)),
+        None => build_response(401, "Unauthorized", "Not logged in"),
+    }
+}
+
+/// トップページ (デモのルート一覧)
+fn index(_request: &Request) -> Response {
+    let routes = [
+        ("GET", url_for("hello", &["world"]), "plain text greeting", true),
+        ("GET", url_for("json", &[]), "JSON response", true),
+        ("GET", url_for("info", &[]), "server info as text, HTML or JSON (by Accept)", true),
+        ("POST", url_for("json", &[]), "echo a JSON body", false),
+        ("POST", url_for("echo", &[]), "echo a text body", false),
+        ("POST", url_for("login", &[]), "log in with the name in the body", false),
+        ("GET", url_for("whoami", &[]), "show the logged-in user", true),
+        ("GET", url_for("tasks", &[]), "TODO REST API", true),
+        ("GET", url_for("upload", &[]), "file upload form", true),
+    ];
+    let text = |s: &str| JsonValue::String(s.to_string());
+    let routes = routes
+        .iter()
+        .map(|(method, path, description, link)| {
+            JsonValue::Object(HashMap::from([
+                ("method".to_string(), text(method)),
+                ("path".to_string(), text(path)),
+                ("description".to_string(), text(description)),
+                ("link".to_string(), JsonValue::Bool(*link)),
+            ]))
+        })
+        .collect();
+    let context = JsonValue::Object(HashMap::from([
+        ("title".to_string(), text("Rust HTTP Server")),
+        ("routes".to_string(), JsonValue::Array(routes)),
+    ]));
+    Response::render(include_str!("../templates/index.html"), &context)
+}
+
+/// テキストのレスポンス (`Connection` ヘッダーは接続の処理側で付ける)
+fn build_response(status_code: u16, status_text: &str, body: &str) -> Response {
+    Response::new(status_code, status_text)
+        .with_header("Content-Type", "text/plain; charset=utf-8")
+        .with_body(body)
 }
 
 /// HTTP レスポンスを構築する
@@ -170,7 +607,8 @@ pub struct Response {
     pub status_code: u16,
     pub status_text: String,
     pub headers: HashMap<String, String>,
-    pub body: String,
+    /// ファイルを返すこともあるのでバイト列で持つ
+    pub body: Vec<u8>,
 }
 
 impl Response {
@@ -179,36 +617,117 @@ impl Response {
             status_code,
             status_text: status_text.to_string(),
             headers: HashMap::new(),
-            body: String::new(),
+            body: Vec::new(),
         }
     }
 
-    pub fn with_body(mut self, body: &str) -> Self {
-        self.body = body.to_string();
+    pub fn with_body(self, body: &str) -> Self {
+        self.with_bytes(body.as_bytes().to_vec())
+    }
+
+    pub fn with_bytes(mut self, body: Vec<u8>) -> Self {
         self.headers
             .insert("Content-Length".to_string(), body.len().to_string());
+        self.body = body;
         self
     }
 
+    /// 送信するバイト列 (ステータス行、ヘッダー、ボディ)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.head().into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// ステータス行とヘッダー (空行まで)
+    fn head(&self) -> String {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status_code, self.status_text);
+        for (key, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+        head.push_str("\r\n");
+        head
+    }
+
     pub fn with_header(mut self, key: &str, value: &str) -> Self {
         self.headers.insert(key.to_string(), value.to_string());
         self
     }
 
-    pub fn to_string(&self) -> String {
-        let mut response = format!(
-            "HTTP/1.1 {} {}\r\n",
-            self.status_code, self.status_text
-        );
+    pub fn with_status(mut self, status_code: u16, status_text: &str) -> Self {
+        self.status_code = status_code;
+        self.status_text = status_text.to_string();
+        self
+    }
 
-        for (key, value) in &self.headers {
-            response.push_str(&format!("{}: {}\r\n", key, value));
+    /// テンプレートに値を埋め込んだ HTML のレスポンス (テンプレートが誤っていれば 500)
+    pub fn render(template: &str, context: &JsonValue) -> Self {
+        match templates::render(template, context) {
+            Ok(html) => Response::new(200, "OK")
+                .with_header("Content-Type", "text/html; charset=utf-8")
+                .with_body(&html),
+            Err(e) => {
+                eprintln!("{}", e);
+                build_response(500, "Internal Server Error", "Failed to render the page")
+            }
         }
+    }
 
-        response.push_str("\r\n");
-        response.push_str(&self.body);
+    /// `location` へのリダイレクト (301 / 302 / 303 / 307 / 308)
+    ///
+    /// 307 と 308 はメソッドとボディを変えずに送り直させる。それ以外のコードはパニックする。
+    pub fn redirect(location: &str, status_code: u16) -> Self {
+        let status_text = match status_code {
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            _ => panic!("{} is not a redirect status", status_code),
+        };
+        build_response(status_code, status_text, &format!("Redirecting to {}", location))
+            .with_header("Location", location)
+    }
 
-        response
+    /// `Accept` に合わせて、登録した表現 (Content-Type と作り方の組) から 1 つを選んで作る
+    ///
+    /// `Accept` が無ければ先頭の表現。どれも受け付けられなければ 406 を返す。
+    /// キャッシュが表現を取り違えないよう `Vary: Accept` を付ける。
+    pub fn negotiate(request: &Request, renderings: &[(&str, &dyn Fn() -> Response)]) -> Self {
+        let offers: Vec<&str> = renderings.iter().map(|(content_type, _)| *content_type).collect();
+        let chosen = negotiate::choose(request.header("accept"), &offers)
+            .and_then(|chosen| renderings.iter().find(|(content_type, _)| *content_type == chosen));
+        let response = match chosen {
+            Some((_, render)) => render(),
+            None => build_response(
+                406,
+                "Not Acceptable",
+                &format!("Available representations: {}", offers.join(", ")),
+            ),
+        };
+        response.with_header("Vary", "Accept")
+    }
+
+    /// JSON のレスポンス (200 OK。ほかのステータスは `with_status` で変える)
+    pub fn json(value: &JsonValue) -> Self {
+        Response::new(200, "OK")
+            .with_header("Content-Type", "application/json; charset=utf-8")
+            .with_body(&value.to_string())
+    }
+}
+
+impl From<RequestError> for Response {
+    /// リクエストの誤りをエラーレスポンスにする
+    fn from(e: RequestError) -> Self {
+        let (code, text) = e.status().unwrap_or((500, "Internal Server Error"));
+        build_response(code, text, &e.to_string())
+    }
+}
+
+impl fmt::Display for Response {
+    /// 表示用 (UTF-8 でないボディは置き換え文字になる。送信には `to_bytes` を使う)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.head(), String::from_utf8_lossy(&self.body))
     }
 }
 
@@ -216,44 +735,245 @@ impl Response {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_parse_request() {
-        let raw = "GET /hello HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\n\r\n";
-        let req = Request::parse(raw).unwrap();
+    /// GET でルーティングしたレスポンス (表示用の文字列)
+    fn get(path: &str) -> String {
+        route_request(&Request::parse(&format!("GET {} HTTP/1.1\r\n\r\n", path)).unwrap()).to_string()
+    }
 
-        assert_eq!(req.method, "GET");
-        assert_eq!(req.path, "/hello");
-        assert_eq!(req.headers.get("host"), Some(&"localhost".to_string()));
+    #[test]
+    fn test_in_flight_waits_for_a_free_slot() {
+        let in_flight = InFlight::new(2);
+        let first = in_flight.acquire();
+        let _second = in_flight.acquire();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let waiting = Arc::clone(&in_flight);
+        let handle = thread::spawn(move || {
+            let _third = waiting.acquire();
+            tx.send(()).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_millis(50)).is_err());
+        drop(first);
+        rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        handle.join().unwrap();
+        assert_eq!(*in_flight.count.lock().unwrap(), 1);
     }
 
     #[test]
     fn test_route_root() {
-        let response = match_route("/");
+        let response = get("/").to_string();
         assert!(response.contains("200 OK"));
         assert!(response.contains("Welcome"));
     }
 
+    #[test]
+    fn test_render() {
+        let context = json_parser::parse(r#"{"name": "<world>"}"#).unwrap();
+        let response = Response::render("<p>Hello, {{ name }}!</p>", &context);
+        assert_eq!(response.headers["Content-Type"], "text/html; charset=utf-8");
+        assert_eq!(response.body, b"<p>Hello, &lt;world&gt;!</p>");
+
+        assert_eq!(Response::render("{% if %}", &context).status_code, 500);
+        assert!(get("/").contains(r#"<a href="/json">GET /json</a>"#));
+    }
+
     #[test]
     fn test_route_hello() {
-        let response = match_route("/hello/world");
+        let response = get("/hello/world").to_string();
         assert!(response.contains("200 OK"));
         assert!(response.contains("Hello, world!"));
+
+        // パスはデコード済みで届く
+        let request = Request::parse("GET /hello/%E4%B8%96%E7%95%8C HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(route_request(&request).body, "Hello, 世界!".as_bytes());
     }
 
     #[test]
     fn test_route_json() {
-        let response = match_route("/json");
+        let response = get("/json").to_string();
         assert!(response.contains("200"));
         assert!(response.contains("application/json"));
+        assert!(response.ends_with(r#"{"message":"Hello, JSON!","status":"ok"}"#));
+    }
+
+    #[test]
+    fn test_route_echo_json() {
+        let raw = "POST /json HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 19\r\n\r\n{ \"b\": 1, \"a\": [] }";
+        let response = route_request(&Request::parse(raw).unwrap());
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.headers["Content-Type"], "application/json; charset=utf-8");
+        assert_eq!(response.body, br#"{"a":[],"b":1}"#);
+
+        let raw = "POST /json HTTP/1.1\r\nContent-Length: 1\r\n\r\n{";
+        let response = route_request(&Request::parse(raw).unwrap());
+        assert_eq!(response.status_code, 400);
+        assert!(response.body.starts_with(b"bad request: invalid JSON"));
     }
 
     #[test]
     fn test_route_not_found() {
-        let response = match_route("/unknown");
+        let response = get("/unknown").to_string();
         assert!(response.contains("404"));
         assert!(response.contains("Not Found"));
     }
 
+    #[test]
+    fn test_route_echo() {
+        let request = Request::parse("POST /echo HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi").unwrap();
+        let response = route_request(&request).to_string();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("\r\n\r\nhi"));
+
+        let request = Request::parse("DELETE / HTTP/1.1\r\n\r\n").unwrap();
+        let response = route_request(&request);
+        assert_eq!(response.status_code, 405);
+        assert_eq!(response.headers["Allow"], "GET");
+    }
+
+    #[test]
+    fn test_route_login_logout() {
+        let pipeline = Pipeline::new(route_request).with(Sessions::new(session::DEFAULT_TTL));
+        let send = |raw: &str| pipeline.handle(&mut Request::parse(raw).unwrap());
+
+        assert_eq!(send("GET /whoami HTTP/1.1\r\n\r\n").status_code, 401);
+        assert_eq!(send("POST /login HTTP/1.1\r\n\r\n").status_code, 400);
+
+        let response = send("POST /login HTTP/1.1\r\nContent-Length: 5\r\n\r\nalice");
+        assert_eq!(response.body, b"Logged in as alice");
+        let cookie = response.headers["Set-Cookie"].split(';').next().unwrap().to_string();
+
+        let response = send(&format!("GET /whoami HTTP/1.1\r\nCookie: {}\r\n\r\n", cookie));
+        assert_eq!(response.body, b"Logged in as alice");
+
+        send(&format!("POST /logout HTTP/1.1\r\nCookie: {}\r\n\r\n", cookie));
+        let response = send(&format!("GET /whoami HTTP/1.1\r\nCookie: {}\r\n\r\n", cookie));
+        assert_eq!(response.status_code, 401);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let options = parse_args(&args(&[])).unwrap();
+        assert_eq!(options.connection.max_requests, connection::DEFAULT_MAX_REQUESTS);
+        assert!(!options.quiet);
+        assert_eq!(options.access_log, None);
+        assert_eq!(options.root, PathBuf::from("public"));
+
+        let options = parse_args(&args(&["--keep-alive-timeout", "30", "--max-requests", "1"])).unwrap();
+        assert_eq!(options.connection.idle_timeout, Duration::from_secs(30));
+        assert_eq!(options.connection.max_requests, 1);
+
+        let options = parse_args(&args(&["--max-headers", "10", "--max-body-size", "1024"])).unwrap();
+        assert_eq!(options.connection.limits.header_count, 10);
+        assert_eq!(options.connection.limits.body_size, 1024);
+        assert!(parse_args(&args(&["--max-header-size", "big"])).is_err());
+
+        let options = parse_args(&args(&["--read-timeout", "3", "--request-timeout", "20"])).unwrap();
+        assert_eq!(options.connection.read_timeout, Duration::from_secs(3));
+        assert_eq!(options.connection.request_timeout, Duration::from_secs(20));
+        assert!(parse_args(&args(&["--write-timeout", "0"])).is_err());
+
+        let options = parse_args(&args(&["--quiet", "--access-log", "access.log"])).unwrap();
+        assert!(options.quiet);
+        assert_eq!(options.access_log, Some(PathBuf::from("access.log")));
+        assert_eq!(options.log_format, LogFormat::Common);
+        let options = parse_args(&args(&["--log-format", "json"])).unwrap();
+        assert_eq!(options.log_format, LogFormat::Json);
+        assert!(parse_args(&args(&["--log-format", "xml"])).is_err());
+
+        let options = parse_args(&args(&["--cache-control", "public, max-age=3600"])).unwrap();
+        assert_eq!(options.cache_control, "public, max-age=3600");
+        assert!(!options.autoindex);
+        assert!(parse_args(&args(&["--autoindex"])).unwrap().autoindex);
+        assert_eq!(options.routes, None);
+        let options = parse_args(&args(&["--routes", "routes.conf"])).unwrap();
+        assert_eq!(options.routes, Some(PathBuf::from("routes.conf")));
+        assert!(parse_args(&args(&["--routes"])).is_err());
+        assert_eq!(options.trailing_slash, TrailingSlash::Strict);
+        let options = parse_args(&args(&["--trailing-slash", "remove"])).unwrap();
+        assert_eq!(options.trailing_slash, TrailingSlash::Remove);
+        assert!(parse_args(&args(&["--trailing-slash", "sometimes"])).is_err());
+
+        assert!(parse_args(&args(&["--keep-alive-timeout", "0"])).is_err());
+        assert!(parse_args(&args(&["--max-requests"])).is_err());
+        assert!(parse_args(&args(&["--port"])).is_err());
+    }
+
+    #[test]
+    fn test_bind_options() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let no_env = |_: &str| None;
+
+        let options = parse_args_with(&args(&[]), no_env).unwrap();
+        assert_eq!((options.addr.as_str(), options.port, options.workers), ("127.0.0.1", 8080, None));
+
+        let options = parse_args_with(&args(&["--addr", "0.0.0.0", "--port", "0", "--workers", "4"]), no_env).unwrap();
+        assert_eq!((options.addr.as_str(), options.port, options.workers), ("0.0.0.0", 0, Some(4)));
+
+        assert!(parse_args_with(&args(&["--port", "65536"]), no_env).is_err());
+        assert!(parse_args_with(&args(&["--workers", "0"]), no_env).is_err());
+        assert_eq!(parse_args_with(&args(&["--event-loop"]), no_env).is_ok(), cfg!(feature = "event-loop"));
+    }
+
+    #[test]
+    fn test_config_file_and_env() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let path = env::temp_dir().join(format!("http-server-{}.conf", std::process::id()));
+        fs::write(&path, "port = 9000\nworkers = 2\nroot = \"/srv/www\"\nquiet = on\n").unwrap();
+        let path = path.to_str().unwrap().to_string();
+
+        let options = parse_args_with(&args(&["--config", &path]), |_| None).unwrap();
+        assert_eq!((options.port, options.workers), (9000, Some(2)));
+        assert_eq!(options.root, PathBuf::from("/srv/www"));
+        assert!(options.quiet);
+
+        // コマンドライン > 環境変数 > 設定ファイル
+        let env = |name: &str| match name {
+            "HTTP_SERVER_CONFIG" => Some(path.clone()),
+            "HTTP_SERVER_PORT" => Some("9001".to_string()),
+            "HTTP_SERVER_WORKERS" => Some("3".to_string()),
+            _ => None,
+        };
+        let options = parse_args_with(&args(&["--workers", "5"]), env).unwrap();
+        assert_eq!((options.port, options.workers), (9001, Some(5)));
+        assert_eq!(options.root, PathBuf::from("/srv/www"));
+
+        fs::write(&path, "port = 9000\nunknown = 1\n").unwrap();
+        let err = parse_args_with(&args(&["--config", &path]), |_| None).unwrap_err();
+        assert!(err.contains("Unknown option: --unknown"));
+        assert!(parse_args_with(&args(&[]), |name: &str| (name == "HTTP_SERVER_PORT").then(|| "x".to_string())).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_route_info_negotiates() {
+        let get = |accept: &str| {
+            let raw = format!("GET /info HTTP/1.1\r\nAccept: {}\r\n\r\n", accept);
+            route_request(&Request::parse(&raw).unwrap())
+        };
+
+        let response = get("text/plain");
+        assert_eq!(response.body, format!("http_server {}", env!("CARGO_PKG_VERSION")).as_bytes());
+        assert_eq!(response.headers["Vary"], "Accept");
+        assert_eq!(get("text/html;q=0.9, */*;q=0.1").headers["Content-Type"], "text/html; charset=utf-8");
+        assert_eq!(get("application/*").headers["Content-Type"], "application/json; charset=utf-8");
+
+        let response = get("image/png");
+        assert_eq!(response.status_code, 406);
+        assert!(String::from_utf8(response.body).unwrap().contains("text/plain, text/html, application/json"));
+    }
+
+    #[test]
+    fn test_redirect() {
+        let response = Response::redirect("/tasks/1", 303);
+        assert_eq!((response.status_code, response.status_text.as_str()), (303, "See Other"));
+        assert_eq!(response.headers["Location"], "/tasks/1");
+        assert_eq!(Response::redirect("/", 308).status_text, "Permanent Redirect");
+        assert!(std::panic::catch_unwind(|| Response::redirect("/", 200)).is_err());
+    }
+
     #[test]
     fn test_response_builder() {
         let response = Response::new(200, "OK")
@@ -0,0 +1,109 @@
+//! ミドルウェア
+//!
+//! ルーティングの前後に処理を挟む仕組み。登録した順に外側から包むので、
+//! 先に登録したミドルウェアほど早くリクエストを受け取り、遅くレスポンスを受け取る。
+
+use crate::request::Request;
+use crate::Response;
+
+/// 次の段 (内側のミドルウェア、最後はハンドラー) を呼ぶ関数
+pub type Next<'a> = &'a dyn Fn(&mut Request) -> Response;
+
+/// リクエストとレスポンスの間に挟む処理
+pub trait Middleware: Send + Sync {
+    /// `next` を呼んで内側に処理を渡す (呼ばなければそこで打ち切る)
+    fn handle(&self, request: &mut Request, next: Next) -> Response;
+}
+
+/// ミドルウェアとハンドラーをつないだもの
+pub struct Pipeline {
+    layers: Vec<Box<dyn Middleware>>,
+    handler: Box<dyn Fn(&Request) -> Response + Send + Sync>,
+}
+
+impl Pipeline {
+    pub fn new(handler: impl Fn(&Request) -> Response + Send + Sync + 'static) -> Self {
+        Pipeline {
+            layers: Vec::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// ミドルウェアを内側に追加する
+    pub fn with(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.layers.push(Box::new(middleware));
+        self
+    }
+
+    /// リクエストを外側のミドルウェアから順に通す
+    pub fn handle(&self, request: &mut Request) -> Response {
+        self.run(0, request)
+    }
+
+    fn run(&self, index: usize, request: &mut Request) -> Response {
+        match self.layers.get(index) {
+            Some(layer) => layer.handle(request, &|request| self.run(index + 1, request)),
+            None => (self.handler)(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 呼ばれた順に名前を記録する
+    struct Trace {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Middleware for Trace {
+        fn handle(&self, request: &mut Request, next: Next) -> Response {
+            self.calls.lock().unwrap().push(format!("{} in", self.name));
+            let response = next(request);
+            self.calls.lock().unwrap().push(format!("{} out", self.name));
+            response
+        }
+    }
+
+    /// 内側に渡さずに 403 を返す
+    struct Deny;
+
+    impl Middleware for Deny {
+        fn handle(&self, _request: &mut Request, _next: Next) -> Response {
+            crate::build_response(403, "Forbidden", "denied")
+        }
+    }
+
+    #[test]
+    fn test_layers_run_in_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let trace = |name| Trace {
+            name,
+            calls: Arc::clone(&calls),
+        };
+        let handler_calls = Arc::clone(&calls);
+        let pipeline = Pipeline::new(move |_| {
+            handler_calls.lock().unwrap().push("handler".to_string());
+            crate::build_response(200, "OK", "")
+        })
+        .with(trace("outer"))
+        .with(trace("inner"));
+
+        let mut request = Request::parse("GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(pipeline.handle(&mut request).status_code, 200);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["outer in", "inner in", "handler", "inner out", "outer out"]
+        );
+    }
+
+    #[test]
+    fn test_middleware_can_short_circuit() {
+        let pipeline = Pipeline::new(|_| panic!("handler must not run")).with(Deny);
+        let mut request = Request::parse("GET / HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(pipeline.handle(&mut request).status_code, 403);
+    }
+}
@@ -0,0 +1,244 @@
+//! `multipart/form-data` のパース (RFC 7578)
+//!
+//! ボディを `--boundary` で区切り、各パートのヘッダー (`Content-Disposition` の
+//! `name` / `filename` と `Content-Type`) を読む。`filename` の付いたパートは
+//! アップロードされたファイルとして扱う。
+//!
+//! ボディは読み込みの時点で (`Content-Length` の上限まで) メモリにあるので、
+//! ファイルのデータもコピーせずにボディを借りる。一時ファイルに書き出しても
+//! メモリは減らず、コピーが増えるだけなのでしない。
+
+use std::collections::HashMap;
+
+/// ボディの 1 パート (データはボディを借りる)
+#[derive(Debug)]
+pub struct Part<'a> {
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: &'a [u8],
+}
+
+/// アップロードされたファイル (データはボディを借りる)
+#[derive(Debug)]
+pub struct UploadedFile<'a> {
+    /// フォームのフィールド名
+    pub field: String,
+    /// クライアントが送ってきたファイル名
+    pub filename: String,
+    pub content_type: String,
+    pub data: &'a [u8],
+}
+
+/// `Content-Type` の `boundary` パラメーター
+pub fn boundary(content_type: &str) -> Option<&str> {
+    let mut params = content_type.split(';');
+    let media = params.next()?.trim();
+    if !media.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params
+        .filter_map(|p| p.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("boundary"))
+        .map(|(_, value)| value.trim().trim_matches('"'))
+        .filter(|b| !b.is_empty() && b.len() <= 70)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// `;` で区切る (引用符の中の `;` では区切らない)
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+/// 引用符で囲まれていれば外し、`\"` などのエスケープを戻す
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => {
+            let mut unquoted = String::new();
+            let mut chars = inner.chars();
+            while let Some(c) = chars.next() {
+                unquoted.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+            }
+            unquoted
+        }
+        None => value.to_string(),
+    }
+}
+
+/// `form-data; name="a"; filename="b.txt"` のパラメーター
+fn disposition_params(value: &str) -> Result<HashMap<String, String>, String> {
+    let params = split_params(value);
+    if !params[0].trim().eq_ignore_ascii_case("form-data") {
+        return Err(format!("unexpected Content-Disposition: {}", value));
+    }
+    Ok(params[1..]
+        .iter()
+        .filter_map(|p| p.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), unquote(value.trim())))
+        .collect())
+}
+
+/// 1 パートのヘッダーとデータを読む
+fn parse_part(raw: &[u8]) -> Result<Part<'_>, String> {
+    let end = find(raw, b"\r\n\r\n").ok_or("part headers are not terminated")?;
+    let head = std::str::from_utf8(&raw[..end]).map_err(|_| "part headers are not valid UTF-8")?;
+
+    let mut params = None;
+    let mut content_type = None;
+    for line in head.split("\r\n").filter(|l| !l.is_empty()) {
+        let (key, value) = line.split_once(':').ok_or_else(|| format!("malformed part header: {}", line))?;
+        match key.trim().to_lowercase().as_str() {
+            "content-disposition" => params = Some(disposition_params(value.trim())?),
+            "content-type" => content_type = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    let mut params = params.ok_or("part has no Content-Disposition")?;
+    Ok(Part {
+        name: params.remove("name").ok_or("part has no name")?,
+        filename: params.remove("filename"),
+        content_type,
+        data: &raw[end + 4..],
+    })
+}
+
+/// ボディをパートに分ける
+pub fn parse<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>, String> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    // 2 つ目以降の区切りは直前の改行を含む
+    let separator = [b"\r\n".as_slice(), &delimiter].concat();
+
+    let start = find(body, &delimiter).ok_or("boundary not found")?;
+    let mut rest = &body[start + delimiter.len()..];
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or("missing line break after boundary")?;
+        let end = find(rest, &separator).ok_or("closing boundary not found")?;
+        parts.push(parse_part(&rest[..end])?);
+        rest = &rest[end + separator.len()..];
+    }
+}
+
+/// テキストのフィールド (`filename` の無いパート)
+pub fn fields(parts: &[Part]) -> Result<HashMap<String, String>, String> {
+    parts
+        .iter()
+        .filter(|part| part.filename.is_none())
+        .map(|part| match std::str::from_utf8(part.data) {
+            Ok(value) => Ok((part.name.clone(), value.to_string())),
+            Err(_) => Err(format!("field '{}' is not valid UTF-8", part.name)),
+        })
+        .collect()
+}
+
+/// ファイルのパート (`filename` の付いたパート) を取り出す
+pub fn files<'a>(parts: &[Part<'a>]) -> Vec<UploadedFile<'a>> {
+    parts
+        .iter()
+        .filter_map(|part| {
+            Some(UploadedFile {
+                field: part.name.clone(),
+                filename: part.filename.clone()?,
+                content_type: part.content_type.clone().unwrap_or("application/octet-stream".to_string()),
+                data: part.data,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\
+\r\n\
+Hello\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+line 1\r\nline 2\r\n\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"empty\"\r\n\
+\r\n\
+\r\n\
+--XyZ--\r\n";
+
+    #[test]
+    fn test_boundary() {
+        assert_eq!(boundary("multipart/form-data; boundary=XyZ"), Some("XyZ"));
+        assert_eq!(boundary("Multipart/Form-Data; charset=utf-8; boundary=\"a b\""), Some("a b"));
+        assert_eq!(boundary("multipart/form-data"), None);
+        assert_eq!(boundary("text/plain; boundary=XyZ"), None);
+    }
+
+    #[test]
+    fn test_parse_parts() {
+        let parts = parse(BODY, "XyZ").unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].name, "title");
+        assert_eq!(parts[0].data, b"Hello");
+        assert_eq!(parts[1].filename.as_deref(), Some("a.txt"));
+        assert_eq!(parts[1].content_type.as_deref(), Some("text/plain"));
+        // データ中の改行はそのまま (区切りの直前の CRLF だけを除く)
+        assert_eq!(parts[1].data, b"line 1\r\nline 2\r\n");
+        assert_eq!(parts[2].data, b"");
+
+        let fields = fields(&parts).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["title"], "Hello");
+    }
+
+    #[test]
+    fn test_files_borrow_the_body() {
+        let parts = parse(BODY, "XyZ").unwrap();
+        let files = files(&parts);
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].field.as_str(), files[0].filename.as_str()), ("file", "a.txt"));
+        assert_eq!(files[0].content_type, "text/plain");
+        assert_eq!(files[0].data, b"line 1\r\nline 2\r\n");
+        assert!(BODY.as_ptr_range().contains(&files[0].data.as_ptr()));
+    }
+
+    #[test]
+    fn test_quoted_disposition_params() {
+        let params = disposition_params(r#"form-data; name="f"; filename="a;b \"c\".txt""#).unwrap();
+        assert_eq!(params["name"], "f");
+        assert_eq!(params["filename"], r#"a;b "c".txt"#);
+        let params = disposition_params("form-data; name=plain").unwrap();
+        assert_eq!(params["name"], "plain");
+    }
+
+    #[test]
+    fn test_malformed_bodies() {
+        assert!(parse(b"no boundary here", "XyZ").is_err());
+        assert!(parse(b"--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nunterminated", "XyZ").is_err());
+        assert!(parse(b"--XyZ\r\nContent-Type: text/plain\r\n\r\nx\r\n--XyZ--", "XyZ").is_err());
+        assert!(parse(b"--XyZ\r\nContent-Disposition: attachment\r\n\r\nx\r\n--XyZ--", "XyZ").is_err());
+        // パートが無くても閉じていればよい
+        assert_eq!(parse(b"--XyZ--\r\n", "XyZ").unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,110 @@
+//! `Accept` ヘッダーによるコンテントネゴシエーション
+//!
+//! `text/html;q=0.9, application/*;q=0.5, */*;q=0.1` のような値をパースし、
+//! サーバーが返せる形式の中から最も好まれるものを選ぶ。各形式の重みは、それに
+//! 当てはまる範囲のうち最も具体的なもの (`type/subtype` > `type/*` > `*/*`) の q 値。
+//! 同じ重みなら、サーバーが先に挙げた形式を選ぶ。
+
+/// `Accept` の 1 項目
+#[derive(Debug, PartialEq)]
+struct MediaRange {
+    kind: String,
+    subtype: String,
+    q: f64,
+}
+
+impl MediaRange {
+    /// `offer` (`text/html` など) に当てはまれば、その具体性 (大きいほど具体的)
+    fn specificity(&self, offer: &str) -> Option<u8> {
+        let (kind, subtype) = offer.split_once('/')?;
+        match (self.kind.as_str(), self.subtype.as_str()) {
+            ("*", "*") => Some(0),
+            (k, "*") if k.eq_ignore_ascii_case(kind) => Some(1),
+            (k, s) if k.eq_ignore_ascii_case(kind) && s.eq_ignore_ascii_case(subtype) => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// `Accept` の値をパースする (書式の誤った項目は読み飛ばす)
+fn parse(accept: &str) -> Vec<MediaRange> {
+    accept
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let (kind, subtype) = params.next()?.trim().split_once('/')?;
+            if kind.is_empty() || subtype.is_empty() || (kind == "*" && subtype != "*") {
+                return None;
+            }
+            let mut q = 1.0;
+            for param in params {
+                if let Some((key, value)) = param.split_once('=') {
+                    if key.trim().eq_ignore_ascii_case("q") {
+                        q = value.trim().parse().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                    }
+                }
+            }
+            Some(MediaRange {
+                kind: kind.to_string(),
+                subtype: subtype.to_string(),
+                q,
+            })
+        })
+        .collect()
+}
+
+/// `offer` の重み (当てはまる範囲が無ければ 0)
+fn quality(ranges: &[MediaRange], offer: &str) -> f64 {
+    ranges
+        .iter()
+        .filter_map(|range| range.specificity(offer).map(|s| (s, range.q)))
+        .max_by_key(|(specificity, _)| *specificity)
+        .map_or(0.0, |(_, q)| q)
+}
+
+/// `offers` から最も好まれる形式を選ぶ (`Accept` が無ければ先頭、どれも受け付けられなければ None)
+pub fn choose<'a>(accept: Option<&str>, offers: &[&'a str]) -> Option<&'a str> {
+    let Some(accept) = accept.filter(|a| !a.trim().is_empty()) else {
+        return offers.first().copied();
+    };
+    let ranges = parse(accept);
+    let mut best: Option<(&str, f64)> = None;
+    for offer in offers {
+        let q = quality(&ranges, offer);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((offer, q));
+        }
+    }
+    best.map(|(offer, _)| offer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFERS: [&str; 3] = ["text/html", "application/json", "text/plain"];
+
+    #[test]
+    fn test_parse() {
+        let ranges = parse("text/html, application/*;q=0.5 , */*;Q=0.1, bogus, */json, text/x;q=2");
+        let parsed: Vec<_> = ranges.iter().map(|r| (r.kind.as_str(), r.subtype.as_str(), r.q)).collect();
+        assert_eq!(parsed, [("text", "html", 1.0), ("application", "*", 0.5), ("*", "*", 0.1)]);
+    }
+
+    #[test]
+    fn test_choose() {
+        assert_eq!(choose(None, &OFFERS), Some("text/html"));
+        assert_eq!(choose(Some("application/json"), &OFFERS), Some("application/json"));
+        assert_eq!(choose(Some("*/*"), &OFFERS), Some("text/html"));
+        // 同じ重みならサーバーの順
+        assert_eq!(choose(Some("text/*"), &OFFERS), Some("text/html"));
+        assert_eq!(choose(Some("text/plain;q=0.8, application/json;q=0.9"), &OFFERS), Some("application/json"));
+        // 具体的な範囲の q が優先される
+        assert_eq!(choose(Some("text/*, text/html;q=0"), &OFFERS), Some("text/plain"));
+        assert_eq!(choose(Some("*/*;q=0.1, TEXT/PLAIN"), &OFFERS), Some("text/plain"));
+
+        assert_eq!(choose(Some("image/png"), &OFFERS), None);
+        assert_eq!(choose(Some("*/*;q=0"), &OFFERS), None);
+        assert_eq!(choose(Some("text/html"), &[]), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! `Range: bytes=...` の解釈 (RFC 9110 14.2)
+//!
+//! 書式が正しくないヘッダーは無視して全体を返す。書式は正しくても、どの範囲も
+//! ファイルに収まらなければ 416 にする。
+
+use std::ops::Range;
+
+/// 1 つのヘッダーで受け付ける範囲の数 (多すぎる指定は無視して全体を返す)
+pub const MAX_RANGES: usize = 16;
+
+/// `Range` ヘッダーを解釈した結果
+#[derive(Debug, PartialEq)]
+pub enum Ranges {
+    /// 全体を返す (ヘッダーが無い、または無視する)
+    Full,
+    /// 指定された範囲を返す (末尾は含まない)
+    Partial(Vec<Range<u64>>),
+    /// どの範囲もファイルに収まらない
+    Unsatisfiable,
+}
+
+/// `first-last` / `first-` / `-suffix` を 1 つ解釈する (書式が誤っていれば Err)
+fn parse_spec(spec: &str, len: u64) -> Result<Option<Range<u64>>, ()> {
+    let (first, last) = spec.trim().split_once('-').ok_or(())?;
+    let number = |s: &str| s.parse::<u64>().map_err(|_| ());
+
+    match (first, last) {
+        ("", "") => Err(()),
+        // 末尾から suffix バイト
+        ("", suffix) => {
+            let suffix = number(suffix)?;
+            Ok((suffix > 0 && len > 0).then(|| len.saturating_sub(suffix)..len))
+        }
+        (first, last) => {
+            let first = number(first)?;
+            let last = if last.is_empty() { u64::MAX } else { number(last)? };
+            if last < first {
+                return Err(());
+            }
+            Ok((first < len).then(|| first..last.min(len - 1) + 1))
+        }
+    }
+}
+
+/// `len` バイトのファイルに対する `Range` ヘッダーを解釈する
+pub fn parse(header: Option<&str>, len: u64) -> Ranges {
+    let Some(specs) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Ranges::Full;
+    };
+
+    let specs: Vec<&str> = specs.split(',').collect();
+    if specs.len() > MAX_RANGES {
+        return Ranges::Full;
+    }
+
+    let mut ranges = Vec::new();
+    for spec in specs {
+        match parse_spec(spec, len) {
+            Ok(Some(range)) => ranges.push(range),
+            Ok(None) => {}
+            Err(()) => return Ranges::Full,
+        }
+    }
+
+    if ranges.is_empty() {
+        Ranges::Unsatisfiable
+    } else {
+        Ranges::Partial(ranges)
+    }
+}
+
+/// `Content-Range` の値 (`bytes 0-99/1000`)
+pub fn content_range(range: &Range<u64>, len: u64) -> String {
+    format!("bytes {}-{}/{}", range.start, range.end - 1, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn single(start: u64, end: u64) -> Ranges {
+        Ranges::Partial(vec![Range { start, end }])
+    }
+
+    #[test]
+    fn test_single_ranges() {
+        assert_eq!(parse(Some("bytes=0-99"), 1000), single(0, 100));
+        assert_eq!(parse(Some("bytes=500-"), 1000), single(500, 1000));
+        assert_eq!(parse(Some("bytes=-100"), 1000), single(900, 1000));
+        // ファイルを超える指定は末尾までに切り詰める
+        assert_eq!(parse(Some("bytes=900-2000"), 1000), single(900, 1000));
+        assert_eq!(parse(Some("bytes=-5000"), 1000), single(0, 1000));
+    }
+
+    #[test]
+    fn test_multiple_ranges() {
+        assert_eq!(
+            parse(Some("bytes=0-0, -1, 2000-3000"), 10),
+            Ranges::Partial(vec![0..1, 9..10])
+        );
+        let many = format!("bytes={}", vec!["0-0"; MAX_RANGES + 1].join(","));
+        assert_eq!(parse(Some(&many), 10), Ranges::Full);
+    }
+
+    #[test]
+    fn test_unsatisfiable_and_invalid() {
+        assert_eq!(parse(Some("bytes=1000-"), 1000), Ranges::Unsatisfiable);
+        assert_eq!(parse(Some("bytes=-0"), 1000), Ranges::Unsatisfiable);
+        assert_eq!(parse(Some("bytes=0-0"), 0), Ranges::Unsatisfiable);
+
+        // 書式が正しくなければ無視する
+        assert_eq!(parse(None, 1000), Ranges::Full);
+        assert_eq!(parse(Some("items=0-1"), 1000), Ranges::Full);
+        assert_eq!(parse(Some("bytes=5-1"), 1000), Ranges::Full);
+        assert_eq!(parse(Some("bytes=a-b"), 1000), Ranges::Full);
+        assert_eq!(parse(Some("bytes=-"), 1000), Ranges::Full);
+    }
+
+    #[test]
+    fn test_content_range() {
+        assert_eq!(content_range(&(0..100), 1000), "bytes 0-99/1000");
+    }
+}
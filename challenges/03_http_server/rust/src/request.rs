@@ -0,0 +1,599 @@
+//! HTTP リクエストの読み込み
+//!
+//! リクエストライン、ヘッダー、`Content-Length` 分のボディを読む。
+//! ボディはバイト列のまま持ち、文字列として使うときに UTF-8 として解釈する。
+//!
+//! 読む量には [`Limits`] で上限を設け、超えた分はメモリに読み込まずに打ち切る。
+//!
+//! HTTP のバージョンは `HTTP/1.0` と `HTTP/1.1` (とその後の 1.x) を受け付け、
+//! 書式の誤りは 400、2.0 などのほかのメジャーバージョンは 505 にする。
+//! chunked のボディはデコードしないので、`Transfer-Encoding` 付きのリクエストは 501 で断る。
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Read};
+use std::net::SocketAddr;
+use std::str::{self, Utf8Error};
+
+use json_parser::JsonValue;
+
+use crate::multipart::{self, Part, UploadedFile};
+use crate::session::Session;
+
+/// 1 リクエストで読む量の上限
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// リクエストラインの長さ (バイト)
+    pub request_line: usize,
+    /// ヘッダーの数
+    pub header_count: usize,
+    /// ヘッダー 1 行の長さ (バイト)
+    pub header_size: usize,
+    /// ボディの長さ (バイト)
+    pub body_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            request_line: 8 * 1024,
+            header_count: 100,
+            header_size: 8 * 1024,
+            body_size: 1024 * 1024,
+        }
+    }
+}
+
+/// 受け付ける HTTP のバージョン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    /// 1.2 以降のマイナーバージョンも 1.1 として扱う
+    Http11,
+}
+
+impl Version {
+    /// `HTTP/1.1` などをパースする
+    pub fn parse(text: &str) -> Result<Self, RequestError> {
+        let malformed = || RequestError::BadRequest(format!("invalid HTTP version: {}", text));
+        let (major, minor) = text
+            .strip_prefix("HTTP/")
+            .and_then(|v| v.split_once('.'))
+            .ok_or_else(malformed)?;
+        let digit = |s: &str| match s.as_bytes() {
+            [d] if d.is_ascii_digit() => Ok(d - b'0'),
+            _ => Err(malformed()),
+        };
+        match (digit(major)?, digit(minor)?) {
+            (1, 0) => Ok(Version::Http10),
+            (1, _) => Ok(Version::Http11),
+            _ => Err(RequestError::VersionNotSupported(text.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Version::Http10 => write!(f, "HTTP/1.0"),
+            Version::Http11 => write!(f, "HTTP/1.1"),
+        }
+    }
+}
+
+/// HTTP リクエスト
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /// デコードして正規化したパス (クエリは含まない)
+    pub path: String,
+    /// `?` より後ろ (デコードしない)
+    pub query: Option<String>,
+    pub version: Version,
+    /// キーは小文字にそろえる
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+    /// 接続元 (接続の処理側で設定する)
+    pub peer: Option<SocketAddr>,
+    /// リクエスト ID (ID のミドルウェアが設定する)
+    pub id: Option<String>,
+    /// セッション (セッションのミドルウェアが設定する)
+    pub session: Option<Session>,
+}
+
+/// リクエストを読めなかった理由
+#[derive(Debug)]
+pub enum RequestError {
+    /// 何も送られないまま接続が閉じた
+    Closed,
+    /// 形式が正しくない、またはボディの途中で接続が閉じた
+    BadRequest(String),
+    /// リクエストラインが長すぎる
+    UriTooLong(usize),
+    /// ヘッダーが多すぎる、または 1 行が長すぎる
+    HeadersTooLarge(String),
+    /// ボディが上限を超える (`Content-Length`, 上限)
+    BodyTooLarge(usize, usize),
+    /// 期限までに読み終わらなかった
+    Timeout,
+    /// ボディの Content-Type が扱えない
+    UnsupportedMediaType(String),
+    /// HTTP/2.0 など、扱えないバージョン
+    VersionNotSupported(String),
+    /// 実装していない機能 (`Transfer-Encoding` など)
+    NotImplemented(String),
+    Io(io::Error),
+}
+
+impl RequestError {
+    /// 返すべきステータス (返さずに切断する場合は None)
+    pub fn status(&self) -> Option<(u16, &'static str)> {
+        match self {
+            RequestError::Closed | RequestError::Io(_) => None,
+            RequestError::BadRequest(_) => Some((400, "Bad Request")),
+            RequestError::UriTooLong(_) => Some((414, "URI Too Long")),
+            RequestError::HeadersTooLarge(_) => Some((431, "Request Header Fields Too Large")),
+            RequestError::BodyTooLarge(..) => Some((413, "Payload Too Large")),
+            RequestError::Timeout => Some((408, "Request Timeout")),
+            RequestError::UnsupportedMediaType(_) => Some((415, "Unsupported Media Type")),
+            RequestError::VersionNotSupported(_) => Some((505, "HTTP Version Not Supported")),
+            RequestError::NotImplemented(_) => Some((501, "Not Implemented")),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::Closed => write!(f, "connection closed"),
+            RequestError::BadRequest(reason) => write!(f, "bad request: {}", reason),
+            RequestError::UriTooLong(limit) => write!(f, "request line exceeds {} bytes", limit),
+            RequestError::HeadersTooLarge(reason) => write!(f, "headers too large: {}", reason),
+            RequestError::BodyTooLarge(size, limit) => {
+                write!(f, "body of {} bytes exceeds {} bytes", size, limit)
+            }
+            RequestError::Timeout => write!(f, "timed out while reading the request"),
+            RequestError::UnsupportedMediaType(media) => write!(f, "unsupported media type: {}", media),
+            RequestError::VersionNotSupported(version) => write!(f, "unsupported HTTP version: {}", version),
+            RequestError::NotImplemented(feature) => write!(f, "not implemented: {}", feature),
+            RequestError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for RequestError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            // ソケットの読み込みタイムアウトは OS によってどちらかになる
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => RequestError::Timeout,
+            _ => RequestError::Io(e),
+        }
+    }
+}
+
+/// 1 行読む (行末の CRLF / LF は除く)。接続が閉じていれば None
+///
+/// `limit` バイトを超える行は最後まで読まずに `too_long` のエラーにする。
+fn read_line(
+    reader: &mut impl BufRead,
+    limit: usize,
+    too_long: impl FnOnce() -> RequestError,
+) -> Result<Option<String>, RequestError> {
+    let mut buf = Vec::new();
+    // CRLF の分だけ余分に読む
+    let max = limit as u64 + 2;
+    if reader.by_ref().take(max).read_until(b'\n', &mut buf)? == 0 {
+        return Ok(None);
+    }
+    if buf.len() as u64 == max && buf.last() != Some(&b'\n') {
+        return Err(too_long());
+    }
+    while buf.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+        buf.pop();
+    }
+    if buf.len() > limit {
+        return Err(too_long());
+    }
+    String::from_utf8(buf)
+        .map(Some)
+        .map_err(|_| RequestError::BadRequest("header is not valid UTF-8".to_string()))
+}
+
+impl Request {
+    /// 文字列からパースする (テスト用)
+    #[cfg(test)]
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::read_from(&mut raw.as_bytes(), &Limits::default()).ok()
+    }
+
+    /// ストリームから 1 リクエスト分を読む
+    pub fn read_from(reader: &mut impl BufRead, limits: &Limits) -> Result<Self, RequestError> {
+        let (mut request, length) = Self::read_head(reader, limits)?;
+        request.body = vec![0; length];
+        reader.read_exact(&mut request.body).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                RequestError::BadRequest("connection closed before the whole body was sent".to_string())
+            }
+            _ => RequestError::from(e),
+        })?;
+        Ok(request)
+    }
+
+    /// リクエストラインとヘッダーだけを読み、(ボディが空のリクエスト, ボディの長さ) を返す
+    ///
+    /// ボディの長さは上限まで確かめてある。
+    pub fn read_head(reader: &mut impl BufRead, limits: &Limits) -> Result<(Self, usize), RequestError> {
+        // リクエストライン
+        let request_line = read_line(reader, limits.request_line, || {
+            RequestError::UriTooLong(limits.request_line)
+        })?
+        .ok_or(RequestError::Closed)?;
+        let parts: Vec<&str> = request_line.split_whitespace().collect();
+
+        let (method, path, version) = match parts.as_slice() {
+            [method, path, version] => (method, path, Version::parse(version)?),
+            _ => return Err(RequestError::BadRequest(format!("invalid request line: {}", request_line))),
+        };
+
+        let (path, query) = crate::url::parse_target(path).map_err(RequestError::BadRequest)?;
+
+        // ヘッダー
+        let mut headers: HashMap<String, String> = HashMap::new();
+        // 同じ名前が繰り返されても行ごとに数える
+        let mut lines = 0;
+        loop {
+            let line = read_line(reader, limits.header_size, || {
+                RequestError::HeadersTooLarge(format!("a header line exceeds {} bytes", limits.header_size))
+            })?
+            .ok_or_else(|| RequestError::BadRequest("connection closed in headers".to_string()))?;
+            if line.is_empty() {
+                break;
+            }
+            lines += 1;
+            if lines > limits.header_count {
+                return Err(RequestError::HeadersTooLarge(format!(
+                    "more than {} headers",
+                    limits.header_count
+                )));
+            }
+            let (key, value) = line
+                .split_once(':')
+                .ok_or_else(|| RequestError::BadRequest(format!("invalid header: {}", line)))?;
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            match headers.get_mut(&key) {
+                // 長さが食い違うとボディの区切りが決まらない (RFC 9112 6.3)
+                Some(length) if key == "content-length" => {
+                    if length != value {
+                        return Err(RequestError::BadRequest("conflicting Content-Length headers".to_string()));
+                    }
+                }
+                // 同じ名前のヘッダーは `, ` でつないで 1 つにする (RFC 9110 5.3)
+                Some(existing) => {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                }
+                None => {
+                    headers.insert(key, value.to_string());
+                }
+            }
+        }
+
+        let request = Request {
+            method: method.to_string(),
+            path,
+            query,
+            version,
+            headers,
+            body: Vec::new(),
+            peer: None,
+            id: None,
+            session: None,
+        };
+
+        if let Some(encoding) = request.header("transfer-encoding") {
+            // 両方あると前段のプロキシと区切りが食い違いうる (RFC 9112 6.1: スマグリング)
+            if request.header("content-length").is_some() {
+                return Err(RequestError::BadRequest("both Transfer-Encoding and Content-Length".to_string()));
+            }
+            // HTTP/1.0 に chunked は無い (RFC 9112 6.1: 枠組みが壊れているとみなす)
+            if request.version == Version::Http10 {
+                return Err(RequestError::BadRequest("Transfer-Encoding in an HTTP/1.0 request".to_string()));
+            }
+            // ボディの終わりが分からないので、読まずに断って接続を閉じる
+            return Err(RequestError::NotImplemented(format!("Transfer-Encoding: {}", encoding)));
+        }
+
+        // ボディは Content-Length の分だけ
+        let length = request.content_length()?;
+        if length > limits.body_size {
+            return Err(RequestError::BodyTooLarge(length, limits.body_size));
+        }
+        Ok((request, length))
+    }
+
+    /// ヘッダーの値 (名前の大文字小文字は区別しない)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    /// Cookie の値
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.header("cookie")?
+            .split(';')
+            .filter_map(|pair| pair.trim().split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// `Content-Length` (無ければ 0)
+    fn content_length(&self) -> Result<usize, RequestError> {
+        match self.header("content-length") {
+            None => Ok(0),
+            Some(value) => value
+                .parse()
+                .map_err(|_| RequestError::BadRequest(format!("invalid Content-Length: {}", value))),
+        }
+    }
+
+    /// ボディを UTF-8 の文字列として取り出す
+    pub fn body_str(&self) -> Result<&str, Utf8Error> {
+        str::from_utf8(&self.body)
+    }
+
+    /// ボディを JSON としてパースする (Content-Type があれば `application/json` に限る)
+    pub fn json(&self) -> Result<JsonValue, RequestError> {
+        if let Some(content_type) = self.header("content-type") {
+            let media = content_type.split(';').next().unwrap_or("").trim();
+            if !media.eq_ignore_ascii_case("application/json") {
+                return Err(RequestError::UnsupportedMediaType(media.to_string()));
+            }
+        }
+        let text = self
+            .body_str()
+            .map_err(|_| RequestError::BadRequest("body is not valid UTF-8".to_string()))?;
+        json_parser::parse(text).map_err(|e| RequestError::BadRequest(format!("invalid JSON: {}", e)))
+    }
+
+    /// `multipart/form-data` のボディをパートに分ける
+    fn parts(&self) -> Result<Vec<Part<'_>>, RequestError> {
+        let content_type = self.header("content-type").unwrap_or("");
+        let boundary = multipart::boundary(content_type).ok_or_else(|| {
+            let media = content_type.split(';').next().unwrap_or("").trim();
+            RequestError::UnsupportedMediaType(media.to_string())
+        })?;
+        multipart::parse(&self.body, boundary).map_err(RequestError::BadRequest)
+    }
+
+    /// `multipart/form-data` のテキストのフィールド
+    pub fn form(&self) -> Result<HashMap<String, String>, RequestError> {
+        multipart::fields(&self.parts()?).map_err(RequestError::BadRequest)
+    }
+
+    /// `multipart/form-data` でアップロードされたファイル
+    pub fn files(&self) -> Result<Vec<UploadedFile<'_>>, RequestError> {
+        Ok(multipart::files(&self.parts()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+
+    /// 1 回の read で数バイトずつしか返さないリーダー (TCP の分割を真似る)
+    struct Trickle<'a> {
+        data: &'a [u8],
+        chunk: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.chunk.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_parse_request() {
+        let raw = "GET /hello HTTP/1.1\r\nHost: localhost\r\nContent-Type: text/plain\r\n\r\n";
+        let req = Request::parse(raw).unwrap();
+
+        assert_eq!(req.method, "GET");
+        assert_eq!(req.path, "/hello");
+        assert_eq!(req.query, None);
+        assert_eq!(req.headers.get("host"), Some(&"localhost".to_string()));
+        assert!(req.body.is_empty());
+    }
+
+    #[test]
+    fn test_read_body() {
+        let raw = "POST /echo HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nhello";
+        let req = Request::parse(raw).unwrap();
+
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.version, Version::Http11);
+        assert_eq!(req.header("Content-Length"), Some("5"));
+        assert_eq!(req.body, b"hello");
+        assert_eq!(req.body_str(), Ok("hello"));
+    }
+
+    #[test]
+    fn test_read_ignores_bytes_after_body() {
+        // Content-Length を超えた分は次のリクエストとして残る
+        let mut raw: &[u8] = b"PUT /x HTTP/1.1\r\ncontent-length: 2\r\n\r\nokGET";
+        let req = Request::read_from(&mut raw, &Limits::default()).unwrap();
+        assert_eq!(req.body, b"ok");
+        assert_eq!(raw, b"GET");
+    }
+
+    #[test]
+    fn test_read_partial_chunks() {
+        let raw = b"POST /echo HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world";
+        let mut reader = BufReader::with_capacity(4, Trickle { data: raw, chunk: 3 });
+        let req = Request::read_from(&mut reader, &Limits::default()).unwrap();
+        assert_eq!(req.body_str(), Ok("hello world"));
+    }
+
+    #[test]
+    fn test_read_truncated_body() {
+        let raw = "POST /echo HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort";
+        let err = Request::read_from(&mut raw.as_bytes(), &Limits::default()).unwrap_err();
+        assert!(matches!(err, RequestError::BadRequest(_)));
+        assert_eq!(err.status(), Some((400, "Bad Request")));
+    }
+
+    #[test]
+    fn test_read_oversized_body() {
+        let limits = Limits::default();
+        let raw = format!("POST /echo HTTP/1.1\r\nContent-Length: {}\r\n\r\n", limits.body_size + 1);
+        let err = Request::read_from(&mut raw.as_bytes(), &limits).unwrap_err();
+        assert!(matches!(err, RequestError::BodyTooLarge(..)));
+        assert_eq!(err.status(), Some((413, "Payload Too Large")));
+    }
+
+    #[test]
+    fn test_read_limits() {
+        let limits = Limits {
+            request_line: 16,
+            header_count: 2,
+            header_size: 20,
+            body_size: 4,
+        };
+        let read = |raw: &str| Request::read_from(&mut raw.as_bytes(), &limits);
+
+        // ちょうど上限までは受け付ける
+        assert!(read("GET /12 HTTP/1.1\r\nA: 12345678901234567\r\nB: 1\r\n\r\n").is_ok());
+        let body = read("POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nabcd");
+        assert_eq!(body.unwrap().body, b"abcd");
+
+        let status = |raw: &str| read(raw).unwrap_err().status().unwrap().0;
+        assert_eq!(status("GET /123 HTTP/1.1\r\n\r\n"), 414);
+        assert_eq!(status("GET / HTTP/1.1\r\nA: 123456789012345678\r\n\r\n"), 431);
+        assert_eq!(status("GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"), 431);
+        assert_eq!(status("GET / HTTP/1.1\r\nA: 1\r\nA: 2\r\nA: 3\r\n\r\n"), 431);
+        assert_eq!(status("POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nabcde"), 413);
+    }
+
+    #[test]
+    fn test_long_line_is_not_buffered() {
+        // 改行の来ない巨大な行でも、上限 + 2 バイトまでしか読まない
+        let raw = vec![b'A'; 1024 * 1024];
+        let mut reader = &raw[..];
+        let err = Request::read_from(&mut reader, &Limits::default()).unwrap_err();
+        assert!(matches!(err, RequestError::UriTooLong(_)));
+        assert_eq!(raw.len() - reader.len(), Limits::default().request_line + 2);
+    }
+
+    #[test]
+    fn test_json_body() {
+        let raw = "POST / HTTP/1.1\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: 8\r\n\r\n{\"a\": 1}";
+        let value = Request::parse(raw).unwrap().json().unwrap();
+        assert_eq!(value.to_string(), r#"{"a":1}"#);
+
+        // Content-Type が無ければそのまま JSON として読む
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 4\r\n\r\nnull";
+        assert_eq!(Request::parse(raw).unwrap().json().unwrap(), JsonValue::Null);
+
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\n{]";
+        let err = Request::parse(raw).unwrap().json().unwrap_err();
+        assert_eq!(err.status(), Some((400, "Bad Request")));
+
+        let raw = "POST / HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\n{}";
+        let err = Request::parse(raw).unwrap().json().unwrap_err();
+        assert_eq!(err.status(), Some((415, "Unsupported Media Type")));
+    }
+
+    #[test]
+    fn test_form_and_files() {
+        let body = "--b\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhi\r\n\
+                    --b\r\nContent-Disposition: form-data; name=\"f\"; filename=\"x.bin\"\r\n\r\n\x01\x02\r\n--b--\r\n";
+        let raw = format!(
+            "POST / HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let req = Request::parse(&raw).unwrap();
+        assert_eq!(req.form().unwrap()["note"], "hi");
+        let files = req.files().unwrap();
+        assert_eq!(files[0].filename, "x.bin");
+        assert_eq!(files[0].content_type, "application/octet-stream");
+        assert_eq!(files[0].data, [1, 2]);
+
+        let raw = "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        let err = Request::parse(raw).unwrap().form().unwrap_err();
+        assert_eq!(err.status(), Some((415, "Unsupported Media Type")));
+        let raw = "POST / HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: 3\r\n\r\n--b";
+        let err = Request::parse(raw).unwrap().files().unwrap_err();
+        assert_eq!(err.status(), Some((400, "Bad Request")));
+    }
+
+    #[test]
+    fn test_repeated_headers() {
+        let req = Request::parse("GET / HTTP/1.1\r\nAccept: text/html\r\naccept: */*\r\n\r\n").unwrap();
+        assert_eq!(req.header("accept"), Some("text/html, */*"));
+
+        // 同じ値の Content-Length は 1 つとみなす
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 2\r\n\r\nok";
+        assert_eq!(Request::parse(raw).unwrap().body, b"ok");
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 2\r\nContent-Length: 5\r\n\r\nok";
+        let err = Request::read_from(&mut raw.as_bytes(), &Limits::default()).unwrap_err();
+        assert_eq!(err.status(), Some((400, "Bad Request")));
+    }
+
+    #[test]
+    fn test_cookie() {
+        let req = Request::parse("GET / HTTP/1.1\r\nCookie: theme=dark; sid=abc.def\r\n\r\n").unwrap();
+        assert_eq!(req.cookie("sid"), Some("abc.def"));
+        assert_eq!(req.cookie("theme"), Some("dark"));
+        assert_eq!(req.cookie("the"), None);
+        assert_eq!(Request::parse("GET / HTTP/1.1\r\n\r\n").unwrap().cookie("sid"), None);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let closed = Request::read_from(&mut &b""[..], &Limits::default());
+        assert!(matches!(closed, Err(RequestError::Closed)));
+        assert!(Request::parse("GARBAGE\r\n\r\n").is_none());
+        assert!(Request::parse("GET / HTTP/1.1\r\nNoColon\r\n\r\n").is_none());
+        assert!(Request::parse("GET / HTTP/1.1\r\nContent-Length: abc\r\n\r\n").is_none());
+        assert!(Request::parse("GET / HTTP/1.1\r\nHost: x\r\n").is_none());
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(Version::parse("HTTP/1.0").unwrap(), Version::Http10);
+        assert_eq!(Version::parse("HTTP/1.1").unwrap(), Version::Http11);
+        assert_eq!(Version::parse("HTTP/1.2").unwrap(), Version::Http11);
+        assert_eq!(Version::Http10.to_string(), "HTTP/1.0");
+
+        let status = |text: &str| Version::parse(text).unwrap_err().status();
+        for malformed in ["HTTP/1", "http/1.1", "HTTP/1.10", "HTTP/a.b", "HTTP/", "FOO"] {
+            assert_eq!(status(malformed), Some((400, "Bad Request")), "{}", malformed);
+        }
+        assert_eq!(status("HTTP/2.0"), Some((505, "HTTP Version Not Supported")));
+        assert_eq!(status("HTTP/0.9"), Some((505, "HTTP Version Not Supported")));
+
+        // バージョンの無い (HTTP/0.9 形式の) リクエストラインは受け付けない
+        assert!(Request::parse("GET /\r\n\r\n").is_none());
+        let read = |raw: &str| Request::read_from(&mut raw.as_bytes(), &Limits::default());
+        let raw = "POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert_eq!(read(raw).unwrap_err().status(), Some((400, "Bad Request")));
+        let raw = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        assert_eq!(read(raw).unwrap_err().status(), Some((501, "Not Implemented")));
+        let raw = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 5\r\n\r\n0\r\n\r\n";
+        assert_eq!(read(raw).unwrap_err().status(), Some((400, "Bad Request")));
+    }
+
+    #[test]
+    fn test_path_is_decoded_and_normalized() {
+        let req = Request::parse("GET //hello/./%E4%B8%96%E7%95%8C?lang=ja HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(req.path, "/hello/世界");
+        assert_eq!(req.query.as_deref(), Some("lang=ja"));
+
+        let err = Request::read_from(&mut &b"GET /a/%zz HTTP/1.1\r\n\r\n"[..], &Limits::default()).unwrap_err();
+        assert_eq!(err.status(), Some((400, "Bad Request")));
+        assert!(Request::parse("GET /static/../../etc/passwd HTTP/1.1\r\n\r\n").is_none());
+    }
+}
@@ -0,0 +1,81 @@
+//! リクエスト ID
+//!
+//! リクエストごとに ID を振り、`Request::id` と `X-Request-Id` レスポンスヘッダーに入れる。
+//! アクセスログにも同じ ID が出るので、クライアントの報告とログを突き合わせられる。
+//! 手前のプロキシが `X-Request-Id` を付けてきたら、書式が妥当な限りそれを引き継ぐ。
+
+use cli_tool::crypto::random_bytes;
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::Response;
+
+/// 引き継ぐ ID の長さの上限
+const MAX_LEN: usize = 64;
+
+/// 新しい ID (ランダムな 16 桁の 16 進数)
+pub fn generate() -> String {
+    random_bytes::<8>().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// クライアントから受け取った ID をそのまま使ってよいか (ログやヘッダーを壊さないもの)
+fn is_valid(id: &str) -> bool {
+    (1..=MAX_LEN).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
+/// ID を振るミドルウェア (ログより外側に置く)
+pub struct RequestIds;
+
+impl Middleware for RequestIds {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let id = match request.header("x-request-id") {
+            Some(id) if is_valid(id) => id.to_string(),
+            _ => generate(),
+        };
+        request.id = Some(id.clone());
+        next(request).with_header("X-Request-Id", &id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+
+    fn pipeline() -> Pipeline {
+        // ハンドラーからも ID が見える
+        Pipeline::new(|request: &Request| crate::build_response(200, "OK", request.id.as_deref().unwrap_or("")))
+            .with(RequestIds)
+    }
+
+    #[test]
+    fn test_assigns_unique_ids() {
+        let pipeline = pipeline();
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let response = pipeline.handle(&mut Request::parse("GET / HTTP/1.1\r\n\r\n").unwrap());
+            let id = response.headers["X-Request-Id"].clone();
+            assert_eq!(id.len(), 16);
+            assert_eq!(response.body, id.as_bytes());
+            ids.push(id);
+        }
+        assert_ne!(ids[0], ids[1]);
+    }
+
+    #[test]
+    fn test_keeps_valid_incoming_id() {
+        let pipeline = pipeline();
+        let raw = "GET / HTTP/1.1\r\nX-Request-Id: edge-42.a_b\r\n\r\n";
+        let response = pipeline.handle(&mut Request::parse(raw).unwrap());
+        assert_eq!(response.headers["X-Request-Id"], "edge-42.a_b");
+
+        // ログを壊しうる ID は使わずに振り直す
+        let raw = "GET / HTTP/1.1\r\nX-Request-Id: a\"b c\r\n\r\n";
+        let response = pipeline.handle(&mut Request::parse(raw).unwrap());
+        assert_eq!(response.headers["X-Request-Id"].len(), 16);
+        assert!(!is_valid(&"x".repeat(MAX_LEN + 1)));
+    }
+}
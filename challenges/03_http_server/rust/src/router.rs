@@ -0,0 +1,215 @@
+//! `routes!` で書くルーター
+//!
+//! パターンの検査と分解は手続きマクロ (`macros/`) がコンパイル時に済ませるので、
+//! ここにはセグメントに分けた表が `static` として入る。実行時はパスを `/` で分けて
+//! 上から順に照らし合わせるだけ。
+//!
+//! ```text
+//! static ROUTER: Router = routes! {
+//!     GET "/hello/:name" => hello,           // fn hello(request: &Request, name: &str)
+//!     GET "/tasks/:id<usize>" => show_task,  // fn show_task(request: &Request, id: usize)
+//!     GET "/files/*path" => file,            // 残り全部 (`/` を含む。空でもよい)
+//! };
+//! ```
+//!
+//! 型を付けたパラメーターは `FromStr` で変換し、失敗すればそのルートには合わなかった
+//! ことにして次を探す。パスは合うがメソッドが違うときは 405 と `Allow` を返す。
+
+pub use http_server_macros::routes;
+
+use crate::request::Request;
+use crate::Response;
+
+/// パターンの 1 セグメント
+pub enum Segment {
+    /// そのままの文字列
+    Literal(&'static str),
+    /// 空でない 1 セグメント (`:name`)
+    Param,
+    /// 残り全部 (`*name`。最後にだけ置ける)
+    // 今のアプリのルートには無い (静的ファイルは StaticFiles が受け持つ)
+    #[allow(dead_code)]
+    Rest,
+}
+
+/// パスから取り出した値を受け取り、ハンドラーを呼ぶ (`None` ならこのルートには合わない)
+pub type Handler = for<'a> fn(&'a Request, &[&'a str]) -> Option<Response>;
+
+pub struct Route {
+    pub method: &'static str,
+    pub segments: &'static [Segment],
+    pub handler: Handler,
+}
+
+/// ハンドラーが返せるもの
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+/// `RequestError` などはエラーレスポンスにする
+impl<E: Into<Response>> IntoResponse for Result<Response, E> {
+    fn into_response(self) -> Response {
+        self.unwrap_or_else(Into::into)
+    }
+}
+
+pub struct Router {
+    routes: &'static [Route],
+}
+
+impl Router {
+    pub const fn new(routes: &'static [Route]) -> Self {
+        Router { routes }
+    }
+
+    /// 合うルートのハンドラーを呼ぶ。パスが合うルートがなければ `None`
+    pub fn dispatch(&self, request: &Request) -> Option<Response> {
+        let mut allowed: Vec<&str> = Vec::new();
+        for route in self.routes {
+            let Some(values) = capture(route.segments, &request.path) else {
+                continue;
+            };
+            if route.method != request.method {
+                if !allowed.contains(&route.method) {
+                    allowed.push(route.method);
+                }
+                continue;
+            }
+            if let Some(response) = (route.handler)(request, &values) {
+                return Some(response);
+            }
+        }
+        if allowed.is_empty() {
+            return None;
+        }
+        let allow = allowed.join(", ");
+        Some(
+            crate::build_response(405, "Method Not Allowed", &format!("Allowed methods: {}", allow))
+                .with_header("Allow", &allow),
+        )
+    }
+}
+
+/// パスがパターンに合えば、パラメーターに当たる部分を順に返す
+fn capture<'p>(segments: &[Segment], path: &'p str) -> Option<Vec<&'p str>> {
+    let rest = path.strip_prefix('/')?;
+    let parts: Vec<&str> = match rest {
+        "" => Vec::new(),
+        _ => rest.split('/').collect(),
+    };
+
+    let mut values = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Rest => {
+                // 先頭の `/` と、手前のセグメントとその後ろの `/` を飛ばした残り
+                let offset = 1 + parts[..i.min(parts.len())].iter().map(|p| p.len() + 1).sum::<usize>();
+                values.push(path.get(offset..).unwrap_or(""));
+                return Some(values);
+            }
+            Segment::Literal(text) => {
+                if parts.get(i) != Some(text) {
+                    return None;
+                }
+            }
+            Segment::Param => match parts.get(i) {
+                Some(part) if !part.is_empty() => values.push(*part),
+                _ => return None,
+            },
+        }
+    }
+    (parts.len() == segments.len()).then_some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestError;
+
+    fn index(_request: &Request) -> Response {
+        crate::build_response(200, "OK", "index")
+    }
+
+    fn user(_request: &Request, name: &str) -> Response {
+        crate::build_response(200, "OK", &format!("user {}", name))
+    }
+
+    fn task(_request: &Request, id: usize) -> Response {
+        crate::build_response(200, "OK", &format!("task #{}", id))
+    }
+
+    fn task_by_slug(_request: &Request, slug: &str) -> Response {
+        crate::build_response(200, "OK", &format!("task {}", slug))
+    }
+
+    fn file(_request: &Request, user: &str, path: &str) -> Response {
+        crate::build_response(200, "OK", &format!("{}: {:?}", user, path))
+    }
+
+    fn fails(_request: &Request) -> Result<Response, RequestError> {
+        Err(RequestError::BodyTooLarge(10, 5))
+    }
+
+    static ROUTER: Router = routes! {
+        GET "/" => index,
+        GET "/users/:name" => user,
+        DELETE "/users/:name" => user,
+        GET "/tasks/:id<usize>" => task,
+        GET "/tasks/:slug" => task_by_slug,
+        GET "/files/:user/*path" => file,
+        POST "/fails" => fails,
+    };
+
+    fn send(method: &str, path: &str) -> Option<(u16, String)> {
+        let request = Request::parse(&format!("{} {} HTTP/1.1\r\n\r\n", method, path)).unwrap();
+        let response = ROUTER.dispatch(&request)?;
+        Some((response.status_code, String::from_utf8(response.body).unwrap()))
+    }
+
+    #[test]
+    fn test_capture() {
+        let segments = [Segment::Literal("a"), Segment::Param, Segment::Rest];
+        assert_eq!(capture(&segments, "/a/b/c/d"), Some(vec!["b", "c/d"]));
+        assert_eq!(capture(&segments, "/a/b/"), Some(vec!["b", ""]));
+        assert_eq!(capture(&segments, "/a/b"), Some(vec!["b", ""]));
+        assert_eq!(capture(&segments, "/a"), None);
+        assert_eq!(capture(&segments, "/x/b/c"), None);
+
+        assert_eq!(capture(&[], "/"), Some(vec![]));
+        assert_eq!(capture(&[], "/a"), None);
+        assert_eq!(capture(&[Segment::Param], "/"), None);
+        assert_eq!(capture(&[Segment::Literal("a")], "/a/"), None);
+        assert_eq!(capture(&[Segment::Rest], "/"), Some(vec![""]));
+    }
+
+    #[test]
+    fn test_dispatch() {
+        assert_eq!(send("GET", "/"), Some((200, "index".to_string())));
+        assert_eq!(send("GET", "/users/alice"), Some((200, "user alice".to_string())));
+        assert_eq!(send("DELETE", "/users/bob"), Some((200, "user bob".to_string())));
+        // 型が合わなければ次のルートへ
+        assert_eq!(send("GET", "/tasks/42"), Some((200, "task #42".to_string())));
+        assert_eq!(send("GET", "/tasks/first"), Some((200, "task first".to_string())));
+        assert_eq!(send("GET", "/files/carol/a/b.txt"), Some((200, "carol: \"a/b.txt\"".to_string())));
+        assert_eq!(send("POST", "/fails").map(|(code, _)| code), Some(413));
+
+        assert_eq!(send("GET", "/users"), None);
+        assert_eq!(send("GET", "/users/"), None);
+        assert_eq!(send("GET", "/missing"), None);
+    }
+
+    #[test]
+    fn test_method_not_allowed() {
+        let request = Request::parse("PUT /users/alice HTTP/1.1\r\n\r\n").unwrap();
+        let response = ROUTER.dispatch(&request).unwrap();
+        assert_eq!(response.status_code, 405);
+        assert_eq!(response.headers["Allow"], "GET, DELETE");
+        assert_eq!(send("GET", "/fails").map(|(code, _)| code), Some(405));
+    }
+}
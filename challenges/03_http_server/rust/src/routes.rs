@@ -0,0 +1,480 @@
+//! 設定ファイルで決めるルート (`--routes FILE`)
+//!
+//! パスの接頭辞ごとに、静的ファイルの配信・リダイレクト・別のサーバーへのプロキシを
+//! 1 行に 1 つ書く (`#` 以降はコメント)。接頭辞が長いルートほど優先する。
+//!
+//! ```text
+//! # routes.conf
+//! /docs   static    ./public/docs
+//! /old    redirect  https://example.com/new
+//! /api    proxy     http://127.0.0.1:9000/v1
+//! ```
+//!
+//! ファイルを監視し、変わったら読み直す。表を丸ごと差し替えるだけなので、
+//! つながっている接続や処理中のリクエストはそのまま動き続ける。読み直した内容に
+//! 誤りがあればログに出し、前の表を使い続ける。SIGHUP で読み直すには
+//! シグナルを扱うクレートが要るので、標準ライブラリだけで済む監視にしている。
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use cli_tool::watch::Watcher;
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::static_files::StaticFiles;
+use crate::Response;
+
+/// プロキシ先への接続と読み書きのタイムアウト
+const PROXY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 転送しない接続ごとのヘッダー (RFC 9110 7.6.1)
+const HOP_BY_HOP: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+];
+
+/// プロキシ先 (`http://host:port/base`)
+#[derive(Debug, PartialEq)]
+struct Upstream {
+    authority: String,
+    base: String,
+}
+
+impl Upstream {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("proxy target must start with http://: {}", url))?;
+        let (authority, base) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return Err(format!("proxy target has no host: {}", url));
+        }
+        let authority = match authority.contains(':') {
+            true => authority.to_string(),
+            false => format!("{}:80", authority),
+        };
+        Ok(Upstream {
+            authority,
+            base: base.trim_end_matches('/').to_string(),
+        })
+    }
+}
+
+enum Action {
+    Static(StaticFiles),
+    Redirect(String),
+    Proxy(Upstream),
+}
+
+struct Route {
+    /// 末尾の `/` を除いた接頭辞 (`/` だけのルートは空文字列)
+    prefix: String,
+    action: Action,
+}
+
+/// 読み込んだルートの一覧
+pub struct RouteTable {
+    routes: Vec<Route>,
+}
+
+impl RouteTable {
+    /// ルートファイルの内容をパースする
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut routes = Vec::new();
+        for (i, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            let [prefix, kind, target] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+                return Err(error("expected <prefix> <static|redirect|proxy> <target>".to_string()));
+            };
+            if !prefix.starts_with('/') {
+                return Err(error(format!("prefix must start with '/': {}", prefix)));
+            }
+            let prefix = prefix.trim_end_matches('/').to_string();
+            let action = match kind {
+                "static" => Action::Static(StaticFiles::new(&prefix, target)),
+                "redirect" => Action::Redirect(target.trim_end_matches('/').to_string()),
+                "proxy" => Action::Proxy(Upstream::parse(target).map_err(error)?),
+                other => return Err(error(format!("unknown route type: {}", other))),
+            };
+            if routes.iter().any(|r: &Route| r.prefix == prefix) {
+                return Err(error(format!("duplicate prefix: {}", prefix)));
+            }
+            routes.push(Route { prefix, action });
+        }
+        routes.sort_by_key(|route| std::cmp::Reverse(route.prefix.len()));
+        Ok(RouteTable { routes })
+    }
+
+    /// パスに合うルートと、接頭辞より後ろの部分 (空か `/` で始まる)
+    fn find<'a>(&self, path: &'a str) -> Option<(&Route, &'a str)> {
+        self.routes.iter().find_map(|route| {
+            let rest = path.strip_prefix(&route.prefix)?;
+            (rest.is_empty() || rest.starts_with('/')).then_some((route, rest))
+        })
+    }
+
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let path = request.path.clone();
+        let Some((route, rest)) = self.find(&path) else {
+            return next(request);
+        };
+        match &route.action {
+            Action::Static(files) => files.handle(request, next),
+            Action::Redirect(target) => {
+                let mut location = format!("{}{}", target, rest);
+                if let Some(query) = &request.query {
+                    location.push('?');
+                    location.push_str(query);
+                }
+                Response::redirect(&location, 301)
+            }
+            Action::Proxy(upstream) => forward(upstream, request, rest).unwrap_or_else(|e| {
+                eprintln!("Proxy to {} failed: {}", upstream.authority, e);
+                crate::build_response(502, "Bad Gateway", "The upstream server did not respond")
+            }),
+        }
+    }
+}
+
+/// リクエストをプロキシ先に送り、返ってきたレスポンスを返す
+fn forward(upstream: &Upstream, request: &Request, rest: &str) -> io::Result<Response> {
+    let addr = upstream
+        .authority
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for the upstream"))?;
+    let mut stream = TcpStream::connect_timeout(&addr, PROXY_TIMEOUT)?;
+    stream.set_read_timeout(Some(PROXY_TIMEOUT))?;
+    stream.set_write_timeout(Some(PROXY_TIMEOUT))?;
+
+    let mut target = crate::url::percent_encode_path(&format!("{}{}", upstream.base, rest));
+    if !target.starts_with('/') {
+        target.insert(0, '/');
+    }
+    if let Some(query) = &request.query {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", request.method, target, upstream.authority);
+    for (key, value) in &request.headers {
+        let skip = ["host", "content-length", "x-forwarded-for"].contains(&key.as_str());
+        if !skip && !HOP_BY_HOP.contains(&key.as_str()) {
+            head.push_str(&format!("{}: {}\r\n", key, value));
+        }
+    }
+    if let Some(peer) = request.peer {
+        let forwarded = match request.header("x-forwarded-for") {
+            Some(earlier) => format!("{}, {}", earlier, peer.ip()),
+            None => peer.ip().to_string(),
+        };
+        head.push_str(&format!("X-Forwarded-For: {}\r\n", forwarded));
+    }
+    // Transfer-Encoding 付きのリクエストは読み込みで断っているので、ボディはいつも Content-Length で送れる
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", request.body.len()));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&request.body)?;
+
+    read_response(&mut BufReader::new(stream), request.method == "HEAD")
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// プロキシ先のレスポンスを読む
+fn read_response(reader: &mut impl BufRead, head_only: bool) -> io::Result<Response> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.trim_end().splitn(3, ' ');
+    let (Some(version), Some(code)) = (parts.next(), parts.next()) else {
+        return Err(invalid("malformed status line"));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(invalid("malformed status line"));
+    }
+    let code: u16 = code.parse().map_err(|_| invalid("malformed status code"))?;
+    let mut response = Response::new(code, parts.next().unwrap_or(""));
+
+    let (mut length, mut chunked) = (None, false);
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("connection closed in headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (key, value) = header.split_once(':').ok_or_else(|| invalid("malformed header"))?;
+        let (name, value) = (key.trim().to_lowercase(), value.trim());
+        match name.as_str() {
+            "content-length" => length = Some(value.parse().map_err(|_| invalid("malformed Content-Length"))?),
+            "transfer-encoding" => chunked = value.eq_ignore_ascii_case("chunked"),
+            _ if HOP_BY_HOP.contains(&name.as_str()) => {}
+            _ => response = response.with_header(key.trim(), value),
+        }
+    }
+
+    // HEAD と 1xx / 204 / 304 にボディは無い
+    let no_body = head_only || code < 200 || code == 204 || code == 304;
+    let body = if no_body {
+        Vec::new()
+    } else if chunked {
+        read_chunked(reader)?
+    } else if let Some(length) = length {
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        body
+    } else {
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body)?;
+        body
+    };
+    match (no_body, length) {
+        // ボディを付けずに元の長さを伝える
+        (true, Some(length)) => Ok(response.with_header("Content-Length", &length.to_string())),
+        (true, None) => Ok(response),
+        (false, _) => Ok(response.with_bytes(body)),
+    }
+}
+
+/// `Transfer-Encoding: chunked` のボディをつなげる
+fn read_chunked(reader: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        reader.read_line(&mut line)?;
+        let size = line.trim().split(';').next().unwrap_or("");
+        let size = usize::from_str_radix(size.trim(), 16).map_err(|_| invalid("malformed chunk size"))?;
+        if size == 0 {
+            // トレーラーは読み捨てる
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+        line.clear();
+        reader.read_line(&mut line)?;
+    }
+}
+
+/// ルートファイルの表を持つミドルウェア (読み直すと表を差し替える)
+pub struct RouteConfig {
+    path: PathBuf,
+    table: Arc<RwLock<Arc<RouteTable>>>,
+}
+
+impl RouteConfig {
+    /// ファイルを読み込む
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let table = read_table(&path)?;
+        Ok(RouteConfig {
+            path,
+            table: Arc::new(RwLock::new(Arc::new(table))),
+        })
+    }
+
+    /// `interval` ごとにファイルを確かめ、変わっていれば読み直すスレッドを起動する
+    pub fn watch(self, interval: Duration) -> Self {
+        let (path, table) = (self.path.clone(), Arc::clone(&self.table));
+        // 読み込んだ時点を基準にする (スレッドが動き出す前の変更も拾う)
+        let mut watcher = Watcher::new(vec![path.clone()], interval);
+        thread::spawn(move || loop {
+            watcher.wait();
+            match reload(&path, &table) {
+                Ok(()) => eprintln!("Reloaded routes from {}", path.display()),
+                Err(e) => eprintln!("Keeping the previous routes: {}", e),
+            }
+        });
+        self
+    }
+}
+
+fn read_table(path: &Path) -> Result<RouteTable, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Failed to read routes {}: {}", path.display(), e))?;
+    RouteTable::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// ファイルを読み直す (誤りがあれば今の表を残してエラーを返す)
+fn reload(path: &Path, table: &RwLock<Arc<RouteTable>>) -> Result<(), String> {
+    let new = Arc::new(read_table(path)?);
+    *table.write().unwrap_or_else(|e| e.into_inner()) = new;
+    Ok(())
+}
+
+impl Middleware for RouteConfig {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        // 処理中は読み直しを待たせないよう、表の参照だけを取ってロックを外す
+        let table = Arc::clone(&self.table.read().unwrap_or_else(|e| e.into_inner()));
+        table.handle(request, next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::{self, Config};
+    use crate::middleware::Pipeline;
+    use std::env;
+    use std::net::TcpListener;
+
+    fn temp(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("http-routes-{}-{}", name, std::process::id()))
+    }
+
+    fn pipeline(routes: RouteConfig) -> Pipeline {
+        Pipeline::new(|_: &Request| crate::build_response(404, "Not Found", "next")).with(routes)
+    }
+
+    fn get(pipeline: &Pipeline, target: &str) -> Response {
+        let raw = format!("GET {} HTTP/1.1\r\n\r\n", target);
+        pipeline.handle(&mut Request::parse(&raw).unwrap())
+    }
+
+    /// リクエストの中身を返すサーバーを起動し、アドレスを返す
+    fn upstream() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    connection::serve(stream, &Config::default(), |request| {
+                        let body = format!(
+                            "{} {} {:?} host={}",
+                            request.method,
+                            request.path,
+                            request.query,
+                            request.header("host").unwrap_or("")
+                        );
+                        crate::build_response(201, "Created", &body).with_header("X-Upstream", "yes")
+                    })
+                });
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = RouteTable::parse("# comment\n/a static /tmp\n/a/b/ redirect /x\n\n/ proxy http://h/base/\n").unwrap();
+        let prefixes: Vec<_> = table.routes.iter().map(|r| r.prefix.as_str()).collect();
+        assert_eq!(prefixes, ["/a/b", "/a", ""]);
+        assert_eq!(table.find("/a/b/c").unwrap().1, "/c");
+        assert_eq!(table.find("/a").unwrap().1, "");
+        // `/ab` は `/a` に含まれない
+        assert_eq!(table.find("/ab").unwrap().1, "/ab");
+
+        let Action::Proxy(upstream) = &table.routes[2].action else { panic!() };
+        assert_eq!(upstream, &Upstream { authority: "h:80".to_string(), base: "/base".to_string() });
+
+        let err = RouteTable::parse("/a static /tmp\n/b teleport /c").err().unwrap();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        assert!(RouteTable::parse("/a static").is_err());
+        assert!(RouteTable::parse("a static /tmp").is_err());
+        assert!(RouteTable::parse("/a proxy https://h").is_err());
+        assert!(RouteTable::parse("/a static /x\n/a/ static /y").is_err());
+    }
+
+    #[test]
+    fn test_static_and_redirect() {
+        let dir = temp("static");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("guide.txt"), "read me").unwrap();
+        let file = temp("static.conf");
+        fs::write(&file, format!("/docs static {}\n/old redirect https://example.com/new/\n", dir.display())).unwrap();
+        let pipeline = pipeline(RouteConfig::load(&file).unwrap());
+
+        assert_eq!(get(&pipeline, "/docs/guide.txt").body, b"read me");
+        assert_eq!(get(&pipeline, "/docs/missing.txt").status_code, 404);
+        assert_eq!(get(&pipeline, "/other").body, b"next");
+
+        let response = get(&pipeline, "/old/page?x=1");
+        assert_eq!(response.status_code, 301);
+        assert_eq!(response.headers["Location"], "https://example.com/new/page?x=1");
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_proxy() {
+        let file = temp("proxy.conf");
+        fs::write(&file, format!("/api proxy http://{}/v1\n/down proxy http://127.0.0.1:1\n", upstream())).unwrap();
+        let pipeline = pipeline(RouteConfig::load(&file).unwrap());
+
+        let response = get(&pipeline, "/api/a%20b?q=1");
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.headers["X-Upstream"], "yes");
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.starts_with(r#"GET /v1/a b Some("q=1") host=127.0.0.1:"#), "{}", body);
+
+        assert_eq!(get(&pipeline, "/down").status_code, 502);
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_reload_keeps_old_table_on_error() {
+        let file = temp("reload.conf");
+        fs::write(&file, "/a redirect /one\n").unwrap();
+        let routes = RouteConfig::load(&file).unwrap();
+        let table = Arc::clone(&routes.table);
+        let pipeline = pipeline(routes);
+        assert_eq!(get(&pipeline, "/a").headers["Location"], "/one");
+
+        fs::write(&file, "/a redirect /two\n").unwrap();
+        reload(&file, &table).unwrap();
+        assert_eq!(get(&pipeline, "/a").headers["Location"], "/two");
+
+        fs::write(&file, "/a teleport /three\n").unwrap();
+        assert!(reload(&file, &table).is_err());
+        assert_eq!(get(&pipeline, "/a").headers["Location"], "/two");
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_watch_reloads_on_change() {
+        let file = temp("watch.conf");
+        fs::write(&file, "/a redirect /one\n").unwrap();
+        let pipeline = pipeline(RouteConfig::load(&file).unwrap().watch(Duration::from_millis(10)));
+
+        // 更新時刻の精度が粗くてもサイズの違いで気付く
+        fs::write(&file, "/a redirect /second\n").unwrap();
+        let mut location = String::new();
+        for _ in 0..200 {
+            location = get(&pipeline, "/a").headers["Location"].clone();
+            if location == "/second" {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(location, "/second");
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,230 @@
+//! サーバー全体の結合テスト
+//!
+//! `main` と同じ [`crate::app`] でパイプラインを組み、空いているポートで待ち受ける
+//! サーバーをバックグラウンドのスレッドで動かす。ふつうのリクエストは
+//! `cli_tool::http` のクライアントで送り、keep-alive や壊れたリクエストは
+//! 1 本の TCP 接続に生のバイト列を書いて確かめる。
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use cli_tool::http::{self, Response};
+
+use crate::connection;
+
+/// 一時ディレクトリを分けるための通し番号
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// バックグラウンドで動くサーバー (タスクファイルと公開ディレクトリは一時ディレクトリに置く)
+struct TestServer {
+    addr: SocketAddr,
+    dir: PathBuf,
+}
+
+impl TestServer {
+    /// コマンドラインと同じ引数で起動する (環境変数と既定の設定ファイルは読まない)
+    fn start(extra: &[&str]) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("http-server-test-{}-{}", std::process::id(), id));
+        fs::create_dir_all(dir.join("public")).unwrap();
+        fs::write(dir.join("public/hello.txt"), "Hello, static!").unwrap();
+
+        let mut args: Vec<String> = ["--quiet", "--root"].iter().map(|s| s.to_string()).collect();
+        args.push(dir.join("public").display().to_string());
+        args.extend(extra.iter().map(|s| s.to_string()));
+        let options = crate::parse_args_with(&args, |_| None).unwrap();
+        let pipeline = Arc::new(crate::app(&options, dir.join("tasks.txt")).unwrap());
+        let config = Arc::new(options.connection);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (pipeline, config) = (Arc::clone(&pipeline), Arc::clone(&config));
+                thread::spawn(move || connection::serve(stream, &config, |request| pipeline.handle(request)));
+            }
+        });
+        TestServer { addr, dir }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// 生のリクエストを送るための接続
+    fn connect(&self) -> Connection {
+        let stream = TcpStream::connect(self.addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        Connection { stream, reader }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Connection {
+    /// 1 リクエストを書き、レスポンスを 1 つ読む
+    fn send(&mut self, raw: &str) -> Response {
+        self.stream.write_all(raw.as_bytes()).unwrap();
+        self.read()
+    }
+
+    fn read(&mut self) -> Response {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        let status = line.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+
+        let mut headers = HashMap::new();
+        loop {
+            line.clear();
+            self.reader.read_line(&mut line).unwrap();
+            match line.trim_end().split_once(':') {
+                Some((key, value)) => headers.insert(key.trim().to_lowercase(), value.trim().to_string()),
+                None => break,
+            };
+        }
+        let length = headers.get("content-length").map_or(0, |v| v.parse().unwrap());
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).unwrap();
+        Response {
+            status,
+            headers,
+            body: String::from_utf8(body).unwrap(),
+        }
+    }
+
+    /// サーバーが接続を閉じたか
+    fn closed(&mut self) -> bool {
+        matches!(self.reader.read(&mut [0; 1]), Ok(0))
+    }
+}
+
+#[test]
+fn test_status_codes_and_headers() {
+    let server = TestServer::start(&[]);
+
+    let response = http::request("GET", &server.url("/"), None).unwrap();
+    assert_eq!(response.status, 200);
+    assert!(response.header("content-type").unwrap().starts_with("text/html"));
+    assert_eq!(response.header("x-request-id").map(str::len), Some(16));
+    assert_eq!(response.header("connection"), Some("close"));
+
+    let response = http::request("GET", &server.url("/hello/%E4%B8%96%E7%95%8C"), None).unwrap();
+    assert_eq!((response.status, response.body.as_str()), (200, "Hello, 世界!"));
+
+    let response = http::request("POST", &server.url("/echo"), Some("ping")).unwrap();
+    assert_eq!((response.status, response.body.as_str()), (200, "ping"));
+
+    // クライアントは JSON を望むので、エラーページも JSON になる
+    let response = http::request("GET", &server.url("/missing"), None).unwrap();
+    assert_eq!(response.status, 404);
+    assert!(response.header("content-type").unwrap().starts_with("application/json"));
+    assert!(response.body.contains(r#""status":404"#));
+
+    assert_eq!(http::request("DELETE", &server.url("/"), None).unwrap().status, 405);
+
+    let response = http::request("GET", &server.url("/static/hello.txt"), None).unwrap();
+    assert_eq!((response.status, response.body.as_str()), (200, "Hello, static!"));
+    assert_eq!(response.header("cache-control"), Some("no-cache"));
+    assert!(response.header("etag").is_some());
+    assert!(response.header("last-modified").is_some());
+
+    let response = http::request("POST", &server.url("/tasks"), Some(r#"{"description": "Buy milk"}"#)).unwrap();
+    assert_eq!(response.status, 201);
+    let response = http::request("GET", &server.url("/tasks"), None).unwrap();
+    assert!(response.body.contains("Buy milk"));
+}
+
+#[test]
+fn test_conditional_and_range_requests() {
+    let server = TestServer::start(&[]);
+    let mut conn = server.connect();
+
+    let response = conn.send("GET /static/hello.txt HTTP/1.1\r\nHost: test\r\n\r\n");
+    let etag = response.header("etag").unwrap().to_string();
+
+    let response = conn.send(&format!("GET /static/hello.txt HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n", etag));
+    assert_eq!((response.status, response.body.as_str()), (304, ""));
+
+    let response = conn.send("GET /static/hello.txt HTTP/1.1\r\nRange: bytes=0-4\r\n\r\n");
+    assert_eq!((response.status, response.body.as_str()), (206, "Hello"));
+    assert_eq!(response.header("content-range"), Some("bytes 0-4/14"));
+}
+
+#[test]
+fn test_keep_alive_reuses_connection() {
+    let server = TestServer::start(&["--max-requests", "3"]);
+    let mut conn = server.connect();
+
+    // 同じ接続でログインし、Cookie を付けて続ける
+    let response = conn.send("POST /login HTTP/1.1\r\nContent-Length: 5\r\n\r\nalice");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("keep-alive"), Some("timeout=5, max=2"));
+    let cookie = response.header("set-cookie").unwrap().split(';').next().unwrap().to_string();
+
+    let response = conn.send(&format!("GET /whoami HTTP/1.1\r\nCookie: {}\r\n\r\n", cookie));
+    assert_eq!((response.status, response.body.as_str()), (200, "Logged in as alice"));
+    assert_eq!(response.header("connection"), Some("keep-alive"));
+
+    // 上限に達したら閉じる
+    let response = conn.send("GET /hello/last HTTP/1.1\r\n\r\n");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.header("connection"), Some("close"));
+    assert!(conn.closed());
+
+    // パイプラインで続けて送っても順に返る
+    let mut conn = server.connect();
+    conn.stream
+        .write_all(b"GET /hello/a HTTP/1.1\r\n\r\nGET /hello/b HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    assert_eq!(conn.read().body, "Hello, a!");
+    assert_eq!(conn.read().body, "Hello, b!");
+    assert!(conn.closed());
+}
+
+#[test]
+fn test_malformed_requests() {
+    let server = TestServer::start(&["--max-header-size", "100", "--max-body-size", "10"]);
+    let cases = [
+        ("garbage\r\n\r\n", 400),
+        ("GET /hello/x\r\n\r\n", 400),
+        ("GET / HTTP/2.0\r\n\r\n", 505),
+        ("GET /%zz HTTP/1.1\r\n\r\n", 400),
+        ("GET /../etc/passwd HTTP/1.1\r\n\r\n", 400),
+        ("POST /echo HTTP/1.1\r\nContent-Length: ten\r\n\r\n", 400),
+        ("POST /echo HTTP/1.1\r\nContent-Length: 11\r\n\r\nhello world", 413),
+        ("POST /echo HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n", 400),
+        ("POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n", 501),
+    ];
+    for (raw, status) in cases {
+        let mut conn = server.connect();
+        let response = conn.send(raw);
+        assert_eq!(response.status, status, "{:?}", raw);
+        assert_eq!(response.header("connection"), Some("close"));
+        assert!(conn.closed(), "{:?}", raw);
+    }
+
+    let mut conn = server.connect();
+    let response = conn.send(&format!("GET / HTTP/1.1\r\nX-Big: {}\r\n\r\n", "a".repeat(200)));
+    assert_eq!(response.status, 431);
+
+    // 壊れたリクエストの後でもサーバーは動き続ける
+    assert_eq!(http::request("GET", &server.url("/hello/ok"), None).unwrap().status, 200);
+}
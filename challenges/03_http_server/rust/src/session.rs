@@ -0,0 +1,285 @@
+//! メモリ上のセッション
+//!
+//! セッション ID は `sid=<ID>.<署名>` という Cookie で渡す。署名はサーバー起動時に
+//! 作る鍵での HMAC-SHA256 なので、クライアントが ID を作ったり書き換えたりしても
+//! 受け付けない。データはプロセス内の `Arc<Mutex<HashMap>>` に置くだけなので、
+//! サーバーを再起動するとすべてのセッションが消える。
+//!
+//! セッションは値を書き込んだときに作られ、最後に使われてから `ttl` で期限切れになる。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use cli_tool::crypto::{constant_time_eq, hmac_sha256, random_bytes};
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::Response;
+
+/// セッション ID を入れる Cookie の名前
+pub const COOKIE_NAME: &str = "sid";
+
+/// 既定の有効期間
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// 期限切れのセッションを掃除する間隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct Entry {
+    values: HashMap<String, String>,
+    expires: Instant,
+}
+
+type Entries = Arc<Mutex<HashMap<String, Entry>>>;
+
+fn lock(entries: &Entries) -> MutexGuard<'_, HashMap<String, Entry>> {
+    // 他のスレッドがパニックしても、セッションのデータ自体は壊れていない
+    entries.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 1 リクエストから見たセッション (ハンドラーは `request.session` から使う)
+#[derive(Debug, Clone)]
+pub struct Session {
+    id: String,
+    entries: Entries,
+    ttl: Duration,
+}
+
+impl Session {
+    pub fn get(&self, key: &str) -> Option<String> {
+        lock(&self.entries).get(&self.id)?.values.get(key).cloned()
+    }
+
+    /// 値を書き込む (セッションがまだ無ければ作る)
+    pub fn insert(&self, key: &str, value: &str) {
+        let expires = Instant::now() + self.ttl;
+        let mut entries = lock(&self.entries);
+        let entry = entries.entry(self.id.clone()).or_insert_with(|| Entry {
+            values: HashMap::new(),
+            expires,
+        });
+        entry.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// セッションごと破棄する (Cookie も消す)
+    pub fn destroy(&self) {
+        lock(&self.entries).remove(&self.id);
+    }
+
+    fn exists(&self) -> bool {
+        lock(&self.entries).contains_key(&self.id)
+    }
+}
+
+/// セッションを管理するミドルウェア
+pub struct Sessions {
+    entries: Entries,
+    key: [u8; 32],
+    ttl: Duration,
+    last_cleanup: Mutex<Instant>,
+}
+
+impl Sessions {
+    /// 署名の鍵は毎回ランダムに作る
+    pub fn new(ttl: Duration) -> Self {
+        Sessions {
+            entries: Arc::default(),
+            key: random_bytes(),
+            ttl,
+            last_cleanup: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// ID に署名を付けた Cookie の値
+    fn sign(&self, id: &str) -> String {
+        format!("{}.{}", id, hex(&hmac_sha256(&self.key, id.as_bytes())))
+    }
+
+    /// Cookie の値の署名を確かめ、ID を取り出す
+    fn verify<'a>(&self, value: &'a str) -> Option<&'a str> {
+        let (id, signature) = value.split_once('.')?;
+        let expected = hex(&hmac_sha256(&self.key, id.as_bytes()));
+        constant_time_eq(expected.as_bytes(), signature.as_bytes()).then_some(id)
+    }
+
+    /// 期限内のセッションなら ID を返し、期限を延ばす
+    fn touch(&self, id: &str) -> bool {
+        let now = Instant::now();
+        let mut entries = lock(&self.entries);
+        match entries.get_mut(id) {
+            Some(entry) if entry.expires > now => {
+                entry.expires = now + self.ttl;
+                true
+            }
+            Some(_) => {
+                entries.remove(id);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// 期限切れのセッションを消す (一定間隔ごと)
+    fn cleanup(&self) {
+        let mut last = self.last_cleanup.lock().unwrap_or_else(|e| e.into_inner());
+        if last.elapsed() < CLEANUP_INTERVAL {
+            return;
+        }
+        *last = Instant::now();
+        let now = Instant::now();
+        lock(&self.entries).retain(|_, entry| entry.expires > now);
+    }
+
+    /// 今あるセッションの数
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        lock(&self.entries).len()
+    }
+}
+
+impl Middleware for Sessions {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        self.cleanup();
+
+        let existing = request
+            .cookie(COOKIE_NAME)
+            .and_then(|value| self.verify(value))
+            .filter(|id| self.touch(id))
+            .map(str::to_string);
+        let existed = existing.is_some();
+        let id = existing.unwrap_or_else(|| hex(&random_bytes::<16>()));
+
+        let session = Session {
+            id,
+            entries: Arc::clone(&self.entries),
+            ttl: self.ttl,
+        };
+        request.session = Some(session.clone());
+        let response = next(request);
+
+        if session.exists() {
+            let cookie = format!(
+                "{}={}; Max-Age={}; Path=/; HttpOnly; SameSite=Lax",
+                COOKIE_NAME,
+                self.sign(&session.id),
+                self.ttl.as_secs()
+            );
+            response.with_header("Set-Cookie", &cookie)
+        } else if existed || request.cookie(COOKIE_NAME).is_some() {
+            // 破棄されたか無効な Cookie は消させる
+            let cookie = format!("{}=; Max-Age=0; Path=/; HttpOnly; SameSite=Lax", COOKIE_NAME);
+            response.with_header("Set-Cookie", &cookie)
+        } else {
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+
+    /// ログイン状態を返すだけのハンドラー
+    fn pipeline(ttl: Duration) -> Pipeline {
+        Pipeline::new(|req: &Request| {
+            let session = req.session.as_ref().unwrap();
+            match req.path.as_str() {
+                "/login" => session.insert("user", "alice"),
+                "/logout" => session.destroy(),
+                _ => {}
+            }
+            let user = session.get("user").unwrap_or_default();
+            crate::build_response(200, "OK", &user)
+        })
+        .with(Sessions::new(ttl))
+    }
+
+    /// Cookie を付けて (付けずに) リクエストし、(Set-Cookie の値, ボディ) を返す
+    fn send(pipeline: &Pipeline, path: &str, cookie: Option<&str>) -> (Option<String>, String) {
+        let header = cookie.map_or(String::new(), |c| format!("Cookie: theme=dark; {}\r\n", c));
+        let mut request = Request::parse(&format!("GET {} HTTP/1.1\r\n{}\r\n", path, header)).unwrap();
+        let response = pipeline.handle(&mut request);
+        let set_cookie = response.headers.get("Set-Cookie").map(|value| {
+            value.split(';').next().unwrap().to_string()
+        });
+        (set_cookie, String::from_utf8(response.body).unwrap())
+    }
+
+    #[test]
+    fn test_login_and_logout() {
+        let pipeline = pipeline(DEFAULT_TTL);
+
+        // 何も書き込まなければ Cookie は発行しない
+        assert_eq!(send(&pipeline, "/", None), (None, String::new()));
+
+        let (cookie, body) = send(&pipeline, "/login", None);
+        let cookie = cookie.unwrap();
+        assert!(cookie.starts_with("sid="));
+        assert_eq!(body, "alice");
+
+        assert_eq!(send(&pipeline, "/", Some(&cookie)).1, "alice");
+        assert_eq!(send(&pipeline, "/", None).1, "");
+
+        let (cleared, body) = send(&pipeline, "/logout", Some(&cookie));
+        assert_eq!(cleared.as_deref(), Some("sid="));
+        assert_eq!(body, "");
+        assert_eq!(send(&pipeline, "/", Some(&cookie)).1, "");
+    }
+
+    #[test]
+    fn test_rejects_forged_ids() {
+        let pipeline = pipeline(DEFAULT_TTL);
+        let cookie = send(&pipeline, "/login", None).0.unwrap();
+        let (id, signature) = cookie.split_once('.').unwrap();
+
+        // 署名の無い ID、別の ID に付け替えた署名は使えない
+        assert_eq!(send(&pipeline, "/", Some(id)).1, "");
+        let forged = format!("sid=0000.{}", signature);
+        assert_eq!(send(&pipeline, "/", Some(&forged)).1, "");
+
+        // 別のサーバー (鍵) が署名した Cookie も使えない
+        let other = self::pipeline(DEFAULT_TTL);
+        assert_eq!(send(&other, "/", Some(&cookie)).1, "");
+    }
+
+    #[test]
+    fn test_sessions_expire() {
+        let sessions = Sessions::new(Duration::ZERO);
+        let cookie = {
+            let pipeline = Pipeline::new(|req: &Request| {
+                req.session.as_ref().unwrap().insert("user", "bob");
+                crate::build_response(200, "OK", "")
+            });
+            let mut request = Request::parse("GET / HTTP/1.1\r\n\r\n").unwrap();
+            let response = sessions.handle(&mut request, &|req| pipeline.handle(req));
+            response.headers["Set-Cookie"].split(';').next().unwrap().to_string()
+        };
+        assert_eq!(sessions.len(), 1);
+
+        let mut request = Request::parse(&format!("GET / HTTP/1.1\r\nCookie: {}\r\n\r\n", cookie)).unwrap();
+        sessions.handle(&mut request, &|req| {
+            assert_eq!(req.session.as_ref().unwrap().get("user"), None);
+            crate::build_response(200, "OK", "")
+        });
+        assert_eq!(sessions.len(), 0);
+
+        // 使われないまま期限が切れたものは掃除で消える
+        sessions.entries.lock().unwrap().insert(
+            "stale".to_string(),
+            Entry {
+                values: HashMap::new(),
+                expires: Instant::now(),
+            },
+        );
+        *sessions.last_cleanup.lock().unwrap() = Instant::now().checked_sub(CLEANUP_INTERVAL).unwrap();
+        sessions.cleanup();
+        assert_eq!(sessions.len(), 0);
+    }
+}
@@ -0,0 +1,454 @@
+//! 静的ファイルの配信
+//!
+//! `prefix` 以下のパスを `root` ディレクトリのファイルに対応させる。
+//! `Range` ヘッダーがあれば指定された範囲だけを読んで 206 を返すので、
+//! 動画や音声の途中から再生したり、中断したダウンロードを再開したりできる。
+//!
+//! `Last-Modified` と設定した `Cache-Control` を付ける (304 は [`crate::conditional`] が返す)。
+//!
+//! `index.html` の無いディレクトリは 404 にする。`autoindex` を有効にすると、
+//! 代わりに中身の一覧 (名前・サイズ・更新日時) を HTML で返す。`.` で始まる名前は載せない。
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cli_tool::crypto::random_bytes;
+use json_parser::JsonValue;
+
+use crate::conditional::http_date;
+use crate::middleware::{Middleware, Next};
+use crate::range::{self, Ranges};
+use crate::request::Request;
+use crate::Response;
+
+/// 既定の `Cache-Control` (キャッシュしてよいが、使う前に毎回 ETag で確かめる)
+pub const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+/// 拡張子から Content-Type を決める
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "json" => "application/json; charset=utf-8",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// ファイルの一部を読む
+fn read_range(file: &mut File, range: &Range<u64>) -> io::Result<Vec<u8>> {
+    file.seek(SeekFrom::Start(range.start))?;
+    let mut buf = vec![0; (range.end - range.start) as usize];
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// `root` 以下のファイルを配信するミドルウェア
+pub struct StaticFiles {
+    prefix: String,
+    root: PathBuf,
+    cache_control: String,
+    /// index.html の無いディレクトリで一覧を返す
+    autoindex: bool,
+}
+
+impl StaticFiles {
+    /// `prefix` は `/static` のように先頭にだけ `/` を付ける
+    pub fn new(prefix: &str, root: impl Into<PathBuf>) -> Self {
+        StaticFiles {
+            prefix: prefix.trim_end_matches('/').to_string(),
+            root: root.into(),
+            cache_control: DEFAULT_CACHE_CONTROL.to_string(),
+            autoindex: false,
+        }
+    }
+
+    /// `Cache-Control` の値を変える (`public, max-age=3600` など)
+    pub fn with_cache_control(mut self, value: &str) -> Self {
+        self.cache_control = value.to_string();
+        self
+    }
+
+    /// index.html の無いディレクトリで一覧を返すか
+    pub fn with_autoindex(mut self, enabled: bool) -> Self {
+        self.autoindex = enabled;
+        self
+    }
+
+    /// リクエストのパスに対応するファイル (`..` を含むなど、root の外を指すものは None)
+    ///
+    /// ディレクトリなら index.html。それが無く `autoindex` が有効ならディレクトリそのもの。
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let rest = path.strip_prefix(&self.prefix)?.strip_prefix('/')?;
+
+        let mut file = self.root.clone();
+        for segment in rest.split('/').filter(|s| !s.is_empty()) {
+            if segment == "." || segment == ".." || segment.contains('\\') {
+                return None;
+            }
+            file.push(segment);
+        }
+        if file.is_dir() && (!self.autoindex || file.join("index.html").is_file()) {
+            file.push("index.html");
+        }
+        Some(file)
+    }
+
+    fn serve(&self, request: &Request, path: &Path) -> Response {
+        let not_found = || crate::build_response(404, "Not Found", &format!("Path '{}' not found", request.path));
+        let Ok(mut file) = File::open(path) else {
+            return not_found();
+        };
+        let (len, modified) = match fs::metadata(path) {
+            Ok(meta) if meta.is_file() => (meta.len(), meta.modified()),
+            _ => return not_found(),
+        };
+
+        let content_type = content_type(path);
+        let result = match range::parse(request.header("range"), len) {
+            Ranges::Full => read_range(&mut file, &(0..len))
+                .map(|body| Response::new(200, "OK").with_header("Content-Type", content_type).with_bytes(body)),
+            Ranges::Partial(ranges) if ranges.len() == 1 => read_range(&mut file, &ranges[0]).map(|body| {
+                Response::new(206, "Partial Content")
+                    .with_header("Content-Type", content_type)
+                    .with_header("Content-Range", &range::content_range(&ranges[0], len))
+                    .with_bytes(body)
+            }),
+            Ranges::Partial(ranges) => multipart(&mut file, &ranges, len, content_type),
+            Ranges::Unsatisfiable => Ok(crate::build_response(416, "Range Not Satisfiable", "Range Not Satisfiable")
+                .with_header("Content-Range", &format!("bytes */{}", len))),
+        };
+
+        match result {
+            Ok(response) => {
+                let response = response
+                    .with_header("Accept-Ranges", "bytes")
+                    .with_header("Cache-Control", &self.cache_control);
+                match modified.ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()) {
+                    Some(since) => response.with_header("Last-Modified", &http_date(since.as_secs())),
+                    None => response,
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                crate::build_response(500, "Internal Server Error", "Failed to read the file")
+            }
+        }
+    }
+}
+
+/// ファイルサイズを読みやすくする (1024 単位)
+fn format_size(len: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if len < 1024 {
+        return len.to_string();
+    }
+    let mut size = len as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1}{}", size, UNITS[unit])
+}
+
+fn unix_secs(time: io::Result<SystemTime>) -> Option<u64> {
+    Some(time.ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+/// ディレクトリの一覧を HTML で返す (ディレクトリが先、それぞれ名前順)
+///
+/// `top` は配信の最上位のディレクトリか (そこからは上に戻るリンクを出さない)。
+fn listing(request: &Request, dir: &Path, top: bool) -> io::Result<Response> {
+    let mut base = request.path.clone();
+    if !base.ends_with('/') {
+        base.push('/');
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let meta = entry.metadata()?;
+        entries.push((!meta.is_dir(), name, meta));
+    }
+    entries.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+    let text = |s: &str| JsonValue::String(s.to_string());
+    let entries = entries
+        .into_iter()
+        .map(|(is_file, name, meta)| {
+            let name = if is_file { name } else { format!("{}/", name) };
+            let size = if is_file { format_size(meta.len()) } else { "-".to_string() };
+            let modified = unix_secs(meta.modified()).map_or(String::new(), http_date);
+            JsonValue::Object(HashMap::from([
+                ("href".to_string(), text(&crate::url::percent_encode_path(&format!("{}{}", base, name)))),
+                ("name".to_string(), text(&name)),
+                ("size".to_string(), text(&size)),
+                ("modified".to_string(), text(&modified)),
+            ]))
+        })
+        .collect();
+
+    let parent = match base.trim_end_matches('/').rsplit_once('/') {
+        Some((parent, _)) if !top => text(&crate::url::percent_encode_path(&format!("{}/", parent))),
+        _ => JsonValue::Null,
+    };
+    let context = JsonValue::Object(HashMap::from([
+        ("path".to_string(), text(&base)),
+        ("parent".to_string(), parent),
+        ("entries".to_string(), JsonValue::Array(entries)),
+    ]));
+    Ok(Response::render(include_str!("../templates/listing.html"), &context))
+}
+
+/// 複数の範囲を `multipart/byteranges` にまとめる
+fn multipart(file: &mut File, ranges: &[Range<u64>], len: u64, content_type: &str) -> io::Result<Response> {
+    let boundary: String = random_bytes::<12>().iter().map(|b| format!("{:02x}", b)).collect();
+    let mut body = Vec::new();
+    for range in ranges {
+        let head = format!(
+            "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+            boundary,
+            content_type,
+            range::content_range(range, len)
+        );
+        body.extend_from_slice(head.as_bytes());
+        body.extend_from_slice(&read_range(file, range)?);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+    Ok(Response::new(206, "Partial Content")
+        .with_header("Content-Type", &format!("multipart/byteranges; boundary={}", boundary))
+        .with_bytes(body))
+}
+
+impl Middleware for StaticFiles {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let Some(path) = self.resolve(&request.path) else {
+            return next(request);
+        };
+        if request.method != "GET" {
+            return crate::build_response(405, "Method Not Allowed", "Static files only support GET")
+                .with_header("Allow", "GET");
+        }
+        if path.is_dir() {
+            let top = request.path.trim_end_matches('/') == self.prefix;
+            return listing(request, &path, top).unwrap_or_else(|e| {
+                eprintln!("Failed to list {}: {}", path.display(), e);
+                crate::build_response(500, "Internal Server Error", "Failed to list the directory")
+            });
+        }
+        self.serve(request, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditional::Conditional;
+    use crate::middleware::Pipeline;
+    use std::env;
+
+    /// 0123456789 の入ったファイルを置いたディレクトリで配信する
+    fn setup(name: &str) -> (Pipeline, PathBuf) {
+        setup_with(name, StaticFiles::new("/static", env::temp_dir()))
+    }
+
+    fn setup_with(name: &str, files: StaticFiles) -> (Pipeline, PathBuf) {
+        let root = env::temp_dir().join(format!("http-static-{}-{}", name, std::process::id()));
+        fs::create_dir_all(root.join("media")).unwrap();
+        fs::write(root.join("media/digits.txt"), "0123456789").unwrap();
+        fs::write(root.join("index.html"), "<h1>home</h1>").unwrap();
+
+        let pipeline = Pipeline::new(|_: &Request| crate::build_response(404, "Not Found", "next"))
+            .with(Conditional)
+            .with(StaticFiles { root: root.clone(), ..files });
+        (pipeline, root)
+    }
+
+    fn get(pipeline: &Pipeline, path: &str, range: Option<&str>) -> Response {
+        let header = range.map_or(String::new(), |r| format!("Range: {}\r\n", r));
+        let raw = format!("GET {} HTTP/1.1\r\n{}\r\n", path, header);
+        pipeline.handle(&mut Request::parse(&raw).unwrap())
+    }
+
+    #[test]
+    fn test_serves_whole_files() {
+        let (pipeline, root) = setup("whole");
+
+        let response = get(&pipeline, "/static/media/digits.txt", None);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"0123456789");
+        assert_eq!(response.headers["Content-Type"], "text/plain; charset=utf-8");
+        assert_eq!(response.headers["Accept-Ranges"], "bytes");
+        assert_eq!(response.headers["Cache-Control"], DEFAULT_CACHE_CONTROL);
+        assert!(response.headers["Last-Modified"].ends_with(" GMT"));
+
+        // ディレクトリは index.html
+        assert_eq!(get(&pipeline, "/static/", None).body, b"<h1>home</h1>");
+
+        assert_eq!(get(&pipeline, "/static/missing.txt", None).status_code, 404);
+        // .. はパースの時点で解決されるので root の外には出られない
+        assert_eq!(get(&pipeline, "/static/../etc/passwd", None).body, b"next");
+        assert_eq!(get(&pipeline, "/static/media/../index.html", None).body, b"<h1>home</h1>");
+        assert_eq!(get(&pipeline, "/other", None).body, b"next");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_single_range() {
+        let (pipeline, root) = setup("single");
+
+        let response = get(&pipeline, "/static/media/digits.txt", Some("bytes=2-4"));
+        assert_eq!(response.status_code, 206);
+        assert_eq!(response.body, b"234");
+        assert_eq!(response.headers["Content-Range"], "bytes 2-4/10");
+        assert_eq!(response.headers["Content-Length"], "3");
+
+        let response = get(&pipeline, "/static/media/digits.txt", Some("bytes=-3"));
+        assert_eq!(response.body, b"789");
+
+        // 書式の誤った Range は無視して全体を返す
+        let response = get(&pipeline, "/static/media/digits.txt", Some("bytes=x"));
+        assert_eq!(response.status_code, 200);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_multiple_ranges() {
+        let (pipeline, root) = setup("multi");
+
+        let response = get(&pipeline, "/static/media/digits.txt", Some("bytes=0-1,8-"));
+        assert_eq!(response.status_code, 206);
+        let content_type = &response.headers["Content-Type"];
+        let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+        let body = String::from_utf8(response.body.clone()).unwrap();
+        let expected = format!(
+            "--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+             --{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(body, expected);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_unsatisfiable_range() {
+        let (pipeline, root) = setup("416");
+
+        let response = get(&pipeline, "/static/media/digits.txt", Some("bytes=10-"));
+        assert_eq!(response.status_code, 416);
+        assert_eq!(response.headers["Content-Range"], "bytes */10");
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        let files = StaticFiles::new("/static", env::temp_dir()).with_cache_control("public, max-age=60");
+        let (pipeline, root) = setup_with("conditional", files);
+
+        let response = get(&pipeline, "/static/media/digits.txt", None);
+        assert_eq!(response.headers["Cache-Control"], "public, max-age=60");
+        let tag = response.headers["ETag"].clone();
+        let modified = response.headers["Last-Modified"].clone();
+
+        let raw = format!("GET /static/media/digits.txt HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n", tag);
+        let response = pipeline.handle(&mut Request::parse(&raw).unwrap());
+        assert_eq!(response.status_code, 304);
+        assert_eq!(response.headers["Cache-Control"], "public, max-age=60");
+
+        let raw = format!("GET /static/media/digits.txt HTTP/1.1\r\nIf-Modified-Since: {}\r\n\r\n", modified);
+        assert_eq!(pipeline.handle(&mut Request::parse(&raw).unwrap()).status_code, 304);
+
+        // 内容が変われば ETag も変わる
+        fs::write(root.join("media/digits.txt"), "9876543210").unwrap();
+        let raw = format!("GET /static/media/digits.txt HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n", tag);
+        assert_eq!(pipeline.handle(&mut Request::parse(&raw).unwrap()).status_code, 200);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_directory_listing() {
+        let (pipeline, root) = setup_with("autoindex", StaticFiles::new("/static", env::temp_dir()).with_autoindex(true));
+        fs::create_dir_all(root.join("media/sub dir")).unwrap();
+        fs::write(root.join("media/<b>&.txt"), "x".repeat(2048)).unwrap();
+        fs::write(root.join("media/.hidden"), "").unwrap();
+
+        let response = get(&pipeline, "/static/media", None);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.headers["Content-Type"], "text/html; charset=utf-8");
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("<title>Index of /static/media/</title>"));
+        assert!(body.contains(r#"<a href="/static/">../</a>"#));
+        assert!(body.contains(r#"<a href="/static/media/sub%20dir/">sub dir/</a>"#));
+        assert!(body.contains(r#"<a href="/static/media/%3Cb%3E%26.txt">&lt;b&gt;&amp;.txt</a></td><td>2.0K</td>"#));
+        assert!(body.contains("<td>10</td>"));
+        assert!(!body.contains(".hidden"));
+        // ディレクトリが先
+        assert!(body.find("sub dir/").unwrap() < body.find("digits.txt").unwrap());
+
+        // index.html があればそちらを返す
+        assert_eq!(get(&pipeline, "/static/", None).body, b"<h1>home</h1>");
+        fs::remove_file(root.join("index.html")).unwrap();
+        let body = String::from_utf8(get(&pipeline, "/static/", None).body).unwrap();
+        assert!(body.contains("media/"));
+        assert!(!body.contains("../"));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_directory_without_index_is_not_found() {
+        let (pipeline, root) = setup("noindex");
+        assert_eq!(get(&pipeline, "/static/media/", None).status_code, 404);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0");
+        assert_eq!(format_size(1023), "1023");
+        assert_eq!(format_size(1536), "1.5K");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0M");
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(content_type(Path::new("a/movie.MP4")), "video/mp4");
+        assert_eq!(content_type(Path::new("index.html")), "text/html; charset=utf-8");
+        assert_eq!(content_type(Path::new("blob")), "application/octet-stream");
+    }
+}
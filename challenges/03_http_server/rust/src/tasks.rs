@@ -0,0 +1,383 @@
+//! TODO の REST API (`/tasks`)
+//!
+//! 05_cli_tool のタスクストアをそのまま使うので、`todo` コマンドと同じファイルを読み書きする。
+//! タスクの JSON 表現も CLI の `export --format json` と同じ。
+//!
+//! | メソッド | パス          | 内容                                                    |
+//! |----------|---------------|---------------------------------------------------------|
+//! | GET      | `/tasks`      | `{"updated": 秒, "tasks": [...]}` (`todo sync` の文書) |
+//! | PUT      | `/tasks`      | 同じ形の文書で一覧を置き換える (`todo sync` の送信)    |
+//! | POST     | `/tasks`      | `{"description": "...", "parent": 1}` で追加 (201)      |
+//! | GET      | `/tasks/:id`  | 1 件を返す                                              |
+//! | PATCH    | `/tasks/:id`  | `description` / `done` を変更する                       |
+//! | DELETE   | `/tasks/:id`  | 削除する (204。サブタスクがあれば 409)                  |
+//!
+//! エラーは `{"error": "..."}` で返す。
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use cli_tool::formats::task_to_json;
+use cli_tool::store::{self, TaskStore};
+use cli_tool::sync::Document;
+use cli_tool::{date, Task};
+use json_parser::JsonValue;
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::urls::url_for;
+use crate::Response;
+
+/// エラーの JSON レスポンス
+fn error(status_code: u16, status_text: &str, message: &str) -> Response {
+    let body = JsonValue::Object(HashMap::from([(
+        "error".to_string(),
+        JsonValue::String(message.to_string()),
+    )]));
+    Response::json(&body).with_status(status_code, status_text)
+}
+
+fn not_found(id: usize) -> Response {
+    error(404, "Not Found", &format!("task {} not found", id))
+}
+
+/// リクエストのボディを JSON のオブジェクトとして読む
+fn object(request: &Request) -> Result<HashMap<String, JsonValue>, Response> {
+    match request.json() {
+        Ok(JsonValue::Object(map)) => Ok(map),
+        Ok(_) => Err(error(400, "Bad Request", "expected a JSON object")),
+        Err(e) => {
+            let (code, text) = e.status().unwrap_or((400, "Bad Request"));
+            Err(error(code, text, &e.to_string()))
+        }
+    }
+}
+
+struct State {
+    store: Box<dyn TaskStore + Send>,
+    /// 最後に書き込んだ時刻 (UNIX 秒。起動後まだ書き込んでいなければ 0)
+    updated: u64,
+}
+
+/// `/tasks` 以下を受け持つミドルウェア (それ以外のパスは次に渡す)
+pub struct TaskApi {
+    state: Mutex<State>,
+}
+
+impl TaskApi {
+    pub fn new(store: impl TaskStore + Send + 'static) -> Self {
+        TaskApi {
+            state: Mutex::new(State {
+                store: Box::new(store),
+                updated: 0,
+            }),
+        }
+    }
+
+    /// パスとメソッドで振り分ける
+    fn route(&self, request: &Request) -> Option<Response> {
+        let rest = request.path.strip_prefix("/tasks")?;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let result = match (request.method.as_str(), rest) {
+            ("GET", "" | "/") => state.document(),
+            ("PUT", "" | "/") => state.replace(request),
+            ("POST", "" | "/") => state.create(request),
+            (_, "" | "/") => Ok(method_not_allowed("GET, PUT, POST")),
+            (method, id) => {
+                let Some(id) = id.strip_prefix('/').and_then(|id| id.parse().ok()) else {
+                    return Some(error(404, "Not Found", &format!("Path '{}' not found", request.path)));
+                };
+                match method {
+                    "GET" => state.show(id),
+                    "PATCH" => state.update(id, request),
+                    "DELETE" => state.delete(id),
+                    _ => Ok(method_not_allowed("GET, PATCH, DELETE")),
+                }
+            }
+        };
+        Some(result.unwrap_or_else(|e| error(500, "Internal Server Error", &e)))
+    }
+}
+
+fn method_not_allowed(allow: &str) -> Response {
+    error(405, "Method Not Allowed", "method not allowed").with_header("Allow", allow)
+}
+
+impl State {
+    /// 書き込んだ時刻を記録して保存する
+    fn save(&mut self, tasks: &[Task], updated: u64) -> Result<(), String> {
+        self.store.save(tasks)?;
+        self.updated = updated;
+        Ok(())
+    }
+
+    fn document(&self) -> Result<Response, String> {
+        let document = Document {
+            updated: self.updated,
+            tasks: self.store.load()?,
+        };
+        Ok(Response::json(&document.to_json()))
+    }
+
+    fn replace(&mut self, request: &Request) -> Result<Response, String> {
+        let Ok(text) = request.body_str() else {
+            return Ok(error(400, "Bad Request", "body is not valid UTF-8"));
+        };
+        let document = match Document::parse(text) {
+            Ok(document) => document,
+            Err(e) => return Ok(error(400, "Bad Request", &e)),
+        };
+        let updated = if document.updated == 0 { date::now() } else { document.updated };
+        self.save(&document.tasks, updated)?;
+        self.document()
+    }
+
+    fn create(&mut self, request: &Request) -> Result<Response, String> {
+        let body = match object(request) {
+            Ok(body) => body,
+            Err(response) => return Ok(response),
+        };
+        let description = match body.get("description") {
+            Some(JsonValue::String(s)) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => return Ok(error(400, "Bad Request", "'description' must be a non-empty string")),
+        };
+
+        let mut tasks = self.store.load()?;
+        let parent = match body.get("parent") {
+            None | Some(JsonValue::Null) => None,
+            Some(JsonValue::Number(n)) if tasks.iter().any(|t| t.id as f64 == *n) => Some(*n as usize),
+            Some(_) => return Ok(error(400, "Bad Request", "'parent' must be the ID of an existing task")),
+        };
+
+        // 時間記録に残っている ID も使わない (CLI の add と同じ)
+        let mut used: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+        used.extend(self.store.load_entries()?.iter().map(|e| e.task));
+        let task = Task {
+            id: store::allocate_ids(&used, 1)[0],
+            description,
+            done: false,
+            parent,
+            created: Some(date::today()),
+            completed: None,
+        };
+        tasks.push(task.clone());
+        self.save(&tasks, date::now())?;
+
+        Ok(Response::json(&task_to_json(&task))
+            .with_status(201, "Created")
+            .with_header("Location", &url_for("task", &[&task.id.to_string()])))
+    }
+
+    fn show(&self, id: usize) -> Result<Response, String> {
+        Ok(match self.store.load()?.iter().find(|t| t.id == id) {
+            Some(task) => Response::json(&task_to_json(task)),
+            None => not_found(id),
+        })
+    }
+
+    fn update(&mut self, id: usize, request: &Request) -> Result<Response, String> {
+        let body = match object(request) {
+            Ok(body) => body,
+            Err(response) => return Ok(response),
+        };
+        let mut tasks = self.store.load()?;
+        let Some(task) = tasks.iter_mut().find(|t| t.id == id) else {
+            return Ok(not_found(id));
+        };
+
+        match body.get("description") {
+            None => {}
+            Some(JsonValue::String(s)) if !s.trim().is_empty() => task.description = s.trim().to_string(),
+            Some(_) => return Ok(error(400, "Bad Request", "'description' must be a non-empty string")),
+        }
+        match body.get("done") {
+            None => {}
+            Some(JsonValue::Bool(done)) if *done != task.done => {
+                task.done = *done;
+                task.completed = done.then(date::today);
+            }
+            Some(JsonValue::Bool(_)) => {}
+            Some(_) => return Ok(error(400, "Bad Request", "'done' must be a boolean")),
+        }
+
+        let task = task.clone();
+        self.save(&tasks, date::now())?;
+        Ok(Response::json(&task_to_json(&task)))
+    }
+
+    fn delete(&mut self, id: usize) -> Result<Response, String> {
+        let mut tasks = self.store.load()?;
+        if !tasks.iter().any(|t| t.id == id) {
+            return Ok(not_found(id));
+        }
+        let children = tasks.iter().filter(|t| t.parent == Some(id)).count();
+        if children > 0 {
+            return Ok(error(
+                409,
+                "Conflict",
+                &format!("task {} has {} subtask(s); delete them first", id, children),
+            ));
+        }
+
+        tasks.retain(|t| t.id != id);
+        self.save(&tasks, date::now())?;
+        Ok(Response::new(204, "No Content").with_body(""))
+    }
+}
+
+impl Middleware for TaskApi {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        match self.route(request) {
+            Some(response) => response,
+            None => next(request),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::{self, Config};
+    use crate::middleware::Pipeline;
+    use cli_tool::formats::tasks_to_json;
+    use cli_tool::http;
+    use cli_tool::output::{Output, OutputFormat};
+    use cli_tool::store::MemoryStore;
+    use std::env;
+    use std::fs;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// API をつないだサーバーを空いているポートで起動し、`http://127.0.0.1:PORT` を返す
+    fn start(store: impl TaskStore + Send + 'static) -> String {
+        let pipeline = Arc::new(
+            Pipeline::new(|_: &Request| crate::build_response(404, "Not Found", "")).with(TaskApi::new(store)),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let pipeline = Arc::clone(&pipeline);
+                thread::spawn(move || connection::serve(stream, &Config::default(), |req| pipeline.handle(req)));
+            }
+        });
+        url
+    }
+
+    fn send(method: &str, url: &str, body: Option<&str>) -> (u16, JsonValue) {
+        let response = http::request(method, url, body).unwrap();
+        let value = match response.body.as_str() {
+            "" => JsonValue::Null,
+            body => json_parser::parse(body).unwrap(),
+        };
+        (response.status, value)
+    }
+
+    fn field<'a>(value: &'a JsonValue, key: &str) -> &'a JsonValue {
+        match value {
+            JsonValue::Object(map) => &map[key],
+            _ => panic!("not an object: {}", value),
+        }
+    }
+
+    fn tasks_of(value: &JsonValue) -> Vec<String> {
+        let JsonValue::Array(tasks) = field(value, "tasks") else {
+            panic!("tasks is not an array");
+        };
+        tasks.iter().map(|t| field(t, "description").to_string()).collect()
+    }
+
+    #[test]
+    fn test_crud() {
+        let base = start(MemoryStore::default());
+        let tasks = format!("{}/tasks", base);
+
+        let (status, value) = send("GET", &tasks, None);
+        assert_eq!(status, 200);
+        assert_eq!(field(&value, "updated"), &JsonValue::Number(0.0));
+        assert!(tasks_of(&value).is_empty());
+
+        let (status, created) = send("POST", &tasks, Some(r#"{"description": "Buy milk"}"#));
+        assert_eq!(status, 201);
+        assert_eq!(field(&created, "id"), &JsonValue::Number(1.0));
+        assert_eq!(field(&created, "created"), &JsonValue::String(date::today()));
+
+        let (status, child) = send("POST", &tasks, Some(r#"{"description": "Skim", "parent": 1}"#));
+        assert_eq!(status, 201);
+        assert_eq!(field(&child, "parent"), &JsonValue::Number(1.0));
+
+        let (status, value) = send("GET", &tasks, None);
+        assert_eq!(status, 200);
+        assert_eq!(tasks_of(&value), [r#""Buy milk""#, r#""Skim""#]);
+        assert_ne!(field(&value, "updated"), &JsonValue::Number(0.0));
+
+        let (status, updated) = send("PATCH", &format!("{}/2", tasks), Some(r#"{"done": true}"#));
+        assert_eq!(status, 200);
+        assert_eq!(field(&updated, "done"), &JsonValue::Bool(true));
+        assert_eq!(field(&updated, "completed"), &JsonValue::String(date::today()));
+        assert_eq!(send("GET", &format!("{}/2", tasks), None).1, updated);
+
+        // サブタスクが残っている間は消せない
+        assert_eq!(send("DELETE", &format!("{}/1", tasks), None).0, 409);
+        assert_eq!(send("DELETE", &format!("{}/2", tasks), None), (204, JsonValue::Null));
+        assert_eq!(send("DELETE", &format!("{}/1", tasks), None).0, 204);
+        assert!(tasks_of(&send("GET", &tasks, None).1).is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let base = start(MemoryStore::default());
+        let tasks = format!("{}/tasks", base);
+
+        let (status, value) = send("GET", &format!("{}/9", tasks), None);
+        assert_eq!(status, 404);
+        assert_eq!(field(&value, "error"), &JsonValue::String("task 9 not found".to_string()));
+
+        assert_eq!(send("GET", &format!("{}/abc", tasks), None).0, 404);
+        assert_eq!(send("PATCH", &format!("{}/9", tasks), Some("{}")).0, 404);
+        assert_eq!(send("POST", &tasks, Some("{")).0, 400);
+        assert_eq!(send("POST", &tasks, Some("[]")).0, 400);
+        assert_eq!(send("POST", &tasks, Some(r#"{"description": ""}"#)).0, 400);
+        assert_eq!(send("POST", &tasks, Some(r#"{"description": "x", "parent": 5}"#)).0, 400);
+        assert_eq!(send("DELETE", &tasks, None).0, 405);
+
+        send("POST", &tasks, Some(r#"{"description": "x"}"#));
+        assert_eq!(send("PATCH", &format!("{}/1", tasks), Some(r#"{"done": "yes"}"#)).0, 400);
+
+        // /tasks 以外は次のハンドラーに渡す
+        assert_eq!(http::request("GET", &format!("{}/tasksx", base), None).unwrap().status, 404);
+        assert_eq!(http::request("GET", &base, None).unwrap().body, "");
+    }
+
+    #[test]
+    fn test_cli_sync_round_trip() {
+        // CLI の `todo sync` でサーバーと手元のファイルを同期する
+        let base = start(MemoryStore::default());
+        let tasks = format!("{}/tasks", base);
+        send("POST", &tasks, Some(r#"{"description": "From the API"}"#));
+
+        let dir = env::temp_dir().join(format!("http-tasks-sync-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file: PathBuf = dir.join("todo.txt");
+        fs::write(&file, "[ ] From the CLI id:5\n").unwrap();
+
+        let args: Vec<String> = ["sync", "--remote", &base, "--no-hooks", "-f", file.to_str().unwrap()]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = cli_tool::Config::parse(&args).unwrap();
+        let mut store = store::TextFileStore::new(file.clone());
+        let mut out = Output::capture(OutputFormat::Text);
+        cli_tool::execute(&config, &mut store, &mut out).unwrap();
+
+        let local = store.load().unwrap();
+        let (_, remote) = send("GET", &tasks, None);
+        assert_eq!(tasks_of(&remote), [r#""From the CLI""#, r#""From the API""#]);
+        assert_eq!(tasks_to_json(&local).to_string(), field(&remote, "tasks").to_string());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
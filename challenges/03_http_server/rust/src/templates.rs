@@ -0,0 +1,304 @@
+//! 小さなテンプレートエンジン
+//!
+//! 値は JSON ([`JsonValue`]) で渡す。書けるのは次の 4 つだけ。
+//!
+//! ```text
+//! {{ user.name }}              値を HTML エスケープして埋め込む
+//! {{ html | safe }}            エスケープせずに埋め込む
+//! {% for task in tasks %}...{% endfor %}    配列の要素ごとに繰り返す (loop.index は 1 から)
+//! {% if user %}...{% else %}...{% endif %}  値が空でなければ (null / false / 0 / "" / [] / {} 以外)
+//! ```
+//!
+//! 存在しない値は空文字列 (`if` では偽) として扱う。
+
+use std::collections::HashMap;
+
+use json_parser::JsonValue;
+
+/// HTML の特殊文字をエスケープする
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Var { path: String, safe: bool },
+    For { name: String, path: String, body: Vec<Node> },
+    If { path: String, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+/// パース済みのテンプレート
+#[derive(Debug)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// テンプレートの字句 (`{{ }}` / `{% %}` の中身は前後の空白を除く)
+enum Token<'a> {
+    Text(&'a str),
+    Var(&'a str),
+    Tag(&'a str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(start) = [rest.find("{{"), rest.find("{%")].into_iter().flatten().min() {
+        let (open, close) = if rest[start..].starts_with("{{") { ("{{", "}}") } else { ("{%", "%}") };
+        if start > 0 {
+            tokens.push(Token::Text(&rest[..start]));
+        }
+        let inner_start = start + open.len();
+        let end = rest[inner_start..]
+            .find(close)
+            .ok_or_else(|| format!("Template: unclosed '{}'", open))?;
+        let inner = rest[inner_start..inner_start + end].trim();
+        tokens.push(if open == "{{" { Token::Var(inner) } else { Token::Tag(inner) });
+        rest = &rest[inner_start + end + close.len()..];
+    }
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    Ok(tokens)
+}
+
+/// `end` のどれかのタグが来るまでパースし、(ノード, 終わりのタグ) を返す
+fn parse_block<'a>(
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    end: &[&str],
+) -> Result<(Vec<Node>, Option<&'a str>), String> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text.to_string())),
+            Token::Var(inner) => {
+                let (path, safe) = match inner.split_once('|') {
+                    None => (inner, false),
+                    Some((path, filter)) if filter.trim() == "safe" => (path.trim(), true),
+                    Some((_, filter)) => return Err(format!("Template: unknown filter '{}'", filter.trim())),
+                };
+                nodes.push(Node::Var {
+                    path: path.to_string(),
+                    safe,
+                });
+            }
+            Token::Tag(tag) => {
+                let words: Vec<&str> = tag.split_whitespace().collect();
+                match words.as_slice() {
+                    [word] if end.contains(word) => return Ok((nodes, Some(tag))),
+                    ["for", name, "in", path] => {
+                        let (body, _) = expect_end(tokens, &["endfor"], "for")?;
+                        nodes.push(Node::For {
+                            name: name.to_string(),
+                            path: path.to_string(),
+                            body,
+                        });
+                    }
+                    ["if", path] => {
+                        let (then, closing) = expect_end(tokens, &["else", "endif"], "if")?;
+                        let otherwise = if closing == "else" {
+                            expect_end(tokens, &["endif"], "if")?.0
+                        } else {
+                            Vec::new()
+                        };
+                        nodes.push(Node::If {
+                            path: path.to_string(),
+                            then,
+                            otherwise,
+                        });
+                    }
+                    _ => return Err(format!("Template: unknown tag '{{% {} %}}'", tag)),
+                }
+            }
+        }
+    }
+    Ok((nodes, None))
+}
+
+/// 閉じタグまで読む (無ければエラー)
+fn expect_end<'a>(
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    end: &[&str],
+    opened: &str,
+) -> Result<(Vec<Node>, &'a str), String> {
+    match parse_block(tokens, end)? {
+        (nodes, Some(closing)) => Ok((nodes, closing)),
+        (_, None) => Err(format!("Template: '{}' is not closed", opened)),
+    }
+}
+
+/// 空でない値か
+fn truthy(value: Option<&JsonValue>) -> bool {
+    match value {
+        None | Some(JsonValue::Null) | Some(JsonValue::Bool(false)) => false,
+        Some(JsonValue::Number(n)) => *n != 0.0,
+        Some(JsonValue::String(s)) => !s.is_empty(),
+        Some(JsonValue::Array(items)) => !items.is_empty(),
+        Some(JsonValue::Object(map)) => !map.is_empty(),
+        Some(JsonValue::Bool(true)) => true,
+    }
+}
+
+/// 変数のスコープ (for の変数を外側より優先する)
+struct Scope<'a> {
+    locals: Vec<HashMap<String, JsonValue>>,
+    root: &'a JsonValue,
+}
+
+impl Scope<'_> {
+    /// `a.b.c` を引く
+    fn lookup(&self, path: &str) -> Option<&JsonValue> {
+        let mut parts = path.split('.');
+        let first = parts.next()?;
+        let mut value = self
+            .locals
+            .iter()
+            .rev()
+            .find_map(|locals| locals.get(first))
+            .or_else(|| match self.root {
+                JsonValue::Object(map) => map.get(first),
+                _ => None,
+            })?;
+        for part in parts {
+            value = match value {
+                JsonValue::Object(map) => map.get(part)?,
+                JsonValue::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &mut Scope, out: &mut String) -> Result<(), String> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var { path, safe } => {
+                let text = match scope.lookup(path) {
+                    None | Some(JsonValue::Null) => String::new(),
+                    Some(JsonValue::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                };
+                out.push_str(&if *safe { text } else { escape_html(&text) });
+            }
+            Node::For { name, path, body } => {
+                let items = match scope.lookup(path) {
+                    None | Some(JsonValue::Null) => Vec::new(),
+                    Some(JsonValue::Array(items)) => items.clone(),
+                    Some(_) => return Err(format!("Template: '{}' is not an array", path)),
+                };
+                for (i, item) in items.into_iter().enumerate() {
+                    let looped = JsonValue::Object(HashMap::from([(
+                        "index".to_string(),
+                        JsonValue::Number((i + 1) as f64),
+                    )]));
+                    scope
+                        .locals
+                        .push(HashMap::from([(name.clone(), item), ("loop".to_string(), looped)]));
+                    let result = render_nodes(body, scope, out);
+                    scope.locals.pop();
+                    result?;
+                }
+            }
+            Node::If { path, then, otherwise } => {
+                let branch = if truthy(scope.lookup(path)) { then } else { otherwise };
+                render_nodes(branch, scope, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut tokens = tokenize(source)?.into_iter();
+        match parse_block(&mut tokens, &[])? {
+            (nodes, None) => Ok(Template { nodes }),
+            (_, Some(tag)) => Err(format!("Template: unexpected '{{% {} %}}'", tag)),
+        }
+    }
+
+    /// `context` (オブジェクト) の値を埋め込む
+    pub fn render(&self, context: &JsonValue) -> Result<String, String> {
+        let mut out = String::new();
+        let mut scope = Scope {
+            locals: Vec::new(),
+            root: context,
+        };
+        render_nodes(&self.nodes, &mut scope, &mut out)?;
+        Ok(out)
+    }
+}
+
+/// パースして埋め込む
+pub fn render(source: &str, context: &JsonValue) -> Result<String, String> {
+    Template::parse(source)?.render(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(json: &str) -> JsonValue {
+        json_parser::parse(json).unwrap()
+    }
+
+    #[test]
+    fn test_variables_are_escaped() {
+        let ctx = context(r#"{"name": "<b>Tom & Jerry</b>", "n": 3, "user": {"id": 7}}"#);
+        assert_eq!(
+            render("Hi {{ name }}! {{n}} {{ user.id }}", &ctx).unwrap(),
+            "Hi &lt;b&gt;Tom &amp; Jerry&lt;/b&gt;! 3 7"
+        );
+        assert_eq!(render("{{ name | safe }}", &ctx).unwrap(), "<b>Tom & Jerry</b>");
+        assert_eq!(render("[{{ missing }}][{{ user.name }}]", &ctx).unwrap(), "[][]");
+        // タグでない波かっこはそのまま
+        assert_eq!(render("{ a } {x}", &ctx).unwrap(), "{ a } {x}");
+    }
+
+    #[test]
+    fn test_for_loop() {
+        let ctx = context(r#"{"tasks": [{"title": "a"}, {"title": "b"}], "title": "outer"}"#);
+        let source = "{% for task in tasks %}{{ loop.index }}:{{ task.title }} {% endfor %}{{ title }}";
+        assert_eq!(render(source, &ctx).unwrap(), "1:a 2:b outer");
+        assert_eq!(render("{% for x in none %}x{% endfor %}", &ctx).unwrap(), "");
+        assert!(render("{% for x in title %}{% endfor %}", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_if_else() {
+        let ctx = context(r#"{"user": {"name": "alice"}, "empty": [], "zero": 0}"#);
+        let source = "{% if user %}Hi {{ user.name }}{% else %}Log in{% endif %}";
+        assert_eq!(render(source, &ctx).unwrap(), "Hi alice");
+        assert_eq!(render(source, &context("{}")).unwrap(), "Log in");
+        assert_eq!(render("{% if empty %}x{% endif %}{% if zero %}y{% endif %}", &ctx).unwrap(), "");
+
+        // 入れ子
+        let ctx = context(r#"{"items": [1, 0, 2]}"#);
+        let source = "{% for i in items %}{% if i %}{{ i }}{% else %}-{% endif %}{% endfor %}";
+        assert_eq!(render(source, &ctx).unwrap(), "1-2");
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let ctx = context("{}");
+        assert!(render("{{ name ", &ctx).is_err());
+        assert!(render("{% if x %}never closed", &ctx).is_err());
+        assert!(render("{% endfor %}", &ctx).is_err());
+        assert!(render("{% while x %}{% endwhile %}", &ctx).is_err());
+        assert!(render("{{ x | upper }}", &ctx).is_err());
+    }
+}
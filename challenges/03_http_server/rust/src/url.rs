@@ -0,0 +1,127 @@
+//! リクエストのパスの正規化
+//!
+//! ルーティングの前に `%XX` をデコードし、`//` をまとめ、`.` と `..` を解決する。
+//! デコードしてから解決するので、`%2e%2e%2f` のように書いても `..` のチェックを
+//! すり抜けることはできない。ルートより上に出るパスは拒否する。
+
+/// `%XX` をデコードする (不正な `%`、NUL、UTF-8 にならないバイト列はエラー)
+pub fn percent_decode(text: &str) -> Result<String, String> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = bytes
+                .get(i + 1..i + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| format!("invalid percent-encoding in {}", text))?;
+            if byte == 0 {
+                return Err(format!("NUL byte in {}", text));
+            }
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| format!("{} is not valid UTF-8 after decoding", text))
+}
+
+/// パスを `%XX` にエンコードする (`/` と英数字、`-._~` 以外)
+pub fn percent_encode_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'/' | b'-' | b'.' | b'_' | b'~') {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    out
+}
+
+/// `//` をまとめ、`.` と `..` を解決する (末尾の `/` は残す)
+pub fn normalize_path(path: &str) -> Result<String, String> {
+    if !path.starts_with('/') {
+        return Err(format!("path must start with '/': {}", path));
+    }
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop().ok_or_else(|| format!("path escapes the root: {}", path))?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+
+    let mut normalized = format!("/{}", segments.join("/"));
+    let last = path.rsplit('/').next().unwrap_or("");
+    let is_dir = path.ends_with('/') || last == "." || last == "..";
+    if is_dir && !segments.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+/// リクエストターゲットを (正規化したパス, クエリ) に分ける
+pub fn parse_target(target: &str) -> Result<(String, Option<String>), String> {
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+    Ok((normalize_path(&percent_decode(path)?)?, query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/hello/%E4%B8%96%E7%95%8C").unwrap(), "/hello/世界");
+        assert_eq!(percent_decode("/a%20b%2fc").unwrap(), "/a b/c");
+        assert_eq!(percent_decode("/plain").unwrap(), "/plain");
+
+        assert!(percent_decode("/%").is_err());
+        assert!(percent_decode("/%4").is_err());
+        assert!(percent_decode("/%zz").is_err());
+        assert!(percent_decode("/%00").is_err());
+        assert!(percent_decode("/%FF").is_err());
+    }
+
+    #[test]
+    fn test_percent_encode_path() {
+        assert_eq!(percent_encode_path("/hello/世界"), "/hello/%E4%B8%96%E7%95%8C");
+        assert_eq!(percent_encode_path("/a b/c?d"), "/a%20b/c%3Fd");
+        assert_eq!(percent_decode(&percent_encode_path("/50% off")).unwrap(), "/50% off");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/").unwrap(), "/");
+        assert_eq!(normalize_path("//a///b").unwrap(), "/a/b");
+        assert_eq!(normalize_path("/a/./b/../c").unwrap(), "/a/c");
+        assert_eq!(normalize_path("/a/b/").unwrap(), "/a/b/");
+        assert_eq!(normalize_path("/a/b/..").unwrap(), "/a/");
+        assert_eq!(normalize_path("/a/..").unwrap(), "/");
+
+        assert!(normalize_path("/..").is_err());
+        assert!(normalize_path("/a/../../etc/passwd").is_err());
+        assert!(normalize_path("relative").is_err());
+    }
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            parse_target("/static/%2e%2e/secret?x=%41").unwrap(),
+            ("/secret".to_string(), Some("x=%41".to_string()))
+        );
+        assert_eq!(parse_target("/").unwrap(), ("/".to_string(), None));
+        // デコード後の .. も解決してから判定する
+        assert!(parse_target("/%2e%2e%2fetc/passwd").is_err());
+    }
+}
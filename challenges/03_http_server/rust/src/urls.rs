@@ -0,0 +1,178 @@
+//! URL の組み立てと末尾の `/`
+//!
+//! ルートに名前を付けておき、`url_for("task", &["3"])` のようにパスを組み立てる。
+//! ハンドラーが `/tasks/3` のような文字列を手で書かずに済み、パスを変えるときも
+//! ここを直すだけでよい。パターンの `{name}` は 1 セグメント (`/` もエンコードする)、
+//! `{*name}` は `/` を含む残り全部に当てはまる。
+//!
+//! 末尾の `/` だけが違うパスは [`TrailingSlash`] の方針でリダイレクトする。
+
+use crate::middleware::{Middleware, Next};
+use crate::request::Request;
+use crate::url::percent_encode_path;
+use crate::Response;
+
+/// 名前付きのルート
+const ROUTES: [(&str, &str); 12] = [
+    ("index", "/"),
+    ("hello", "/hello/{name}"),
+    ("json", "/json"),
+    ("info", "/info"),
+    ("echo", "/echo"),
+    ("login", "/login"),
+    ("logout", "/logout"),
+    ("whoami", "/whoami"),
+    ("upload", "/upload"),
+    ("tasks", "/tasks"),
+    ("task", "/tasks/{id}"),
+    ("static", "/static/{*path}"),
+];
+
+/// 名前付きのルートのパスに `params` を順に埋め込む
+///
+/// 名前や引数の数が合わないのは呼び出し側の誤りなのでパニックする。
+pub fn url_for(name: &str, params: &[&str]) -> String {
+    let (_, pattern) = ROUTES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .unwrap_or_else(|| panic!("no route named {}", name));
+
+    let mut params = params.iter();
+    let mut url = String::new();
+    let mut rest = *pattern;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').expect("unclosed placeholder") + start;
+        url.push_str(&rest[..start]);
+        let value = params
+            .next()
+            .unwrap_or_else(|| panic!("route {} needs more parameters", name));
+        let encoded = percent_encode_path(value);
+        match rest[start + 1..end].starts_with('*') {
+            true => url.push_str(encoded.trim_start_matches('/')),
+            false => url.push_str(&encoded.replace('/', "%2F")),
+        }
+        rest = &rest[end + 1..];
+    }
+    url.push_str(rest);
+    assert!(params.next().is_none(), "route {} got too many parameters", name);
+    url
+}
+
+/// 末尾の `/` だけが違うパスの扱い
+///
+/// 404 になった GET / HEAD のうち、`/` を付けた (外した) パスなら見つかるものを
+/// 308 でそちらへリダイレクトする。見つかるかは内側をもう一度呼んで確かめる。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrailingSlash {
+    /// リダイレクトしない (`/a` と `/a/` は別のパス)
+    Strict,
+    /// `/a` を `/a/` へ
+    Add,
+    /// `/a/` を `/a` へ
+    Remove,
+}
+
+impl TrailingSlash {
+    /// `strict` / `add` / `remove`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        match text {
+            "strict" => Ok(TrailingSlash::Strict),
+            "add" => Ok(TrailingSlash::Add),
+            "remove" => Ok(TrailingSlash::Remove),
+            _ => Err(format!("Unknown trailing slash policy: {} (expected strict, add or remove)", text)),
+        }
+    }
+
+    /// リダイレクト先の候補
+    fn alternative(self, path: &str) -> Option<String> {
+        match self {
+            TrailingSlash::Add if !path.ends_with('/') => Some(format!("{}/", path)),
+            TrailingSlash::Remove if path.ends_with('/') && path != "/" => Some(path[..path.len() - 1].to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl Middleware for TrailingSlash {
+    fn handle(&self, request: &mut Request, next: Next) -> Response {
+        let response = next(request);
+        if response.status_code != 404 || !matches!(request.method.as_str(), "GET" | "HEAD") {
+            return response;
+        }
+        let Some(alternative) = self.alternative(&request.path) else {
+            return response;
+        };
+
+        let original = std::mem::replace(&mut request.path, alternative);
+        let found = next(request).status_code != 404;
+        let alternative = std::mem::replace(&mut request.path, original);
+        if !found {
+            return response;
+        }
+        let mut location = percent_encode_path(&alternative);
+        if let Some(query) = &request.query {
+            location.push('?');
+            location.push_str(query);
+        }
+        Response::redirect(&location, 308)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::Pipeline;
+    use std::panic;
+
+    #[test]
+    fn test_url_for() {
+        assert_eq!(url_for("index", &[]), "/");
+        assert_eq!(url_for("task", &["3"]), "/tasks/3");
+        assert_eq!(url_for("hello", &["a b/c"]), "/hello/a%20b%2Fc");
+        assert_eq!(url_for("static", &["media/clip 1.mp4"]), "/static/media/clip%201.mp4");
+
+        assert!(panic::catch_unwind(|| url_for("nope", &[])).is_err());
+        assert!(panic::catch_unwind(|| url_for("task", &[])).is_err());
+        assert!(panic::catch_unwind(|| url_for("task", &["1", "2"])).is_err());
+    }
+
+    fn pipeline(policy: TrailingSlash) -> Pipeline {
+        Pipeline::new(|request: &Request| match request.path.as_str() {
+            "/docs/" | "/about" | "/a b/" => crate::build_response(200, "OK", "found"),
+            _ => crate::build_response(404, "Not Found", "missing"),
+        })
+        .with(policy)
+    }
+
+    fn get(pipeline: &Pipeline, target: &str) -> Response {
+        let raw = format!("GET {} HTTP/1.1\r\n\r\n", target);
+        pipeline.handle(&mut Request::parse(&raw).unwrap())
+    }
+
+    #[test]
+    fn test_trailing_slash() {
+        let add = pipeline(TrailingSlash::Add);
+        let response = get(&add, "/docs?page=2");
+        assert_eq!(response.status_code, 308);
+        assert_eq!(response.headers["Location"], "/docs/?page=2");
+        assert_eq!(get(&add, "/a%20b").headers["Location"], "/a%20b/");
+        // どちらの形でも見つからなければ 404 のまま
+        assert_eq!(get(&add, "/missing").status_code, 404);
+        assert_eq!(get(&add, "/about/").status_code, 404);
+
+        let remove = pipeline(TrailingSlash::Remove);
+        assert_eq!(get(&remove, "/about/").headers["Location"], "/about");
+        assert_eq!(get(&remove, "/docs").status_code, 404);
+
+        let strict = pipeline(TrailingSlash::Strict);
+        assert_eq!(get(&strict, "/docs").status_code, 404);
+        assert_eq!(get(&strict, "/docs/").body, b"found");
+
+        // 副作用のあるメソッドはやり直さない
+        let raw = "POST /docs HTTP/1.1\r\n\r\n";
+        assert_eq!(add.handle(&mut Request::parse(raw).unwrap()).status_code, 404);
+
+        assert_eq!(TrailingSlash::parse("add").unwrap(), TrailingSlash::Add);
+        assert!(TrailingSlash::parse("always").is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Rust の値と [`JsonValue`] の相互変換
+//!
+//! serde の代わりに、ほかの課題のクレートが自分の型を JSON にしたり JSON から
+//! 読み込んだりするための小さなトレイト。コンテナは要素の型が実装していれば使える。
+
+use crate::JsonValue;
+
+/// JSON に変換できる型
+pub trait ToJson {
+    fn to_json(&self) -> JsonValue;
+}
+
+/// JSON から作れる型 (形が合わなければ理由を返す)
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonValue) -> Result<Self, String>;
+}
+
+/// エラーメッセージ用の型名
+fn kind(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "bool",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+impl ToJson for JsonValue {
+    fn to_json(&self) -> JsonValue {
+        self.clone()
+    }
+}
+
+impl FromJson for JsonValue {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        Ok(value.clone())
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Bool(*self)
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::Bool(b) => Ok(*b),
+            other => Err(format!("expected bool, found {}", kind(other))),
+        }
+    }
+}
+
+impl ToJson for f64 {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Number(*self)
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::Number(n) => Ok(*n),
+            other => Err(format!("expected number, found {}", kind(other))),
+        }
+    }
+}
+
+/// 整数は f64 で表せる範囲の整数だけを受け付ける
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl ToJson for $ty {
+                fn to_json(&self) -> JsonValue {
+                    JsonValue::Number(*self as f64)
+                }
+            }
+
+            impl FromJson for $ty {
+                fn from_json(value: &JsonValue) -> Result<Self, String> {
+                    let n = f64::from_json(value)?;
+                    if n.fract() != 0.0 || n < <$ty>::MIN as f64 || n > <$ty>::MAX as f64 {
+                        return Err(format!("{} is not a valid {}", n, stringify!($ty)));
+                    }
+                    Ok(n as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i32, i64, u32, u64, usize);
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.clone())
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::String(self.to_string())
+    }
+}
+
+impl FromJson for String {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::String(s) => Ok(s.clone()),
+            other => Err(format!("expected string, found {}", kind(other))),
+        }
+    }
+}
+
+/// None は null
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Some(value) => value.to_json(),
+            None => JsonValue::Null,
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        match value {
+            JsonValue::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonValue {
+        JsonValue::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonValue {
+        self.as_slice().to_json()
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonValue) -> Result<Self, String> {
+        from_json_array(value)?.collect()
+    }
+}
+
+/// 配列の要素を順に変換する (列を作るコンテナ向け。エラーには要素の位置を付ける)
+pub fn from_json_array<T: FromJson>(
+    value: &JsonValue,
+) -> Result<impl Iterator<Item = Result<T, String>> + '_, String> {
+    match value {
+        JsonValue::Array(items) => Ok(items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| T::from_json(item).map_err(|e| format!("[{}]: {}", i, e)))),
+        other => Err(format!("expected array, found {}", kind(other))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(true.to_json(), JsonValue::Bool(true));
+        assert_eq!(3u32.to_json().to_string(), "3");
+        assert_eq!("a\"b".to_json().to_string(), r#""a\"b""#);
+        assert_eq!(None::<i32>.to_json(), JsonValue::Null);
+
+        assert_eq!(i64::from_json(&parse("-12").unwrap()), Ok(-12));
+        assert_eq!(f64::from_json(&parse("1.5").unwrap()), Ok(1.5));
+        assert_eq!(Option::<String>::from_json(&JsonValue::Null), Ok(None));
+        assert_eq!(String::from_json(&parse("\"x\"").unwrap()), Ok("x".to_string()));
+
+        assert_eq!(u32::from_json(&parse("-1").unwrap()), Err("-1 is not a valid u32".to_string()));
+        assert_eq!(i32::from_json(&parse("1.5").unwrap()), Err("1.5 is not a valid i32".to_string()));
+        assert_eq!(bool::from_json(&parse("\"yes\"").unwrap()), Err("expected bool, found string".to_string()));
+    }
+
+    #[test]
+    fn test_vec_round_trip() {
+        let values = vec![Some(1), None, Some(3)];
+        let json = values.to_json().to_string();
+        assert_eq!(json, "[1,null,3]");
+        assert_eq!(Vec::<Option<i32>>::from_json(&parse(&json).unwrap()), Ok(values));
+
+        let error = Vec::<u64>::from_json(&parse("[1, 2, \"3\"]").unwrap()).unwrap_err();
+        assert_eq!(error, "[2]: expected number, found string");
+        assert!(Vec::<u64>::from_json(&parse("{}").unwrap()).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+//! 設定ファイル (~/.config/todo/config) の読み込み
+//!
+//! `key = value` 形式の行だけを解釈する小さなパーサー。
+//! `#` 以降はコメント、空行は無視する。置き場所は [`crate::paths`] を参照。
+//!
+//! ```text
+//! # ~/.config/todo/config
+//! file = ~/todo.txt
+//! sort = priority
+//! color = off
+//! date_format = %d/%m/%Y
+//! hooks = ~/.config/todo/hooks
+//! hook_timeout = 5
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::paths::Env;
+use crate::SortKey;
+
+/// 設定ファイルから読み込んだ既定値 (未指定の項目は None)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConfigFile {
+    /// タスクファイル (load では環境変数と既定値も反映済み)
+    pub file: Option<PathBuf>,
+    pub sort: Option<SortKey>,
+    pub color: Option<bool>,
+    pub date_format: Option<String>,
+    /// フック用ディレクトリ (load では未指定なら `~/.config/todo/hooks`)
+    pub hooks: Option<PathBuf>,
+    /// フックの制限時間
+    pub hook_timeout: Option<Duration>,
+}
+
+impl ConfigFile {
+    /// 既定の場所から読み込む (ファイルが無ければ空の設定)
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(&Env::from_process())
+    }
+
+    /// `env` の示す場所から読み込み、環境変数と既定値を反映する
+    pub fn load_from(env: &Env) -> Result<Self, String> {
+        let config_dir = env.config_dir();
+        let mut config = match config_dir.as_ref().map(|dir| dir.join("config")) {
+            Some(path) if path.exists() => {
+                let text = fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read config {:?}: {}", path, e))?;
+                Self::parse(&text).map_err(|e| format!("{:?}: {}", path, e))?
+            }
+            _ => ConfigFile::default(),
+        };
+
+        config.file = Some(env.resolve_file(config.file.take()));
+        if config.hooks.is_none() {
+            config.hooks = config_dir.map(|dir| dir.join("hooks"));
+        }
+        Ok(config)
+    }
+
+    /// 設定ファイルの内容をパースする
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config = ConfigFile::default();
+
+        for (i, raw) in text.lines().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected key = value", i + 1))?;
+            let key = key.trim();
+            let value = unquote(value.trim());
+
+            match key {
+                "file" => config.file = Some(expand_home(value)),
+                "sort" => {
+                    config.sort = Some(
+                        SortKey::parse(value).map_err(|e| format!("line {}: {}", i + 1, e))?,
+                    )
+                }
+                "color" => {
+                    config.color = Some(
+                        parse_bool(value).map_err(|e| format!("line {}: {}", i + 1, e))?,
+                    )
+                }
+                "date_format" => config.date_format = Some(value.to_string()),
+                "hooks" => config.hooks = Some(expand_home(value)),
+                "hook_timeout" => {
+                    let secs: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|s: &f64| *s > 0.0 && s.is_finite())
+                        .ok_or_else(|| format!("line {}: invalid hook_timeout '{}'", i + 1, value))?;
+                    config.hook_timeout = Some(Duration::from_secs_f64(secs));
+                }
+                other => return Err(format!("line {}: unknown key '{}'", i + 1, other)),
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// 先頭の `~/` をホームディレクトリに展開する
+fn expand_home(value: &str) -> PathBuf {
+    match (value.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(value),
+    }
+}
+
+/// コメントを除く (`#` は行頭か空白の直後で、引用符の外にあるときだけコメント)
+fn strip_comment(line: &str) -> &str {
+    let (mut quoted, mut after_space) = (false, true);
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted && after_space => return &line[..i],
+            _ => {}
+        }
+        after_space = c.is_whitespace();
+    }
+    line
+}
+
+/// 値を囲む引用符を外す
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+pub(crate) fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "yes" | "1" => Ok(true),
+        "off" | "false" | "no" | "0" => Ok(false),
+        other => Err(format!("expected on/off, got '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_all_keys() {
+        let text = r#"
+# comment
+file = "tasks.txt"
+sort = priority   # trailing comment
+color = off
+date_format = %d/%m/%Y
+"#;
+        let config = ConfigFile::parse(text).unwrap();
+
+        assert_eq!(config.file, Some(PathBuf::from("tasks.txt")));
+        assert_eq!(config.sort, Some(SortKey::Priority));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.date_format, Some("%d/%m/%Y".to_string()));
+    }
+
+    #[test]
+    fn test_hash_inside_values() {
+        let text = "hooks = /tmp/my#dir # hooks\ndate_format = \"# %d\"\n  # indented comment";
+        let config = ConfigFile::parse(text).unwrap();
+        assert_eq!(config.hooks, Some(PathBuf::from("/tmp/my#dir")));
+        assert_eq!(config.date_format, Some("# %d".to_string()));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(ConfigFile::parse("").unwrap(), ConfigFile::default());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ConfigFile::parse("file").is_err());
+        assert!(ConfigFile::parse("unknown = 1").is_err());
+        assert!(ConfigFile::parse("color = maybe").is_err());
+
+        let err = ConfigFile::parse("\nsort = random").unwrap_err();
+        assert!(err.starts_with("line 2:"));
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let config = ConfigFile::parse("hooks = /tmp/hooks\nhook_timeout = 0.5").unwrap();
+        assert_eq!(config.hooks, Some(PathBuf::from("/tmp/hooks")));
+        assert_eq!(config.hook_timeout, Some(Duration::from_millis(500)));
+
+        assert!(ConfigFile::parse("hook_timeout = 0").is_err());
+        assert!(ConfigFile::parse("hook_timeout = soon").is_err());
+    }
+
+    #[test]
+    fn test_load_from_xdg_config_home() {
+        let dir = env::temp_dir().join(format!("todo-xdg-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("todo")).unwrap();
+        fs::write(dir.join("todo/config"), "file = /tmp/from-config.txt\n").unwrap();
+
+        let env = Env {
+            config_home: Some(dir.clone()),
+            ..Env::default()
+        };
+        let config = ConfigFile::load_from(&env).unwrap();
+        assert_eq!(config.file, Some(PathBuf::from("/tmp/from-config.txt")));
+        assert_eq!(config.hooks, Some(dir.join("todo/hooks")));
+
+        let env = Env {
+            todo_file: Some(PathBuf::from("/tmp/from-env.txt")),
+            ..env
+        };
+        let config = ConfigFile::load_from(&env).unwrap();
+        assert_eq!(config.file, Some(PathBuf::from("/tmp/from-env.txt")));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
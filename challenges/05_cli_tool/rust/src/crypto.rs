@@ -0,0 +1,425 @@
+//! タスクファイルの暗号化 (`--encrypt`)
+//!
+//! 外部クレートを使わずに実装した教材用の暗号。ChaCha20 (RFC 8439) で暗号化し、
+//! HMAC-SHA256 で改ざんと誤ったパスフレーズを検出する (Encrypt-then-MAC)。
+//! 鍵はパスフレーズから PBKDF2-HMAC-SHA256 で導出する。
+//!
+//! アルゴリズム自体は標準的なものだが、実装は監査を受けていない。
+//! 本当に守りたいデータには実績のあるライブラリを使うこと。
+//! また、sync の状態ファイル (`todo.sync.json`) と export の出力は暗号化しない。
+//!
+//! ```text
+//! "TODOENC1" | salt (16) | nonce (12) | 暗号文 | HMAC (32)
+//! ```
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 暗号化したファイルの先頭
+pub const MAGIC: &[u8] = b"TODOENC1";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + NONCE_LEN;
+
+/// PBKDF2 の繰り返し回数 (テストでは時間がかかりすぎるので減らす)
+const ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 100_000 };
+
+/// 暗号化されたデータかどうか
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// 暗号化されたファイルかどうか (先頭だけ読む)
+pub fn is_encrypted_file(path: &Path) -> bool {
+    let mut head = [0u8; MAGIC.len()];
+    File::open(path).and_then(|mut f| f.read_exact(&mut head)).is_ok() && is_encrypted(&head)
+}
+
+/// パスフレーズと、導出済みの鍵 (salt ごと)
+///
+/// 鍵の導出は重いので、同じファイルを読み書きする間は salt を使い回して
+/// 導出を 1 回で済ませる。nonce は書き込みのたびに作り直す。
+#[derive(Debug)]
+pub struct Cipher {
+    passphrase: String,
+    keys: RefCell<Option<([u8; SALT_LEN], Keys)>>,
+}
+
+/// 暗号化用と MAC 用の鍵
+#[derive(Debug, Clone, Copy)]
+struct Keys {
+    enc: [u8; 32],
+    mac: [u8; 32],
+}
+
+impl Cipher {
+    pub fn new(passphrase: &str) -> Self {
+        Cipher {
+            passphrase: passphrase.to_string(),
+            keys: RefCell::new(None),
+        }
+    }
+
+    fn keys(&self, salt: &[u8; SALT_LEN]) -> Keys {
+        if let Some((cached, keys)) = *self.keys.borrow() {
+            if &cached == salt {
+                return keys;
+            }
+        }
+
+        let mut derived = [0u8; 64];
+        pbkdf2(self.passphrase.as_bytes(), salt, ITERATIONS, &mut derived);
+        let mut keys = Keys { enc: [0; 32], mac: [0; 32] };
+        keys.enc.copy_from_slice(&derived[..32]);
+        keys.mac.copy_from_slice(&derived[32..]);
+        *self.keys.borrow_mut() = Some((*salt, keys));
+        keys
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Vec<u8> {
+        let salt = match *self.keys.borrow() {
+            Some((salt, _)) => salt,
+            None => random_bytes(),
+        };
+        let nonce: [u8; NONCE_LEN] = random_bytes();
+        let keys = self.keys(&salt);
+
+        let mut data = Vec::with_capacity(HEADER_LEN + plaintext.len() + TAG_LEN);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&salt);
+        data.extend_from_slice(&nonce);
+        let mut body = plaintext.to_vec();
+        chacha20(&keys.enc, &nonce, 1, &mut body);
+        data.extend_from_slice(&body);
+
+        let tag = hmac_sha256(&keys.mac, &data);
+        data.extend_from_slice(&tag);
+        data
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if !is_encrypted(data) || data.len() < HEADER_LEN + TAG_LEN {
+            return Err("Not an encrypted todo file".to_string());
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(&data[MAGIC.len()..MAGIC.len() + SALT_LEN]);
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce.copy_from_slice(&data[MAGIC.len() + SALT_LEN..HEADER_LEN]);
+        let (signed, tag) = data.split_at(data.len() - TAG_LEN);
+        let keys = self.keys(&salt);
+
+        if !constant_time_eq(&hmac_sha256(&keys.mac, signed), tag) {
+            return Err("Wrong passphrase or corrupted file".to_string());
+        }
+
+        let mut body = signed[HEADER_LEN..].to_vec();
+        chacha20(&keys.enc, &nonce, 1, &mut body);
+        Ok(body)
+    }
+}
+
+/// 比較にかかる時間が内容によらない等値判定
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// OS の乱数 (/dev/urandom)。使えなければ時刻などから作る
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut out = [0u8; N];
+    let filled = File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut out));
+    if filled.is_err() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let seed = format!("{}:{}:{:p}", nanos, std::process::id(), &out);
+        for (chunk, counter) in out.chunks_mut(32).zip(0u32..) {
+            let hash = sha256(format!("{}:{}", seed, counter).as_bytes());
+            chunk.copy_from_slice(&hash[..chunk.len()]);
+        }
+    }
+    out
+}
+
+// ---- ChaCha20 (RFC 8439) ----
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+fn chacha20_block(key: &[u8; 32], nonce: &[u8; NONCE_LEN], counter: u32) -> [u8; 64] {
+    let word = |b: &[u8]| u32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for i in 0..8 {
+        state[4 + i] = word(&key[i * 4..]);
+    }
+    state[12] = counter;
+    for i in 0..3 {
+        state[13 + i] = word(&nonce[i * 4..]);
+    }
+
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+
+    let mut out = [0u8; 64];
+    for i in 0..16 {
+        let value = working[i].wrapping_add(state[i]);
+        out[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+/// 鍵ストリームとの XOR (暗号化と復号は同じ処理)
+fn chacha20(key: &[u8; 32], nonce: &[u8; NONCE_LEN], counter: u32, data: &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, nonce, counter.wrapping_add(i as u32));
+        for (byte, k) in chunk.iter_mut().zip(block) {
+            *byte ^= k;
+        }
+    }
+}
+
+// ---- SHA-256 / HMAC / PBKDF2 ----
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// 64 バイトのブロック 1 つ分の圧縮関数
+fn compress(h: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for i in 0..16 {
+        w[i] = u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = *h;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        hh = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (x, y) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+        *x = x.wrapping_add(y);
+    }
+}
+
+fn digest(h: &[u32; 8]) -> [u8; 32] {
+    let mut out = [0u8; 32];
+    for (i, word) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    // パディング: 0x80、0 埋め、ビット長 (64 ビット big endian)
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    let mut h = H0;
+    for block in message.chunks(64) {
+        compress(&mut h, block);
+    }
+    digest(&h)
+}
+
+/// 鍵と ipad / opad の XOR を圧縮した後の状態
+///
+/// PBKDF2 では同じ鍵で何度も HMAC を計算するので、ここまでを使い回す。
+fn hmac_states(key: &[u8]) -> ([u32; 8], [u32; 8]) {
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let pad = |byte: u8| {
+        let mut h = H0;
+        compress(&mut h, &block.map(|b| b ^ byte));
+        h
+    };
+    (pad(0x36), pad(0x5c))
+}
+
+/// HMAC-SHA256 (RFC 2104)
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let (inner, outer) = hmac_states(key);
+    let finish = |state: [u32; 8], data: &[u8]| {
+        // 1 ブロック目 (鍵) は圧縮済みなので、残りにパディングを付けて続ける
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend_from_slice(&((64 + data.len() as u64) * 8).to_be_bytes());
+
+        let mut h = state;
+        for block in message.chunks(64) {
+            compress(&mut h, block);
+        }
+        digest(&h)
+    };
+    finish(outer, &finish(inner, data))
+}
+
+fn pbkdf2(passphrase: &[u8], salt: &[u8], iterations: u32, out: &mut [u8]) {
+    let (inner, outer) = hmac_states(passphrase);
+
+    // 32 バイトの入力の HMAC。パディング込みで 1 ブロックに収まる
+    let mut block = [0u8; 64];
+    block[32] = 0x80;
+    block[56..].copy_from_slice(&((64 + 32) as u64 * 8).to_be_bytes());
+    let mut hmac = |u: &[u8; 32]| {
+        let mut h = inner;
+        block[..32].copy_from_slice(u);
+        compress(&mut h, &block);
+        block[..32].copy_from_slice(&digest(&h));
+        h = outer;
+        compress(&mut h, &block);
+        digest(&h)
+    };
+
+    for (i, chunk) in out.chunks_mut(32).enumerate() {
+        let mut input = salt.to_vec();
+        input.extend_from_slice(&(i as u32 + 1).to_be_bytes());
+
+        let mut u = hmac_sha256(passphrase, &input);
+        let mut t = u;
+        for _ in 1..iterations {
+            u = hmac(&u);
+            for (x, y) in t.iter_mut().zip(u) {
+                *x ^= y;
+            }
+        }
+        chunk.copy_from_slice(&t[..chunk.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_sha256_and_hmac_vectors() {
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        // RFC 4231 test case 2
+        assert_eq!(
+            hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_vector() {
+        let mut out = [0u8; 32];
+        pbkdf2(b"password", b"salt", 2, &mut out);
+        assert_eq!(
+            hex(&out),
+            "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43"
+        );
+    }
+
+    #[test]
+    fn test_chacha20_vector() {
+        // RFC 8439 2.4.2
+        let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let mut data = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec();
+        chacha20(&key, &nonce, 1, &mut data);
+        assert_eq!(hex(&data[..16]), "6e2e359a2568f98041ba0728dd0d6981");
+        assert_eq!(hex(&data[data.len() - 2..]), "874d");
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let cipher = Cipher::new("secret");
+        let data = cipher.encrypt(b"[ ] Buy milk id:1\n");
+
+        assert!(is_encrypted(&data));
+        assert!(!data.windows(4).any(|w| w == b"milk"));
+        assert_eq!(cipher.decrypt(&data).unwrap(), b"[ ] Buy milk id:1\n");
+
+        // 同じ内容でも nonce が違うので暗号文は変わる
+        assert_ne!(cipher.encrypt(b"[ ] Buy milk id:1\n"), data);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_wrong_passphrase_and_tampering() {
+        let data = Cipher::new("secret").encrypt(b"hello");
+        assert!(Cipher::new("wrong").decrypt(&data).is_err());
+
+        let mut tampered = data.clone();
+        tampered[HEADER_LEN] ^= 1;
+        assert!(Cipher::new("secret").decrypt(&tampered).is_err());
+        assert!(Cipher::new("secret").decrypt(b"[ ] plain").is_err());
+    }
+}
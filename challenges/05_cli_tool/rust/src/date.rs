@@ -0,0 +1,159 @@
+//! 日付の簡易ユーティリティ
+//!
+//! 外部クレートを使わず `YYYY-MM-DD` 形式の文字列として日付を扱う。
+//! この形式は辞書順に比較すれば日付順になる。
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 既定の表示書式
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d";
+
+/// 現在時刻 (1970-01-01 からの秒数)
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 今日の日付 (UTC) を `YYYY-MM-DD` で返す
+pub fn today() -> String {
+    from_days((now() / 86_400) as i64)
+}
+
+/// 秒数を `YYYY-MM-DDTHH:MM:SSZ` (UTC) に変換する
+pub fn format_timestamp(secs: u64) -> String {
+    let time = secs % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        from_days((secs / 86_400) as i64),
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ` を秒数に変換する
+pub fn parse_timestamp(s: &str) -> Option<u64> {
+    let (day, time) = s.strip_suffix('Z')?.split_once('T')?;
+    let days = u64::try_from(to_days(day)?).ok()?;
+
+    let parts: Vec<u64> = time
+        .split(':')
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    match parts.as_slice() {
+        [h, m, s] if *h < 24 && *m < 60 && *s < 60 => Some(days * 86_400 + h * 3600 + m * 60 + s),
+        _ => None,
+    }
+}
+
+/// 1970-01-01 からの日数を `YYYY-MM-DD` に変換する
+///
+/// Howard Hinnant の civil_from_days アルゴリズム
+pub fn from_days(days: i64) -> String {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `YYYY-MM-DD` を 1970-01-01 からの日数に変換する
+///
+/// Howard Hinnant の days_from_civil アルゴリズム
+pub fn to_days(iso: &str) -> Option<i64> {
+    if !is_valid(iso) {
+        return None;
+    }
+    let year: i64 = iso[0..4].parse().ok()?;
+    let month: i64 = iso[5..7].parse().ok()?;
+    let day: i64 = iso[8..10].parse().ok()?;
+
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    Some(era * 146_097 + doe - 719_468)
+}
+
+/// `YYYY-MM-DD` の日付を書式に従って整形する
+///
+/// 書式中の `%Y` `%m` `%d` をそれぞれ年・月・日に置き換える。
+pub fn format(iso: &str, fmt: &str) -> String {
+    if !is_valid(iso) {
+        return iso.to_string();
+    }
+    fmt.replace("%Y", &iso[0..4])
+        .replace("%m", &iso[5..7])
+        .replace("%d", &iso[8..10])
+}
+
+/// `YYYY-MM-DD` 形式かどうか (月日の範囲のみ確認)
+pub fn is_valid(s: &str) -> bool {
+    let parts: Vec<&str> = s.split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return false;
+    }
+    if !parts.iter().all(|p| p.bytes().all(|b| b.is_ascii_digit())) {
+        return false;
+    }
+
+    let month: u32 = parts[1].parse().unwrap_or(0);
+    let day: u32 = parts[2].parse().unwrap_or(0);
+    (1..=12).contains(&month) && (1..=31).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_days() {
+        assert_eq!(from_days(0), "1970-01-01");
+        assert_eq!(from_days(59), "1970-03-01");
+        assert_eq!(from_days(11_016), "2000-02-29");
+        assert_eq!(from_days(19_723), "2024-01-01");
+    }
+
+    #[test]
+    fn test_to_days_round_trip() {
+        for days in [0, 59, 11_016, 19_723, -1, 30_000] {
+            assert_eq!(to_days(&from_days(days)), Some(days));
+        }
+        assert_eq!(to_days("soon"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format("2024-05-01", "%d/%m/%Y"), "01/05/2024");
+        assert_eq!(format("2024-05-01", DEFAULT_FORMAT), "2024-05-01");
+        assert_eq!(format("someday", "%d/%m/%Y"), "someday");
+    }
+
+    #[test]
+    fn test_timestamp_round_trip() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_715_335_200), "2024-05-10T10:00:00Z");
+        assert_eq!(parse_timestamp("2024-05-10T10:00:00Z"), Some(1_715_335_200));
+        assert_eq!(parse_timestamp("2024-05-10T24:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-05-10"), None);
+    }
+
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("2024-12-31"));
+        assert!(!is_valid("2024-13-01"));
+        assert!(!is_valid("2024-1-01"));
+        assert!(!is_valid("tomorrow"));
+    }
+}
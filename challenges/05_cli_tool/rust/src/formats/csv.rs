@@ -0,0 +1,146 @@
+//! CSV 形式 (RFC 4180)
+//!
+//! `id,parent,done,description` の 4 列。カンマや引用符を含む値は
+//! `"` で囲み、中の `"` は `""` に二重化する。
+
+use crate::Task;
+
+pub const HEADER: &str = "id,parent,done,description";
+
+pub fn write(tasks: &[Task]) -> String {
+    let mut out = format!("{}\n", HEADER);
+    for task in tasks {
+        let parent = task.parent.map(|p| p.to_string()).unwrap_or_default();
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            task.id,
+            parent,
+            task.done,
+            quote(&task.description)
+        ));
+    }
+    out
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 1 レコード分のフィールドを取り出す (引用符内の改行も扱う)
+fn parse_records(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (in_quotes, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => in_quotes = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => in_quotes = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("CSV: unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+pub fn read(text: &str) -> Result<Vec<Task>, String> {
+    let mut records = parse_records(text)?.into_iter().enumerate();
+
+    match records.next() {
+        Some((_, header)) if header.join(",") == HEADER => {}
+        _ => return Err(format!("CSV: expected header '{}'", HEADER)),
+    }
+
+    let mut tasks = Vec::new();
+    for (i, record) in records {
+        let line = i + 1;
+        if record.iter().all(|f| f.is_empty()) {
+            continue;
+        }
+        if record.len() != 4 {
+            return Err(format!("CSV line {}: expected 4 fields, got {}", line, record.len()));
+        }
+
+        let id = record[0]
+            .parse()
+            .map_err(|_| format!("CSV line {}: invalid id '{}'", line, record[0]))?;
+        let parent = match record[1].as_str() {
+            "" => None,
+            p => Some(
+                p.parse()
+                    .map_err(|_| format!("CSV line {}: invalid parent '{}'", line, p))?,
+            ),
+        };
+        let done = match record[2].as_str() {
+            "true" | "1" | "x" => true,
+            "false" | "0" | "" => false,
+            other => return Err(format!("CSV line {}: invalid done '{}'", line, other)),
+        };
+
+        tasks.push(Task {
+            id,
+            description: record[3].clone(),
+            done,
+            parent,
+            ..Default::default()
+        });
+    }
+
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("plain"), "plain");
+        assert_eq!(quote("a,b"), "\"a,b\"");
+        assert_eq!(quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_read_quoted_fields() {
+        let text = "id,parent,done,description\r\n1,,false,\"a, \"\"b\"\"\nc\"\r\n2,1,true,d\r\n";
+        let tasks = read(text).unwrap();
+
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].description, "a, \"b\"\nc");
+        assert_eq!(tasks[1].parent, Some(1));
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(read("a,b\n").is_err());
+        assert!(read("id,parent,done,description\nx,,false,a\n").is_err());
+        assert!(read("id,parent,done,description\n1,,false\n").is_err());
+        assert!(read("id,parent,done,description\n1,,false,\"open\n").is_err());
+    }
+}
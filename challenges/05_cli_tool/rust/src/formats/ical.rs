@@ -0,0 +1,219 @@
+//! iCalendar 形式 (RFC 5545) の VTODO
+//!
+//! 優先度 `(A)` は PRIORITY (A=1 … I=9)、`due:` は DUE、
+//! 親子関係は RELATED-TO で表す。
+
+use crate::{date, Task};
+
+/// 1 行の最大長 (オクテット)。これを超える行は折り返す
+const MAX_LINE: usize = 75;
+
+fn uid(id: usize) -> String {
+    format!("task-{}@todo", id)
+}
+
+pub fn write(tasks: &[Task]) -> String {
+    let stamp = format!("{}T000000Z", date::today().replace('-', ""));
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//lang_lab//todo//EN".to_string(),
+    ];
+
+    for task in tasks {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", uid(task.id)));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        lines.push(format!("SUMMARY:{}", escape(&task.description)));
+        let status = if task.done { "COMPLETED" } else { "NEEDS-ACTION" };
+        lines.push(format!("STATUS:{}", status));
+        if let Some(p) = task.priority() {
+            lines.push(format!("PRIORITY:{}", (p as u8 - b'A' + 1).min(9)));
+        }
+        if let Some(due) = task.due() {
+            lines.push(format!("DUE;VALUE=DATE:{}", due.replace('-', "")));
+        }
+        if let Some(parent) = task.parent {
+            lines.push(format!("RELATED-TO:{}", uid(parent)));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|l| fold(l) + "\r\n").collect()
+}
+
+/// 長い行を `CRLF + 空白` で折り返す (文字の途中では切らない)
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// 折り返された行を元に戻す
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+/// VTODO 1 件分の読み込み途中の値
+#[derive(Default)]
+struct Todo {
+    uid: Option<String>,
+    summary: String,
+    done: bool,
+    priority: Option<u8>,
+    due: Option<String>,
+    related_to: Option<String>,
+}
+
+pub fn read(text: &str) -> Result<Vec<Task>, String> {
+    let mut todos = Vec::new();
+    let mut current: Option<Todo> = None;
+
+    for line in unfold(text) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("iCal: invalid line '{}'", line))?;
+        // `DUE;VALUE=DATE` のようなパラメータは名前から外す
+        let name = name.split(';').next().unwrap_or(name).to_ascii_uppercase();
+
+        match (name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value == "VTODO" => current = Some(Todo::default()),
+            ("END", Some(_)) if value == "VTODO" => todos.extend(current.take()),
+            ("UID", Some(todo)) => todo.uid = Some(value.to_string()),
+            ("SUMMARY", Some(todo)) => todo.summary = unescape(value),
+            ("STATUS", Some(todo)) => todo.done = value == "COMPLETED",
+            ("PRIORITY", Some(todo)) => todo.priority = value.parse().ok().filter(|p| *p > 0),
+            ("DUE", Some(todo)) if value.len() >= 8 => {
+                todo.due = Some(format!("{}-{}-{}", &value[0..4], &value[4..6], &value[6..8]))
+            }
+            ("RELATED-TO", Some(todo)) => todo.related_to = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    if current.is_some() {
+        return Err("iCal: missing END:VTODO".to_string());
+    }
+
+    let ids: Vec<Option<&String>> = todos.iter().map(|t| t.uid.as_ref()).collect();
+    let tasks = todos
+        .iter()
+        .enumerate()
+        .map(|(i, todo)| {
+            let mut task = Task {
+                id: i + 1,
+                description: todo.summary.clone(),
+                done: todo.done,
+                parent: todo
+                    .related_to
+                    .as_ref()
+                    .and_then(|r| ids.iter().position(|u| *u == Some(r)))
+                    .map(|p| p + 1),
+                ..Default::default()
+            };
+            // 説明に書かれていない優先度・期限は todo.txt 形式で補う
+            if let (Some(p), None) = (todo.priority, task.priority()) {
+                let letter = (b'A' + p.min(9) - 1) as char;
+                task.description = format!("({}) {}", letter, task.description);
+            }
+            if let (Some(due), None) = (&todo.due, task.due()) {
+                if date::is_valid(due) {
+                    task.description = format!("{} due:{}", task.description, due);
+                }
+            }
+            task
+        })
+        .collect();
+
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_properties() {
+        let tasks = crate::store::parse_lines(&[
+            "[ ] (B) Pay rent due:2024-05-01".to_string(),
+            "  [x] a, b".to_string(),
+        ]);
+        let text = write(&tasks);
+
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(text.contains("PRIORITY:2\r\n"));
+        assert!(text.contains("DUE;VALUE=DATE:20240501\r\n"));
+        assert!(text.contains("SUMMARY:a\\, b\r\n"));
+        assert!(text.contains("STATUS:COMPLETED\r\n"));
+        assert!(text.contains("RELATED-TO:task-1@todo\r\n"));
+    }
+
+    #[test]
+    fn test_fold_and_unfold() {
+        let long = format!("SUMMARY:{}", "あ".repeat(40));
+        let folded = fold(&long);
+
+        assert!(folded.split("\r\n").all(|l| l.len() <= MAX_LINE));
+        assert_eq!(unfold(&folded), vec![long]);
+    }
+
+    #[test]
+    fn test_read_fills_priority_and_due() {
+        let text = "BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:x\nSUMMARY:Call mom\nPRIORITY:1\n\
+                    DUE:20240601T090000Z\nEND:VTODO\nEND:VCALENDAR\n";
+        let tasks = read(text).unwrap();
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "(A) Call mom due:2024-06-01");
+        assert!(!tasks[0].done);
+    }
+
+    #[test]
+    fn test_read_unterminated() {
+        assert!(read("BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY:x\n").is_err());
+    }
+}
@@ -0,0 +1,111 @@
+//! JSON 形式
+//!
+//! タスクの配列として書き出す。`todo sync` の送受信にも使う。
+//!
+//! ```text
+//! [{"id": 1, "parent": null, "done": false, "description": "...",
+//!   "created": "2024-05-01", "completed": null}]
+//! ```
+
+use std::collections::HashMap;
+
+use json_parser::JsonValue;
+
+use crate::Task;
+
+fn optional(value: &Option<String>) -> JsonValue {
+    value.clone().map_or(JsonValue::Null, JsonValue::String)
+}
+
+/// タスク 1 件分のオブジェクト
+pub fn task_value(task: &Task) -> JsonValue {
+    JsonValue::Object(HashMap::from([
+        ("id".to_string(), JsonValue::Number(task.id as f64)),
+        (
+            "parent".to_string(),
+            task.parent.map_or(JsonValue::Null, |p| JsonValue::Number(p as f64)),
+        ),
+        ("done".to_string(), JsonValue::Bool(task.done)),
+        ("description".to_string(), JsonValue::String(task.description.clone())),
+        ("created".to_string(), optional(&task.created)),
+        ("completed".to_string(), optional(&task.completed)),
+    ]))
+}
+
+pub fn to_value(tasks: &[Task]) -> JsonValue {
+    JsonValue::Array(tasks.iter().map(task_value).collect())
+}
+
+/// 1 以上の整数かどうかを確認して ID にする
+fn id(value: &JsonValue, name: &str, index: usize) -> Result<usize, String> {
+    match value {
+        JsonValue::Number(n) if *n >= 1.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(format!("JSON: task {}: invalid {}", index + 1, name)),
+    }
+}
+
+/// `to_value` の逆。省略できるのは parent / done / created / completed
+pub fn from_value(value: &JsonValue) -> Result<Vec<Task>, String> {
+    let JsonValue::Array(items) = value else {
+        return Err("JSON: expected an array of tasks".to_string());
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let JsonValue::Object(map) = item else {
+                return Err(format!("JSON: task {}: expected an object", i + 1));
+            };
+            let text = |key: &str| match map.get(key) {
+                Some(JsonValue::String(s)) => Some(s.clone()),
+                _ => None,
+            };
+
+            Ok(Task {
+                id: id(map.get("id").unwrap_or(&JsonValue::Null), "id", i)?,
+                description: text("description")
+                    .ok_or_else(|| format!("JSON: task {}: missing description", i + 1))?,
+                done: matches!(map.get("done"), Some(JsonValue::Bool(true))),
+                parent: match map.get("parent") {
+                    None | Some(JsonValue::Null) => None,
+                    Some(p) => Some(id(p, "parent", i)?),
+                },
+                created: text("created"),
+                completed: text("completed"),
+            })
+        })
+        .collect()
+}
+
+pub fn write(tasks: &[Task]) -> String {
+    to_value(tasks).to_pretty_string() + "\n"
+}
+
+pub fn read(text: &str) -> Result<Vec<Task>, String> {
+    let value = json_parser::parse(text).map_err(|e| format!("JSON: {}", e))?;
+    from_value(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_keeps_metadata() {
+        let tasks = crate::store::parse_lines(&[
+            "[ ] A id:4 created:2024-05-01".to_string(),
+            "  [x] B id:2 completed:2024-05-02".to_string(),
+        ]);
+        assert_eq!(read(&write(&tasks)).unwrap(), tasks);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(read("{}").is_err());
+        assert!(read(r#"[{"description": "no id"}]"#).is_err());
+        assert!(read(r#"[{"id": 1.5, "description": "x"}]"#).is_err());
+        assert!(read(r#"[{"id": 1}]"#).is_err());
+        assert!(read("[").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Markdown のタスクリスト形式 (`- [ ] ...`)
+//!
+//! サブタスクはネストしたリストとして書き出す。
+
+use std::collections::HashMap;
+
+use crate::Task;
+
+/// ID などのメタデータは書き出さず、説明と完了状態だけを残す
+pub fn write(tasks: &[Task]) -> String {
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut out = String::new();
+    for task in tasks {
+        let depth = task
+            .parent
+            .and_then(|p| depths.get(&p))
+            .map_or(0, |d| d + 1);
+        depths.insert(task.id, depth);
+
+        let mark = if task.done { "[x]" } else { "[ ]" };
+        out.push_str(&format!("{}- {} {}\n", "  ".repeat(depth), mark, task.description));
+    }
+    out
+}
+
+/// リスト項目の行かどうか
+pub fn is_task_line(line: &str) -> bool {
+    parse_item(line.trim_start()).is_some()
+}
+
+/// `- [ ] text` / `* [x] text` / `- text` を (完了, 説明) にする
+fn parse_item(line: &str) -> Option<(bool, String)> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))?;
+
+    let (done, description) = if let Some(d) = rest.strip_prefix("[ ] ") {
+        (false, d)
+    } else if let Some(d) = rest
+        .strip_prefix("[x] ")
+        .or_else(|| rest.strip_prefix("[X] "))
+    {
+        (true, d)
+    } else {
+        (false, rest)
+    };
+
+    let description = description.trim();
+    if description.is_empty() {
+        None
+    } else {
+        Some((done, description.to_string()))
+    }
+}
+
+/// リスト項目以外の行 (見出しや本文) は読み飛ばす
+pub fn read(text: &str) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    // (インデント幅, ID) のスタック
+    let mut ancestors: Vec<(usize, usize)> = Vec::new();
+
+    for line in text.lines() {
+        let body = line.trim_start();
+        let Some((done, description)) = parse_item(body) else {
+            continue;
+        };
+
+        let width = line.len() - body.len();
+        while ancestors.last().is_some_and(|&(w, _)| w >= width) {
+            ancestors.pop();
+        }
+
+        let id = tasks.len() + 1;
+        tasks.push(Task {
+            id,
+            description,
+            done,
+            parent: ancestors.last().map(|&(_, p)| p),
+            ..Default::default()
+        });
+        ancestors.push((width, id));
+    }
+
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let tasks = crate::store::parse_lines(&["[ ] A".to_string(), "  [x] B".to_string()]);
+        assert_eq!(write(&tasks), "- [ ] A\n  - [x] B\n");
+    }
+
+    #[test]
+    fn test_read_skips_other_lines() {
+        let text = "# TODO\n\nSome notes.\n- [ ] A\n    * [X] B\n- plain item\n";
+        let tasks = read(text);
+
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[1].description, "B");
+        assert!(tasks[1].done);
+        assert_eq!(tasks[1].parent, Some(1));
+        assert_eq!(tasks[2].description, "plain item");
+        assert_eq!(tasks[2].parent, None);
+    }
+}
@@ -0,0 +1,153 @@
+//! タスク一覧のインポート / エクスポート形式
+//!
+//! 各形式ごとに `write` (タスク → 文字列) と `read` (文字列 → タスク) を持つ。
+//! 読み込んだタスクの ID はファイル内でだけ通用する仮の値で、
+//! 親子関係 (`parent`) もその ID で表す。
+
+mod csv;
+mod ical;
+mod json;
+mod markdown;
+
+use std::path::Path;
+
+use crate::Task;
+
+pub use json::{from_value as tasks_from_json, task_value as task_to_json, to_value as tasks_to_json};
+
+/// 対応しているファイル形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Csv,
+    Ical,
+    Json,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "md" | "markdown" => Ok(Format::Markdown),
+            "csv" => Ok(Format::Csv),
+            "ical" | "ics" => Ok(Format::Ical),
+            "json" => Ok(Format::Json),
+            other => Err(format!(
+                "Unknown format: {} (expected md, csv, ical or json)",
+                other
+            )),
+        }
+    }
+
+    /// 拡張子から形式を推測する
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "csv" => Some(Format::Csv),
+            "ics" | "ical" => Some(Format::Ical),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// 内容の先頭から形式を推測する
+    pub fn sniff(text: &str) -> Option<Self> {
+        let first = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+        if first.eq_ignore_ascii_case("BEGIN:VCALENDAR") {
+            Some(Format::Ical)
+        } else if first == "[" || first == "[]" || first.starts_with("[{") {
+            Some(Format::Json)
+        } else if first == csv::HEADER {
+            Some(Format::Csv)
+        } else if markdown::is_task_line(first) || first.starts_with('#') {
+            Some(Format::Markdown)
+        } else {
+            None
+        }
+    }
+
+    /// 拡張子、だめなら内容から形式を決める
+    pub fn detect(path: &Path, text: &str) -> Result<Self, String> {
+        Format::from_path(path)
+            .or_else(|| Format::sniff(text))
+            .ok_or_else(|| format!("Cannot detect the format of {:?}", path))
+    }
+}
+
+/// タスクを指定形式の文字列にする
+pub fn write(format: Format, tasks: &[Task]) -> String {
+    match format {
+        Format::Markdown => markdown::write(tasks),
+        Format::Csv => csv::write(tasks),
+        Format::Ical => ical::write(tasks),
+        Format::Json => json::write(tasks),
+    }
+}
+
+/// 指定形式の文字列からタスクを読み込む
+pub fn read(format: Format, text: &str) -> Result<Vec<Task>, String> {
+    match format {
+        Format::Markdown => Ok(markdown::read(text)),
+        Format::Csv => csv::read(text),
+        Format::Ical => ical::read(text),
+        Format::Json => json::read(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_parse() {
+        assert_eq!(Format::parse("md").unwrap(), Format::Markdown);
+        assert_eq!(Format::parse("ical").unwrap(), Format::Ical);
+        assert_eq!(Format::parse("json").unwrap(), Format::Json);
+        assert!(Format::parse("xml").is_err());
+    }
+
+    #[test]
+    fn test_detect_by_extension() {
+        assert_eq!(Format::from_path(Path::new("a.CSV")), Some(Format::Csv));
+        assert_eq!(Format::from_path(Path::new("a.ics")), Some(Format::Ical));
+        assert_eq!(Format::from_path(Path::new("a.txt")), None);
+    }
+
+    #[test]
+    fn test_detect_by_content() {
+        assert_eq!(Format::sniff("BEGIN:VCALENDAR\r\n"), Some(Format::Ical));
+        assert_eq!(Format::sniff("id,parent,done,description\n"), Some(Format::Csv));
+        assert_eq!(Format::sniff("\n- [ ] milk\n"), Some(Format::Markdown));
+        assert_eq!(Format::sniff("[\n  {\"id\": 1}\n]"), Some(Format::Json));
+        assert_eq!(Format::sniff("hello"), None);
+        assert_eq!(Format::sniff("[ ] todo.txt line"), None);
+
+        assert_eq!(
+            Format::detect(Path::new("export.txt"), "- [x] a").unwrap(),
+            Format::Markdown
+        );
+        assert!(Format::detect(Path::new("export.txt"), "???").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_all_formats() {
+        let tasks = crate::store::parse_lines(&[
+            "[ ] (A) Pay rent, \"soon\" due:2024-05-01".to_string(),
+            "  [x] Find checkbook; maybe".to_string(),
+            "[ ] Walk dog".to_string(),
+        ]);
+
+        for format in [Format::Markdown, Format::Csv, Format::Ical, Format::Json] {
+            let text = write(format, &tasks);
+            let back = read(format, &text).unwrap();
+
+            assert_eq!(back.len(), tasks.len(), "{:?}", format);
+            for (a, b) in tasks.iter().zip(&back) {
+                assert_eq!(a.description, b.description, "{:?}", format);
+                assert_eq!(a.done, b.done, "{:?}", format);
+            }
+            assert_eq!(back[1].parent, Some(back[0].id), "{:?}", format);
+            assert_eq!(back[2].parent, None, "{:?}", format);
+        }
+    }
+}
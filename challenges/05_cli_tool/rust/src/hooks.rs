@@ -0,0 +1,247 @@
+//! タスクのイベントで実行するフック
+//!
+//! フック用ディレクトリ (既定は `~/.config/todo/hooks/`) にある実行ファイルのうち、
+//! 名前がイベント名と同じもの (`task-added`) か、イベント名に拡張子を付けたもの
+//! (`task-added.sh`) を実行する。標準入力には次の JSON を 1 つ渡す。
+//!
+//! ```text
+//! {"event": "task-added", "task": {"id": 3, "description": "...", ...}}
+//! ```
+//!
+//! フックは制限時間を過ぎると強制終了する。フックの失敗は警告にとどめ、
+//! コマンド自体は成功扱いにする。
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use json_parser::JsonValue;
+
+use crate::formats::task_to_json;
+use crate::Task;
+
+/// 既定の制限時間
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 終了を確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// フックを起動するイベント
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Added,
+    Completed,
+    Removed,
+    /// clear でアーカイブへ移した
+    Archived,
+}
+
+impl Event {
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Added => "task-added",
+            Event::Completed => "task-completed",
+            Event::Removed => "task-removed",
+            Event::Archived => "task-archived",
+        }
+    }
+}
+
+/// コマンドの実行中に起きたイベント
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskEvent {
+    pub event: Event,
+    pub task: Task,
+}
+
+impl TaskEvent {
+    pub fn new(event: Event, task: &Task) -> Self {
+        TaskEvent {
+            event,
+            task: task.clone(),
+        }
+    }
+
+    /// フックの標準入力に渡す JSON
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(HashMap::from([
+            ("event".to_string(), JsonValue::String(self.event.name().to_string())),
+            ("task".to_string(), task_to_json(&self.task)),
+        ]))
+    }
+}
+
+/// フック用ディレクトリと制限時間
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hooks {
+    pub dir: PathBuf,
+    pub timeout: Duration,
+}
+
+impl Hooks {
+    pub fn new(dir: PathBuf) -> Self {
+        Hooks {
+            dir,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// イベントに対応する実行ファイル (名前順)
+    pub fn scripts(&self, event: Event) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut scripts: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|path| {
+                let stem = path.file_stem().and_then(|s| s.to_str());
+                stem == Some(event.name()) && is_executable(path)
+            })
+            .collect();
+        scripts.sort();
+        scripts
+    }
+
+    /// イベントごとにフックを実行し、失敗したものの警告を返す
+    pub fn dispatch(&self, events: &[TaskEvent]) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for event in events {
+            let input = event.to_json().to_string();
+            for script in self.scripts(event.event) {
+                if let Err(e) = run_script(&script, event.event, &input, self.timeout) {
+                    warnings.push(format!("hook {:?}: {}", script, e));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// 1 つのフックを実行する
+///
+/// 作業ディレクトリはフック用ディレクトリにし、標準出力は捨てる
+/// (JSON 出力などコマンド自身の出力に混ざらないように)。
+fn run_script(script: &Path, event: Event, input: &str, timeout: Duration) -> Result<(), String> {
+    let mut child = Command::new(script)
+        .arg(event.name())
+        .env("TODO_EVENT", event.name())
+        .current_dir(script.parent().unwrap_or(Path::new(".")))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start: {}", e))?;
+
+    // 入力を読まずに終わるフックもあるので、書き込みの失敗は無視する
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(format!("exited with {}", status)),
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("timed out after {:?}", timeout));
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(e) => return Err(format!("failed to wait: {}", e)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// テストごとの一時ディレクトリ
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("todo-hooks-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn script(dir: &Path, name: &str, body: &str) {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    fn added(description: &str) -> TaskEvent {
+        let task = Task::from_line(1, &format!("[ ] {}", description));
+        TaskEvent::new(Event::Added, &task)
+    }
+
+    #[test]
+    fn test_scripts_match_event_name() {
+        let dir = temp_dir("match");
+        script(&dir, "task-added", "true");
+        script(&dir, "task-added.sh", "true");
+        script(&dir, "task-completed", "true");
+        fs::write(dir.join("task-added.txt"), "not executable").unwrap();
+
+        let names: Vec<String> = Hooks::new(dir.clone())
+            .scripts(Event::Added)
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["task-added", "task-added.sh"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dispatch_passes_json_on_stdin() {
+        let dir = temp_dir("stdin");
+        script(&dir, "task-added", "cat > received.json; echo \"$TODO_EVENT\" > event.txt");
+
+        let warnings = Hooks::new(dir.clone()).dispatch(&[added("Buy milk")]);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let received = fs::read_to_string(dir.join("received.json")).unwrap();
+        let value = json_parser::parse(&received).unwrap();
+        assert_eq!(value, added("Buy milk").to_json());
+        assert_eq!(fs::read_to_string(dir.join("event.txt")).unwrap(), "task-added\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_dispatch_reports_failures_and_timeouts() {
+        let dir = temp_dir("fail");
+        script(&dir, "task-added", "exit 3");
+        script(&dir, "task-added.slow", "sleep 5");
+
+        let hooks = Hooks {
+            dir: dir.clone(),
+            timeout: Duration::from_millis(200),
+        };
+        let started = Instant::now();
+        let warnings = hooks.dispatch(&[added("x")]);
+
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("exited with"));
+        assert!(warnings[1].contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+//! `todo sync` 用の最小限の HTTP/1.1 クライアント
+//!
+//! `http://host:port/path` だけに対応する (TLS やチャンク転送は扱わない)。
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// 接続・送受信のタイムアウト
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// ステータスコード・ヘッダー・本文
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// キーは小文字にそろえる
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Response {
+    /// ヘッダーの値 (名前の大文字小文字は区別しない)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+}
+
+/// URL を (`host:port`, パス) に分ける
+fn split_url(url: &str) -> Result<(String, String), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("Unsupported URL (expected http://): {}", url))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(format!("Missing host in URL: {}", url));
+    }
+
+    let authority = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    Ok((authority, path.to_string()))
+}
+
+/// リクエストを 1 回送り、レスポンスを受け取る
+pub fn request(method: &str, url: &str, body: Option<&str>) -> Result<Response, String> {
+    let (authority, path) = split_url(url)?;
+    let host = authority.trim_end_matches(":80");
+
+    let mut stream = TcpStream::connect(&authority)
+        .map_err(|e| format!("Failed to connect to {}: {}", authority, e))?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok();
+    stream.set_write_timeout(Some(TIMEOUT)).ok();
+
+    let body = body.unwrap_or("");
+    let mut message = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n",
+        method, path, host
+    );
+    if !body.is_empty() || method != "GET" {
+        message.push_str("Content-Type: application/json\r\n");
+        message.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    message.push_str("\r\n");
+    message.push_str(body);
+
+    stream
+        .write_all(message.as_bytes())
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut raw = Vec::new();
+    stream
+        .read_to_end(&mut raw)
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    parse_response(&raw)
+}
+
+/// ステータス行・ヘッダー・本文を分ける (本文は Content-Length があればその長さ)
+fn parse_response(raw: &[u8]) -> Result<Response, String> {
+    let text = String::from_utf8_lossy(raw);
+    let (head, body) = text
+        .split_once("\r\n\r\n")
+        .ok_or("Malformed HTTP response")?;
+
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|s| s.parse().ok())
+        .ok_or("Malformed HTTP status line")?;
+
+    let headers: HashMap<String, String> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string()))
+        .collect();
+    let length = headers.get("content-length").and_then(|v| v.parse::<usize>().ok());
+    let body = match length {
+        Some(n) => body.get(..n).unwrap_or(body),
+        None => body,
+    };
+
+    Ok(Response {
+        status,
+        headers,
+        body: body.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_split_url() {
+        assert_eq!(
+            split_url("http://localhost:8080/tasks").unwrap(),
+            ("localhost:8080".to_string(), "/tasks".to_string())
+        );
+        assert_eq!(
+            split_url("http://example.com").unwrap(),
+            ("example.com:80".to_string(), "/".to_string())
+        );
+        assert!(split_url("https://example.com").is_err());
+        assert!(split_url("http:///tasks").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let raw = b"HTTP/1.1 404 Not Found\r\nContent-Length: 2\r\nX-Id: a:b\r\n\r\nnoextra";
+        let response = parse_response(raw).unwrap();
+        assert_eq!((response.status, response.body.as_str()), (404, "no"));
+        assert_eq!(response.header("X-ID"), Some("a:b"));
+        assert_eq!(response.header("content-length"), Some("2"));
+        assert!(parse_response(b"garbage").is_err());
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // リクエスト行と本文をそのまま返すサーバー
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(v) = line.strip_prefix("Content-Length: ") {
+                    length = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+
+            let reply = format!("{} {}", request_line.trim(), String::from_utf8(body).unwrap());
+            write!(
+                &stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                reply.len(),
+                reply
+            )
+            .unwrap();
+        });
+
+        let url = format!("http://{}/tasks", addr);
+        let response = request("PUT", &url, Some("[]")).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "PUT /tasks HTTP/1.1 []");
+    }
+}
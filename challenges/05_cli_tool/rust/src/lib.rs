@@ -0,0 +1,2225 @@
+//! CLI Tool - Rust 実装
+//!
+//! 標準ライブラリのみでシンプルな TODO CLI を実装
+//!
+//! コマンドの解釈と実行はこのライブラリにあり、`main.rs` は引数を渡すだけ。
+//! 保存先 ([`TaskStore`]) と出力先 ([`Output`]) を差し替えれば、
+//! プロセスを起動せずにコマンドを実行できる。
+//!
+//! ```
+//! use cli_tool::output::{Output, OutputFormat};
+//! use cli_tool::store::{MemoryStore, TaskStore};
+//! use cli_tool::{execute, Config};
+//!
+//! let mut store = MemoryStore::default();
+//! let mut out = Output::capture(OutputFormat::Text);
+//! for args in [&["add", "Buy milk"][..], &["done", "milk"]] {
+//!     let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+//!     execute(&Config::parse(&args).unwrap(), &mut store, &mut out).unwrap();
+//! }
+//!
+//! assert_eq!(out.lines(), ["Added: Buy milk", "Done: Buy milk"]);
+//! assert!(store.load().unwrap()[0].done);
+//! ```
+
+pub mod config_file;
+pub mod crypto;
+pub mod date;
+pub mod formats;
+pub mod hooks;
+pub mod http;
+pub mod messages;
+pub mod output;
+pub mod passphrase;
+pub mod paths;
+pub mod stats;
+pub mod store;
+mod style;
+pub mod sync;
+pub mod timelog;
+pub mod watch;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use config_file::ConfigFile;
+use formats::Format;
+use hooks::{Event, Hooks, TaskEvent};
+use json_parser::JsonValue;
+use messages::Locale;
+use output::{Output, OutputFormat};
+use paths::Env;
+use stats::Stats;
+use store::{TaskStore, TextFileStore};
+use style::Style;
+use timelog::{TimeEntry, TimeReport};
+
+/// 使い方を表示する
+pub fn print_help() {
+    println!(
+        r#"
+todo - A simple TODO CLI tool
+
+USAGE:
+    todo <COMMAND> [OPTIONS]
+
+COMMANDS:
+    add <task>    Add a new task (use "-" to read one task per line from stdin)
+    list          List all tasks
+    done <ids>    Mark tasks as done (e.g. 3, 1 2 5, 3-7)
+    done <text>   Mark the pending task containing <text> as done
+    rm <ids>      Remove tasks permanently (or --all-done)
+    clear         Clear all completed tasks (moved to <file>.done.txt)
+    start <id>    Start tracking time on a task (stops the running one)
+    stop          Stop tracking time
+    time          Show tracked time per task, tag and day
+    stats         Show counts, completion rate and the oldest pending tasks
+    export        Print all tasks in another format (see --format)
+    import <file> Append tasks from a Markdown, CSV, iCal or JSON file
+    sync          Push/pull tasks to a REST API (see --remote)
+    help          Show this help message
+
+OPTIONS:
+    -f, --file <path>    Use a custom file (default: see FILES)
+    -v, --verbose        Show verbose output
+    -p, --parent <id>    Add the task as a subtask of <id>
+    --cascade            done/rm: also apply to subtasks
+    --all-done           rm: remove every completed task
+    --ids-only           list: print only task IDs, one per line
+    --watch              list: redraw whenever the file changes (Ctrl-C to quit)
+    --overdue            list: show only overdue tasks
+    -s, --sort <key>     Sort list by file, priority, due or status
+    --days <n>           stats: completion rate window (default: 7)
+    --json               Print the result of any command as JSON
+    --format <fmt>       export/import format: md, csv, ical or json
+                         (import detects it from the file if omitted)
+    --remote <url>       sync: server URL (e.g. http://localhost:8080)
+    --dry-run            sync: show the changes without writing
+    --no-color           Disable colored output (also: NO_COLOR env)
+    --no-hooks           Do not run hooks for this command
+    --encrypt            Encrypt the task file with a passphrase
+                         (asked without echo, or TODO_PASSPHRASE env).
+                         Encrypted files are detected automatically.
+
+FILES:
+    The task file is chosen in this order:
+      TODO_FILE (a path), TODO_LIST (a list name, e.g. work), -f/--file,
+      the config file, ./todo.txt if it exists, and finally
+      $XDG_DATA_HOME/todo/todo.txt (~/.local/share/todo/todo.txt).
+    TODO_LIST=work uses work.txt in the same data directory.
+
+CONFIG:
+    ~/.config/todo/config ($XDG_CONFIG_HOME/todo/config) sets defaults for
+    file, sort, color (on/off) and date_format (e.g. %d/%m/%Y).
+    Command-line options override the config file, and the environment
+    variables TODO_SORT, TODO_COLOR and TODO_DATE_FORMAT override both.
+
+HOOKS:
+    Executables in ~/.config/todo/hooks/ named after an event
+    (task-added, task-completed, task-removed, task-archived),
+    optionally with an extension, run after the command succeeds.
+    They get the event and the task as JSON on stdin and are
+    killed after hook_timeout seconds (default: 5). Set the directory
+    with the hooks key in the config file.
+
+TASK SYNTAX:
+    (A) ... (B) ...      Priority, highlighted in list
+    due:YYYY-MM-DD       Due date, overdue tasks are shown in red
+    +project @context    Tags, counted by stats
+
+EXAMPLES:
+    todo add "Buy milk"
+    todo list
+    todo done 1
+    todo done 1 2 5
+    todo done milk
+    todo rm 3-7
+    todo rm --all-done
+    todo add --parent 1 "Compare prices"
+    todo done 1 --cascade
+    todo list --verbose
+    todo list --watch
+    cat tasks.txt | todo add -
+    todo list --overdue --ids-only | xargs -n1 todo done
+    todo export --format ical > tasks.ics
+    todo import tasks.csv
+    todo stats --days 30 --json
+    todo list --json | jq '.tasks[].description'
+    todo start 3 && todo stop && todo time
+    todo sync --remote http://localhost:8080 --dry-run
+"#
+    );
+}
+
+/// コマンドの種類
+#[derive(Debug)]
+pub enum Command {
+    Add(String),
+    List,
+    Done(Selector),
+    Remove(Vec<usize>),
+    Clear,
+    Start(Selector),
+    Stop,
+    Time,
+    Stats,
+    Export,
+    Import(PathBuf),
+    Sync,
+    Help,
+}
+
+/// done / start の対象の指定方法
+#[derive(Debug, PartialEq)]
+pub enum Selector {
+    /// ID (範囲・複数指定を展開済み)
+    Ids(Vec<usize>),
+    /// 説明に含まれる文字列 (未完了タスクから 1 件に絞る)
+    Text(String),
+}
+
+impl Selector {
+    /// 数字で始まる引数だけなら ID、それ以外は説明の検索とみなす
+    pub fn parse(args: &[&str]) -> Result<Self, String> {
+        if args.iter().all(|a| a.starts_with(|c: char| c.is_ascii_digit())) {
+            Ok(Selector::Ids(parse_ids(args)?))
+        } else {
+            Ok(Selector::Text(args.join(" ")))
+        }
+    }
+
+    /// 対象のタスク ID を決める
+    pub fn resolve(&self, tasks: &[Task]) -> Result<Vec<usize>, String> {
+        match self {
+            Selector::Ids(ids) => {
+                ensure_exist(tasks, ids)?;
+                Ok(ids.clone())
+            }
+            Selector::Text(query) => find_pending(tasks, query).map(|id| vec![id]),
+        }
+    }
+}
+
+/// 設定
+#[derive(Debug)]
+pub struct Config {
+    pub command: Command,
+    pub file_path: PathBuf,
+    pub verbose: bool,
+    /// add で親にするタスク ID
+    pub parent: Option<usize>,
+    /// done で未完了のサブタスクもまとめて完了にするか
+    pub cascade: bool,
+    /// 色付けを無効にするか
+    pub no_color: bool,
+    /// list の並び順
+    pub sort: SortKey,
+    /// 日付の表示書式
+    pub date_format: String,
+    /// list で ID だけを出力するか
+    pub ids_only: bool,
+    /// list で期限切れのタスクだけを表示するか
+    pub overdue: bool,
+    /// export / import の形式 (import では省略時に自動判定)
+    pub format: Option<Format>,
+    /// stats の集計期間 (日数)
+    pub days: usize,
+    /// 結果の出力形式
+    pub output: OutputFormat,
+    /// rm で完了済みのタスクをすべて削除するか
+    pub all_done: bool,
+    /// sync の相手 (`http://host:port`)
+    pub remote: Option<String>,
+    /// sync で差分を表示するだけにするか
+    pub dry_run: bool,
+    /// イベントで実行するフック (None なら実行しない)
+    pub hooks: Option<Hooks>,
+    /// タスクファイルを暗号化するか (暗号化済みのファイルは指定がなくても復号する)
+    pub encrypt: bool,
+    /// list をファイルの変更のたびに表示し直すか
+    pub watch: bool,
+}
+
+impl Config {
+    /// 設定ファイルを使わずに引数をパースする
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        Self::parse_with(args, &ConfigFile::default())
+    }
+
+    /// 設定ファイルの値を既定値にして引数をパースする
+    pub fn parse_with(args: &[String], defaults: &ConfigFile) -> Result<Self, String> {
+        let mut file_path = defaults
+            .file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from("todo.txt"));
+        let mut verbose = false;
+        let mut parent = None;
+        let mut cascade = false;
+        let mut no_color = defaults.color == Some(false);
+        let mut ids_only = false;
+        let mut overdue = false;
+        let mut format = None;
+        let mut days = 7;
+        let mut output = OutputFormat::Text;
+        let mut all_done = false;
+        let mut remote = None;
+        let mut dry_run = false;
+        let mut encrypt = false;
+        let mut watch = false;
+        let mut hooks = defaults.hooks.clone().map(|dir| Hooks {
+            dir,
+            timeout: defaults.hook_timeout.unwrap_or(hooks::DEFAULT_TIMEOUT),
+        });
+        let mut sort = defaults.sort.unwrap_or(SortKey::File);
+        let date_format = defaults
+            .date_format
+            .clone()
+            .unwrap_or_else(|| date::DEFAULT_FORMAT.to_string());
+        let mut remaining_args: Vec<&str> = Vec::new();
+
+        let mut iter = args.iter().peekable();
+
+        // オプションとコマンドを分離
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "-f" | "--file" => {
+                    let path = iter.next().ok_or("--file requires a path")?;
+                    file_path = PathBuf::from(path);
+                }
+                "-v" | "--verbose" => {
+                    verbose = true;
+                }
+                "-p" | "--parent" => {
+                    let id = iter.next().ok_or("--parent requires a task ID")?;
+                    parent = Some(id.parse().map_err(|_| "Invalid parent task ID")?);
+                }
+                "--cascade" => {
+                    cascade = true;
+                }
+                "--no-color" => {
+                    no_color = true;
+                }
+                "--ids-only" => {
+                    ids_only = true;
+                }
+                "--overdue" => {
+                    overdue = true;
+                }
+                "--days" => {
+                    let n = iter.next().ok_or("--days requires a number")?;
+                    days = n.parse().map_err(|_| "Invalid number of days")?;
+                }
+                "--json" => {
+                    output = OutputFormat::Json;
+                }
+                "--all-done" => {
+                    all_done = true;
+                }
+                "--remote" => {
+                    let url = iter.next().ok_or("--remote requires a URL")?;
+                    remote = Some(url.clone());
+                }
+                "--dry-run" => {
+                    dry_run = true;
+                }
+                "--no-hooks" => {
+                    hooks = None;
+                }
+                "--encrypt" => {
+                    encrypt = true;
+                }
+                "--watch" => {
+                    watch = true;
+                }
+                "--format" => {
+                    let name = iter.next().ok_or("--format requires a format")?;
+                    format = Some(Format::parse(name)?);
+                }
+                "-s" | "--sort" => {
+                    let key = iter.next().ok_or("--sort requires a key")?;
+                    sort = SortKey::parse(key)?;
+                }
+                _ => {
+                    remaining_args.push(arg);
+                }
+            }
+        }
+
+        if remaining_args.is_empty() {
+            return Err("No command specified".to_string());
+        }
+
+        let command = match remaining_args[0] {
+            "add" => {
+                if remaining_args.len() < 2 {
+                    return Err("add requires a task description".to_string());
+                }
+                Command::Add(remaining_args[1..].join(" "))
+            }
+            "list" => Command::List,
+            "done" => {
+                if remaining_args.len() < 2 {
+                    return Err("done requires a task ID".to_string());
+                }
+                Command::Done(Selector::parse(&remaining_args[1..])?)
+            }
+            "rm" => {
+                if remaining_args.len() < 2 && !all_done {
+                    return Err("rm requires a task ID or --all-done".to_string());
+                }
+                Command::Remove(parse_ids(&remaining_args[1..])?)
+            }
+            "clear" => Command::Clear,
+            "start" => {
+                if remaining_args.len() < 2 {
+                    return Err("start requires a task ID".to_string());
+                }
+                Command::Start(Selector::parse(&remaining_args[1..])?)
+            }
+            "stop" => Command::Stop,
+            "time" => Command::Time,
+            "stats" => Command::Stats,
+            "export" => Command::Export,
+            "import" => {
+                if remaining_args.len() < 2 {
+                    return Err("import requires a file".to_string());
+                }
+                Command::Import(PathBuf::from(remaining_args[1]))
+            }
+            "sync" => {
+                if remote.is_none() {
+                    return Err("sync requires --remote <url>".to_string());
+                }
+                Command::Sync
+            }
+            "help" | "-h" | "--help" => Command::Help,
+            other => return Err(format!("Unknown command: {}", other)),
+        };
+
+        if watch && !matches!(command, Command::List) {
+            return Err("--watch is only supported by list".to_string());
+        }
+
+        Ok(Config {
+            command,
+            file_path,
+            verbose,
+            parent,
+            cascade,
+            no_color,
+            sort,
+            date_format,
+            ids_only,
+            overdue,
+            format,
+            days,
+            output,
+            all_done,
+            remote,
+            dry_run,
+            hooks,
+            encrypt,
+            watch,
+        })
+    }
+
+    /// 環境変数の指定を反映する (コマンドライン引数や設定ファイルより優先)
+    pub fn apply_env(&mut self, env: &Env) -> Result<(), String> {
+        if let Some(file) = env.env_file() {
+            self.file_path = file;
+        }
+        if let Some(key) = &env.todo_sort {
+            self.sort = SortKey::parse(key).map_err(|e| format!("TODO_SORT: {}", e))?;
+        }
+        if let Some(value) = &env.todo_color {
+            let color =
+                config_file::parse_bool(value).map_err(|e| format!("TODO_COLOR: {}", e))?;
+            self.no_color = !color;
+        }
+        if let Some(format) = &env.todo_date_format {
+            self.date_format = format.clone();
+        }
+        Ok(())
+    }
+}
+
+/// list の並び順
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// ファイルに書かれた順
+    File,
+    /// 優先度 (A が先、優先度なしは最後)
+    Priority,
+    /// 期限が近い順 (期限なしは最後)
+    Due,
+    /// 未完了が先
+    Status,
+}
+
+impl SortKey {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "file" => Ok(SortKey::File),
+            "priority" => Ok(SortKey::Priority),
+            "due" => Ok(SortKey::Due),
+            "status" => Ok(SortKey::Status),
+            other => Err(format!("Unknown sort key: {}", other)),
+        }
+    }
+}
+
+/// タスク
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Task {
+    pub id: usize,
+    pub description: String,
+    pub done: bool,
+    /// 親タスクの ID (トップレベルなら None)
+    pub parent: Option<usize>,
+    /// 作成日 (YYYY-MM-DD)
+    pub created: Option<String>,
+    /// 完了日 (YYYY-MM-DD)
+    pub completed: Option<String>,
+}
+
+impl Task {
+    /// 保存形式の 1 行を読む (インデントは取り除いてから渡す)
+    ///
+    /// ```
+    /// use cli_tool::Task;
+    ///
+    /// let task = Task::from_line(0, "[x] (A) Pay rent due:2024-05-01 id:3 completed:2024-04-30");
+    /// assert_eq!(task.id, 3);
+    /// assert!(task.done);
+    /// assert_eq!(task.priority(), Some('A'));
+    /// assert_eq!(task.due(), Some("2024-05-01"));
+    /// assert_eq!(task.completed.as_deref(), Some("2024-04-30"));
+    /// ```
+    pub fn from_line(id: usize, line: &str) -> Self {
+        let done = line.starts_with("[x] ");
+        let mut description = if done || line.starts_with("[ ] ") {
+            line[4..].to_string()
+        } else {
+            line.to_string()
+        };
+
+        // 行末の `id:` / `created:` / `completed:` はツールが書くメタデータ
+        let mut id = id;
+        let mut created = None;
+        let mut completed = None;
+        while let Some((rest, last)) = description.rsplit_once(' ') {
+            match last.split_once(':') {
+                Some(("id", n)) if n.parse::<usize>().is_ok_and(|n| n > 0) => {
+                    id = n.parse().unwrap();
+                }
+                Some(("created", d)) if date::is_valid(d) => created = Some(d.to_string()),
+                Some(("completed", d)) if date::is_valid(d) => completed = Some(d.to_string()),
+                _ => break,
+            }
+            description = rest.to_string();
+        }
+
+        Task {
+            id,
+            description,
+            done,
+            parent: None,
+            created,
+            completed,
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        let prefix = if self.done { "[x]" } else { "[ ]" };
+        let mut line = format!("{} {} id:{}", prefix, self.description, self.id);
+        if let Some(created) = &self.created {
+            line.push_str(&format!(" created:{}", created));
+        }
+        if let Some(completed) = &self.completed {
+            line.push_str(&format!(" completed:{}", completed));
+        }
+        line
+    }
+
+    /// 説明中の `+project` / `@context` タグ
+    pub fn tags(&self) -> Vec<&str> {
+        self.description
+            .split_whitespace()
+            .filter(|w| w.len() > 1 && (w.starts_with('+') || w.starts_with('@')))
+            .collect()
+    }
+
+    /// 説明の先頭にある `(A)` 形式の優先度
+    pub fn priority(&self) -> Option<char> {
+        match self.description.as_bytes() {
+            [b'(', p, b')', b' ', ..] if p.is_ascii_uppercase() => Some(*p as char),
+            _ => None,
+        }
+    }
+
+    /// 説明中の `due:YYYY-MM-DD` で指定された期限
+    pub fn due(&self) -> Option<&str> {
+        self.description
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix("due:"))
+            .find(|d| date::is_valid(d))
+    }
+
+    /// 未完了で期限を過ぎているか
+    pub fn is_overdue(&self, today: &str) -> bool {
+        !self.done && self.due().is_some_and(|due| due < today)
+    }
+}
+
+/// 設定どおりのファイルに対してコマンドを実行し、結果を表示する
+pub fn run(config: Config) -> Result<(), String> {
+    let mut out = Output::new(config.output).with_locale(Locale::from_env());
+    let result = open_store(&config).and_then(|mut store| {
+        if config.watch {
+            return watch_list(&config, &mut store);
+        }
+        execute(&config, &mut store, &mut out)
+    });
+    out.finish(&result);
+    result
+}
+
+/// ファイルが変わるたびに list を表示し直す (Ctrl-C で終了するまで戻らない)
+///
+/// 書きかけのファイルを読んでエラーになっても、表示して監視を続ける。
+fn watch_list(config: &Config, store: &mut dyn TaskStore) -> Result<(), String> {
+    let mut watcher = watch::Watcher::new(vec![config.file_path.clone()], watch::DEFAULT_INTERVAL);
+    let clear = io::stdout().is_terminal() && config.output == OutputFormat::Text;
+
+    loop {
+        if clear {
+            // 画面を消してカーソルを左上に戻す
+            print!("\x1b[2J\x1b[H");
+            let _ = io::stdout().flush();
+        }
+        let mut out = Output::new(config.output).with_locale(Locale::from_env());
+        let result = execute(config, store, &mut out);
+        out.finish(&result);
+        watcher.wait();
+    }
+}
+
+/// タスクファイルを開く
+///
+/// `--encrypt` が指定されているか、ファイルが暗号化済みならパスフレーズを聞く。
+/// 平文のファイルに `--encrypt` を指定したときは、その場で暗号化し直す。
+fn open_store(config: &Config) -> Result<TextFileStore, String> {
+    let path = config.file_path.clone();
+    let encrypted = crypto::is_encrypted_file(&path);
+    if matches!(config.command, Command::Help) || !(config.encrypt || encrypted) {
+        return Ok(TextFileStore::new(path));
+    }
+
+    // 新しく暗号化するときは打ち間違いを防ぐために 2 回聞く
+    let passphrase = passphrase::obtain(!encrypted)?;
+    let store = TextFileStore::encrypted(path, &passphrase);
+    if !encrypted {
+        store.rewrite()?;
+    }
+    Ok(store)
+}
+
+/// コマンドを実行する (保存先と出力先は呼び出し側が決める)
+///
+/// コマンドが成功したら、その間に起きたイベントのフックを実行する。
+/// フックの失敗は警告として出力するだけで、結果はエラーにしない。
+pub fn execute(config: &Config, store: &mut dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let mut events = Vec::new();
+    run_command(config, store, out, &mut events)?;
+
+    if let Some(hooks) = &config.hooks {
+        for warning in hooks.dispatch(&events) {
+            out.warn(warning);
+        }
+    }
+    Ok(())
+}
+
+fn run_command(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+) -> Result<(), String> {
+    match &config.command {
+        Command::Add(task) => add_task(config, store, out, events, task),
+        Command::List => list_tasks(config, store, out),
+        Command::Done(selector) => mark_done(config, store, out, events, selector),
+        Command::Remove(ids) => remove_tasks(config, store, out, events, ids),
+        Command::Clear => clear_done(config, store, out, events),
+        Command::Start(selector) => start_timer(store, out, selector),
+        Command::Stop => stop_timer(store, out),
+        Command::Time => show_time(store, out),
+        Command::Stats => show_stats(config, store, out),
+        Command::Export => export_tasks(config, store, out),
+        Command::Import(path) => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+            let format = match config.format {
+                Some(format) => format,
+                None => Format::detect(path, &text)?,
+            };
+            import_tasks(store, out, events, format, &text)?;
+            Ok(())
+        }
+        Command::Sync => sync_tasks(config, store, out),
+        Command::Help => {
+            print_help();
+            Ok(())
+        }
+    }
+}
+
+fn add_task(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    description: &str,
+) -> Result<(), String> {
+    if description == "-" {
+        let stdin = io::stdin();
+        return add_from_reader(config, store, out, events, stdin.lock());
+    }
+    add_tasks(config, store, out, events, &[description.to_string()])
+}
+
+/// 1 行 1 タスクとして読み込んで追加する (空行は無視)
+fn add_from_reader(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    reader: impl BufRead,
+) -> Result<(), String> {
+    let mut descriptions = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("Failed to read stdin: {}", e))?;
+        let line = line.trim();
+        if !line.is_empty() {
+            descriptions.push(line.to_string());
+        }
+    }
+
+    if descriptions.is_empty() {
+        return Err(out.text("no_stdin_tasks", &[]));
+    }
+
+    add_tasks(config, store, out, events, &descriptions)
+}
+
+fn add_tasks(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    descriptions: &[String],
+) -> Result<(), String> {
+    let mut tasks = store.load()?;
+    let used = used_ids(store, &tasks)?;
+    let added: Vec<Task> = store::allocate_ids(&used, descriptions.len())
+        .into_iter()
+        .zip(descriptions)
+        .map(|(id, description)| Task {
+            id,
+            description: description.clone(),
+            done: false,
+            parent: config.parent,
+            created: Some(date::today()),
+            completed: None,
+        })
+        .collect();
+
+    match config.parent {
+        None => {
+            for task in &added {
+                store.append(task)?;
+            }
+        }
+        Some(parent_id) => {
+            let index = tasks
+                .iter()
+                .position(|t| t.id == parent_id)
+                .ok_or_else(|| out.text("parent_not_found", &[&parent_id]))?;
+
+            // 親のサブツリーの末尾に差し込む
+            let insert_at = index + 1 + descendants(&tasks, parent_id).len();
+            tasks.splice(insert_at..insert_at, added.iter().cloned());
+            store.save(&tasks)?;
+        }
+    }
+
+    for task in &added {
+        out.msg("added", &[&task.description]);
+        events.push(TaskEvent::new(Event::Added, task));
+    }
+    out.field("added", formats::tasks_to_json(&added));
+
+    if config.verbose {
+        out.msg("file", &[&format!("{:?}", config.file_path)]);
+    }
+
+    Ok(())
+}
+
+fn list_tasks(config: &Config, store: &dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let today = date::today();
+    let mut tasks = store.load()?;
+
+    if config.overdue {
+        tasks.retain(|t| t.is_overdue(&today));
+    }
+
+    let tasks = sort_tasks(&tasks, config.sort);
+
+    if out.is_json() {
+        let ids = tasks.iter().map(|t| output::number(t.id)).collect();
+        out.field("ids", JsonValue::Array(ids));
+        if !config.ids_only {
+            out.field("tasks", formats::tasks_to_json(&tasks));
+        }
+        return Ok(());
+    }
+
+    // パイプ向け: ID だけを 1 行ずつ出力する
+    if config.ids_only {
+        for task in &tasks {
+            out.line(task.id.to_string());
+        }
+        return Ok(());
+    }
+
+    if tasks.is_empty() {
+        out.msg("no_tasks", &[]);
+        return Ok(());
+    }
+
+    let style = Style::detect(config.no_color);
+
+    out.msg("tasks_header", &[]);
+    for task in &tasks {
+        let status = if task.done { "✓" } else { " " };
+        let indent = "  ".repeat(depth(&tasks, task));
+        let description = format_dates(&task.description, &config.date_format);
+        let line = format!("{} [{}] {}", task.id, status, description);
+        out.line(format!("  {}{}", indent, render_task(&style, task, &line, &today)));
+    }
+
+    if config.verbose {
+        let done_count = tasks.iter().filter(|t| t.done).count();
+        out.msg("totals", &[&tasks.len(), &done_count, &(tasks.len() - done_count)]);
+    }
+
+    Ok(())
+}
+
+/// 複数のタスクを完了にする (読み込みと保存は 1 回だけ)
+///
+/// 途中でエラーになった場合は何も保存しない。
+fn mark_done(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    selector: &Selector,
+) -> Result<(), String> {
+    let mut tasks = store.load()?;
+    let ids = &selector.resolve(&tasks)?;
+
+    let today = date::today();
+    let mut completed = Vec::new();
+
+    for &id in ids {
+        let index = tasks.iter().position(|t| t.id == id).unwrap();
+        if tasks[index].done {
+            out.msg("already_done", &[&id]);
+            continue;
+        }
+
+        // 同時に指定されたサブタスクは後で完了になるので数えない
+        let pending: Vec<usize> = descendants(&tasks, id)
+            .into_iter()
+            .filter(|&i| !tasks[i].done && !ids.contains(&tasks[i].id))
+            .collect();
+
+        if !pending.is_empty() && !config.cascade {
+            return Err(out.text("pending_subtasks", &[&id, &pending.len()]));
+        }
+
+        for i in pending.into_iter().chain([index]) {
+            tasks[i].done = true;
+            tasks[i].completed = Some(today.clone());
+            out.msg("done", &[&tasks[i].description]);
+            completed.push(tasks[i].clone());
+        }
+    }
+
+    if !completed.is_empty() {
+        store.save(&tasks)?;
+    }
+    out.field("done", formats::tasks_to_json(&completed));
+    events.extend(completed.iter().map(|t| TaskEvent::new(Event::Completed, t)));
+
+    Ok(())
+}
+
+/// タスクを完全に削除する (アーカイブには残さない)
+fn remove_tasks(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    ids: &[usize],
+) -> Result<(), String> {
+    let tasks = store.load()?;
+    ensure_exist(&tasks, ids)?;
+
+    let mut targets: HashSet<usize> = ids.iter().copied().collect();
+    for &id in ids {
+        let children: Vec<usize> = descendants(&tasks, id)
+            .into_iter()
+            .map(|i| tasks[i].id)
+            .filter(|child| !targets.contains(child))
+            .collect();
+        if !children.is_empty() && !config.cascade {
+            return Err(out.text("has_subtasks", &[&id, &children.len()]));
+        }
+        targets.extend(children);
+    }
+
+    if config.all_done {
+        targets.extend(tasks.iter().filter(|t| t.done).map(|t| t.id));
+    }
+
+    let (removed, kept) = split_tasks(&tasks, &targets);
+    out.field("removed", formats::tasks_to_json(&removed));
+
+    if removed.is_empty() {
+        out.msg("nothing_to_remove", &[]);
+        return Ok(());
+    }
+
+    store.save(&kept)?;
+    events.extend(removed.iter().map(|t| TaskEvent::new(Event::Removed, t)));
+
+    out.msg("removed", &[&removed.len()]);
+    if config.verbose {
+        for task in &removed {
+            out.line(format!("  - {}", task.description));
+        }
+    }
+
+    Ok(())
+}
+
+fn clear_done(
+    config: &Config,
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+) -> Result<(), String> {
+    let tasks = store.load()?;
+    let targets: HashSet<usize> = tasks.iter().filter(|t| t.done).map(|t| t.id).collect();
+
+    // pending のみを保存
+    let (done, pending) = split_tasks(&tasks, &targets);
+    out.field("cleared", formats::tasks_to_json(&done));
+
+    if done.is_empty() {
+        out.msg("nothing_to_clear", &[]);
+        return Ok(());
+    }
+
+    store.save(&pending)?;
+
+    // 完了タスクは捨てずにアーカイブへ移す (stats の集計に使う)
+    store.archive(&done)?;
+    events.extend(done.iter().map(|t| TaskEvent::new(Event::Archived, t)));
+
+    out.msg("cleared", &[&done.len()]);
+
+    if config.verbose {
+        for task in done {
+            out.line(format!("  - {}", task.description));
+        }
+    }
+
+    Ok(())
+}
+
+/// タスクの作業時間の計測を始める (計測中のものがあれば止める)
+fn start_timer(store: &mut dyn TaskStore, out: &mut Output, selector: &Selector) -> Result<(), String> {
+    let tasks = store.load()?;
+    let id = match selector.resolve(&tasks)?.as_slice() {
+        [id] => *id,
+        _ => return Err(out.text("single_task", &[])),
+    };
+    let task = tasks.iter().find(|t| t.id == id).unwrap();
+    if task.done {
+        return Err(out.text("already_done", &[&id]));
+    }
+    out.field("started", output::number(id));
+
+    let mut entries = store.load_entries()?;
+    let now = date::now();
+    if let Some(running) = entries.iter_mut().find(|e| e.end.is_none()) {
+        if running.task == id {
+            out.msg("already_tracking", &[&task.description]);
+            return Ok(());
+        }
+        running.end = Some(now);
+        report_stopped(out, &tasks, running, now);
+    }
+
+    entries.push(TimeEntry { task: id, start: now, end: None });
+    store.save_entries(&entries)?;
+    out.msg("started", &[&task.description]);
+    Ok(())
+}
+
+fn stop_timer(store: &mut dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let mut entries = store.load_entries()?;
+    let now = date::now();
+    let running = entries
+        .iter_mut()
+        .find(|e| e.end.is_none())
+        .ok_or_else(|| out.text("not_tracking", &[]))?;
+
+    running.end = Some(now);
+    report_stopped(out, &store.load()?, running, now);
+    store.save_entries(&entries)
+}
+
+fn report_stopped(out: &mut Output, tasks: &[Task], entry: &TimeEntry, now: u64) {
+    let description = match tasks.iter().find(|t| t.id == entry.task) {
+        Some(task) => task.description.clone(),
+        None => out.text("removed_task", &[]),
+    };
+    let duration = timelog::format_duration(entry.duration(now));
+    out.msg("stopped", &[&description, &duration]);
+    out.field("stopped", entry.to_json(now));
+}
+
+/// タスク・タグ・日ごとの作業時間を表示する
+fn show_time(store: &dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    // 片付けたタスクの記録も説明付きで出せるようにアーカイブも渡す
+    let mut tasks = store.load_archive()?;
+    tasks.extend(store.load()?);
+
+    let report = TimeReport::compute(&store.load_entries()?, &tasks, date::now());
+    if out.is_json() {
+        out.field("time", report.to_json());
+    } else {
+        out.raw(&report.to_text());
+    }
+    Ok(())
+}
+
+fn show_stats(config: &Config, store: &dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let tasks = store.load()?;
+    let archive = store.load_archive()?;
+    let stats = Stats::compute(&tasks, &archive, &date::today(), config.days);
+
+    if out.is_json() {
+        out.field("stats", stats.to_json());
+    } else {
+        out.raw(&stats.to_text());
+    }
+    Ok(())
+}
+
+fn export_tasks(config: &Config, store: &dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let tasks = store.load()?;
+    let format = config.format.unwrap_or(Format::Markdown);
+    out.raw(&formats::write(format, &tasks));
+    Ok(())
+}
+
+/// 読み込んだタスクを既存のタスクの後ろに追加し、追加した件数を返す
+fn import_tasks(
+    store: &mut dyn TaskStore,
+    out: &mut Output,
+    events: &mut Vec<TaskEvent>,
+    format: Format,
+    text: &str,
+) -> Result<usize, String> {
+    // 親の直後に子が並ぶ順序にそろえる
+    let imported = sort_tasks(&formats::read(format, text)?, SortKey::File);
+    let mut tasks = store.load()?;
+
+    // ファイル内の仮の ID を、既存のタスクと衝突しない ID に振り直す
+    let used = used_ids(store, &tasks)?;
+    let count = imported.len();
+    let ids: HashMap<usize, usize> = imported
+        .iter()
+        .map(|t| t.id)
+        .zip(store::allocate_ids(&used, count))
+        .collect();
+    for task in imported {
+        tasks.push(Task {
+            id: ids[&task.id],
+            // 1 行 1 タスクの保存形式に合わせて改行は空白にする
+            description: task.description.replace(['\r', '\n'], " "),
+            parent: task.parent.and_then(|p| ids.get(&p).copied()),
+            ..task
+        });
+    }
+
+    store.save(&tasks)?;
+    let added = &tasks[tasks.len() - count..];
+    events.extend(added.iter().map(|t| TaskEvent::new(Event::Added, t)));
+    out.msg("imported", &[&count]);
+    out.field("imported", output::number(count));
+    Ok(count)
+}
+
+/// 新しいタスクに使えない ID
+///
+/// 作業時間の記録が指している ID は、タスクを片付けた後も再利用しない。
+fn used_ids(store: &dyn TaskStore, tasks: &[Task]) -> Result<HashSet<usize>, String> {
+    let mut used: HashSet<usize> = tasks.iter().map(|t| t.id).collect();
+    used.extend(store.load_entries()?.iter().map(|e| e.task));
+    Ok(used)
+}
+
+/// REST API と同期する (`--dry-run` なら差分の表示だけ)
+fn sync_tasks(config: &Config, store: &mut dyn TaskStore, out: &mut Output) -> Result<(), String> {
+    let remote = config.remote.as_deref().unwrap_or_default();
+    let url = format!("{}/tasks", remote.trim_end_matches('/'));
+
+    let response = http::request("GET", &url, None)?;
+    let theirs = match response.status {
+        200 => sync::Document::parse(&response.body)?,
+        // まだ誰も書き込んでいない
+        404 => sync::Document::default(),
+        status => return Err(format!("GET {} failed with status {}", url, status)),
+    };
+
+    // 手元の更新時刻はタスクファイルの最終更新時刻とみなす
+    let local_updated = fs::metadata(&config.file_path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+
+    let state = sync::state_path(&config.file_path);
+    let base = sync::load_state(&state)?;
+    let local = store.load()?;
+    let merge = sync::merge(&base, &local, &theirs.tasks, local_updated >= theirs.updated);
+
+    for change in &merge.pushed {
+        out.line(format!("push {}", change));
+    }
+    for change in &merge.pulled {
+        out.line(format!("pull {}", change));
+    }
+    let changes = |list: &[sync::Change]| JsonValue::Array(list.iter().map(|c| c.to_json()).collect());
+    out.field("pushed", changes(&merge.pushed));
+    out.field("pulled", changes(&merge.pulled));
+    out.field("dry_run", JsonValue::Bool(config.dry_run));
+
+    if config.dry_run {
+        out.msg("dry_run", &[]);
+        return Ok(());
+    }
+
+    if !merge.pulled.is_empty() {
+        store.save(&merge.tasks)?;
+    }
+    let document = sync::Document {
+        updated: if merge.pushed.is_empty() { theirs.updated } else { date::now() },
+        tasks: merge.tasks,
+    };
+    if !merge.pushed.is_empty() {
+        let response = http::request("PUT", &url, Some(&document.to_json().to_string()))?;
+        if !(200..300).contains(&response.status) {
+            return Err(format!("PUT {} failed with status {}", url, response.status));
+        }
+    }
+    sync::save_state(&state, &document)?;
+
+    if merge.pushed.is_empty() && merge.pulled.is_empty() {
+        out.msg("in_sync", &[]);
+    } else {
+        out.msg("synced", &[&merge.pushed.len(), &merge.pulled.len()]);
+    }
+    Ok(())
+}
+
+/// 指定した ID がすべて存在するか確認する
+fn ensure_exist(tasks: &[Task], ids: &[usize]) -> Result<(), String> {
+    match ids.iter().find(|id| !tasks.iter().any(|t| t.id == **id)) {
+        Some(id) => Err(format!("Task {} not found", id)),
+        None => Ok(()),
+    }
+}
+
+/// 説明に `query` を含む未完了タスクを 1 件だけ探す (大文字小文字は区別しない)
+///
+/// 複数見つかった場合は候補を並べたエラーにする。
+fn find_pending(tasks: &[Task], query: &str) -> Result<usize, String> {
+    let needle = query.to_lowercase();
+    let matches: Vec<&Task> = tasks
+        .iter()
+        .filter(|t| !t.done && t.description.to_lowercase().contains(&needle))
+        .collect();
+
+    match matches.as_slice() {
+        [] => Err(format!("No pending task matches '{}'", query)),
+        [task] => Ok(task.id),
+        _ => {
+            let candidates: Vec<String> = matches
+                .iter()
+                .map(|t| format!("  {} {}", t.id, t.description))
+                .collect();
+            Err(format!(
+                "'{}' matches {} pending tasks:\n{}",
+                query,
+                matches.len(),
+                candidates.join("\n")
+            ))
+        }
+    }
+}
+
+/// 指定した ID のタスクを取り除き、(取り除いたもの, 残ったもの) を返す
+///
+/// 消える親の下にいたタスクは、残っている祖先に付け替える。
+fn split_tasks(tasks: &[Task], targets: &HashSet<usize>) -> (Vec<Task>, Vec<Task>) {
+    let parents: HashMap<usize, Option<usize>> =
+        tasks.iter().map(|t| (t.id, t.parent)).collect();
+    let (removed, mut kept): (Vec<Task>, Vec<Task>) =
+        tasks.iter().cloned().partition(|t| targets.contains(&t.id));
+
+    for task in &mut kept {
+        while let Some(p) = task.parent {
+            if !targets.contains(&p) {
+                break;
+            }
+            task.parent = parents[&p];
+        }
+    }
+
+    (removed, kept)
+}
+
+/// `3`、`1,2`、`3-7` 形式の ID 指定を展開する (重複は除く)
+fn parse_ids(args: &[&str]) -> Result<Vec<usize>, String> {
+    let parse_one = |s: &str| -> Result<usize, String> {
+        s.trim()
+            .parse()
+            .map_err(|_| format!("Invalid task ID: {}", s))
+    };
+
+    let mut ids = Vec::new();
+    for part in args.iter().flat_map(|a| a.split(',')).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse_one(start)?, parse_one(end)?);
+                if start > end {
+                    return Err(format!("Invalid ID range: {}", part));
+                }
+                ids.extend(start..=end);
+            }
+            None => ids.push(parse_one(part)?),
+        }
+    }
+
+    let mut seen = HashSet::new();
+    ids.retain(|id| seen.insert(*id));
+    Ok(ids)
+}
+
+/// タスクの状態に応じて一覧の 1 行を装飾する
+///
+/// 完了は淡色、期限切れは赤、それ以外は優先度で色分けする。
+fn render_task(style: &Style, task: &Task, line: &str, today: &str) -> String {
+    if task.done {
+        return style.dim(line);
+    }
+    if task.is_overdue(today) {
+        return style.red(line);
+    }
+    match task.priority() {
+        Some('A') => style.red(line),
+        Some('B') => style.yellow(line),
+        Some(_) => style.green(line),
+        None => line.to_string(),
+    }
+}
+
+/// 階層を保ったまま、兄弟同士を指定キーで並べ替える
+fn sort_tasks(tasks: &[Task], key: SortKey) -> Vec<Task> {
+    fn visit(tasks: &[Task], parent: Option<usize>, key: SortKey, out: &mut Vec<Task>) {
+        let mut siblings: Vec<&Task> = tasks.iter().filter(|t| t.parent == parent).collect();
+        match key {
+            SortKey::File => {}
+            SortKey::Priority => siblings.sort_by_key(|t| t.priority().unwrap_or('~')),
+            SortKey::Due => siblings.sort_by_key(|t| t.due().unwrap_or("~")),
+            SortKey::Status => siblings.sort_by_key(|t| t.done),
+        }
+        for task in siblings {
+            out.push(task.clone());
+            visit(tasks, Some(task.id), key, out);
+        }
+    }
+
+    // 親が見つからないタスクはトップレベル扱いにする
+    let mut roots: Vec<Task> = tasks.to_vec();
+    for task in &mut roots {
+        if task.parent.is_some_and(|p| !tasks.iter().any(|t| t.id == p)) {
+            task.parent = None;
+        }
+    }
+
+    let mut out = Vec::with_capacity(tasks.len());
+    visit(&roots, None, key, &mut out);
+    out
+}
+
+/// 説明中の `due:YYYY-MM-DD` を表示書式に変換する
+fn format_dates(description: &str, fmt: &str) -> String {
+    description
+        .split(' ')
+        .map(|word| match word.strip_prefix("due:") {
+            Some(d) if date::is_valid(d) => format!("due:{}", date::format(d, fmt)),
+            _ => word.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// タスクの深さ (トップレベルが 0)
+fn depth(tasks: &[Task], task: &Task) -> usize {
+    let mut depth = 0;
+    let mut parent = task.parent;
+    while let Some(p) = parent {
+        depth += 1;
+        parent = tasks.iter().find(|t| t.id == p).and_then(|t| t.parent);
+    }
+    depth
+}
+
+/// 指定したタスクの子孫のインデックス (ファイル順)
+///
+/// 子孫は親の直後に連続して並んでいる。
+fn descendants(tasks: &[Task], id: usize) -> Vec<usize> {
+    let Some(start) = tasks.iter().position(|t| t.id == id) else {
+        return Vec::new();
+    };
+
+    let mut ids = vec![id];
+    let mut result = Vec::new();
+    for (i, task) in tasks.iter().enumerate().skip(start + 1) {
+        match task.parent {
+            Some(p) if ids.contains(&p) => {
+                ids.push(task.id);
+                result.push(i);
+            }
+            _ => break,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use store::MemoryStore;
+
+    /// メタデータ (ID や日付) を除いた保存内容
+    fn outline(store: &MemoryStore) -> Vec<String> {
+        store::format_tasks(&store.load().unwrap())
+            .into_iter()
+            .map(|line| {
+                let end = line.find(" id:").unwrap_or(line.len());
+                line[..end].to_string()
+            })
+            .collect()
+    }
+
+    fn quiet() -> Output {
+        Output::capture(OutputFormat::Text)
+    }
+
+    fn config_for(args: &[&str]) -> Config {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        Config::parse(&args).unwrap()
+    }
+
+    #[test]
+    fn test_parse_add() {
+        let args = vec!["add".to_string(), "Buy milk".to_string()];
+        let config = Config::parse(&args).unwrap();
+
+        match config.command {
+            Command::Add(s) => assert_eq!(s, "Buy milk"),
+            _ => panic!("Expected Add command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let args = vec!["list".to_string()];
+        let config = Config::parse(&args).unwrap();
+
+        match config.command {
+            Command::List => {}
+            _ => panic!("Expected List command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_done() {
+        let args = vec!["done".to_string(), "3".to_string()];
+        let config = Config::parse(&args).unwrap();
+
+        match config.command {
+            Command::Done(selector) => assert_eq!(selector, Selector::Ids(vec![3])),
+            _ => panic!("Expected Done command"),
+        }
+    }
+
+    #[test]
+    fn test_parse_verbose() {
+        let args = vec!["--verbose".to_string(), "list".to_string()];
+        let config = Config::parse(&args).unwrap();
+
+        assert!(config.verbose);
+    }
+
+    #[test]
+    fn test_parse_custom_file() {
+        let args = vec![
+            "--file".to_string(),
+            "custom.txt".to_string(),
+            "list".to_string(),
+        ];
+        let config = Config::parse(&args).unwrap();
+
+        assert_eq!(config.file_path, PathBuf::from("custom.txt"));
+    }
+
+    #[test]
+    fn test_task_from_line() {
+        let task = Task::from_line(1, "[ ] Buy milk");
+        assert!(!task.done);
+        assert_eq!(task.description, "Buy milk");
+
+        let task = Task::from_line(2, "[x] Done task");
+        assert!(task.done);
+        assert_eq!(task.description, "Done task");
+    }
+
+    #[test]
+    fn test_task_to_line() {
+        let task = Task {
+            id: 1,
+            description: "Test".to_string(),
+            done: false,
+            ..Default::default()
+        };
+        assert_eq!(task.to_line(), "[ ] Test id:1");
+
+        let task = Task {
+            id: 2,
+            description: "Done".to_string(),
+            done: true,
+            ..Default::default()
+        };
+        assert_eq!(task.to_line(), "[x] Done id:2");
+    }
+
+    #[test]
+    fn test_parse_error_no_command() {
+        let args: Vec<String> = vec![];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_unknown_command() {
+        let args = vec!["unknown".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_execute_add_and_done() {
+        let mut store = MemoryStore::default();
+
+        execute(&config_for(&["add", "Buy milk"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "Write code"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "2"]), &mut store, &mut quiet()).unwrap();
+
+        let tasks = store.load().unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert!(!tasks[0].done);
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn test_execute_done_not_found() {
+        let mut store = MemoryStore::default();
+        assert!(execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).is_err());
+    }
+
+    #[test]
+    fn test_execute_clear() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "b"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+
+        let tasks = store.load().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].description, "b");
+        assert_eq!(tasks[0].id, 2);
+    }
+
+    #[test]
+    fn test_parse_parent_and_cascade() {
+        let config = config_for(&["add", "--parent", "3", "Sub"]);
+        assert_eq!(config.parent, Some(3));
+
+        let config = config_for(&["done", "1", "--cascade"]);
+        assert!(config.cascade);
+
+        let args = vec!["add".to_string(), "--parent".to_string(), "x".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_execute_add_subtask() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Parent"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "Other"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "1", "Child"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "3", "Grandchild"]), &mut store, &mut quiet()).unwrap();
+
+        assert_eq!(
+            outline(&store),
+            vec!["[ ] Parent", "  [ ] Child", "    [ ] Grandchild", "[ ] Other"]
+        );
+    }
+
+    #[test]
+    fn test_execute_add_subtask_missing_parent() {
+        let mut store = MemoryStore::default();
+        assert!(execute(&config_for(&["add", "-p", "9", "Child"]), &mut store, &mut quiet()).is_err());
+    }
+
+    #[test]
+    fn test_execute_done_parent_requires_children() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Parent"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "1", "Child"]), &mut store, &mut quiet()).unwrap();
+
+        assert!(execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).is_err());
+        assert!(store.load().unwrap().iter().all(|t| !t.done));
+
+        execute(&config_for(&["done", "2"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+        assert!(store.load().unwrap().iter().all(|t| t.done));
+    }
+
+    #[test]
+    fn test_execute_done_cascade() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Parent"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "Other"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "1", "Child"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "3", "Grandchild"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1", "--cascade"]), &mut store, &mut quiet()).unwrap();
+
+        let done: Vec<bool> = store.load().unwrap().iter().map(|t| t.done).collect();
+        assert_eq!(done, vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn test_execute_clear_keeps_hierarchy() {
+        let mut store = MemoryStore {
+            lines: vec![
+                "[ ] A".to_string(),
+                "  [x] B".to_string(),
+                "  [ ] C".to_string(),
+                "    [ ] D".to_string(),
+            ],
+            ..Default::default()
+        };
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+
+        assert_eq!(outline(&store), vec!["[ ] A", "  [ ] C", "    [ ] D"]);
+    }
+
+    fn task(description: &str, done: bool) -> Task {
+        Task {
+            id: 1,
+            description: description.to_string(),
+            done,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_task_priority_and_due() {
+        assert_eq!(task("(A) Pay rent", false).priority(), Some('A'));
+        assert_eq!(task("Pay (A) rent", false).priority(), None);
+        assert_eq!(task("Pay rent due:2024-05-01", false).due(), Some("2024-05-01"));
+        assert_eq!(task("Pay rent due:soon", false).due(), None);
+    }
+
+    #[test]
+    fn test_task_is_overdue() {
+        assert!(task("x due:2024-05-01", false).is_overdue("2024-05-02"));
+        assert!(!task("x due:2024-05-01", false).is_overdue("2024-05-01"));
+        assert!(!task("x due:2024-05-01", true).is_overdue("2024-05-02"));
+        assert!(!task("x", false).is_overdue("2024-05-02"));
+    }
+
+    #[test]
+    fn test_render_task() {
+        let style = Style::new(true);
+        let today = "2024-05-02";
+
+        let done = task("(A) x", true);
+        assert_eq!(render_task(&style, &done, "l", today), style.dim("l"));
+
+        let overdue = task("(C) x due:2024-05-01", false);
+        assert_eq!(render_task(&style, &overdue, "l", today), style.red("l"));
+
+        let low = task("(C) x", false);
+        assert_eq!(render_task(&style, &low, "l", today), style.green("l"));
+
+        let plain = task("x", false);
+        assert_eq!(render_task(&style, &plain, "l", today), "l");
+        assert_eq!(render_task(&Style::new(false), &low, "l", today), "l");
+    }
+
+    #[test]
+    fn test_parse_no_color() {
+        assert!(config_for(&["list", "--no-color"]).no_color);
+        assert!(!config_for(&["list"]).no_color);
+    }
+
+    #[test]
+    fn test_parse_with_config_file_defaults() {
+        let defaults = ConfigFile {
+            file: Some(PathBuf::from("from_config.txt")),
+            sort: Some(SortKey::Due),
+            color: Some(false),
+            date_format: Some("%d/%m/%Y".to_string()),
+            ..ConfigFile::default()
+        };
+
+        let args = vec!["list".to_string()];
+        let config = Config::parse_with(&args, &defaults).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("from_config.txt"));
+        assert_eq!(config.sort, SortKey::Due);
+        assert!(config.no_color);
+        assert_eq!(config.date_format, "%d/%m/%Y");
+
+        // コマンドライン引数が設定ファイルより優先される
+        let args: Vec<String> = ["-f", "cli.txt", "--sort", "status", "list"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let config = Config::parse_with(&args, &defaults).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("cli.txt"));
+        assert_eq!(config.sort, SortKey::Status);
+    }
+
+    #[test]
+    fn test_env_wins_over_flags_and_config() {
+        let defaults = ConfigFile {
+            file: Some(PathBuf::from("from_config.txt")),
+            sort: Some(SortKey::Due),
+            color: Some(true),
+            date_format: Some("%d/%m/%Y".to_string()),
+            ..ConfigFile::default()
+        };
+        let args: Vec<String> = ["-f", "cli.txt", "--sort", "status", "--no-color", "list"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let env = Env {
+            todo_file: Some(PathBuf::from("env.txt")),
+            todo_sort: Some("priority".to_string()),
+            todo_color: Some("on".to_string()),
+            todo_date_format: Some("%Y/%m/%d".to_string()),
+            ..Env::default()
+        };
+
+        let mut config = Config::parse_with(&args, &defaults).unwrap();
+        config.apply_env(&env).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("env.txt"));
+        assert_eq!(config.sort, SortKey::Priority);
+        assert!(!config.no_color);
+        assert_eq!(config.date_format, "%Y/%m/%d");
+
+        // 環境変数が無ければコマンドライン引数のまま
+        let mut config = Config::parse_with(&args, &defaults).unwrap();
+        config.apply_env(&Env::default()).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("cli.txt"));
+        assert_eq!(config.sort, SortKey::Status);
+        assert!(config.no_color);
+        assert_eq!(config.date_format, "%d/%m/%Y");
+    }
+
+    #[test]
+    fn test_env_list_wins_over_file_flag() {
+        let args: Vec<String> = ["-f", "cli.txt", "list"].iter().map(|s| s.to_string()).collect();
+        let env = Env {
+            data_home: Some(PathBuf::from("/data")),
+            todo_list: Some("work".to_string()),
+            ..Env::default()
+        };
+
+        let mut config = Config::parse(&args).unwrap();
+        config.apply_env(&env).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("/data/todo/work.txt"));
+    }
+
+    #[test]
+    fn test_env_invalid_values() {
+        let mut config = config_for(&["list"]);
+        let env = Env {
+            todo_sort: Some("random".to_string()),
+            ..Env::default()
+        };
+        assert!(config.apply_env(&env).unwrap_err().starts_with("TODO_SORT:"));
+
+        let env = Env {
+            todo_color: Some("maybe".to_string()),
+            ..Env::default()
+        };
+        assert!(config.apply_env(&env).unwrap_err().starts_with("TODO_COLOR:"));
+    }
+
+    #[test]
+    fn test_parse_sort_error() {
+        let args = vec!["--sort".to_string(), "random".to_string(), "list".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_sort_tasks_keeps_hierarchy() {
+        let tasks = store::parse_lines(&[
+            "[ ] (C) one".to_string(),
+            "  [ ] (B) child b".to_string(),
+            "  [ ] (A) child a".to_string(),
+            "[ ] (A) two".to_string(),
+            "[ ] three".to_string(),
+        ]);
+
+        let sorted = sort_tasks(&tasks, SortKey::Priority);
+        let ids: Vec<usize> = sorted.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![4, 1, 3, 2, 5]);
+
+        let unsorted = sort_tasks(&tasks, SortKey::File);
+        let ids: Vec<usize> = unsorted.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_sort_tasks_by_due_and_status() {
+        let tasks = store::parse_lines(&[
+            "[x] a due:2024-01-01".to_string(),
+            "[ ] b".to_string(),
+            "[ ] c due:2023-12-01".to_string(),
+        ]);
+
+        let ids: Vec<usize> = sort_tasks(&tasks, SortKey::Due).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+
+        let ids: Vec<usize> = sort_tasks(&tasks, SortKey::Status).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn test_format_dates() {
+        assert_eq!(
+            format_dates("Pay rent due:2024-05-01 now", "%d/%m/%Y"),
+            "Pay rent due:01/05/2024 now"
+        );
+        assert_eq!(format_dates("due:later", "%d/%m/%Y"), "due:later");
+    }
+
+    #[test]
+    fn test_parse_list_filters() {
+        let config = config_for(&["list", "--overdue", "--ids-only"]);
+        assert!(config.overdue);
+        assert!(config.ids_only);
+    }
+
+    #[test]
+    fn test_add_from_reader() {
+        let mut store = MemoryStore::default();
+        let config = config_for(&["add", "-"]);
+        let input = io::Cursor::new("Buy milk\n\n  Walk dog  \n");
+
+        add_from_reader(&config, &mut store, &mut quiet(), &mut Vec::new(), input).unwrap();
+
+        assert_eq!(outline(&store), vec!["[ ] Buy milk", "[ ] Walk dog"]);
+    }
+
+    #[test]
+    fn test_add_from_reader_with_parent() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Parent"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "Other"]), &mut store, &mut quiet()).unwrap();
+
+        let config = config_for(&["add", "-p", "1", "-"]);
+        add_from_reader(&config, &mut store, &mut quiet(), &mut Vec::new(), io::Cursor::new("a\nb\n")).unwrap();
+
+        assert_eq!(outline(&store), vec!["[ ] Parent", "  [ ] a", "  [ ] b", "[ ] Other"]);
+    }
+
+    #[test]
+    fn test_add_from_reader_empty() {
+        let mut store = MemoryStore::default();
+        let config = config_for(&["add", "-"]);
+        assert!(add_from_reader(&config, &mut store, &mut quiet(), &mut Vec::new(), io::Cursor::new("\n")).is_err());
+    }
+
+    #[test]
+    fn test_parse_export_import() {
+        let config = config_for(&["export", "--format", "csv"]);
+        assert!(matches!(config.command, Command::Export));
+        assert_eq!(config.format, Some(Format::Csv));
+
+        let config = config_for(&["import", "tasks.ics"]);
+        match config.command {
+            Command::Import(path) => assert_eq!(path, PathBuf::from("tasks.ics")),
+            _ => panic!("Expected Import command"),
+        }
+        assert_eq!(config.format, None);
+
+        let args = vec!["export".to_string(), "--format".to_string(), "xml".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_import_appends_after_existing() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Existing"]), &mut store, &mut quiet()).unwrap();
+
+        let csv = "id,parent,done,description\n7,5,true,\"child\nline\"\n5,,false,parent\n";
+        let count = import_tasks(&mut store, &mut quiet(), &mut Vec::new(), Format::Csv, csv).unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(outline(&store), vec!["[ ] Existing", "[ ] parent", "  [x] child line"]);
+    }
+
+    #[test]
+    fn test_task_metadata_round_trip() {
+        let line = "[x] Buy milk +home id:4 created:2024-05-01 completed:2024-05-03";
+        let task = Task::from_line(1, line);
+        assert_eq!(task.id, 4);
+        assert_eq!(task.description, "Buy milk +home");
+        assert_eq!(task.created.as_deref(), Some("2024-05-01"));
+        assert_eq!(task.completed.as_deref(), Some("2024-05-03"));
+        assert_eq!(task.to_line(), line);
+        assert_eq!(task.tags(), vec!["+home"]);
+
+        // 日付として不正なものは説明の一部として残す
+        let task = Task::from_line(1, "[ ] Meet created:tomorrow");
+        assert_eq!(task.description, "Meet created:tomorrow");
+        assert_eq!(task.created, None);
+    }
+
+    #[test]
+    fn test_execute_records_dates() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+
+        let today = date::today();
+        let task = &store.load().unwrap()[0];
+        assert_eq!(task.created.as_ref(), Some(&today));
+        assert_eq!(task.completed.as_ref(), Some(&today));
+    }
+
+    #[test]
+    fn test_execute_clear_archives() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "b"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "2"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+
+        let archived = store.load_archive().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].description, "b");
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        let config = config_for(&["stats", "--days", "30", "--json"]);
+        assert!(matches!(config.command, Command::Stats));
+        assert_eq!(config.days, 30);
+        assert_eq!(config.output, OutputFormat::Json);
+
+        assert_eq!(config_for(&["stats"]).days, 7);
+    }
+
+    #[test]
+    fn test_ids_stable_after_clear() {
+        let mut store = MemoryStore::default();
+        for name in ["a", "b", "c"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+
+        // clear 後も残ったタスクの ID は変わらない
+        execute(&config_for(&["done", "3"]), &mut store, &mut quiet()).unwrap();
+        let tasks = store.load().unwrap();
+        assert_eq!(tasks[0].id, 2);
+        assert!(!tasks[0].done);
+        assert_eq!(tasks[1].id, 3);
+        assert!(tasks[1].done);
+    }
+
+    #[test]
+    fn test_freed_id_is_reused() {
+        let mut store = MemoryStore::default();
+        for name in ["a", "b", "c"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+        execute(&config_for(&["done", "2"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "d"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "e"]), &mut store, &mut quiet()).unwrap();
+
+        let ids: Vec<(usize, String)> = store
+            .load()
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.id, t.description))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                (1, "a".to_string()),
+                (3, "c".to_string()),
+                (2, "d".to_string()),
+                (4, "e".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ids() {
+        assert_eq!(parse_ids(&["1", "2", "5"]).unwrap(), vec![1, 2, 5]);
+        assert_eq!(parse_ids(&["3-5"]).unwrap(), vec![3, 4, 5]);
+        assert_eq!(parse_ids(&["1,3-4", "3", "9"]).unwrap(), vec![1, 3, 4, 9]);
+        assert!(parse_ids(&["5-3"]).is_err());
+        assert!(parse_ids(&["a"]).is_err());
+        assert!(parse_ids(&["1-"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_rm() {
+        let config = config_for(&["rm", "--all-done"]);
+        assert!(matches!(config.command, Command::Remove(ref ids) if ids.is_empty()));
+        assert!(config.all_done);
+
+        let args = vec!["rm".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_execute_done_many() {
+        let mut store = MemoryStore::default();
+        for name in ["a", "b", "c", "d", "e"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+        execute(&config_for(&["done", "1", "3-4"]), &mut store, &mut quiet()).unwrap();
+
+        let done: Vec<bool> = store.load().unwrap().iter().map(|t| t.done).collect();
+        assert_eq!(done, vec![true, false, true, true, false]);
+    }
+
+    #[test]
+    fn test_execute_done_many_is_atomic() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        let before = store.lines.clone();
+
+        assert!(execute(&config_for(&["done", "1", "9"]), &mut store, &mut quiet()).is_err());
+        assert_eq!(store.lines, before);
+    }
+
+    #[test]
+    fn test_execute_done_parent_with_listed_children() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Parent"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "-p", "1", "Child"]), &mut store, &mut quiet()).unwrap();
+
+        // 子も同時に指定していれば --cascade は不要
+        execute(&config_for(&["done", "1-2"]), &mut store, &mut quiet()).unwrap();
+        assert!(store.load().unwrap().iter().all(|t| t.done));
+    }
+
+    #[test]
+    fn test_execute_rm() {
+        let mut store = MemoryStore::default();
+        for name in ["a", "b", "c"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+        execute(&config_for(&["add", "-p", "2", "b child"]), &mut store, &mut quiet()).unwrap();
+
+        assert!(execute(&config_for(&["rm", "2"]), &mut store, &mut quiet()).is_err());
+        execute(&config_for(&["rm", "2", "--cascade"]), &mut store, &mut quiet()).unwrap();
+        assert_eq!(outline(&store), vec!["[ ] a", "[ ] c"]);
+        assert!(store.archived.is_empty());
+    }
+
+    #[test]
+    fn test_execute_rm_all_done() {
+        let mut store = MemoryStore::default();
+        for name in ["a", "b", "c"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+        execute(&config_for(&["done", "1", "3"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["rm", "--all-done"]), &mut store, &mut quiet()).unwrap();
+
+        assert_eq!(outline(&store), vec!["[ ] b"]);
+    }
+
+    #[test]
+    fn test_parse_done_text() {
+        let config = config_for(&["done", "buy", "milk"]);
+        assert!(matches!(config.command, Command::Done(Selector::Text(ref q)) if q == "buy milk"));
+
+        // 数字で始まる引数は ID として扱うので、不正な範囲はエラーになる
+        assert!(Config::parse(&["done".to_string(), "3-".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_execute_done_by_text() {
+        let mut store = MemoryStore::default();
+        for name in ["Buy milk", "Buy bread", "Call mom"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+
+        execute(&config_for(&["done", "MILK"]), &mut store, &mut quiet()).unwrap();
+        assert!(store.load().unwrap()[0].done);
+
+        // 完了済みの "Buy milk" は候補に入らない
+        execute(&config_for(&["done", "buy"]), &mut store, &mut quiet()).unwrap();
+        assert!(store.load().unwrap()[1].done);
+    }
+
+    #[test]
+    fn test_execute_done_by_text_ambiguous() {
+        let mut store = MemoryStore::default();
+        for name in ["Buy milk", "Buy bread", "Call mom"] {
+            execute(&config_for(&["add", name]), &mut store, &mut quiet()).unwrap();
+        }
+
+        let err = execute(&config_for(&["done", "buy"]), &mut store, &mut quiet()).unwrap_err();
+        assert!(err.contains("1 Buy milk") && err.contains("2 Buy bread"));
+        assert!(execute(&config_for(&["done", "tea"]), &mut store, &mut quiet()).is_err());
+        assert!(store.load().unwrap().iter().all(|t| !t.done));
+    }
+
+    #[test]
+    fn test_execute_start_stop() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "Write report"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "Gym"]), &mut store, &mut quiet()).unwrap();
+
+        assert!(execute(&config_for(&["stop"]), &mut store, &mut quiet()).is_err());
+
+        execute(&config_for(&["start", "report"]), &mut store, &mut quiet()).unwrap();
+        // 別のタスクを始めると前の計測は止まる
+        execute(&config_for(&["start", "2"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["stop"]), &mut store, &mut quiet()).unwrap();
+
+        let entries = store.load_entries().unwrap();
+        let tasks: Vec<usize> = entries.iter().map(|e| e.task).collect();
+        assert_eq!(tasks, vec![1, 2]);
+        assert!(entries.iter().all(|e| e.end.is_some()));
+        assert!(execute(&config_for(&["time"]), &mut store, &mut quiet()).is_ok());
+    }
+
+    #[test]
+    fn test_execute_start_rejects_done_and_many() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "b"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+
+        assert!(execute(&config_for(&["start", "1"]), &mut store, &mut quiet()).is_err());
+        assert!(execute(&config_for(&["start", "1-2"]), &mut store, &mut quiet()).is_err());
+        assert!(store.entries.is_empty());
+    }
+
+    #[test]
+    fn test_tracked_id_is_not_reused() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["start", "1"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["stop"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["clear"]), &mut store, &mut quiet()).unwrap();
+
+        // 記録が ID 1 を指しているので新しいタスクは 2 になる
+        execute(&config_for(&["add", "b"]), &mut store, &mut quiet()).unwrap();
+        assert_eq!(store.load().unwrap()[0].id, 2);
+    }
+
+    #[test]
+    fn test_parse_sync() {
+        let config = config_for(&["sync", "--remote", "http://localhost:8080", "--dry-run"]);
+        assert!(matches!(config.command, Command::Sync));
+        assert_eq!(config.remote.as_deref(), Some("http://localhost:8080"));
+        assert!(config.dry_run);
+
+        assert!(Config::parse(&["sync".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_execute_json_output() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&config_for(&["add", "b"]), &mut store, &mut quiet()).unwrap();
+
+        let mut out = Output::capture(OutputFormat::Json);
+        execute(&config_for(&["done", "2", "--json"]), &mut store, &mut out).unwrap();
+        assert_eq!(out.lines(), ["Done: b"]);
+        assert!(matches!(out.get("done"), Some(JsonValue::Array(a)) if a.len() == 1));
+
+        let mut out = Output::capture(OutputFormat::Json);
+        execute(&config_for(&["list", "--json"]), &mut store, &mut out).unwrap();
+        let tasks = formats::tasks_from_json(out.get("tasks").unwrap()).unwrap();
+        assert_eq!(tasks, store.load().unwrap());
+        // JSON では一覧の行は出さない
+        assert!(out.lines().is_empty());
+    }
+
+    #[test]
+    fn test_execute_json_error() {
+        let mut store = MemoryStore::default();
+        let mut out = Output::capture(OutputFormat::Json);
+        let result = execute(&config_for(&["done", "9", "--json"]), &mut store, &mut out);
+
+        let json = out.to_json(result.as_ref().err().map(String::as_str)).to_string();
+        assert!(json.contains(r#""error":"Task 9 not found""#));
+        assert!(json.contains(r#""ok":false"#));
+    }
+
+    #[test]
+    fn test_execute_text_output() {
+        let mut store = MemoryStore::default();
+        execute(&config_for(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+
+        let mut out = quiet();
+        execute(&config_for(&["list", "--no-color"]), &mut store, &mut out).unwrap();
+        assert_eq!(out.lines(), ["Tasks:", "  1 [ ] a"]);
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let defaults = ConfigFile {
+            hooks: Some(PathBuf::from("/tmp/hooks")),
+            ..ConfigFile::default()
+        };
+        let args = vec!["list".to_string()];
+        let config = Config::parse_with(&args, &defaults).unwrap();
+        assert_eq!(config.hooks, Some(Hooks::new(PathBuf::from("/tmp/hooks"))));
+
+        let args = vec!["list".to_string(), "--no-hooks".to_string()];
+        assert_eq!(Config::parse_with(&args, &defaults).unwrap().hooks, None);
+        assert_eq!(config_for(&["list"]).hooks, None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_runs_hooks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("todo-exec-hooks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["task-added", "task-completed"] {
+            let path = dir.join(name);
+            fs::write(&path, "#!/bin/sh\necho \"$1\" >> events.log\n").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        fs::write(dir.join("task-removed"), "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(dir.join("task-removed"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let with_hooks = |args: &[&str]| Config {
+            hooks: Some(Hooks::new(dir.clone())),
+            ..config_for(args)
+        };
+        let mut store = MemoryStore::default();
+        execute(&with_hooks(&["add", "a"]), &mut store, &mut quiet()).unwrap();
+        execute(&with_hooks(&["done", "1"]), &mut store, &mut quiet()).unwrap();
+        // 失敗したコマンドではフックを実行しない
+        assert!(execute(&with_hooks(&["done", "9"]), &mut store, &mut quiet()).is_err());
+
+        let log = fs::read_to_string(dir.join("events.log")).unwrap();
+        assert_eq!(log, "task-added\ntask-completed\n");
+
+        // フックの失敗は警告になるだけ
+        let mut out = quiet();
+        execute(&with_hooks(&["rm", "1"]), &mut store, &mut out).unwrap();
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(out.warnings().len(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_encrypt() {
+        assert!(config_for(&["list", "--encrypt"]).encrypt);
+        assert!(!config_for(&["list"]).encrypt);
+    }
+
+    #[test]
+    fn test_parse_watch() {
+        assert!(config_for(&["list", "--watch"]).watch);
+        assert!(!config_for(&["list"]).watch);
+
+        let args = vec!["done".to_string(), "1".to_string(), "--watch".to_string()];
+        assert!(Config::parse(&args).is_err());
+    }
+
+    #[test]
+    fn test_cli_file_wins_over_env() {
+        let env = paths::Env {
+            todo_file: Some(PathBuf::from("/tmp/from-env.txt")),
+            ..paths::Env::default()
+        };
+        let defaults = ConfigFile::load_from(&env).unwrap();
+
+        let args = vec!["list".to_string()];
+        let config = Config::parse_with(&args, &defaults).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("/tmp/from-env.txt"));
+
+        let args: Vec<String> = ["-f", "cli.txt", "list"].iter().map(|s| s.to_string()).collect();
+        let config = Config::parse_with(&args, &defaults).unwrap();
+        assert_eq!(config.file_path, PathBuf::from("cli.txt"));
+    }
+
+    #[test]
+    fn test_execute_localized_output() {
+        let mut store = MemoryStore::default();
+        let mut out = Output::capture(OutputFormat::Text).with_locale(Locale::Ja);
+        execute(&config_for(&["add", "牛乳を買う"]), &mut store, &mut out).unwrap();
+        execute(&config_for(&["done", "1"]), &mut store, &mut out).unwrap();
+        assert_eq!(out.lines(), ["追加: 牛乳を買う", "完了: 牛乳を買う"]);
+
+        let err = execute(&config_for(&["stop"]), &mut store, &mut out).unwrap_err();
+        assert_eq!(err, "計測中のタスクはありません");
+    }
+}
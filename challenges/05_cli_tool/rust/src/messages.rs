@@ -0,0 +1,177 @@
+//! 表示メッセージの翻訳表
+//!
+//! コマンドの処理はキーと引数だけを渡し、文面はロケールごとの表から引く。
+//! 言語を増やすときは [`Locale`] に値を足し、表を 1 つ追加するだけでよい。
+//! 表に無いキーは英語の文面を使う。
+//!
+//! 文面の `{0}`, `{1}` は引数の位置を表す (語順が違う言語でも並べ替えられる)。
+//! ヘルプ、集計レポート、引数やタスクの指定 (ID・検索語) のエラーは英語のまま。
+
+use std::env;
+use std::fmt::Display;
+
+/// 表示言語
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Ja,
+}
+
+impl Locale {
+    /// `LC_ALL` > `LC_MESSAGES` > `LANG` の順に見て決める
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Locale::En, |value| Locale::from_lang(&value))
+    }
+
+    /// `ja_JP.UTF-8` のような値から決める (知らない言語は英語)
+    pub fn from_lang(value: &str) -> Self {
+        match value.split(['_', '.', '@']).next().unwrap_or("") {
+            "ja" => Locale::Ja,
+            _ => Locale::En,
+        }
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Locale::En => EN,
+            Locale::Ja => JA,
+        }
+    }
+}
+
+/// キーの文面に引数を埋め込む
+pub fn get(locale: Locale, key: &str, args: &[&dyn Display]) -> String {
+    let lookup = |table: &[(&str, &'static str)]| {
+        table.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+    };
+    let template = lookup(locale.table()).or_else(|| lookup(EN)).unwrap_or(key);
+
+    // 引数の中の `{1}` などを置き換えないように、文面を 1 回だけ走査する
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after
+            .find('}')
+            .and_then(|end| Some((after[..end].parse::<usize>().ok()?, end)))
+            .and_then(|(i, end)| Some((args.get(i)?, end)));
+        match arg {
+            Some((arg, end)) => {
+                text.push_str(&arg.to_string());
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+const EN: &[(&str, &str)] = &[
+    ("error", "Error: {0}"),
+    ("warning", "Warning: {0}"),
+    ("added", "Added: {0}"),
+    ("file", "  File: {0}"),
+    ("no_stdin_tasks", "No tasks read from stdin"),
+    ("parent_not_found", "Parent task {0} not found"),
+    ("no_tasks", "No tasks found."),
+    ("tasks_header", "Tasks:"),
+    ("totals", "\n  Total: {0}, Done: {1}, Pending: {2}"),
+    ("already_done", "Task {0} is already done"),
+    ("done", "Done: {0}"),
+    ("pending_subtasks", "Task {0} has {1} pending subtask(s); finish them first or use --cascade"),
+    ("has_subtasks", "Task {0} has {1} subtask(s); remove them too with --cascade"),
+    ("nothing_to_remove", "No tasks to remove."),
+    ("removed", "Removed {0} task(s)."),
+    ("nothing_to_clear", "No completed tasks to clear."),
+    ("cleared", "Cleared {0} completed task(s)."),
+    ("single_task", "start takes a single task"),
+    ("already_tracking", "Already tracking: {0}"),
+    ("started", "Started: {0}"),
+    ("not_tracking", "No task is being tracked"),
+    ("stopped", "Stopped: {0} ({1})"),
+    ("removed_task", "(removed)"),
+    ("imported", "Imported {0} task(s)."),
+    ("dry_run", "Dry run: nothing was written."),
+    ("in_sync", "Already in sync."),
+    ("synced", "Pushed {0} change(s), pulled {1} change(s)."),
+];
+
+const JA: &[(&str, &str)] = &[
+    ("error", "エラー: {0}"),
+    ("warning", "警告: {0}"),
+    ("added", "追加: {0}"),
+    ("file", "  ファイル: {0}"),
+    ("no_stdin_tasks", "標準入力からタスクを読み込めませんでした"),
+    ("parent_not_found", "親タスク {0} が見つかりません"),
+    ("no_tasks", "タスクはありません。"),
+    ("tasks_header", "タスク:"),
+    ("totals", "\n  合計: {0}、完了: {1}、未完了: {2}"),
+    ("already_done", "タスク {0} は完了済みです"),
+    ("done", "完了: {0}"),
+    ("pending_subtasks", "タスク {0} には未完了のサブタスクが {1} 件あります。先に完了させるか --cascade を指定してください"),
+    ("has_subtasks", "タスク {0} にはサブタスクが {1} 件あります。まとめて削除するには --cascade を指定してください"),
+    ("nothing_to_remove", "削除するタスクはありません。"),
+    ("removed", "{0} 件のタスクを削除しました。"),
+    ("nothing_to_clear", "片付ける完了タスクはありません。"),
+    ("cleared", "完了タスクを {0} 件片付けました。"),
+    ("single_task", "start に指定できるタスクは 1 件だけです"),
+    ("already_tracking", "計測中: {0}"),
+    ("started", "計測開始: {0}"),
+    ("not_tracking", "計測中のタスクはありません"),
+    ("stopped", "計測終了: {0} ({1})"),
+    ("removed_task", "(削除済み)"),
+    ("imported", "{0} 件のタスクを読み込みました。"),
+    ("dry_run", "ドライラン: 何も書き込んでいません。"),
+    ("in_sync", "同期済みです。"),
+    ("synced", "{0} 件の変更を送信し、{1} 件の変更を受信しました。"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 文面に含まれる `{N}` の一覧
+    fn placeholders(text: &str) -> Vec<usize> {
+        (0..10).filter(|i| text.contains(&format!("{{{}}}", i))).collect()
+    }
+
+    #[test]
+    fn test_from_lang() {
+        assert_eq!(Locale::from_lang("ja_JP.UTF-8"), Locale::Ja);
+        assert_eq!(Locale::from_lang("ja"), Locale::Ja);
+        assert_eq!(Locale::from_lang("en_US.UTF-8"), Locale::En);
+        assert_eq!(Locale::from_lang("C"), Locale::En);
+        assert_eq!(Locale::from_lang("jam"), Locale::En);
+    }
+
+    #[test]
+    fn test_get_fills_arguments() {
+        assert_eq!(get(Locale::En, "added", &[&"Buy milk"]), "Added: Buy milk");
+        assert_eq!(get(Locale::Ja, "removed", &[&3]), "3 件のタスクを削除しました。");
+        assert_eq!(get(Locale::En, "unknown_key", &[]), "unknown_key");
+        assert_eq!(get(Locale::En, "stopped", &[&"{1}", &"1h 00m"]), "Stopped: {1} (1h 00m)");
+        // 足りない引数はそのまま残す
+        assert_eq!(get(Locale::En, "synced", &[&1]), "Pushed 1 change(s), pulled {1} change(s).");
+    }
+
+    #[test]
+    fn test_tables_have_the_same_keys_and_placeholders() {
+        for table in [JA] {
+            assert_eq!(table.len(), EN.len());
+            for (key, text) in table {
+                let english = EN.iter().find(|(k, _)| k == key).expect(key).1;
+                assert_eq!(placeholders(text), placeholders(english), "{}", key);
+            }
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! コマンドの出力
+//!
+//! ハンドラーは `println!` ではなく `Output` に書き込む。テキスト形式では
+//! その場で表示し、JSON 形式では最後に 1 つのオブジェクトにまとめて出す。
+//!
+//! ```text
+//! {"ok": true, "messages": ["Added: Buy milk"], "added": [ ... ]}
+//! {"ok": false, "error": "Task 9 not found", "messages": []}
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use json_parser::JsonValue;
+
+use crate::messages::{self, Locale};
+
+/// 出力形式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// コマンドの出力先
+#[derive(Debug)]
+pub struct Output {
+    format: OutputFormat,
+    /// 標準出力にすぐ書くか (テストでは溜めるだけ)
+    echo: bool,
+    /// 人が読むための行。JSON では "messages" になる
+    lines: Vec<String>,
+    /// JSON でだけ出すデータ
+    fields: HashMap<String, JsonValue>,
+    warnings: Vec<String>,
+    /// メッセージの言語
+    locale: Locale,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Output {
+            format,
+            echo: true,
+            lines: Vec::new(),
+            fields: HashMap::new(),
+            warnings: Vec::new(),
+            locale: Locale::En,
+        }
+    }
+
+    pub fn with_locale(self, locale: Locale) -> Self {
+        Output { locale, ..self }
+    }
+
+    /// 翻訳表から引いた文面 (エラーメッセージなど、行として出さないもの用)
+    pub fn text(&self, key: &str, args: &[&dyn Display]) -> String {
+        messages::get(self.locale, key, args)
+    }
+
+    /// 翻訳表から引いた 1 行のメッセージ
+    pub fn msg(&mut self, key: &str, args: &[&dyn Display]) {
+        let text = self.text(key, args);
+        self.line(text);
+    }
+
+    /// 表示せずに溜めるだけの出力 (テストや埋め込み用)
+    pub fn capture(format: OutputFormat) -> Self {
+        Output {
+            echo: false,
+            ..Output::new(format)
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// 1 行のメッセージ
+    pub fn line(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        if self.echo && !self.is_json() {
+            println!("{}", text);
+        }
+        self.lines.push(text);
+    }
+
+    /// 加工せずにそのまま出すテキスト (export の本文など)
+    ///
+    /// JSON では "output" フィールドに入れる。
+    pub fn raw(&mut self, text: &str) {
+        if self.is_json() {
+            self.field("output", JsonValue::String(text.to_string()));
+        } else {
+            if self.echo {
+                print!("{}", text);
+            }
+            self.lines.extend(text.lines().map(str::to_string));
+        }
+    }
+
+    /// 警告。テキストでは標準エラーへ、JSON では "warnings" に入れる
+    pub fn warn(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        if self.echo && !self.is_json() {
+            eprintln!("{}", self.text("warning", &[&text]));
+        }
+        self.warnings.push(text);
+    }
+
+    /// JSON でだけ出すデータ (テキストでは無視する)
+    pub fn field(&mut self, key: &str, value: JsonValue) {
+        self.fields.insert(key.to_string(), value);
+    }
+
+    /// これまでに書いた行
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// JSON 用に設定したフィールド
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.fields.get(key)
+    }
+
+    /// 結果をまとめた JSON
+    pub fn to_json(&self, error: Option<&str>) -> JsonValue {
+        let mut map = self.fields.clone();
+        map.insert("ok".to_string(), JsonValue::Bool(error.is_none()));
+        if let Some(error) = error {
+            map.insert("error".to_string(), JsonValue::String(error.to_string()));
+        }
+        let messages = self.lines.iter().cloned().map(JsonValue::String).collect();
+        map.insert("messages".to_string(), JsonValue::Array(messages));
+        if !self.warnings.is_empty() {
+            let warnings = self.warnings.iter().cloned().map(JsonValue::String).collect();
+            map.insert("warnings".to_string(), JsonValue::Array(warnings));
+        }
+        JsonValue::Object(map)
+    }
+
+    /// 最後の出力。JSON ならまとめて標準出力へ、テキストならエラーだけ標準エラーへ
+    pub fn finish(&self, result: &Result<(), String>) {
+        let error = result.as_ref().err().map(String::as_str);
+        if self.is_json() {
+            println!("{}", self.to_json(error).to_pretty_string());
+        } else if let Some(e) = error {
+            eprintln!("{}", self.text("error", &[&e]));
+        }
+    }
+}
+
+/// 数値の JSON (ID や件数用)
+pub fn number(n: usize) -> JsonValue {
+    JsonValue::Number(n as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_keeps_lines() {
+        let mut out = Output::capture(OutputFormat::Text);
+        out.line("Added: a");
+        out.field("added", number(1));
+        out.raw("x\ny\n");
+
+        assert_eq!(out.lines(), ["Added: a", "x", "y"]);
+    }
+
+    #[test]
+    fn test_json_document() {
+        let mut out = Output::capture(OutputFormat::Json);
+        out.line("Added: a");
+        out.field("count", number(1));
+        out.raw("body");
+
+        let ok = out.to_json(None).to_string();
+        assert_eq!(
+            ok,
+            r#"{"count":1,"messages":["Added: a"],"ok":true,"output":"body"}"#
+        );
+
+        let failed = out.to_json(Some("boom"));
+        match failed {
+            JsonValue::Object(map) => {
+                assert_eq!(map.get("ok"), Some(&JsonValue::Bool(false)));
+                assert_eq!(map.get("error"), Some(&JsonValue::String("boom".to_string())));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut out = Output::capture(OutputFormat::Json);
+        assert!(!out.to_json(None).to_string().contains("warnings"));
+
+        out.warn("hook failed");
+        assert_eq!(out.warnings(), ["hook failed"]);
+        assert!(out.lines().is_empty());
+        assert!(out.to_json(None).to_string().contains(r#""warnings":["hook failed"]"#));
+    }
+
+    #[test]
+    fn test_msg_uses_locale() {
+        let mut out = Output::capture(OutputFormat::Text);
+        out.msg("added", &[&"a"]);
+        let mut out_ja = Output::capture(OutputFormat::Text).with_locale(Locale::Ja);
+        out_ja.msg("added", &[&"a"]);
+
+        assert_eq!(out.lines(), ["Added: a"]);
+        assert_eq!(out_ja.lines(), ["追加: a"]);
+        assert_eq!(out_ja.text("error", &[&"x"]), "エラー: x");
+    }
+}
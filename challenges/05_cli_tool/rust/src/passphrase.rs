@@ -0,0 +1,75 @@
+//! パスフレーズの入力
+//!
+//! 環境変数 `TODO_PASSPHRASE` があればそれを使う (スクリプトやテスト用)。
+//! 無ければ端末から入力を受ける。Unix では `stty -echo` で入力を表示しない。
+
+use std::env;
+use std::io::{self, BufRead, Write};
+
+/// パスフレーズを渡す環境変数
+pub const ENV: &str = "TODO_PASSPHRASE";
+
+/// パスフレーズを取得する。`confirm` なら 2 回入力させて一致を確認する
+pub fn obtain(confirm: bool) -> Result<String, String> {
+    if let Ok(passphrase) = env::var(ENV) {
+        return non_empty(passphrase);
+    }
+
+    let passphrase = non_empty(prompt("Passphrase: ")?)?;
+    if confirm && prompt("Confirm passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+fn non_empty(passphrase: String) -> Result<String, String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// 入力を表示せずに 1 行読む
+#[cfg(unix)]
+fn prompt(message: &str) -> Result<String, String> {
+    use std::fs::{File, OpenOptions};
+    use std::io::BufReader;
+    use std::process::Command;
+
+    // 標準入力は `add -` などで使うので、端末を直接開く
+    let mut tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+        .map_err(|e| format!("Cannot read passphrase (no terminal; set {}): {}", ENV, e))?;
+
+    let stty = |arg: &str| {
+        File::open("/dev/tty")
+            .and_then(|input| Command::new("stty").arg(arg).stdin(input).status())
+            .is_ok_and(|status| status.success())
+    };
+
+    write!(tty, "{}", message).and_then(|_| tty.flush()).map_err(|e| e.to_string())?;
+    let hidden = stty("-echo");
+    let line = read_line(BufReader::new(&tty));
+    if hidden {
+        stty("echo");
+    }
+    // 改行も表示されないので補う
+    let _ = writeln!(tty);
+    line
+}
+
+#[cfg(not(unix))]
+fn prompt(message: &str) -> Result<String, String> {
+    eprint!("{}", message);
+    read_line(io::stdin().lock())
+}
+
+fn read_line(mut reader: impl BufRead) -> Result<String, String> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e: io::Error| format!("Failed to read passphrase: {}", e))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,174 @@
+//! 設定ファイルとタスクファイルの置き場所、設定を上書きする環境変数
+//!
+//! タスクファイルは次の順で決める (先にあるものが優先)。
+//!
+//! 1. 環境変数 `TODO_FILE` (パス)、`TODO_LIST` (データディレクトリ内のリスト名)
+//! 2. コマンドラインの `-f` / `--file`
+//! 3. 設定ファイルの `file`
+//! 4. カレントディレクトリの `todo.txt` (既にある場合だけ。以前の動作との互換)
+//! 5. `$XDG_DATA_HOME/todo/todo.txt` (未設定なら `~/.local/share/todo/todo.txt`)
+//!
+//! 設定ファイルは `$XDG_CONFIG_HOME/todo/config` (未設定なら `~/.config/todo/config`)。
+//! 並び順・色・日付形式も同じく 環境変数 > コマンドライン > 設定ファイル > 既定値 の順で、
+//! 環境変数は `TODO_SORT`、`TODO_COLOR` (on/off)、`TODO_DATE_FORMAT`。
+
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// 既定のファイル名
+pub const DEFAULT_FILE: &str = "todo.txt";
+
+/// 置き場所や設定の決定に使う環境 (テストでは値を直接組み立てる)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Env {
+    pub home: Option<PathBuf>,
+    pub config_home: Option<PathBuf>,
+    pub data_home: Option<PathBuf>,
+    pub todo_file: Option<PathBuf>,
+    pub todo_list: Option<String>,
+    pub todo_sort: Option<String>,
+    pub todo_color: Option<String>,
+    pub todo_date_format: Option<String>,
+    /// カレントディレクトリにある todo.txt
+    pub local_file: Option<PathBuf>,
+}
+
+impl Env {
+    /// 実行中のプロセスの環境変数とカレントディレクトリから作る
+    pub fn from_process() -> Self {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.is_empty());
+        // XDG の仕様どおり、相対パスは無視する
+        let dir = |name: &str| var(name).map(PathBuf::from).filter(|p| p.is_absolute());
+
+        Env {
+            home: var("HOME").map(PathBuf::from),
+            config_home: dir("XDG_CONFIG_HOME"),
+            data_home: dir("XDG_DATA_HOME"),
+            todo_file: var("TODO_FILE").map(PathBuf::from),
+            todo_list: var("TODO_LIST"),
+            todo_sort: var("TODO_SORT"),
+            todo_color: var("TODO_COLOR"),
+            todo_date_format: var("TODO_DATE_FORMAT"),
+            local_file: Some(PathBuf::from(DEFAULT_FILE)).filter(|p| p.is_file()),
+        }
+    }
+
+    /// 設定ファイルやフックを置くディレクトリ
+    pub fn config_dir(&self) -> Option<PathBuf> {
+        self.config_home
+            .clone()
+            .or_else(|| self.home.as_ref().map(|h| h.join(".config")))
+            .map(|dir| dir.join("todo"))
+    }
+
+    /// タスクファイルを置くディレクトリ
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        self.data_home
+            .clone()
+            .or_else(|| self.home.as_ref().map(|h| h.join(".local/share")))
+            .map(|dir| dir.join("todo"))
+    }
+
+    /// 環境変数で指定されたタスクファイル (`TODO_FILE` が `TODO_LIST` より優先)
+    pub fn env_file(&self) -> Option<PathBuf> {
+        if let Some(file) = &self.todo_file {
+            return Some(file.clone());
+        }
+        let name = format!("{}.txt", self.todo_list.as_ref()?);
+        Some(self.in_data_dir(&name))
+    }
+
+    /// 何も指定されていないときのタスクファイル
+    pub fn default_file(&self) -> PathBuf {
+        match &self.local_file {
+            Some(file) => file.clone(),
+            None => self.in_data_dir(DEFAULT_FILE),
+        }
+    }
+
+    /// データディレクトリ内のパス (ホームが分からなければカレントディレクトリ)
+    fn in_data_dir(&self, name: &str) -> PathBuf {
+        match self.data_dir() {
+            Some(dir) => dir.join(name),
+            None => Path::new(name).to_path_buf(),
+        }
+    }
+
+    /// 設定ファイルの値も含めて、`-f` が無いときのタスクファイルを決める
+    pub fn resolve_file(&self, configured: Option<PathBuf>) -> PathBuf {
+        self.env_file()
+            .or(configured)
+            .unwrap_or_else(|| self.default_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> Env {
+        Env {
+            home: Some(PathBuf::from("/home/u")),
+            ..Env::default()
+        }
+    }
+
+    #[test]
+    fn test_default_uses_xdg_data_home() {
+        assert_eq!(home().resolve_file(None), PathBuf::from("/home/u/.local/share/todo/todo.txt"));
+
+        let env = Env {
+            data_home: Some(PathBuf::from("/data")),
+            ..home()
+        };
+        assert_eq!(env.resolve_file(None), PathBuf::from("/data/todo/todo.txt"));
+
+        // ホームが分からなければカレントディレクトリ
+        assert_eq!(Env::default().resolve_file(None), PathBuf::from("todo.txt"));
+    }
+
+    #[test]
+    fn test_existing_local_file_wins_over_default() {
+        let env = Env {
+            local_file: Some(PathBuf::from("todo.txt")),
+            ..home()
+        };
+        assert_eq!(env.resolve_file(None), PathBuf::from("todo.txt"));
+        assert_eq!(env.resolve_file(Some("cfg.txt".into())), PathBuf::from("cfg.txt"));
+    }
+
+    #[test]
+    fn test_config_wins_over_default() {
+        assert_eq!(home().resolve_file(Some("cfg.txt".into())), PathBuf::from("cfg.txt"));
+    }
+
+    #[test]
+    fn test_env_wins_over_config() {
+        let env = Env {
+            todo_list: Some("work".to_string()),
+            ..home()
+        };
+        assert_eq!(
+            env.resolve_file(Some("cfg.txt".into())),
+            PathBuf::from("/home/u/.local/share/todo/work.txt")
+        );
+
+        let env = Env {
+            todo_file: Some(PathBuf::from("/tmp/t.txt")),
+            ..env
+        };
+        assert_eq!(env.resolve_file(Some("cfg.txt".into())), PathBuf::from("/tmp/t.txt"));
+    }
+
+    #[test]
+    fn test_config_dir() {
+        assert_eq!(home().config_dir(), Some(PathBuf::from("/home/u/.config/todo")));
+
+        let env = Env {
+            config_home: Some(PathBuf::from("/xdg")),
+            ..home()
+        };
+        assert_eq!(env.config_dir(), Some(PathBuf::from("/xdg/todo")));
+        assert_eq!(Env::default().config_dir(), None);
+    }
+}
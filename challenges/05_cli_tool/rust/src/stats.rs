@@ -0,0 +1,261 @@
+//! `todo stats` の集計
+//!
+//! 現在のタスクとアーカイブ (clear で片付けたタスク) から統計を作る。
+
+use std::collections::{BTreeMap, HashMap};
+
+use json_parser::JsonValue;
+
+use crate::{date, Task};
+
+/// oldest_pending に載せる件数
+const OLDEST_LIMIT: usize = 5;
+
+/// 未完了タスクの経過日数
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingAge {
+    pub id: usize,
+    pub description: String,
+    pub created: String,
+    pub age_days: i64,
+}
+
+/// 集計結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub total: usize,
+    pub pending: usize,
+    pub done: usize,
+    pub archived: usize,
+    /// 未完了タスクの優先度ごとの件数 (優先度なしは "none")
+    pub by_priority: BTreeMap<String, usize>,
+    /// タグ (`+tag` / `@context`) ごとの件数
+    pub by_tag: BTreeMap<String, usize>,
+    /// 集計期間の日数
+    pub days: usize,
+    /// 期間内に完了したタスク数 (アーカイブを含む)
+    pub completed_recently: usize,
+    /// 作成日が古い順の未完了タスク
+    pub oldest_pending: Vec<PendingAge>,
+}
+
+impl Stats {
+    /// 直近 `days` 日 (今日を含む) を期間として集計する
+    pub fn compute(tasks: &[Task], archive: &[Task], today: &str, days: usize) -> Self {
+        let today_n = date::to_days(today).unwrap_or(0);
+        let since = today_n - days as i64 + 1;
+
+        let pending: Vec<&Task> = tasks.iter().filter(|t| !t.done).collect();
+
+        let mut by_priority = BTreeMap::new();
+        for task in &pending {
+            let key = task
+                .priority()
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| "none".to_string());
+            *by_priority.entry(key).or_insert(0) += 1;
+        }
+
+        let mut by_tag = BTreeMap::new();
+        for task in tasks {
+            for tag in task.tags() {
+                *by_tag.entry(tag.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        let completed_recently = tasks
+            .iter()
+            .chain(archive)
+            .filter_map(|t| t.completed.as_deref().and_then(date::to_days))
+            .filter(|&d| d >= since && d <= today_n)
+            .count();
+
+        let mut oldest_pending: Vec<PendingAge> = pending
+            .iter()
+            .filter_map(|t| {
+                let created = t.created.as_ref()?;
+                Some(PendingAge {
+                    id: t.id,
+                    description: t.description.clone(),
+                    created: created.clone(),
+                    age_days: today_n - date::to_days(created)?,
+                })
+            })
+            .collect();
+        oldest_pending.sort_by(|a, b| a.created.cmp(&b.created).then(a.id.cmp(&b.id)));
+        oldest_pending.truncate(OLDEST_LIMIT);
+
+        Stats {
+            total: tasks.len(),
+            pending: pending.len(),
+            done: tasks.len() - pending.len(),
+            archived: archive.len(),
+            by_priority,
+            by_tag,
+            days,
+            completed_recently,
+            oldest_pending,
+        }
+    }
+
+    /// 期間内の 1 日あたり完了数
+    pub fn per_day(&self) -> f64 {
+        if self.days == 0 {
+            0.0
+        } else {
+            self.completed_recently as f64 / self.days as f64
+        }
+    }
+
+    /// 人が読むためのテキスト
+    pub fn to_text(&self) -> String {
+        let counts = |map: &BTreeMap<String, usize>| {
+            if map.is_empty() {
+                "-".to_string()
+            } else {
+                map.iter()
+                    .map(|(k, v)| format!("{} {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }
+        };
+
+        let mut out = format!(
+            "Tasks: {} (pending {}, done {}, archived {})\n",
+            self.total, self.pending, self.done, self.archived
+        );
+        out.push_str(&format!("By priority: {}\n", counts(&self.by_priority)));
+        out.push_str(&format!("By tag: {}\n", counts(&self.by_tag)));
+        out.push_str(&format!(
+            "Completed in last {} day(s): {} ({:.2}/day)\n",
+            self.days,
+            self.completed_recently,
+            self.per_day()
+        ));
+
+        if !self.oldest_pending.is_empty() {
+            out.push_str("Oldest pending:\n");
+            for p in &self.oldest_pending {
+                out.push_str(&format!(
+                    "  {} {} (created {}, {} day(s) ago)\n",
+                    p.id, p.description, p.created, p.age_days
+                ));
+            }
+        }
+
+        out
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let counts = |map: &BTreeMap<String, usize>| {
+            JsonValue::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), JsonValue::Number(*v as f64)))
+                    .collect(),
+            )
+        };
+
+        let oldest = self
+            .oldest_pending
+            .iter()
+            .map(|p| {
+                JsonValue::Object(HashMap::from([
+                    ("id".to_string(), JsonValue::Number(p.id as f64)),
+                    ("description".to_string(), JsonValue::String(p.description.clone())),
+                    ("created".to_string(), JsonValue::String(p.created.clone())),
+                    ("age_days".to_string(), JsonValue::Number(p.age_days as f64)),
+                ]))
+            })
+            .collect();
+
+        JsonValue::Object(HashMap::from([
+            ("total".to_string(), JsonValue::Number(self.total as f64)),
+            ("pending".to_string(), JsonValue::Number(self.pending as f64)),
+            ("done".to_string(), JsonValue::Number(self.done as f64)),
+            ("archived".to_string(), JsonValue::Number(self.archived as f64)),
+            ("by_priority".to_string(), counts(&self.by_priority)),
+            ("by_tag".to_string(), counts(&self.by_tag)),
+            ("days".to_string(), JsonValue::Number(self.days as f64)),
+            (
+                "completed_recently".to_string(),
+                JsonValue::Number(self.completed_recently as f64),
+            ),
+            ("completed_per_day".to_string(), JsonValue::Number(self.per_day())),
+            ("oldest_pending".to_string(), JsonValue::Array(oldest)),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::parse_lines;
+
+    fn lines(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn sample() -> Stats {
+        let tasks = parse_lines(&lines(&[
+            "[ ] (A) Pay rent +home created:2024-04-01",
+            "[ ] Walk dog +home @park created:2024-03-15",
+            "[x] (B) Ship release +work created:2024-04-20 completed:2024-05-09",
+            "[ ] No date",
+        ]));
+        let archive = parse_lines(&lines(&[
+            "[x] Old +work completed:2024-05-04",
+            "[x] Older completed:2024-05-03",
+        ]));
+        Stats::compute(&tasks, &archive, "2024-05-10", 7)
+    }
+
+    #[test]
+    fn test_counts() {
+        let stats = sample();
+
+        assert_eq!((stats.total, stats.pending, stats.done, stats.archived), (4, 3, 1, 2));
+        assert_eq!(stats.by_priority.get("A"), Some(&1));
+        assert_eq!(stats.by_priority.get("none"), Some(&2));
+        assert_eq!(stats.by_priority.get("B"), None);
+        assert_eq!(stats.by_tag.get("+home"), Some(&2));
+        assert_eq!(stats.by_tag.get("@park"), Some(&1));
+        assert_eq!(stats.by_tag.get("+work"), Some(&1));
+    }
+
+    #[test]
+    fn test_completed_window() {
+        let stats = sample();
+
+        // 05-04 〜 05-10 の 7 日間。05-03 は範囲外
+        assert_eq!(stats.completed_recently, 2);
+        assert!((stats.per_day() - 2.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_oldest_pending() {
+        let stats = sample();
+        let ids: Vec<usize> = stats.oldest_pending.iter().map(|p| p.id).collect();
+
+        assert_eq!(ids, vec![2, 1]);
+        assert_eq!(stats.oldest_pending[0].age_days, 56);
+    }
+
+    #[test]
+    fn test_to_text_and_json() {
+        let stats = sample();
+
+        let text = stats.to_text();
+        assert!(text.contains("Tasks: 4 (pending 3, done 1, archived 2)"));
+        assert!(text.contains("By tag: +home 2, +work 1, @park 1"));
+        assert!(text.contains("Completed in last 7 day(s): 2 (0.29/day)"));
+
+        let json = json_parser::parse(&stats.to_json().to_string()).unwrap();
+        match json {
+            JsonValue::Object(map) => {
+                assert_eq!(map.get("pending"), Some(&JsonValue::Number(3.0)));
+                assert!(matches!(map.get("oldest_pending"), Some(JsonValue::Array(a)) if a.len() == 2));
+            }
+            _ => panic!("Expected object"),
+        }
+    }
+}
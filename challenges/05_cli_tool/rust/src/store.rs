@@ -0,0 +1,468 @@
+//! タスクの永続化層
+//!
+//! 保存先をトレイトで抽象化し、ファイル以外のバックエンドにも差し替えられるようにする
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::{self, Cipher};
+use crate::passphrase;
+use crate::timelog::{self, TimeEntry};
+use crate::Task;
+
+/// タスクの保存先を抽象化するトレイト
+pub trait TaskStore {
+    /// 全タスクを読み込む
+    fn load(&self) -> Result<Vec<Task>, String>;
+
+    /// 全タスクを書き込む (既存の内容は置き換える)
+    fn save(&mut self, tasks: &[Task]) -> Result<(), String>;
+
+    /// トップレベルのタスクを 1 件末尾に追加する
+    fn append(&mut self, task: &Task) -> Result<(), String>;
+
+    /// 片付けた完了タスクをアーカイブに追記する
+    fn archive(&mut self, tasks: &[Task]) -> Result<(), String>;
+
+    /// アーカイブ済みのタスクを読み込む
+    fn load_archive(&self) -> Result<Vec<Task>, String>;
+
+    /// 作業時間の記録を読み込む
+    fn load_entries(&self) -> Result<Vec<TimeEntry>, String>;
+
+    /// 作業時間の記録を書き込む (既存の記録は置き換える)
+    fn save_entries(&mut self, entries: &[TimeEntry]) -> Result<(), String>;
+}
+
+/// 1 行 1 タスクのテキストファイルに保存するストア
+///
+/// パスフレーズを渡すと、タスクファイルとアーカイブを暗号化して保存する。
+/// 暗号化されていないファイルも読めるので、次に書き込んだときに暗号化される。
+#[derive(Debug)]
+pub struct TextFileStore {
+    path: PathBuf,
+    cipher: Option<Cipher>,
+}
+
+impl TextFileStore {
+    pub fn new(path: PathBuf) -> Self {
+        TextFileStore { path, cipher: None }
+    }
+
+    /// 暗号化して保存するストア
+    pub fn encrypted(path: PathBuf, passphrase: &str) -> Self {
+        TextFileStore {
+            path,
+            cipher: Some(Cipher::new(passphrase)),
+        }
+    }
+
+    /// 既存のファイルを今の設定 (暗号化するかどうか) で書き直す
+    pub fn rewrite(&self) -> Result<(), String> {
+        for path in [self.path.clone(), self.archive_path()] {
+            if path.exists() {
+                let lines = self.read_lines(&path)?;
+                self.write_lines(&path, &lines)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// アーカイブファイルのパス (todo.txt なら todo.done.txt)
+    pub fn archive_path(&self) -> PathBuf {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = match self.path.extension() {
+            Some(ext) => format!("{}.done.{}", stem, ext.to_string_lossy()),
+            None => format!("{}.done", stem),
+        };
+        self.path.with_file_name(name)
+    }
+
+    /// ファイル全体を書き直す
+    fn write(&self, lines: &[String]) -> Result<(), String> {
+        self.write_lines(&self.path, lines)
+    }
+
+    /// ファイルを行単位で読み込む (存在しなければ空)
+    fn read_lines(&self, path: &Path) -> Result<Vec<String>, String> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut data = fs::read(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        if crypto::is_encrypted(&data) {
+            let cipher = self.cipher.as_ref().ok_or_else(|| {
+                format!("{:?} is encrypted; use --encrypt or set {}", path, passphrase::ENV)
+            })?;
+            data = cipher.decrypt(&data)?;
+        }
+
+        let text = String::from_utf8(data).map_err(|e| format!("Failed to read line: {}", e))?;
+        Ok(text.lines().map(str::to_string).collect())
+    }
+
+    fn write_lines(&self, path: &Path, lines: &[String]) -> Result<(), String> {
+        create_parent(path)?;
+        let text = lines.join("\n") + "\n";
+        let data = match &self.cipher {
+            Some(cipher) => cipher.encrypt(text.as_bytes()),
+            None => text.into_bytes(),
+        };
+        fs::write(path, data).map_err(|e| format!("Failed to write file: {}", e))
+    }
+
+    /// ファイル末尾に行を追記する (暗号化している場合は全体を書き直す)
+    fn append_lines(&self, path: &Path, lines: &[String]) -> Result<(), String> {
+        if self.cipher.is_some() {
+            let mut all = self.read_lines(path)?;
+            all.extend_from_slice(lines);
+            return self.write_lines(path, &all);
+        }
+        append_lines(path, lines)
+    }
+}
+
+/// 行を (タスク, 作業時間の記録) に分ける
+fn split_section(mut lines: Vec<String>) -> (Vec<String>, Vec<String>) {
+    match lines.iter().position(|l| l.trim() == timelog::SECTION) {
+        Some(i) => {
+            let entries = lines.split_off(i + 1);
+            lines.pop();
+            (lines, entries)
+        }
+        None => (lines, Vec::new()),
+    }
+}
+
+/// タスクと記録をまとめて 1 つのファイルの行にする
+fn join_section(tasks: &[Task], entries: &[TimeEntry]) -> Vec<String> {
+    let mut lines = format_tasks(tasks);
+    if !entries.is_empty() {
+        lines.push(timelog::SECTION.to_string());
+        lines.extend(entries.iter().map(|e| e.to_line()));
+    }
+    lines
+}
+
+/// 保存先のディレクトリが無ければ作る (~/.local/share/todo など)
+fn create_parent(path: &Path) -> Result<(), String> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() && !dir.exists() => fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create directory {:?}: {}", dir, e)),
+        _ => Ok(()),
+    }
+}
+
+/// ファイル末尾に行を追記する
+fn append_lines(path: &Path, lines: &[String]) -> Result<(), String> {
+    create_parent(path)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    for line in lines {
+        writeln!(file, "{}", line)
+            .map_err(|e| format!("Failed to write: {}", e))?;
+    }
+
+    Ok(())
+}
+
+impl TaskStore for TextFileStore {
+    fn load(&self) -> Result<Vec<Task>, String> {
+        let (tasks, _) = split_section(self.read_lines(&self.path)?);
+        Ok(parse_lines(&tasks))
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), String> {
+        let entries = self.load_entries()?;
+        self.write(&join_section(tasks, &entries))
+    }
+
+    fn append(&mut self, task: &Task) -> Result<(), String> {
+        let (_, entries) = split_section(self.read_lines(&self.path)?);
+        if entries.is_empty() {
+            return self.append_lines(&self.path, &[task.to_line()]);
+        }
+
+        // 記録の後ろには追記できないので書き直す
+        let mut tasks = self.load()?;
+        tasks.push(task.clone());
+        self.save(&tasks)
+    }
+
+    fn archive(&mut self, tasks: &[Task]) -> Result<(), String> {
+        let lines: Vec<String> = tasks.iter().map(|t| t.to_line()).collect();
+        self.append_lines(&self.archive_path(), &lines)
+    }
+
+    fn load_archive(&self) -> Result<Vec<Task>, String> {
+        // アーカイブは階層を持たないので、インデントがあっても無視される
+        let lines: Vec<String> = self.read_lines(&self.archive_path())?
+            .iter()
+            .map(|l| l.trim_start().to_string())
+            .collect();
+        Ok(parse_lines(&lines))
+    }
+
+    fn load_entries(&self) -> Result<Vec<TimeEntry>, String> {
+        let (_, entries) = split_section(self.read_lines(&self.path)?);
+        Ok(timelog::parse_entries(&entries))
+    }
+
+    fn save_entries(&mut self, entries: &[TimeEntry]) -> Result<(), String> {
+        let tasks = self.load()?;
+        self.write(&join_section(&tasks, entries))
+    }
+}
+
+/// 1 階層あたりのインデント
+const INDENT: &str = "  ";
+
+/// 使われていない ID を小さい順に `count` 個返す
+///
+/// 削除 (clear) で空いた ID は再利用する。ID は 1 から始まる。
+///
+/// ```
+/// use std::collections::HashSet;
+/// use cli_tool::store::allocate_ids;
+///
+/// let used: HashSet<usize> = [1, 3].into_iter().collect();
+/// assert_eq!(allocate_ids(&used, 2), vec![2, 4]);
+/// ```
+pub fn allocate_ids(used: &HashSet<usize>, count: usize) -> Vec<usize> {
+    (1..).filter(|id| !used.contains(id)).take(count).collect()
+}
+
+/// ファイルの各行をタスクに変換する
+///
+/// ID は行末の `id:N` から読む。ID が無い行 (古い形式) や重複した行には
+/// 空いている ID をファイル順に割り当てる。インデントが深い行は、
+/// それより前にある浅い行の子タスクになる。
+pub fn parse_lines(lines: &[String]) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut depths = Vec::new();
+    let mut used = HashSet::new();
+
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let body = line.trim_start();
+        depths.push((line.len() - body.len()) / INDENT.len());
+
+        // ID 0 は「未割り当て」の印
+        let mut task = Task::from_line(0, body);
+        if task.id != 0 && !used.insert(task.id) {
+            task.id = 0;
+        }
+        tasks.push(task);
+    }
+
+    let missing = tasks.iter().filter(|t| t.id == 0).count();
+    let mut fresh = allocate_ids(&used, missing).into_iter();
+    for task in tasks.iter_mut().filter(|t| t.id == 0) {
+        task.id = fresh.next().unwrap_or_default();
+    }
+
+    // (深さ, ID) のスタック。末尾が直近の祖先候補
+    let mut ancestors: Vec<(usize, usize)> = Vec::new();
+    for (task, &depth) in tasks.iter_mut().zip(&depths) {
+        while ancestors.last().is_some_and(|&(d, _)| d >= depth) {
+            ancestors.pop();
+        }
+        task.parent = ancestors.last().map(|&(_, id)| id);
+        ancestors.push((depth, task.id));
+    }
+
+    tasks
+}
+
+/// タスクを行に変換する (子タスクは親より 1 段深くインデント)
+///
+/// 親の直後に子孫が並ぶ順序で渡されることを前提とする。
+/// 親が見つからないタスクはトップレベルとして書き出す。
+pub fn format_tasks(tasks: &[Task]) -> Vec<String> {
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+
+    tasks
+        .iter()
+        .map(|task| {
+            let depth = task
+                .parent
+                .and_then(|p| depths.get(&p))
+                .map_or(0, |d| d + 1);
+            depths.insert(task.id, depth);
+            format!("{}{}", INDENT.repeat(depth), task.to_line())
+        })
+        .collect()
+}
+
+/// メモリ上にだけ保持するストア (テストや埋め込み用)
+///
+/// ファイルと同じ行形式で保持するので、読み込み時の ID の扱いも同じになる
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    pub lines: Vec<String>,
+    pub archived: Vec<String>,
+    pub entries: Vec<String>,
+}
+
+impl TaskStore for MemoryStore {
+    fn load(&self) -> Result<Vec<Task>, String> {
+        Ok(parse_lines(&self.lines))
+    }
+
+    fn save(&mut self, tasks: &[Task]) -> Result<(), String> {
+        self.lines = format_tasks(tasks);
+        Ok(())
+    }
+
+    fn append(&mut self, task: &Task) -> Result<(), String> {
+        self.lines.push(task.to_line());
+        Ok(())
+    }
+
+    fn archive(&mut self, tasks: &[Task]) -> Result<(), String> {
+        self.archived.extend(tasks.iter().map(|t| t.to_line()));
+        Ok(())
+    }
+
+    fn load_archive(&self) -> Result<Vec<Task>, String> {
+        Ok(parse_lines(&self.archived))
+    }
+
+    fn load_entries(&self) -> Result<Vec<TimeEntry>, String> {
+        Ok(timelog::parse_entries(&self.entries))
+    }
+
+    fn save_entries(&mut self, entries: &[TimeEntry]) -> Result<(), String> {
+        self.entries = entries.iter().map(|e| e.to_line()).collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_nested_lines() {
+        let tasks = parse_lines(&lines(&[
+            "[ ] Parent",
+            "  [ ] Child",
+            "    [x] Grandchild",
+            "  [ ] Second child",
+            "[ ] Other",
+        ]));
+
+        let parents: Vec<_> = tasks.iter().map(|t| t.parent).collect();
+        assert_eq!(parents, vec![None, Some(1), Some(2), Some(1), None]);
+        assert_eq!(tasks[2].description, "Grandchild");
+        assert!(tasks[2].done);
+    }
+
+    #[test]
+    fn test_format_round_trip() {
+        let raw = lines(&["[ ] A id:1", "  [ ] B id:2", "    [ ] C id:3", "[x] D id:4"]);
+        assert_eq!(format_tasks(&parse_lines(&raw)), raw);
+    }
+
+    #[test]
+    fn test_parse_keeps_stored_ids() {
+        let tasks = parse_lines(&lines(&["[ ] A id:7", "", "  [ ] B id:3"]));
+
+        assert_eq!(tasks[0].id, 7);
+        assert_eq!(tasks[1].id, 3);
+        assert_eq!(tasks[1].parent, Some(7));
+    }
+
+    #[test]
+    fn test_parse_assigns_free_ids_to_legacy_lines() {
+        // ID の無い行には、使われていない ID が小さい順に振られる
+        let tasks = parse_lines(&lines(&["[ ] A", "[ ] B id:1", "[ ] C", "[ ] D id:1"]));
+        let ids: Vec<usize> = tasks.iter().map(|t| t.id).collect();
+
+        assert_eq!(ids, vec![2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn test_allocate_ids_reuses_gaps() {
+        let used: HashSet<usize> = [1, 2, 4, 7].into_iter().collect();
+        assert_eq!(allocate_ids(&used, 3), vec![3, 5, 6]);
+        assert_eq!(allocate_ids(&HashSet::new(), 2), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_archive_path() {
+        let store = TextFileStore::new(PathBuf::from("dir/todo.txt"));
+        assert_eq!(store.archive_path(), PathBuf::from("dir/todo.done.txt"));
+
+        let store = TextFileStore::new(PathBuf::from("tasks"));
+        assert_eq!(store.archive_path(), PathBuf::from("tasks.done"));
+    }
+
+    #[test]
+    fn test_split_section() {
+        let (tasks, entries) = split_section(lines(&[
+            "[ ] A id:1",
+            "## time",
+            "2024-05-10T09:00:00Z - id:1",
+        ]));
+        assert_eq!(tasks, lines(&["[ ] A id:1"]));
+        assert_eq!(entries, lines(&["2024-05-10T09:00:00Z - id:1"]));
+
+        let tasks = parse_lines(&tasks);
+        let entries = timelog::parse_entries(&entries);
+        assert_eq!(join_section(&tasks, &entries).len(), 3);
+        assert_eq!(join_section(&tasks, &[]), lines(&["[ ] A id:1"]));
+    }
+
+    #[test]
+    fn test_format_orphan_goes_to_top_level() {
+        let mut tasks = parse_lines(&lines(&["[ ] A", "  [ ] B"]));
+        tasks.remove(0);
+        assert_eq!(format_tasks(&tasks), lines(&["[ ] B id:2"]));
+    }
+
+    #[test]
+    fn test_encrypted_store() {
+        let dir = std::env::temp_dir().join(format!("todo-encrypted-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("todo.txt");
+        fs::write(&path, "[ ] Plain id:1\n").unwrap();
+
+        // 平文のファイルを読み、暗号化して書き直す
+        let mut store = TextFileStore::encrypted(path.clone(), "secret");
+        store.rewrite().unwrap();
+        store.append(&Task::from_line(2, "[x] Secret plan")).unwrap();
+        store.archive(&store.load().unwrap()[1..]).unwrap();
+
+        for file in [&path, &store.archive_path()] {
+            let data = fs::read(file).unwrap();
+            assert!(crypto::is_encrypted(&data));
+            assert!(!String::from_utf8_lossy(&data).contains("Secret"));
+        }
+        let descriptions: Vec<String> = store.load().unwrap().into_iter().map(|t| t.description).collect();
+        assert_eq!(descriptions, ["Plain", "Secret plan"]);
+        assert_eq!(store.load_archive().unwrap()[0].description, "Secret plan");
+
+        assert!(TextFileStore::new(path.clone()).load().unwrap_err().contains("encrypted"));
+        assert!(TextFileStore::encrypted(path, "wrong").load().is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,77 @@
+//! ANSI エスケープによる端末出力の装飾
+//!
+//! 色を付けるかどうかは `--no-color`、環境変数 `NO_COLOR`、
+//! 標準出力が端末かどうかから自動で決める。
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// 出力スタイル (無効なら文字列をそのまま返す)
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    enabled: bool,
+}
+
+impl Style {
+    /// 色付けを明示的に指定して作る
+    pub fn new(enabled: bool) -> Self {
+        Style { enabled }
+    }
+
+    /// 実行環境から色付けの有無を判定する
+    ///
+    /// `no_color` が true、`NO_COLOR` が空でない値で設定されている、
+    /// または標準出力が端末でなければ無効になる。
+    pub fn detect(no_color: bool) -> Self {
+        let env_disabled = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Style::new(!no_color && !env_disabled && io::stdout().is_terminal())
+    }
+
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn dim(&self, text: &str) -> String {
+        self.paint(text, "2")
+    }
+
+    pub fn red(&self, text: &str) -> String {
+        self.paint(text, "31")
+    }
+
+    pub fn yellow(&self, text: &str) -> String {
+        self.paint(text, "33")
+    }
+
+    pub fn green(&self, text: &str) -> String {
+        self.paint(text, "32")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_returns_plain_text() {
+        let style = Style::new(false);
+        assert_eq!(style.red("x"), "x");
+        assert_eq!(style.dim("x"), "x");
+    }
+
+    #[test]
+    fn test_enabled_wraps_with_escape() {
+        let style = Style::new(true);
+        assert_eq!(style.red("x"), "\x1b[31mx\x1b[0m");
+        assert_eq!(style.dim("x"), "\x1b[2mx\x1b[0m");
+    }
+
+    #[test]
+    fn test_detect_respects_flag() {
+        assert!(!Style::detect(true).enabled);
+    }
+}
@@ -0,0 +1,283 @@
+//! `todo sync` — REST API とのタスク一覧の同期
+//!
+//! サーバーとは `GET /tasks` と `PUT /tasks` で次の文書をやり取りする。
+//!
+//! ```text
+//! {"updated": 1715335200, "tasks": [ ... formats の JSON 形式 ... ]}
+//! ```
+//!
+//! 前回同期したときの一覧 (`todo.sync.json`) を基準に三方向でマージし、
+//! 両方で変わったタスクは更新が新しい側を採用する (last-write-wins)。
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use json_parser::JsonValue;
+
+use crate::formats::{tasks_from_json, tasks_to_json};
+use crate::{store, SortKey, Task};
+
+/// サーバーとやり取りする文書
+#[derive(Debug, Default, PartialEq)]
+pub struct Document {
+    /// 最後に書き込まれた時刻 (UNIX 秒)
+    pub updated: u64,
+    pub tasks: Vec<Task>,
+}
+
+impl Document {
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(HashMap::from([
+            ("updated".to_string(), JsonValue::Number(self.updated as f64)),
+            ("tasks".to_string(), tasks_to_json(&self.tasks)),
+        ]))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let value = json_parser::parse(text).map_err(|e| format!("Sync: {}", e))?;
+        let JsonValue::Object(map) = value else {
+            return Err("Sync: expected a JSON object".to_string());
+        };
+
+        let updated = match map.get("updated") {
+            Some(JsonValue::Number(n)) if *n >= 0.0 => *n as u64,
+            None | Some(JsonValue::Null) => 0,
+            _ => return Err("Sync: invalid 'updated'".to_string()),
+        };
+        let tasks = match map.get("tasks") {
+            Some(tasks) => tasks_from_json(tasks)?,
+            None => Vec::new(),
+        };
+        Ok(Document { updated, tasks })
+    }
+}
+
+/// 前回同期した一覧の保存先 (todo.txt なら todo.sync.json)
+pub fn state_path(file: &Path) -> PathBuf {
+    let stem = file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    file.with_file_name(format!("{}.sync.json", stem))
+}
+
+/// 前回同期した一覧を読み込む (初回は空)
+pub fn load_state(path: &Path) -> Result<Vec<Task>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+    Ok(Document::parse(&text)?.tasks)
+}
+
+pub fn save_state(path: &Path, document: &Document) -> Result<(), String> {
+    fs::write(path, document.to_json().to_pretty_string() + "\n")
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// 1 件の変更の種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// 片側に反映する変更
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub task: Task,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = match self.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Updated => '~',
+            ChangeKind::Removed => '-',
+        };
+        write!(f, "{} {} {}", mark, self.task.id, self.task.description)
+    }
+}
+
+impl Change {
+    pub fn to_json(&self) -> JsonValue {
+        let kind = match self.kind {
+            ChangeKind::Added => "added",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Removed => "removed",
+        };
+        JsonValue::Object(HashMap::from([
+            ("kind".to_string(), JsonValue::String(kind.to_string())),
+            ("id".to_string(), JsonValue::Number(self.task.id as f64)),
+            ("description".to_string(), JsonValue::String(self.task.description.clone())),
+        ]))
+    }
+}
+
+/// `before` を `after` にするための変更 (同じなら None)
+fn change(before: Option<&Task>, after: Option<&Task>) -> Option<Change> {
+    let (kind, task) = match (before, after) {
+        (None, Some(t)) => (ChangeKind::Added, t),
+        (Some(t), None) => (ChangeKind::Removed, t),
+        (Some(a), Some(b)) if a != b => (ChangeKind::Updated, b),
+        _ => return None,
+    };
+    Some(Change { kind, task: task.clone() })
+}
+
+/// マージの結果
+#[derive(Debug, Default)]
+pub struct Merge {
+    pub tasks: Vec<Task>,
+    /// サーバーへ送る変更
+    pub pushed: Vec<Change>,
+    /// 手元に取り込む変更
+    pub pulled: Vec<Change>,
+}
+
+/// 前回から両側で別々に追加されて ID がぶつかった手元のタスクに新しい ID を振る
+fn renumber_collisions(base: &[Task], local: &[Task], remote: &[Task]) -> Vec<Task> {
+    let find = |tasks: &[Task], id| tasks.iter().find(|t| t.id == id).cloned();
+    let colliding: Vec<usize> = local
+        .iter()
+        .filter(|t| find(base, t.id).is_none())
+        .filter(|t| find(remote, t.id).is_some_and(|r| r != **t))
+        .map(|t| t.id)
+        .collect();
+
+    let used: HashSet<usize> = base.iter().chain(local).chain(remote).map(|t| t.id).collect();
+    let renamed: HashMap<usize, usize> = colliding
+        .iter()
+        .copied()
+        .zip(store::allocate_ids(&used, colliding.len()))
+        .collect();
+
+    local
+        .iter()
+        .map(|t| Task {
+            id: renamed.get(&t.id).copied().unwrap_or(t.id),
+            parent: t.parent.map(|p| renamed.get(&p).copied().unwrap_or(p)),
+            ..t.clone()
+        })
+        .collect()
+}
+
+/// 三方向マージ。両側で変わったタスクは `local_wins` で決める
+pub fn merge(base: &[Task], local: &[Task], remote: &[Task], local_wins: bool) -> Merge {
+    let local = renumber_collisions(base, local, remote);
+    let find = |tasks: &[Task], id| tasks.iter().find(|t| t.id == id).cloned();
+
+    // 手元の順序を保ち、サーバーにしかないものは後ろに足す
+    let mut ids: Vec<usize> = local.iter().map(|t| t.id).collect();
+    for task in remote.iter().chain(base) {
+        if !ids.contains(&task.id) {
+            ids.push(task.id);
+        }
+    }
+
+    let mut merge = Merge::default();
+    let mut merged = Vec::new();
+    for id in ids {
+        let (b, l, r) = (find(base, id), find(&local, id), find(remote, id));
+        let chosen = if l == r || l == b {
+            r.clone()
+        } else if r == b || local_wins {
+            l.clone()
+        } else {
+            r.clone()
+        };
+
+        merge.pulled.extend(change(l.as_ref(), chosen.as_ref()));
+        merge.pushed.extend(change(r.as_ref(), chosen.as_ref()));
+        merged.extend(chosen);
+    }
+
+    // 親の直後に子が並ぶ順序に戻す
+    merge.tasks = crate::sort_tasks(&merged, SortKey::File);
+    merge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::parse_lines;
+
+    fn tasks(raw: &[&str]) -> Vec<Task> {
+        parse_lines(&raw.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    fn outline(tasks: &[Task]) -> Vec<String> {
+        tasks.iter().map(|t| t.to_line()).collect()
+    }
+
+    #[test]
+    fn test_merge_one_sided_changes() {
+        let base = tasks(&["[ ] A id:1", "[ ] B id:2", "[ ] C id:3"]);
+        let local = tasks(&["[x] A id:1", "[ ] B id:2", "[ ] C id:3", "[ ] D id:4"]);
+        let remote = tasks(&["[ ] A id:1", "[ ] B2 id:2", "[ ] E id:5"]);
+
+        let merge = merge(&base, &local, &remote, false);
+
+        assert_eq!(
+            outline(&merge.tasks),
+            vec!["[x] A id:1", "[ ] B2 id:2", "[ ] D id:4", "[ ] E id:5"]
+        );
+        let pushed: Vec<String> = merge.pushed.iter().map(|c| c.to_string()).collect();
+        let pulled: Vec<String> = merge.pulled.iter().map(|c| c.to_string()).collect();
+        assert_eq!(pushed, vec!["~ 1 A", "+ 4 D"]);
+        assert_eq!(pulled, vec!["~ 2 B2", "- 3 C", "+ 5 E"]);
+    }
+
+    #[test]
+    fn test_merge_conflict_last_write_wins() {
+        let base = tasks(&["[ ] A id:1"]);
+        let local = tasks(&["[ ] A local id:1"]);
+        let remote = tasks(&["[ ] A remote id:1"]);
+
+        assert_eq!(outline(&merge(&base, &local, &remote, true).tasks), vec!["[ ] A local id:1"]);
+        assert_eq!(outline(&merge(&base, &local, &remote, false).tasks), vec!["[ ] A remote id:1"]);
+
+        // 手元で消したタスクがサーバーで変更されていた場合も新しい側を採る
+        let merge = merge(&base, &[], &remote, true);
+        assert!(merge.tasks.is_empty());
+        assert_eq!(merge.pushed[0].kind, ChangeKind::Removed);
+    }
+
+    #[test]
+    fn test_merge_keeps_both_new_tasks_with_same_id() {
+        let local = tasks(&["[ ] Mine id:1", "  [ ] Mine child id:2"]);
+        let remote = tasks(&["[ ] Theirs id:1"]);
+
+        let merge = merge(&[], &local, &remote, true);
+        assert_eq!(
+            outline(&merge.tasks),
+            vec!["[ ] Mine id:3", "[ ] Mine child id:2", "[ ] Theirs id:1"]
+        );
+        assert_eq!(merge.tasks[1].parent, Some(3));
+        assert_eq!(merge.pushed.len(), 2);
+    }
+
+    #[test]
+    fn test_document_round_trip() {
+        let document = Document {
+            updated: 1_715_335_200,
+            tasks: tasks(&["[ ] A id:1", "  [x] B id:2"]),
+        };
+        assert_eq!(Document::parse(&document.to_json().to_string()).unwrap(), document);
+        assert!(Document::parse("[]").is_err());
+        assert_eq!(Document::parse("{}").unwrap(), Document::default());
+    }
+
+    #[test]
+    fn test_state_path() {
+        assert_eq!(
+            state_path(Path::new("dir/todo.txt")),
+            PathBuf::from("dir/todo.sync.json")
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! 作業時間の記録 (`todo start` / `stop` / `time`)
+//!
+//! 記録はタスクファイルの `## time` 行より後ろに 1 行 1 件で保存する。
+//!
+//! ```text
+//! 2024-05-10T09:00:00Z 2024-05-10T10:30:00Z id:3
+//! 2024-05-10T11:00:00Z - id:5
+//! ```
+//!
+//! 終了時刻が `-` の記録は計測中を表す。
+
+use std::collections::{BTreeMap, HashMap};
+
+use json_parser::JsonValue;
+
+use crate::{date, Task};
+
+/// タスクと記録を区切る行
+pub const SECTION: &str = "## time";
+
+/// 1 回分の作業時間
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeEntry {
+    pub task: usize,
+    /// 開始時刻 (UNIX 秒)
+    pub start: u64,
+    /// 終了時刻。計測中なら None
+    pub end: Option<u64>,
+}
+
+impl TimeEntry {
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let start = date::parse_timestamp(parts.next()?)?;
+        let end = match parts.next()? {
+            "-" => None,
+            s => Some(date::parse_timestamp(s)?),
+        };
+        let task = parts.next()?.strip_prefix("id:")?.parse().ok()?;
+        Some(TimeEntry { task, start, end })
+    }
+
+    pub fn to_line(&self) -> String {
+        let end = self
+            .end
+            .map(date::format_timestamp)
+            .unwrap_or_else(|| "-".to_string());
+        format!("{} {} id:{}", date::format_timestamp(self.start), end, self.task)
+    }
+
+    /// 経過秒数 (計測中なら `now` までの分)
+    pub fn duration(&self, now: u64) -> u64 {
+        self.end.unwrap_or(now).saturating_sub(self.start)
+    }
+
+    pub fn to_json(&self, now: u64) -> JsonValue {
+        let stamp = |secs| JsonValue::String(date::format_timestamp(secs));
+        JsonValue::Object(HashMap::from([
+            ("task".to_string(), JsonValue::Number(self.task as f64)),
+            ("start".to_string(), stamp(self.start)),
+            ("end".to_string(), self.end.map_or(JsonValue::Null, stamp)),
+            ("seconds".to_string(), JsonValue::Number(self.duration(now) as f64)),
+        ]))
+    }
+}
+
+/// 記録の行を読み込む (読めない行は無視する)
+pub fn parse_entries(lines: &[String]) -> Vec<TimeEntry> {
+    lines.iter().filter_map(|l| TimeEntry::from_line(l)).collect()
+}
+
+/// `1h 05m` 形式
+pub fn format_duration(secs: u64) -> String {
+    format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+}
+
+/// `todo time` の集計結果 (値はすべて秒)
+#[derive(Debug, Default, PartialEq)]
+pub struct TimeReport {
+    /// (タスク ID, 説明) ごとの合計。消えたタスクの説明は None
+    pub by_task: BTreeMap<usize, (Option<String>, u64)>,
+    pub by_tag: BTreeMap<String, u64>,
+    /// 開始日 (UTC) ごとの合計
+    pub by_day: BTreeMap<String, u64>,
+    pub total: u64,
+}
+
+impl TimeReport {
+    /// `tasks` にはアーカイブ済みのタスクも含めて渡す (後にあるものを優先)
+    pub fn compute(entries: &[TimeEntry], tasks: &[Task], now: u64) -> Self {
+        let mut report = TimeReport::default();
+
+        for entry in entries {
+            let secs = entry.duration(now);
+            let task = tasks.iter().rev().find(|t| t.id == entry.task);
+
+            let slot = report
+                .by_task
+                .entry(entry.task)
+                .or_insert_with(|| (task.map(|t| t.description.clone()), 0));
+            slot.1 += secs;
+
+            for tag in task.map(|t| t.tags()).unwrap_or_default() {
+                *report.by_tag.entry(tag.to_string()).or_insert(0) += secs;
+            }
+
+            let day = date::from_days((entry.start / 86_400) as i64);
+            *report.by_day.entry(day).or_insert(0) += secs;
+            report.total += secs;
+        }
+
+        report
+    }
+
+    pub fn to_text(&self) -> String {
+        if self.by_task.is_empty() {
+            return "No time recorded.\n".to_string();
+        }
+
+        let mut out = "By task:\n".to_string();
+        for (id, (description, secs)) in &self.by_task {
+            let description = description.as_deref().unwrap_or("(removed)");
+            out.push_str(&format!("  {:>8}  {} {}\n", format_duration(*secs), id, description));
+        }
+
+        let sections = [("By tag:", &self.by_tag), ("By day:", &self.by_day)];
+        for (title, map) in sections {
+            if map.is_empty() {
+                continue;
+            }
+            out.push_str(title);
+            out.push('\n');
+            for (key, secs) in map {
+                out.push_str(&format!("  {:>8}  {}\n", format_duration(*secs), key));
+            }
+        }
+
+        out.push_str(&format!("Total: {}\n", format_duration(self.total)));
+        out
+    }
+
+    /// 値はすべて秒数
+    pub fn to_json(&self) -> JsonValue {
+        let seconds = |secs: u64| JsonValue::Number(secs as f64);
+        let totals = |map: &BTreeMap<String, u64>| {
+            JsonValue::Object(map.iter().map(|(k, v)| (k.clone(), seconds(*v))).collect())
+        };
+
+        let by_task = self
+            .by_task
+            .iter()
+            .map(|(id, (description, secs))| {
+                JsonValue::Object(HashMap::from([
+                    ("id".to_string(), JsonValue::Number(*id as f64)),
+                    (
+                        "description".to_string(),
+                        description.clone().map_or(JsonValue::Null, JsonValue::String),
+                    ),
+                    ("seconds".to_string(), seconds(*secs)),
+                ]))
+            })
+            .collect();
+
+        JsonValue::Object(HashMap::from([
+            ("by_task".to_string(), JsonValue::Array(by_task)),
+            ("by_tag".to_string(), totals(&self.by_tag)),
+            ("by_day".to_string(), totals(&self.by_day)),
+            ("total".to_string(), seconds(self.total)),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::parse_lines;
+
+    const T0: u64 = 1_715_335_200; // 2024-05-10T10:00:00Z
+
+    #[test]
+    fn test_line_round_trip() {
+        for line in [
+            "2024-05-10T09:00:00Z 2024-05-10T10:30:00Z id:3",
+            "2024-05-10T11:00:00Z - id:5",
+        ] {
+            assert_eq!(TimeEntry::from_line(line).unwrap().to_line(), line);
+        }
+        assert_eq!(TimeEntry::from_line("2024-05-10T11:00:00Z -"), None);
+        assert_eq!(TimeEntry::from_line("[ ] task"), None);
+    }
+
+    #[test]
+    fn test_duration() {
+        let entry = TimeEntry { task: 1, start: T0, end: None };
+        assert_eq!(entry.duration(T0 + 90), 90);
+        assert_eq!(format_duration(3600 + 5 * 60), "1h 05m");
+    }
+
+    #[test]
+    fn test_report() {
+        let tasks = parse_lines(&[
+            "[ ] Write report +work".to_string(),
+            "[ ] Gym +health".to_string(),
+        ]);
+        let entries = vec![
+            TimeEntry { task: 1, start: T0, end: Some(T0 + 3600) },
+            TimeEntry { task: 2, start: T0 + 86_400, end: Some(T0 + 86_400 + 1800) },
+            TimeEntry { task: 1, start: T0 + 86_400, end: None },
+            TimeEntry { task: 9, start: T0, end: Some(T0 + 60) },
+        ];
+        let report = TimeReport::compute(&entries, &tasks, T0 + 86_400 + 600);
+
+        assert_eq!(report.by_task[&1], (Some("Write report +work".to_string()), 4200));
+        assert_eq!(report.by_task[&9].0, None);
+        assert_eq!(report.by_tag["+work"], 4200);
+        assert_eq!(report.by_tag["+health"], 1800);
+        assert_eq!(report.by_day["2024-05-10"], 3660);
+        assert_eq!(report.by_day["2024-05-11"], 2400);
+        assert_eq!(report.total, 6060);
+
+        let text = report.to_text();
+        assert!(text.contains("1h 10m  1 Write report +work"));
+        assert!(text.contains("9 (removed)"));
+        assert!(text.contains("Total: 1h 41m"));
+
+        let json = report.to_json().to_string();
+        assert!(json.contains(r#""by_day":{"2024-05-10":3660,"2024-05-11":2400}"#));
+        assert!(json.contains(r#"{"description":null,"id":9,"seconds":60}"#));
+    }
+}
@@ -0,0 +1,108 @@
+//! ファイルの変更監視 (`todo list --watch`)
+//!
+//! 更新時刻とサイズを一定間隔で見比べるだけのポーリング式。OS ごとの通知 API
+//! (inotify など) を使わないので、標準ライブラリだけでどの環境でも動く。
+//! このクレートの型に依存しないので、他の課題からもそのまま使える。
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//! use std::time::Duration;
+//! use cli_tool::watch::Watcher;
+//!
+//! let mut watcher = Watcher::new(vec![PathBuf::from("todo.txt")], Duration::from_millis(500));
+//! loop {
+//!     watcher.wait();
+//!     println!("changed");
+//! }
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// 既定の確認間隔
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 変更の判定に使う値 (ファイルが無ければ None)
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+/// 複数のファイルをまとめて監視する
+#[derive(Debug)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    interval: Duration,
+    stamps: Vec<Stamp>,
+}
+
+impl Watcher {
+    /// 今の状態を基準にして監視を始める
+    pub fn new(paths: Vec<PathBuf>, interval: Duration) -> Self {
+        let stamps = paths.iter().map(|p| stamp(p)).collect();
+        Watcher {
+            paths,
+            interval,
+            stamps,
+        }
+    }
+
+    /// 前回の確認から変わったファイルがあるか (作成・削除も変更に含む)
+    pub fn changed(&mut self) -> bool {
+        let stamps: Vec<Stamp> = self.paths.iter().map(|p| stamp(p)).collect();
+        let changed = stamps != self.stamps;
+        self.stamps = stamps;
+        changed
+    }
+
+    /// 変更があるまで待つ
+    pub fn wait(&mut self) {
+        while !self.changed() {
+            thread::sleep(self.interval);
+        }
+    }
+
+    /// 変更があるまで最大 `timeout` だけ待ち、変更があったかを返す
+    pub fn wait_timeout(&mut self, timeout: Duration) -> bool {
+        let deadline = SystemTime::now() + timeout;
+        loop {
+            if self.changed() {
+                return true;
+            }
+            if SystemTime::now() >= deadline {
+                return false;
+            }
+            thread::sleep(self.interval);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_create_modify_and_delete() {
+        let path = std::env::temp_dir().join(format!("todo-watch-{}.txt", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut watcher = Watcher::new(vec![path.clone()], Duration::from_millis(10));
+        assert!(!watcher.changed());
+
+        fs::write(&path, "a\n").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        // 更新時刻の精度が粗くてもサイズの違いで気付く
+        fs::write(&path, "a\nb\n").unwrap();
+        assert!(watcher.wait_timeout(Duration::from_secs(1)));
+
+        fs::remove_file(&path).unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.wait_timeout(Duration::from_millis(50)));
+    }
+}
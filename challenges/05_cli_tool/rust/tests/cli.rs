@@ -0,0 +1,399 @@
+//! ビルドしたバイナリを実際に起動して確かめる結合テスト
+//!
+//! テストごとに一時ディレクトリを作り、そこを HOME とカレントディレクトリにする。
+//! 環境変数は空にしてから必要なものだけ渡すので、実行する人の設定には影響されない。
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+
+use json_parser::JsonValue;
+
+/// テスト用の一時ディレクトリ (終わったら消す)
+struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("todo-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Sandbox { dir }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_cli_tool"));
+        command
+            .args(args)
+            .current_dir(&self.dir)
+            .env_clear()
+            .env("HOME", &self.dir)
+            .env("LANG", "C")
+            .env("NO_COLOR", "1")
+            .env("TODO_FILE", self.path("todo.txt"))
+            .stdin(Stdio::null());
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    fn run_with_stdin(&self, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .command(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.path(name)).unwrap_or_default()
+    }
+
+    /// ID や日付を除いたタスクファイルの各行
+    fn outline(&self) -> Vec<String> {
+        self.read("todo.txt")
+            .lines()
+            .take_while(|l| *l != "## time")
+            .map(|l| l[..l.find(" id:").unwrap_or(l.len())].to_string())
+            .collect()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// 成功したことを確かめて標準出力を返す
+fn ok(output: Output) -> String {
+    assert!(output.status.success(), "stderr: {}", stderr(&output));
+    stdout(&output)
+}
+
+/// 失敗 (終了コード 1) したことを確かめて標準エラーを返す
+fn fail(output: Output) -> String {
+    assert_eq!(output.status.code(), Some(1), "stdout: {}", stdout(&output));
+    stderr(&output)
+}
+
+fn field<'a>(value: &'a JsonValue, key: &str) -> &'a JsonValue {
+    match value {
+        JsonValue::Object(map) => map.get(key).unwrap_or_else(|| panic!("missing {}", key)),
+        _ => panic!("expected an object"),
+    }
+}
+
+#[test]
+fn test_help() {
+    let sandbox = Sandbox::new("help");
+    assert!(ok(sandbox.run(&["help"])).contains("USAGE:"));
+    assert!(ok(sandbox.run(&[])).contains("COMMANDS:"));
+
+    let err = fail(sandbox.run(&["frobnicate"]));
+    assert!(err.contains("Error: Unknown command: frobnicate"));
+}
+
+#[test]
+fn test_add_list_done() {
+    let sandbox = Sandbox::new("basic");
+    assert_eq!(ok(sandbox.run(&["add", "Buy milk"])), "Added: Buy milk\n");
+    ok(sandbox.run(&["add", "Write report"]));
+
+    assert!(sandbox.read("todo.txt").starts_with("[ ] Buy milk id:1 created:"));
+    assert_eq!(
+        ok(sandbox.run(&["list"])),
+        "Tasks:\n  1 [ ] Buy milk\n  2 [ ] Write report\n"
+    );
+
+    assert_eq!(ok(sandbox.run(&["done", "milk"])), "Done: Buy milk\n");
+    assert_eq!(ok(sandbox.run(&["done", "1"])), "Task 1 is already done\n");
+    assert_eq!(sandbox.outline(), ["[x] Buy milk", "[ ] Write report"]);
+}
+
+#[test]
+fn test_add_from_stdin_and_subtasks() {
+    let sandbox = Sandbox::new("stdin");
+    ok(sandbox.run_with_stdin(&["add", "-"], "Plan trip\n\nBook hotel\n"));
+    ok(sandbox.run(&["add", "--parent", "1", "Pick dates"]));
+
+    assert_eq!(sandbox.outline(), ["[ ] Plan trip", "  [ ] Pick dates", "[ ] Book hotel"]);
+
+    let err = fail(sandbox.run(&["done", "1"]));
+    assert!(err.contains("pending subtask"));
+    ok(sandbox.run(&["done", "1", "--cascade"]));
+    assert_eq!(sandbox.outline(), ["[x] Plan trip", "  [x] Pick dates", "[ ] Book hotel"]);
+}
+
+#[test]
+fn test_invalid_ids_leave_the_file_alone() {
+    let sandbox = Sandbox::new("invalid");
+    ok(sandbox.run(&["add", "A"]));
+    let before = sandbox.read("todo.txt");
+
+    assert!(fail(sandbox.run(&["done", "9"])).contains("Error: Task 9 not found"));
+    assert!(fail(sandbox.run(&["done", "1", "9"])).contains("Task 9 not found"));
+    assert!(fail(sandbox.run(&["rm", "5-3"])).contains("Invalid ID range: 5-3"));
+    assert!(fail(sandbox.run(&["rm", "1x"])).contains("Invalid task ID: 1x"));
+    assert!(fail(sandbox.run(&["done", "nothing"])).contains("No pending task matches"));
+    assert!(fail(sandbox.run(&["done"])).contains("done requires a task ID"));
+
+    assert_eq!(sandbox.read("todo.txt"), before);
+}
+
+#[test]
+fn test_rm_and_clear() {
+    let sandbox = Sandbox::new("rm");
+    for task in ["A", "B", "C", "D"] {
+        ok(sandbox.run(&["add", task]));
+    }
+    ok(sandbox.run(&["done", "2", "4"]));
+
+    assert_eq!(ok(sandbox.run(&["rm", "1"])), "Removed 1 task(s).\n");
+    assert_eq!(ok(sandbox.run(&["clear"])), "Cleared 2 completed task(s).\n");
+    assert_eq!(ok(sandbox.run(&["clear"])), "No completed tasks to clear.\n");
+
+    assert_eq!(sandbox.outline(), ["[ ] C"]);
+    let archive = sandbox.read("todo.done.txt");
+    let archived: Vec<&str> = archive.lines().map(|l| &l[..5]).collect();
+    assert_eq!(archived, ["[x] B", "[x] D"]);
+
+    ok(sandbox.run(&["done", "3"]));
+    assert_eq!(ok(sandbox.run(&["rm", "--all-done"])), "Removed 1 task(s).\n");
+    assert_eq!(ok(sandbox.run(&["list"])), "No tasks found.\n");
+}
+
+#[test]
+fn test_start_stop_time() {
+    let sandbox = Sandbox::new("time");
+    ok(sandbox.run(&["add", "Write report +work"]));
+
+    assert_eq!(ok(sandbox.run(&["start", "1"])), "Started: Write report +work\n");
+    assert!(sandbox.read("todo.txt").contains("## time\n"));
+    assert!(ok(sandbox.run(&["stop"])).starts_with("Stopped: Write report +work (0h 00m)"));
+    assert!(fail(sandbox.run(&["stop"])).contains("No task is being tracked"));
+
+    let report = ok(sandbox.run(&["time"]));
+    assert!(report.contains("By task:"));
+    assert!(report.contains("+work"));
+}
+
+#[test]
+fn test_stats() {
+    let sandbox = Sandbox::new("stats");
+    ok(sandbox.run(&["add", "A +home"]));
+    ok(sandbox.run(&["add", "B"]));
+    ok(sandbox.run(&["done", "1"]));
+
+    let stats = ok(sandbox.run(&["stats"]));
+    assert!(stats.contains("Tasks: 2 (pending 1, done 1"), "{}", stats);
+    assert!(stats.contains("+home"), "{}", stats);
+}
+
+#[test]
+fn test_export_import_round_trip() {
+    let sandbox = Sandbox::new("export");
+    ok(sandbox.run(&["add", "Parent"]));
+    ok(sandbox.run(&["add", "--parent", "1", "Child"]));
+
+    let json = ok(sandbox.run(&["export", "--format", "json"]));
+    fs::write(sandbox.path("tasks.json"), &json).unwrap();
+    assert_eq!(ok(sandbox.run(&["import", "tasks.json"])), "Imported 2 task(s).\n");
+    assert_eq!(sandbox.outline(), ["[ ] Parent", "  [ ] Child", "[ ] Parent", "  [ ] Child"]);
+
+    let markdown = ok(sandbox.run(&["export"]));
+    assert!(markdown.contains("- [ ] Parent"), "{}", markdown);
+
+    assert!(fail(sandbox.run(&["import", "missing.csv"])).contains("Failed to read"));
+    assert!(fail(sandbox.run(&["export", "--format", "xml"])).contains("xml"));
+}
+
+#[test]
+fn test_json_output() {
+    let sandbox = Sandbox::new("json");
+    ok(sandbox.run(&["add", "A", "--json"]));
+
+    let list = json_parser::parse(&ok(sandbox.run(&["list", "--json"]))).unwrap();
+    assert_eq!(field(&list, "ok"), &JsonValue::Bool(true));
+    assert_eq!(field(&list, "ids"), &JsonValue::Array(vec![JsonValue::Number(1.0)]));
+
+    // 失敗しても標準出力に JSON を出す
+    let output = sandbox.run(&["done", "9", "--json"]);
+    assert_eq!(output.status.code(), Some(1));
+    let error = json_parser::parse(&stdout(&output)).unwrap();
+    assert_eq!(field(&error, "ok"), &JsonValue::Bool(false));
+    assert_eq!(field(&error, "error"), &JsonValue::String("Task 9 not found".to_string()));
+
+    let output = sandbox.run(&["bogus", "--json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(json_parser::parse(&stdout(&output)).is_ok());
+}
+
+#[test]
+fn test_unwritable_file() {
+    let sandbox = Sandbox::new("unwritable");
+    fs::write(sandbox.path("blocker"), "a regular file").unwrap();
+
+    // -f は TODO_FILE より弱いので、環境変数を外して渡す
+    let run = |args: &[&str]| sandbox.command(args).env_remove("TODO_FILE").output().unwrap();
+
+    // 親が通常ファイルなのでディレクトリを作れない (root で実行しても失敗する)
+    let err = fail(run(&["-f", "blocker/todo.txt", "add", "A"]));
+    assert!(err.starts_with("Error: Failed to"), "{}", err);
+
+    // ディレクトリはタスクファイルとして読めない
+    fs::create_dir(sandbox.path("dir")).unwrap();
+    let err = fail(run(&["-f", "dir", "list"]));
+    assert!(err.starts_with("Error: Failed to"), "{}", err);
+}
+
+#[test]
+fn test_default_path_follows_xdg() {
+    let sandbox = Sandbox::new("xdg");
+    let output = sandbox
+        .command(&["add", "A"])
+        .env_remove("TODO_FILE")
+        .env("XDG_DATA_HOME", sandbox.path("data"))
+        .output()
+        .unwrap();
+    ok(output);
+    assert!(sandbox.path("data/todo/todo.txt").is_file());
+
+    let output = sandbox
+        .command(&["add", "B"])
+        .env_remove("TODO_FILE")
+        .env("TODO_LIST", "work")
+        .output()
+        .unwrap();
+    ok(output);
+    assert!(sandbox.path(".local/share/todo/work.txt").is_file());
+}
+
+#[test]
+fn test_localized_messages() {
+    let sandbox = Sandbox::new("locale");
+    let output = sandbox.command(&["add", "A"]).env("LANG", "ja_JP.UTF-8").output().unwrap();
+    assert_eq!(ok(output), "追加: A\n");
+}
+
+#[test]
+fn test_encrypted_file() {
+    let sandbox = Sandbox::new("encrypt");
+    let output = sandbox
+        .command(&["add", "Secret", "--encrypt"])
+        .env("TODO_PASSPHRASE", "pw")
+        .output()
+        .unwrap();
+    ok(output);
+
+    let data = fs::read(sandbox.path("todo.txt")).unwrap();
+    assert!(data.starts_with(b"TODOENC1"));
+
+    // 端末もパスフレーズも無ければ読めない
+    assert!(fail(sandbox.run(&["list"])).contains("TODO_PASSPHRASE"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hooks_run_after_add() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let sandbox = Sandbox::new("hooks");
+    let hooks = sandbox.path(".config/todo/hooks");
+    fs::create_dir_all(&hooks).unwrap();
+    fs::write(hooks.join("task-added"), "#!/bin/sh\ncat > added.json\n").unwrap();
+    fs::set_permissions(hooks.join("task-added"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    ok(sandbox.run(&["add", "Hooked"]));
+    let payload = json_parser::parse(&fs::read_to_string(hooks.join("added.json")).unwrap()).unwrap();
+    assert_eq!(field(&payload, "event"), &JsonValue::String("task-added".to_string()));
+
+    ok(sandbox.run(&["add", "Quiet", "--no-hooks"]));
+    let payload = json_parser::parse(&fs::read_to_string(hooks.join("added.json")).unwrap()).unwrap();
+    let task = field(&payload, "task");
+    assert_eq!(field(task, "description"), &JsonValue::String("Hooked".to_string()));
+}
+
+/// GET には 404、PUT には 200 を返し、PUT の本文を返す 1 回限りのサーバー
+fn stub_server() -> (String, thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let mut body = String::new();
+        for _ in 0..2 {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut buf = vec![0; length];
+            reader.read_exact(&mut buf).unwrap();
+
+            let status = if request_line.starts_with("PUT") {
+                body = String::from_utf8(buf).unwrap();
+                "200 OK"
+            } else {
+                "404 Not Found"
+            };
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        }
+        body
+    });
+
+    (url, handle)
+}
+
+#[test]
+fn test_sync() {
+    let sandbox = Sandbox::new("sync");
+    assert!(fail(sandbox.run(&["sync"])).contains("sync requires --remote"));
+
+    ok(sandbox.run(&["add", "Shared"]));
+    let (url, server) = stub_server();
+    let out = ok(sandbox.run(&["sync", "--remote", &url]));
+    assert!(out.contains("push + 1 Shared"), "{}", out);
+
+    let pushed = json_parser::parse(&server.join().unwrap()).unwrap();
+    let tasks = field(&pushed, "tasks");
+    assert!(matches!(tasks, JsonValue::Array(items) if items.len() == 1));
+    assert!(Path::new(&sandbox.path("todo.sync.json")).is_file());
+
+    // つながらない相手はエラーになる
+    let err = fail(sandbox.run(&["sync", "--remote", "http://127.0.0.1:1"]));
+    assert!(err.starts_with("Error: "), "{}", err);
+}
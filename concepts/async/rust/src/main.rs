@@ -0,0 +1,262 @@
+//! # Rust の async/await (tokio)
+//!
+//! `async fn` は呼んだだけでは何もせず、`Future` (まだ終わっていない計算) を返す。
+//! ランタイム (ここでは tokio) が `.await` のところで待ちの間に別の Future を進めるので、
+//! 1 つのスレッドでも多数の待ちを並行に扱える。
+//!
+//! スレッド版は `concepts/concurrency/rust` を参照。違いは最後の `compare_with_threads` で比べる。
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinSet;
+use tokio::time::{sleep, timeout};
+
+#[tokio::main]
+async fn main() {
+    println!("=== Rust async/await (tokio) ===\n");
+
+    lazy_futures().await;
+    join_macro().await;
+    select_macro().await;
+    spawning_tasks().await;
+    timeouts().await;
+    channels().await;
+    compare_with_threads().await;
+}
+
+/// 時間のかかる処理の代わり (`delay_ms` だけ待ってから返す)
+async fn fetch(id: u32, delay_ms: u64) -> String {
+    sleep(Duration::from_millis(delay_ms)).await;
+    format!("data#{}", id)
+}
+
+/// Future は .await されるまで動かない
+async fn lazy_futures() {
+    println!("--- Future は遅延評価 ---");
+
+    let future = async {
+        println!("  (ここは .await されたときに実行される)");
+        42
+    };
+    println!("  Future を作った (まだ何も起きていない)");
+    let value = future.await;
+    println!("  .await の結果: {}\n", value);
+}
+
+/// join!: 全部を並行に進めて、全部が終わるのを待つ
+async fn join_macro() {
+    println!("--- join! ---");
+
+    let started = Instant::now();
+    let (a, b, c) = tokio::join!(fetch(1, 50), fetch(2, 30), fetch(3, 40));
+    // 順に .await すると 120ms かかるが、並行なので一番遅い 50ms 程度
+    println!("  {}, {}, {} ({:?})", a, b, c, started.elapsed());
+
+    // 失敗するかもしれないものは try_join! (1 つでも Err ならそこで終わる)
+    let result: Result<(u32, u32), String> = tokio::try_join!(async { Ok(1) }, async { Err("failed".to_string()) });
+    println!("  try_join!: {:?}\n", result);
+}
+
+/// 先に終わった方を使う (負けた方の Future は捨てられる)
+async fn race(fast_ms: u64, slow_ms: u64) -> &'static str {
+    tokio::select! {
+        _ = sleep(Duration::from_millis(fast_ms)) => "fast",
+        _ = sleep(Duration::from_millis(slow_ms)) => "slow",
+    }
+}
+
+/// select!: 最初に終わったものだけを使う
+async fn select_macro() {
+    println!("--- select! ---");
+
+    println!("  race(10ms, 100ms) -> {}", race(10, 100).await);
+
+    // ループの中で、メッセージと締め切りのどちらか先に来た方を処理する
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        for i in 0..3 {
+            sleep(Duration::from_millis(10)).await;
+            let _ = tx.send(i).await;
+        }
+        // tx はここで落ちるが、締め切りの方が先
+        sleep(Duration::from_millis(200)).await;
+    });
+    let deadline = sleep(Duration::from_millis(80));
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            Some(message) = rx.recv() => println!("  受信: {}", message),
+            _ = &mut deadline => {
+                println!("  締め切り");
+                break;
+            }
+        }
+    }
+    println!();
+}
+
+/// すべてのタスクの結果を、終わった順に集める
+async fn fetch_all(delays_ms: &[u64]) -> Vec<String> {
+    let mut set = JoinSet::new();
+    for (id, &delay) in delays_ms.iter().enumerate() {
+        set.spawn(fetch(id as u32, delay));
+    }
+    let mut results = Vec::new();
+    while let Some(result) = set.join_next().await {
+        results.push(result.expect("task panicked"));
+    }
+    results
+}
+
+/// tokio::spawn: ランタイムのスレッドでタスクとして動かす
+async fn spawning_tasks() {
+    println!("--- タスクの生成 (tokio::spawn) ---");
+
+    // spawn したタスクは 'static でなければならない (move で所有権を渡す)
+    let name = String::from("worker");
+    let handle = tokio::spawn(async move { format!("{} finished", name) });
+    println!("  JoinHandle: {}", handle.await.unwrap());
+
+    // abort で取り消すと、次の .await の地点で止まって JoinError になる
+    // (タスクの中の panic も同じく JoinError になり、ランタイムは止まらない)
+    let endless = tokio::spawn(sleep(Duration::from_secs(3600)));
+    endless.abort();
+    println!("  abort したタスク: is_cancelled = {}", endless.await.unwrap_err().is_cancelled());
+
+    // JoinSet: 終わった順に受け取る
+    println!("  JoinSet: {:?}\n", fetch_all(&[30, 10, 20]).await);
+}
+
+/// `limit_ms` までに終わらなければエラーにする
+async fn fetch_with_timeout(id: u32, delay_ms: u64, limit_ms: u64) -> Result<String, String> {
+    timeout(Duration::from_millis(limit_ms), fetch(id, delay_ms))
+        .await
+        .map_err(|_| format!("request {} timed out after {}ms", id, limit_ms))
+}
+
+/// timeout: 時間内に終わらなければ Future を捨てる
+async fn timeouts() {
+    println!("--- タイムアウト ---");
+    println!("  {:?}", fetch_with_timeout(1, 10, 50).await);
+    println!("  {:?}\n", fetch_with_timeout(2, 100, 50).await);
+}
+
+/// 仕事を受け取って、結果を oneshot で返すワーカー
+struct Job {
+    input: u64,
+    reply: oneshot::Sender<u64>,
+}
+
+/// mpsc で仕事を受け取るワーカーを立て、`inputs` を 2 乗した結果を順に返す
+async fn square_all(inputs: &[u64]) -> Vec<u64> {
+    let (tx, mut rx) = mpsc::channel::<Job>(4);
+    let worker = tokio::spawn(async move {
+        // 送信側がすべて落ちると None になって終わる
+        while let Some(job) = rx.recv().await {
+            let _ = job.reply.send(job.input * job.input);
+        }
+    });
+
+    let mut results = Vec::new();
+    for &input in inputs {
+        let (reply, response) = oneshot::channel();
+        // 容量 (4) がいっぱいなら空くまで待つ (背圧)
+        tx.send(Job { input, reply }).await.unwrap();
+        results.push(response.await.unwrap());
+    }
+    drop(tx);
+    worker.await.unwrap();
+    results
+}
+
+/// 非同期チャネル: 受信を .await で待つ (スレッドを止めない)
+async fn channels() {
+    println!("--- 非同期チャネル ---");
+
+    // mpsc: 複数の送信者と 1 つの受信者
+    let (tx, mut rx) = mpsc::channel(16);
+    for producer in 0..3 {
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            for i in 0..2 {
+                tx.send(format!("[P{}] {}", producer, i)).await.unwrap();
+                sleep(Duration::from_millis(5)).await;
+            }
+        });
+    }
+    drop(tx);
+    let mut received = Vec::new();
+    while let Some(message) = rx.recv().await {
+        received.push(message);
+    }
+    received.sort();
+    println!("  mpsc: {:?}", received);
+
+    // oneshot: 1 回だけの返事 (リクエスト/レスポンス)
+    println!("  mpsc + oneshot: {:?}\n", square_all(&[1, 2, 3, 4, 5]).await);
+}
+
+/// 大量の待ちをスレッドとタスクで比べる
+///
+/// OS スレッドはそれぞれにスタック (既定で 2MiB の予約) と切り替えのコストがある。
+/// タスクは待っている間は数百バイトの状態として置いておかれるだけなので、何万個でも作れる。
+async fn compare_with_threads() {
+    println!("--- スレッドとの比較 ---");
+    const COUNT: usize = 1_000;
+    const WAIT: Duration = Duration::from_millis(20);
+
+    let started = Instant::now();
+    let handles: Vec<_> = (0..COUNT).map(|_| std::thread::spawn(|| std::thread::sleep(WAIT))).collect();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+    println!("  OS スレッド {} 本が {:?} 待つ: {:?}", COUNT, WAIT, started.elapsed());
+
+    for count in [COUNT, COUNT * 100] {
+        let started = Instant::now();
+        let mut set = JoinSet::new();
+        for _ in 0..count {
+            set.spawn(sleep(WAIT));
+        }
+        while set.join_next().await.is_some() {}
+        println!("  タスク {} 個が {:?} 待つ: {:?}", count, WAIT, started.elapsed());
+    }
+
+    // CPU を使い続ける処理はランタイムのスレッドを塞ぐので、spawn_blocking で別スレッドに逃がす
+    let sum = tokio::task::spawn_blocking(|| (1..=1_000_000u64).sum::<u64>()).await.unwrap();
+    println!("  spawn_blocking: sum = {}", sum);
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // start_paused: 時計を止めておき、全タスクが待ちに入ったら次の時刻まで進める。
+    // 実際には待たないので、時間に頼るテストでも速く、結果も揺れない。
+
+    #[tokio::test(start_paused = true)]
+    async fn test_join_runs_concurrently() {
+        let started = tokio::time::Instant::now();
+        let (a, b) = tokio::join!(fetch(1, 50), fetch(2, 30));
+        assert_eq!((a.as_str(), b.as_str()), ("data#1", "data#2"));
+        assert_eq!(started.elapsed(), Duration::from_millis(50));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_select_and_timeout() {
+        assert_eq!(race(10, 100).await, "fast");
+        assert_eq!(race(100, 10).await, "slow");
+        assert_eq!(fetch_with_timeout(1, 10, 50).await, Ok("data#1".to_string()));
+        assert_eq!(
+            fetch_with_timeout(2, 100, 50).await,
+            Err("request 2 timed out after 50ms".to_string())
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tasks_and_channels() {
+        // 終わった順 (待ち時間の短い順)
+        assert_eq!(fetch_all(&[30, 10, 20]).await, vec!["data#1", "data#2", "data#0"]);
+        assert_eq!(square_all(&[1, 2, 3, 10]).await, vec![1, 4, 9, 100]);
+    }
+}
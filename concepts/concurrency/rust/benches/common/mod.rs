@@ -0,0 +1,15 @@
+//! ベンチマークで共有する計測の道具 (各ベンチマークから `mod common;` で読み込む)
+
+use std::time::{Duration, Instant};
+
+/// `f` を何回か実行し、最も速かった時間を返す
+pub fn measure(mut f: impl FnMut()) -> Duration {
+    (0..5)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
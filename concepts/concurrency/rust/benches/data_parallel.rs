@@ -0,0 +1,56 @@
+//! 逐次・標準ライブラリのスレッド・rayon の比較
+//!
+//! ```text
+//! cargo bench --bench data_parallel                    # 逐次と thread::scope
+//! cargo bench --bench data_parallel --features rayon   # rayon の列も出す
+//! ```
+//!
+//! コアが 1 つしかない環境では並列版は速くならない (スレッドの分だけ遅くなる)。
+
+mod common;
+
+use std::hint::black_box;
+use std::thread;
+use std::time::Duration;
+
+use concurrency::data_parallel::{self, Image};
+
+use common::measure;
+
+fn row(name: &str, times: &[(&str, Duration)]) {
+    print!("{:<20}", name);
+    for (label, time) in times {
+        print!(" {:>8}: {:>9.2?}", label, time);
+    }
+    println!();
+}
+
+fn main() {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    println!("available parallelism: {}", threads);
+
+    for limit in [20_000u64, 200_000] {
+        // rayon の列はフィーチャーがあるときだけ足す
+        #[cfg_attr(not(feature = "rayon"), allow(unused_mut))]
+        let mut times = vec![
+            ("seq", measure(|| _ = black_box(data_parallel::count_primes_seq(black_box(limit))))),
+            ("scoped", measure(|| _ = black_box(data_parallel::count_primes_scoped(black_box(limit), threads)))),
+        ];
+        #[cfg(feature = "rayon")]
+        times.push(("rayon", measure(|| _ = black_box(data_parallel::count_primes_par(black_box(limit))))));
+        row(&format!("primes < {}", limit), &times);
+    }
+
+    for size in [256, 1024] {
+        let image = Image::pattern(size, size);
+        // rayon の列はフィーチャーがあるときだけ足す
+        #[cfg_attr(not(feature = "rayon"), allow(unused_mut))]
+        let mut times = vec![
+            ("seq", measure(|| _ = black_box(image.blur_seq()))),
+            ("scoped", measure(|| _ = black_box(image.blur_scoped(threads)))),
+        ];
+        #[cfg(feature = "rayon")]
+        times.push(("rayon", measure(|| _ = black_box(image.blur_par()))));
+        row(&format!("blur {}x{}", size, size), &times);
+    }
+}
@@ -0,0 +1,369 @@
+//! アトミック操作とメモリ順序
+//!
+//! | 順序 | 保証 | 使いどころ |
+//! |------|------|-----------|
+//! | `Relaxed` | その変数への操作が分割されないことだけ | カウンター、統計 |
+//! | `Release` (書く側) / `Acquire` (読む側) | Release より前の書き込みが、それを Acquire で読んだ側に見える | ロック、データの受け渡し |
+//! | `SeqCst` | 全スレッドが同じ 1 つの順序で全操作を見る | 複数の変数にまたがる判断 ([`store_buffer_litmus`]) |
+//!
+//! loom (全部のインターリーブを試すツール) は使わず、多数のスレッドで繰り返す
+//! ストレステストで確かめている。通っても正しさの証明にはならない点に注意。
+
+use std::cell::UnsafeCell;
+use std::hint;
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::thread;
+
+/// `threads` 個のスレッドで `per_thread` 回ずつ数える
+///
+/// 数えるだけで、ほかのデータの受け渡しに使わないので `Relaxed` でよい。
+/// 最後の読み出しは join の後なので、join が同期してくれる。
+pub fn relaxed_count(threads: usize, per_thread: usize) -> usize {
+    let counter = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                for _ in 0..per_thread {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    counter.load(Ordering::Relaxed)
+}
+
+/// `compare_exchange` のループで最大値を更新する (`fetch_max` と同じことを手で)
+///
+/// 読んだ値から新しい値を計算し、その間にほかのスレッドが書き換えていなければ入れる。
+/// 書き換えられていたら、その値で計算し直す。前の値を返す。
+pub fn update_max(target: &AtomicUsize, value: usize) -> usize {
+    let mut current = target.load(Ordering::Relaxed);
+    loop {
+        if value <= current {
+            return current;
+        }
+        // weak は偽の失敗があり得るが、どうせループするので速い方を使う
+        match target.compare_exchange_weak(current, value, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(previous) => return previous,
+            Err(actual) => current = actual,
+        }
+    }
+}
+
+/// `limit` 未満のときだけ 1 増やす (枠の取り合い)。増やせたら true
+pub fn increment_below(target: &AtomicUsize, limit: usize) -> bool {
+    target
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| (n < limit).then_some(n + 1))
+        .is_ok()
+}
+
+/// 別スレッドで作った値を、フラグ 1 つで受け渡す
+///
+/// 値は `Relaxed` で書き、フラグを `Release` で立てる。フラグを `Acquire` で読んで true なら、
+/// Release より前の書き込み (値) も必ず見える。両方 `Relaxed` にすると、フラグだけ見えて
+/// 値が古いことが (ARM などでは実際に) 起こり得る。
+pub fn handoff(value: usize) -> usize {
+    let data = AtomicUsize::new(0);
+    let ready = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            data.store(value, Ordering::Relaxed);
+            ready.store(true, Ordering::Release);
+        });
+        let reader = s.spawn(|| {
+            while !ready.load(Ordering::Acquire) {
+                hint::spin_loop();
+            }
+            data.load(Ordering::Relaxed)
+        });
+        reader.join().unwrap()
+    })
+}
+
+/// ストアバッファのリトマステスト。両方のスレッドが相手の書き込みを見逃した回数を返す
+///
+/// ```text
+/// スレッド A: x = 1; a = y      スレッド B: y = 1; b = x
+/// ```
+///
+/// `SeqCst` なら全操作に 1 つの順序があるので `a == 0 && b == 0` にはならない。
+/// `Release` / `Acquire` では書き込みがストアバッファに残ったまま読めるので起こり得る
+/// (x86 でも起きる)。`ordering` は書き込みと読み込みの両方に使う (Acquire/Release は
+/// それぞれの操作に合わせて読み替える)。
+pub fn store_buffer_litmus(ordering: Ordering, rounds: usize) -> usize {
+    let (store, load) = match ordering {
+        Ordering::Acquire | Ordering::Release | Ordering::AcqRel => (Ordering::Release, Ordering::Acquire),
+        other => (other, other),
+    };
+    let mut both_zero = 0;
+    for _ in 0..rounds {
+        let (x, y) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        let (a, b) = thread::scope(|s| {
+            let a = s.spawn(|| {
+                x.store(1, store);
+                y.load(load)
+            });
+            let b = s.spawn(|| {
+                y.store(1, store);
+                x.load(load)
+            });
+            (a.join().unwrap(), b.join().unwrap())
+        });
+        if a == 0 && b == 0 {
+            both_zero += 1;
+        }
+    }
+    both_zero
+}
+
+/// スピンロック
+///
+/// 取れるまで `compare_exchange` を繰り返す。取るときは `Acquire`、放すときは `Release`
+/// なので、前の持ち主が中で書いたものは次の持ち主に見える。待つ間 CPU を使い続けるので、
+/// 保持時間がごく短いとき以外は `Mutex` (OS に眠らせてもらう) の方がよい。
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+// SAFETY: 中身には一度に 1 つのスレッドしか触らないので、T を別スレッドに送れれば共有してよい
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        SpinLock {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn lock(&self) -> SpinGuard<'_, T> {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // 書き込みを試さずに読むだけで待つ (キャッシュラインを奪い合わない)
+            while self.locked.load(Ordering::Relaxed) {
+                hint::spin_loop();
+            }
+        }
+        SpinGuard { lock: self }
+    }
+
+    pub fn try_lock(&self) -> Option<SpinGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| SpinGuard { lock: self })
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// ロックを持っている間の中身への参照 (落とすと放す)
+pub struct SpinGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: ガードがある間はこのスレッドだけがロックを持っている
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T> DerefMut for SpinGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: 同上。&mut self なのでガード経由の参照も 1 つだけ
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T> Drop for SpinGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+const UNINIT: u8 = 0;
+const RUNNING: u8 = 1;
+const READY: u8 = 2;
+
+/// 1 度だけ初期化する値 (`std::sync::OnceLock` の簡易版)
+///
+/// 最初に `UNINIT` → `RUNNING` へ書き換えたスレッドだけが初期化し、終わったら
+/// `READY` を `Release` で書く。ほかのスレッドは `READY` を `Acquire` で読むまで待つので、
+/// 初期化した値が必ず見える。初期化中の panic には対応していない (待つ側が回り続ける)。
+pub struct OnceInit<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: 値は READY になってから共有参照でしか触らない。初期化したスレッドと違うスレッドで
+// 落ちることがあるので Send、共有参照を配るので Sync も要る
+unsafe impl<T: Send + Sync> Sync for OnceInit<T> {}
+
+impl<T> Default for OnceInit<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> OnceInit<T> {
+    pub const fn new() -> Self {
+        OnceInit {
+            state: AtomicU8::new(UNINIT),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// 初期化済みなら値
+    pub fn get(&self) -> Option<&T> {
+        // SAFETY: READY を Acquire で見たので、初期化の書き込みは見えている
+        (self.state.load(Ordering::Acquire) == READY).then(|| unsafe { (*self.value.get()).assume_init_ref() })
+    }
+
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        match self
+            .state
+            .compare_exchange(UNINIT, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                // SAFETY: RUNNING にできたのはこのスレッドだけなので、書くのもこのスレッドだけ
+                unsafe { (*self.value.get()).write(init()) };
+                self.state.store(READY, Ordering::Release);
+            }
+            Err(_) => {
+                while self.state.load(Ordering::Acquire) != READY {
+                    hint::spin_loop();
+                    thread::yield_now();
+                }
+            }
+        }
+        self.get().expect("initialized")
+    }
+}
+
+impl<T> Drop for OnceInit<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            // SAFETY: READY なら初期化済み。&mut self なのでほかに参照はない
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_relaxed_count_and_cas_loops() {
+        assert_eq!(relaxed_count(4, 10_000), 40_000);
+
+        let max = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for t in 0..4 {
+                let max = &max;
+                s.spawn(move || (0..1_000).for_each(|i| _ = update_max(max, i * 4 + t)));
+            }
+        });
+        assert_eq!(max.load(Ordering::Relaxed), 3_999);
+        assert_eq!(update_max(&max, 10), 3_999);
+
+        // 枠は 100 個。取り合っても超えない
+        let slots = AtomicUsize::new(0);
+        let taken = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..50 {
+                        if increment_below(&slots, 100) {
+                            taken.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!((slots.into_inner(), taken.into_inner()), (100, 100));
+    }
+
+    #[test]
+    fn test_handoff_and_seqcst_litmus() {
+        for i in 1..200 {
+            assert_eq!(handoff(i), i);
+        }
+        assert_eq!(store_buffer_litmus(Ordering::SeqCst, 200), 0);
+        // Acquire/Release は 0 とは限らないので、回数の範囲だけ確かめる
+        assert!(store_buffer_litmus(Ordering::AcqRel, 50) <= 50);
+    }
+
+    #[test]
+    fn test_spinlock_stress() {
+        let lock = SpinLock::new(0u64);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..5_000 {
+                        // 読んで足して書くのは分割されるが、ロックの中なので失われない
+                        let mut guard = lock.lock();
+                        let current = *guard;
+                        *guard = current + 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.into_inner(), 20_000);
+
+        let lock = SpinLock::new(vec![1]);
+        let guard = lock.lock();
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+        lock.try_lock().unwrap().push(2);
+        assert_eq!(*lock.lock(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_once_init_runs_once() {
+        for _ in 0..50 {
+            let once = OnceInit::new();
+            let calls = AtomicUsize::new(0);
+            let seen: Vec<usize> = thread::scope(|s| {
+                let handles: Vec<_> = (0..4)
+                    .map(|i| {
+                        let (once, calls) = (&once, &calls);
+                        s.spawn(move || {
+                            *once.get_or_init(|| {
+                                calls.fetch_add(1, Ordering::Relaxed);
+                                i
+                            })
+                        })
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            assert_eq!(calls.load(Ordering::Relaxed), 1);
+            assert!(seen.iter().all(|&v| v == seen[0]));
+        }
+
+        // 初期化した値は一緒に落ちる
+        let value = Arc::new(());
+        let once = OnceInit::new();
+        assert!(once.get().is_none());
+        once.get_or_init(|| Arc::clone(&value));
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(once);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
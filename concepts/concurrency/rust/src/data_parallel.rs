@@ -0,0 +1,151 @@
+//! データ並列: 同じ計算を要素ごとに分けて並列に行う
+//!
+//! 同じ処理を 3 通りに書いて比べる (`benches/data_parallel.rs`):
+//!
+//! - `*_seq`: ふつうのイテレーター
+//! - `*_scoped`: 標準ライブラリだけで、`thread::scope` で塊に分ける
+//! - `*_par`: rayon の `par_iter` (`rayon` フィーチャーのときだけ)。作業を盗み合うので、
+//!   塊ごとの重さが違っても (素数判定は大きい数ほど重い) スレッドが遊ばない
+//!
+//! rayon 版はイテレーターの `iter` を `par_iter` に変えるだけで、形がほとんど同じになる。
+
+use std::thread;
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// 試し割りで素数か判定する (わざと重い CPU 処理にしている)
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    (2..).take_while(|d| d * d <= n).all(|d| !n.is_multiple_of(d))
+}
+
+/// `limit` 未満の素数の数
+pub fn count_primes_seq(limit: u64) -> usize {
+    (0..limit).filter(|&n| is_prime(n)).count()
+}
+
+/// スレッドごとに `i, i + threads, i + 2 * threads, ...` を受け持つ
+///
+/// 連続した範囲に分けると、大きい数を持ったスレッドだけが遅くなるので、とびとびに配る。
+pub fn count_primes_scoped(limit: u64, threads: usize) -> usize {
+    let threads = threads.max(1) as u64;
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|start| s.spawn(move || (start..limit).step_by(threads as usize).filter(|&n| is_prime(n)).count()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+#[cfg(feature = "rayon")]
+pub fn count_primes_par(limit: u64) -> usize {
+    (0..limit).into_par_iter().filter(|&n| is_prime(n)).count()
+}
+
+/// 8 ビットのグレースケール画像
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// 位置から決まる模様 (テストとベンチ用)
+    pub fn pattern(width: usize, height: usize) -> Self {
+        let pixels = (0..width * height)
+            .map(|i| ((i % width) * 7 + (i / width) * 13 + (i * i) % 31) as u8)
+            .collect();
+        Image { width, height, pixels }
+    }
+
+    /// 周り 3×3 の平均 (端は画像の中にある分だけ)
+    fn blurred_pixel(&self, x: usize, y: usize) -> u8 {
+        let (mut sum, mut count) = (0u32, 0u32);
+        for ny in y.saturating_sub(1)..=(y + 1).min(self.height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(self.width - 1) {
+                sum += self.pixels[ny * self.width + nx] as u32;
+                count += 1;
+            }
+        }
+        (sum / count) as u8
+    }
+
+    /// 1 行分をぼかして `row` に書く
+    fn blur_row(&self, y: usize, row: &mut [u8]) {
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = self.blurred_pixel(x, y);
+        }
+    }
+
+    pub fn blur_seq(&self) -> Image {
+        let mut pixels = vec![0; self.pixels.len()];
+        for (y, row) in pixels.chunks_mut(self.width.max(1)).enumerate() {
+            self.blur_row(y, row);
+        }
+        Image { pixels, ..*self }
+    }
+
+    /// 行をまとめて `threads` 個の塊にし、重ならない `&mut` を各スレッドに渡す
+    pub fn blur_scoped(&self, threads: usize) -> Image {
+        let mut pixels = vec![0; self.pixels.len()];
+        let rows_per_thread = self.height.div_ceil(threads.max(1)).max(1);
+        thread::scope(|s| {
+            for (i, block) in pixels.chunks_mut(rows_per_thread * self.width.max(1)).enumerate() {
+                s.spawn(move || {
+                    for (j, row) in block.chunks_mut(self.width).enumerate() {
+                        self.blur_row(i * rows_per_thread + j, row);
+                    }
+                });
+            }
+        });
+        Image { pixels, ..*self }
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn blur_par(&self) -> Image {
+        let mut pixels = vec![0; self.pixels.len()];
+        pixels
+            .par_chunks_mut(self.width.max(1))
+            .enumerate()
+            .for_each(|(y, row)| self.blur_row(y, row));
+        Image { pixels, ..*self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_primes() {
+        assert_eq!((0..20).filter(|&n| is_prime(n)).collect::<Vec<_>>(), vec![2, 3, 5, 7, 11, 13, 17, 19]);
+        assert_eq!(count_primes_seq(10_000), 1_229);
+        for threads in [1, 3, 8] {
+            assert_eq!(count_primes_scoped(10_000, threads), 1_229);
+        }
+        assert_eq!(count_primes_scoped(2, 4), 0);
+        #[cfg(feature = "rayon")]
+        assert_eq!(count_primes_par(10_000), 1_229);
+    }
+
+    #[test]
+    fn test_blur() {
+        let flat = Image { width: 3, height: 2, pixels: vec![90; 6] };
+        assert_eq!(flat.blur_seq(), flat);
+
+        let dot = Image { width: 3, height: 3, pixels: vec![0, 0, 0, 0, 90, 0, 0, 0, 0] };
+        assert_eq!(dot.blur_seq().pixels, vec![22, 15, 22, 15, 10, 15, 22, 15, 22]);
+
+        let image = Image::pattern(37, 23);
+        let expected = image.blur_seq();
+        for threads in [1, 2, 5, 64] {
+            assert_eq!(image.blur_scoped(threads), expected);
+        }
+        #[cfg(feature = "rayon")]
+        assert_eq!(image.blur_par(), expected);
+    }
+}
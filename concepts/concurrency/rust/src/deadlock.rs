@@ -0,0 +1,229 @@
+//! 失敗の例: デッドロックと Mutex のポイズニング
+//!
+//! | 失敗 | 原因 | 対策 |
+//! |------|------|------|
+//! | デッドロック | 2 つのロックを逆の順で取る | 取る順番を決める ([`transfer_ordered`])、`try_lock` で引く ([`transfer_try_lock`]) |
+//! | ポイズニング | ロックを持ったまま panic | `PoisonError::into_inner` で中身を取り出す、`clear_poison` |
+//!
+//! Rust の型検査はデータ競合を防ぐが、デッドロックは防がない。検出は
+//! [`with_watchdog`] のように時間切れで気づくしかない (止まったスレッドは殺せない)。
+
+use std::sync::mpsc;
+use std::sync::{Arc, Barrier, Mutex, PoisonError, TryLockError};
+use std::thread;
+use std::time::Duration;
+
+/// 別スレッドで `f` を動かし、`timeout` 以内に終わらなければ `Err` を返す
+///
+/// 止まったスレッドを外から止める方法は無いので、時間切れのときはそのまま残す
+/// (プロセスが終わるまでロックも握られたまま)。検出して報告するための道具。
+pub fn with_watchdog<T, F>(timeout: Duration, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // 受け取る側が時間切れで先に居なくなっていたら送れないが、それでよい
+        let _ = tx.send(f());
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(value) => Ok(value),
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(format!("did not finish within {:?} (deadlock?)", timeout)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err("the worker panicked".to_string()),
+    }
+}
+
+/// わざとデッドロックさせる: A → B と B → A の順でロックを取る 2 つのスレッド
+///
+/// `Barrier` で「両方が 1 つ目を取った」ところまで揃えるので、必ず止まる。
+/// ウォッチドッグの `Err` を返す。
+pub fn lock_order_deadlock(timeout: Duration) -> Result<(), String> {
+    with_watchdog(timeout, || {
+        let a = Arc::new(Mutex::new(0));
+        let b = Arc::new(Mutex::new(0));
+        let barrier = Arc::new(Barrier::new(2));
+
+        let handles: Vec<_> = [(Arc::clone(&a), Arc::clone(&b)), (b, a)]
+            .into_iter()
+            .map(|(first, second)| {
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    let mut first = first.lock().unwrap();
+                    barrier.wait();
+                    // 相手が持っているので、ここで永遠に待つ
+                    let mut second = second.lock().unwrap();
+                    *first += 1;
+                    *second += 1;
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    })
+}
+
+/// 口座 (残高をロックで守る)。`id` はロックを取る順番に使う
+pub struct Account {
+    id: usize,
+    balance: Mutex<i64>,
+}
+
+impl Account {
+    pub fn new(id: usize, balance: i64) -> Self {
+        Account { id, balance: Mutex::new(balance) }
+    }
+
+    pub fn balance(&self) -> i64 {
+        *self.balance.lock().unwrap()
+    }
+}
+
+/// 対策 1: いつも `id` の小さい口座から取る
+///
+/// 全員が同じ順で取れば、「相手が持っているものを待つ」輪ができない。
+pub fn transfer_ordered(from: &Account, to: &Account, amount: i64) {
+    assert_ne!(from.id, to.id, "cannot transfer to the same account");
+    let (first, second) = if from.id < to.id { (from, to) } else { (to, from) };
+    let mut first = first.balance.lock().unwrap();
+    let mut second = second.balance.lock().unwrap();
+    let (from_balance, to_balance) = if from.id < to.id {
+        (&mut *first, &mut *second)
+    } else {
+        (&mut *second, &mut *first)
+    };
+    *from_balance -= amount;
+    *to_balance += amount;
+}
+
+/// 対策 2: 2 つ目は `try_lock` で取り、取れなければ 1 つ目も放してやり直す。やり直した回数を返す
+///
+/// 順番を決められない (ロックの組み合わせが実行時に決まる) ときに使える。
+/// 待ち続けないので止まらないが、両者が譲り合い続けると進まない (ライブロック) ことがあるので
+/// `yield_now` で間をあける。
+pub fn transfer_try_lock(from: &Account, to: &Account, amount: i64) -> usize {
+    let mut retries = 0;
+    loop {
+        let mut from_balance = from.balance.lock().unwrap();
+        match to.balance.try_lock() {
+            Ok(mut to_balance) => {
+                *from_balance -= amount;
+                *to_balance += amount;
+                return retries;
+            }
+            Err(TryLockError::WouldBlock) => {
+                drop(from_balance);
+                retries += 1;
+                thread::yield_now();
+            }
+            Err(TryLockError::Poisoned(e)) => panic!("poisoned: {}", e),
+        }
+    }
+}
+
+/// ロックを持ったまま panic させて、Mutex をポイズニングさせる
+///
+/// 中身は `value` を途中まで書き換えた状態で残る (半端な状態かもしれない、という警告が
+/// ポイズニング)。
+pub fn poison(mutex: &Arc<Mutex<Vec<i32>>>, value: i32) {
+    let mutex = Arc::clone(mutex);
+    let result = thread::spawn(move || {
+        let mut guard = mutex.lock().unwrap();
+        guard.push(value);
+        panic!("panicked while holding the lock");
+    })
+    .join();
+    assert!(result.is_err());
+}
+
+/// ポイズニングされた Mutex から中身を取り出す。されていなければそのまま
+///
+/// `lock()` の `Err` (`PoisonError`) にもガードが入っているので、中身が使えると
+/// 判断したら `into_inner` で取り出せる。`clear_poison` で印を消せば、以後の `lock()` は `Ok` に戻る。
+pub fn lock_recovering(mutex: &Mutex<Vec<i32>>) -> (Vec<i32>, bool) {
+    let (contents, poisoned) = match mutex.lock() {
+        Ok(guard) => (guard.clone(), false),
+        Err(poisoned) => (poisoned.into_inner().clone(), true),
+    };
+    if poisoned {
+        mutex.clear_poison();
+    }
+    (contents, poisoned)
+}
+
+/// `lock().unwrap()` だとポイズニングが他のスレッドに panic として広がる。その様子を返す
+pub fn poison_spreads(mutex: &Arc<Mutex<Vec<i32>>>) -> Result<usize, String> {
+    let mutex = Arc::clone(mutex);
+    thread::spawn(move || mutex.lock().unwrap().len())
+        .join()
+        .map_err(|e| {
+            e.downcast_ref::<String>()
+                .cloned()
+                .unwrap_or_else(|| "unknown panic".to_string())
+        })
+}
+
+/// `PoisonError` を無視して使い続ける書き方 (中身の整合性を自分で保証できるときだけ)
+pub fn lock_ignoring_poison(mutex: &Mutex<Vec<i32>>) -> usize {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_detects_deadlock() {
+        let err = lock_order_deadlock(Duration::from_millis(200)).unwrap_err();
+        assert!(err.contains("deadlock"), "{}", err);
+        assert_eq!(with_watchdog(Duration::from_secs(5), || 1 + 1), Ok(2));
+        assert_eq!(
+            with_watchdog(Duration::from_secs(5), || -> () { panic!("boom") }),
+            Err("the worker panicked".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ordered_and_try_lock_transfers_finish() {
+        // 逆向きの送金を同時にたくさん流しても止まらず、合計も変わらない
+        for transfer in [
+            |a: &Account, b: &Account| {
+                transfer_ordered(a, b, 1);
+            },
+            |a: &Account, b: &Account| {
+                transfer_try_lock(a, b, 1);
+            },
+        ] {
+            let result = with_watchdog(Duration::from_secs(10), move || {
+                let a = Account::new(1, 1_000);
+                let b = Account::new(2, 1_000);
+                thread::scope(|s| {
+                    s.spawn(|| (0..1_000).for_each(|_| transfer(&a, &b)));
+                    s.spawn(|| (0..500).for_each(|_| transfer(&b, &a)));
+                });
+                (a.balance(), b.balance())
+            });
+            assert_eq!(result, Ok((500, 1_500)));
+        }
+    }
+
+    #[test]
+    fn test_poisoned_mutex() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        poison(&mutex, 2);
+        assert!(mutex.is_poisoned());
+
+        // unwrap している側は panic を受け継ぐ
+        let err = poison_spreads(&mutex).unwrap_err();
+        assert!(err.contains("PoisonError"), "{}", err);
+        // 無視する書き方なら読める
+        assert_eq!(lock_ignoring_poison(&mutex), 2);
+
+        // 取り出して印を消せば元どおり
+        assert_eq!(lock_recovering(&mutex), (vec![1, 2], true));
+        assert!(!mutex.is_poisoned());
+        assert_eq!(lock_recovering(&mutex), (vec![1, 2], false));
+        assert_eq!(poison_spreads(&mutex), Ok(2));
+    }
+}
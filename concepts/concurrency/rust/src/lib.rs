@@ -0,0 +1,9 @@
+//! # Rust の並行処理 (ライブラリ部分)
+//!
+//! `main.rs` のデモから使う。
+
+pub mod atomics;
+pub mod data_parallel;
+pub mod deadlock;
+pub mod pool;
+pub mod scoped;
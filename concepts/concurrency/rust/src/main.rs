@@ -4,6 +4,12 @@
 //! 型システムと所有権により、データ競合をコンパイル時に防ぐ。
 
 use std::sync::{Arc, Mutex, mpsc};
+
+use concurrency::atomics::{self, OnceInit, SpinLock};
+use concurrency::data_parallel::{self, Image};
+use concurrency::deadlock::{self, Account};
+use concurrency::pool::ThreadPool;
+use concurrency::scoped;
 use std::thread;
 use std::time::Duration;
 
@@ -12,13 +18,15 @@ fn main() {
 
     basic_threads();
     move_closure();
+    scoped_threads();
     shared_state();
+    atomics_and_ordering();
+    data_parallelism();
+    thread_pool();
+    deadlock_and_poisoning();
     message_passing();
 
-    // async は別途 tokio ランタイムが必要
-    println!("--- async/await (tokio) ---");
-    println!("  tokio::main で非同期処理を実行");
-    println!("  (この例では同期版のみ)\n");
+    // async/await (tokio) の例は concepts/async/rust にある
 }
 
 /// 基本的なスレッド
@@ -61,6 +69,39 @@ fn move_closure() {
     println!();
 }
 
+/// スコープ付きスレッド (thread::scope)
+fn scoped_threads() {
+    println!("--- スコープ付きスレッド ---");
+
+    // move_closure と違い、v を借りたまま使える (スコープの終わりで必ず join される)
+    let v = vec![1, 2, 3];
+    let mut total = 0;
+    thread::scope(|s| {
+        s.spawn(|| println!("  スレッド内でベクタを借用: {:?}", v));
+        s.spawn(|| total = v.iter().sum::<i32>());
+    });
+    println!("  スコープの後も v を使える: {:?} (合計 {})", v, total);
+
+    // fork-join: スライスを分けて足す。Arc 版と結果は同じ
+    let data: Vec<u64> = (1..=1_000_000).collect();
+    println!("  parallel_sum (借用): {}", scoped::parallel_sum(&data, 4));
+    println!("  parallel_sum_with_arc (Arc + move): {}", scoped::parallel_sum_with_arc(Arc::new(data), 4));
+
+    // 重ならない &mut の塊を別々のスレッドへ
+    let mut squares: Vec<u32> = (1..=8).collect();
+    scoped::parallel_for_each_mut(&mut squares, 3, |x| *x *= *x);
+    println!("  parallel_for_each_mut: {:?}", squares);
+
+    let mut values = [5, 3, 9, 1, 7, 2, 8, 6, 4];
+    scoped::parallel_merge_sort(&mut values, 2);
+    println!("  parallel_merge_sort: {:?}", values);
+
+    let counts = scoped::word_counts(&["to be or", "not to be"], 2);
+    println!("  word_counts: to = {}, be = {}, or = {}", counts["to"], counts["be"], counts["or"]);
+    println!("  parse_all: {:?} / {:?}", scoped::parse_all(&["1", "2"]), scoped::parse_all(&["1", "x"]));
+    println!();
+}
+
 /// 共有状態 (Mutex + Arc)
 fn shared_state() {
     println!("--- 共有状態 (Mutex + Arc) ---");
@@ -88,6 +129,155 @@ fn shared_state() {
     println!();
 }
 
+/// アトミック操作とメモリ順序
+fn atomics_and_ordering() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    println!("--- アトミック操作とメモリ順序 ---");
+
+    println!("  Relaxed カウンター (4 スレッド × 1000): {}", atomics::relaxed_count(4, 1_000));
+
+    let max = AtomicUsize::new(3);
+    atomics::update_max(&max, 10);
+    atomics::update_max(&max, 7);
+    println!("  compare_exchange ループで最大値: {}", max.load(Ordering::Relaxed));
+
+    // Release で書いたフラグを Acquire で読めば、その前に書いた値も見える
+    println!("  Release/Acquire で受け渡し: {}", atomics::handoff(42));
+
+    // SeqCst なら両方 0 にはならない (Acquire/Release では起こり得る)
+    let rounds = 200;
+    println!(
+        "  ストアバッファ (両方 0 の回数 / {}): AcqRel = {}, SeqCst = {}",
+        rounds,
+        atomics::store_buffer_litmus(Ordering::AcqRel, rounds),
+        atomics::store_buffer_litmus(Ordering::SeqCst, rounds)
+    );
+
+    let lock = SpinLock::new(Vec::new());
+    thread::scope(|s| {
+        for i in 0..3 {
+            let lock = &lock;
+            s.spawn(move || lock.lock().push(i));
+        }
+    });
+    let mut pushed = lock.into_inner();
+    pushed.sort();
+    println!("  SpinLock で守った Vec: {:?}", pushed);
+
+    static CONFIG: OnceInit<String> = OnceInit::new();
+    thread::scope(|s| {
+        for i in 0..3 {
+            s.spawn(move || CONFIG.get_or_init(|| format!("initialized by thread {}", i)));
+        }
+    });
+    println!("  OnceInit (最初の 1 回だけ): {}", CONFIG.get().unwrap());
+    println!();
+}
+
+/// データ並列 (rayon 版は `--features rayon` のときだけ)
+fn data_parallelism() {
+    use std::time::Instant;
+
+    println!("--- データ並列 ---");
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let limit = 100_000;
+
+    let start = Instant::now();
+    let seq = data_parallel::count_primes_seq(limit);
+    println!("  素数 < {} (逐次): {} 個, {:?}", limit, seq, start.elapsed());
+
+    let start = Instant::now();
+    let scoped = data_parallel::count_primes_scoped(limit, threads);
+    println!("  素数 < {} (scope, {} スレッド): {} 個, {:?}", limit, threads, scoped, start.elapsed());
+
+    #[cfg(feature = "rayon")]
+    {
+        let start = Instant::now();
+        let par = data_parallel::count_primes_par(limit);
+        println!("  素数 < {} (rayon par_iter): {} 個, {:?}", limit, par, start.elapsed());
+    }
+
+    let image = Image::pattern(512, 512);
+    let blurred = image.blur_seq();
+    println!("  ぼかし 512x512: 逐次と scope 版が一致 = {}", blurred == image.blur_scoped(threads));
+    #[cfg(feature = "rayon")]
+    println!("  ぼかし 512x512: 逐次と rayon 版が一致 = {}", blurred == image.blur_par());
+    #[cfg(not(feature = "rayon"))]
+    println!("  (rayon 版は `cargo run --features rayon` で)");
+    println!();
+}
+
+/// ワークスティーリングのスレッドプール
+fn thread_pool() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    println!("--- スレッドプール (ワークスティーリング) ---");
+
+    let pool = ThreadPool::new(4);
+    let total = Arc::new(AtomicUsize::new(0));
+    for i in 1..=100 {
+        let total = Arc::clone(&total);
+        pool.execute(move || {
+            total.fetch_add(i, Ordering::Relaxed);
+        });
+    }
+    pool.join().unwrap();
+    println!("  100 個のジョブで 1..=100 の和: {}", total.load(Ordering::Relaxed));
+
+    // 1 つのジョブが子ジョブを自分の deque に積んで待つ。暇なワーカーが盗んで進める
+    let spawner = pool.spawner();
+    let (tx, rx) = mpsc::channel();
+    pool.execute(move || {
+        for i in 0..8 {
+            let tx = tx.clone();
+            spawner.execute(move || tx.send(i * i).unwrap());
+        }
+    });
+    pool.join().unwrap();
+    let mut squares: Vec<i32> = rx.iter().collect();
+    squares.sort();
+    println!("  子ジョブの結果: {:?} (盗んだ回数: {})", squares, pool.steals());
+
+    pool.execute(|| panic!("job failed"));
+    println!("  panic したジョブ: {:?}", pool.join());
+    println!();
+}
+
+/// 失敗の例: デッドロックとポイズニング
+fn deadlock_and_poisoning() {
+    println!("--- デッドロックとポイズニング ---");
+
+    // 逆順でロックを取る 2 スレッドは止まる (止まったスレッドはプロセス終了まで残る)
+    let result = deadlock::lock_order_deadlock(Duration::from_millis(200));
+    println!("  A→B と B→A: {:?}", result);
+
+    let a = Account::new(1, 100);
+    let b = Account::new(2, 100);
+    thread::scope(|s| {
+        s.spawn(|| (0..100).for_each(|_| deadlock::transfer_ordered(&a, &b, 1)));
+        s.spawn(|| (0..50).for_each(|_| deadlock::transfer_ordered(&b, &a, 1)));
+    });
+    println!("  順番を決めて取る: a = {}, b = {}", a.balance(), b.balance());
+
+    let retries = thread::scope(|s| {
+        let forward = s.spawn(|| (0..100).map(|_| deadlock::transfer_try_lock(&a, &b, 1)).sum::<usize>());
+        let backward = s.spawn(|| (0..100).map(|_| deadlock::transfer_try_lock(&b, &a, 1)).sum::<usize>());
+        forward.join().unwrap() + backward.join().unwrap()
+    });
+    println!("  try_lock で引く: a = {}, b = {} (やり直し {} 回)", a.balance(), b.balance(), retries);
+
+    // ロックを持ったまま panic すると、以後の lock() は Err になる
+    let log = Arc::new(Mutex::new(vec![1]));
+    deadlock::poison(&log, 2);
+    println!("  ポイズニング: is_poisoned = {}", log.is_poisoned());
+    println!("  lock().unwrap() する側: {:?}", deadlock::poison_spreads(&log).map_err(|_| "panic"));
+    println!("  into_inner で取り出す: {:?}", deadlock::lock_recovering(&log));
+    println!("  clear_poison の後: {:?}", deadlock::poison_spreads(&log));
+    println!();
+}
+
 /// メッセージパッシング (チャネル)
 fn message_passing() {
     println!("--- メッセージパッシング (チャネル) ---");
@@ -120,36 +310,3 @@ fn message_passing() {
 
     println!();
 }
-
-// ============================================================
-// 以下は async/await の例 (tokio が必要)
-// ============================================================
-
-/*
-#[tokio::main]
-async fn async_example() {
-    println!("--- async/await ---");
-
-    // 非同期関数
-    async fn fetch_data(id: u32) -> String {
-        // 非同期的に待機
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        format!("Data for {}", id)
-    }
-
-    // 並列実行
-    let (result1, result2) = tokio::join!(
-        fetch_data(1),
-        fetch_data(2)
-    );
-
-    println!("  result1: {}", result1);
-    println!("  result2: {}", result2);
-
-    // select! で最初に完了したものを取得
-    tokio::select! {
-        val = fetch_data(3) => println!("  first: {}", val),
-        val = fetch_data(4) => println!("  first: {}", val),
-    }
-}
-*/
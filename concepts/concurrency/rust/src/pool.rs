@@ -0,0 +1,319 @@
+//! ワークスティーリングのスレッドプール (crossbeam なし、`Mutex` と `Condvar` だけ)
+//!
+//! ```text
+//!            execute (外から: 順番に配る / ワーカーの中から: 自分の deque へ)
+//!               │
+//!   ┌───────────┼───────────┐
+//!   ▼           ▼           ▼
+//! [deque 0]  [deque 1]  [deque 2]     各ワーカーは自分の deque の後ろから取り (LIFO)、
+//!   ▲ │                    ▲          空なら他人の deque の前から盗む (FIFO)
+//!   │ └──── steal ─────────┘
+//! ```
+//!
+//! 自分の分を後ろから取るのは、直前に積んだ (キャッシュに残っている) 仕事から片付けるため。
+//! 盗むときに前から取るのは、古い仕事ほど大きな塊であることが多く、持ち主と取り合いにならないため。
+//!
+//! deque ごとに `Mutex` を持つので、本物 (crossbeam-deque や rayon) のロックフリーな
+//! deque より遅い。仕組みを見るための実装。
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// プールを見分ける番号 (ワーカーのスレッドがどのプールのものかを覚えておく)
+static NEXT_POOL_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// このスレッドがワーカーなら (プールの番号, ワーカーの番号)
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// 数を数えるための状態 (ジョブそのものは deque にある)
+#[derive(Default)]
+struct State {
+    /// deque にあって、まだどのワーカーも取る予約をしていないジョブの数
+    queued: usize,
+    /// 投入されてまだ終わっていないジョブの数 (実行中を含む)
+    pending: usize,
+    /// `join` が前回返ってから panic したジョブの数
+    panicked: usize,
+    shutdown: bool,
+}
+
+struct Shared {
+    id: usize,
+    deques: Vec<Mutex<VecDeque<Job>>>,
+    state: Mutex<State>,
+    /// ジョブが積まれた / 終了の合図
+    work_available: Condvar,
+    /// `pending` が 0 になった
+    all_done: Condvar,
+    /// 外から投入するときの配り先
+    next: AtomicUsize,
+    steals: AtomicUsize,
+}
+
+impl Shared {
+    fn push(&self, job: Job) {
+        let index = match WORKER.get() {
+            Some((pool, index)) if pool == self.id => index,
+            _ => self.next.fetch_add(1, Ordering::Relaxed) % self.deques.len(),
+        };
+        // 先に deque に入れてから数を増やす (数えた分は必ずどこかの deque にある)
+        self.deques[index].lock().unwrap().push_back(job);
+        let mut state = self.state.lock().unwrap();
+        state.queued += 1;
+        state.pending += 1;
+        drop(state);
+        self.work_available.notify_one();
+    }
+
+    /// 予約したジョブを探す: 自分の deque の後ろ → 他人の deque の前
+    fn find_job(&self, me: usize) -> Option<Job> {
+        if let Some(job) = self.deques[me].lock().unwrap().pop_back() {
+            return Some(job);
+        }
+        let n = self.deques.len();
+        (1..n).find_map(|offset| {
+            let job = self.deques[(me + offset) % n].lock().unwrap().pop_front()?;
+            self.steals.fetch_add(1, Ordering::Relaxed);
+            Some(job)
+        })
+    }
+
+    fn worker_loop(&self, me: usize) {
+        WORKER.set(Some((self.id, me)));
+        loop {
+            // 1 つ取る権利を予約してから探す。眠るかどうかは数だけで決めるので、
+            // 「探した直後に積まれて起こしてもらえない」ことがない
+            {
+                let mut state = self.state.lock().unwrap();
+                while state.queued == 0 && !state.shutdown {
+                    state = self.work_available.wait(state).unwrap();
+                }
+                if state.queued == 0 {
+                    // shutdown で、残りも無い
+                    return;
+                }
+                state.queued -= 1;
+            }
+
+            // 予約した数だけジョブは deque にあるが、他のワーカーと同時に探すと
+            // 見逃すことがあるので、見つかるまで探し直す
+            let job = loop {
+                match self.find_job(me) {
+                    Some(job) => break job,
+                    None => thread::yield_now(),
+                }
+            };
+
+            // panic してもワーカーは止めない (数は `join` で知らせる)
+            let result = panic::catch_unwind(AssertUnwindSafe(job));
+
+            let mut state = self.state.lock().unwrap();
+            state.pending -= 1;
+            if result.is_err() {
+                state.panicked += 1;
+            }
+            if state.pending == 0 {
+                self.all_done.notify_all();
+            }
+        }
+    }
+}
+
+/// ワークスティーリングのスレッドプール
+///
+/// 落とすと、積まれているジョブを全部実行してからワーカーを終わらせる。
+pub struct ThreadPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// `size` 個のワーカーを起動する (`size` は 1 以上)
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "a thread pool needs at least one worker");
+        let shared = Arc::new(Shared {
+            id: NEXT_POOL_ID.fetch_add(1, Ordering::Relaxed),
+            deques: (0..size).map(|_| Mutex::new(VecDeque::new())).collect(),
+            state: Mutex::new(State::default()),
+            work_available: Condvar::new(),
+            all_done: Condvar::new(),
+            next: AtomicUsize::new(0),
+            steals: AtomicUsize::new(0),
+        });
+        let workers = (0..size)
+            .map(|me| {
+                let shared = Arc::clone(&shared);
+                thread::Builder::new()
+                    .name(format!("pool-worker-{}", me))
+                    .spawn(move || shared.worker_loop(me))
+                    .expect("failed to spawn a worker thread")
+            })
+            .collect();
+        ThreadPool { shared, workers }
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// ジョブを積む。ワーカーの中から呼べばそのワーカーの deque に、外からなら順番に配る
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.shared.push(Box::new(job));
+    }
+
+    /// ジョブの中から子ジョブを積むための取っ手
+    pub fn spawner(&self) -> Spawner {
+        Spawner { shared: Arc::clone(&self.shared) }
+    }
+
+    /// 積んだジョブ (そこから積まれた子ジョブも) が全部終わるまで待つ
+    ///
+    /// 前回から panic したジョブがあれば、その数を `Err` で返す。
+    pub fn join(&self) -> Result<(), String> {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.pending > 0 {
+            state = self.shared.all_done.wait(state).unwrap();
+        }
+        match std::mem::take(&mut state.panicked) {
+            0 => Ok(()),
+            n => Err(format!("{} job(s) panicked", n)),
+        }
+    }
+
+    /// 他のワーカーの deque から盗んだ回数
+    pub fn steals(&self) -> usize {
+        self.shared.steals.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.work_available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// [`ThreadPool::spawner`] の戻り値。ジョブへ move して使う
+#[derive(Clone)]
+pub struct Spawner {
+    shared: Arc<Shared>,
+}
+
+impl Spawner {
+    /// [`ThreadPool::execute`] と同じ
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.shared.push(Box::new(job));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_execute_and_join() {
+        let pool = ThreadPool::new(4);
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..1_000 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        pool.join().unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 1_000);
+
+        // join の後も使える
+        let counter2 = Arc::clone(&counter);
+        pool.execute(move || {
+            counter2.fetch_add(1, Ordering::Relaxed);
+        });
+        pool.join().unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 1_001);
+    }
+
+    #[test]
+    fn test_nested_jobs_are_joined() {
+        // 木の形に子ジョブを積む: 親が終わっても子が残っていれば join は待つ
+        fn spawn_tree(spawner: Spawner, depth: u32, counter: Arc<AtomicUsize>) {
+            counter.fetch_add(1, Ordering::Relaxed);
+            if depth == 0 {
+                return;
+            }
+            for _ in 0..2 {
+                let (s, c) = (spawner.clone(), Arc::clone(&counter));
+                spawner.execute(move || spawn_tree(s, depth - 1, c));
+            }
+        }
+
+        let pool = ThreadPool::new(3);
+        let counter = Arc::new(AtomicUsize::new(0));
+        let (spawner, c) = (pool.spawner(), Arc::clone(&counter));
+        pool.execute(move || spawn_tree(spawner, 8, c));
+        pool.join().unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), (1 << 9) - 1);
+    }
+
+    #[test]
+    fn test_idle_worker_steals() {
+        // 親ジョブが自分の deque に子を積んでから、子が全部終わるまで居座る。
+        // 盗まれなければ子は動けず、recv_timeout で失敗する
+        let pool = ThreadPool::new(2);
+        let spawner = pool.spawner();
+        let (done_tx, done_rx) = mpsc::channel();
+        pool.execute(move || {
+            let (tx, rx) = mpsc::channel();
+            for i in 0..10 {
+                let tx = tx.clone();
+                spawner.execute(move || tx.send(i).unwrap());
+            }
+            let received = (0..10).filter_map(|_| rx.recv_timeout(Duration::from_secs(5)).ok()).count();
+            done_tx.send(received).unwrap();
+        });
+        assert_eq!(done_rx.recv().unwrap(), 10);
+        pool.join().unwrap();
+        // 子は全部盗まれている。外から積んだ親ジョブ自体も盗まれることがある
+        assert!(pool.steals() >= 10, "steals: {}", pool.steals());
+    }
+
+    #[test]
+    fn test_panics_are_reported_and_drop_drains() {
+        let pool = ThreadPool::new(2);
+        pool.execute(|| panic!("boom"));
+        pool.execute(|| {});
+        assert_eq!(pool.join(), Err("1 job(s) panicked".to_string()));
+        // 数えた分は返したので、次は Ok
+        pool.execute(|| {});
+        assert_eq!(pool.join(), Ok(()));
+
+        // 落とすときは積まれている分を全部実行してから終わる
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..100 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        drop(pool);
+        assert_eq!(counter.load(Ordering::Relaxed), 100);
+    }
+}
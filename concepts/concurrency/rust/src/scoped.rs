@@ -0,0 +1,197 @@
+//! スコープ付きスレッド (`std::thread::scope`)
+//!
+//! `thread::spawn` のスレッドはいつ終わるか分からないので、クロージャは `'static` でなければならず、
+//! データは `move` で渡すか `Arc` で共有する。`thread::scope` の中で作ったスレッドは
+//! スコープを抜ける前に必ず join されるので、スタック上のデータをそのまま借りられる
+//! (構造化された並行性: スレッドの寿命がブロックの形で決まる)。
+//!
+//! | | `thread::spawn` + `move` | `thread::scope` |
+//! |---|---|---|
+//! | データの渡し方 | 所有権を移す / `Arc` で共有 | `&` / `&mut` で借りる |
+//! | join | 呼び出し側の責任 (忘れても動く) | スコープの終わりで必ず |
+//! | 子の panic | `join` の `Err` | `join` の `Err`。join していなければスコープが panic |
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+/// `threads` 個にほぼ等しく分けたときの 1 つの大きさ (少なくとも 1)
+fn chunk_size(len: usize, threads: usize) -> usize {
+    len.div_ceil(threads.max(1)).max(1)
+}
+
+/// 分けて足し、結果を合わせる (fork-join)
+///
+/// `data` は呼び出し側のスライスを借りたまま各スレッドに渡す。
+pub fn parallel_sum(data: &[u64], threads: usize) -> u64 {
+    thread::scope(|s| {
+        let handles: Vec<_> = data
+            .chunks(chunk_size(data.len(), threads))
+            .map(|chunk| s.spawn(move || chunk.iter().sum::<u64>()))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+/// 同じことを `thread::spawn` で書く (比較用)
+///
+/// スレッドが `data` より長生きするかもしれないので、借りられない。
+/// `Arc` に入れて各スレッドに複製を持たせ、範囲は添字で渡す。
+pub fn parallel_sum_with_arc(data: Arc<Vec<u64>>, threads: usize) -> u64 {
+    let size = chunk_size(data.len(), threads);
+    let handles: Vec<_> = (0..data.len())
+        .step_by(size)
+        .map(|start| {
+            let data = Arc::clone(&data);
+            thread::spawn(move || data[start..(start + size).min(data.len())].iter().sum::<u64>())
+        })
+        .collect();
+    handles.into_iter().map(|h| h.join().unwrap()).sum()
+}
+
+/// 各要素を書き換える。重ならない `&mut` の塊を別々のスレッドに貸す
+pub fn parallel_for_each_mut<T: Send>(data: &mut [T], threads: usize, f: impl Fn(&mut T) + Sync) {
+    let size = chunk_size(data.len(), threads);
+    let f = &f;
+    thread::scope(|s| {
+        for chunk in data.chunks_mut(size) {
+            s.spawn(move || chunk.iter_mut().for_each(f));
+        }
+    });
+}
+
+/// 単語の出現回数。行を分けて数え、最後に 1 つにまとめる
+pub fn word_counts(lines: &[&str], threads: usize) -> HashMap<String, usize> {
+    thread::scope(|s| {
+        let handles: Vec<_> = lines
+            .chunks(chunk_size(lines.len(), threads))
+            .map(|chunk| {
+                s.spawn(move || {
+                    let mut counts = HashMap::new();
+                    for word in chunk.iter().flat_map(|line| line.split_whitespace()) {
+                        *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+                    }
+                    counts
+                })
+            })
+            .collect();
+
+        let mut total = HashMap::new();
+        for handle in handles {
+            for (word, count) in handle.join().unwrap() {
+                *total.entry(word).or_insert(0) += count;
+            }
+        }
+        total
+    })
+}
+
+/// 再帰的な fork-join のマージソート
+///
+/// 半分に分け、片方を別スレッド、もう片方をこのスレッドでソートする。
+/// `depth` 段まで分ける (2^depth 個のスレッド)。それより下は標準のソート。
+pub fn parallel_merge_sort<T: Ord + Copy + Send>(data: &mut [T], depth: u32) {
+    if depth == 0 || data.len() < 2 {
+        data.sort();
+        return;
+    }
+    let mid = data.len() / 2;
+    let (left, right) = data.split_at_mut(mid);
+    thread::scope(|s| {
+        s.spawn(|| parallel_merge_sort(left, depth - 1));
+        parallel_merge_sort(right, depth - 1);
+    });
+
+    let mut merged = Vec::with_capacity(data.len());
+    let (mut i, mut j) = (0, mid);
+    while i < mid && j < data.len() {
+        if data[j] < data[i] {
+            merged.push(data[j]);
+            j += 1;
+        } else {
+            merged.push(data[i]);
+            i += 1;
+        }
+    }
+    merged.extend_from_slice(&data[i..mid]);
+    merged.extend_from_slice(&data[j..]);
+    data.copy_from_slice(&merged);
+}
+
+/// すべてを並行してパースし、1 つでも失敗すれば最初の (入力順で) エラーを返す
+///
+/// 子スレッドの結果はスコープの中で全部受け取るので、失敗しても置き去りのスレッドは残らない。
+/// 子が panic したときも `join` の `Err` としてここで受け止める。
+pub fn parse_all(inputs: &[&str]) -> Result<Vec<i64>, String> {
+    thread::scope(|s| {
+        let handles: Vec<_> = inputs
+            .iter()
+            .map(|input| s.spawn(move || input.trim().parse::<i64>().map_err(|e| format!("{:?}: {}", input, e))))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().map_err(|_| "worker panicked".to_string())?)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parallel_sum_matches_arc_version() {
+        let data: Vec<u64> = (1..=10_000).collect();
+        for threads in [1, 3, 8, 64] {
+            assert_eq!(parallel_sum(&data, threads), 50_005_000);
+            assert_eq!(parallel_sum_with_arc(Arc::new(data.clone()), threads), 50_005_000);
+        }
+        // 要素よりスレッドが多くてもよい
+        assert_eq!(parallel_sum(&[1, 2, 3], 10), 6);
+        assert_eq!(parallel_sum(&[], 4), 0);
+        assert_eq!(parallel_sum_with_arc(Arc::new(Vec::new()), 4), 0);
+        // 借りただけなので data はまだ使える
+        assert_eq!(data.len(), 10_000);
+    }
+
+    #[test]
+    fn test_for_each_mut_and_word_counts() {
+        let mut values: Vec<u32> = (0..100).collect();
+        parallel_for_each_mut(&mut values, 4, |v| *v *= 2);
+        assert!(values.iter().enumerate().all(|(i, &v)| v == i as u32 * 2));
+
+        let lines = ["the quick fox", "The lazy dog", "", "the end"];
+        let counts = word_counts(&lines, 3);
+        assert_eq!(counts["the"], 3);
+        assert_eq!(counts["fox"], 1);
+        assert_eq!(counts.len(), 6);
+    }
+
+    #[test]
+    fn test_parallel_merge_sort() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut data: Vec<u64> = (0..5_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % 1_000
+            })
+            .collect();
+        let mut expected = data.clone();
+        expected.sort();
+        parallel_merge_sort(&mut data, 3);
+        assert_eq!(data, expected);
+
+        let mut tiny = [1];
+        parallel_merge_sort(&mut tiny, 4);
+        assert_eq!(tiny, [1]);
+    }
+
+    #[test]
+    fn test_parse_all() {
+        assert_eq!(parse_all(&["1", " 2 ", "-3"]), Ok(vec![1, 2, -3]));
+        assert_eq!(parse_all(&["1", "x", "y"]), Err("\"x\": invalid digit found in string".to_string()));
+        assert_eq!(parse_all(&[]), Ok(vec![]));
+    }
+}
@@ -0,0 +1,36 @@
+//! ベンチマークで共有する計測の道具 (各ベンチマークから `mod common;` で読み込む)
+
+// ベンチマークごとに使う関数が違う
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+/// `f` を何回か実行し、最も速かった時間を返す
+pub fn measure(f: impl FnMut()) -> Duration {
+    measure_runs(5, f)
+}
+
+/// `f` を `runs` 回実行し、最も速かった時間を返す
+pub fn measure_runs(runs: usize, mut f: impl FnMut()) -> Duration {
+    (0..runs)
+        .map(|_| {
+            let started = Instant::now();
+            f();
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+/// 準備してから `f` を何回か実行し、最も速かった時間を返す (準備の時間は含めない)
+pub fn measure_with<T>(mut setup: impl FnMut() -> T, mut f: impl FnMut(T)) -> Duration {
+    (0..5)
+        .map(|_| {
+            let input = setup();
+            let started = Instant::now();
+            f(input);
+            started.elapsed()
+        })
+        .min()
+        .unwrap()
+}
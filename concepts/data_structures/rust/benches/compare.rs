@@ -0,0 +1,188 @@
+//! 自作のデータ構造と標準ライブラリの比較
+//!
+//! 要素数を変えながら、入れる・引く・消す・辿るの時間を測り、1 件ずつ
+//! `group, structure, op, n, nanos` をタブ区切りで出力する。
+//! 表にするには `bench_report` に渡す:
+//!
+//! ```text
+//! cargo bench --bench compare | cargo run --release --bin bench_report
+//! ```
+//!
+//! キーは 0..n を混ぜた順に入れる (整列した順だと `Bst` が片寄って O(n²) になる)。
+
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+use std::hint::black_box;
+use std::time::Duration;
+
+use data_structures::{AvlTree, BTree, Bst, MyHashMap, SkipListMap};
+use linked_list::arena::ArenaList;
+use linked_list::LinkedList;
+
+use common::{measure, measure_with};
+
+fn report(group: &str, structure: &str, op: &str, n: usize, time: Duration) {
+    println!("{}\t{}\t{}\t{}\t{}", group, structure, op, n, time.as_nanos());
+}
+
+/// 0..n を混ぜた順 (xorshift で Fisher-Yates)
+fn shuffled(n: usize) -> Vec<u64> {
+    let mut keys: Vec<u64> = (0..n as u64).collect();
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    for i in (1..n).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        keys.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    keys
+}
+
+/// 比べるマップの共通の操作
+trait Map {
+    const NAME: &'static str;
+    fn empty() -> Self;
+    fn insert(&mut self, key: u64, value: u64);
+    fn get(&self, key: &u64) -> Option<&u64>;
+    fn remove(&mut self, key: &u64) -> Option<u64>;
+    fn sum(&self) -> u64;
+}
+
+macro_rules! impl_map {
+    ($type:ty, $name:expr, $new:expr) => {
+        impl Map for $type {
+            const NAME: &'static str = $name;
+
+            fn empty() -> Self {
+                $new
+            }
+
+            fn insert(&mut self, key: u64, value: u64) {
+                <$type>::insert(self, key, value);
+            }
+
+            fn get(&self, key: &u64) -> Option<&u64> {
+                <$type>::get(self, key)
+            }
+
+            fn remove(&mut self, key: &u64) -> Option<u64> {
+                <$type>::remove(self, key)
+            }
+
+            fn sum(&self) -> u64 {
+                self.iter().map(|(_, value)| value).sum()
+            }
+        }
+    };
+}
+
+impl_map!(HashMap<u64, u64>, "std::HashMap", HashMap::new());
+impl_map!(MyHashMap<u64, u64>, "MyHashMap", MyHashMap::new());
+impl_map!(BTreeMap<u64, u64>, "std::BTreeMap", BTreeMap::new());
+impl_map!(Bst<u64, u64>, "Bst", Bst::new());
+impl_map!(AvlTree<u64, u64>, "AvlTree", AvlTree::new());
+impl_map!(BTree<u64, u64>, "BTree", BTree::new());
+impl_map!(SkipListMap<u64, u64>, "SkipListMap", SkipListMap::new());
+
+fn bench_map<M: Map>(group: &str, keys: &[u64]) {
+    let n = keys.len();
+    let build = || {
+        let mut map = M::empty();
+        keys.iter().for_each(|&key| map.insert(key, key));
+        map
+    };
+    let insert = measure(|| {
+        black_box(build());
+    });
+    let map = build();
+    let get = measure(|| {
+        keys.iter().for_each(|key| {
+            black_box(map.get(black_box(key)));
+        })
+    });
+    let iter = measure(|| {
+        black_box(map.sum());
+    });
+    let remove = measure_with(build, |mut map| {
+        keys.iter().for_each(|key| {
+            black_box(map.remove(key));
+        })
+    });
+    for (op, time) in [("insert", insert), ("get", get), ("remove", remove), ("iter", iter)] {
+        report(group, M::NAME, op, n, time);
+    }
+}
+
+/// 比べるリストの共通の操作
+trait List {
+    const NAME: &'static str;
+    fn empty() -> Self;
+    fn push_back(&mut self, value: u64);
+    fn pop_front(&mut self) -> Option<u64>;
+    fn sum(&self) -> u64;
+}
+
+macro_rules! impl_list {
+    ($type:ty, $name:expr) => {
+        impl List for $type {
+            const NAME: &'static str = $name;
+
+            fn empty() -> Self {
+                <$type>::new()
+            }
+
+            fn push_back(&mut self, value: u64) {
+                <$type>::push_back(self, value);
+            }
+
+            fn pop_front(&mut self) -> Option<u64> {
+                <$type>::pop_front(self)
+            }
+
+            fn sum(&self) -> u64 {
+                self.iter().sum()
+            }
+        }
+    };
+}
+
+impl_list!(std::collections::LinkedList<u64>, "std::LinkedList");
+impl_list!(LinkedList<u64>, "LinkedList");
+impl_list!(ArenaList<u64>, "ArenaList");
+
+fn bench_list<L: List>(n: usize) {
+    let build = || {
+        let mut list = L::empty();
+        (0..n as u64).for_each(|value| list.push_back(value));
+        list
+    };
+    let push = measure(|| {
+        black_box(build());
+    });
+    let list = build();
+    let iter = measure(|| {
+        black_box(list.sum());
+    });
+    let pop = measure_with(build, |mut list| while black_box(list.pop_front()).is_some() {});
+    for (op, time) in [("push", push), ("pop_front", pop), ("iter", iter)] {
+        report("list", L::NAME, op, n, time);
+    }
+}
+
+fn main() {
+    println!("group\tstructure\top\tn\tnanos");
+    for n in [1_000, 10_000, 100_000] {
+        let keys = shuffled(n);
+        bench_map::<HashMap<u64, u64>>("hash", &keys);
+        bench_map::<MyHashMap<u64, u64>>("hash", &keys);
+        bench_map::<BTreeMap<u64, u64>>("ordered", &keys);
+        bench_map::<Bst<u64, u64>>("ordered", &keys);
+        bench_map::<AvlTree<u64, u64>>("ordered", &keys);
+        bench_map::<BTree<u64, u64>>("ordered", &keys);
+        bench_map::<SkipListMap<u64, u64>>("ordered", &keys);
+        bench_list::<std::collections::LinkedList<u64>>(n);
+        bench_list::<LinkedList<u64>>(n);
+        bench_list::<ArenaList<u64>>(n);
+    }
+}
@@ -0,0 +1,133 @@
+//! `MyHashMap` と `std::collections::HashMap` の比較
+//!
+//! ハッシュ関数は 3 つ:
+//!
+//! - SipHash (`RandomState`): 標準の既定。鍵がプロセスごとに変わるので衝突を狙えない
+//! - FNV-1a: 速いが鍵が無い。出力が読めるので、衝突するキーを作れてしまう
+//! - そのまま (`Identity`): キーの値をハッシュ値にする。衝突の極端な例
+//!
+//! 「狙った衝突」は下位ビットが全部 0 のキー (`i << 32`)。位置は下位ビットで決まるので、
+//! `Identity` では全部が同じ位置に落ち、線形探索が 1 件ごとに O(n) になる。
+//! SipHash なら同じキーでも散らばる。
+//!
+//! ```text
+//! cargo bench --bench hash_map
+//! ```
+
+mod common;
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::hint::black_box;
+use std::time::Duration;
+
+use data_structures::hash_map::MyHashMap;
+
+use common::measure;
+
+/// FNV-1a (64 ビット)
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// キーの値をそのままハッシュ値にする
+#[derive(Default)]
+struct Identity(u64);
+
+impl Hasher for Identity {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    // u64 以外のキーはバイト列を畳み込む (ベンチでは使わない)
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.rotate_left(8) ^ b as u64;
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = n;
+    }
+}
+
+/// `keys` を全部入れて全部引く時間
+fn mine<S: BuildHasher + Default>(keys: &[u64]) -> Duration {
+    measure(|| {
+        let mut map = MyHashMap::with_hasher(S::default());
+        for &key in keys {
+            map.insert(black_box(key), key);
+        }
+        for key in keys {
+            black_box(map.get(key));
+        }
+    })
+}
+
+fn std<S: BuildHasher + Default>(keys: &[u64]) -> Duration {
+    measure(|| {
+        let mut map = HashMap::with_hasher(S::default());
+        for &key in keys {
+            map.insert(black_box(key), key);
+        }
+        for key in keys {
+            black_box(map.get(key));
+        }
+    })
+}
+
+fn main() {
+    type Sip = RandomState;
+    type Fnv1a = BuildHasherDefault<Fnv>;
+    type Raw = BuildHasherDefault<Identity>;
+
+    println!("{:>8} {:>10} {:>12} {:>12} {:>12} {:>12} {:>12}", "n", "keys", "mine/sip", "mine/fnv", "mine/raw", "std/sip", "std/raw");
+    for n in [1_000u64, 10_000, 100_000] {
+        let sequential: Vec<u64> = (0..n).collect();
+        println!(
+            "{:>8} {:>10} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?}",
+            n,
+            "0..n",
+            mine::<Sip>(&sequential),
+            mine::<Fnv1a>(&sequential),
+            mine::<Raw>(&sequential),
+            std::<Sip>(&sequential),
+            std::<Raw>(&sequential),
+        );
+
+        // 衝突させると n² になるので、大きい n は SipHash だけ測る
+        let colliding: Vec<u64> = (0..n).map(|i| i << 32).collect();
+        let slow = |f: fn(&[u64]) -> Duration| match n <= 10_000 {
+            true => format!("{:.2?}", f(&colliding)),
+            false => "-".to_string(),
+        };
+        println!(
+            "{:>8} {:>10} {:>12.2?} {:>12.2?} {:>12} {:>12.2?} {:>12}",
+            "",
+            "i << 32",
+            mine::<Sip>(&colliding),
+            mine::<Fnv1a>(&colliding),
+            slow(mine::<Raw>),
+            std::<Sip>(&colliding),
+            slow(std::<Raw>),
+        );
+    }
+}
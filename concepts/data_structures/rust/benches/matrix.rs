@@ -0,0 +1,41 @@
+//! 行列の掛け算: (i, j, k) 順とブロック化の比較
+//!
+//! (i, j, k) 順は右の行列を列方向に読むので、n が大きくなってキャッシュに
+//! 収まらなくなると急に遅くなる。ブロック化は区画の大きさを変えて測る。
+//!
+//! ```text
+//! cargo bench --bench matrix
+//! ```
+
+mod common;
+
+use std::hint::black_box;
+
+use data_structures::matrix::Matrix;
+
+use common::measure_runs;
+
+fn main() {
+    let blocks = [8, 32, 128];
+    print!("{:>6} {:>12}", "n", "naive");
+    for block in blocks {
+        print!(" {:>12}", format!("block {}", block));
+    }
+    println!();
+
+    for n in [64, 256, 512] {
+        let a = Matrix::from_fn(n, n, |i, j| ((i * 31 + j * 17) % 101) as f64);
+        let b = Matrix::from_fn(n, n, |i, j| ((i * 13 + j * 7) % 97) as f64);
+        let naive = measure_runs(3, || {
+            black_box(a.multiply(&b).unwrap());
+        });
+        print!("{:>6} {:>12.2?}", n, naive);
+        for block in blocks {
+            let blocked = measure_runs(3, || {
+                black_box(a.multiply_blocked(&b, block).unwrap());
+            });
+            print!(" {:>12.2?}", blocked);
+        }
+        println!();
+    }
+}
@@ -0,0 +1,100 @@
+//! 小さな整数 ID の集合: `SparseSet` / `BitSet` / `HashSet` の比較
+//!
+//! 容量 1,000,000 のうち `n` 個の ID を入れて、入れる・引く・辿る・全部消すを測る。
+//! `SparseSet` と `BitSet` はハッシュを計算しない分速い。辿るのと全消去は、
+//! `SparseSet` が要素数に、`BitSet` が容量に比例するので、疎なほど差が開く。
+//!
+//! ```text
+//! cargo bench --bench sparse_set
+//! ```
+
+mod common;
+
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use data_structures::bit_set::BitSet;
+use data_structures::sparse_set::SparseSet;
+
+use common::measure;
+
+const CAPACITY: usize = 1_000_000;
+
+/// `f` を 1 回だけ実行した時間
+fn once(f: impl FnOnce()) -> Duration {
+    let started = Instant::now();
+    f();
+    started.elapsed()
+}
+
+/// 容量の中に散らばった ID
+fn ids(n: usize) -> Vec<usize> {
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    (0..n)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % CAPACITY as u64) as usize
+        })
+        .collect()
+}
+
+fn main() {
+    println!("{:>8} {:>10} {:>12} {:>12} {:>12}", "n", "", "SparseSet", "BitSet", "HashSet");
+    for n in [1_000, 10_000, 100_000] {
+        let ids = ids(n);
+
+        let mut sparse = SparseSet::new(CAPACITY);
+        let mut bits = BitSet::new(CAPACITY);
+        let mut hash = HashSet::new();
+        let insert = [
+            measure(|| {
+                sparse.clear();
+                ids.iter().for_each(|&id| {
+                    sparse.insert(black_box(id));
+                });
+            }),
+            measure(|| {
+                bits = BitSet::new(CAPACITY);
+                ids.iter().for_each(|&id| {
+                    bits.set(black_box(id));
+                });
+            }),
+            measure(|| {
+                hash.clear();
+                ids.iter().for_each(|&id| {
+                    hash.insert(black_box(id));
+                });
+            }),
+        ];
+        let contains = [
+            measure(|| ids.iter().for_each(|&id| assert!(sparse.contains(black_box(id))))),
+            measure(|| ids.iter().for_each(|&id| assert!(bits.test(black_box(id))))),
+            measure(|| ids.iter().for_each(|&id| assert!(hash.contains(&black_box(id))))),
+        ];
+        let iterate = [
+            measure(|| {
+                black_box(sparse.iter().sum::<usize>());
+            }),
+            measure(|| {
+                black_box(bits.iter().sum::<usize>());
+            }),
+            measure(|| {
+                black_box(hash.iter().sum::<usize>());
+            }),
+        ];
+        // 2 回目からは空を消すことになるので 1 回だけ測る。BitSet は作り直す時間
+        let clear = [
+            once(|| sparse.clear()),
+            once(|| bits = BitSet::new(CAPACITY)),
+            once(|| hash.clear()),
+        ];
+
+        for (name, times) in [("insert", insert), ("contains", contains), ("iter", iterate), ("clear", clear)] {
+            let label = if name == "insert" { n.to_string() } else { String::new() };
+            println!("{:>8} {:>10} {:>12.2?} {:>12.2?} {:>12.2?}", label, name, times[0], times[1], times[2]);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! AVL 木 (自己平衡二分探索木)
+//!
+//! 各ノードで左右の部分木の高さの差を 1 以内に保つ。挿入や削除で差が 2 になったら
+//! 回転で組み替えるので、高さはいつも O(log n) (最悪でも約 1.44 log₂ n) に収まる。
+//! ソート済みの順に入れても [`crate::bst::Bst`] のように片側へ伸びない。
+//!
+//! 高さが低いと分かっているので、[`crate::bst`] と違って再帰で書いている。
+
+use std::cmp::Ordering;
+use std::fmt::{Debug, Write};
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    /// このノードを根とする部分木の高さ (葉なら 1)
+    height: usize,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+fn height<K, V>(link: &Link<K, V>) -> usize {
+    link.as_ref().map_or(0, |node| node.height)
+}
+
+impl<K, V> Node<K, V> {
+    fn new(key: K, value: V) -> Box<Self> {
+        Box::new(Node {
+            key,
+            value,
+            height: 1,
+            left: None,
+            right: None,
+        })
+    }
+
+    fn update_height(&mut self) {
+        self.height = 1 + height(&self.left).max(height(&self.right));
+    }
+
+    /// 左が高ければ正、右が高ければ負
+    fn balance(&self) -> isize {
+        height(&self.left) as isize - height(&self.right) as isize
+    }
+}
+
+/// 右回転: 左の子を根に持ち上げる
+///
+/// ```text
+///       y            x
+///      / \          / \
+///     x   C   =>   A   y
+///    / \              / \
+///   A   B            B   C
+/// ```
+fn rotate_right<K, V>(mut y: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    let mut x = y.left.take().expect("rotate_right needs a left child");
+    y.left = x.right.take();
+    y.update_height();
+    x.right = Some(y);
+    x.update_height();
+    x
+}
+
+/// 左回転: 右の子を根に持ち上げる (`rotate_right` の逆)
+fn rotate_left<K, V>(mut x: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    let mut y = x.right.take().expect("rotate_left needs a right child");
+    x.right = y.left.take();
+    x.update_height();
+    y.left = Some(x);
+    y.update_height();
+    y
+}
+
+/// 高さの差が 2 になったノードを回転で直す
+///
+/// 高い側の子が逆向きに傾いていれば (左右 / 右左のケース)、先に子を回転させてから
+/// 自分を回転させる (二重回転)。
+fn rebalance<K, V>(mut node: Box<Node<K, V>>) -> Box<Node<K, V>> {
+    node.update_height();
+    let balance = node.balance();
+    if balance > 1 {
+        if node.left.as_ref().is_some_and(|left| left.balance() < 0) {
+            node.left = node.left.take().map(rotate_left);
+        }
+        return rotate_right(node);
+    }
+    if balance < -1 {
+        if node.right.as_ref().is_some_and(|right| right.balance() > 0) {
+            node.right = node.right.take().map(rotate_right);
+        }
+        return rotate_left(node);
+    }
+    node
+}
+
+fn insert<K: Ord, V>(link: Link<K, V>, key: K, value: V, old: &mut Option<V>) -> Box<Node<K, V>> {
+    let Some(mut node) = link else {
+        return Node::new(key, value);
+    };
+    match key.cmp(&node.key) {
+        Ordering::Less => node.left = Some(insert(node.left.take(), key, value, old)),
+        Ordering::Greater => node.right = Some(insert(node.right.take(), key, value, old)),
+        Ordering::Equal => {
+            *old = Some(std::mem::replace(&mut node.value, value));
+            return node;
+        }
+    }
+    rebalance(node)
+}
+
+fn remove<K: Ord, V>(link: Link<K, V>, key: &K, removed: &mut Option<V>) -> Link<K, V> {
+    let mut node = link?;
+    match key.cmp(&node.key) {
+        Ordering::Less => node.left = remove(node.left.take(), key, removed),
+        Ordering::Greater => node.right = remove(node.right.take(), key, removed),
+        Ordering::Equal => {
+            let Node { value, left, right, .. } = *node;
+            *removed = Some(value);
+            return match (left, right) {
+                // 子が 2 つなら、右の部分木の最小のノードをこの場所に持ってくる
+                (Some(left), Some(right)) => {
+                    let (rest, mut successor) = take_min(right);
+                    successor.left = Some(left);
+                    successor.right = rest;
+                    Some(rebalance(successor))
+                }
+                (left, right) => left.or(right),
+            };
+        }
+    }
+    Some(rebalance(node))
+}
+
+/// 部分木から最小のノードを外し、(残りの部分木, 外したノード) を返す
+fn take_min<K, V>(mut node: Box<Node<K, V>>) -> (Link<K, V>, Box<Node<K, V>>) {
+    match node.left.take() {
+        None => (node.right.take(), node),
+        Some(left) => {
+            let (rest, min) = take_min(left);
+            node.left = rest;
+            (Some(rebalance(node)), min)
+        }
+    }
+}
+
+/// AVL 木で作るマップ
+pub struct AvlTree<K, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> AvlTree<K, V> {
+    pub fn new() -> Self {
+        AvlTree { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 木の高さ (空なら 0)。各ノードが覚えているので O(1)
+    pub fn height(&self) -> usize {
+        height(&self.root)
+    }
+
+    /// 追加する (同じキーがあれば値を置き換えて古い値を返す)
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let mut old = None;
+        self.root = Some(insert(self.root.take(), key, value, &mut old));
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match key.cmp(&node.key) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+
+    /// 取り除いて値を返す (通り道のノードを根に向かって直していく)
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let mut removed = None;
+        self.root = remove(self.root.take(), key, &mut removed);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// キーの小さい順に辿るイテレータ
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+impl<K: Debug, V> AvlTree<K, V> {
+    /// 木の形を文字列にする (子は左、右の順。片方しか無ければもう片方を `·` で示す)
+    ///
+    /// ```text
+    /// 2
+    /// ├── 1
+    /// └── 3
+    /// ```
+    pub fn shape(&self) -> String {
+        let mut out = String::new();
+        if let Some(root) = &self.root {
+            writeln!(out, "{:?}", root.key).unwrap();
+            write_children(&mut out, root, "");
+        }
+        out
+    }
+}
+
+fn write_children<K: Debug, V>(out: &mut String, node: &Node<K, V>, prefix: &str) {
+    if node.left.is_none() && node.right.is_none() {
+        return;
+    }
+    for (link, last) in [(&node.left, false), (&node.right, true)] {
+        let branch = if last { "└── " } else { "├── " };
+        match link {
+            Some(child) => {
+                writeln!(out, "{}{}{:?}", prefix, branch, child.key).unwrap();
+                let extension = if last { "    " } else { "│   " };
+                write_children(out, child, &format!("{}{}", prefix, extension));
+            }
+            None => writeln!(out, "{}{}·", prefix, branch).unwrap(),
+        }
+    }
+}
+
+impl<K: Ord, V> Default for AvlTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 中間順のイテレータ
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    fn push_left(&mut self, mut node: Option<&'a Node<K, V>>) {
+        while let Some(current) = node {
+            self.stack.push(current);
+            node = current.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bst::Bst;
+
+    /// 並び順・覚えている高さ・高さの差がすべて正しいか確かめ、部分木の高さを返す
+    fn check<K: Ord, V>(link: &Link<K, V>, low: Option<&K>, high: Option<&K>) -> usize {
+        let Some(node) = link else {
+            return 0;
+        };
+        assert!(low.is_none_or(|low| *low < node.key));
+        assert!(high.is_none_or(|high| node.key < *high));
+        let left = check(&node.left, low, Some(&node.key));
+        let right = check(&node.right, Some(&node.key), high);
+        assert!(left.abs_diff(right) <= 1, "unbalanced node");
+        assert_eq!(node.height, 1 + left.max(right));
+        node.height
+    }
+
+    #[test]
+    fn test_sorted_input_stays_balanced() {
+        let mut avl = AvlTree::new();
+        let mut bst = Bst::new();
+        for i in 0..1000 {
+            avl.insert(i, ());
+            bst.insert(i, ());
+        }
+        check(&avl.root, None, None);
+        // 完全二分木なら 10 段。AVL は 1.44 log₂ n を超えない
+        assert!(avl.height() <= 14, "height {}", avl.height());
+        assert_eq!(bst.height(), 1000);
+
+        // 逆順でも同じ
+        let mut avl = AvlTree::new();
+        for i in (0..1000).rev() {
+            avl.insert(i, ());
+        }
+        check(&avl.root, None, None);
+        assert!(avl.height() <= 14);
+        assert_eq!(avl.iter().map(|(k, _)| *k).collect::<Vec<_>>(), (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_double_rotations() {
+        // 左右のケース: 3, 1, 2 -> 2 が根になる
+        let mut tree = AvlTree::new();
+        for key in [3, 1, 2] {
+            tree.insert(key, ());
+        }
+        assert_eq!(tree.shape(), "2\n├── 1\n└── 3\n");
+
+        // 右左のケース
+        let mut tree = AvlTree::new();
+        for key in [1, 3, 2] {
+            tree.insert(key, ());
+        }
+        assert_eq!(tree.shape(), "2\n├── 1\n└── 3\n");
+    }
+
+    #[test]
+    fn test_shape() {
+        let mut tree = AvlTree::new();
+        assert_eq!(tree.shape(), "");
+        for key in 1..=5 {
+            tree.insert(key, ());
+        }
+        assert_eq!(
+            tree.shape(),
+            "2\n├── 1\n└── 4\n    ├── 3\n    └── 5\n"
+        );
+        tree.insert(6, ());
+        tree.remove(&1);
+        assert_eq!(
+            tree.shape(),
+            "4\n├── 2\n│   ├── ·\n│   └── 3\n└── 5\n    ├── ·\n    └── 6\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut tree = AvlTree::new();
+        for key in [50, 20, 80, 10, 30, 70, 90, 25] {
+            assert_eq!(tree.insert(key, key * 10), None);
+        }
+        assert_eq!(tree.insert(30, 0), Some(300));
+        assert_eq!(tree.len(), 8);
+        assert_eq!(tree.get(&25), Some(&250));
+        assert_eq!(tree.get(&26), None);
+
+        // 葉 / 子が 1 つ / 子が 2 つ (根)
+        assert_eq!(tree.remove(&90), Some(900));
+        assert_eq!(tree.remove(&30), Some(0));
+        assert_eq!(tree.remove(&50), Some(500));
+        assert_eq!(tree.remove(&50), None);
+        check(&tree.root, None, None);
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.iter().map(|(k, _)| *k).collect::<Vec<_>>(), vec![10, 20, 25, 70, 80]);
+    }
+
+    #[test]
+    fn test_balanced_after_many_removals() {
+        let mut tree = AvlTree::new();
+        for i in 0..500 {
+            tree.insert(i, i);
+        }
+        // 片側ばかり消しても偏らない
+        for i in 0..400 {
+            assert_eq!(tree.remove(&i), Some(i));
+            if i % 50 == 0 {
+                check(&tree.root, None, None);
+            }
+        }
+        check(&tree.root, None, None);
+        assert_eq!(tree.len(), 100);
+        assert!(tree.height() <= 9, "height {}", tree.height());
+        assert!(tree.iter().map(|(k, _)| *k).eq(400..500));
+        for i in 400..500 {
+            tree.remove(&i);
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+    }
+}
@@ -0,0 +1,175 @@
+//! `benches/compare.rs` の出力を Markdown の表にする
+//!
+//! ```text
+//! cargo bench --bench compare | cargo run --release --bin bench_report
+//! ```
+//!
+//! グループごとに 1 つの表を作り、`std::` で始まる構造を基準にした倍率を添える
+//! (1.00x より大きければ標準ライブラリより遅い)。
+
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::process;
+
+/// 測った 1 件
+#[derive(Debug, PartialEq)]
+struct Record {
+    group: String,
+    structure: String,
+    op: String,
+    n: usize,
+    nanos: u128,
+}
+
+fn parse_line(line: &str) -> Result<Record, String> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [group, structure, op, n, nanos] = fields[..] else {
+        return Err(format!("expected 5 tab-separated fields: {:?}", line));
+    };
+    Ok(Record {
+        group: group.to_string(),
+        structure: structure.to_string(),
+        op: op.to_string(),
+        n: n.parse().map_err(|e| format!("bad n {:?}: {}", n, e))?,
+        nanos: nanos.parse().map_err(|e| format!("bad nanos {:?}: {}", nanos, e))?,
+    })
+}
+
+/// 見出しの行と空行を飛ばして読む
+fn parse(input: &str) -> Result<Vec<Record>, String> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("group\t"))
+        .map(parse_line)
+        .collect()
+}
+
+fn format_nanos(nanos: u128) -> String {
+    match nanos {
+        0..=9_999 => format!("{}ns", nanos),
+        10_000..=9_999_999 => format!("{:.1}µs", nanos as f64 / 1e3),
+        _ => format!("{:.1}ms", nanos as f64 / 1e6),
+    }
+}
+
+fn render(records: &[Record]) -> String {
+    let mut out = String::new();
+    // 出てきた順を保つ
+    let mut groups: Vec<&str> = Vec::new();
+    for record in records {
+        if !groups.contains(&record.group.as_str()) {
+            groups.push(&record.group);
+        }
+    }
+
+    for group in groups {
+        let in_group: Vec<&Record> = records.iter().filter(|r| r.group == group).collect();
+        let mut structures: Vec<&str> = Vec::new();
+        let mut rows: Vec<(&str, usize)> = Vec::new();
+        let mut times = BTreeMap::new();
+        for record in &in_group {
+            if !structures.contains(&record.structure.as_str()) {
+                structures.push(&record.structure);
+            }
+            if !rows.contains(&(record.op.as_str(), record.n)) {
+                rows.push((&record.op, record.n));
+            }
+            times.insert((record.op.as_str(), record.n, record.structure.as_str()), record.nanos);
+        }
+        let baseline = structures.iter().find(|name| name.starts_with("std::")).copied();
+
+        out.push_str(&format!("## {}\n\n| op | n | {} |\n", group, structures.join(" | ")));
+        out.push_str(&format!("|----|---|{}\n", "---|".repeat(structures.len())));
+        // op ごとにまとめ、n の小さい順に並べる
+        rows.sort_by_key(|&(op, n)| (rows_order(&in_group, op), n));
+        for (op, n) in rows {
+            let base = baseline.and_then(|name| times.get(&(op, n, name)));
+            let cells: Vec<String> = structures
+                .iter()
+                .map(|&name| match (times.get(&(op, n, name)), base) {
+                    (None, _) => "-".to_string(),
+                    (Some(&nanos), Some(&base)) if name != baseline.unwrap() && base > 0 => {
+                        format!("{} ({:.2}x)", format_nanos(nanos), nanos as f64 / base as f64)
+                    }
+                    (Some(&nanos), _) => format_nanos(nanos),
+                })
+                .collect();
+            out.push_str(&format!("| {} | {} | {} |\n", op, n, cells.join(" | ")));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// op が最初に出てきた位置
+fn rows_order(records: &[&Record], op: &str) -> usize {
+    records.iter().position(|r| r.op == op).unwrap_or(usize::MAX)
+}
+
+fn main() {
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+    match parse(&input) {
+        Ok(records) if records.is_empty() => {
+            eprintln!("error: no records (pipe the output of `cargo bench --bench compare`)");
+            process::exit(1);
+        }
+        Ok(records) => print!("{}", render(&records)),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "group\tstructure\top\tn\tnanos
+hash\tstd::HashMap\tinsert\t1000\t2000
+hash\tMyHashMap\tinsert\t1000\t3000
+hash\tstd::HashMap\tget\t1000\t1000
+hash\tMyHashMap\tget\t1000\t500
+hash\tstd::HashMap\tinsert\t10000\t20000000
+hash\tMyHashMap\tinsert\t10000\t30000000
+";
+
+    #[test]
+    fn test_parse() {
+        let records = parse(SAMPLE).unwrap();
+        assert_eq!(records.len(), 6);
+        assert_eq!(
+            records[1],
+            Record {
+                group: "hash".to_string(),
+                structure: "MyHashMap".to_string(),
+                op: "insert".to_string(),
+                n: 1000,
+                nanos: 3000,
+            }
+        );
+        assert!(parse("hash\tstd::HashMap\tinsert\t1000").is_err());
+        assert!(parse("hash\tstd::HashMap\tinsert\tmany\t1").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let table = render(&parse(SAMPLE).unwrap());
+        assert_eq!(
+            table,
+            "## hash
+
+| op | n | std::HashMap | MyHashMap |
+|----|---|---|---|
+| insert | 1000 | 2000ns | 3000ns (1.50x) |
+| insert | 10000 | 20.0ms | 30.0ms (1.50x) |
+| get | 1000 | 1000ns | 500ns (0.50x) |
+
+"
+        );
+    }
+}
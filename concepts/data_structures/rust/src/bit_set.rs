@@ -0,0 +1,180 @@
+//! ビット集合
+//!
+//! 0..capacity の整数の集合を 1 要素 1 ビットで持つ。`Vec<bool>` の 1/8 の大きさで、
+//! 和・積・差は 64 要素ずつまとめて 1 命令で計算できる。
+//! `BloomFilter` のビット列や、グラフ探索の訪問済みの印に使っている。
+
+/// 0..capacity の整数の集合
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitSet {
+    words: Vec<u64>,
+    capacity: usize,
+}
+
+impl BitSet {
+    /// 空の集合 (`capacity` 未満の整数を入れられる)
+    pub fn new(capacity: usize) -> Self {
+        BitSet {
+            words: vec![0; capacity.div_ceil(64)],
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// ビットを立てる。もともと立っていなければ true
+    pub fn set(&mut self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        let was_clear = self.words[word] & mask == 0;
+        self.words[word] |= mask;
+        was_clear
+    }
+
+    /// ビットを下ろす。もともと立っていれば true
+    pub fn clear(&mut self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        let was_set = self.words[word] & mask != 0;
+        self.words[word] &= !mask;
+        was_set
+    }
+
+    /// ビットが立っているか
+    pub fn test(&self, index: usize) -> bool {
+        let (word, mask) = self.locate(index);
+        self.words[word] & mask != 0
+    }
+
+    /// 立っているビットの数
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// 和集合 (容量は大きい方)
+    pub fn union(&self, other: &BitSet) -> BitSet {
+        let (mut result, smaller) = match self.capacity >= other.capacity {
+            true => (self.clone(), other),
+            false => (other.clone(), self),
+        };
+        for (word, &bits) in result.words.iter_mut().zip(&smaller.words) {
+            *word |= bits;
+        }
+        result
+    }
+
+    /// 積集合 (容量は `self` と同じ)
+    pub fn intersection(&self, other: &BitSet) -> BitSet {
+        let mut result = BitSet::new(self.capacity);
+        for ((word, &a), &b) in result.words.iter_mut().zip(&self.words).zip(&other.words) {
+            *word = a & b;
+        }
+        result
+    }
+
+    /// 差集合 `self - other` (容量は `self` と同じ)
+    pub fn difference(&self, other: &BitSet) -> BitSet {
+        let mut result = self.clone();
+        for (word, &bits) in result.words.iter_mut().zip(&other.words) {
+            *word &= !bits;
+        }
+        result
+    }
+
+    /// 立っているビットの位置を小さい順に返す
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            words: &self.words,
+            index: 0,
+            current: self.words.first().copied().unwrap_or(0),
+        }
+    }
+
+    /// (語の位置, 語の中のビット)
+    fn locate(&self, index: usize) -> (usize, u64) {
+        assert!(index < self.capacity, "bit {} out of bounds for capacity {}", index, self.capacity);
+        (index / 64, 1 << (index % 64))
+    }
+}
+
+impl std::fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// 立っているビットのイテレータ
+pub struct Iter<'a> {
+    words: &'a [u64],
+    /// 今見ている語の位置
+    index: usize,
+    /// 今見ている語のうち、まだ返していないビット
+    current: u64,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        // 一番下の立っているビットを下ろす
+        self.current &= self.current - 1;
+        Some(self.index * 64 + bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(capacity: usize, indexes: &[usize]) -> BitSet {
+        let mut set = BitSet::new(capacity);
+        for &index in indexes {
+            set.set(index);
+        }
+        set
+    }
+
+    #[test]
+    fn test_set_clear_test() {
+        let mut set = BitSet::new(130);
+        assert_eq!(set.capacity(), 130);
+        assert!(set.set(0));
+        assert!(set.set(64));
+        assert!(set.set(129));
+        assert!(!set.set(64));
+        assert!(set.test(129));
+        assert!(!set.test(128));
+        assert_eq!(set.count_ones(), 3);
+
+        assert!(set.clear(64));
+        assert!(!set.clear(64));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 129]);
+        assert_eq!(format!("{:?}", set), "{0, 129}");
+        assert!(std::panic::catch_unwind(|| BitSet::new(130).test(130)).is_err());
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = bits(100, &[1, 2, 3, 70, 99]);
+        let b = bits(200, &[2, 3, 4, 70, 150]);
+        assert_eq!(a.union(&b).iter().collect::<Vec<_>>(), vec![1, 2, 3, 4, 70, 99, 150]);
+        assert_eq!(a.union(&b).capacity(), 200);
+        assert_eq!(a.intersection(&b), bits(100, &[2, 3, 70]));
+        assert_eq!(a.difference(&b), bits(100, &[1, 99]));
+        assert_eq!(b.difference(&a), bits(200, &[4, 150]));
+    }
+
+    #[test]
+    fn test_iter_matches_test() {
+        let set = bits(1000, &(0..1000).filter(|i| i % 7 == 0 || i % 64 == 63).collect::<Vec<_>>());
+        let expected: Vec<_> = (0..1000).filter(|&i| set.test(i)).collect();
+        assert_eq!(set.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(set.count_ones(), expected.len());
+        assert_eq!(BitSet::new(0).iter().next(), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! Bloom フィルタ
+//!
+//! 要素を k 個のハッシュ値で m ビットのビット列に記録する。`contains` が false なら
+//! 確実に入っていないが、true は「たぶん入っている」(偽陽性がある)。要素そのものは
+//! 持たないので、集合の大きさに比べてずっと小さいメモリで済む。
+//!
+//! n 個入れたときの偽陽性率を p 以下にするには
+//! m = -n ln p / (ln 2)² ビット、k = (m / n) ln 2 個のハッシュがあればよい。
+//! k 個のハッシュは 2 つのハッシュ値 h1, h2 から `h1 + i * h2` で作る (ダブルハッシュ)。
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::bit_set::BitSet;
+
+/// 偽陽性率を指定して作る Bloom フィルタ
+pub struct BloomFilter<T: ?Sized> {
+    bits: BitSet,
+    hashes: usize,
+    _marker: PhantomData<fn(&T)>,
+}
+
+impl<T: Hash + ?Sized> BloomFilter<T> {
+    /// `expected_items` 個入れたときの偽陽性率が `false_positive_rate` になる大きさで作る
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be between 0 and 1"
+        );
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bits = (-n * false_positive_rate.ln() / (ln2 * ln2)).ceil() as usize;
+        let hashes = ((bits as f64 / n) * ln2).round().max(1.0) as usize;
+        BloomFilter {
+            bits: BitSet::new(bits.max(1)),
+            hashes,
+            _marker: PhantomData,
+        }
+    }
+
+    /// ビット列の長さ m
+    pub fn bit_len(&self) -> usize {
+        self.bits.capacity()
+    }
+
+    /// ハッシュ関数の数 k
+    pub fn hash_count(&self) -> usize {
+        self.hashes
+    }
+
+    pub fn insert(&mut self, item: &T) {
+        for index in self.indexes(item) {
+            self.bits.set(index);
+        }
+    }
+
+    /// false なら確実に入っていない。true は偽陽性かもしれない
+    pub fn contains(&self, item: &T) -> bool {
+        self.indexes(item).all(|index| self.bits.test(index))
+    }
+
+    /// 今のビットの埋まり具合から見積もった偽陽性率 ((立っているビットの割合)^k)
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let filled = self.bits.count_ones() as f64 / self.bits.capacity() as f64;
+        filled.powi(self.hashes as i32)
+    }
+
+    /// `item` が使う k 個のビットの位置
+    fn indexes(&self, item: &T) -> impl Iterator<Item = usize> {
+        let h1 = hash_with_seed(item, 0);
+        // h2 が 0 だと k 個が全部同じ位置になるので奇数にする
+        let h2 = hash_with_seed(item, 1) | 1;
+        let len = self.bits.capacity() as u64;
+        (0..self.hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % len) as usize)
+    }
+}
+
+fn hash_with_seed<T: Hash + ?Sized>(item: &T, seed: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    item.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizing() {
+        // n = 1000, p = 1% なら m ≈ 9586 ビット、k ≈ 7
+        let filter: BloomFilter<u32> = BloomFilter::new(1000, 0.01);
+        assert_eq!(filter.bit_len(), 9586);
+        assert_eq!(filter.hash_count(), 7);
+
+        let tiny: BloomFilter<str> = BloomFilter::new(0, 0.5);
+        assert!(tiny.bit_len() >= 1 && tiny.hash_count() >= 1);
+        assert!(std::panic::catch_unwind(|| BloomFilter::<u32>::new(10, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_no_false_negatives() {
+        let mut filter = BloomFilter::new(100, 0.01);
+        let words = ["apple", "banana", "cherry", "date"];
+        for word in words {
+            filter.insert(word);
+        }
+        assert!(words.iter().all(|word| filter.contains(word)));
+        assert!(filter.estimated_false_positive_rate() < 1e-6);
+    }
+
+    #[test]
+    fn test_empirical_false_positive_rate() {
+        let n = 10_000u32;
+        let mut filter = BloomFilter::new(n as usize, 0.01);
+        for i in 0..n {
+            filter.insert(&i);
+        }
+        assert!((0..n).all(|i| filter.contains(&i)));
+
+        // 入れていない 10 万個のうち、入っていると答えた割合
+        let trials = 100_000;
+        let false_positives = (n..n + trials).filter(|i| filter.contains(i)).count();
+        let rate = false_positives as f64 / trials as f64;
+        assert!(rate < 0.015, "false positive rate {}", rate);
+        assert!((filter.estimated_false_positive_rate() - 0.01).abs() < 0.005);
+    }
+}
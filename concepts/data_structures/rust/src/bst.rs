@@ -0,0 +1,299 @@
+//! 二分探索木
+//!
+//! 各ノードの左の部分木には小さいキー、右の部分木には大きいキーだけを置く。
+//! 探索・挿入・削除は木の高さ h に比例し、ランダムな順に入れれば O(log n) だが、
+//! ソート済みの順に入れると片側に伸びて h = n (連結リストと同じ) になる。
+//! 偏った木でもスタックを溢れさせないよう、再帰を使わずにループで辿る。
+
+use std::cmp::Ordering;
+use std::mem;
+
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    left: Link<K, V>,
+    right: Link<K, V>,
+}
+
+/// 二分探索木で作るマップ
+pub struct Bst<K, V> {
+    root: Link<K, V>,
+    len: usize,
+}
+
+impl<K: Ord, V> Bst<K, V> {
+    pub fn new() -> Self {
+        Bst { root: None, len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// `key` のノードか、`key` を置くべき空きリンクを探す
+    fn find_link<'a>(mut link: &'a mut Link<K, V>, key: &K) -> &'a mut Link<K, V> {
+        loop {
+            let ordering = match link.as_deref() {
+                Some(node) => key.cmp(&node.key),
+                None => return link,
+            };
+            if ordering == Ordering::Equal {
+                return link;
+            }
+            let node = link.as_mut().unwrap();
+            link = match ordering {
+                Ordering::Less => &mut node.left,
+                _ => &mut node.right,
+            };
+        }
+    }
+
+    /// 追加する (同じキーがあれば値を置き換えて古い値を返す)
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let link = Self::find_link(&mut self.root, &key);
+        match link {
+            Some(node) => Some(mem::replace(&mut node.value, value)),
+            None => {
+                *link = Some(Box::new(Node {
+                    key,
+                    value,
+                    left: None,
+                    right: None,
+                }));
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut link = &self.root;
+        while let Some(node) = link {
+            link = match key.cmp(&node.key) {
+                Ordering::Less => &node.left,
+                Ordering::Greater => &node.right,
+                Ordering::Equal => return Some(&node.value),
+            };
+        }
+        None
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// 取り除いて値を返す
+    ///
+    /// - 子が無い: そのまま外す
+    /// - 子が 1 つ: 子をその場所へ繰り上げる
+    /// - 子が 2 つ: 右の部分木の最小 (直後のキー) を外し、その中身で置き換える
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let link = Self::find_link(&mut self.root, key);
+        let node = link.as_mut()?;
+        self.len -= 1;
+        match (node.left.is_some(), node.right.is_some()) {
+            (true, true) => {
+                let successor = take_min(&mut node.right);
+                node.key = successor.key;
+                Some(mem::replace(&mut node.value, successor.value))
+            }
+            _ => {
+                let mut node = link.take().unwrap();
+                *link = node.left.take().or(node.right.take());
+                Some(node.value)
+            }
+        }
+    }
+
+    /// 最小のキーと値
+    pub fn min(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(left) = node.left.as_deref() {
+            node = left;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// 最大のキーと値
+    pub fn max(&self) -> Option<(&K, &V)> {
+        let mut node = self.root.as_deref()?;
+        while let Some(right) = node.right.as_deref() {
+            node = right;
+        }
+        Some((&node.key, &node.value))
+    }
+
+    /// 根から一番深い葉までのノード数 (空なら 0)
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut stack: Vec<(&Node<K, V>, usize)> = self.root.as_deref().map(|root| (root, 1)).into_iter().collect();
+        while let Some((node, depth)) = stack.pop() {
+            height = height.max(depth);
+            for child in [&node.left, &node.right].into_iter().flatten() {
+                stack.push((child, depth + 1));
+            }
+        }
+        height
+    }
+
+    /// キーの小さい順 (中間順) に辿るイテレータ
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        iter.push_left(self.root.as_deref());
+        iter
+    }
+}
+
+/// 部分木の最小のノードを外す (その右の子が代わりに入る)
+fn take_min<K, V>(mut link: &mut Link<K, V>) -> Box<Node<K, V>> {
+    while link.as_ref().is_some_and(|node| node.left.is_some()) {
+        link = &mut link.as_mut().unwrap().left;
+    }
+    let mut node = link.take().expect("take_min on an empty subtree");
+    *link = node.right.take();
+    node
+}
+
+impl<K: Ord, V> Default for Bst<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Drop for Bst<K, V> {
+    fn drop(&mut self) {
+        // 偏った木を再帰的に drop するとスタックを使い切るので、子を外しながら積む
+        let mut stack: Vec<Box<Node<K, V>>> = self.root.take().into_iter().collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+}
+
+/// 中間順のイテレータ (まだ返していない祖先をスタックに積んでおく)
+pub struct Iter<'a, K, V> {
+    stack: Vec<&'a Node<K, V>>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    /// `node` から左の子を辿れるだけ積む
+    fn push_left(&mut self, mut node: Option<&'a Node<K, V>>) {
+        while let Some(current) = node {
+            self.stack.push(current);
+            node = current.left.as_deref();
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.push_left(node.right.as_deref());
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(keys: &[i32]) -> Bst<i32, String> {
+        let mut tree = Bst::new();
+        for &key in keys {
+            tree.insert(key, key.to_string());
+        }
+        tree
+    }
+
+    fn keys(tree: &Bst<i32, String>) -> Vec<i32> {
+        tree.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn test_insert_and_get() {
+        let mut tree = tree(&[5, 3, 8, 1, 4]);
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.get(&4).map(String::as_str), Some("4"));
+        assert_eq!(tree.get(&7), None);
+        assert!(tree.contains_key(&8));
+
+        assert_eq!(tree.insert(4, "four".to_string()).as_deref(), Some("4"));
+        assert_eq!(tree.get(&4).map(String::as_str), Some("four"));
+        assert_eq!(tree.len(), 5);
+    }
+
+    #[test]
+    fn test_iter_min_max() {
+        let tree = tree(&[50, 30, 70, 20, 40, 60, 80, 35]);
+        assert_eq!(keys(&tree), vec![20, 30, 35, 40, 50, 60, 70, 80]);
+        assert_eq!(tree.min().map(|(k, _)| *k), Some(20));
+        assert_eq!(tree.max().map(|(k, _)| *k), Some(80));
+
+        let empty: Bst<i32, ()> = Bst::new();
+        assert_eq!((empty.min(), empty.max(), empty.iter().next()), (None, None, None));
+        assert_eq!(empty.height(), 0);
+    }
+
+    #[test]
+    fn test_remove_all_three_cases() {
+        //        50
+        //      /    \
+        //    30      70
+        //   /  \       \
+        //  20  40       80
+        //      /
+        //     35
+        let mut tree = tree(&[50, 30, 70, 20, 40, 80, 35]);
+
+        // 葉
+        assert_eq!(tree.remove(&20).as_deref(), Some("20"));
+        assert_eq!(keys(&tree), vec![30, 35, 40, 50, 70, 80]);
+        // 子が 1 つ (右だけ / 左だけ)
+        assert_eq!(tree.remove(&70).as_deref(), Some("70"));
+        assert_eq!(tree.remove(&40).as_deref(), Some("40"));
+        assert_eq!(keys(&tree), vec![30, 35, 50, 80]);
+        // 子が 2 つ (根)
+        assert_eq!(tree.remove(&50).as_deref(), Some("50"));
+        assert_eq!(keys(&tree), vec![30, 35, 80]);
+        assert_eq!(tree.get(&80).map(String::as_str), Some("80"));
+
+        assert_eq!(tree.remove(&99), None);
+        assert_eq!(tree.len(), 3);
+        for key in [30, 35, 80] {
+            assert!(tree.remove(&key).is_some());
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.iter().next(), None);
+    }
+
+    #[test]
+    fn test_remove_two_children_with_deep_successor() {
+        // 直後のキー (55) が右の子を持っている
+        let mut tree = tree(&[50, 30, 70, 60, 80, 55, 57]);
+        assert_eq!(tree.remove(&50).as_deref(), Some("50"));
+        assert_eq!(keys(&tree), vec![30, 55, 57, 60, 70, 80]);
+        assert_eq!(tree.get(&57).map(String::as_str), Some("57"));
+    }
+
+    #[test]
+    fn test_sorted_input_degrades_to_a_list() {
+        // 挿入のたびに全部辿るので、作るだけで O(n²) かかる
+        let mut tree = Bst::new();
+        for i in 0..10_000 {
+            tree.insert(i, ());
+        }
+        assert_eq!(tree.height(), 10_000);
+        assert_eq!(tree.iter().count(), 10_000);
+        assert_eq!(tree.remove(&0), Some(()));
+        assert_eq!(tree.min().map(|(k, _)| *k), Some(1));
+    }
+}
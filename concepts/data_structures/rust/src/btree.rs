@@ -0,0 +1,444 @@
+//! B 木
+//!
+//! 1 つのノードにキーを最大 2t - 1 個まで並べ、子を最大 2t 本持つ探索木 (t は最小次数)。
+//! 根以外のノードは少なくとも t - 1 個のキーを持ち、葉はすべて同じ深さにある。
+//!
+//! 二分木に比べてずっと低い (t = 100 なら 100 万件でも高さ 3 ほど) ので、
+//! ノード 1 つをディスクのページ 1 枚に対応させるデータベースやファイルシステムでは、
+//! 読むページ数がそのまま木の高さになる。メモリの上でもキャッシュラインを
+//! まとめて使えるので、標準の `BTreeMap` も B 木 (t = 6) になっている。
+//!
+//! 挿入・削除は根から 1 回下りるだけで済むように、下りる前に子を直す:
+//!
+//! - 挿入: 満杯 (2t - 1 個) の子は先に 2 つに分ける
+//! - 削除: t - 1 個しかない子は、兄弟から 1 個借りるか兄弟と併合してから下りる
+
+use std::cmp::Ordering;
+
+/// 標準の `BTreeMap` と同じ最小次数
+const DEFAULT_DEGREE: usize = 6;
+
+struct Node<K, V> {
+    keys: Vec<K>,
+    values: Vec<V>,
+    /// 葉なら空。そうでなければ `keys.len() + 1` 本
+    children: Vec<Node<K, V>>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new() -> Self {
+        Node {
+            keys: Vec::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// 最小次数を選べる B 木のマップ
+pub struct BTree<K, V> {
+    root: Node<K, V>,
+    degree: usize,
+    len: usize,
+}
+
+impl<K: Ord, V> Default for BTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> BTree<K, V> {
+    pub fn new() -> Self {
+        Self::with_degree(DEFAULT_DEGREE)
+    }
+
+    /// 最小次数 `degree` (2 以上) の木を作る。2 なら 2-3-4 木
+    pub fn with_degree(degree: usize) -> Self {
+        assert!(degree >= 2, "B-tree degree must be at least 2");
+        BTree {
+            root: Node::new(),
+            degree,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// 根から葉までのノードの数 (空なら 0)
+    pub fn height(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut height = 1;
+        let mut node = &self.root;
+        while let Some(child) = node.children.first() {
+            height += 1;
+            node = child;
+        }
+        height
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        let mut node = &self.root;
+        loop {
+            match node.keys.binary_search(key) {
+                Ok(i) => return Some(&node.values[i]),
+                Err(_) if node.is_leaf() => return None,
+                Err(i) => node = &node.children[i],
+            }
+        }
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// 入れる。同じキーがあれば値を置き換えて古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if self.root.keys.len() == self.max_keys() {
+            // 根が満杯なら新しい根の下で分ける (木が高くなるのはここだけ)
+            let old_root = std::mem::replace(&mut self.root, Node::new());
+            self.root.children.push(old_root);
+            split_child(&mut self.root, 0, self.degree);
+        }
+        let old = insert_non_full(&mut self.root, key, value, self.degree);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let removed = remove(&mut self.root, key, self.degree);
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        // 根のキーが併合で無くなったら 1 段低くする
+        if self.root.keys.is_empty() && !self.root.is_leaf() {
+            self.root = self.root.children.pop().unwrap();
+        }
+        removed
+    }
+
+    /// キーの順に辿る
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter { stack: Vec::new() };
+        if !self.is_empty() {
+            iter.push_left(&self.root);
+        }
+        iter
+    }
+
+    fn max_keys(&self) -> usize {
+        2 * self.degree - 1
+    }
+}
+
+/// 満杯の `node.children[i]` を真ん中のキーで 2 つに分け、真ん中を `node` に上げる
+fn split_child<K, V>(node: &mut Node<K, V>, i: usize, degree: usize) {
+    let child = &mut node.children[i];
+    let right = Node {
+        keys: child.keys.split_off(degree),
+        values: child.values.split_off(degree),
+        children: match child.is_leaf() {
+            true => Vec::new(),
+            false => child.children.split_off(degree),
+        },
+    };
+    let middle_key = child.keys.pop().unwrap();
+    let middle_value = child.values.pop().unwrap();
+    node.keys.insert(i, middle_key);
+    node.values.insert(i, middle_value);
+    node.children.insert(i + 1, right);
+}
+
+/// 満杯でない `node` の下に入れる
+fn insert_non_full<K: Ord, V>(node: &mut Node<K, V>, key: K, value: V, degree: usize) -> Option<V> {
+    let mut i = match node.keys.binary_search(&key) {
+        Ok(i) => return Some(std::mem::replace(&mut node.values[i], value)),
+        Err(i) => i,
+    };
+    if node.is_leaf() {
+        node.keys.insert(i, key);
+        node.values.insert(i, value);
+        return None;
+    }
+    if node.children[i].keys.len() == 2 * degree - 1 {
+        split_child(node, i, degree);
+        // 上がってきたキーと比べて、どちらに下りるか決め直す
+        match key.cmp(&node.keys[i]) {
+            Ordering::Equal => return Some(std::mem::replace(&mut node.values[i], value)),
+            Ordering::Greater => i += 1,
+            Ordering::Less => {}
+        }
+    }
+    insert_non_full(&mut node.children[i], key, value, degree)
+}
+
+fn remove<K: Ord, V>(node: &mut Node<K, V>, key: &K, degree: usize) -> Option<V> {
+    match node.keys.binary_search(key) {
+        Ok(i) if node.is_leaf() => {
+            node.keys.remove(i);
+            Some(node.values.remove(i))
+        }
+        Ok(i) => {
+            // 内部ノードのキーは、前後の葉から 1 個持ってきて置き換える
+            if node.children[i].keys.len() >= degree {
+                let (key, value) = remove_max(&mut node.children[i], degree);
+                node.keys[i] = key;
+                Some(std::mem::replace(&mut node.values[i], value))
+            } else if node.children[i + 1].keys.len() >= degree {
+                let (key, value) = remove_min(&mut node.children[i + 1], degree);
+                node.keys[i] = key;
+                Some(std::mem::replace(&mut node.values[i], value))
+            } else {
+                // 両隣とも最小なら、キーごと 1 つに併合してから下で消す
+                merge_children(node, i);
+                remove(&mut node.children[i], key, degree)
+            }
+        }
+        Err(_) if node.is_leaf() => None,
+        Err(i) => {
+            let i = fill_child(node, i, degree);
+            remove(&mut node.children[i], key, degree)
+        }
+    }
+}
+
+fn remove_max<K, V>(node: &mut Node<K, V>, degree: usize) -> (K, V) {
+    if node.is_leaf() {
+        return (node.keys.pop().unwrap(), node.values.pop().unwrap());
+    }
+    let i = fill_child(node, node.children.len() - 1, degree);
+    remove_max(&mut node.children[i], degree)
+}
+
+fn remove_min<K, V>(node: &mut Node<K, V>, degree: usize) -> (K, V) {
+    if node.is_leaf() {
+        return (node.keys.remove(0), node.values.remove(0));
+    }
+    let i = fill_child(node, 0, degree);
+    remove_min(&mut node.children[i], degree)
+}
+
+/// `node.children[i]` が t 個以上のキーを持つようにし、下りるべき子の位置を返す
+///
+/// 兄弟に余裕があれば親を通して 1 個回してもらい (回転)、無ければ兄弟と併合する。
+fn fill_child<K, V>(node: &mut Node<K, V>, i: usize, degree: usize) -> usize {
+    if node.children[i].keys.len() >= degree {
+        return i;
+    }
+    if i > 0 && node.children[i - 1].keys.len() >= degree {
+        let (left, right) = node.children.split_at_mut(i);
+        let (left, child) = (&mut left[i - 1], &mut right[0]);
+        let key = std::mem::replace(&mut node.keys[i - 1], left.keys.pop().unwrap());
+        let value = std::mem::replace(&mut node.values[i - 1], left.values.pop().unwrap());
+        child.keys.insert(0, key);
+        child.values.insert(0, value);
+        if let Some(grandchild) = left.children.pop() {
+            child.children.insert(0, grandchild);
+        }
+        i
+    } else if i + 1 < node.children.len() && node.children[i + 1].keys.len() >= degree {
+        let (left, right) = node.children.split_at_mut(i + 1);
+        let (child, right) = (&mut left[i], &mut right[0]);
+        let key = std::mem::replace(&mut node.keys[i], right.keys.remove(0));
+        let value = std::mem::replace(&mut node.values[i], right.values.remove(0));
+        child.keys.push(key);
+        child.values.push(value);
+        if !right.is_leaf() {
+            child.children.push(right.children.remove(0));
+        }
+        i
+    } else if i + 1 < node.children.len() {
+        merge_children(node, i);
+        i
+    } else {
+        merge_children(node, i - 1);
+        i - 1
+    }
+}
+
+/// `node.children[i]`、キー `i`、`node.children[i + 1]` を 1 つのノードにする
+fn merge_children<K, V>(node: &mut Node<K, V>, i: usize) {
+    let right = node.children.remove(i + 1);
+    let key = node.keys.remove(i);
+    let value = node.values.remove(i);
+    let left = &mut node.children[i];
+    left.keys.push(key);
+    left.values.push(value);
+    left.keys.extend(right.keys);
+    left.values.extend(right.values);
+    left.children.extend(right.children);
+}
+
+/// キーの順に辿るイテレータ
+pub struct Iter<'a, K, V> {
+    /// (ノード, 次に返すキーの位置)
+    stack: Vec<(&'a Node<K, V>, usize)>,
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    /// `node` から一番左の葉までを積む
+    fn push_left(&mut self, mut node: &'a Node<K, V>) {
+        loop {
+            self.stack.push((node, 0));
+            match node.children.first() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, i) = self.stack.pop()?;
+            if i < node.keys.len() {
+                self.stack.push((node, i + 1));
+                if let Some(child) = node.children.get(i + 1) {
+                    self.push_left(child);
+                }
+                return Some((&node.keys[i], &node.values[i]));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// キーが整列し、根以外が t - 1 個以上 2t - 1 個以下、葉の深さが揃っていること
+    fn check<K: Ord, V>(tree: &BTree<K, V>) {
+        fn walk<K: Ord, V>(node: &Node<K, V>, degree: usize, is_root: bool, depth: usize, leaf_depth: &mut Option<usize>) {
+            assert!(node.keys.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(node.keys.len(), node.values.len());
+            assert!(node.keys.len() < 2 * degree);
+            if !is_root {
+                assert!(node.keys.len() >= degree - 1);
+            }
+            if node.is_leaf() {
+                assert_eq!(*leaf_depth.get_or_insert(depth), depth);
+                return;
+            }
+            assert_eq!(node.children.len(), node.keys.len() + 1);
+            for (i, child) in node.children.iter().enumerate() {
+                // 子のキーは親の隣り合うキーの間に収まる
+                if i > 0 {
+                    assert!(child.keys.first() > node.keys.get(i - 1));
+                }
+                if i < node.keys.len() {
+                    assert!(child.keys.last() < node.keys.get(i));
+                }
+                walk(child, degree, false, depth + 1, leaf_depth);
+            }
+        }
+        walk(&tree.root, tree.degree, true, 1, &mut None);
+        assert_eq!(tree.iter().count(), tree.len());
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut tree = BTree::with_degree(2);
+        assert!(tree.is_empty());
+        assert_eq!(tree.height(), 0);
+        for (i, key) in [50, 20, 80, 10, 30, 60, 90, 25, 35, 5].into_iter().enumerate() {
+            assert_eq!(tree.insert(key, i), None);
+            check(&tree);
+        }
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.insert(30, 100), Some(4));
+        assert_eq!(tree.get(&30), Some(&100));
+        assert!(!tree.contains_key(&31));
+
+        let keys: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, [5, 10, 20, 25, 30, 35, 50, 60, 80, 90]);
+
+        for key in [50, 5, 30, 90, 20] {
+            assert!(tree.remove(&key).is_some());
+            check(&tree);
+        }
+        assert_eq!(tree.remove(&50), None);
+        let keys: Vec<_> = tree.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, [10, 25, 35, 60, 80]);
+    }
+
+    #[test]
+    fn test_wide_nodes_keep_tree_shallow() {
+        let mut narrow = BTree::with_degree(2);
+        let mut wide = BTree::with_degree(64);
+        for i in 0..100_000 {
+            narrow.insert(i, ());
+            wide.insert(i, ());
+        }
+        assert_eq!(wide.degree(), 64);
+        // 2-3-4 木は log2(n) 程度、t = 64 なら 3 段で収まる
+        assert!(narrow.height() >= 9, "height {}", narrow.height());
+        assert!(wide.height() <= 3, "height {}", wide.height());
+        check(&narrow);
+        check(&wide);
+
+        for i in 0..100_000 {
+            wide.remove(&i);
+        }
+        assert!(wide.is_empty());
+        assert_eq!(wide.height(), 0);
+    }
+
+    #[test]
+    fn test_matches_btreemap_model() {
+        for degree in [2, 3, 6] {
+            let mut state = 0xda94_2042_e4dd_58b5u64;
+            let mut random = move |bound: u64| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state % bound
+            };
+
+            let mut tree = BTree::with_degree(degree);
+            let mut model = BTreeMap::new();
+            for step in 0..10_000 {
+                let key = random(400);
+                match random(3) {
+                    0 => assert_eq!(tree.insert(key, step), model.insert(key, step)),
+                    1 => assert_eq!(tree.remove(&key), model.remove(&key)),
+                    _ => assert_eq!(tree.get(&key), model.get(&key)),
+                }
+                assert_eq!(tree.len(), model.len());
+                if step % 500 == 0 {
+                    check(&tree);
+                    assert!(tree.iter().eq(model.iter()));
+                }
+            }
+            check(&tree);
+            assert!(tree.iter().eq(model.iter()));
+        }
+    }
+
+    #[test]
+    fn test_degree_must_be_at_least_two() {
+        assert_eq!(BTree::<i32, ()>::new().degree(), DEFAULT_DEGREE);
+        assert!(std::panic::catch_unwind(|| BTree::<i32, ()>::with_degree(1)).is_err());
+    }
+}
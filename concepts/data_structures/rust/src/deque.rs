@@ -0,0 +1,316 @@
+//! 両端キュー (リングバッファ)
+//!
+//! `VecDeque` と同じ作り。確保した配列を輪のように使い、先頭の位置 `head` と
+//! 要素数 `len` だけを持つ。`i` 番目の要素は `(head + i) % capacity` にあるので、
+//! 先頭への追加も `head` を 1 つ戻すだけで O(1) になる。
+//!
+//! 満杯になったら倍の配列に移す (償却 O(1))。要素が配列の端をまたいでいる場合があるので、
+//! 移すときに先頭から順に並べ直す。
+//!
+//! 配列は `MaybeUninit<T>` で持ち、使っていない場所は未初期化のままにする。
+//! [`head`, `head + len`) の外を読まないことがこの型の不変条件で、`unsafe` は
+//! すべてそれに頼っている (`cargo +nightly miri test deque` で確かめられる)。
+
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::{Index, IndexMut};
+
+/// リングバッファで作る両端キュー
+pub struct MyDeque<T> {
+    buf: Box<[MaybeUninit<T>]>,
+    /// 先頭の要素の位置
+    head: usize,
+    len: usize,
+}
+
+impl<T> Default for MyDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MyDeque<T> {
+    /// 確保しないで作る
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        MyDeque {
+            buf: uninit_buffer(capacity),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn push_back(&mut self, value: T) {
+        self.grow_if_full();
+        let slot = self.physical(self.len);
+        self.buf[slot].write(value);
+        self.len += 1;
+    }
+
+    pub fn push_front(&mut self, value: T) {
+        self.grow_if_full();
+        self.head = (self.head + self.capacity() - 1) % self.capacity();
+        self.buf[self.head].write(value);
+        self.len += 1;
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        // SAFETY: 空でないので head には初期化済みの要素がある。読んだ後は範囲から外す
+        let value = unsafe { self.buf[self.head].assume_init_read() };
+        self.head = (self.head + 1) % self.capacity();
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        let slot = self.physical(self.len);
+        // SAFETY: 元の最後の要素なので初期化済み。len を減らしたので二度は読まない
+        Some(unsafe { self.buf[slot].assume_init_read() })
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.len.checked_sub(1)?)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None;
+        }
+        // SAFETY: index < len なので初期化済みの範囲
+        Some(unsafe { self.buf[self.physical(index)].assume_init_ref() })
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.len {
+            return None;
+        }
+        let slot = self.physical(index);
+        // SAFETY: index < len なので初期化済みの範囲
+        Some(unsafe { self.buf[slot].assume_init_mut() })
+    }
+
+    /// 先頭から順に
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        let (front, back) = self.as_slices();
+        front.iter().chain(back)
+    }
+
+    /// 要素を配列の並びのまま 2 つに分けて返す (端をまたいでいなければ後ろは空)
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let front_len = self.len.min(self.capacity() - self.head);
+        let front = &self.buf[self.head..self.head + front_len];
+        let back = &self.buf[..self.len - front_len];
+        // SAFETY: どちらも [head, head + len) の中で初期化済み。MaybeUninit<T> と T は同じ配置
+        unsafe { (assume_init_slice(front), assume_init_slice(back)) }
+    }
+
+    /// `i` 番目の要素の配列の中の位置
+    fn physical(&self, index: usize) -> usize {
+        (self.head + index) % self.capacity()
+    }
+
+    /// 満杯なら倍の配列に、先頭から順に並べ直して移す
+    fn grow_if_full(&mut self) {
+        if self.len < self.capacity() {
+            return;
+        }
+        let mut buf = uninit_buffer((self.capacity() * 2).max(4));
+        for (i, slot) in buf.iter_mut().take(self.len).enumerate() {
+            let old = self.physical(i);
+            // SAFETY: i < len なので初期化済み。古い配列は中身を落とさずに捨てるので、二重には落ちない
+            slot.write(unsafe { self.buf[old].assume_init_read() });
+        }
+        self.buf = buf;
+        self.head = 0;
+    }
+}
+
+fn uninit_buffer<T>(capacity: usize) -> Box<[MaybeUninit<T>]> {
+    (0..capacity).map(|_| MaybeUninit::uninit()).collect()
+}
+
+/// # Safety
+///
+/// `slice` の要素はすべて初期化済みであること
+unsafe fn assume_init_slice<T>(slice: &[MaybeUninit<T>]) -> &[T] {
+    unsafe { &*(slice as *const [MaybeUninit<T>] as *const [T]) }
+}
+
+impl<T> Drop for MyDeque<T> {
+    fn drop(&mut self) {
+        // MaybeUninit は中身を落とさないので、残っている要素を 1 つずつ落とす
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T> Index<usize> for MyDeque<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        let len = self.len;
+        self.get(index).unwrap_or_else(|| panic!("index {} out of bounds for length {}", index, len))
+    }
+}
+
+impl<T> IndexMut<usize> for MyDeque<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.len;
+        self.get_mut(index).unwrap_or_else(|| panic!("index {} out of bounds for length {}", index, len))
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MyDeque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_push_pop_both_ends() {
+        let mut deque = MyDeque::new();
+        assert_eq!(deque.capacity(), 0);
+        assert_eq!(deque.pop_front(), None);
+        deque.push_back(2);
+        deque.push_back(3);
+        deque.push_front(1);
+        deque.push_front(0);
+        assert_eq!(deque.len(), 4);
+        assert_eq!((deque.front(), deque.back()), (Some(&0), Some(&3)));
+        assert_eq!(format!("{:?}", deque), "[0, 1, 2, 3]");
+
+        deque[1] = 10;
+        assert_eq!(deque[1], 10);
+        assert_eq!(deque.pop_back(), Some(3));
+        assert_eq!(deque.pop_front(), Some(0));
+        assert_eq!(deque.iter().rev().collect::<Vec<_>>(), vec![&2, &10]);
+        assert!(std::panic::catch_unwind(|| MyDeque::<i32>::new()[0]).is_err());
+    }
+
+    #[test]
+    fn test_wraps_around_and_grows_in_order() {
+        let mut deque = MyDeque::with_capacity(4);
+        for i in 0..4 {
+            deque.push_back(i);
+        }
+        deque.pop_front();
+        deque.pop_front();
+        deque.push_back(4);
+        deque.push_back(5);
+        // 配列の端をまたいでいる: [4, 5, 2, 3] で head = 2
+        assert_eq!(deque.capacity(), 4);
+        assert_eq!(deque.as_slices(), (&[2, 3][..], &[4, 5][..]));
+
+        // 満杯から増やすと先頭から並べ直される
+        deque.push_front(1);
+        assert_eq!(deque.capacity(), 8);
+        assert_eq!(deque.iter().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        assert_eq!(deque.as_slices().0.len() + deque.as_slices().1.len(), 5);
+    }
+
+    #[test]
+    fn test_drops_each_element_once() {
+        let drops = Rc::new(Cell::new(0));
+        struct Counted(Rc<Cell<usize>>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let mut deque = MyDeque::new();
+        for i in 0..10 {
+            match i % 2 {
+                0 => deque.push_back(Counted(drops.clone())),
+                _ => deque.push_front(Counted(drops.clone())),
+            }
+        }
+        drop(deque.pop_front());
+        drop(deque.pop_back());
+        assert_eq!(drops.get(), 2);
+        drop(deque);
+        assert_eq!(drops.get(), 10);
+    }
+
+    #[test]
+    fn test_zero_sized_values() {
+        let mut deque = MyDeque::new();
+        for _ in 0..100 {
+            deque.push_front(());
+        }
+        assert_eq!(deque.len(), 100);
+        assert_eq!(deque.pop_back(), Some(()));
+        assert_eq!(deque.iter().count(), 99);
+    }
+
+    #[test]
+    fn test_matches_vecdeque() {
+        let mut state = 0x3c6e_f372_fe94_f82bu64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut deque = MyDeque::new();
+        let mut model = VecDeque::new();
+        for step in 0..20_000 {
+            match random(6) {
+                0 => {
+                    deque.push_back(step);
+                    model.push_back(step);
+                }
+                1 => {
+                    deque.push_front(step);
+                    model.push_front(step);
+                }
+                2 => assert_eq!(deque.pop_back(), model.pop_back()),
+                3 => assert_eq!(deque.pop_front(), model.pop_front()),
+                4 => {
+                    let index = random(model.len() as u64 + 1) as usize;
+                    assert_eq!(deque.get(index), model.get(index));
+                    if let (Some(a), Some(b)) = (deque.get_mut(index), model.get_mut(index)) {
+                        *a += 1;
+                        *b += 1;
+                    }
+                }
+                _ => assert_eq!((deque.front(), deque.back()), (model.front(), model.back())),
+            }
+            assert_eq!(deque.len(), model.len());
+        }
+        assert!(deque.iter().eq(model.iter()));
+    }
+}
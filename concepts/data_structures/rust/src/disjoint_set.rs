@@ -0,0 +1,139 @@
+//! Union-Find (素集合データ構造)
+//!
+//! 要素を木で表し、根をその集合の代表にする。2 つの工夫で木をほぼ平らに保つ:
+//!
+//! - ランクによる併合: 低い木を高い木の根の下につなぐ
+//! - 経路圧縮: `find` で辿ったノードを根に直接つなぎ直す
+//!
+//! 両方を使うと `find` / `union` は償却でほぼ定数時間 (逆アッカーマン関数) になる。
+
+/// 0..n の要素を素集合に分ける
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    /// 木の高さの上限 (経路圧縮で実際の高さは下がるが、ランクは下げない)
+    rank: Vec<u8>,
+    sets: usize,
+}
+
+impl DisjointSet {
+    /// `n` 個の要素をそれぞれ 1 つずつの集合にする
+    pub fn new(n: usize) -> Self {
+        DisjointSet {
+            parent: (0..n).collect(),
+            rank: vec![0; n],
+            sets: n,
+        }
+    }
+
+    /// 要素の数
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// 集合の数
+    pub fn sets(&self) -> usize {
+        self.sets
+    }
+
+    /// `x` の集合の代表 (辿った要素は根に直接つなぎ直す)
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut current = x;
+        while self.parent[current] != root {
+            let next = self.parent[current];
+            self.parent[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    /// `a` と `b` の集合をまとめる (もともと同じ集合なら false)
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        let (low, high) = match self.rank[a] < self.rank[b] {
+            true => (a, b),
+            false => (b, a),
+        };
+        self.parent[low] = high;
+        if self.rank[low] == self.rank[high] {
+            self.rank[high] += 1;
+        }
+        self.sets -= 1;
+        true
+    }
+
+    /// `a` と `b` が同じ集合か
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// 集合ごとの要素 (各集合は小さい順、集合は一番小さい要素の順)
+    pub fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut by_root: Vec<Vec<usize>> = vec![Vec::new(); self.len()];
+        for x in 0..self.len() {
+            let root = self.find(x);
+            by_root[root].push(x);
+        }
+        let mut groups: Vec<_> = by_root.into_iter().filter(|group| !group.is_empty()).collect();
+        groups.sort();
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_union_and_find() {
+        let mut set = DisjointSet::new(6);
+        assert_eq!(set.sets(), 6);
+        assert!(!set.connected(0, 1));
+
+        assert!(set.union(0, 1));
+        assert!(set.union(2, 3));
+        assert!(set.union(1, 3));
+        assert!(!set.union(0, 2));
+        assert_eq!(set.sets(), 3);
+        assert!(set.connected(0, 3));
+        assert!(!set.connected(0, 4));
+        assert_eq!(set.groups(), vec![vec![0, 1, 2, 3], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn test_path_compression_and_rank() {
+        let mut set = DisjointSet::new(1000);
+        for i in 1..1000 {
+            set.union(i - 1, i);
+        }
+        assert_eq!(set.sets(), 1);
+        // ランクで併合するので、鎖のようにつないでも木は高くならない
+        assert!(set.rank.iter().all(|&rank| rank <= 10));
+
+        let root = set.find(999);
+        // 辿ったあとは根に直接つながる
+        assert_eq!(set.parent[999], root);
+        for i in 0..1000 {
+            set.find(i);
+        }
+        assert!((0..1000).all(|i| set.parent[i] == root));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut set = DisjointSet::new(0);
+        assert!(set.is_empty());
+        assert_eq!((set.len(), set.sets()), (0, 0));
+        assert!(set.groups().is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+//! グラフ (隣接リスト)
+//!
+//! ノードは追加した順の番号 ([`NodeId`]) で指し、各ノードから出る辺を
+//! `Vec<(行き先, 重み)>` で持つ。無向グラフは両向きの辺を 1 本ずつ持つ。
+//!
+//! - 幅優先探索 / 深さ優先探索: 訪れた順にノードを返すイテレータ
+//! - Dijkstra 法: 距離が縮んだノードは [`IndexedHeap`] で優先度を下げる (古い候補を積み残さない)
+//! - 閉路の検出: 有向なら DFS の「探索中」のノードに戻る辺、無向なら辺の本数で判定する
+
+use std::collections::VecDeque;
+
+use crate::bit_set::BitSet;
+use crate::indexed_heap::IndexedHeap;
+
+/// ノードの番号
+pub type NodeId = usize;
+
+/// 隣接リストで表すグラフ
+pub struct Graph<N> {
+    nodes: Vec<N>,
+    edges: Vec<Vec<(NodeId, u64)>>,
+    directed: bool,
+    /// 追加した辺の本数 (無向の辺も 1 本と数える)
+    edge_count: usize,
+}
+
+impl<N> Graph<N> {
+    /// 有向グラフ
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    /// 無向グラフ
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    fn new(directed: bool) -> Self {
+        Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            directed,
+            edge_count: 0,
+        }
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// ノードの数
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn add_node(&mut self, data: N) -> NodeId {
+        self.nodes.push(data);
+        self.edges.push(Vec::new());
+        self.nodes.len() - 1
+    }
+
+    /// 重み付きの辺を足す (存在しないノードを指すのは呼び出し側の誤りなのでパニックする)
+    pub fn add_edge(&mut self, from: NodeId, to: NodeId, weight: u64) {
+        assert!(from < self.len() && to < self.len(), "no such node");
+        self.edges[from].push((to, weight));
+        if !self.directed && from != to {
+            self.edges[to].push((from, weight));
+        }
+        self.edge_count += 1;
+    }
+
+    pub fn node(&self, id: NodeId) -> &N {
+        &self.nodes[id]
+    }
+
+    /// `id` から出る辺の (行き先, 重み)
+    pub fn neighbors(&self, id: NodeId) -> impl Iterator<Item = (NodeId, u64)> + '_ {
+        self.edges[id].iter().copied()
+    }
+
+    /// `start` から幅優先で辿る (近いノードから順に)
+    pub fn bfs(&self, start: NodeId) -> Bfs<'_, N> {
+        let mut visited = BitSet::new(self.len());
+        visited.set(start);
+        Bfs {
+            graph: self,
+            queue: VecDeque::from([start]),
+            visited,
+        }
+    }
+
+    /// `start` から深さ優先で辿る (行きがけ順。辺は足した順に試す)
+    pub fn dfs(&self, start: NodeId) -> Dfs<'_, N> {
+        Dfs {
+            graph: self,
+            stack: vec![start],
+            visited: BitSet::new(self.len()),
+        }
+    }
+
+    /// `from` から `to` への最短経路 (重みの合計, 通るノード)。届かなければ None
+    pub fn shortest_path(&self, from: NodeId, to: NodeId) -> Option<(u64, Vec<NodeId>)> {
+        let mut distance = vec![u64::MAX; self.len()];
+        let mut previous: Vec<Option<NodeId>> = vec![None; self.len()];
+        let mut heap = IndexedHeap::new();
+        distance[from] = 0;
+        heap.push(from, 0);
+
+        // 取り出したノードの距離は確定している
+        while let Some((node, cost)) = heap.pop() {
+            if node == to {
+                break;
+            }
+            for (next, weight) in self.neighbors(node) {
+                let candidate = cost + weight;
+                if candidate < distance[next] {
+                    distance[next] = candidate;
+                    previous[next] = Some(node);
+                    // 既に入っていれば優先度を下げるだけ
+                    heap.push(next, candidate);
+                }
+            }
+        }
+
+        if distance[to] == u64::MAX {
+            return None;
+        }
+        let mut path = vec![to];
+        while let Some(prev) = previous[*path.last().unwrap()] {
+            path.push(prev);
+        }
+        path.reverse();
+        Some((distance[to], path))
+    }
+
+    /// 閉路があるか
+    pub fn has_cycle(&self) -> bool {
+        match self.directed {
+            true => self.has_directed_cycle(),
+            // 森 (閉路の無いグラフ) なら辺の数 = ノード数 - 連結成分の数
+            false => self.edge_count > self.len() - self.components(),
+        }
+    }
+
+    /// 探索中 (Gray) のノードへ戻る辺があれば閉路
+    fn has_directed_cycle(&self) -> bool {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Color {
+            White,
+            Gray,
+            Black,
+        }
+        let mut color = vec![Color::White; self.len()];
+        for start in 0..self.len() {
+            if color[start] != Color::White {
+                continue;
+            }
+            // (ノード, 次に試す辺の位置)
+            let mut stack = vec![(start, 0)];
+            color[start] = Color::Gray;
+            while let Some((node, index)) = stack.pop() {
+                let Some(&(next, _)) = self.edges[node].get(index) else {
+                    color[node] = Color::Black;
+                    continue;
+                };
+                stack.push((node, index + 1));
+                match color[next] {
+                    Color::Gray => return true,
+                    Color::White => {
+                        color[next] = Color::Gray;
+                        stack.push((next, 0));
+                    }
+                    Color::Black => {}
+                }
+            }
+        }
+        false
+    }
+
+    /// 連結成分の数 (無向グラフ用)
+    fn components(&self) -> usize {
+        let mut seen = BitSet::new(self.len());
+        let mut count = 0;
+        for start in 0..self.len() {
+            if !seen.test(start) {
+                count += 1;
+                for node in self.bfs(start) {
+                    seen.set(node);
+                }
+            }
+        }
+        count
+    }
+}
+
+/// 幅優先探索のイテレータ
+pub struct Bfs<'a, N> {
+    graph: &'a Graph<N>,
+    queue: VecDeque<NodeId>,
+    visited: BitSet,
+}
+
+impl<N> Iterator for Bfs<'_, N> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let node = self.queue.pop_front()?;
+        for (next, _) in self.graph.neighbors(node) {
+            if self.visited.set(next) {
+                self.queue.push_back(next);
+            }
+        }
+        Some(node)
+    }
+}
+
+/// 深さ優先探索のイテレータ
+pub struct Dfs<'a, N> {
+    graph: &'a Graph<N>,
+    stack: Vec<NodeId>,
+    visited: BitSet,
+}
+
+impl<N> Iterator for Dfs<'_, N> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        while let Some(node) = self.stack.pop() {
+            if !self.visited.set(node) {
+                continue;
+            }
+            // 先に足した辺から辿るよう、逆順に積む
+            let unvisited = self.graph.edges[node].iter().rev().filter(|(next, _)| !self.visited.test(*next));
+            self.stack.extend(unvisited.map(|(next, _)| *next));
+            return Some(node);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A - B - D
+    /// |   |
+    /// C - E   F
+    fn sample() -> Graph<&'static str> {
+        let mut graph = Graph::undirected();
+        let ids: Vec<_> = ["A", "B", "C", "D", "E", "F"].into_iter().map(|name| graph.add_node(name)).collect();
+        for (from, to) in [(0, 1), (0, 2), (1, 3), (1, 4), (2, 4)] {
+            graph.add_edge(ids[from], ids[to], 1);
+        }
+        graph
+    }
+
+    fn names(graph: &Graph<&'static str>, ids: impl Iterator<Item = NodeId>) -> String {
+        ids.map(|id| *graph.node(id)).collect()
+    }
+
+    #[test]
+    fn test_bfs_and_dfs() {
+        let graph = sample();
+        assert_eq!(names(&graph, graph.bfs(0)), "ABCDE");
+        assert_eq!(names(&graph, graph.dfs(0)), "ABDEC");
+        // 届かないノードは出てこない
+        assert_eq!(names(&graph, graph.bfs(5)), "F");
+        assert_eq!(names(&graph, graph.dfs(3)), "DBACE");
+    }
+
+    #[test]
+    fn test_directed_traversal_follows_edges() {
+        let mut graph = Graph::directed();
+        let a = graph.add_node('a');
+        let b = graph.add_node('b');
+        let c = graph.add_node('c');
+        graph.add_edge(a, b, 1);
+        graph.add_edge(c, b, 1);
+        assert!(graph.is_directed());
+        assert_eq!(graph.bfs(a).collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(graph.dfs(b).collect::<Vec<_>>(), vec![b]);
+    }
+
+    #[test]
+    fn test_shortest_path() {
+        // Dijkstra 法の説明でよく使われる 6 ノードの例 (0 から 4 へは 0-2-5-4 の 20)
+        let mut graph = Graph::undirected();
+        for i in 0..6 {
+            graph.add_node(i);
+        }
+        for (from, to, weight) in [(0, 1, 7), (0, 2, 9), (0, 5, 14), (1, 2, 10), (1, 3, 15), (2, 3, 11), (2, 5, 2), (3, 4, 6), (4, 5, 9)] {
+            graph.add_edge(from, to, weight);
+        }
+        assert_eq!(graph.shortest_path(0, 4), Some((20, vec![0, 2, 5, 4])));
+        assert_eq!(graph.shortest_path(0, 3), Some((20, vec![0, 2, 3])));
+        assert_eq!(graph.shortest_path(3, 3), Some((0, vec![3])));
+
+        // 有向なら逆向きには辿れない
+        let mut graph = Graph::directed();
+        let a = graph.add_node(());
+        let b = graph.add_node(());
+        graph.add_edge(a, b, 5);
+        assert_eq!(graph.shortest_path(a, b), Some((5, vec![a, b])));
+        assert_eq!(graph.shortest_path(b, a), None);
+    }
+
+    #[test]
+    fn test_has_cycle() {
+        // sample は A-B-E-C-A で閉路
+        assert!(sample().has_cycle());
+
+        let mut tree = Graph::undirected();
+        for i in 0..5 {
+            tree.add_node(i);
+        }
+        for (from, to) in [(0, 1), (0, 2), (2, 3)] {
+            tree.add_edge(from, to, 1);
+        }
+        assert!(!tree.has_cycle());
+        // 同じ 2 点を結ぶ辺をもう 1 本足すと閉路になる
+        tree.add_edge(3, 2, 1);
+        assert!(tree.has_cycle());
+
+        let mut dag = Graph::directed();
+        for i in 0..4 {
+            dag.add_node(i);
+        }
+        for (from, to) in [(0, 1), (0, 2), (1, 3), (2, 3)] {
+            dag.add_edge(from, to, 1);
+        }
+        // ひし形でも向きが揃っていれば閉路ではない
+        assert!(!dag.has_cycle());
+        dag.add_edge(3, 0, 1);
+        assert!(dag.has_cycle());
+
+        let mut self_loop = Graph::directed();
+        let a = self_loop.add_node(());
+        self_loop.add_edge(a, a, 1);
+        assert!(self_loop.has_cycle());
+    }
+}
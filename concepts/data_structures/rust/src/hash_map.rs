@@ -0,0 +1,326 @@
+//! ハッシュマップ (オープンアドレス法)
+//!
+//! 1 本の配列にエントリを直接並べ、ハッシュ値の位置が埋まっていたら隣へ進む
+//! (線形探索)。連鎖法のようなノードの確保が無く、探索がキャッシュに優しい。
+//!
+//! - 削除: 穴を空けると、その先に置いたエントリが見つからなくなるので、
+//!   「墓石」を置いて探索を続けさせる。墓石は挿入で再利用する
+//! - 拡張: (エントリ + 墓石) が容量の 3/4 を超えたら作り直す。墓石が多いだけなら
+//!   同じ容量で作り直して墓石を掃除する
+//!
+//! ハッシュ関数は `BuildHasher` で差し替えられる。既定の `RandomState` は
+//! プロセスごとに鍵を変える SipHash なので、同じ位置に落ちるキーを外から狙って
+//! 送り込まれても (HashDoS) 探索が O(n) に劣化しにくい。
+//! `cargo bench --bench hash_map` で速い弱いハッシュとの差を測れる。
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+
+/// 最初に確保する容量
+const MIN_CAPACITY: usize = 8;
+
+enum Slot<K, V> {
+    Empty,
+    /// 削除済み (探索はここで止めない)
+    Tombstone,
+    Full(K, V),
+}
+
+/// 線形探索のオープンアドレス法で作るハッシュマップ
+pub struct MyHashMap<K, V, S = RandomState> {
+    /// 長さは 0 か 2 のべき乗 (ハッシュ値を `& mask` で位置にする)
+    slots: Vec<Slot<K, V>>,
+    len: usize,
+    tombstones: usize,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V> MyHashMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V> Default for MyHashMap<K, V, RandomState> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> MyHashMap<K, V, S> {
+    /// ハッシュ関数を指定して作る
+    pub fn with_hasher(hasher: S) -> Self {
+        MyHashMap {
+            slots: Vec::new(),
+            len: 0,
+            tombstones: 0,
+            hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 確保しているスロットの数
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.slots[self.find(key)?] {
+            Slot::Full(_, value) => Some(value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        match &mut self.slots[index] {
+            Slot::Full(_, value) => Some(value),
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    /// 入れる。同じキーがあれば値を置き換えて古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.reserve_one();
+        let mask = self.slots.len() - 1;
+        let mut index = self.hash(&key) & mask;
+        let mut first_tombstone = None;
+        loop {
+            match &mut self.slots[index] {
+                Slot::Full(existing, old) if *existing == key => {
+                    return Some(std::mem::replace(old, value));
+                }
+                Slot::Full(..) => {}
+                Slot::Tombstone => {
+                    first_tombstone.get_or_insert(index);
+                }
+                Slot::Empty => break,
+            }
+            index = (index + 1) & mask;
+        }
+        // キーが無いことを確かめてから、通り過ぎた墓石があればそこに置く
+        if let Some(tombstone) = first_tombstone {
+            index = tombstone;
+            self.tombstones -= 1;
+        }
+        self.slots[index] = Slot::Full(key, value);
+        self.len += 1;
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        let mask = self.slots.len() - 1;
+        // 次が空なら、ここで探索が止まっても困らないので墓石は要らない
+        let replacement = match self.slots[(index + 1) & mask] {
+            Slot::Empty => Slot::Empty,
+            _ => {
+                self.tombstones += 1;
+                Slot::Tombstone
+            }
+        };
+        self.len -= 1;
+        match std::mem::replace(&mut self.slots[index], replacement) {
+            Slot::Full(_, value) => Some(value),
+            _ => unreachable!(),
+        }
+    }
+
+    /// 順番は決まっていない
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Full(key, value) => Some((key, value)),
+            _ => None,
+        })
+    }
+
+    /// `key` のあるスロット
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.slots.is_empty() {
+            return None;
+        }
+        let mask = self.slots.len() - 1;
+        let mut index = self.hash(key) & mask;
+        // 空きが必ずあるので、どこかで止まる
+        loop {
+            match &self.slots[index] {
+                Slot::Empty => return None,
+                Slot::Full(existing, _) if existing.borrow() == key => return Some(index),
+                _ => index = (index + 1) & mask,
+            }
+        }
+    }
+
+    fn hash<Q: Hash + ?Sized>(&self, key: &Q) -> usize {
+        self.hasher.hash_one(key) as usize
+    }
+
+    /// もう 1 つ入れても (エントリ + 墓石) が 3/4 以下になるようにする
+    fn reserve_one(&mut self) {
+        let used = self.len + self.tombstones + 1;
+        if used * 4 <= self.slots.len() * 3 {
+            return;
+        }
+        // 墓石を除いても半分を超えるなら倍にする。そうでなければ掃除だけ
+        let capacity = match (self.len + 1) * 2 > self.slots.len() {
+            true => (self.slots.len() * 2).max(MIN_CAPACITY),
+            false => self.slots.len(),
+        };
+        self.rehash(capacity);
+    }
+
+    fn rehash(&mut self, capacity: usize) {
+        let old = std::mem::replace(&mut self.slots, (0..capacity).map(|_| Slot::Empty).collect());
+        let mask = capacity - 1;
+        self.tombstones = 0;
+        for slot in old {
+            if let Slot::Full(key, value) = slot {
+                let mut index = self.hash(&key) & mask;
+                while let Slot::Full(..) = self.slots[index] {
+                    index = (index + 1) & mask;
+                }
+                self.slots[index] = Slot::Full(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::{BuildHasherDefault, Hasher};
+
+    /// どのキーも同じハッシュ値にする (最悪の衝突)
+    #[derive(Default)]
+    struct Constant;
+
+    impl Hasher for Constant {
+        fn finish(&self) -> u64 {
+            42
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map = MyHashMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.get("a"), None);
+        assert_eq!(map.insert("a".to_string(), 1), None);
+        assert_eq!(map.insert("b".to_string(), 2), None);
+        assert_eq!(map.insert("a".to_string(), 10), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("a"), Some(&10));
+
+        *map.get_mut("b").unwrap() += 5;
+        assert_eq!(map.get("b"), Some(&7));
+
+        assert_eq!(map.remove("a"), Some(10));
+        assert_eq!(map.remove("a"), None);
+        assert!(!map.contains_key("a"));
+        assert!(map.contains_key("b"));
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(&"b".to_string(), &7)]);
+    }
+
+    #[test]
+    fn test_grows_by_load_factor() {
+        let mut map = MyHashMap::new();
+        for i in 0..1000 {
+            map.insert(i, i);
+            assert!(map.len() * 4 <= map.capacity() * 3);
+            assert!(map.capacity().is_power_of_two());
+        }
+        assert_eq!(map.capacity(), 2048);
+        assert!((0..1000).all(|i| map.get(&i) == Some(&i)));
+    }
+
+    #[test]
+    fn test_tombstones_are_found_through_and_reused() {
+        let mut map = MyHashMap::with_hasher(BuildHasherDefault::<Constant>::default());
+        for i in 0..5 {
+            map.insert(i, i);
+        }
+        // 全部が同じ位置から並ぶので、途中を消しても後ろが見つかる
+        assert_eq!(map.remove(&1), Some(1));
+        assert_eq!(map.tombstones, 1);
+        assert_eq!(map.get(&4), Some(&4));
+        // 墓石を再利用する (同じキーがもっと後ろに無いことは確かめてから)
+        assert_eq!(map.insert(4, 40), Some(4));
+        assert_eq!(map.insert(5, 5), None);
+        assert_eq!((map.len(), map.tombstones), (5, 0));
+    }
+
+    #[test]
+    fn test_churn_does_not_grow() {
+        let mut map = MyHashMap::new();
+        for i in 0..10_000 {
+            map.insert(i, ());
+            map.remove(&(i - 3));
+            assert!(map.len() <= 4);
+        }
+        // 墓石が溜まっても作り直しで掃除されるので、容量は小さいまま
+        assert_eq!(map.capacity(), MIN_CAPACITY);
+    }
+
+    #[test]
+    fn test_matches_std_hash_map() {
+        let mut state = 0x853c_49e6_748f_ea9bu64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut map = MyHashMap::new();
+        let mut model = HashMap::new();
+        for step in 0..20_000 {
+            let key = random(300);
+            match random(3) {
+                0 => assert_eq!(map.insert(key, step), model.insert(key, step)),
+                1 => assert_eq!(map.remove(&key), model.remove(&key)),
+                _ => assert_eq!(map.get(&key), model.get(&key)),
+            }
+            assert_eq!(map.len(), model.len());
+        }
+        let mut entries: Vec<_> = map.iter().map(|(&key, &value)| (key, value)).collect();
+        entries.sort();
+        let mut expected: Vec<_> = model.into_iter().collect();
+        expected.sort();
+        assert_eq!(entries, expected);
+    }
+}
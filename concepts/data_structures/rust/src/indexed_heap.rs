@@ -0,0 +1,236 @@
+//! 添字付きヒープ
+//!
+//! 優先度が一番小さいキーを O(log n) で取り出せる二分ヒープに、キーから配列の中の
+//! 位置を引く表を足したもの。`BinaryHeap` では中の要素を探せないので、優先度を
+//! 変えたいときは新しい値を積み直して古い方を捨てるしかないが、これなら
+//! 位置を引いて上下に動かすだけで済む。
+//!
+//! - Dijkstra 法: 距離が縮んだノードの優先度を下げる ([`Graph::shortest_path`])
+//! - タイマー: 期限の近い順に取り出し、延長・取り消しはキーで行う
+//!
+//! [`Graph::shortest_path`]: crate::graph::Graph::shortest_path
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// 優先度の小さい順に取り出すヒープ (キーは重複しない)
+pub struct IndexedHeap<K, P> {
+    /// 二分ヒープ (親は `(i - 1) / 2`)
+    heap: Vec<(K, P)>,
+    /// キーの `heap` の中の位置
+    positions: HashMap<K, usize>,
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> Default for IndexedHeap<K, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq + Clone, P: Ord> IndexedHeap<K, P> {
+    pub fn new() -> Self {
+        IndexedHeap {
+            heap: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    pub fn priority(&self, key: &K) -> Option<&P> {
+        self.positions.get(key).map(|&i| &self.heap[i].1)
+    }
+
+    /// 優先度が一番小さいもの
+    pub fn peek(&self) -> Option<(&K, &P)> {
+        self.heap.first().map(|(key, priority)| (key, priority))
+    }
+
+    /// 入れる。同じキーがあれば優先度を変えて古い優先度を返す
+    pub fn push(&mut self, key: K, priority: P) -> Option<P> {
+        if self.contains(&key) {
+            return self.change_priority(&key, priority);
+        }
+        self.positions.insert(key.clone(), self.heap.len());
+        self.heap.push((key, priority));
+        self.sift_up(self.heap.len() - 1);
+        None
+    }
+
+    /// 優先度が一番小さいものを取り出す
+    pub fn pop(&mut self) -> Option<(K, P)> {
+        let first = self.heap.first()?.0.clone();
+        self.remove(&first).map(|priority| (first, priority))
+    }
+
+    /// `key` の優先度を変え、古い優先度を返す (無ければ None)
+    pub fn change_priority(&mut self, key: &K, priority: P) -> Option<P> {
+        let &i = self.positions.get(key)?;
+        let old = std::mem::replace(&mut self.heap[i].1, priority);
+        // 小さくなったら上へ、大きくなったら下へ動かす
+        match self.heap[i].1 < old {
+            true => self.sift_up(i),
+            false => self.sift_down(i),
+        }
+        Some(old)
+    }
+
+    /// `key` を取り除き、その優先度を返す
+    pub fn remove(&mut self, key: &K) -> Option<P> {
+        let i = self.positions.remove(key)?;
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let (_, priority) = self.heap.pop().unwrap();
+        if i < self.heap.len() {
+            // 末尾から持ってきた要素は、上にも下にも動く可能性がある
+            self.positions.insert(self.heap[i].0.clone(), i);
+            self.sift_up(i);
+            self.sift_down(i);
+        }
+        Some(priority)
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[i].1 >= self.heap[parent].1 {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut smallest = i;
+            if left < self.heap.len() && self.heap[left].1 < self.heap[smallest].1 {
+                smallest = left;
+            }
+            if right < self.heap.len() && self.heap[right].1 < self.heap[smallest].1 {
+                smallest = right;
+            }
+            if smallest == i {
+                break;
+            }
+            self.swap(i, smallest);
+            i = smallest;
+        }
+    }
+
+    /// 2 つの要素を入れ替え、表の位置も直す
+    fn swap(&mut self, a: usize, b: usize) {
+        self.heap.swap(a, b);
+        for i in [a, b] {
+            if let Some(position) = self.positions.get_mut(&self.heap[i].0) {
+                *position = i;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_push_pop_in_priority_order() {
+        let mut heap = IndexedHeap::new();
+        for (key, priority) in [("c", 3), ("a", 1), ("e", 5), ("b", 2), ("d", 4)] {
+            assert_eq!(heap.push(key, priority), None);
+        }
+        assert_eq!(heap.len(), 5);
+        assert_eq!(heap.peek(), Some((&"a", &1)));
+        let order: Vec<_> = std::iter::from_fn(|| heap.pop()).map(|(key, _)| key).collect();
+        assert_eq!(order, ["a", "b", "c", "d", "e"]);
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn test_change_priority_and_remove() {
+        let mut heap = IndexedHeap::new();
+        for (key, priority) in [("timer1", 30), ("timer2", 10), ("timer3", 20)] {
+            heap.push(key, priority);
+        }
+        // 期限を早める・遅らせる
+        assert_eq!(heap.change_priority(&"timer1", 5), Some(30));
+        assert_eq!(heap.peek(), Some((&"timer1", &5)));
+        assert_eq!(heap.push("timer1", 40), Some(5));
+        assert_eq!(heap.priority(&"timer1"), Some(&40));
+        assert_eq!(heap.change_priority(&"missing", 1), None);
+
+        // 取り消し
+        assert_eq!(heap.remove(&"timer2"), Some(10));
+        assert_eq!(heap.remove(&"timer2"), None);
+        assert!(!heap.contains(&"timer2"));
+        assert_eq!(heap.pop(), Some(("timer3", 20)));
+        assert_eq!(heap.pop(), Some(("timer1", 40)));
+        assert_eq!(heap.pop(), None);
+    }
+
+    #[test]
+    fn test_matches_ordered_set_model() {
+        let mut state = 0xbb67_ae85_84ca_a73bu64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut heap = IndexedHeap::new();
+        // (優先度, キー) の集合と、キーごとの優先度
+        let mut model = BTreeSet::new();
+        let mut priorities = HashMap::new();
+        for _ in 0..20_000 {
+            let key = random(100);
+            let priority = random(1000);
+            match random(4) {
+                0 => {
+                    let old = priorities.insert(key, priority);
+                    if let Some(old) = old {
+                        model.remove(&(old, key));
+                    }
+                    model.insert((priority, key));
+                    assert_eq!(heap.push(key, priority), old);
+                }
+                1 => {
+                    let old = priorities.remove(&key);
+                    if let Some(old) = old {
+                        model.remove(&(old, key));
+                    }
+                    assert_eq!(heap.remove(&key), old);
+                }
+                2 if priorities.contains_key(&key) => {
+                    let old = priorities.insert(key, priority).unwrap();
+                    model.remove(&(old, key));
+                    model.insert((priority, key));
+                    assert_eq!(heap.change_priority(&key, priority), Some(old));
+                }
+                _ => {
+                    // 優先度が同じものの順番は決まらないので、一番小さい優先度のどれかであればよい
+                    let smallest = model.first().map(|&(priority, _)| priority);
+                    let popped = heap.pop();
+                    assert_eq!(popped.map(|(_, priority)| priority), smallest);
+                    if let Some((key, priority)) = popped {
+                        assert!(model.remove(&(priority, key)));
+                        priorities.remove(&key);
+                    }
+                }
+            }
+            assert_eq!(heap.len(), model.len());
+        }
+    }
+}
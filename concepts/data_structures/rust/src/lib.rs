@@ -0,0 +1,75 @@
+//! データ構造 - Rust 実装
+//!
+//! 標準ライブラリのコレクションの中身にあたるデータ構造を自分で組み立てる。
+//! 使い方のデモは `main.rs`。
+//!
+//! ほかのクレートからはパスで依存して使う:
+//!
+//! ```toml
+//! [dependencies]
+//! data_structures = { path = "../../../concepts/data_structures/rust" }
+//! ```
+//!
+//! 主な型はクレートの直下からも使える:
+//!
+//! ```
+//! use data_structures::{LruCache, Queue};
+//!
+//! let mut queue = Queue::new();
+//! queue.enqueue("GET /");
+//! queue.enqueue("GET /about");
+//! assert_eq!(queue.dequeue(), Some("GET /"));
+//!
+//! let mut cache = LruCache::new(1);
+//! cache.put("/", "index.html");
+//! assert_eq!(cache.get(&"/"), Some(&"index.html"));
+//! ```
+//!
+//! | 種類 | 型 |
+//! |------|-----|
+//! | 列 | [`Stack`], [`Queue`], [`MyDeque`] |
+//! | 探索木 | [`Bst`], [`AvlTree`], [`BTree`], [`SkipListMap`] |
+//! | ハッシュ | [`MyHashMap`], [`LruCache`], [`BloomFilter`] |
+//! | ヒープ | [`IndexedHeap`] |
+//! | 整数の集合 | [`BitSet`], [`SparseSet`], [`DisjointSet`] |
+//! | 区間クエリ | [`FenwickTree`], [`SegmentTree`] |
+//! | その他 | [`Graph`], [`Matrix`] |
+//!
+//! 文字列の検索や編集距離は [`strings`] モジュールの関数。
+
+pub mod avl;
+pub mod bit_set;
+pub mod bloom;
+pub mod bst;
+pub mod btree;
+pub mod deque;
+pub mod disjoint_set;
+pub mod graph;
+pub mod hash_map;
+pub mod indexed_heap;
+pub mod lru;
+pub mod matrix;
+pub mod queue;
+pub mod range_query;
+pub mod skip_list;
+pub mod sparse_set;
+pub mod stack;
+pub mod strings;
+
+pub use avl::AvlTree;
+pub use bit_set::BitSet;
+pub use bloom::BloomFilter;
+pub use bst::Bst;
+pub use btree::BTree;
+pub use deque::MyDeque;
+pub use disjoint_set::DisjointSet;
+pub use graph::Graph;
+pub use hash_map::MyHashMap;
+pub use indexed_heap::IndexedHeap;
+pub use lru::LruCache;
+pub use matrix::Matrix;
+pub use queue::Queue;
+pub use range_query::{FenwickTree, SegmentTree};
+pub use skip_list::SkipListMap;
+pub use sparse_set::SparseSet;
+pub use stack::Stack;
@@ -0,0 +1,252 @@
+//! LRU キャッシュ
+//!
+//! `HashMap` でキーからエントリの位置を引き、エントリ同士を使った順の双方向連結リストで
+//! つなぐ。リストのリンクはエントリの中に持つ (侵入型) ので、見つけたエントリを
+//! 先頭へ付け替えるのも末尾 (一番古いもの) を追い出すのも O(1)。
+//!
+//! エントリは `Vec` に並べ、リンクは添字で持つ (`challenges/02_linked_list` の
+//! `ArenaList` と同じ作り)。生ポインタを使わないので `unsafe` は要らない。
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// リンクが無いことを表す添字
+const NIL: usize = usize::MAX;
+
+struct Entry<K, V> {
+    key: K,
+    value: V,
+    /// 1 つ新しいエントリ
+    prev: usize,
+    /// 1 つ古いエントリ
+    next: usize,
+}
+
+/// ヒットとミスの回数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl Stats {
+    /// ヒット率 (まだ 1 度も引いていなければ 0)
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// 容量を超えたら一番長く使われていないものを追い出すキャッシュ
+///
+/// キーは `HashMap` とエントリの両方に置くので `Clone` が要る。
+pub struct LruCache<K, V> {
+    capacity: usize,
+    map: HashMap<K, usize>,
+    entries: Vec<Entry<K, V>>,
+    /// 一番新しいエントリ
+    head: usize,
+    /// 一番古いエントリ (次に追い出す)
+    tail: usize,
+    stats: Stats,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// `capacity` 個まで持つキャッシュ (0 は意味が無いのでパニックする)
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "LruCache capacity must be at least 1");
+        LruCache {
+            capacity,
+            map: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            head: NIL,
+            tail: NIL,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// 値を引き、一番新しいものにする (ヒット / ミスを数える)
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let Some(&index) = self.map.get(key) else {
+            self.stats.misses += 1;
+            return None;
+        };
+        self.stats.hits += 1;
+        self.detach(index);
+        self.attach_front(index);
+        Some(&self.entries[index].value)
+    }
+
+    /// 使った順も統計も変えずに値を見る
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.map.get(key).map(|&index| &self.entries[index].value)
+    }
+
+    /// 入れて一番新しいものにする
+    ///
+    /// 容量を超えるときは一番古いエントリを追い出して返す。同じキーがあれば値を
+    /// 置き換えるだけで、何も追い出さない。
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        if let Some(&index) = self.map.get(&key) {
+            self.entries[index].value = value;
+            self.detach(index);
+            self.attach_front(index);
+            return None;
+        }
+
+        if self.entries.len() < self.capacity {
+            let index = self.entries.len();
+            self.entries.push(Entry {
+                key: key.clone(),
+                value,
+                prev: NIL,
+                next: NIL,
+            });
+            self.map.insert(key, index);
+            self.attach_front(index);
+            return None;
+        }
+
+        // 一番古いエントリの場所を使い回す
+        let index = self.tail;
+        self.detach(index);
+        let entry = &mut self.entries[index];
+        let old_key = std::mem::replace(&mut entry.key, key.clone());
+        let old_value = std::mem::replace(&mut entry.value, value);
+        self.map.remove(&old_key);
+        self.map.insert(key, index);
+        self.attach_front(index);
+        Some((old_key, old_value))
+    }
+
+    /// 新しい順に辿る
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let mut index = self.head;
+        std::iter::from_fn(move || {
+            let entry = self.entries.get(index)?;
+            index = entry.next;
+            Some((&entry.key, &entry.value))
+        })
+    }
+
+    /// リストから外す (エントリ自体は残る)
+    fn detach(&mut self, index: usize) {
+        let (prev, next) = (self.entries[index].prev, self.entries[index].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    /// 先頭 (一番新しい位置) につなぐ
+    fn attach_front(&mut self, index: usize) {
+        self.entries[index].prev = NIL;
+        self.entries[index].next = self.head;
+        match self.head {
+            NIL => self.tail = index,
+            head => self.entries[head].prev = index,
+        }
+        self.head = index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(cache: &LruCache<&'static str, i32>) -> Vec<&'static str> {
+        cache.iter().map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        assert_eq!(cache.put("a", 1), None);
+        assert_eq!(cache.put("b", 2), None);
+        assert_eq!(keys(&cache), vec!["b", "a"]);
+
+        // a を使うと b が一番古くなる
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.put("c", 3), Some(("b", 2)));
+        assert_eq!(keys(&cache), vec!["c", "a"]);
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.len(), 2);
+
+        // 置き換えは何も追い出さず、一番新しくなる
+        assert_eq!(cache.put("a", 10), None);
+        assert_eq!(keys(&cache), vec!["a", "c"]);
+        assert_eq!(cache.put("d", 4), Some(("c", 3)));
+        assert_eq!(cache.peek(&"a"), Some(&10));
+    }
+
+    #[test]
+    fn test_peek_does_not_touch_order_or_stats() {
+        let mut cache = LruCache::new(2);
+        cache.put("a", 1);
+        cache.put("b", 2);
+        assert_eq!(cache.peek(&"a"), Some(&1));
+        assert_eq!(cache.stats(), Stats::default());
+        assert_eq!(cache.put("c", 3), Some(("a", 1)));
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut cache = LruCache::new(3);
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+        for key in ["a", "b", "c"] {
+            cache.put(key, 0);
+        }
+        for key in ["a", "x", "b", "a"] {
+            cache.get(&key);
+        }
+        assert_eq!(cache.stats(), Stats { hits: 3, misses: 1 });
+        assert_eq!(cache.stats().hit_rate(), 0.75);
+    }
+
+    #[test]
+    fn test_capacity_one() {
+        let mut cache = LruCache::new(1);
+        assert_eq!(cache.capacity(), 1);
+        cache.put("a", 1);
+        assert_eq!(cache.put("b", 2), Some(("a", 1)));
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(keys(&cache), vec!["b"]);
+
+        assert!(std::panic::catch_unwind(|| LruCache::<i32, i32>::new(0)).is_err());
+    }
+
+    #[test]
+    fn test_many_operations_keep_links_consistent() {
+        let mut cache = LruCache::new(8);
+        for i in 0..200u32 {
+            cache.put(i % 13, i);
+            cache.get(&(i % 5));
+            // 新しい順に辿った数と持っている数がいつも一致する
+            assert_eq!(cache.iter().count(), cache.len());
+        }
+        assert_eq!(cache.len(), 8);
+    }
+}
@@ -1,9 +1,15 @@
 //! データ構造 - Rust 実装
 //!
-//! Rust の標準ライブラリのデータ構造
+//! Rust の標準ライブラリのデータ構造と、`lib.rs` で組み立てたデータ構造のデモ
 
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 
+use data_structures::{
+    AvlTree, BTree, BitSet, BloomFilter, Bst, DisjointSet, FenwickTree, Graph, IndexedHeap, LruCache, Matrix, MyDeque, MyHashMap, Queue,
+    SegmentTree, SkipListMap, SparseSet, Stack,
+};
+use data_structures::strings;
+
 fn main() {
     println!("=== Data Structures Demo ===\n");
 
@@ -13,9 +19,26 @@ fn main() {
     demo_hashset();
     demo_binary_heap();
     demo_custom_struct();
+    demo_bst();
+    demo_avl();
+    demo_graph();
+    demo_lru();
+    demo_disjoint_set();
+    demo_bloom();
+    demo_skip_list();
+    demo_my_hash_map();
+    demo_btree();
+    demo_range_query();
+    demo_bit_set();
+    demo_matrix();
+    demo_sparse_set();
+    demo_strings();
+    demo_indexed_heap();
+    demo_my_deque();
 }
 
 /// Vec - 動的配列
+#[allow(clippy::vec_init_then_push)] // push を順に見せるため
 fn demo_vec() {
     println!("--- Vec (動的配列) ---");
 
@@ -149,6 +172,7 @@ fn demo_custom_struct() {
     println!("stack: {:?}", stack);
     println!("pop: {:?}", stack.pop());
     println!("peek: {:?}", stack.peek());
+    println!("len: {}, is_empty: {}", stack.len(), stack.is_empty());
 
     // キュー
     let mut queue: Queue<i32> = Queue::new();
@@ -158,107 +182,304 @@ fn demo_custom_struct() {
     println!("\nqueue: {:?}", queue);
     println!("dequeue: {:?}", queue.dequeue());
     println!("front: {:?}", queue.front());
+    println!("len: {}, is_empty: {}", queue.len(), queue.is_empty());
 }
 
-/// スタック (LIFO)
-#[derive(Debug)]
-struct Stack<T> {
-    items: Vec<T>,
-}
+/// 二分探索木
+fn demo_bst() {
+    println!("\n--- Binary Search Tree ---");
 
-impl<T> Stack<T> {
-    fn new() -> Self {
-        Stack { items: Vec::new() }
+    let mut tree = Bst::new();
+    for (key, value) in [(50, "fifty"), (30, "thirty"), (70, "seventy"), (20, "twenty"), (40, "forty")] {
+        tree.insert(key, value);
     }
-
-    fn push(&mut self, item: T) {
-        self.items.push(item);
+    let in_order: Vec<_> = tree.iter().map(|(key, _)| key).collect();
+    println!("in-order: {:?}", in_order);
+    println!("get(40): {:?}", tree.get(&40));
+    println!("min: {:?}, max: {:?}", tree.min(), tree.max());
+    println!("remove(30): {:?}", tree.remove(&30));
+    println!("len: {}, height: {}", tree.len(), tree.height());
+
+    // ソート済みの順に入れると片側に伸びる
+    let mut sorted = Bst::new();
+    for i in 0..100 {
+        sorted.insert(i, ());
     }
+    println!("sorted 0..100 -> height: {}", sorted.height());
+}
+
+/// AVL 木 (自己平衡二分探索木)
+fn demo_avl() {
+    println!("\n--- AVL Tree ---");
 
-    fn pop(&mut self) -> Option<T> {
-        self.items.pop()
+    // ソート済みの順に入れても回転で平衡が保たれる
+    let mut tree = AvlTree::new();
+    for i in 1..=7 {
+        tree.insert(i, i * i);
     }
+    print!("{}", tree.shape());
+    println!("get(5): {:?}", tree.get(&5));
+    println!("remove(4): {:?}", tree.remove(&4));
+    print!("{}", tree.shape());
+    let keys: Vec<_> = tree.iter().map(|(key, _)| key).collect();
+    println!("keys: {:?}, len: {}, is_empty: {}", keys, tree.len(), tree.is_empty());
+
+    let mut sorted = AvlTree::new();
+    for i in 0..100 {
+        sorted.insert(i, ());
+    }
+    println!("sorted 0..100 -> height: {} (Bst: 100)", sorted.height());
+}
 
-    fn peek(&self) -> Option<&T> {
-        self.items.last()
+/// グラフ (隣接リスト)
+fn demo_graph() {
+    println!("\n--- Graph ---");
+
+    let mut graph = Graph::undirected();
+    let tokyo = graph.add_node("Tokyo");
+    let nagoya = graph.add_node("Nagoya");
+    let kyoto = graph.add_node("Kyoto");
+    let osaka = graph.add_node("Osaka");
+    graph.add_edge(tokyo, nagoya, 350);
+    graph.add_edge(nagoya, kyoto, 140);
+    graph.add_edge(kyoto, osaka, 50);
+    graph.add_edge(nagoya, osaka, 190);
+    graph.add_edge(tokyo, osaka, 600);
+
+    let bfs: Vec<_> = graph.bfs(tokyo).map(|id| *graph.node(id)).collect();
+    let dfs: Vec<_> = graph.dfs(tokyo).map(|id| *graph.node(id)).collect();
+    println!("nodes: {}, empty: {}", graph.len(), graph.is_empty());
+    println!("bfs: {:?}", bfs);
+    println!("dfs: {:?}", dfs);
+    if let Some((cost, path)) = graph.shortest_path(tokyo, osaka) {
+        let path: Vec<_> = path.into_iter().map(|id| *graph.node(id)).collect();
+        println!("shortest Tokyo -> Osaka: {} km via {:?}", cost, path);
     }
+    println!("directed: {}, has_cycle: {}", graph.is_directed(), graph.has_cycle());
+}
 
-    fn is_empty(&self) -> bool {
-        self.items.is_empty()
+/// LRU キャッシュ
+fn demo_lru() {
+    println!("\n--- LRU Cache ---");
+
+    let mut cache = LruCache::new(2);
+    println!("capacity: {}, empty: {}", cache.capacity(), cache.is_empty());
+    cache.put("a", 1);
+    cache.put("b", 2);
+    println!("get(a): {:?}", cache.get(&"a"));
+    println!("put(c) evicts: {:?}", cache.put("c", 3));
+    println!("get(b): {:?}", cache.get(&"b"));
+    println!("peek(c): {:?}", cache.peek(&"c"));
+    let order: Vec<_> = cache.iter().map(|(key, _)| *key).collect();
+    println!("newest first: {:?} (len {})", order, cache.len());
+    let stats = cache.stats();
+    println!("hits: {}, misses: {}, hit rate: {:.2}", stats.hits, stats.misses, stats.hit_rate());
+}
+
+/// Union-Find で連結成分を数える
+fn demo_disjoint_set() {
+    println!("\n--- Disjoint Set (Union-Find) ---");
+
+    // 0-1-2   3-4   5
+    let edges = [(0, 1), (1, 2), (3, 4)];
+    let mut set = DisjointSet::new(6);
+    for (a, b) in edges {
+        set.union(a, b);
     }
+    println!("elements: {}, empty: {}", set.len(), set.is_empty());
+    println!("components: {} {:?}", set.sets(), set.groups());
+    println!("connected(0, 2): {}, connected(2, 3): {}", set.connected(0, 2), set.connected(2, 3));
+    println!("find(2): {}", set.find(2));
+}
+
+/// Bloom フィルタ
+fn demo_bloom() {
+    println!("\n--- Bloom Filter ---");
 
-    fn len(&self) -> usize {
-        self.items.len()
+    let mut filter = BloomFilter::new(1000, 0.01);
+    println!("m = {} bits, k = {} hashes", filter.bit_len(), filter.hash_count());
+    for word in ["rust", "ruby", "go"] {
+        filter.insert(word);
     }
+    println!("contains(rust): {}", filter.contains("rust"));
+    println!("contains(java): {} (true なら偽陽性)", filter.contains("java"));
+    println!("estimated FPR: {:.2e}", filter.estimated_false_positive_rate());
 }
 
-/// キュー (FIFO)
-#[derive(Debug)]
-struct Queue<T> {
-    items: VecDeque<T>,
+/// スキップリスト
+fn demo_skip_list() {
+    println!("\n--- Skip List ---");
+
+    let mut map = SkipListMap::default();
+    for (year, lang) in [(2015, "Rust 1.0"), (1995, "Java"), (2009, "Go"), (1991, "Python")] {
+        map.insert(year, lang);
+    }
+    map.insert(2012, "TypeScript");
+    println!("len: {}, levels: {}, empty: {}", map.len(), map.levels(), map.is_empty());
+    println!("get(2009): {:?}, contains(2000): {}", map.get(&2009), map.contains_key(&2000));
+    println!("all: {:?}", map.iter().collect::<Vec<_>>());
+    println!("2000..: {:?}", map.range(2000..).map(|(_, lang)| *lang).collect::<Vec<_>>());
+    println!("remove(1995): {:?}", map.remove(&1995));
 }
 
-impl<T> Queue<T> {
-    fn new() -> Self {
-        Queue {
-            items: VecDeque::new(),
+/// オープンアドレス法のハッシュマップ
+fn demo_my_hash_map() {
+    println!("\n--- MyHashMap (open addressing) ---");
+
+    let mut map = MyHashMap::default();
+    for word in "the quick brown fox jumps over the lazy dog the end".split(' ') {
+        match map.get_mut(word) {
+            Some(count) => *count += 1,
+            None => {
+                map.insert(word, 1);
+            }
         }
     }
+    println!("len: {}, capacity: {}, empty: {}", map.len(), map.capacity(), map.is_empty());
+    println!("the: {:?}, cat: {:?}", map.get("the"), map.contains_key("cat"));
+    println!("remove(fox): {:?}", map.remove("fox"));
+    let mut counts: Vec<_> = map.iter().collect();
+    counts.sort();
+    println!("counts: {:?}", counts);
+}
 
-    fn enqueue(&mut self, item: T) {
-        self.items.push_back(item);
-    }
+/// B 木
+fn demo_btree() {
+    println!("\n--- B-Tree ---");
 
-    fn dequeue(&mut self) -> Option<T> {
-        self.items.pop_front()
+    let mut small = BTree::with_degree(2);
+    let mut wide = BTree::default();
+    for i in 0..10_000 {
+        small.insert(i, i * i);
+        wide.insert(i, i * i);
     }
-
-    fn front(&self) -> Option<&T> {
-        self.items.front()
+    println!("n = {}: height {} (t = {}), height {} (t = {})", wide.len(), small.height(), small.degree(), wide.height(), wide.degree());
+    println!("get(99): {:?}, contains(10000): {}", wide.get(&99), wide.contains_key(&10_000));
+    for i in 10..10_000 {
+        wide.remove(&i);
     }
+    println!("after remove: {:?} (empty: {})", wide.iter().map(|(key, _)| *key).collect::<Vec<_>>(), wide.is_empty());
+}
+
+/// 区間クエリ (Fenwick 木とセグメント木)
+fn demo_range_query() {
+    println!("\n--- Range Query ---");
+
+    let values = [3, 1, 4, 1, 5, 9, 2, 6];
+    let mut fenwick = FenwickTree::from_slice(&values);
+    let mut min = SegmentTree::min(&values);
+    let sum = SegmentTree::sum(&values);
+    println!("values: {:?}", values);
+    println!("sum(2..6): {} / {}", fenwick.sum(2..6), sum.query(2..6));
+    println!("prefix_sum(4): {}, min(4..): {}", fenwick.prefix_sum(4), min.query(4..));
+
+    fenwick.add(5, -9);
+    min.set(5, 0);
+    println!("values[5] = 0 -> sum(2..6): {}, min(4..): {} (get(5) = {})", fenwick.sum(2..6), min.query(4..), min.get(5));
+    println!("len: {} / {}, empty: {} / {}", fenwick.len(), min.len(), FenwickTree::<i64>::new(0).is_empty(), min.is_empty());
+}
 
-    fn is_empty(&self) -> bool {
-        self.items.is_empty()
+/// ビット集合
+fn demo_bit_set() {
+    println!("\n--- BitSet ---");
+
+    let mut evens = BitSet::new(20);
+    let mut threes = BitSet::new(20);
+    for i in 0..20 {
+        if i % 2 == 0 {
+            evens.set(i);
+        }
+        if i % 3 == 0 {
+            threes.set(i);
+        }
     }
+    evens.clear(0);
+    println!("evens: {:?} ({} bits, capacity {})", evens, evens.count_ones(), evens.capacity());
+    println!("threes: {:?}, test(9): {}", threes, threes.test(9));
+    println!("union: {:?}", evens.union(&threes));
+    println!("intersection: {:?}", evens.intersection(&threes).iter().collect::<Vec<_>>());
+    println!("difference: {:?}", evens.difference(&threes));
+}
 
-    fn len(&self) -> usize {
-        self.items.len()
+/// 行列
+fn demo_matrix() {
+    println!("\n--- Matrix ---");
+
+    let mut a = Matrix::from_vec(2, 3, vec![1, 2, 3, 4, 5, 6]).unwrap();
+    a[(0, 0)] = 0;
+    let b = a.transpose();
+    println!("a ({}x{}):\n{}", a.rows(), a.cols(), a);
+    println!("a^T:\n{}", b);
+    println!("a * a^T:\n{}", a.multiply(&b).unwrap());
+    println!("blocked == naive: {}", a.multiply_blocked(&b, 2) == a.multiply(&b));
+    println!("a * a: {:?}", a.multiply(&a));
+    println!("rows of a: {:?}, row(1): {:?}, (1, 2): {}", a.iter_rows().collect::<Vec<_>>(), a.row(1), a[(1, 2)]);
+    println!("zeros: {:?}", Matrix::<i32>::new(1, 2));
+    println!("from_fn: {:?}", Matrix::from_fn(2, 2, |i, j| i * 2 + j).iter_rows().collect::<Vec<_>>());
+}
+
+/// 疎集合
+fn demo_sparse_set() {
+    println!("\n--- SparseSet ---");
+
+    let mut alive = SparseSet::new(100);
+    for id in [42, 7, 99, 3] {
+        alive.insert(id);
     }
+    println!("dense: {:?} (len {}, capacity {})", alive.as_slice(), alive.len(), alive.capacity());
+    alive.remove(7);
+    println!("remove(7): {:?}, contains(7): {}", alive.iter().collect::<Vec<_>>(), alive.contains(7));
+    alive.clear();
+    println!("clear -> empty: {}, contains(42): {}", alive.is_empty(), alive.contains(42));
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// 文字列アルゴリズム
+fn demo_strings() {
+    println!("\n--- String Algorithms ---");
 
-    #[test]
-    fn test_stack() {
-        let mut stack = Stack::new();
-        assert!(stack.is_empty());
+    let text = "abracadabra";
+    println!("kmp({}, abra): {:?}", text, strings::kmp_search(text, "abra"));
+    println!("rabin-karp({}, a): {:?}", text, strings::rabin_karp_search(text, "a"));
+    println!("lcs(AGGTAB, GXTXAYB): {}", strings::longest_common_subsequence("AGGTAB", "GXTXAYB"));
+    println!("edit_distance(kitten, sitting): {}", strings::edit_distance("kitten", "sitting"));
+}
 
-        stack.push(1);
-        stack.push(2);
-        stack.push(3);
+/// 添字付きヒープ (タイマーの例)
+fn demo_indexed_heap() {
+    println!("\n--- Indexed Heap ---");
 
-        assert_eq!(stack.len(), 3);
-        assert_eq!(stack.peek(), Some(&3));
-        assert_eq!(stack.pop(), Some(3));
-        assert_eq!(stack.pop(), Some(2));
-        assert_eq!(stack.len(), 1);
+    let mut timers = IndexedHeap::default();
+    for (id, deadline_ms) in [("conn-1", 300), ("conn-2", 100), ("conn-3", 200)] {
+        timers.push(id, deadline_ms);
     }
+    println!("next: {:?} (len {})", timers.peek(), timers.len());
+    timers.change_priority(&"conn-2", 500);
+    timers.remove(&"conn-3");
+    println!("conn-2 -> {:?}, contains(conn-3): {}", timers.priority(&"conn-2"), timers.contains(&"conn-3"));
+    while let Some((id, deadline_ms)) = timers.pop() {
+        println!("  timeout {} at {}ms", id, deadline_ms);
+    }
+    println!("empty: {}", timers.is_empty());
+}
 
-    #[test]
-    fn test_queue() {
-        let mut queue = Queue::new();
-        assert!(queue.is_empty());
-
-        queue.enqueue(1);
-        queue.enqueue(2);
-        queue.enqueue(3);
+/// リングバッファの両端キュー
+fn demo_my_deque() {
+    println!("\n--- MyDeque (ring buffer) ---");
 
-        assert_eq!(queue.len(), 3);
-        assert_eq!(queue.front(), Some(&1));
-        assert_eq!(queue.dequeue(), Some(1));
-        assert_eq!(queue.dequeue(), Some(2));
-        assert_eq!(queue.len(), 1);
+    let mut deque = MyDeque::with_capacity(4);
+    for i in 1..=4 {
+        deque.push_back(i);
+    }
+    deque.pop_front();
+    deque.push_back(5);
+    println!("{:?}: slices {:?} (capacity {})", deque, deque.as_slices(), deque.capacity());
+    deque.push_front(0);
+    deque[0] = -1;
+    println!("{:?}: slices {:?} (capacity {})", deque, deque.as_slices(), deque.capacity());
+    println!("front: {:?}, back: {:?}, get(2): {:?}", deque.front(), deque.back(), deque.get(2));
+    if let Some(last) = deque.get_mut(4) {
+        *last *= 10;
     }
+    println!("pop_back: {:?}, len: {}, rev: {:?}", deque.pop_back(), deque.len(), deque.iter().rev().collect::<Vec<_>>());
+    println!("empty: {}", MyDeque::<i32>::default().is_empty());
 }
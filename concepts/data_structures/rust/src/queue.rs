@@ -0,0 +1,77 @@
+//! キュー (FIFO)
+//!
+//! 先に入れたものから取り出す。`Vec` の先頭からの削除は O(n) なので、
+//! 両端とも O(1) の `VecDeque` を包む。
+
+use std::collections::VecDeque;
+
+/// 先入れ先出しのキュー
+#[derive(Debug, Clone, PartialEq)]
+pub struct Queue<T> {
+    items: VecDeque<T>,
+}
+
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Queue<T> {
+    pub fn new() -> Self {
+        Queue {
+            items: VecDeque::new(),
+        }
+    }
+
+    /// 末尾に入れる
+    pub fn enqueue(&mut self, item: T) {
+        self.items.push_back(item);
+    }
+
+    /// 先頭を取り出す
+    pub fn dequeue(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    /// 先頭を取り出さずに見る
+    pub fn front(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        let mut queue = Queue::new();
+        assert!(queue.is_empty());
+
+        queue.enqueue(1);
+        queue.enqueue(2);
+        queue.enqueue(3);
+
+        assert_eq!(queue.len(), 3);
+        assert_eq!(queue.front(), Some(&1));
+        assert_eq!(queue.dequeue(), Some(1));
+        assert_eq!(queue.dequeue(), Some(2));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn test_dequeue_empty() {
+        let mut queue: Queue<i32> = Queue::default();
+        assert_eq!(queue.dequeue(), None);
+        assert_eq!(queue.front(), None);
+    }
+}
@@ -0,0 +1,274 @@
+//! 区間クエリ: Fenwick 木とセグメント木
+//!
+//! 配列の 1 点を書き換えながら、区間の和や最小値を何度も求めたいときに使う。
+//! 素直に配列を持つと更新 O(1) / 問い合わせ O(n)、累積和を持つと逆になるが、
+//! どちらの木も両方を O(log n) にする。
+//!
+//! - Fenwick 木 (Binary Indexed Tree): 添字の最下位ビットで担当区間を決める。
+//!   配列 1 本で済み速いが、引き算で区間を出すので和のような「逆のある」演算専用
+//! - セグメント木: 区間を半分ずつに分けた完全二分木。結合的な演算なら何でもよく、
+//!   最小値・最大値にも使える
+
+use std::ops::{Add, Bound, RangeBounds, Sub};
+
+/// `range` を長さ `len` の配列の [start, end) にする (はみ出したらパニックする)
+fn to_bounds(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end + 1,
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+    assert!(start <= end && end <= len, "range {}..{} out of bounds for length {}", start, end, len);
+    (start, end)
+}
+
+/// 1 点に足し込み、区間の和を求める Fenwick 木
+pub struct FenwickTree<T> {
+    /// 1 始まり。`tree[i]` は (i - lowbit(i), i] の和
+    tree: Vec<T>,
+}
+
+impl<T: Copy + Default + Add<Output = T> + Sub<Output = T>> FenwickTree<T> {
+    /// 全部 0 (`T::default()`) の長さ `len` の木
+    pub fn new(len: usize) -> Self {
+        FenwickTree {
+            tree: vec![T::default(); len + 1],
+        }
+    }
+
+    /// `values` から O(n) で作る
+    pub fn from_slice(values: &[T]) -> Self {
+        let mut tree = vec![T::default()];
+        tree.extend_from_slice(values);
+        // 自分の値を、自分を含む次の区間へ順に渡していく
+        for i in 1..tree.len() {
+            let parent = i + lowbit(i);
+            if parent < tree.len() {
+                tree[parent] = tree[parent] + tree[i];
+            }
+        }
+        FenwickTree { tree }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `index` 番目に `delta` を足す
+    pub fn add(&mut self, index: usize, delta: T) {
+        assert!(index < self.len(), "index {} out of bounds for length {}", index, self.len());
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] = self.tree[i] + delta;
+            i += lowbit(i);
+        }
+    }
+
+    /// [0, end) の和
+    pub fn prefix_sum(&self, end: usize) -> T {
+        assert!(end <= self.len(), "end {} out of bounds for length {}", end, self.len());
+        let mut sum = T::default();
+        let mut i = end;
+        while i > 0 {
+            sum = sum + self.tree[i];
+            i -= lowbit(i);
+        }
+        sum
+    }
+
+    /// `range` の和
+    pub fn sum(&self, range: impl RangeBounds<usize>) -> T {
+        let (start, end) = to_bounds(range, self.len());
+        self.prefix_sum(end) - self.prefix_sum(start)
+    }
+}
+
+/// 一番下の立っているビット
+fn lowbit(i: usize) -> usize {
+    i & i.wrapping_neg()
+}
+
+/// 結合的な演算で区間をまとめるセグメント木
+///
+/// 演算は `combine(a, combine(b, c)) == combine(combine(a, b), c)` を満たし、
+/// `identity` はその単位元であること (和なら 0、最小値なら最大の値)。
+pub struct SegmentTree<T> {
+    /// 1 始まりの完全二分木。葉は `len..2 * len`
+    tree: Vec<T>,
+    len: usize,
+    identity: T,
+    combine: fn(&T, &T) -> T,
+}
+
+impl<T: Clone> SegmentTree<T> {
+    pub fn new(values: &[T], identity: T, combine: fn(&T, &T) -> T) -> Self {
+        let len = values.len();
+        let mut tree = vec![identity.clone(); len];
+        tree.extend_from_slice(values);
+        for i in (1..len).rev() {
+            tree[i] = combine(&tree[2 * i], &tree[2 * i + 1]);
+        }
+        SegmentTree {
+            tree,
+            len,
+            identity,
+            combine,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> &T {
+        assert!(index < self.len, "index {} out of bounds for length {}", index, self.len);
+        &self.tree[self.len + index]
+    }
+
+    /// `index` 番目を `value` にし、根までの値を作り直す
+    pub fn set(&mut self, index: usize, value: T) {
+        assert!(index < self.len, "index {} out of bounds for length {}", index, self.len);
+        let mut i = self.len + index;
+        self.tree[i] = value;
+        while i > 1 {
+            i /= 2;
+            self.tree[i] = (self.combine)(&self.tree[2 * i], &self.tree[2 * i + 1]);
+        }
+    }
+
+    /// `range` の要素を左から順にまとめた値 (空なら単位元)
+    pub fn query(&self, range: impl RangeBounds<usize>) -> T {
+        let (start, end) = to_bounds(range, self.len);
+        let (mut left, mut right) = (start + self.len, end + self.len);
+        // 可換とは限らないので、左端と右端から別々に集めて最後に繋ぐ
+        let mut from_left = self.identity.clone();
+        let mut from_right = self.identity.clone();
+        while left < right {
+            if left % 2 == 1 {
+                from_left = (self.combine)(&from_left, &self.tree[left]);
+                left += 1;
+            }
+            if right % 2 == 1 {
+                right -= 1;
+                from_right = (self.combine)(&self.tree[right], &from_right);
+            }
+            left /= 2;
+            right /= 2;
+        }
+        (self.combine)(&from_left, &from_right)
+    }
+}
+
+impl SegmentTree<i64> {
+    /// 区間の和
+    pub fn sum(values: &[i64]) -> Self {
+        Self::new(values, 0, |a, b| a + b)
+    }
+
+    /// 区間の最小値
+    pub fn min(values: &[i64]) -> Self {
+        Self::new(values, i64::MAX, |a, b| *a.min(b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xorshift(mut state: u64) -> impl FnMut(u64) -> u64 {
+        move |bound| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        }
+    }
+
+    #[test]
+    fn test_fenwick() {
+        let mut tree = FenwickTree::from_slice(&[3, 1, 4, 1, 5, 9, 2, 6]);
+        assert_eq!(tree.len(), 8);
+        assert_eq!(tree.prefix_sum(0), 0);
+        assert_eq!(tree.prefix_sum(8), 31);
+        assert_eq!(tree.sum(2..5), 10);
+        assert_eq!(tree.sum(..=2), 8);
+        assert_eq!(tree.sum(3..3), 0);
+
+        tree.add(4, -5);
+        assert_eq!(tree.sum(2..5), 5);
+        assert_eq!(tree.sum(..), 26);
+
+        let empty = FenwickTree::<i32>::new(0);
+        assert!(empty.is_empty());
+        assert_eq!(empty.sum(..), 0);
+    }
+
+    #[test]
+    fn test_segment_tree() {
+        let values = [5, 2, 8, -1, 7, 3];
+        let mut min = SegmentTree::min(&values);
+        let sum = SegmentTree::sum(&values);
+        assert_eq!(min.query(..), -1);
+        assert_eq!(min.query(0..3), 2);
+        assert_eq!(min.query(4..4), i64::MAX);
+        assert_eq!(sum.query(1..=4), 16);
+
+        min.set(3, 10);
+        assert_eq!(*min.get(3), 10);
+        assert_eq!(min.query(..), 2);
+        assert!(std::panic::catch_unwind(|| sum.query(2..7)).is_err());
+    }
+
+    #[test]
+    fn test_segment_tree_keeps_order_for_non_commutative_ops() {
+        // 文字列の連結は可換でないので、左からの順が崩れると結果が変わる
+        let letters: Vec<String> = "segment".chars().map(String::from).collect();
+        let mut tree = SegmentTree::new(&letters, String::new(), |a, b| format!("{}{}", a, b));
+        assert_eq!(tree.query(..), "segment");
+        assert_eq!(tree.query(1..6), "egmen");
+        tree.set(0, "S".to_string());
+        assert_eq!(tree.query(..3), "Seg");
+        assert!(!tree.is_empty());
+        assert_eq!(tree.len(), 7);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        for len in [1, 2, 7, 64, 100] {
+            let mut random = xorshift(0x1234_5678_9abc_def0 ^ len);
+            let mut values: Vec<i64> = (0..len).map(|_| random(100) as i64 - 50).collect();
+            let mut fenwick = FenwickTree::from_slice(&values);
+            let mut sum = SegmentTree::sum(&values);
+            let mut min = SegmentTree::min(&values);
+
+            for _ in 0..2000 {
+                let index = random(len) as usize;
+                let value = random(100) as i64 - 50;
+                fenwick.add(index, value - values[index]);
+                sum.set(index, value);
+                min.set(index, value);
+                values[index] = value;
+
+                let (a, b) = (random(len + 1) as usize, random(len + 1) as usize);
+                let (start, end) = (a.min(b), a.max(b));
+                let slice = &values[start..end];
+                assert_eq!(fenwick.sum(start..end), slice.iter().sum::<i64>());
+                assert_eq!(sum.query(start..end), slice.iter().sum::<i64>());
+                assert_eq!(min.query(start..end), slice.iter().copied().min().unwrap_or(i64::MAX));
+            }
+        }
+    }
+}
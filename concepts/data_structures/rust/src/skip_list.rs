@@ -0,0 +1,371 @@
+//! スキップリスト
+//!
+//! 整列した連結リストに「急行」の段を重ねたもの。各ノードは確率 1/2 で 1 段ずつ高くなり、
+//! 探索は一番上の段から、行き過ぎる手前で 1 段下りることを繰り返す。期待値で
+//! O(log n) になり、木のような回転や色の付け替えなしで平衡した探索木と同じ計算量を得る。
+//! ただしそれは乱数のおかげで、最悪の場合は O(n) になる。
+//!
+//! ノードは `Vec` に並べ、リンクは添字で持つ (`LruCache` と同じ作り)。
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+
+/// リンクが無いことを表す添字
+const NIL: usize = usize::MAX;
+
+/// 段の数の上限 (2^16 個ほどまでなら十分)
+const MAX_LEVEL: usize = 16;
+
+struct Node<K, V> {
+    key: K,
+    value: V,
+    /// 段ごとの次のノード (長さがこのノードの高さ)
+    next: Vec<usize>,
+}
+
+/// キーの順に並べて持つマップ
+pub struct SkipListMap<K, V> {
+    nodes: Vec<Option<Node<K, V>>>,
+    /// 削除して空いた `nodes` の位置
+    free: Vec<usize>,
+    /// 段ごとの先頭のノード (長さが今の一番高い段)
+    head: Vec<usize>,
+    len: usize,
+    /// 高さを決める乱数 (xorshift) の状態
+    rng: u64,
+}
+
+impl<K: Ord, V> Default for SkipListMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Ord, V> SkipListMap<K, V> {
+    pub fn new() -> Self {
+        SkipListMap {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: Vec::new(),
+            len: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 今の一番高い段の数
+    pub fn levels(&self) -> usize {
+        self.head.len()
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.lower_bound(key);
+        let node = self.node(index)?;
+        (node.key.borrow() == key).then_some(&node.value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// 入れる。同じキーがあれば値を置き換えて古い値を返す
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let preds = self.predecessors(&key);
+        let found = self.next_of(preds[0], 0);
+        if let Some(node) = self.node_mut(found) {
+            if node.key == key {
+                return Some(std::mem::replace(&mut node.value, value));
+            }
+        }
+
+        let height = self.random_height();
+        let next = (0..height).map(|level| self.next_of(preds[level], level)).collect();
+        let node = Node { key, value, next };
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        for (level, &pred) in preds.iter().enumerate().take(height) {
+            // 今より高い段は先頭から直接つなぐ
+            self.set_next(pred, level, index);
+        }
+        self.len += 1;
+        None
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let preds = self.predecessors(key);
+        let index = self.next_of(preds[0], 0);
+        if self.node(index)?.key.borrow() != key {
+            return None;
+        }
+        let node = self.nodes[index].take().unwrap();
+        for (level, &next) in node.next.iter().enumerate() {
+            self.set_next(preds[level], level, next);
+        }
+        // 空になった上の段は畳む
+        while self.head.last() == Some(&NIL) {
+            self.head.pop();
+        }
+        self.free.push(index);
+        self.len -= 1;
+        Some(node.value)
+    }
+
+    /// キーの順に辿る
+    pub fn iter(&self) -> Range<'_, K, V> {
+        self.range::<K, _>(..)
+    }
+
+    /// `range` に入るキーを順に辿る
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.lower_bound(key),
+            Bound::Excluded(key) => self.upper_bound(key),
+            Bound::Unbounded => self.head.first().copied().unwrap_or(NIL),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.upper_bound(key),
+            Bound::Excluded(key) => self.lower_bound(key),
+            Bound::Unbounded => NIL,
+        };
+        // 始まりが終わりより後ろなら空
+        let end_before_start = match (self.node(start), self.node(end)) {
+            (Some(start), Some(end)) => start.key > end.key,
+            _ => false,
+        };
+        Range {
+            map: self,
+            current: if end_before_start { NIL } else { start },
+            end,
+        }
+    }
+
+    /// `key` 以上の最初のノード
+    fn lower_bound<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.first_where(|node_key| node_key.borrow().cmp(key) != Ordering::Less)
+    }
+
+    /// `key` より大きい最初のノード
+    fn upper_bound<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.first_where(|node_key| node_key.borrow().cmp(key) == Ordering::Greater)
+    }
+
+    /// `found` が true になる最初のノード (キーの順に false, ..., true, ... と並ぶこと)
+    fn first_where(&self, found: impl Fn(&K) -> bool) -> usize {
+        let mut current = None;
+        for level in (0..self.head.len()).rev() {
+            loop {
+                let next = self.next_of(current, level);
+                match self.node(next) {
+                    Some(node) if !found(&node.key) => current = Some(next),
+                    _ => break,
+                }
+            }
+        }
+        self.next_of(current, 0)
+    }
+
+    /// 段ごとの、`key` より小さい最後のノード (None は先頭。まだ無い段も先頭)
+    fn predecessors<Q>(&self, key: &Q) -> Vec<Option<usize>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut preds = vec![None; MAX_LEVEL];
+        let mut current = None;
+        for level in (0..self.head.len()).rev() {
+            loop {
+                let next = self.next_of(current, level);
+                match self.node(next) {
+                    Some(node) if node.key.borrow() < key => current = Some(next),
+                    _ => break,
+                }
+            }
+            preds[level] = current;
+        }
+        preds
+    }
+
+    fn node(&self, index: usize) -> Option<&Node<K, V>> {
+        self.nodes.get(index)?.as_ref()
+    }
+
+    fn node_mut(&mut self, index: usize) -> Option<&mut Node<K, V>> {
+        self.nodes.get_mut(index)?.as_mut()
+    }
+
+    /// `from` (None は先頭) の `level` 段目の次
+    fn next_of(&self, from: Option<usize>, level: usize) -> usize {
+        match from {
+            None => self.head.get(level).copied().unwrap_or(NIL),
+            Some(index) => self.nodes[index].as_ref().unwrap().next[level],
+        }
+    }
+
+    fn set_next(&mut self, from: Option<usize>, level: usize, to: usize) {
+        match from {
+            None if level == self.head.len() => self.head.push(to),
+            None => self.head[level] = to,
+            Some(index) => self.nodes[index].as_mut().unwrap().next[level] = to,
+        }
+    }
+
+    /// 1 段目から始めて、確率 1/2 で 1 段ずつ高くする
+    fn random_height(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        let height = self.rng.trailing_ones() as usize + 1;
+        height.min(MAX_LEVEL).min(self.head.len() + 1)
+    }
+}
+
+/// キーの順に辿るイテレータ
+pub struct Range<'a, K, V> {
+    map: &'a SkipListMap<K, V>,
+    current: usize,
+    /// ここに来たら止まる (範囲の外の最初のノード)
+    end: usize,
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current == self.end {
+            return None;
+        }
+        let node = self.map.nodes.get(self.current)?.as_ref()?;
+        self.current = node.next[0];
+        Some((&node.key, &node.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map = SkipListMap::new();
+        assert!(map.is_empty());
+        for (i, word) in ["delta", "alpha", "echo", "charlie", "bravo"].into_iter().enumerate() {
+            assert_eq!(map.insert(word.to_string(), i), None);
+        }
+        assert_eq!(map.len(), 5);
+        assert_eq!(map.get("charlie"), Some(&3));
+        assert_eq!(map.insert("charlie".to_string(), 30), Some(3));
+        assert_eq!(map.get("charlie"), Some(&30));
+        assert!(!map.contains_key("foxtrot"));
+
+        let keys: Vec<_> = map.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["alpha", "bravo", "charlie", "delta", "echo"]);
+
+        assert_eq!(map.remove("alpha"), Some(1));
+        assert_eq!(map.remove("alpha"), None);
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.iter().next().map(|(key, _)| key.as_str()), Some("bravo"));
+    }
+
+    #[test]
+    fn test_range() {
+        let mut map = SkipListMap::new();
+        for i in (0..20).step_by(2) {
+            map.insert(i, i * 10);
+        }
+        let keys = |range: Range<'_, i32, i32>| range.map(|(key, _)| *key).collect::<Vec<_>>();
+        assert_eq!(keys(map.range(3..9)), [4, 6, 8]);
+        assert_eq!(keys(map.range(4..=8)), [4, 6, 8]);
+        assert_eq!(keys(map.range((Bound::Excluded(4), Bound::Unbounded))), [6, 8, 10, 12, 14, 16, 18]);
+        assert_eq!(keys(map.range(..3)), [0, 2]);
+        assert_eq!(keys(map.range(100..)), Vec::<i32>::new());
+        assert_eq!(keys(map.range(5..5)), Vec::<i32>::new());
+        assert_eq!(keys(map.range((Bound::Excluded(6), Bound::Excluded(5)))), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_levels_stay_logarithmic() {
+        let mut map = SkipListMap::new();
+        for i in 0..10_000 {
+            map.insert(i, ());
+        }
+        // 1 万個なら期待値で 14 段ほど
+        assert!((8..=MAX_LEVEL).contains(&map.levels()), "levels {}", map.levels());
+        for i in 0..10_000 {
+            map.remove(&i);
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.levels(), 0);
+    }
+
+    #[test]
+    fn test_matches_btreemap_model() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut map = SkipListMap::new();
+        let mut model = BTreeMap::new();
+        for step in 0..20_000 {
+            let key = random(500);
+            match random(4) {
+                0 | 1 => assert_eq!(map.insert(key, step), model.insert(key, step)),
+                2 => assert_eq!(map.remove(&key), model.remove(&key)),
+                _ => assert_eq!(map.get(&key), model.get(&key)),
+            }
+            assert_eq!(map.len(), model.len());
+            if step % 1000 == 0 {
+                let (low, high) = (random(500), random(500));
+                let (low, high) = (low.min(high), low.max(high));
+                assert!(map.range(low..high).eq(model.range(low..high)));
+                assert!(map.iter().eq(model.iter()));
+            }
+        }
+        assert!(map.iter().eq(model.iter()));
+    }
+}
@@ -0,0 +1,159 @@
+//! 疎集合 (Sparse Set)
+//!
+//! 0..capacity の小さな整数 ID の集合を、2 本の配列で持つ:
+//!
+//! - `dense`: 入っている ID を詰めて並べたもの (辿るのはここだけ)
+//! - `sparse`: ID から `dense` の中の位置を引く表
+//!
+//! `sparse[id]` は消した後の古い値が残っていてもよく、`dense` 側と指し合っているかで
+//! 入っているかを判定する。そのため挿入・削除・判定がハッシュ無しの O(1) で、
+//! 全消去も `dense` を空にするだけで済む。辿る時間は容量でなく要素数に比例する
+//! (`BitSet` は容量に比例する)。`cargo bench --bench sparse_set` で `HashSet` と比べられる。
+
+/// 0..capacity の整数の集合
+pub struct SparseSet {
+    dense: Vec<usize>,
+    sparse: Vec<usize>,
+}
+
+impl SparseSet {
+    /// 空の集合 (`capacity` 未満の ID を入れられる)
+    pub fn new(capacity: usize) -> Self {
+        SparseSet {
+            dense: Vec::new(),
+            sparse: vec![0; capacity],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.sparse.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        // 範囲外の ID は入っていないだけなので、パニックしない
+        match self.sparse.get(id) {
+            Some(&index) => self.dense.get(index) == Some(&id),
+            None => false,
+        }
+    }
+
+    /// 入れる。もともと無ければ true
+    pub fn insert(&mut self, id: usize) -> bool {
+        assert!(id < self.capacity(), "id {} out of bounds for capacity {}", id, self.capacity());
+        if self.contains(id) {
+            return false;
+        }
+        self.sparse[id] = self.dense.len();
+        self.dense.push(id);
+        true
+    }
+
+    /// 取り除く。入っていれば true
+    ///
+    /// 最後の要素を空いた位置へ移すので、`iter` の順番は変わる。
+    pub fn remove(&mut self, id: usize) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        let index = self.sparse[id];
+        self.dense.swap_remove(index);
+        if let Some(&moved) = self.dense.get(index) {
+            self.sparse[moved] = index;
+        }
+        true
+    }
+
+    /// 全部取り除く (`sparse` は触らないので O(1))
+    pub fn clear(&mut self) {
+        self.dense.clear();
+    }
+
+    /// 入っている ID (順番は決まっていない)
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.dense.iter().copied()
+    }
+
+    /// 入っている ID を詰めた配列
+    pub fn as_slice(&self) -> &[usize] {
+        &self.dense
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_insert_remove_contains() {
+        let mut set = SparseSet::new(10);
+        assert!(set.is_empty());
+        assert!(set.insert(3));
+        assert!(set.insert(7));
+        assert!(set.insert(0));
+        assert!(!set.insert(7));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(7));
+        assert!(!set.contains(4));
+        assert!(!set.contains(100));
+
+        // 7 を消すと最後の 0 がその位置に来る
+        assert!(set.remove(7));
+        assert!(!set.remove(7));
+        assert_eq!(set.as_slice(), &[3, 0]);
+        assert!(set.contains(0));
+        assert!(std::panic::catch_unwind(|| SparseSet::new(10).insert(10)).is_err());
+    }
+
+    #[test]
+    fn test_clear_leaves_stale_entries_harmless() {
+        let mut set = SparseSet::new(5);
+        for id in 0..5 {
+            set.insert(id);
+        }
+        set.clear();
+        assert!(set.is_empty());
+        // sparse には古い位置が残っているが、dense と指し合わないので入っていない扱い
+        assert!((0..5).all(|id| !set.contains(id)));
+        set.insert(4);
+        assert!(set.contains(4));
+        assert!(!set.contains(0));
+        assert_eq!(set.capacity(), 5);
+    }
+
+    #[test]
+    fn test_matches_hash_set() {
+        let mut state = 0x2f6b_1d0a_c3e5_9847u64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+
+        let mut set = SparseSet::new(200);
+        let mut model = HashSet::new();
+        for step in 0..20_000 {
+            let id = random(200) as usize;
+            match random(3) {
+                0 => assert_eq!(set.insert(id), model.insert(id)),
+                1 => assert_eq!(set.remove(id), model.remove(&id)),
+                _ => assert_eq!(set.contains(id), model.contains(&id)),
+            }
+            if step % 5000 == 4999 {
+                set.clear();
+                model.clear();
+            }
+            assert_eq!(set.len(), model.len());
+        }
+        assert_eq!(set.iter().collect::<HashSet<_>>(), model);
+    }
+}
@@ -0,0 +1,73 @@
+//! スタック (LIFO)
+//!
+//! 最後に積んだものから取り出す。`Vec` の末尾への追加と削除がどちらも O(1) なので、
+//! `Vec` をそのまま包む。
+
+/// 後入れ先出しのスタック
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stack<T> {
+    items: Vec<T>,
+}
+
+impl<T> Default for Stack<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Stack<T> {
+    pub fn new() -> Self {
+        Stack { items: Vec::new() }
+    }
+
+    /// 一番上に積む
+    pub fn push(&mut self, item: T) {
+        self.items.push(item);
+    }
+
+    /// 一番上を取り出す
+    pub fn pop(&mut self) -> Option<T> {
+        self.items.pop()
+    }
+
+    /// 一番上を取り出さずに見る
+    pub fn peek(&self) -> Option<&T> {
+        self.items.last()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack() {
+        let mut stack = Stack::new();
+        assert!(stack.is_empty());
+
+        stack.push(1);
+        stack.push(2);
+        stack.push(3);
+
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek(), Some(&3));
+        assert_eq!(stack.pop(), Some(3));
+        assert_eq!(stack.pop(), Some(2));
+        assert_eq!(stack.len(), 1);
+    }
+
+    #[test]
+    fn test_pop_empty() {
+        let mut stack: Stack<i32> = Stack::default();
+        assert_eq!(stack.pop(), None);
+        assert_eq!(stack.peek(), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! 文字列アルゴリズム
+//!
+//! - 部分文字列の検索: 素直に比べると O(nm) だが、KMP 法は比べ終えた部分を
+//!   使い回して O(n + m)、Rabin-Karp 法は窓のハッシュ値を O(1) でずらして比べる
+//! - 最長共通部分列 (LCS) と編集距離: どちらも 2 つの文字列の接頭辞同士の表を埋める動的計画法
+//!
+//! 検索はバイト単位で、見つけた位置は `str::find` と同じくバイトの位置で返す。
+//! LCS と編集距離は `char` 単位。
+
+/// KMP 法で `pattern` の現れる位置をすべて返す (重なりも数える)
+pub fn kmp_search(text: &str, pattern: &str) -> Vec<usize> {
+    let (text, pattern) = (text.as_bytes(), pattern.as_bytes());
+    if pattern.is_empty() {
+        return (0..=text.len()).collect();
+    }
+    let failure = failure_function(pattern);
+    let mut found = Vec::new();
+    // 今までに一致しているパターンの長さ
+    let mut matched = 0;
+    for (i, &byte) in text.iter().enumerate() {
+        while matched > 0 && pattern[matched] != byte {
+            matched = failure[matched - 1];
+        }
+        if pattern[matched] == byte {
+            matched += 1;
+        }
+        if matched == pattern.len() {
+            found.push(i + 1 - matched);
+            matched = failure[matched - 1];
+        }
+    }
+    found
+}
+
+/// `failure[i]`: `pattern[..=i]` の、自分自身ではない接頭辞で接尾辞でもあるものの最長の長さ
+///
+/// 食い違ったとき、パターンをどこまで戻せばよいかを表す。
+fn failure_function(pattern: &[u8]) -> Vec<usize> {
+    let mut failure = vec![0; pattern.len()];
+    let mut length = 0;
+    for i in 1..pattern.len() {
+        while length > 0 && pattern[i] != pattern[length] {
+            length = failure[length - 1];
+        }
+        if pattern[i] == pattern[length] {
+            length += 1;
+        }
+        failure[i] = length;
+    }
+    failure
+}
+
+/// ハッシュの基数と法 (法は素数)
+const BASE: u64 = 256;
+const MODULUS: u64 = 1_000_000_007;
+
+/// Rabin-Karp 法で `pattern` の現れる位置をすべて返す (重なりも数える)
+///
+/// 窓を 1 つずらすときは、出ていく文字の分を引いて、入ってくる文字の分を足すだけでよい
+/// (ローリングハッシュ)。ハッシュ値が一致したら衝突でないかを実際に比べて確かめる。
+pub fn rabin_karp_search(text: &str, pattern: &str) -> Vec<usize> {
+    let (text, pattern) = (text.as_bytes(), pattern.as_bytes());
+    let m = pattern.len();
+    if m == 0 {
+        return (0..=text.len()).collect();
+    }
+    if m > text.len() {
+        return Vec::new();
+    }
+
+    let hash = |bytes: &[u8]| bytes.iter().fold(0, |hash, &byte| (hash * BASE + byte as u64) % MODULUS);
+    // 窓の先頭の文字の重み BASE^(m - 1)
+    let leading = (1..m).fold(1, |power, _| power * BASE % MODULUS);
+    let target = hash(pattern);
+    let mut window = hash(&text[..m]);
+
+    let mut found = Vec::new();
+    for start in 0..=text.len() - m {
+        if window == target && &text[start..start + m] == pattern {
+            found.push(start);
+        }
+        if start + m < text.len() {
+            let outgoing = text[start] as u64 * leading % MODULUS;
+            window = ((window + MODULUS - outgoing) * BASE + text[start + m] as u64) % MODULUS;
+        }
+    }
+    found
+}
+
+/// 最長共通部分列 (順番を保って両方から拾える最長の文字の並び) の 1 つ
+pub fn longest_common_subsequence(a: &str, b: &str) -> String {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // table[i][j]: a[i..] と b[j..] の LCS の長さ
+    let mut table = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            table[i][j] = match a[i] == b[j] {
+                true => table[i + 1][j + 1] + 1,
+                false => table[i + 1][j].max(table[i][j + 1]),
+            };
+        }
+    }
+
+    // 表を先頭から辿って文字を拾う
+    let mut lcs = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            lcs.push(a[i]);
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    lcs
+}
+
+/// 編集距離 (1 文字の挿入・削除・置換で `a` を `b` にする最小の回数)
+///
+/// 表は 1 行ずつしか使わないので、2 行分だけ持つ。
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // previous[j]: a のここまでの接頭辞と b[..j] の距離
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(ca != cb);
+            let delete = previous[j + 1] + 1;
+            let insert = current[j] + 1;
+            current[j + 1] = replace.min(delete).min(insert);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 素直に全部の位置で比べる
+    fn naive_search(text: &str, pattern: &str) -> Vec<usize> {
+        let (text, pattern) = (text.as_bytes(), pattern.as_bytes());
+        (0..=text.len().saturating_sub(pattern.len()))
+            .filter(|&i| text.len() >= pattern.len() && &text[i..i + pattern.len()] == pattern)
+            .collect()
+    }
+
+    #[test]
+    fn test_search() {
+        for search in [kmp_search, rabin_karp_search] {
+            assert_eq!(search("abracadabra", "abra"), vec![0, 7]);
+            assert_eq!(search("aaaa", "aa"), vec![0, 1, 2]);
+            assert_eq!(search("hello", "xyz"), Vec::<usize>::new());
+            assert_eq!(search("ab", "abc"), Vec::<usize>::new());
+            assert_eq!(search("ab", ""), vec![0, 1, 2]);
+            // 位置はバイト単位
+            assert_eq!(search("すもももももも", "もも"), vec![3, 6, 9, 12, 15]);
+        }
+        assert_eq!(failure_function(b"abacabab"), vec![0, 0, 1, 0, 1, 2, 3, 2]);
+    }
+
+    #[test]
+    fn test_search_matches_naive() {
+        let mut state = 0x6a09_e667_f3bc_c909u64;
+        let mut random = move |bound: u64| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state % bound
+        };
+        // 2 文字だけの文字列なら一致も部分的な一致もたくさん起きる
+        let mut random_string = |len: u64| -> String { (0..random(len) + 1).map(|_| if random(2) == 0 { 'a' } else { 'b' }).collect() };
+        for _ in 0..500 {
+            let text = random_string(60);
+            let pattern = random_string(5);
+            let expected = naive_search(&text, &pattern);
+            assert_eq!(kmp_search(&text, &pattern), expected, "{} / {}", text, pattern);
+            assert_eq!(rabin_karp_search(&text, &pattern), expected, "{} / {}", text, pattern);
+        }
+    }
+
+    #[test]
+    fn test_longest_common_subsequence() {
+        assert_eq!(longest_common_subsequence("ABCBDAB", "BDCABA").chars().count(), 4);
+        assert_eq!(longest_common_subsequence("AGGTAB", "GXTXAYB"), "GTAB");
+        assert_eq!(longest_common_subsequence("abc", "def"), "");
+        assert_eq!(longest_common_subsequence("", "abc"), "");
+        assert_eq!(longest_common_subsequence("いろはにほへと", "いはほと"), "いはほと");
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("flaw", "lawn"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+        assert_eq!(edit_distance("東京", "京都"), 2);
+        // 距離は対称
+        assert_eq!(edit_distance("sunday", "saturday"), edit_distance("saturday", "sunday"));
+    }
+}
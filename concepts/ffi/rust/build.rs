@@ -0,0 +1,34 @@
+//! `c/geometry.c` をコンパイルして静的ライブラリにし、このクレートにリンクする
+//!
+//! 普通は `cc` クレートを使うが、このリポジトリは依存を増やさない方針なので、
+//! C コンパイラ (`$CC`、無ければ `cc`) と `ar` を直接呼ぶ。
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn run(command: &mut Command) {
+    let status = command
+        .status()
+        .unwrap_or_else(|e| panic!("failed to run {:?}: {}", command, e));
+    assert!(status.success(), "{:?} exited with {}", command, status);
+}
+
+fn main() {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let object = out_dir.join("geometry.o");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+
+    run(Command::new(&compiler)
+        .args(["-c", "-fPIC", "-O2", "-Wall", "-Wextra", "-Werror", "-Iinclude"])
+        .arg("c/geometry.c")
+        .arg("-o")
+        .arg(&object));
+    run(Command::new("ar").arg("rcs").arg(out_dir.join("libgeometry.a")).arg(&object));
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=geometry");
+    println!("cargo:rerun-if-changed=c/geometry.c");
+    println!("cargo:rerun-if-changed=include/ffi_concepts.h");
+    println!("cargo:rerun-if-env-changed=CC");
+}
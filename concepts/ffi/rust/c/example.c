@@ -0,0 +1,23 @@
+/*
+ * example.c - C から Rust の共有ライブラリを使う
+ *
+ *   cargo build
+ *   cc c/example.c -Iinclude -Ltarget/debug -lffi_concepts -o target/example
+ *   LD_LIBRARY_PATH=target/debug ./target/example
+ */
+#include <stdio.h>
+
+#include "ffi_concepts.h"
+
+int main(void) {
+    printf("rust_add(2, 3) = %d\n", rust_add(2, 3));
+
+    Rect rect = {{1.0, 2.0}, 3.0, 4.0};
+    Rect scaled = rust_rect_scale(rect, 2.0);
+    printf("scaled: %.1f x %.1f\n", scaled.width, scaled.height);
+
+    char *greeting = rust_greet("C");
+    printf("%s\n", greeting);
+    rust_free_string(greeting);
+    return 0;
+}
@@ -0,0 +1,63 @@
+/*
+ * geometry.c - Rust から呼ぶ C の関数
+ *
+ * build.rs が静的ライブラリにしてリンクする。c_call_rust_* は逆に Rust の関数を呼ぶ。
+ */
+#include <ctype.h>
+#include <string.h>
+
+#include "ffi_concepts.h"
+
+/* ポインタで受け取る */
+double rect_area(const Rect *rect) {
+    return rect->width * rect->height;
+}
+
+/* 値で受け取って値で返す */
+Point rect_center(Rect rect) {
+    Point center = {rect.origin.x + rect.width / 2, rect.origin.y + rect.height / 2};
+    return center;
+}
+
+/* 呼び出し側のバッファを書き換え、長さを返す */
+size_t c_upper(char *s) {
+    size_t len = 0;
+    for (; s[len] != '\0'; len++) {
+        s[len] = (char)toupper((unsigned char)s[len]);
+    }
+    return len;
+}
+
+/* 関数ポインタ (コールバック) で各要素を変換して足す */
+int32_t c_sum_with(const int32_t *values, size_t len, int32_t (*map)(int32_t)) {
+    int32_t sum = 0;
+    for (size_t i = 0; i < len; i++) {
+        sum += map(values[i]);
+    }
+    return sum;
+}
+
+/* C が持っている静的な文字列 (解放しない) */
+const char *c_version(void) {
+    return "geometry 1.0";
+}
+
+/* C から Rust を呼ぶ */
+int32_t c_call_rust_add(int32_t a, int32_t b) {
+    return rust_add(a, b);
+}
+
+/* Rust が確保した文字列を out にコピーし、Rust に解放させる。失敗したら -1 */
+int c_call_rust_greet(const char *name, char *out, size_t capacity) {
+    char *greeting = rust_greet(name);
+    if (greeting == NULL) {
+        return -1;
+    }
+    size_t len = strlen(greeting);
+    int ok = len < capacity;
+    if (ok) {
+        memcpy(out, greeting, len + 1);
+    }
+    rust_free_string(greeting);
+    return ok ? (int)len : -1;
+}
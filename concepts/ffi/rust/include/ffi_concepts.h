@@ -0,0 +1,30 @@
+/*
+ * ffi_concepts.h - Rust と C で共有する型と、Rust 側が公開する関数
+ *
+ * 構造体は Rust 側の #[repr(C)] と同じ並びにしておくこと。
+ */
+#ifndef FFI_CONCEPTS_H
+#define FFI_CONCEPTS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef struct {
+    double x;
+    double y;
+} Point;
+
+typedef struct {
+    Point origin;
+    double width;
+    double height;
+} Rect;
+
+/* Rust 側が公開する関数 (libffi_concepts.so) */
+int32_t rust_add(int32_t a, int32_t b);
+Rect rust_rect_scale(Rect rect, double factor);
+/* 戻り値は rust_free_string で解放する。name が UTF-8 でなければ NULL */
+char *rust_greet(const char *name);
+void rust_free_string(char *s);
+
+#endif
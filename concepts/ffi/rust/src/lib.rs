@@ -0,0 +1,262 @@
+//! # Rust の FFI (C との相互運用)
+//!
+//! - `extern "C"` ブロック: C の関数を宣言して呼ぶ (呼び出しは `unsafe`)
+//! - `#[repr(C)]`: 構造体のフィールドを C と同じ順番・配置にする
+//! - `CString` / `CStr`: Rust の文字列 (長さ付き・UTF-8) と C の文字列 (NUL 終端) の変換
+//! - `#[no_mangle] extern "C" fn`: Rust の関数を C から呼べるように公開する
+//!
+//! C の側は `c/geometry.c` (build.rs が静的ライブラリにしてリンクする) と
+//! `include/ffi_concepts.h`。`unsafe` は下の安全なラッパーの中に閉じ込め、
+//! 外からは普通の Rust の関数として使えるようにしている。
+
+use std::ffi::{c_char, c_int, CStr, CString};
+
+/// 2 次元の点 (C の `Point` と同じ配置)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// 長方形 (C の `Rect` と同じ配置)
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub origin: Point,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// `c/geometry.c` の関数
+mod sys {
+    use super::*;
+
+    extern "C" {
+        pub fn rect_area(rect: *const Rect) -> f64;
+        pub fn rect_center(rect: Rect) -> Point;
+        pub fn c_upper(s: *mut c_char) -> usize;
+        pub fn c_sum_with(values: *const i32, len: usize, map: extern "C" fn(i32) -> i32) -> i32;
+        pub fn c_version() -> *const c_char;
+        pub fn c_call_rust_add(a: i32, b: i32) -> i32;
+        pub fn c_call_rust_greet(name: *const c_char, out: *mut c_char, capacity: usize) -> c_int;
+    }
+}
+
+// ============================================================
+// C の関数を呼ぶ (安全なラッパー)
+// ============================================================
+
+pub fn area(rect: &Rect) -> f64 {
+    // SAFETY: 参照なので有効なポインタ。C 側は読むだけ
+    unsafe { sys::rect_area(rect) }
+}
+
+pub fn center(rect: Rect) -> Point {
+    // SAFETY: 値渡しで、#[repr(C)] なので C と同じ配置
+    unsafe { sys::rect_center(rect) }
+}
+
+/// C の関数で大文字にする (途中に NUL を含む文字列は C に渡せない)
+pub fn to_upper(s: &str) -> Result<String, String> {
+    let mut bytes = CString::new(s).map_err(|e| e.to_string())?.into_bytes_with_nul();
+    // SAFETY: NUL 終端のバッファで、C 側は NUL までしか書き換えない
+    let len = unsafe { sys::c_upper(bytes.as_mut_ptr().cast()) };
+    bytes.truncate(len);
+    // toupper は ASCII しか変えないので UTF-8 のまま
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// `map` で変換した合計を C に計算させる (コールバックは `extern "C" fn`)
+pub fn sum_with(values: &[i32], map: extern "C" fn(i32) -> i32) -> i32 {
+    // SAFETY: ポインタと長さはスライスから作るので一致している
+    unsafe { sys::c_sum_with(values.as_ptr(), values.len(), map) }
+}
+
+/// C の静的な文字列を借りる (`'static` なのは C 側がずっと持っているから)
+pub fn c_version() -> &'static str {
+    // SAFETY: C の文字列リテラルで、NUL 終端かつ解放されない
+    unsafe { CStr::from_ptr(sys::c_version()) }.to_str().unwrap()
+}
+
+/// C を経由して Rust の [`rust_add`] を呼ぶ
+pub fn add_via_c(a: i32, b: i32) -> i32 {
+    // SAFETY: 整数を渡すだけ
+    unsafe { sys::c_call_rust_add(a, b) }
+}
+
+/// C を経由して [`rust_greet`] と [`rust_free_string`] を呼ぶ
+pub fn greet_via_c(name: &str) -> Result<String, String> {
+    let name = CString::new(name).map_err(|e| e.to_string())?;
+    let mut out = vec![0u8; 256];
+    // SAFETY: name は NUL 終端、out は capacity バイトのバッファ
+    let len = unsafe { sys::c_call_rust_greet(name.as_ptr(), out.as_mut_ptr().cast(), out.len()) };
+    if len < 0 {
+        return Err("greeting failed".to_string());
+    }
+    out.truncate(len as usize);
+    String::from_utf8(out).map_err(|e| e.to_string())
+}
+
+// ============================================================
+// libc を呼ぶ
+// ============================================================
+
+/// `strlen` (NUL までのバイト数)
+// Rust では `CStr::count_bytes` で足りるが、libc を呼ぶ例としてそのまま使う
+#[allow(clippy::strlen_on_c_strings)]
+pub fn c_strlen(s: &CStr) -> usize {
+    // SAFETY: CStr は NUL 終端が保証されている
+    unsafe { libc::strlen(s.as_ptr()) }
+}
+
+extern "C" fn compare_i32(a: *const libc::c_void, b: *const libc::c_void) -> c_int {
+    // SAFETY: qsort は配列の要素へのポインタを渡してくる
+    let (a, b) = unsafe { (*(a as *const i32), *(b as *const i32)) };
+    a.cmp(&b) as c_int
+}
+
+/// libc の `qsort` で並べる (比較関数を Rust で渡す)
+pub fn c_qsort(values: &mut [i32]) {
+    // SAFETY: 要素の数と大きさはスライスのもの。compare_i32 は i32 として読む
+    unsafe {
+        libc::qsort(
+            values.as_mut_ptr().cast(),
+            values.len(),
+            std::mem::size_of::<i32>(),
+            Some(compare_i32),
+        )
+    }
+}
+
+/// 可変長引数の `snprintf` で `"<label>=<value>"` を作る
+pub fn c_format(label: &str, value: i32) -> Result<String, String> {
+    let label = CString::new(label).map_err(|e| e.to_string())?;
+    let mut buf = vec![0u8; 64];
+    // SAFETY: 書式と引数の型が合っている (%s に C 文字列、%d に c_int)。buf の大きさを渡す
+    let written = unsafe {
+        libc::snprintf(buf.as_mut_ptr().cast(), buf.len(), c"%s=%d".as_ptr(), label.as_ptr(), value as c_int)
+    };
+    if written < 0 || written as usize >= buf.len() {
+        return Err("snprintf failed or truncated".to_string());
+    }
+    buf.truncate(written as usize);
+    String::from_utf8(buf).map_err(|e| e.to_string())
+}
+
+// ============================================================
+// Rust の関数を C に公開する (include/ffi_concepts.h)
+// ============================================================
+
+/// 溢れたら折り返す (C に panic を伝えることはできないので、panic しないようにする)
+#[no_mangle]
+pub extern "C" fn rust_add(a: i32, b: i32) -> i32 {
+    a.wrapping_add(b)
+}
+
+#[no_mangle]
+pub extern "C" fn rust_rect_scale(rect: Rect, factor: f64) -> Rect {
+    Rect {
+        width: rect.width * factor,
+        height: rect.height * factor,
+        ..rect
+    }
+}
+
+/// `"Hello, <name>!"` を Rust で確保して返す (UTF-8 でなければ NULL)
+///
+/// # Safety
+///
+/// `name` は NUL 終端の文字列を指すこと。戻り値は [`rust_free_string`] で解放すること
+/// (C の `free` で解放してはいけない。確保したアロケータが違う)。
+#[no_mangle]
+pub unsafe extern "C" fn rust_greet(name: *const c_char) -> *mut c_char {
+    if name.is_null() {
+        return std::ptr::null_mut();
+    }
+    // SAFETY: 呼び出し側が NUL 終端を保証する
+    let Ok(name) = (unsafe { CStr::from_ptr(name) }).to_str() else {
+        return std::ptr::null_mut();
+    };
+    // 所有権を C に渡す (Rust 側では解放しない)
+    CString::new(format!("Hello, {}!", name)).unwrap().into_raw()
+}
+
+/// [`rust_greet`] の戻り値を解放する (NULL なら何もしない)
+///
+/// # Safety
+///
+/// `s` は [`rust_greet`] が返したポインタで、まだ解放していないこと。
+#[no_mangle]
+pub unsafe extern "C" fn rust_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        // SAFETY: into_raw で渡したものを取り戻して落とす
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern "C" fn square(x: i32) -> i32 {
+        x * x
+    }
+
+    #[test]
+    fn test_call_c() {
+        let rect = Rect {
+            origin: Point { x: 1.0, y: 2.0 },
+            width: 4.0,
+            height: 6.0,
+        };
+        assert_eq!(area(&rect), 24.0);
+        assert_eq!(center(rect), Point { x: 3.0, y: 5.0 });
+        assert_eq!(to_upper("hello, ffi"), Ok("HELLO, FFI".to_string()));
+        assert_eq!(to_upper("héllo"), Ok("HéLLO".to_string()));
+        assert!(to_upper("nul\0inside").is_err());
+        assert_eq!(sum_with(&[1, 2, 3], square), 14);
+        assert_eq!(sum_with(&[], square), 0);
+        assert_eq!(c_version(), "geometry 1.0");
+        // 配置が C と同じ
+        assert_eq!(std::mem::size_of::<Rect>(), 32);
+    }
+
+    #[test]
+    fn test_round_trip_through_c() {
+        assert_eq!(add_via_c(2, 3), 5);
+        assert_eq!(add_via_c(i32::MAX, 1), i32::MIN);
+        assert_eq!(greet_via_c("C"), Ok("Hello, C!".to_string()));
+        assert_eq!(greet_via_c("世界"), Ok("Hello, 世界!".to_string()));
+
+        let scaled = rust_rect_scale(
+            Rect {
+                origin: Point { x: 1.0, y: 1.0 },
+                width: 2.0,
+                height: 3.0,
+            },
+            2.0,
+        );
+        assert_eq!((scaled.origin, scaled.width, scaled.height), (Point { x: 1.0, y: 1.0 }, 4.0, 6.0));
+        // UTF-8 でない名前と NULL は NULL を返す
+        unsafe {
+            assert!(rust_greet(c"\xff".as_ptr()).is_null());
+            assert!(rust_greet(std::ptr::null()).is_null());
+            rust_free_string(std::ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_libc() {
+        assert_eq!(c_strlen(c"hello"), 5);
+        assert_eq!(c_strlen(c""), 0);
+
+        let mut values = [5, -1, 3, 3, 0, i32::MIN, i32::MAX];
+        c_qsort(&mut values);
+        assert_eq!(values, [i32::MIN, -1, 0, 3, 3, 5, i32::MAX]);
+        c_qsort(&mut []);
+
+        assert_eq!(c_format("answer", 42), Ok("answer=42".to_string()));
+        assert!(c_format(&"x".repeat(100), 1).is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! # FFI のデモ
+//!
+//! 処理の本体 (C の関数の宣言と安全なラッパー、C に公開する関数) は `lib.rs` にある。
+
+use std::ffi::CString;
+
+use ffi_concepts::*;
+
+extern "C" fn double(x: i32) -> i32 {
+    x * 2
+}
+
+fn main() {
+    println!("=== Rust FFI ===\n");
+
+    call_c();
+    strings();
+    call_libc();
+    call_rust_from_c();
+}
+
+/// #[repr(C)] の構造体を C の関数に渡す
+fn call_c() {
+    println!("--- C の関数を呼ぶ ---");
+
+    let rect = Rect {
+        origin: Point { x: 0.0, y: 0.0 },
+        width: 3.0,
+        height: 4.0,
+    };
+    println!("  rect_area(&{:?}) = {}", rect, area(&rect));
+    println!("  rect_center(値渡し) = {:?}", center(rect));
+    println!("  c_sum_with([1, 2, 3], double) = {}", sum_with(&[1, 2, 3], double));
+    println!("  size_of::<Rect>() = {} (C の sizeof(Rect) と同じ)\n", std::mem::size_of::<Rect>());
+}
+
+/// CString (Rust → C) と CStr (C → Rust)
+fn strings() {
+    println!("--- CString / CStr ---");
+
+    println!("  c_upper(\"hello\") = {:?}", to_upper("hello"));
+    // C の文字列は NUL で終わるので、途中に NUL があると渡せない
+    println!("  c_upper(\"a\\0b\") = {:?}", to_upper("a\0b"));
+    println!("  c_version() = {:?} (C が持つ文字列を借りる)", c_version());
+
+    let owned = CString::new("ownership").unwrap();
+    println!("  strlen({:?}) = {}\n", owned, c_strlen(&owned));
+}
+
+/// libc クレートの宣言を使う
+fn call_libc() {
+    println!("--- libc ---");
+
+    let mut values = [42, 7, 19, -3, 0];
+    c_qsort(&mut values);
+    println!("  qsort (比較関数は Rust) = {:?}", values);
+    println!("  snprintf(\"%s=%d\") = {:?}", c_format("answer", 42));
+    // SAFETY: 引数のない関数
+    println!("  getpid() = {}\n", unsafe { libc::getpid() });
+}
+
+/// C から Rust を呼ぶ (cdylib として公開している関数)
+fn call_rust_from_c() {
+    println!("--- C から Rust を呼ぶ ---");
+
+    println!("  c_call_rust_add(40, 2) = {}", add_via_c(40, 2));
+    println!("  c_call_rust_greet(\"C\") = {:?}", greet_via_c("C"));
+    println!("  共有ライブラリ: target/debug/libffi_concepts.so (c/example.c を参照)");
+    println!();
+}
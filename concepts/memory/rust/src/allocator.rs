@@ -0,0 +1,275 @@
+//! 確保の戦略: バンプアロケーターとアリーナ
+//!
+//! | 方式 | 確保 | 解放 | 向いているもの |
+//! |------|------|------|----------------|
+//! | 汎用 (`System`, malloc) | 空きを探す | 1 つずつ | 何でも |
+//! | バンプ ([`BumpAllocator`]) | 先頭をずらすだけ | しない (全部まとめて捨てる) | 短命なプロセス、1 リクエスト分 |
+//! | アリーナ ([`Arena`]) | 塊の中に並べる | アリーナごと | 寿命のそろった多数のノード (木、リスト、AST) |
+//!
+//! `BumpAllocator` は `GlobalAlloc` を実装しているので、`#[global_allocator]` にできる。
+//! デモでは `cargo run --features bump-global` のときだけ使う (`main.rs`)。
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::{RefCell, UnsafeCell};
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ============================================================
+// バンプアロケーター
+// ============================================================
+
+/// `N` バイトの領域の先頭から順に切り出すだけのアロケーター
+///
+/// ```text
+/// [ 使用済み | 使用済み | ......空き...... ]
+///                       ^ next (ここをずらすだけ)
+/// ```
+///
+/// `dealloc` は何もしない (数えるだけ)。使い切ったら null を返すので、グローバルに
+/// していればそこで `handle_alloc_error` (abort) になる。
+pub struct BumpAllocator<const N: usize> {
+    memory: UnsafeCell<[MaybeUninit<u8>; N]>,
+    /// 次に切り出す位置 (先頭からのバイト数)
+    next: AtomicUsize,
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+}
+
+// SAFETY: 領域への書き込みは、`next` の compare_exchange で重ならないように切り出した
+// 範囲にだけ行う (同じ範囲を 2 つのスレッドに渡さない)
+unsafe impl<const N: usize> Sync for BumpAllocator<N> {}
+
+impl<const N: usize> Default for BumpAllocator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> BumpAllocator<N> {
+    /// `static` に置けるよう `const fn`
+    pub const fn new() -> Self {
+        BumpAllocator {
+            memory: UnsafeCell::new([MaybeUninit::uninit(); N]),
+            next: AtomicUsize::new(0),
+            allocations: AtomicUsize::new(0),
+            deallocations: AtomicUsize::new(0),
+        }
+    }
+
+    /// 切り出したバイト数 (位置合わせの隙間を含む)
+    pub fn used(&self) -> usize {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    /// (確保した回数, 解放された回数)。解放されてもメモリは戻らない
+    pub fn counts(&self) -> (usize, usize) {
+        (self.allocations.load(Ordering::Relaxed), self.deallocations.load(Ordering::Relaxed))
+    }
+}
+
+unsafe impl<const N: usize> GlobalAlloc for BumpAllocator<N> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let base = self.memory.get() as *mut u8;
+        let mut next = self.next.load(Ordering::Relaxed);
+        loop {
+            // 位置合わせは「先頭からの位置」ではなく実際のアドレスで行う
+            let start = match (base as usize + next).checked_next_multiple_of(layout.align()) {
+                Some(address) => address - base as usize,
+                None => return ptr::null_mut(),
+            };
+            let end = match start.checked_add(layout.size()) {
+                Some(end) if end <= N => end,
+                _ => return ptr::null_mut(),
+            };
+            // ほかのスレッドが先にずらしていたら、その位置からやり直す
+            match self.next.compare_exchange_weak(next, end, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    self.allocations.fetch_add(1, Ordering::Relaxed);
+                    return base.add(start);
+                }
+                Err(current) => next = current,
+            }
+        }
+    }
+
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {
+        self.deallocations.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// ============================================================
+// アリーナ
+// ============================================================
+
+/// 同じ型の値をまとめて置き、アリーナごと解放する
+///
+/// 値は塊 (`Vec`) に並べ、塊がいっぱいになったら倍の大きさの塊を足す。一度置いた値は
+/// 動かさないので、`&self` から `&'a mut T` を返せる。返した参照はアリーナより長生きできない:
+///
+/// ```compile_fail,E0597
+/// use memory::allocator::Arena;
+///
+/// let value;
+/// {
+///     let arena = Arena::new();
+///     value = arena.alloc(1);
+/// }
+/// println!("{}", value);
+/// ```
+pub struct Arena<T> {
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Arena<T> {
+    const FIRST_CHUNK: usize = 16;
+
+    pub fn new() -> Self {
+        Arena { chunks: RefCell::new(vec![Vec::with_capacity(Self::FIRST_CHUNK)]) }
+    }
+
+    // 要素ごとに別の場所なので、&self から &mut を返しても重ならない
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.borrow_mut();
+        let last = chunks.last_mut().unwrap();
+        if last.len() == last.capacity() {
+            // いっぱいの塊に push すると中身ごと引っ越してしまうので、新しい塊を足す
+            let capacity = last.capacity() * 2;
+            chunks.push(Vec::with_capacity(capacity));
+        }
+        let chunk = chunks.last_mut().unwrap();
+        chunk.push(value);
+        // SAFETY: 塊は容量を超えて push しないので、要素は動かない。塊を捨てるのは
+        // アリーナを drop するときだけで、そのとき `&self` の借用 (返した参照) は終わっている。
+        // 同じ要素への参照を 2 度返すことはない。
+        // `last_mut()` は塊全体への `&mut [T]` を作り、前に返した参照を無効にしてしまう
+        // (Miri が検出する) ので、`as_mut_ptr` から位置を計算する
+        unsafe { &mut *chunk.as_mut_ptr().add(chunk.len() - 1) }
+    }
+
+    /// 置いた値の数
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 塊の数 (確保した回数)
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+}
+
+/// アリーナに置く単方向リストのノード
+///
+/// 次のノードは `Box` ではなくアリーナの中への参照。ノード 1 つごとの確保も解放も無く、
+/// 末尾を共有するリストも (参照カウント無しで) 作れる。
+pub struct Node<'a, T> {
+    pub value: T,
+    pub next: Option<&'a Node<'a, T>>,
+}
+
+impl<'a, T> Node<'a, T> {
+    /// 先頭に 1 つ足したリストを返す (元のリストはそのまま共有する)
+    pub fn cons(arena: &'a Arena<Node<'a, T>>, value: T, next: Option<&'a Node<'a, T>>) -> &'a Node<'a, T> {
+        arena.alloc(Node { value, next })
+    }
+
+    /// `values` の順に並んだリストを作る
+    pub fn list<I>(arena: &'a Arena<Node<'a, T>>, values: I) -> Option<&'a Node<'a, T>>
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: DoubleEndedIterator,
+    {
+        values.into_iter().rev().fold(None, |next, value| Some(Node::cons(arena, value, next)))
+    }
+
+    pub fn iter(&'a self) -> impl Iterator<Item = &'a T> {
+        std::iter::successors(Some(self), |node| node.next).map(|node| &node.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_alloc() {
+        let bump = Box::new(BumpAllocator::<256>::new());
+        unsafe {
+            let a = bump.alloc(Layout::new::<u8>());
+            let b = bump.alloc(Layout::new::<u64>());
+            assert!(!a.is_null() && !b.is_null());
+            // u64 の位置合わせで隙間が空く
+            assert_eq!(b as usize % 8, 0);
+            assert!(b as usize > a as usize);
+            b.cast::<u64>().write(42);
+            assert_eq!(b.cast::<u64>().read(), 42);
+
+            // 解放しても戻らない
+            let used = bump.used();
+            bump.dealloc(b, Layout::new::<u64>());
+            assert_eq!(bump.used(), used);
+            assert_eq!(bump.counts(), (2, 1));
+
+            // 使い切ると null
+            assert!(bump.alloc(Layout::from_size_align(512, 1).unwrap()).is_null());
+            assert!(!bump.alloc(Layout::from_size_align(64, 64).unwrap()).is_null());
+        }
+    }
+
+    #[test]
+    fn test_bump_alloc_from_threads() {
+        // 切り出した範囲が重ならないこと
+        let bump = Box::new(BumpAllocator::<{ 4 * 8 * 100 }>::new());
+        let mut addresses: Vec<usize> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let bump = &*bump;
+                    s.spawn(move || {
+                        (0..100).map(|_| unsafe { bump.alloc(Layout::new::<u64>()) } as usize).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+        });
+        assert!(addresses.iter().all(|&a| a != 0));
+        addresses.sort();
+        assert!(addresses.windows(2).all(|w| w[1] - w[0] >= 8));
+    }
+
+    #[test]
+    fn test_arena_keeps_references_valid() {
+        let arena = Arena::new();
+        assert!(arena.is_empty());
+        let first = arena.alloc(1000);
+        let rest: Vec<&mut usize> = (0..99).map(|i| arena.alloc(i)).collect();
+        // 塊が増えても前の参照は有効
+        *first += 1;
+        assert_eq!(*first + rest.iter().map(|r| **r).sum::<usize>(), 1001 + 4851);
+        assert_eq!(arena.len(), 100);
+        // 16 + 32 + 64 >= 100
+        assert_eq!(arena.chunk_count(), 3);
+    }
+
+    #[test]
+    fn test_arena_linked_list_shares_tail() {
+        let arena = Arena::new();
+        let tail = Node::list(&arena, [3, 4, 5]);
+        let a = Node::cons(&arena, 1, tail);
+        let b = Node::cons(&arena, 2, tail);
+        assert_eq!(a.iter().copied().collect::<Vec<_>>(), [1, 3, 4, 5]);
+        assert_eq!(b.iter().copied().collect::<Vec<_>>(), [2, 3, 4, 5]);
+        assert!(std::ptr::eq(a.next.unwrap(), b.next.unwrap()));
+        assert_eq!(arena.len(), 5);
+    }
+}
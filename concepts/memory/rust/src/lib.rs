@@ -0,0 +1,9 @@
+//! # Rust のメモリ管理 (ライブラリ部分)
+//!
+//! `main.rs` のデモから使う。コンパイルが通らない例は `compile_fail` の
+//! doctest にしてあり、`cargo test` で「確かにエラーになる」ことを確かめられる。
+
+pub mod allocator;
+pub mod lifetimes;
+pub mod rc;
+pub mod refcell;
@@ -0,0 +1,333 @@
+//! ライフタイムの発展編
+//!
+//! - 省略規則: 注釈を書かなくてよい場合と、書かないと通らない場合
+//! - 高階トレイト境界 (`for<'a>`): どんなライフタイムでも受け付けるクロージャ
+//! - 変性: `&'a T` は短くできるが、`&mut T` の中身は変えられない
+//! - 再借用: `&mut` を渡しても使い続けられる理由と、そうならない場合
+//! - 自己参照構造体: 書けない理由と回避策
+//!
+//! 通らない例はそれぞれの項目の `compile_fail` の doctest にある。
+
+use std::ops::Range;
+use std::rc::Rc;
+
+// ============================================================
+// 省略規則
+// ============================================================
+
+/// 省略規則 1, 2: 引数の参照が 1 つなら、戻り値はそのライフタイムになる
+///
+/// `fn first_word<'a>(s: &'a str) -> &'a str` と書いたのと同じ。
+///
+/// 引数の参照が 2 つあると、戻り値がどちらから借りているか決まらないので注釈が必要:
+///
+/// ```compile_fail,E0106
+/// fn longest(x: &str, y: &str) -> &str {
+///     if x.len() > y.len() { x } else { y }
+/// }
+/// ```
+pub fn first_word(s: &str) -> &str {
+    s.split_whitespace().next().unwrap_or("")
+}
+
+/// どちらを返すか実行時に決まるので、両方に同じ `'a` を付ける
+///
+/// 戻り値は短い方の引数の間しか使えない:
+///
+/// ```compile_fail,E0597
+/// use memory::lifetimes::longest;
+///
+/// let outer = String::from("outer string");
+/// let result;
+/// {
+///     let inner = String::from("inner");
+///     result = longest(&outer, &inner);
+/// }
+/// println!("{}", result);
+/// ```
+pub fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() >= y.len() {
+        x
+    } else {
+        y
+    }
+}
+
+/// テキストの中のキーワードを探す
+pub struct Highlighter {
+    keyword: String,
+}
+
+impl Highlighter {
+    pub fn new(keyword: &str) -> Self {
+        Highlighter {
+            keyword: keyword.to_string(),
+        }
+    }
+
+    /// 省略規則 3: `&self` があれば、戻り値は `self` から借りているとみなされる
+    ///
+    /// `self` と関係ない引数から借りたものを返すなら、注釈で明示する ([`Highlighter::find`])。
+    ///
+    /// ```compile_fail
+    /// struct Highlighter {
+    ///     keyword: String,
+    /// }
+    ///
+    /// impl Highlighter {
+    ///     // 戻り値は self から借りているはずなのに text を返している
+    ///     fn find(&self, text: &str) -> &str {
+    ///         let start = text.find(&self.keyword).unwrap_or(0);
+    ///         &text[start..]
+    ///     }
+    /// }
+    /// ```
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    /// キーワードから後ろ (`text` から借りる。`self` はすぐに捨ててもよい)
+    pub fn find<'t>(&self, text: &'t str) -> Option<&'t str> {
+        text.find(&self.keyword).map(|start| &text[start..])
+    }
+}
+
+// ============================================================
+// 高階トレイト境界 (HRTB)
+// ============================================================
+
+/// `f` を各要素に適用する
+///
+/// `for<'a> Fn(&'a str) -> &'a str` は「どんな `'a` でも、`&'a str` を受け取って
+/// 同じ `'a` の `&str` を返す」という意味。関数の中で作った一時的な借用も渡せる。
+///
+/// ライフタイムを関数の引数にすると、呼び出し側が 1 つに決めてしまうので、
+/// 関数の中のローカル変数の借用は渡せない:
+///
+/// ```compile_fail,E0597
+/// fn trim_all<'x, F: Fn(&'x str) -> &'x str>(items: &[String], f: F) -> Vec<String> {
+///     items
+///         .iter()
+///         .map(|item| {
+///             let owned = item.clone();
+///             f(&owned).to_string() // owned は 'x ほど長く生きない
+///         })
+///         .collect()
+/// }
+/// ```
+pub fn map_borrowed<F>(items: &[String], f: F) -> Vec<String>
+where
+    F: for<'a> Fn(&'a str) -> &'a str,
+{
+    items
+        .iter()
+        .map(|item| {
+            // 一時的な値を借りて渡せる
+            let lowered = item.to_lowercase();
+            f(&lowered).to_string()
+        })
+        .collect()
+}
+
+/// トレイトオブジェクトにも書ける (クロージャの `|s: &str| ...` は自動で HRTB になる)
+pub type StrTransform = Box<dyn for<'a> Fn(&'a str) -> &'a str>;
+
+pub fn trim_transform() -> StrTransform {
+    Box::new(|s: &str| s.trim())
+}
+
+// ============================================================
+// 変性
+// ============================================================
+
+/// 共変: `&'static str` は `&'a str` として使える (長い借用を短く見るのは安全)
+pub fn shorten<'a>(s: &'static str) -> &'a str {
+    s
+}
+
+/// `&mut T` は `T` について不変。`&mut &'static str` を `&mut &'a str` として
+/// 扱えてしまうと、短い借用を書き込まれて、後でダングリング参照を読むことになる:
+///
+/// ```compile_fail,E0597
+/// use memory::lifetimes::assign;
+///
+/// let mut s: &'static str = "static";
+/// {
+///     let local = String::from("local");
+///     assign(&mut s, &local); // &mut &'static str を &mut &'local str にはできない
+/// }
+/// println!("{}", s);
+/// ```
+///
+/// `Cell<T>` や `RefCell<T>` も中身を書き換えられるので同じく不変。
+pub fn assign<'a>(target: &mut &'a str, value: &'a str) {
+    *target = value;
+}
+
+// ============================================================
+// 再借用
+// ============================================================
+
+/// `&mut` の引数には暗黙に `&mut *v` (再借用) が渡されるので、呼んだ後も `v` を使える
+pub fn push_twice(v: &mut Vec<i32>, value: i32) {
+    push_one(v, value); // push_one(&mut *v, value) と同じ
+    push_one(v, value);
+}
+
+fn push_one(v: &mut Vec<i32>, value: i32) {
+    v.push(value);
+}
+
+/// ジェネリックな引数 `T` では再借用が起きず、`&mut` がムーブされる:
+///
+/// ```compile_fail,E0382
+/// use memory::lifetimes::consume;
+///
+/// let mut v = vec![1];
+/// let r = &mut v;
+/// consume(r); // r はムーブされた
+/// r.push(2);
+/// ```
+///
+/// 明示的に `consume(&mut *r)` と書けば `r` を使い続けられる。
+pub fn consume<T>(_value: T) {}
+
+// ============================================================
+// 自己参照構造体
+// ============================================================
+
+/// `key=value` の行。`text` の中を指す `&str` は持てないので、位置 (`Range`) を持つ
+///
+/// 持ち主と、それを指す参照を同じ構造体に入れることはできない。
+/// 構造体をムーブすると持ち主の場所が変わり、参照が古い場所を指してしまうため:
+///
+/// ```compile_fail,E0505
+/// struct Line<'a> {
+///     text: String,
+///     key: &'a str,
+/// }
+///
+/// fn parse<'a>(text: String) -> Line<'a> {
+///     let key = text.split('=').next().unwrap();
+///     Line { text, key } // key が text を借りたまま text をムーブしている
+/// }
+/// ```
+///
+/// 回避策:
+/// - 位置を持ち、使うときに取り出す (この型)
+/// - 共有の持ち主 (`Rc<str>`) と位置を一緒に持つ ([`SharedSlice`])
+/// - `Pin` と `unsafe` で動かないことを保証する (async の Future が内部で使う)
+#[derive(Debug)]
+pub struct KeyValue {
+    text: String,
+    key: Range<usize>,
+    value: Range<usize>,
+}
+
+impl KeyValue {
+    pub fn parse(text: String) -> Option<Self> {
+        let eq = text.find('=')?;
+        Some(KeyValue {
+            key: 0..eq,
+            value: eq + 1..text.len(),
+            text,
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.text[self.key.clone()]
+    }
+
+    pub fn value(&self) -> &str {
+        &self.text[self.value.clone()]
+    }
+}
+
+/// 共有の文字列の一部 (持ち主を `Rc` で共有するので、何度切り出してもコピーしない)
+#[derive(Debug, Clone)]
+pub struct SharedSlice {
+    source: Rc<str>,
+    range: Range<usize>,
+}
+
+impl SharedSlice {
+    pub fn new(source: Rc<str>) -> Self {
+        let range = 0..source.len();
+        SharedSlice { source, range }
+    }
+
+    /// 空白で区切った部分
+    pub fn words(&self) -> Vec<SharedSlice> {
+        let base = self.range.start;
+        self.as_str()
+            .split_whitespace()
+            .map(|word| {
+                // word は as_str() の中を指しているので、先頭からの位置を求められる
+                let start = base + (word.as_ptr() as usize - self.as_str().as_ptr() as usize);
+                SharedSlice {
+                    source: Rc::clone(&self.source),
+                    range: start..start + word.len(),
+                }
+            })
+            .collect()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source[self.range.clone()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elision_and_hrtb() {
+        assert_eq!(first_word("hello world"), "hello");
+        assert_eq!(longest("abc", "de"), "abc");
+
+        let highlighter = Highlighter::new("fn");
+        let text = String::from("pub fn main()");
+        // find の結果は text から借りているので、highlighter を捨てても使える
+        let found = highlighter.find(&text);
+        drop(highlighter);
+        assert_eq!(found, Some("fn main()"));
+
+        let items = vec!["  Hello ".to_string(), "WORLD".to_string()];
+        assert_eq!(map_borrowed(&items, str::trim), vec!["hello", "world"]);
+        assert_eq!(map_borrowed(&items, |s| &s[..1]), vec![" ", "w"]);
+        assert_eq!(trim_transform()("  x  "), "x");
+    }
+
+    #[test]
+    fn test_variance_and_reborrow() {
+        let owned = String::from("short");
+        let mut s = shorten("static");
+        assign(&mut s, &owned);
+        assert_eq!(s, "short");
+
+        let mut v = Vec::new();
+        push_twice(&mut v, 7);
+        let r = &mut v;
+        consume(&mut *r);
+        r.push(8);
+        assert_eq!(v, [7, 7, 8]);
+    }
+
+    #[test]
+    fn test_self_referential_workarounds() {
+        let line = KeyValue::parse("name=ferris".to_string()).unwrap();
+        // ムーブしても位置は変わらない
+        let moved = line;
+        assert_eq!((moved.key(), moved.value()), ("name", "ferris"));
+        assert!(KeyValue::parse("no separator".to_string()).is_none());
+
+        let sentence = SharedSlice::new(Rc::from("the quick  brown fox"));
+        let words = sentence.words();
+        let texts: Vec<&str> = words.iter().map(SharedSlice::as_str).collect();
+        assert_eq!(texts, ["the", "quick", "brown", "fox"]);
+        assert_eq!(words[2].words()[0].as_str(), "brown");
+        // 持ち主は 1 つを共有している
+        assert_eq!(Rc::strong_count(&sentence.source), 5);
+    }
+}
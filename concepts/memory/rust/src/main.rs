@@ -3,6 +3,16 @@
 //! Rust はGCなしでメモリ安全性を保証する。
 //! 所有権・借用・ライフタイムの3つの概念がその基盤。
 
+use memory::allocator::{Arena, Node};
+use memory::lifetimes::{self, Highlighter, KeyValue, SharedSlice};
+use memory::rc::MyRc;
+use memory::refcell::MyRefCell;
+
+/// `--features bump-global` のときは、このプログラムの確保を全部バンプアロケーターで行う
+#[cfg(feature = "bump-global")]
+#[global_allocator]
+static GLOBAL: memory::allocator::BumpAllocator<{ 8 * 1024 * 1024 }> = memory::allocator::BumpAllocator::new();
+
 fn main() {
     println!("=== Rust メモリ管理 ===\n");
 
@@ -11,7 +21,11 @@ fn main() {
     borrowing();
     mutable_borrowing();
     lifetimes();
+    advanced_lifetimes();
     smart_pointers();
+    my_rc();
+    my_refcell();
+    allocation_strategies();
 }
 
 /// 所有権の基本
@@ -46,6 +60,8 @@ fn move_semantics() {
         // 関数終了時に s はドロップ
     }
 
+    // 所有権が移ることを見せるため、いったん変数に束縛してから返す
+    #[allow(clippy::let_and_return)]
     fn give_ownership() -> String {
         let s = String::from("yours");
         s  // 所有権を返す
@@ -73,6 +89,8 @@ fn move_semantics() {
 fn borrowing() {
     println!("--- 借用 (不変参照) ---");
 
+    // &str の方が汎用的だが、String を借りていることを見せるため &String にしている
+    #[allow(clippy::ptr_arg)]
     fn calculate_length(s: &String) -> usize {
         s.len()
         // s は借用しているだけなのでドロップされない
@@ -91,6 +109,8 @@ fn borrowing() {
 }
 
 /// 可変借用
+// String に追記する例なので、1 文字でも push_str のままにする
+#[allow(clippy::single_char_add_str)]
 fn mutable_borrowing() {
     println!("--- 可変借用 ---");
 
@@ -136,6 +156,8 @@ fn lifetimes() {
     println!("  longest: {}", result);
 
     // 構造体とライフタイム
+    // 中身は Debug で表示するだけ
+    #[allow(dead_code)]
     #[derive(Debug)]
     struct ImportantExcerpt<'a> {
         part: &'a str,
@@ -153,6 +175,47 @@ fn lifetimes() {
     println!();
 }
 
+/// ライフタイムの発展編 (通らない例は src/lifetimes.rs の doctest)
+fn advanced_lifetimes() {
+    println!("--- ライフタイム (発展) ---");
+
+    // 省略規則
+    println!("  first_word: {}", lifetimes::first_word("hello world"));
+    let text = String::from("let x = 1;");
+    let found = {
+        let highlighter = Highlighter::new("x");
+        // 戻り値は text から借りているので、highlighter より長く使える
+        highlighter.find(&text)
+    };
+    println!("  find (text から借りる): {:?}", found);
+
+    // 高階トレイト境界: 関数の中の一時的な値を借りて渡せる
+    let items = vec!["  Rust ".to_string(), " Lifetimes".to_string()];
+    println!("  for<'a> Fn(&'a str) -> &'a str: {:?}", lifetimes::map_borrowed(&items, str::trim));
+
+    // 変性: &'static str は短いライフタイムとして使える
+    let owned = String::from("borrowed");
+    let mut s = lifetimes::shorten("static");
+    lifetimes::assign(&mut s, &owned);
+    println!("  共変: {}", s);
+
+    // 再借用
+    let mut v = vec![1];
+    let r = &mut v;
+    lifetimes::push_twice(r, 2);
+    r.push(3);
+    println!("  再借用の後も使える: {:?}", v);
+
+    // 自己参照の代わりに位置を持つ
+    let line = KeyValue::parse("lang=rust".to_string()).unwrap();
+    println!("  KeyValue: {} = {}", line.key(), line.value());
+    let words = SharedSlice::new("borrow checker rules".into()).words();
+    let words: Vec<&str> = words.iter().map(SharedSlice::as_str).collect();
+    println!("  SharedSlice (Rc<str> を共有): {:?}", words);
+
+    println!();
+}
+
 /// スマートポインタ
 fn smart_pointers() {
     println!("--- スマートポインタ ---");
@@ -162,6 +225,8 @@ fn smart_pointers() {
     println!("  Box: {}", b);
 
     // 再帰的な型に必須
+    // 中身は Debug で表示するだけ
+    #[allow(dead_code)]
     #[derive(Debug)]
     enum List {
         Cons(i32, Box<List>),
@@ -175,7 +240,10 @@ fn smart_pointers() {
     // Rc<T>: 参照カウント（単一スレッド）
     use std::rc::Rc;
     let a = Rc::new(String::from("shared"));
+    // b と c は参照カウントを増やすためだけに持つ
+    #[allow(unused_variables)]
     let b = Rc::clone(&a);
+    #[allow(unused_variables)]
     let c = Rc::clone(&a);
     println!("  Rc count: {} (a, b, c が共有)", Rc::strong_count(&a));
 
@@ -193,3 +261,90 @@ fn smart_pointers() {
 
     println!();
 }
+
+/// Rc / Weak を自分で書いたもの (src/rc.rs)
+fn my_rc() {
+    println!("--- MyRc / MyWeak (自作の参照カウント) ---");
+
+    let a = MyRc::new(String::from("shared"));
+    let b = a.clone();
+    println!("  clone しても中身は 1 つ: ptr_eq = {}, strong = {}", MyRc::ptr_eq(&a, &b), MyRc::strong_count(&a));
+
+    let weak = MyRc::downgrade(&a);
+    println!("  downgrade: strong = {}, weak = {}", MyRc::strong_count(&a), MyRc::weak_count(&a));
+    println!("  upgrade (strong あり): {:?}", weak.upgrade());
+
+    drop(a);
+    drop(b);
+    // 中身は drop 済み。箱は weak が落ちるまで残る
+    println!("  strong が 0 になった後の upgrade: {:?}", weak.upgrade());
+
+    let mut unique = MyRc::new(vec![1, 2]);
+    MyRc::get_mut(&mut unique).unwrap().push(3);
+    println!("  自分だけなら get_mut / try_unwrap: {:?}", MyRc::try_unwrap(unique));
+
+    println!();
+}
+
+/// RefCell を自分で書いたもの (src/refcell.rs)
+fn my_refcell() {
+    println!("--- MyRefCell (実行時の借用チェック) ---");
+
+    // MyRc<MyRefCell<T>>: 共有しつつ書き換える定番の組み合わせ
+    let log = MyRc::new(MyRefCell::new(Vec::new()));
+    let writer = log.clone();
+    writer.borrow_mut().push("first");
+    log.borrow_mut().push("second");
+    println!("  共有して書き換え: {:?}", log.borrow());
+
+    {
+        let _reader = log.borrow();
+        let _reader2 = log.borrow();
+        // 読んでいる間は書けない (borrow_mut なら panic)
+        println!("  読んでいる間の try_borrow_mut: {:?}", log.try_borrow_mut().map(|_| ()));
+    }
+    // ガードが落ちたので書ける
+    println!("  ガードが落ちた後の try_borrow_mut: {:?}", log.try_borrow_mut().map(|_| ()));
+
+    let guard = log.borrow_mut();
+    println!("  書いている間の Debug: {:?}", log);
+    drop(guard);
+    println!("  落とした後の Debug: {:?}", log);
+
+    println!();
+}
+
+/// 確保の戦略: アリーナとバンプアロケーター (src/allocator.rs)
+fn allocation_strategies() {
+    println!("--- 確保の戦略 (アリーナ / バンプ) ---");
+
+    // ノードはアリーナの中に並び、アリーナを落とすとまとめて解放される
+    let arena = Arena::new();
+    let tail = Node::list(&arena, [3, 4, 5]);
+    let a = Node::cons(&arena, 1, tail);
+    let b = Node::cons(&arena, 2, tail);
+    println!("  a: {:?}", a.iter().collect::<Vec<_>>());
+    println!("  b: {:?} (末尾の 3 つを a と共有)", b.iter().collect::<Vec<_>>());
+    println!("  ノード {} 個、確保した塊 {} 個", arena.len(), arena.chunk_count());
+
+    let numbers = Arena::new();
+    for i in 0..1_000 {
+        numbers.alloc(i);
+    }
+    println!("  1000 個置いても塊は {} 個 (倍々に増やす)", numbers.chunk_count());
+
+    #[cfg(feature = "bump-global")]
+    {
+        let (allocations, deallocations) = GLOBAL.counts();
+        println!(
+            "  グローバル (バンプ): {} バイト使用、確保 {} 回 / 解放 {} 回 (解放しても戻らない)",
+            GLOBAL.used(),
+            allocations,
+            deallocations
+        );
+    }
+    #[cfg(not(feature = "bump-global"))]
+    println!("  (`cargo run --features bump-global` でプログラム全体をバンプアロケーターで動かす)");
+
+    println!();
+}
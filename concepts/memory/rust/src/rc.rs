@@ -0,0 +1,319 @@
+//! `Rc` / `Weak` を自分で書く
+//!
+//! ```text
+//! MyRc ─┐
+//! MyRc ─┼──▶ RcBox { strong: 2, weak: 2, value: T }
+//! MyWeak┘        (weak には「strong がある」分の 1 を含む)
+//! ```
+//!
+//! - strong が 0 になったら中身 (`value`) を drop する
+//! - weak も 0 になったら箱 (メモリ) を解放する
+//! - `MyWeak::upgrade` は strong が 0 なら `None`
+//!
+//! カウントは `Cell` なのでスレッドをまたげない (`NonNull` を持つので自動で `!Send` になる)。
+//! unsafe を使っているので、`cargo +nightly miri test rc` で未定義動作が無いことを確かめている。
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::NonNull;
+
+/// ヒープに置く箱
+struct RcBox<T> {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+    /// strong が 0 になった時点で drop し、箱はそのまま残すので `ManuallyDrop`
+    value: ManuallyDrop<T>,
+}
+
+/// 参照カウントのスマートポインタ (`std::rc::Rc` の簡易版)
+///
+/// スレッドには渡せない:
+///
+/// ```compile_fail,E0277
+/// use memory::rc::MyRc;
+///
+/// let rc = MyRc::new(1);
+/// std::thread::spawn(move || println!("{}", *rc));
+/// ```
+pub struct MyRc<T> {
+    ptr: NonNull<RcBox<T>>,
+    /// `RcBox<T>` を (共有して) 持っていることを drop チェッカーに知らせる
+    _owns: PhantomData<RcBox<T>>,
+}
+
+/// 中身を生かしておかない参照 (`std::rc::Weak` の簡易版)
+pub struct MyWeak<T> {
+    ptr: NonNull<RcBox<T>>,
+}
+
+impl<T> MyRc<T> {
+    pub fn new(value: T) -> Self {
+        let boxed = Box::new(RcBox {
+            strong: Cell::new(1),
+            // 全部の strong でまとめて 1 つ持つ
+            weak: Cell::new(1),
+            value: ManuallyDrop::new(value),
+        });
+        MyRc { ptr: NonNull::from(Box::leak(boxed)), _owns: PhantomData }
+    }
+
+    fn counts(&self) -> Counts<'_> {
+        // SAFETY: self がある間、箱は生きている
+        unsafe { counts(self.ptr) }
+    }
+
+    pub fn strong_count(this: &Self) -> usize {
+        this.counts().strong.get()
+    }
+
+    /// 自分以外の `MyWeak` の数 (strong がまとめて持つ 1 は数えない)
+    pub fn weak_count(this: &Self) -> usize {
+        this.counts().weak.get() - 1
+    }
+
+    pub fn downgrade(this: &Self) -> MyWeak<T> {
+        let weak = this.counts().weak;
+        weak.set(weak.get() + 1);
+        MyWeak { ptr: this.ptr }
+    }
+
+    /// 同じ箱を指しているか
+    pub fn ptr_eq(a: &Self, b: &Self) -> bool {
+        a.ptr == b.ptr
+    }
+
+    /// ほかに strong も weak も無ければ、中身を書き換えられる
+    pub fn get_mut(this: &mut Self) -> Option<&mut T> {
+        if Self::strong_count(this) == 1 && Self::weak_count(this) == 0 {
+            // SAFETY: ほかに誰も指していないので、&mut self から &mut T を作ってよい
+            Some(unsafe { &mut (*this.ptr.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// strong が自分だけなら中身を取り出す。ほかにもあればそのまま返す
+    pub fn try_unwrap(this: Self) -> Result<T, Self> {
+        if Self::strong_count(&this) != 1 {
+            return Err(this);
+        }
+        // 自分の Drop は走らせず、ここで同じことをする
+        let this = ManuallyDrop::new(this);
+        this.counts().strong.set(0);
+        // SAFETY: strong が 0 になったので、もう誰も中身を読まない。取り出すのは 1 度だけ
+        let value = unsafe { std::ptr::read(&*(*this.ptr.as_ptr()).value) };
+        release_weak(this.ptr);
+        Ok(value)
+    }
+}
+
+/// 箱のカウントだけを指す参照
+struct Counts<'a> {
+    strong: &'a Cell<usize>,
+    weak: &'a Cell<usize>,
+}
+
+/// カウントのフィールドだけを指す参照を作る
+///
+/// 中身の drop 中 (中身への `&mut` がある間) にも子の `MyWeak` から呼ばれるので、
+/// `ptr.as_ref()` で箱全体 (中身も含む) への `&` を作ると借用規則違反になる (Miri が検出する)。
+///
+/// # Safety
+///
+/// 箱がまだ解放されていないこと (strong か weak を 1 つ持っていること)
+unsafe fn counts<'a, T>(ptr: NonNull<RcBox<T>>) -> Counts<'a> {
+    let ptr = ptr.as_ptr();
+    Counts { strong: &(*ptr).strong, weak: &(*ptr).weak }
+}
+
+/// weak を 1 つ減らし、0 になったら箱を解放する
+fn release_weak<T>(ptr: NonNull<RcBox<T>>) {
+    // SAFETY: 呼ぶ側が weak を 1 つ持っているので、箱はまだ生きている
+    let weak = unsafe { counts(ptr) }.weak;
+    weak.set(weak.get() - 1);
+    if weak.get() == 0 {
+        // SAFETY: strong も weak も 0 なので、もう誰も指していない。中身は drop 済み
+        // (`ManuallyDrop` なので箱の解放で 2 回 drop されることはない)
+        drop(unsafe { Box::from_raw(ptr.as_ptr()) });
+    }
+}
+
+impl<T> Clone for MyRc<T> {
+    /// 中身はコピーせず、strong を 1 増やすだけ
+    fn clone(&self) -> Self {
+        let strong = self.counts().strong;
+        strong.set(strong.get() + 1);
+        MyRc { ptr: self.ptr, _owns: PhantomData }
+    }
+}
+
+impl<T> Deref for MyRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: strong が 1 以上ある (self がある) 間、中身は生きている
+        unsafe { &(*self.ptr.as_ptr()).value }
+    }
+}
+
+impl<T> Drop for MyRc<T> {
+    fn drop(&mut self) {
+        let strong = self.counts().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() == 0 {
+            // SAFETY: 最後の strong。中身を drop するのはここ 1 回だけ。
+            // 箱全体ではなく `value` のフィールドだけを指す &mut を作る ([`counts`] と同じ理由)
+            unsafe { ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).value) };
+            // strong がまとめて持っていた weak の 1 を返す
+            release_weak(self.ptr);
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MyRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> MyWeak<T> {
+    /// 中身がまだあれば strong を 1 増やして返す
+    pub fn upgrade(&self) -> Option<MyRc<T>> {
+        // SAFETY: self が weak を 1 つ持っているので、箱は生きている (中身は分からない)
+        let strong = unsafe { counts(self.ptr) }.strong;
+        match strong.get() {
+            0 => None,
+            n => {
+                strong.set(n + 1);
+                Some(MyRc { ptr: self.ptr, _owns: PhantomData })
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        // SAFETY: upgrade と同じ
+        unsafe { counts(self.ptr) }.strong.get()
+    }
+}
+
+impl<T> Clone for MyWeak<T> {
+    fn clone(&self) -> Self {
+        // SAFETY: upgrade と同じ
+        let weak = unsafe { counts(self.ptr) }.weak;
+        weak.set(weak.get() + 1);
+        MyWeak { ptr: self.ptr }
+    }
+}
+
+impl<T> Drop for MyWeak<T> {
+    fn drop(&mut self) {
+        release_weak(self.ptr);
+    }
+}
+
+impl<T> fmt::Debug for MyWeak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(MyWeak)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// drop された回数を数える
+    struct DropCounter<'a>(&'a Cell<usize>);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[test]
+    fn test_clone_and_drop() {
+        let drops = Cell::new(0);
+        let a = MyRc::new(DropCounter(&drops));
+        let b = a.clone();
+        assert_eq!(MyRc::strong_count(&a), 2);
+        assert!(MyRc::ptr_eq(&a, &b));
+        drop(a);
+        assert_eq!(drops.get(), 0);
+        assert_eq!(MyRc::strong_count(&b), 1);
+        drop(b);
+        assert_eq!(drops.get(), 1);
+
+        let mut c = MyRc::new(String::from("hello"));
+        MyRc::get_mut(&mut c).unwrap().push_str(" world");
+        assert_eq!(c.len(), 11);
+        let d = c.clone();
+        assert!(MyRc::get_mut(&mut c).is_none());
+        let c = MyRc::try_unwrap(c).unwrap_err();
+        drop(d);
+        assert_eq!(MyRc::try_unwrap(c).unwrap(), "hello world");
+    }
+
+    #[test]
+    fn test_weak_upgrade() {
+        let drops = Cell::new(0);
+        let strong = MyRc::new(DropCounter(&drops));
+        let weak = MyRc::downgrade(&strong);
+        let weak2 = weak.clone();
+        assert_eq!((MyRc::strong_count(&strong), MyRc::weak_count(&strong)), (1, 2));
+
+        let upgraded = weak.upgrade().unwrap();
+        assert_eq!(MyRc::strong_count(&upgraded), 2);
+        drop(upgraded);
+        drop(strong);
+        // 中身は消えたが、箱は weak のために残っている
+        assert_eq!(drops.get(), 1);
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak2.strong_count(), 0);
+        drop(weak);
+        drop(weak2);
+
+        // weak が残っていると try_unwrap で取り出した後も箱は残る
+        let strong = MyRc::new(5);
+        let weak = MyRc::downgrade(&strong);
+        assert_eq!(MyRc::try_unwrap(strong).unwrap(), 5);
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_parent_pointer_with_weak_does_not_leak() {
+        // 子 → 親を MyWeak にすれば循環しないので、全部 drop される (Miri はリークも検出する)
+        struct Node<'a> {
+            parent: RefCell<Option<MyWeak<Node<'a>>>>,
+            children: RefCell<Vec<MyRc<Node<'a>>>>,
+            _counter: DropCounter<'a>,
+        }
+        let drops = Cell::new(0);
+        let node = || MyRc::new(Node {
+            parent: RefCell::new(None),
+            children: RefCell::new(Vec::new()),
+            _counter: DropCounter(&drops),
+        });
+
+        let root = node();
+        for _ in 0..3 {
+            let child = node();
+            *child.parent.borrow_mut() = Some(MyRc::downgrade(&root));
+            root.children.borrow_mut().push(child);
+        }
+        let child = root.children.borrow()[0].clone();
+        let parent = child.parent.borrow().as_ref().unwrap().upgrade().unwrap();
+        assert!(MyRc::ptr_eq(&parent, &root));
+        assert_eq!((MyRc::strong_count(&root), MyRc::weak_count(&root)), (2, 3));
+        drop(parent);
+        drop(root);
+        // 親は消え、子からは辿れなくなる
+        assert_eq!(drops.get(), 3);
+        assert!(child.parent.borrow().as_ref().unwrap().upgrade().is_none());
+        drop(child);
+        assert_eq!(drops.get(), 4);
+    }
+}
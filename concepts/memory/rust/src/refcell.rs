@@ -0,0 +1,227 @@
+//! `RefCell` を自分で書く
+//!
+//! 借用規則 (`&` は何個でも、`&mut` は 1 つだけ) を、コンパイル時ではなく実行時に数えて守る。
+//!
+//! | `borrow` の値 | 状態 |
+//! |---------------|------|
+//! | `0` | 誰も借りていない |
+//! | `n > 0` | `borrow()` で `n` 個借りている |
+//! | `-1` | `borrow_mut()` で借りている |
+//!
+//! 借りるとガード (`Ref` / `RefMut`) が返り、ガードの `Drop` で返す。
+//! 規則に反する借用は panic する (`try_borrow` / `try_borrow_mut` なら `Err`)。
+
+use std::cell::{Cell, UnsafeCell};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+const UNUSED: isize = 0;
+const WRITING: isize = -1;
+
+/// 実行時に借用を数えるセル (`std::cell::RefCell` の簡易版)
+///
+/// カウントが `Cell` (アトミックでない) なので、スレッドをまたいで共有できない:
+///
+/// ```compile_fail,E0277
+/// use memory::refcell::MyRefCell;
+///
+/// let cell = MyRefCell::new(0);
+/// std::thread::scope(|s| {
+///     s.spawn(|| *cell.borrow_mut() += 1);
+/// });
+/// ```
+pub struct MyRefCell<T> {
+    borrow: Cell<isize>,
+    value: UnsafeCell<T>,
+}
+
+impl<T> MyRefCell<T> {
+    pub fn new(value: T) -> Self {
+        MyRefCell { borrow: Cell::new(UNUSED), value: UnsafeCell::new(value) }
+    }
+
+    /// 共有して借りる。`borrow_mut` で借りられている間は `Err`
+    pub fn try_borrow(&self) -> Result<Ref<'_, T>, String> {
+        match self.borrow.get() {
+            WRITING => Err("already mutably borrowed".to_string()),
+            n => {
+                self.borrow.set(n + 1);
+                Ok(Ref { cell: self })
+            }
+        }
+    }
+
+    /// 排他的に借りる。ほかに 1 つでも借りられていれば `Err`
+    pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, String> {
+        match self.borrow.get() {
+            UNUSED => {
+                self.borrow.set(WRITING);
+                Ok(RefMut { cell: self })
+            }
+            WRITING => Err("already mutably borrowed".to_string()),
+            _ => Err("already borrowed".to_string()),
+        }
+    }
+
+    /// [`try_borrow`](Self::try_borrow) の panic する版
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.try_borrow().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// [`try_borrow_mut`](Self::try_borrow_mut) の panic する版
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        self.try_borrow_mut().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// 中身を入れ替えて、前の値を返す (借りられていれば panic)
+    pub fn replace(&self, value: T) -> T {
+        std::mem::replace(&mut *self.borrow_mut(), value)
+    }
+
+    /// `&mut self` があれば数えなくてよい (コンパイル時に排他が保証されている)
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MyRefCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_borrow() {
+            Ok(value) => f.debug_struct("MyRefCell").field("value", &*value).finish(),
+            Err(_) => f.write_str("MyRefCell { <borrowed> }"),
+        }
+    }
+}
+
+/// `borrow()` のガード。落とすと共有の借用を 1 つ返す
+pub struct Ref<'a, T> {
+    cell: &'a MyRefCell<T>,
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: このガードがある間 borrow は 1 以上なので、&mut は作られない
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Ref<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for Ref<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(self.cell.borrow.get() - 1);
+    }
+}
+
+/// `borrow_mut()` のガード。落とすと排他の借用を返す
+pub struct RefMut<'a, T> {
+    cell: &'a MyRefCell<T>,
+}
+
+impl<T> Deref for RefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: このガードがある間 borrow は WRITING なので、ほかに参照は無い
+        unsafe { &*self.cell.value.get() }
+    }
+}
+
+impl<T> DerefMut for RefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: 同上。&mut self なのでガード経由の参照も 1 つだけ
+        unsafe { &mut *self.cell.value.get() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for RefMut<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for RefMut<'_, T> {
+    fn drop(&mut self) {
+        self.cell.borrow.set(UNUSED);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+
+    /// panic したときのメッセージ
+    fn panic_message(f: impl FnOnce()) -> Option<String> {
+        let payload = panic::catch_unwind(AssertUnwindSafe(f)).err()?;
+        payload.downcast_ref::<String>().cloned()
+    }
+
+    #[test]
+    fn test_shared_then_exclusive() {
+        let cell = MyRefCell::new(vec![1, 2]);
+        {
+            let a = cell.borrow();
+            let b = cell.borrow();
+            assert_eq!((a.len(), b.len()), (2, 2));
+            assert_eq!(cell.borrow.get(), 2);
+            assert!(cell.try_borrow_mut().is_err());
+        }
+        // ガードが落ちたので借りられる
+        cell.borrow_mut().push(3);
+        assert_eq!(cell.borrow.get(), UNUSED);
+        assert_eq!(cell.replace(vec![]), vec![1, 2, 3]);
+        assert_eq!(format!("{:?}", cell), "MyRefCell { value: [] }");
+
+        let mut cell = cell;
+        cell.get_mut().push(9);
+        assert_eq!(cell.into_inner(), vec![9]);
+    }
+
+    #[test]
+    fn test_conflicting_borrows_panic() {
+        let cell = MyRefCell::new(0);
+
+        let message = panic_message(|| {
+            let _reader = cell.borrow();
+            let _writer = cell.borrow_mut();
+        });
+        assert_eq!(message.as_deref(), Some("already borrowed"));
+
+        let message = panic_message(|| {
+            let _writer = cell.borrow_mut();
+            let _reader = cell.borrow();
+        });
+        assert_eq!(message.as_deref(), Some("already mutably borrowed"));
+
+        let message = panic_message(|| {
+            let _first = cell.borrow_mut();
+            let _second = cell.borrow_mut();
+        });
+        assert_eq!(message.as_deref(), Some("already mutably borrowed"));
+
+        // panic の巻き戻しでもガードの Drop が走るので、借用は残らない
+        assert_eq!(cell.borrow.get(), UNUSED);
+        *cell.borrow_mut() += 1;
+        assert_eq!(*cell.borrow(), 1);
+    }
+
+    #[test]
+    fn test_debug_while_mutably_borrowed() {
+        let cell = MyRefCell::new(1);
+        let guard = cell.borrow_mut();
+        assert_eq!(format!("{:?}", cell), "MyRefCell { <borrowed> }");
+        drop(guard);
+        assert_eq!(format!("{:?}", cell), "MyRefCell { value: 1 }");
+    }
+}
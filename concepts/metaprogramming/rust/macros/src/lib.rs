@@ -0,0 +1,214 @@
+//! 手続きマクロ
+//!
+//! - `#[derive(Describe)]`: フィールドから `Describe::describe` を生成する
+//! - `#[timed]`: 関数の実行時間を測って報告する
+//!
+//! どちらも `proc_macro::TokenStream` を `proc_macro2` に移してから処理する。
+//! `proc_macro` はコンパイラの中でしか使えないが、`proc_macro2` なら
+//! 普通の単体テストで展開結果を確かめられる。
+//!
+//! 生成したコードは `::metaprogramming::...` を参照するので、使う側は
+//! `metaprogramming` クレート経由で使う (このクレートを直接使わない)。
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, ItemFn, LitStr};
+
+/// フィールドを `Name { field: value, .. }` の形で説明する `Describe` を実装する
+///
+/// - 各フィールドは `Debug` で表示する (型引数には `Debug` 境界を足す)
+/// - `#[describe(skip)]` を付けたフィールドは出さない (パスワードなど)
+/// - `#[describe(rename = "...")]` で表示名を変える
+#[proc_macro_derive(Describe, attributes(describe))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_describe(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// 関数の本体の前に計測用のガードを置く
+///
+/// ガードが落ちるとき (`return`・`?`・panic を含む) に経過時間を報告するので、
+/// 本体をクロージャで包む方法と違って制御の流れを変えない。
+/// `#[timed("label")]` で報告の名前を変えられる (既定は関数名)。
+#[proc_macro_attribute]
+pub fn timed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    expand_timed(attr.into(), item).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// `#[describe(...)]` の中身
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    rename: Option<String>,
+}
+
+fn field_options(attrs: &[syn::Attribute]) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("describe")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                options.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("rename") {
+                options.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `skip` or `rename = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+fn expand_describe(mut input: DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(data) => {
+            return Err(syn::Error::new(
+                data.enum_token.span,
+                "Describe can only be derived for structs",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "Describe can only be derived for structs",
+            ))
+        }
+    };
+
+    // 表示する (名前, 式) の組。名前のないフィールドは位置を名前にする
+    let mut entries = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let options = field_options(&field.attrs)?;
+        if options.skip {
+            continue;
+        }
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(index);
+                quote!(#index)
+            }
+        };
+        let label = options.rename.unwrap_or_else(|| match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => index.to_string(),
+        });
+        entries.push((label, member));
+    }
+
+    let type_name = name.to_string();
+    let body = if matches!(fields, Fields::Unit) || entries.is_empty() {
+        quote!(::std::string::String::from(#type_name))
+    } else {
+        let format = format!(
+            "{} {{{{ {} }}}}",
+            type_name,
+            entries.iter().map(|(label, _)| format!("{}: {{:?}}", label)).collect::<Vec<_>>().join(", ")
+        );
+        let members = entries.iter().map(|(_, member)| member);
+        quote!(::std::format!(#format, #(&self.#members),*))
+    };
+
+    // フィールドを Debug で表示するので、型引数にも Debug を要求する
+    for param in input.generics.type_params_mut() {
+        param.bounds.push(syn::parse_quote!(::std::fmt::Debug));
+    }
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::metaprogramming::Describe for #name #type_generics #where_clause {
+            fn describe(&self) -> ::std::string::String {
+                #body
+            }
+        }
+    })
+}
+
+fn expand_timed(attr: TokenStream2, mut item: ItemFn) -> syn::Result<TokenStream2> {
+    let label = if attr.is_empty() {
+        item.sig.ident.to_string()
+    } else {
+        syn::parse2::<LitStr>(attr)?.value()
+    };
+    if let Some(constness) = &item.sig.constness {
+        // Drop を持つ値は const fn の中に置けない
+        return Err(syn::Error::new(constness.span, "#[timed] cannot be applied to a const fn"));
+    }
+
+    let block = &item.block;
+    item.block = syn::parse_quote!({
+        let __timed_guard = ::metaprogramming::TimedGuard::start(#label);
+        #block
+    });
+    Ok(quote!(#item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    /// 比べやすいように空白をそろえる
+    fn normalize(tokens: TokenStream2) -> String {
+        tokens.to_string().split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_expand_describe() {
+        let expanded = expand_describe(parse_quote! {
+            struct User<T> {
+                name: String,
+                #[describe(rename = "years")]
+                age: T,
+                #[describe(skip)]
+                password: String,
+            }
+        })
+        .unwrap();
+        let expanded = normalize(expanded);
+        assert!(expanded.contains("impl < T : :: std :: fmt :: Debug > :: metaprogramming :: Describe for User < T >"));
+        assert!(expanded.contains("\"User {{ name: {:?}, years: {:?} }}\" , & self . name , & self . age"));
+        assert!(!expanded.contains("password"));
+
+        let tuple = normalize(expand_describe(parse_quote!(struct Meters(f64);)).unwrap());
+        assert!(tuple.contains("\"Meters {{ 0: {:?} }}\" , & self . 0"));
+        let unit = normalize(expand_describe(parse_quote!(struct Marker;)).unwrap());
+        assert!(unit.contains(":: std :: string :: String :: from (\"Marker\")"));
+    }
+
+    #[test]
+    fn test_describe_errors() {
+        let error = expand_describe(parse_quote!(enum Color { Red })).unwrap_err();
+        assert_eq!(error.to_string(), "Describe can only be derived for structs");
+        let error = expand_describe(parse_quote! {
+            struct User {
+                #[describe(hide)]
+                name: String,
+            }
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "expected `skip` or `rename = \"...\"`");
+    }
+
+    #[test]
+    fn test_expand_timed() {
+        let expanded = expand_timed(TokenStream2::new(), parse_quote!(fn work(n: u64) -> u64 { n * 2 }));
+        let expanded = normalize(expanded.unwrap());
+        assert_eq!(
+            expanded,
+            "fn work (n : u64) -> u64 { let __timed_guard = :: metaprogramming :: TimedGuard :: start (\"work\") ; { n * 2 } }"
+        );
+
+        let labeled = expand_timed(quote!("load config"), parse_quote!(fn load() {})).unwrap();
+        assert!(normalize(labeled).contains("start (\"load config\")"));
+
+        assert!(expand_timed(quote!(42), parse_quote!(fn f() {})).is_err());
+        let error = expand_timed(TokenStream2::new(), parse_quote!(const fn f() {})).unwrap_err();
+        assert_eq!(error.to_string(), "#[timed] cannot be applied to a const fn");
+    }
+}
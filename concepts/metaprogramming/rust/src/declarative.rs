@@ -0,0 +1,252 @@
+//! macro_rules! の発展編
+//!
+//! - [`state_machine!`](crate::state_machine): トークンを先頭から 1 つずつ食べる再帰 (TT muncher)
+//! - [`count!`](crate::count): `@` で始まる内部ルールで補助のマクロを 1 つにまとめる
+//! - 衛生性 (hygiene): マクロの中の `let` は呼び出し側の変数とぶつからない
+//! - `$crate`: `#[macro_export]` したマクロが、どのクレートから呼ばれても自分の項目を指す
+//!
+//! `#[macro_export]` したマクロはクレートの直下に置かれるので、`metaprogramming::count!` のように呼ぶ。
+//!
+//! 衛生性のため、マクロの本体は呼び出し側のローカル変数を (引数で渡されない限り) 参照できない:
+//!
+//! ```compile_fail,E0425
+//! macro_rules! double_y {
+//!     () => {
+//!         y * 2
+//!     };
+//! }
+//!
+//! let y = 21;
+//! let _ = double_y!(); // マクロの `y` は定義した場所で探すので、この y は見えない
+//! ```
+//!
+//! 遷移の書き方を誤ると、最後のルールが `compile_error!` で止める:
+//!
+//! ```compile_fail
+//! metaprogramming::state_machine! {
+//!     machine Door { initial: Closed, states: [Closed, Opened] }
+//!     Closed -> Opened; // error: expected `From --event--> To;`
+//! }
+//! ```
+
+use std::fmt;
+
+/// 状態から出ていない遷移を求めた
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionError {
+    pub machine: &'static str,
+    pub state: String,
+    pub event: String,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: no transition from {} on {:?}", self.machine, self.state, self.event)
+    }
+}
+
+impl std::error::Error for TransitionError {}
+
+/// 状態機械を定義する
+///
+/// ```
+/// metaprogramming::state_machine! {
+///     machine Door { initial: Closed, states: [Closed, Opened, Locked] }
+///     Closed --open--> Opened;
+///     Opened --close--> Closed;
+///     Closed --lock--> Locked;
+///     Locked --unlock--> Closed;
+/// }
+///
+/// let door = Door::INITIAL.fire("open").unwrap();
+/// assert_eq!(door, Door::Opened);
+/// assert!(door.fire("lock").is_err());
+/// assert_eq!(Door::Closed.events(), vec!["open", "lock"]);
+/// ```
+///
+/// 矢印 `--event-->` は `-` `-` `event` `-` `->` というトークンの並びなので、
+/// `$($t:tt)*` の繰り返しでは分けられない。先頭の遷移を 1 つ読んでマッチの腕を
+/// `[...]` にため、残りのトークンで自分を呼び直す (`@munch`)。食べ尽くしたら `@emit` で出力する。
+#[macro_export]
+macro_rules! state_machine {
+    // 入口: 見出しを読んで、遷移の列を食べ始める
+    (
+        machine $name:ident { initial: $initial:ident, states: [$($state:ident),+ $(,)?] }
+        $($transitions:tt)*
+    ) => {
+        $crate::state_machine!(@munch $name $initial [$($state)+] [] $($transitions)*);
+    };
+
+    // 遷移を 1 つ食べて、(元, イベント, 先) を ため込む
+    (@munch $name:ident $initial:ident [$($state:ident)+] [$($arms:tt)*]
+        $from:ident -- $event:ident --> $to:ident ; $($rest:tt)*
+    ) => {
+        $crate::state_machine!(@munch $name $initial [$($state)+]
+            [$($arms)* ($from, $event, $to)]
+            $($rest)*);
+    };
+
+    // 食べ尽くした
+    (@munch $name:ident $initial:ident [$($state:ident)+] [$($arms:tt)*]) => {
+        $crate::state_machine!(@emit $name $initial [$($state)+] [$($arms)*]);
+    };
+
+    // どの形にも合わない遷移
+    (@munch $name:ident $initial:ident [$($state:ident)+] [$($arms:tt)*] $($rest:tt)+) => {
+        ::std::compile_error!(::std::concat!(
+            "expected `From --event--> To;` but found: ",
+            ::std::stringify!($($rest)+)
+        ));
+    };
+
+    (@emit $name:ident $initial:ident [$($state:ident)+] [$(($from:ident, $event:ident, $to:ident))*]) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($state),+
+        }
+
+        impl $name {
+            pub const INITIAL: $name = $name::$initial;
+
+            /// (元, イベント, 先) の一覧 (書いた順)
+            pub const TRANSITIONS: &'static [($name, &'static str, $name)] = &[
+                $(($name::$from, ::std::stringify!($event), $name::$to)),*
+            ];
+
+            /// イベントで次の状態へ (`$crate` なので、どのクレートで展開しても同じ型を指す)
+            pub fn fire(self, event: &str) -> ::std::result::Result<$name, $crate::declarative::TransitionError> {
+                $(
+                    if self == $name::$from && event == ::std::stringify!($event) {
+                        return ::std::result::Result::Ok($name::$to);
+                    }
+                )*
+                ::std::result::Result::Err($crate::declarative::TransitionError {
+                    machine: ::std::stringify!($name),
+                    state: ::std::format!("{:?}", self),
+                    event: event.to_string(),
+                })
+            }
+
+            /// この状態で受け付けるイベント
+            pub fn events(self) -> ::std::vec::Vec<&'static str> {
+                Self::TRANSITIONS
+                    .iter()
+                    .filter(|(from, _, _)| *from == self)
+                    .map(|(_, event, _)| *event)
+                    .collect()
+            }
+        }
+    };
+}
+
+/// トークン木の数をコンパイル時の定数として数える
+///
+/// ```
+/// const N: usize = metaprogramming::count!(a b (c d) [e]);
+/// assert_eq!(N, 4);
+/// ```
+///
+/// `@unit` は外から呼ぶためのものではない内部ルール。別のマクロに分けると
+/// それも `#[macro_export]` しなければならず、公開する名前が増える。
+#[macro_export]
+macro_rules! count {
+    (@unit $_t:tt) => {
+        ()
+    };
+    ($($t:tt)*) => {
+        <[()]>::len(&[$($crate::count!(@unit $t)),*])
+    };
+}
+
+/// 各値の `Describe::describe` を集める
+///
+/// `$crate::Describe` と書くので、呼び出し側が `Describe` を `use` していなくても、
+/// 同じ名前の別のトレイトを `use` していても、このクレートのトレイトを指す。
+#[macro_export]
+macro_rules! describe_all {
+    ($($value:expr),* $(,)?) => {
+        ::std::vec![$($crate::Describe::describe(&$value)),*]
+    };
+}
+
+/// 式を 1 度だけ評価して 2 倍にする (衛生性の例)
+///
+/// マクロの中の `tmp` は呼び出し側の `tmp` とは別の変数として扱われるので、
+/// `twice!(tmp + 1)` の `tmp` は呼び出し側のものを指す。
+#[macro_export]
+macro_rules! twice {
+    ($value:expr) => {{
+        let tmp = $value;
+        tmp + tmp
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    crate::state_machine! {
+        machine Light { initial: Off, states: [Off, On, Broken] }
+        Off --toggle--> On;
+        On --toggle--> Off;
+        On --overload--> Broken;
+    }
+
+    #[test]
+    fn test_state_machine() {
+        let light = Light::INITIAL;
+        assert_eq!(light, Light::Off);
+        let light = light.fire("toggle").unwrap();
+        assert_eq!(light, Light::On);
+        assert_eq!(light.events(), vec!["toggle", "overload"]);
+        let light = light.fire("overload").unwrap();
+        assert!(light.events().is_empty());
+
+        let error = light.fire("toggle").unwrap_err();
+        assert_eq!(error.to_string(), "Light: no transition from Broken on \"toggle\"");
+        assert_eq!(Light::TRANSITIONS.len(), 3);
+        assert_eq!(Light::TRANSITIONS[2], (Light::On, "overload", Light::Broken));
+    }
+
+    #[test]
+    fn test_count() {
+        const EMPTY: usize = crate::count!();
+        assert_eq!(EMPTY, 0);
+        assert_eq!(crate::count!(x), 1);
+        // グループ () [] {} は 1 つの木
+        assert_eq!(crate::count!(a, b; {c d}), 5);
+    }
+
+    #[test]
+    fn test_hygiene() {
+        // 引数の tmp は呼び出し側の tmp (マクロの let tmp に取り違えない)
+        let tmp = 10;
+        assert_eq!(crate::twice!(tmp + 1), 22);
+        assert_eq!(tmp, 10);
+
+        // 式は 1 度だけ評価される
+        let mut calls = 0;
+        let mut next = || {
+            calls += 1;
+            calls
+        };
+        assert_eq!(crate::twice!(next()), 2);
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_dollar_crate() {
+        // 同じ名前の別のトレイトがあっても $crate::Describe を使う
+        #[allow(dead_code)]
+        trait Describe {
+            fn describe(&self) -> String;
+        }
+
+        #[derive(crate::Describe)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let described = crate::describe_all![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }];
+        assert_eq!(described, vec!["Point { x: 1, y: 2 }", "Point { x: 3, y: 4 }"]);
+    }
+}
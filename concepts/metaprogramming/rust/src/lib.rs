@@ -0,0 +1,127 @@
+//! メタプログラミング - ライブラリ部分
+//!
+//! 手続きマクロ (`macros/`) が生成するコードはこのクレートの項目を参照する。
+//! マクロもここから再公開するので、使う側は `metaprogramming` だけに依存すればよい。
+//!
+//! マクロの誤った使い方はコンパイルエラーになる (trybuild の代わりに `compile_fail` の doctest で確かめる):
+//!
+//! ```compile_fail
+//! use metaprogramming::Describe;
+//!
+//! #[derive(Describe)] // error: Describe can only be derived for structs
+//! enum Color {
+//!     Red,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use metaprogramming::Describe;
+//!
+//! #[derive(Describe)]
+//! struct User {
+//!     #[describe(hide)] // error: expected `skip` or `rename = "..."`
+//!     name: String,
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! use metaprogramming::Describe;
+//!
+//! struct Secret;
+//!
+//! #[derive(Describe)]
+//! struct Vault {
+//!     secret: Secret, // error: Secret は Debug でない
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[metaprogramming::timed] // error: #[timed] cannot be applied to a const fn
+//! const fn answer() -> u32 {
+//!     42
+//! }
+//! ```
+//!
+//! ```compile_fail
+//! #[metaprogramming::timed] // error: expected `fn`
+//! struct NotAFunction;
+//! ```
+
+pub mod declarative;
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+// 生成したコードの `::metaprogramming::...` がこのクレートの中 (単体テスト) でも解決できるように
+extern crate self as metaprogramming;
+
+/// `#[derive(Describe)]`・`#[timed]`
+pub use metaprogramming_macros::{timed, Describe};
+
+/// 値を 1 行で説明する (`#[derive(Describe)]` で実装できる)
+pub trait Describe {
+    fn describe(&self) -> String;
+}
+
+thread_local! {
+    /// このスレッドで測った (名前, 時間)
+    static TIMINGS: RefCell<Vec<(&'static str, Duration)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// `#[timed]` が関数の先頭に置くガード。落ちるときに経過時間を報告する
+pub struct TimedGuard {
+    label: &'static str,
+    started: Instant,
+}
+
+impl TimedGuard {
+    pub fn start(label: &'static str) -> Self {
+        TimedGuard { label, started: Instant::now() }
+    }
+}
+
+impl Drop for TimedGuard {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        eprintln!("[timed] {} took {:?}", self.label, elapsed);
+        TIMINGS.with(|timings| timings.borrow_mut().push((self.label, elapsed)));
+    }
+}
+
+/// このスレッドで記録した計測結果を取り出す (取り出した分は消える)
+pub fn take_timings() -> Vec<(&'static str, Duration)> {
+    TIMINGS.with(|timings| timings.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Describe)]
+    struct Config<T> {
+        name: &'static str,
+        #[describe(rename = "value")]
+        inner: T,
+    }
+
+    #[timed]
+    fn double(n: u64) -> u64 {
+        n * 2
+    }
+
+    #[test]
+    fn test_derive_inside_defining_crate() {
+        let config = Config { name: "retries", inner: 3 };
+        assert_eq!(config.describe(), "Config { name: \"retries\", value: 3 }");
+    }
+
+    #[test]
+    fn test_timed_records_each_call() {
+        take_timings();
+        assert_eq!(double(21), 42);
+        assert_eq!(double(1), 2);
+        let labels: Vec<&str> = take_timings().into_iter().map(|(label, _)| label).collect();
+        assert_eq!(labels, vec!["double", "double"]);
+        assert!(take_timings().is_empty());
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Rust のマクロと derive
 
+use metaprogramming::{timed, Describe};
+
 // === マクロ定義 (使用前に定義が必要) ===
 
 /// 単純な加算マクロ
@@ -74,6 +76,8 @@ fn main() {
     demo_derive_macros();
     demo_custom_derive();
     demo_macro_rules();
+    demo_proc_macros();
+    demo_advanced_macro_rules();
 }
 
 /// 宣言的マクロ (macro_rules!)
@@ -161,28 +165,20 @@ impl std::fmt::Display for User {
     }
 }
 
-/// カスタムトレイト
-trait Describe {
-    fn describe(&self) -> String;
-}
-
+/// 手で書いた実装 (Product は #[derive(Describe)] で生成する)
 impl Describe for User {
     fn describe(&self) -> String {
         format!("User: {} is {} years old", self.name, self.age)
     }
 }
 
+#[derive(Describe)]
 struct Product {
     name: String,
+    #[describe(rename = "price_usd")]
     price: f64,
 }
 
-impl Describe for Product {
-    fn describe(&self) -> String {
-        format!("Product: {} costs ${:.2}", self.name, self.price)
-    }
-}
-
 /// macro_rules! パターン
 fn demo_macro_rules() {
     println!("--- macro_rules! Patterns ---");
@@ -214,6 +210,83 @@ fn demo_macro_rules() {
     println!();
 }
 
+/// 手続きマクロ (macros/ クレートで実装)
+fn demo_proc_macros() {
+    println!("--- Procedural Macros ---");
+
+    #[derive(Describe)]
+    struct Account {
+        id: u64,
+        owner: String,
+        #[describe(skip)]
+        #[allow(dead_code)]
+        api_key: String,
+    }
+
+    let account = Account {
+        id: 7,
+        owner: "Carol".to_string(),
+        api_key: "secret".to_string(),
+    };
+    println!("#[derive(Describe)] (skip した api_key は出ない): {}", account.describe());
+
+    // 報告は stderr に出る: [timed] fibonacci took ...
+    #[timed]
+    fn fibonacci(n: u32) -> u64 {
+        (0..n).fold((0u64, 1u64), |(a, b), _| (b, a + b)).0
+    }
+
+    println!("#[timed] fibonacci(50) = {}", fibonacci(50));
+    for (label, elapsed) in metaprogramming::take_timings() {
+        println!("  recorded: {} took {:?}", label, elapsed);
+    }
+
+    println!();
+}
+
+metaprogramming::state_machine! {
+    machine Order { initial: Cart, states: [Cart, Paid, Shipped, Cancelled] }
+    Cart --pay--> Paid;
+    Cart --cancel--> Cancelled;
+    Paid --ship--> Shipped;
+    Paid --cancel--> Cancelled;
+}
+
+/// macro_rules! の発展編 (src/declarative.rs)
+fn demo_advanced_macro_rules() {
+    println!("--- Advanced macro_rules! ---");
+
+    // TT muncher で作った状態機械
+    let mut order = Order::INITIAL;
+    for event in ["pay", "ship", "cancel"] {
+        match order.fire(event) {
+            Ok(next) => {
+                println!("state_machine!: {:?} --{}--> {:?}", order, event, next);
+                order = next;
+            }
+            Err(e) => println!("state_machine!: {}", e),
+        }
+    }
+    println!("Order::Cart で受け付けるイベント: {:?}", Order::Cart.events());
+
+    // 内部ルール
+    const TOKENS: usize = metaprogramming::count!(let x = 1;);
+    println!("count!(let x = 1;) = {}", TOKENS);
+
+    // 衛生性: マクロの中の tmp と外の tmp は別の変数
+    let tmp = 10;
+    println!("twice!(tmp + 1) = {} (tmp = {} のまま)", metaprogramming::twice!(tmp + 1), tmp);
+
+    // $crate: 呼び出し側の use に関係なく metaprogramming::Describe を使う
+    let products = metaprogramming::describe_all![
+        Product { name: "Pen".to_string(), price: 1.5 },
+        Product { name: "Ink".to_string(), price: 4.0 },
+    ];
+    println!("describe_all!: {:?}", products);
+
+    println!();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,87 @@
+//! 手続きマクロを外のクレートとして使う (生成したコードが `::metaprogramming` で解決できること)
+
+use metaprogramming::{take_timings, timed, Describe};
+
+#[derive(Describe)]
+struct User {
+    name: String,
+    age: u32,
+    #[describe(skip)]
+    #[allow(dead_code)]
+    password: String,
+}
+
+#[derive(Describe)]
+struct Meters(f64);
+
+#[derive(Describe)]
+struct Marker;
+
+#[derive(Describe)]
+struct Pair<A, B>
+where
+    A: Clone,
+{
+    left: A,
+    right: B,
+}
+
+#[test]
+fn test_derive_describe() {
+    let user = User { name: "Alice".to_string(), age: 30, password: "hunter2".to_string() };
+    assert_eq!(user.describe(), "User { name: \"Alice\", age: 30 }");
+    assert_eq!(Meters(1.5).describe(), "Meters { 0: 1.5 }");
+    assert_eq!(Marker.describe(), "Marker");
+    assert_eq!(Pair { left: 'a', right: [1, 2] }.describe(), "Pair { left: 'a', right: [1, 2] }");
+}
+
+#[timed("parse number")]
+fn parse(text: &str) -> Result<u32, std::num::ParseIntError> {
+    let value = text.trim().parse::<u32>()?;
+    Ok(value)
+}
+
+#[timed]
+fn early_return(flag: bool) -> &'static str {
+    if flag {
+        return "early";
+    }
+    "late"
+}
+
+struct Counter(u32);
+
+impl Counter {
+    #[timed]
+    fn bump(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+#[test]
+fn test_timed_keeps_control_flow() {
+    take_timings();
+    assert_eq!(parse(" 7 "), Ok(7));
+    // ? で早く抜けても報告される
+    assert!(parse("x").is_err());
+    assert_eq!(early_return(true), "early");
+    assert_eq!(early_return(false), "late");
+    let mut counter = Counter(0);
+    assert_eq!(counter.bump(), 1);
+
+    let labels: Vec<&str> = take_timings().into_iter().map(|(label, _)| label).collect();
+    assert_eq!(labels, vec!["parse number", "parse number", "early_return", "early_return", "bump"]);
+}
+
+#[test]
+fn test_timed_reports_on_panic() {
+    #[timed]
+    fn explode() {
+        panic!("boom");
+    }
+
+    take_timings();
+    assert!(std::panic::catch_unwind(explode).is_err());
+    assert_eq!(take_timings().len(), 1);
+}
@@ -0,0 +1,354 @@
+//! トレイトの発展編
+//!
+//! - ジェネリック関連型 (GAT): 自分の中を貸し出すイテレータ
+//! - 関連定数: 型ごとに決まる値
+//! - 既定の型引数: `Add<Rhs = Self>` のように省略できる型引数
+//! - 封印したトレイト: 外のクレートに実装させない
+//! - ブランケット実装の落とし穴: 後から個別の実装を足せない
+
+use std::fmt::{self, Display};
+use std::ops::Add;
+
+// ============================================================
+// ジェネリック関連型 (GAT)
+// ============================================================
+
+/// 要素を貸し出すイテレータ
+///
+/// `Iterator::Item` はライフタイムを持てないので、`next` が返す参照は
+/// イテレータ自身より長く生きられる前提になる。そのため「前の要素を返し終わってから
+/// 次を貸す」(重なる `&mut` の窓など) は書けない。`Item<'a>` にすると、
+/// 返す参照を `next` の `&'a mut self` の借用に結び付けられる。
+///
+/// `for` 文は `Iterator` にしか使えないので `while let` で回す。`for_each` のような
+/// `impl for<'a> FnMut(Self::Item<'a>)` を受け取る既定メソッドは、今の型検査では
+/// `Self: 'static` を要求してしまい、借りた slice を持つ型では使えない。
+pub trait LendingIterator {
+    type Item<'a>
+    where
+        Self: 'a;
+
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+}
+
+/// 長さ `size` の重なる窓を `&mut` で順に貸す (`slice::windows` の可変版)
+///
+/// 普通の `Iterator` にはできない。2 つの窓を同時に持てると、同じ要素への
+/// `&mut` が 2 つできてしまうため:
+///
+/// ```compile_fail,E0499
+/// use oop::advanced_traits::{LendingIterator, WindowsMut};
+///
+/// let mut values = [1, 2, 3];
+/// let mut windows = WindowsMut::new(&mut values, 2);
+/// let first = windows.next().unwrap();
+/// let second = windows.next().unwrap(); // first を借りている間は次を借りられない
+/// first[1] = second[0];
+/// ```
+pub struct WindowsMut<'s, T> {
+    slice: &'s mut [T],
+    size: usize,
+    start: usize,
+}
+
+impl<'s, T> WindowsMut<'s, T> {
+    pub fn new(slice: &'s mut [T], size: usize) -> Self {
+        assert!(size > 0, "window size must be non-zero");
+        WindowsMut { slice, size, start: 0 }
+    }
+}
+
+impl<'s, T> LendingIterator for WindowsMut<'s, T> {
+    type Item<'a>
+        = &'a mut [T]
+    where
+        Self: 'a;
+
+    fn next(&mut self) -> Option<&mut [T]> {
+        let window = self.slice.get_mut(self.start..self.start + self.size)?;
+        self.start += 1;
+        Some(window)
+    }
+}
+
+// ============================================================
+// 関連定数
+// ============================================================
+
+/// 型ごとに決まる定数を持つ図形
+pub trait Polygon {
+    const SIDES: u32;
+    const NAME: &'static str;
+
+    /// 既定値を持つ関連定数 (実装側で上書きできる)
+    const REGULAR: bool = false;
+
+    /// 内角の和 (度)。インスタンスが無くても型から計算できる
+    fn interior_angle_sum() -> u32 {
+        (Self::SIDES - 2) * 180
+    }
+}
+
+pub struct Triangle;
+pub struct Square;
+
+impl Polygon for Triangle {
+    const SIDES: u32 = 3;
+    const NAME: &'static str = "triangle";
+}
+
+impl Polygon for Square {
+    const SIDES: u32 = 4;
+    const NAME: &'static str = "square";
+    const REGULAR: bool = true;
+}
+
+/// 型だけから説明を作る
+pub fn describe<P: Polygon>() -> String {
+    format!(
+        "{}: {} sides, angles sum to {}°{}",
+        P::NAME,
+        P::SIDES,
+        P::interior_angle_sum(),
+        if P::REGULAR { " (regular)" } else { "" }
+    )
+}
+
+// ============================================================
+// 既定の型引数と、封印したトレイト
+// ============================================================
+
+mod sealed {
+    /// 外からは名前を書けないので、このクレートの外では実装できない
+    pub trait Sealed {}
+}
+
+/// 長さの単位 (このクレートにある型だけが実装できる)
+///
+/// 封印しておくと、後からメソッドを足しても外のクレートを壊さない。
+/// 外で実装しようとすると `Sealed` を満たせずにエラーになる:
+///
+/// ```compile_fail,E0277
+/// use oop::advanced_traits::Unit;
+///
+/// struct Inches(f64);
+///
+/// impl Unit for Inches {
+///     const SUFFIX: &'static str = "in";
+///     fn to_meters(&self) -> f64 {
+///         self.0 * 0.0254
+///     }
+/// }
+/// ```
+pub trait Unit: sealed::Sealed {
+    const SUFFIX: &'static str;
+    fn to_meters(&self) -> f64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Meters(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Centimeters(pub f64);
+
+impl sealed::Sealed for Meters {}
+impl sealed::Sealed for Centimeters {}
+
+impl Unit for Meters {
+    const SUFFIX: &'static str = "m";
+    fn to_meters(&self) -> f64 {
+        self.0
+    }
+}
+
+impl Unit for Centimeters {
+    const SUFFIX: &'static str = "cm";
+    fn to_meters(&self) -> f64 {
+        self.0 / 100.0
+    }
+}
+
+/// `Add` は `trait Add<Rhs = Self>`。`impl Add for Meters` は `Add<Meters>` の省略
+impl Add for Meters {
+    type Output = Meters;
+
+    fn add(self, rhs: Meters) -> Meters {
+        Meters(self.0 + rhs.0)
+    }
+}
+
+/// 型引数を書けば別の型とも足せる
+impl Add<Centimeters> for Meters {
+    type Output = Meters;
+
+    fn add(self, rhs: Centimeters) -> Meters {
+        Meters(self.0 + rhs.to_meters())
+    }
+}
+
+/// 自分で定義するトレイトにも既定の型引数を付けられる
+pub trait Render<Format = Plain> {
+    fn render(&self) -> String;
+}
+
+/// [`Render`] の既定の形式
+pub struct Plain;
+pub struct Json;
+
+impl<U: Unit> Render for U {
+    fn render(&self) -> String {
+        format!("{}{}", self.to_meters(), Meters::SUFFIX)
+    }
+}
+
+impl<U: Unit> Render<Json> for U {
+    fn render(&self) -> String {
+        format!("{{\"meters\": {}}}", self.to_meters())
+    }
+}
+
+// ============================================================
+// ブランケット実装の落とし穴
+// ============================================================
+
+/// `Display` を実装した型すべてに実装する (ブランケット実装)
+///
+/// 便利だが、後から個別の実装を足せなくなる。`Display` を実装した型に
+/// 別の実装を書くと、2 つの実装が重なってエラーになる:
+///
+/// ```compile_fail,E0119
+/// use std::fmt::Display;
+///
+/// trait Describe {
+///     fn describe(&self) -> String;
+/// }
+///
+/// impl<T: Display> Describe for T {
+///     fn describe(&self) -> String {
+///         format!("<{}>", self)
+///     }
+/// }
+///
+/// // i32 は Display なので上と重なる
+/// impl Describe for i32 {
+///     fn describe(&self) -> String {
+///         format!("int {}", self)
+///     }
+/// }
+/// ```
+///
+/// 今は `Display` でない型でも、「上流のクレートが後で `Display` を実装するかもしれない」
+/// 型には書けない:
+///
+/// ```compile_fail,E0119
+/// use std::fmt::Display;
+///
+/// trait Describe {
+///     fn describe(&self) -> String;
+/// }
+///
+/// impl<T: Display> Describe for T {
+///     fn describe(&self) -> String {
+///         format!("<{}>", self)
+///     }
+/// }
+///
+/// // 標準ライブラリが将来 Vec<u8> に Display を実装するかもしれない
+/// impl Describe for Vec<u8> {
+///     fn describe(&self) -> String {
+///         format!("{} bytes", self.len())
+///     }
+/// }
+/// ```
+///
+/// 回避策は、ブランケット実装の代わりに印のトレイトで明示的に選ばせること ([`Labeled`])。
+pub trait Describe {
+    fn describe(&self) -> String;
+}
+
+impl<T: Display> Describe for T {
+    fn describe(&self) -> String {
+        format!("<{}>", self)
+    }
+}
+
+/// 印のトレイト: 実装した型だけが [`Label`] の既定の実装を使う
+pub trait Labeled: Display {}
+
+pub trait Label {
+    fn label(&self) -> String;
+}
+
+/// `Labeled` はこのクレートのトレイトなので、外の型に後から実装されることはない
+/// (重なりの心配がない)
+impl<T: Labeled> Label for T {
+    fn label(&self) -> String {
+        format!("[{}]", self)
+    }
+}
+
+impl Labeled for Meters {}
+
+/// `Labeled` でないので個別に実装できる
+impl Label for Centimeters {
+    fn label(&self) -> String {
+        format!("[{} cm]", self.0)
+    }
+}
+
+impl Display for Meters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.0, Self::SUFFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lending_iterator() {
+        let mut values = [1, 2, 3, 4, 5];
+        // 各窓で後ろの要素に前の要素を足す (前の窓で書き換えた値が次の窓に見える)
+        let mut windows = WindowsMut::new(&mut values, 2);
+        while let Some(window) = windows.next() {
+            window[1] += window[0];
+        }
+        assert_eq!(values, [1, 3, 6, 10, 15]);
+
+        let mut windows = WindowsMut::new(&mut values, 3);
+        let mut count = 0;
+        while let Some(window) = windows.next() {
+            assert_eq!(window.len(), 3);
+            count += 1;
+        }
+        assert_eq!(count, 3);
+        assert!(WindowsMut::new(&mut values, 6).next().is_none());
+    }
+
+    #[test]
+    fn test_associated_consts() {
+        assert_eq!(Triangle::interior_angle_sum(), 180);
+        assert_eq!(Square::interior_angle_sum(), 360);
+        assert_eq!(describe::<Triangle>(), "triangle: 3 sides, angles sum to 180°");
+        assert_eq!(describe::<Square>(), "square: 4 sides, angles sum to 360° (regular)");
+    }
+
+    #[test]
+    fn test_default_type_parameters() {
+        assert_eq!(Meters(1.5) + Meters(2.0), Meters(3.5));
+        assert_eq!(Meters(1.0) + Centimeters(50.0), Meters(1.5));
+        // 型引数を省略すると Plain
+        assert_eq!(<Centimeters as Render>::render(&Centimeters(250.0)), "2.5m");
+        assert_eq!(<Centimeters as Render<Json>>::render(&Centimeters(250.0)), "{\"meters\": 2.5}");
+    }
+
+    #[test]
+    fn test_blanket_impls() {
+        assert_eq!(42.describe(), "<42>");
+        assert_eq!("text".describe(), "<text>");
+        assert_eq!(Meters(2.0).describe(), "<2m>");
+        assert_eq!(Meters(2.0).label(), "[2m]");
+        assert_eq!(Centimeters(5.0).label(), "[5 cm]");
+    }
+}
@@ -0,0 +1,6 @@
+//! # Rust のオブジェクト指向 (ライブラリ部分)
+//!
+//! `main.rs` のデモから使う。外のクレートからは書けないこと (封印したトレイトの実装など) は
+//! `compile_fail` の doctest で確かめている。
+
+pub mod advanced_traits;
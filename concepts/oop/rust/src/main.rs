@@ -3,6 +3,11 @@
 //! Rust は古典的なOOPではなく、Trait ベースの多態性を採用。
 //! 「継承より合成」の原則を言語レベルでサポート。
 
+use oop::advanced_traits::{
+    self, Centimeters, Describe, Json, Label, LendingIterator, Meters, Render, Square, Triangle, Unit,
+    WindowsMut,
+};
+
 fn main() {
     println!("=== Rust OOP ===\n");
 
@@ -11,6 +16,7 @@ fn main() {
     trait_objects();
     composition_over_inheritance();
     associated_types();
+    advanced_traits();
 }
 
 /// 構造体と impl
@@ -112,6 +118,7 @@ fn traits_example() {
 
     println!("  article: {}", article.summarize());
     println!("  tweet: {}", tweet.summarize());
+    println!("  preview (デフォルト実装): {}", tweet.preview());
 
     // ジェネリクスで Trait 境界
     fn notify<T: Summary>(item: &T) {
@@ -252,3 +259,35 @@ fn associated_types() {
     println!("  stack top: {:?}", stack.get());
     println!();
 }
+
+/// トレイトの発展編 (外から書けないことは src/advanced_traits.rs の doctest)
+fn advanced_traits() {
+    println!("--- トレイト (発展) ---");
+
+    // GAT: 重なる &mut の窓を順に貸す
+    let mut values = [1, 2, 3, 4];
+    let mut windows = WindowsMut::new(&mut values, 2);
+    while let Some(window) = windows.next() {
+        window[1] += window[0];
+    }
+    println!("  LendingIterator (累積和): {:?}", values);
+
+    // 関連定数
+    println!("  {}", advanced_traits::describe::<Triangle>());
+    println!("  {}", advanced_traits::describe::<Square>());
+
+    // 既定の型引数: Add<Rhs = Self>
+    println!("  Meters + Meters: {}", Meters(1.0) + Meters(2.0));
+    println!("  Meters + Centimeters: {}", Meters(1.0) + Centimeters(50.0));
+    // 型の位置で `Render` と書くと `Render<Plain>` (式の位置の `Render::render` では推論できない)
+    println!("  Render (既定は Plain): {}", <Centimeters as Render>::render(&Centimeters(20.0)));
+    println!("  Render<Json>: {}", <Centimeters as Render<Json>>::render(&Centimeters(20.0)));
+
+    // 封印したトレイト: Unit は Meters と Centimeters だけ
+    println!("  Unit::SUFFIX: {} / {}", Meters::SUFFIX, Centimeters::SUFFIX);
+
+    // ブランケット実装と、印のトレイトで選ばせる実装
+    println!("  Describe (Display なら何でも): {} {}", 42.describe(), Meters(3.0).describe());
+    println!("  Label: {} {}", Meters(3.0).label(), Centimeters(3.0).label());
+    println!();
+}
@@ -0,0 +1,353 @@
+//! const ジェネリクスとコンパイル時の評価
+//!
+//! - `const N: usize` を型引数にして、大きさを型に持たせる ([`Matrix`])
+//! - `const fn` はコンパイル時にも実行時にも呼べる ([`fib`], [`FIB_TABLE`])
+//! - `const _: () = assert!(..)` で条件をコンパイル時に確かめる
+//! - ジェネリックな関連定数の中の `assert!` で、型引数ごとに確かめる ([`RingBuffer`])
+
+use std::fmt;
+use std::ops::{Add, Mul};
+
+// ============================================================
+// 大きさを型に持つ行列
+// ============================================================
+
+/// `R` 行 `C` 列の行列 (大きさは型の一部なので、実行時には持たない)
+///
+/// 掛け算は `R×K` と `K×C` の組み合わせにしか実装していないので、
+/// 大きさが合わない掛け算はコンパイルエラーになる:
+///
+/// ```compile_fail,E0308
+/// use type_system::const_generics::Matrix;
+///
+/// let a = Matrix::<2, 3>::zero();
+/// let b = Matrix::<2, 3>::zero();
+/// let _ = a * b; // 3 列と 2 行は掛けられない
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Matrix<const R: usize, const C: usize> {
+    rows: [[i64; C]; R],
+}
+
+impl<const R: usize, const C: usize> Matrix<R, C> {
+    pub const fn new(rows: [[i64; C]; R]) -> Self {
+        Matrix { rows }
+    }
+
+    pub const fn zero() -> Self {
+        Matrix { rows: [[0; C]; R] }
+    }
+
+    /// (行数, 列数)。型から決まるので `const fn` にできる
+    pub const fn shape(&self) -> (usize, usize) {
+        (R, C)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<i64> {
+        self.rows.get(row)?.get(col).copied()
+    }
+
+    /// 転置すると型も `C×R` に変わる
+    pub fn transpose(&self) -> Matrix<C, R> {
+        let mut out = Matrix::<C, R>::zero();
+        for (r, row) in self.rows.iter().enumerate() {
+            for (c, &value) in row.iter().enumerate() {
+                out.rows[c][r] = value;
+            }
+        }
+        out
+    }
+}
+
+/// 正方行列にだけあるもの
+impl<const N: usize> Matrix<N, N> {
+    pub const fn identity() -> Self {
+        let mut rows = [[0; N]; N];
+        // const fn の中では for が使えないので while で回す
+        let mut i = 0;
+        while i < N {
+            rows[i][i] = 1;
+            i += 1;
+        }
+        Matrix { rows }
+    }
+
+    pub fn trace(&self) -> i64 {
+        (0..N).map(|i| self.rows[i][i]).sum()
+    }
+}
+
+impl<const R: usize, const C: usize> Add for Matrix<R, C> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        for (row, rhs_row) in self.rows.iter_mut().zip(rhs.rows) {
+            for (value, rhs_value) in row.iter_mut().zip(rhs_row) {
+                *value += rhs_value;
+            }
+        }
+        self
+    }
+}
+
+/// `R×K` と `K×C` の積は `R×C`
+impl<const R: usize, const K: usize, const C: usize> Mul<Matrix<K, C>> for Matrix<R, K> {
+    type Output = Matrix<R, C>;
+
+    fn mul(self, rhs: Matrix<K, C>) -> Matrix<R, C> {
+        let mut out = Matrix::<R, C>::zero();
+        for r in 0..R {
+            for c in 0..C {
+                out.rows[r][c] = (0..K).map(|k| self.rows[r][k] * rhs.rows[k][c]).sum();
+            }
+        }
+        out
+    }
+}
+
+impl<const R: usize, const C: usize> fmt::Display for Matrix<R, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self.rows.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let cells: Vec<String> = row.iter().map(i64::to_string).collect();
+            write!(f, "[{}]", cells.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 配列の長さも const 引数として受け取れる
+pub fn sum_array<const N: usize>(values: [i64; N]) -> i64 {
+    values.iter().sum()
+}
+
+/// slice から固定長の配列へ (長さが違えば `Err`)
+pub fn first_chunk<const N: usize>(values: &[i64]) -> Result<[i64; N], String> {
+    values
+        .get(..N)
+        .ok_or_else(|| format!("need {} values, got {}", N, values.len()))?
+        .try_into()
+        .map_err(|_| "length mismatch".to_string())
+}
+
+// ============================================================
+// const fn とコンパイル時の表
+// ============================================================
+
+/// フィボナッチ数 (`const` の初期化にも使える)
+pub const fn fib(n: u32) -> u64 {
+    let (mut a, mut b) = (0u64, 1u64);
+    let mut i = 0;
+    while i < n {
+        let next = a + b;
+        a = b;
+        b = next;
+        i += 1;
+    }
+    a
+}
+
+pub const fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+/// 長さ `N` の表をコンパイル時に作る
+const fn fib_table<const N: usize>() -> [u64; N] {
+    let mut table = [0; N];
+    let mut i = 0;
+    while i < N {
+        table[i] = fib(i as u32);
+        i += 1;
+    }
+    table
+}
+
+/// ビルド時に計算済みの表 (実行時には読むだけ)
+pub const FIB_TABLE: [u64; 20] = fib_table();
+
+// 静的アサーション: 成り立たなければコンパイルが止まる
+const _: () = assert!(FIB_TABLE[19] == 4181);
+const _: () = assert!(gcd(1071, 462) == 21);
+const _: () = assert!(std::mem::size_of::<Matrix<2, 3>>() == 6 * std::mem::size_of::<i64>());
+
+// ============================================================
+// 容量をコンパイル時に確かめるリングバッファ
+// ============================================================
+
+/// 容量 `N` のリングバッファ (満杯なら一番古い要素を押し出す)
+///
+/// 位置を `% N` の代わりに `& (N - 1)` で求めるので、`N` は 2 の冪でなければならない。
+/// これを [`RingBuffer::CAPACITY_OK`] の `assert!` でコンパイル時に確かめる。
+/// 関連定数は型引数ごとに評価されるので、違反すると使った所でビルドが止まる:
+///
+/// ```compile_fail,E0080
+/// use type_system::const_generics::RingBuffer;
+///
+/// let mut ring = RingBuffer::<i32, 3>::new();
+/// ring.push(1);
+/// ```
+///
+/// 容量 0 も同じく通らない:
+///
+/// ```compile_fail,E0080
+/// use type_system::const_generics::RingBuffer;
+///
+/// let _ = RingBuffer::<i32, 0>::new();
+/// ```
+#[derive(Debug)]
+pub struct RingBuffer<T, const N: usize> {
+    slots: [Option<T>; N],
+    /// 一番古い要素の位置
+    head: usize,
+    len: usize,
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// 容量の条件。`new` で参照するので、型ごとに 1 度だけコンパイル時に評価される
+    pub const CAPACITY_OK: () = assert!(N.is_power_of_two(), "RingBuffer capacity must be a non-zero power of two");
+
+    const MASK: usize = N - 1;
+
+    pub fn new() -> Self {
+        let () = Self::CAPACITY_OK;
+        RingBuffer {
+            slots: std::array::from_fn(|_| None),
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 末尾に入れる。満杯なら一番古い要素を返す
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let slot = (self.head + self.len) & Self::MASK;
+        if self.len == N {
+            self.head = (self.head + 1) & Self::MASK;
+            self.slots[slot].replace(value)
+        } else {
+            self.len += 1;
+            self.slots[slot] = Some(value);
+            None
+        }
+    }
+
+    /// 一番古い要素を取り出す
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.slots[self.head].take();
+        self.head = (self.head + 1) & Self::MASK;
+        self.len -= 1;
+        value
+    }
+
+    /// 古い順に
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len).filter_map(move |i| self.slots[(self.head + i) & Self::MASK].as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix() {
+        let a = Matrix::new([[1, 2, 3], [4, 5, 6]]);
+        let b = Matrix::new([[7, 8], [9, 10], [11, 12]]);
+        let product: Matrix<2, 2> = a * b;
+        assert_eq!(product, Matrix::new([[58, 64], [139, 154]]));
+        assert_eq!(product * Matrix::identity(), product);
+        assert_eq!(product.trace(), 212);
+
+        let t = a.transpose();
+        assert_eq!(t.shape(), (3, 2));
+        assert_eq!(t.get(2, 1), Some(6));
+        assert_eq!(t.get(3, 0), None);
+        assert_eq!(a + a, Matrix::new([[2, 4, 6], [8, 10, 12]]));
+        assert_eq!(a.to_string(), "[1, 2, 3]\n[4, 5, 6]");
+    }
+
+    #[test]
+    fn test_const_fn() {
+        // 同じ関数を実行時にも呼べる
+        let n = std::hint::black_box(19);
+        assert_eq!(fib(n), FIB_TABLE[19]);
+        assert_eq!(&FIB_TABLE[..8], &[0, 1, 1, 2, 3, 5, 8, 13]);
+        assert_eq!(gcd(48, 18), 6);
+        assert_eq!(gcd(7, 0), 7);
+
+        const IDENTITY: Matrix<3, 3> = Matrix::identity();
+        assert_eq!(IDENTITY.trace(), 3);
+        assert_eq!(sum_array([1, 2, 3, 4]), 10);
+        assert_eq!(first_chunk::<2>(&[5, 6, 7]), Ok([5, 6]));
+        assert!(first_chunk::<4>(&[5, 6, 7]).is_err());
+    }
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut ring = RingBuffer::<i32, 4>::new();
+        assert_eq!(ring.capacity(), 4);
+        assert_eq!(ring.pop(), None);
+        for i in 1..=4 {
+            assert_eq!(ring.push(i), None);
+        }
+        // 満杯なので古い順に押し出される
+        assert_eq!(ring.push(5), Some(1));
+        assert_eq!(ring.push(6), Some(2));
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+        assert_eq!(ring.pop(), Some(3));
+        ring.push(7);
+        assert_eq!(ring.len(), 4);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![4, 5, 6, 7]);
+        while ring.pop().is_some() {}
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn test_ring_buffer_matches_vecdeque() {
+        use std::collections::VecDeque;
+
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut ring = RingBuffer::<u64, 8>::new();
+        let mut model = VecDeque::new();
+        for step in 0..5_000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            if state.is_multiple_of(3) {
+                assert_eq!(ring.pop(), model.pop_front());
+            } else {
+                let evicted = if model.len() == 8 { model.pop_front() } else { None };
+                model.push_back(step);
+                assert_eq!(ring.push(step), evicted);
+            }
+            assert!(ring.iter().eq(model.iter()));
+        }
+    }
+}
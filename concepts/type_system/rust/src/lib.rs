@@ -0,0 +1,5 @@
+//! # Rust の型システム (ライブラリ部分)
+//!
+//! `main.rs` のデモから使う。コンパイルが通らない例は `compile_fail` の doctest にしている。
+
+pub mod const_generics;
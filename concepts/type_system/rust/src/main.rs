@@ -3,6 +3,8 @@
 //! Rust は静的型付け・強い型付けの言語。
 //! コンパイル時に型チェックが行われ、実行時エラーを防ぐ。
 
+use type_system::const_generics::{self, Matrix, RingBuffer, FIB_TABLE};
+
 fn main() {
     println!("=== Rust 型システム ===\n");
 
@@ -12,9 +14,12 @@ fn main() {
     option_and_result();
     newtype_pattern();
     type_aliases();
+    const_generics();
 }
 
 /// 基本的な型
+// 型の書き方を並べる例なので、使わない変数や PI に近いリテラルはそのままにする
+#[allow(unused_variables, clippy::approx_constant)]
 fn basic_types() {
     println!("--- 基本型 ---");
 
@@ -54,6 +59,7 @@ fn basic_types() {
 }
 
 /// 型推論
+#[allow(clippy::approx_constant, clippy::vec_init_then_push)]
 fn type_inference() {
     println!("--- 型推論 ---");
 
@@ -77,6 +83,7 @@ fn type_inference() {
 }
 
 /// ジェネリクス
+#[allow(dead_code)]
 fn generics_example() {
     println!("--- ジェネリクス ---");
 
@@ -179,6 +186,7 @@ fn option_and_result() {
 }
 
 /// Newtype パターン
+#[allow(dead_code)]
 fn newtype_pattern() {
     println!("--- Newtype パターン ---");
 
@@ -211,6 +219,7 @@ fn newtype_pattern() {
 }
 
 /// 型エイリアス
+#[allow(dead_code)]
 fn type_aliases() {
     println!("--- 型エイリアス ---");
 
@@ -231,3 +240,33 @@ fn type_aliases() {
     println!("  read: {:?}", read_something());
     println!();
 }
+
+/// const ジェネリクスとコンパイル時の評価 (通らない例は src/const_generics.rs の doctest)
+fn const_generics() {
+    println!("--- const ジェネリクス ---");
+
+    // 大きさが型に入っている: 2×3 と 3×2 の積は 2×2
+    let a = Matrix::new([[1, 2, 3], [4, 5, 6]]);
+    let b = a.transpose();
+    let product: Matrix<2, 2> = a * b;
+    println!("  {:?} × {:?} = {:?}", a.shape(), b.shape(), product.shape());
+    for line in product.to_string().lines() {
+        println!("    {}", line);
+    }
+    println!("  配列の長さも引数に: sum_array([1, 2, 3]) = {}", const_generics::sum_array([1, 2, 3]));
+
+    // const fn: コンパイル時に作った表
+    const FIB_30: u64 = const_generics::fib(30);
+    println!("  FIB_TABLE[..10]: {:?}", &FIB_TABLE[..10]);
+    println!("  fib(30) (コンパイル時): {}", FIB_30);
+
+    // 容量が 2 の冪であることはコンパイル時に確かめる (RingBuffer::<_, 3> はビルドが止まる)
+    let mut ring = RingBuffer::<&str, 4>::new();
+    for word in ["a", "b", "c", "d", "e"] {
+        if let Some(evicted) = ring.push(word) {
+            println!("  ring: {} を押し出した", evicted);
+        }
+    }
+    println!("  ring: {:?}", ring.iter().collect::<Vec<_>>());
+    println!();
+}
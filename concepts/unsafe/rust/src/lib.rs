@@ -0,0 +1,294 @@
+//! # unsafe Rust
+//!
+//! `unsafe` はコンパイラが確かめられない約束を、書いた人が守ると宣言する場所。
+//! できるようになるのは次の 5 つだけで、借用チェックなどが止まるわけではない。
+//!
+//! 1. 生ポインタ (`*const T` / `*mut T`) の参照外し
+//! 2. `unsafe fn` の呼び出し (FFI を含む)
+//! 3. `static mut` の読み書き
+//! 4. `unsafe trait` の実装 (`Send` / `Sync` など)
+//! 5. `union` のフィールドの読み出し
+//!
+//! 約束を破ると未定義動作 (UB) になり、テストが通っても壊れていることがある。
+//! テストは Miri (UB を検出するインタプリタ) でも走らせる前提で小さく書いている:
+//!
+//! ```text
+//! rustup +nightly component add miri
+//! cargo +nightly miri test
+//! ```
+
+use std::alloc::{self, Layout};
+use std::marker::PhantomData;
+use std::ptr::{self, NonNull};
+
+// ============================================================
+// 生ポインタと std::ptr
+// ============================================================
+
+/// 生ポインタで配列を逆順にする (`slice::reverse` と同じことを手で)
+pub fn reverse_in_place<T>(values: &mut [T]) {
+    let len = values.len();
+    let base = values.as_mut_ptr();
+    for i in 0..len / 2 {
+        // SAFETY: i < len / 2 なので両方とも範囲内で、同じ要素を指すことはない
+        unsafe { ptr::swap(base.add(i), base.add(len - 1 - i)) };
+    }
+}
+
+/// `values` から `index` 番目を取り除いて詰める (`Vec::remove` の中身)
+pub fn remove_at<T>(values: &mut Vec<T>, index: usize) -> T {
+    let len = values.len();
+    assert!(index < len, "index {} out of bounds for length {}", index, len);
+    // SAFETY: index < len。取り出した後は後ろを 1 つ前へずらし (重なるので copy)、
+    // 長さを 1 減らすので、取り出した要素を二重に落とすことはない
+    unsafe {
+        let slot = values.as_mut_ptr().add(index);
+        let removed = ptr::read(slot);
+        ptr::copy(slot.add(1), slot, len - index - 1);
+        values.set_len(len - 1);
+        removed
+    }
+}
+
+// ============================================================
+// 小さな安全な抽象化: split_at_mut
+// ============================================================
+
+/// `slice::split_at_mut` と同じもの
+///
+/// 借用チェッカーは「同じスライスから可変参照を 2 つ作る」ことを許さないが、
+/// 重ならない 2 つの範囲なら安全。その判断を `unsafe` の中で行い、外には安全な関数として出す。
+pub fn split_at_mut<T>(values: &mut [T], mid: usize) -> (&mut [T], &mut [T]) {
+    let len = values.len();
+    assert!(mid <= len, "mid {} out of bounds for length {}", mid, len);
+    let ptr = values.as_mut_ptr();
+    // SAFETY: [0, mid) と [mid, len) は重ならず、どちらも元のスライスの中。
+    // 戻り値の寿命は values の借用と同じなので、元のスライスとも同時には使えない
+    unsafe { (std::slice::from_raw_parts_mut(ptr, mid), std::slice::from_raw_parts_mut(ptr.add(mid), len - mid)) }
+}
+
+// ============================================================
+// alloc / dealloc で自分でメモリを管理する
+// ============================================================
+
+/// `alloc` / `realloc` / `dealloc` で作る最小の可変長配列
+///
+/// 不変条件: `ptr` は `cap` 個分の確保済み領域 (ゼロサイズ型か cap == 0 ならダングリング) で、
+/// 先頭の `len` 個だけが初期化されている。
+pub struct TinyVec<T> {
+    ptr: NonNull<T>,
+    cap: usize,
+    len: usize,
+    /// T を持っていることを drop チェッカーに伝える
+    _owns: PhantomData<T>,
+}
+
+impl<T> Default for TinyVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TinyVec<T> {
+    pub fn new() -> Self {
+        TinyVec {
+            ptr: NonNull::dangling(),
+            // ゼロサイズ型は確保しないので、最初から容量は無限扱い
+            cap: if std::mem::size_of::<T>() == 0 { usize::MAX } else { 0 },
+            len: 0,
+            _owns: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.grow();
+        }
+        // SAFETY: len < cap なので確保済みで、まだ初期化していない場所
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        // SAFETY: 元の最後の要素なので初期化済み。len を減らしたので二度は読まない
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: 先頭の len 個は初期化済み (len == 0 ならダングリングでもよい)
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: as_slice と同じ。&mut self なので他に参照はない
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    fn grow(&mut self) {
+        // ゼロサイズ型で usize::MAX 個を超えた
+        assert!(std::mem::size_of::<T>() != 0, "capacity overflow");
+        let new_cap = if self.cap == 0 { 4 } else { self.cap * 2 };
+        let new_layout = Layout::array::<T>(new_cap).expect("capacity overflow");
+        let new_ptr = if self.cap == 0 {
+            // SAFETY: T はゼロサイズではないので new_layout の大きさは 0 でない
+            unsafe { alloc::alloc(new_layout) }
+        } else {
+            let old_layout = Layout::array::<T>(self.cap).unwrap();
+            // SAFETY: ptr は old_layout で確保したもの。中身はそのまま新しい場所へ移される
+            unsafe { alloc::realloc(self.ptr.as_ptr().cast(), old_layout, new_layout.size()) }
+        };
+        // 確保に失敗したら null が返るので、決まった方法で中断する
+        self.ptr = NonNull::new(new_ptr.cast()).unwrap_or_else(|| alloc::handle_alloc_error(new_layout));
+        self.cap = new_cap;
+    }
+}
+
+impl<T> Drop for TinyVec<T> {
+    fn drop(&mut self) {
+        // 先に要素を落としてから領域を返す
+        // SAFETY: 先頭の len 個は初期化済みで、この後は使わない
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) };
+        if self.cap != 0 && std::mem::size_of::<T>() != 0 {
+            // SAFETY: grow が同じ Layout で確保した
+            unsafe { alloc::dealloc(self.ptr.as_ptr().cast(), Layout::array::<T>(self.cap).unwrap()) };
+        }
+    }
+}
+
+// ============================================================
+// transmute の落とし穴
+// ============================================================
+
+/// `f32` のビット列 (transmute を使わなくても標準の関数がある)
+pub fn float_bits(x: f32) -> u32 {
+    // let bits: u32 = unsafe { std::mem::transmute(x) }; と同じ結果になる
+    x.to_bits()
+}
+
+/// バイト列を bool にする (transmute すると 0/1 以外で UB になる)
+pub fn byte_to_bool(byte: u8) -> Option<bool> {
+    // unsafe { std::mem::transmute::<u8, bool>(2) } は UB。値の範囲を確かめてから変換する
+    match byte {
+        0 => Some(false),
+        1 => Some(true),
+        _ => None,
+    }
+}
+
+/// 4 バイトを u32 にする (transmute はエンディアンに依存し、長さも確かめない)
+pub fn u32_from_le(bytes: [u8; 4]) -> u32 {
+    u32::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    // Miri は遅いので、繰り返しは少なめにする
+    const N: usize = if cfg!(miri) { 20 } else { 1_000 };
+
+    #[test]
+    fn test_raw_pointer_helpers() {
+        let mut values = [1, 2, 3, 4, 5];
+        reverse_in_place(&mut values);
+        assert_eq!(values, [5, 4, 3, 2, 1]);
+        reverse_in_place::<i32>(&mut []);
+
+        let mut strings: Vec<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(remove_at(&mut strings, 1), "b");
+        assert_eq!(remove_at(&mut strings, 2), "d");
+        assert_eq!(strings, ["a", "c"]);
+        assert!(std::panic::catch_unwind(move || remove_at(&mut strings, 2)).is_err());
+    }
+
+    #[test]
+    fn test_split_at_mut() {
+        let mut values = [1, 2, 3, 4, 5];
+        let (left, right) = split_at_mut(&mut values, 2);
+        // 2 つの可変参照を同時に使える
+        left[0] = 10;
+        right[0] = 30;
+        std::mem::swap(&mut left[1], &mut right[2]);
+        assert_eq!(values, [10, 5, 30, 4, 2]);
+
+        for mid in [0, 5] {
+            let (left, right) = split_at_mut(&mut values, mid);
+            assert_eq!(left.len() + right.len(), 5);
+        }
+        assert!(std::panic::catch_unwind(|| {
+            split_at_mut(&mut [0; 3], 4);
+        }).is_err());
+    }
+
+    #[test]
+    fn test_tiny_vec_matches_vec() {
+        let mut tiny = TinyVec::new();
+        let mut model = Vec::new();
+        for i in 0..N {
+            if i % 3 == 2 {
+                assert_eq!(tiny.pop(), model.pop());
+            } else {
+                tiny.push(i.to_string());
+                model.push(i.to_string());
+            }
+        }
+        assert_eq!(tiny.as_slice(), model.as_slice());
+        assert!(tiny.capacity() >= tiny.len());
+        tiny.as_mut_slice()[0].push('!');
+        assert_eq!(tiny.as_slice()[0], "0!");
+    }
+
+    #[test]
+    fn test_tiny_vec_drops_each_element_once() {
+        let drops = Rc::new(Cell::new(0));
+        struct Counted(Rc<Cell<usize>>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let mut tiny = TinyVec::new();
+        for _ in 0..10 {
+            tiny.push(Counted(drops.clone()));
+        }
+        drop(tiny.pop());
+        assert_eq!(drops.get(), 1);
+        drop(tiny);
+        assert_eq!(drops.get(), 10);
+
+        // ゼロサイズ型は確保しない
+        let mut units = TinyVec::new();
+        for _ in 0..N {
+            units.push(());
+        }
+        assert_eq!((units.len(), units.capacity()), (N, usize::MAX));
+    }
+
+    #[test]
+    fn test_transmute_alternatives() {
+        assert_eq!(float_bits(1.0), 0x3f80_0000);
+        assert_eq!(float_bits(1.0), u32::from_ne_bytes(1.0f32.to_ne_bytes()));
+        assert_eq!(byte_to_bool(1), Some(true));
+        assert_eq!(byte_to_bool(2), None);
+        assert_eq!(u32_from_le([0x78, 0x56, 0x34, 0x12]), 0x1234_5678);
+    }
+}
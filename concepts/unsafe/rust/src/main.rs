@@ -0,0 +1,84 @@
+//! # unsafe Rust のデモ
+//!
+//! 関数と型の本体は `lib.rs` にある。
+
+use unsafe_concepts::*;
+
+fn main() {
+    println!("=== unsafe Rust ===\n");
+
+    raw_pointers();
+    safe_abstraction();
+    manual_memory();
+    transmute_pitfalls();
+}
+
+/// 生ポインタは作るだけなら安全、参照外しが unsafe
+fn raw_pointers() {
+    println!("--- 生ポインタ ---");
+
+    let mut x = 10;
+    let read = &x as *const i32;
+    let write = &mut x as *mut i32;
+    // SAFETY: どちらも生きている x を指し、ここでは他に参照がない
+    unsafe {
+        *write += 5;
+        println!("  *read = {}", *read);
+    }
+
+    // どこも指さないポインタも作れる (参照外しは UB)
+    let null: *const i32 = std::ptr::null();
+    println!("  null.is_null() = {}", null.is_null());
+
+    // ポインタ演算 (add は要素単位で進む)
+    let values = [10, 20, 30];
+    let base = values.as_ptr();
+    // SAFETY: 2 < values.len()
+    println!("  *base.add(2) = {}", unsafe { *base.add(2) });
+
+    let mut numbers = [1, 2, 3, 4];
+    reverse_in_place(&mut numbers);
+    println!("  ptr::swap で逆順: {:?}", numbers);
+
+    let mut names = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+    let removed = remove_at(&mut names, 0);
+    println!("  ptr::read + ptr::copy で取り除く: {} -> {:?}\n", removed, names);
+}
+
+/// unsafe な中身を安全なインターフェースで包む
+fn safe_abstraction() {
+    println!("--- 安全な抽象化 (split_at_mut) ---");
+
+    let mut values = [1, 2, 3, 4, 5, 6];
+    // let (a, b) = (&mut values[..3], &mut values[3..]);  // エラー: 可変借用が 2 つ
+    let (left, right) = split_at_mut(&mut values, 3);
+    left.iter_mut().zip(right.iter_mut()).for_each(|(a, b)| std::mem::swap(a, b));
+    println!("  前半と後半を入れ替え: {:?}\n", values);
+}
+
+/// alloc / realloc / dealloc を直接使う
+fn manual_memory() {
+    println!("--- alloc / dealloc ---");
+
+    let mut tiny = TinyVec::new();
+    for i in 0..10 {
+        tiny.push(i * i);
+        if tiny.len().is_power_of_two() {
+            println!("  len = {:>2}, capacity = {}", tiny.len(), tiny.capacity());
+        }
+    }
+    println!("  {:?}", tiny.as_slice());
+    println!("  pop = {:?} (Drop で残りを落としてから dealloc)\n", tiny.pop());
+}
+
+/// transmute はビット列をそのまま別の型として読む (型の約束を確かめない)
+fn transmute_pitfalls() {
+    println!("--- transmute の落とし穴 ---");
+
+    println!("  1.0f32.to_bits() = {:#x} (transmute の代わり)", float_bits(1.0));
+    println!("  u8 → bool: 1 = {:?}, 2 = {:?} (transmute なら 2 は UB)", byte_to_bool(1), byte_to_bool(2));
+    println!("  [0x78, 0x56, 0x34, 0x12] → {:#x} (from_le_bytes ならエンディアンが決まる)", u32_from_le([0x78, 0x56, 0x34, 0x12]));
+    // &T → &mut T の transmute は、たとえ書き込まなくても UB
+    println!("  &T → &mut T の transmute は常に UB (内部可変性には UnsafeCell を使う)");
+    println!();
+}